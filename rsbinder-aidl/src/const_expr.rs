@@ -221,6 +221,12 @@ pub enum ValueType {
     FileDescriptor,
     Holder,
     UserDefined(String),
+    // `android.hardware.common.fmq.MQDescriptor<T, Flavor>`, backed by the
+    // runtime crate's `fmq` module. `flavor` is the flavor's simple name.
+    QueueDescriptor {
+        element: Box<ValueType>,
+        flavor: String,
+    },
     Reference {
         // Full AIDL enum type. Short enum names can collide across packages.
         enum_type: String,
@@ -276,6 +282,7 @@ impl ValueType {
             ValueType::Holder => 16,
             ValueType::UserDefined(_) => 17,
             ValueType::Reference { .. } => 18,
+            ValueType::QueueDescriptor { .. } => 19,
         }
     }
 
@@ -294,6 +294,7 @@ pub mod {{mod}} {
             impl<T, R> {{crate}}::Interface for Wrapper<T, R> where T: {{crate}}::Interface, R: Send + Sync {
                 fn as_binder(&self) -> {{crate}}::SIBinder { self._inner.as_binder() }
                 fn dump(&self, _writer: &mut dyn std::io::Write, _args: &[String]) -> {{crate}}::Result<()> { self._inner.dump(_writer, _args) }
+                fn shell_command(&self, _command: &mut {{crate}}::shell_command::ShellCommand) -> {{crate}}::Result<()> { self._inner.shell_command(_command) }
             }
             impl<T, R> {{bn_name}}Adapter for Wrapper<T, R>
             where
@@ -397,7 +398,7 @@ pub mod {{mod}} {
             {%- else %}
             let data = self.binder.as_remote().ok_or({{crate}}::StatusCode::BadType)?.prepare_transact(true)?;
             {%- endif %}
-            Ok(data)
+            {{crate}}::trace::client_call(data, "{{ member.identifier }}")
         }
         fn read_response_{{ member.identifier }}({{ member.args }}, _aidl_reply: {{crate}}::Result<Option<{{crate}}::Parcel>>) -> {{crate}}::BinderResult<{{ member.return_type }}> {
             {%- if oneway or member.oneway %}
@@ -429,7 +430,7 @@ pub mod {{mod}} {
         {%- if version %}
         fn build_parcel_getInterfaceVersion(&self) -> {{crate}}::Result<{{crate}}::Parcel> {
             let data = self.binder.as_remote().ok_or({{crate}}::StatusCode::BadType)?.prepare_transact(true)?;
-            Ok(data)
+            {{crate}}::trace::client_call(data, "getInterfaceVersion")
         }
         fn read_response_getInterfaceVersion(&self, _aidl_reply: {{crate}}::Result<Option<{{crate}}::Parcel>>) -> {{crate}}::BinderResult<i32> {
             let mut _aidl_reply = _aidl_reply?.ok_or({{crate}}::StatusCode::UnexpectedNull)?;
@@ -443,7 +444,7 @@ pub mod {{mod}} {
         {%- if hash %}
         fn build_parcel_getInterfaceHash(&self) -> {{crate}}::Result<{{crate}}::Parcel> {
             let data = self.binder.as_remote().ok_or({{crate}}::StatusCode::BadType)?.prepare_transact(true)?;
-            Ok(data)
+            {{crate}}::trace::client_call(data, "getInterfaceHash")
         }
         fn read_response_getInterfaceHash(&self, _aidl_reply: {{crate}}::Result<Option<{{crate}}::Parcel>>) -> {{crate}}::BinderResult<String> {
             let mut _aidl_reply = _aidl_reply?.ok_or({{crate}}::StatusCode::UnexpectedNull)?;
@@ -498,17 +499,15 @@ pub mod {{mod}} {
                 Ok(_aidl_data) => _aidl_data,
                 Err(err) => return Box::pin(std::future::ready(Err(err.into()))),
             };
-            let binder = self.binder.clone();
-            P::spawn(
-                move || binder.as_remote().ok_or({{crate}}::StatusCode::BadType)?.submit_transact(transactions::r#{{ member.identifier }}, &_aidl_data, {% if oneway or member.oneway %}{{crate}}::FLAG_ONEWAY | {% endif %}{{crate}}::FLAG_CLEAR_BUF | {{crate}}::FLAG_PRIVATE_LOCAL),
-                move |_aidl_reply| async move {
-                    {%- if member.func_call_params|length > 0 %}
-                    self.read_response_{{ member.identifier }}({{ member.func_call_params }}, _aidl_reply)
-                    {%- else %}
-                    self.read_response_{{ member.identifier }}(_aidl_reply)
-                    {%- endif %}
-                }
-            )
+            let _aidl_reply = P::transact(self.binder.clone(), transactions::r#{{ member.identifier }}, _aidl_data, {% if oneway or member.oneway %}{{crate}}::FLAG_ONEWAY | {% endif %}{{crate}}::FLAG_CLEAR_BUF | {{crate}}::FLAG_PRIVATE_LOCAL);
+            Box::pin(async move {
+                let _aidl_reply = _aidl_reply.await;
+                {%- if member.func_call_params|length > 0 %}
+                self.read_response_{{ member.identifier }}({{ member.func_call_params }}, _aidl_reply)
+                {%- else %}
+                self.read_response_{{ member.identifier }}(_aidl_reply)
+                {%- endif %}
+            })
         }
         {%- endfor %}
         {%- if version %}
@@ -519,13 +518,11 @@ pub mod {{mod}} {
                 Ok(_aidl_data) => _aidl_data,
                 Err(err) => return Box::pin(std::future::ready(Err(err.into()))),
             };
-            let binder = self.binder.clone();
-            P::spawn(
-                move || binder.as_remote().ok_or({{crate}}::StatusCode::BadType)?.submit_transact(transactions::r#getInterfaceVersion, &_aidl_data, {{crate}}::FLAG_PRIVATE_LOCAL | {{crate}}::FLAG_CLEAR_BUF),
-                move |_aidl_reply| async move {
-                    self.read_response_getInterfaceVersion(_aidl_reply)
-                }
-            )
+            let _aidl_reply = P::transact(self.binder.clone(), transactions::r#getInterfaceVersion, _aidl_data, {{crate}}::FLAG_PRIVATE_LOCAL | {{crate}}::FLAG_CLEAR_BUF);
+            Box::pin(async move {
+                let _aidl_reply = _aidl_reply.await;
+                self.read_response_getInterfaceVersion(_aidl_reply)
+            })
         }
         {%- endif %}
         {%- if hash %}
@@ -540,13 +537,11 @@ pub mod {{mod}} {
                 Ok(_aidl_data) => _aidl_data,
                 Err(err) => return Box::pin(std::future::ready(Err(err.into()))),
             };
-            let binder = self.binder.clone();
-            P::spawn(
-                move || binder.as_remote().ok_or({{crate}}::StatusCode::BadType)?.submit_transact(transactions::r#getInterfaceHash, &_aidl_data, {{crate}}::FLAG_PRIVATE_LOCAL | {{crate}}::FLAG_CLEAR_BUF),
-                move |_aidl_reply| async move {
-                    self.read_response_getInterfaceHash(_aidl_reply)
-                }
-            )
+            let _aidl_reply = P::transact(self.binder.clone(), transactions::r#getInterfaceHash, _aidl_data, {{crate}}::FLAG_PRIVATE_LOCAL | {{crate}}::FLAG_CLEAR_BUF);
+            Box::pin(async move {
+                let _aidl_reply = _aidl_reply.await;
+                self.read_response_getInterfaceHash(_aidl_reply)
+            })
         }
         {%- endif %}
     }
@@ -575,6 +570,7 @@ pub mod {{mod}} {
         match _code {
         {%- for member in fn_members %}
             transactions::r#{{ member.identifier }} => {
+                {{crate}}::trace::server_method("{{ member.identifier }}");
             {%- if member.enforce_permission_check %}
                 {{ member.enforce_permission_check }}
             {%- endif %}
@@ -606,6 +602,7 @@ pub mod {{mod}} {
         {%- endfor %}
         {%- if version %}
             transactions::r#getInterfaceVersion => {
+                {{crate}}::trace::server_method("getInterfaceVersion");
                 let _aidl_return = _service.r#getInterfaceVersion();
                 match &_aidl_return {
                     Ok(_aidl_return) => {
@@ -621,6 +618,7 @@ pub mod {{mod}} {
         {%- endif %}
         {%- if hash %}
             transactions::r#getInterfaceHash => {
+                {{crate}}::trace::server_method("getInterfaceHash");
                 let _aidl_return = _service.r#getInterfaceHash();
                 match &_aidl_return {
                     Ok(_aidl_return) => {
@@ -701,6 +699,7 @@ fn make_fn_member(method: &parser::MethodDecl, crate_name: &str) -> Result<FnMem
     let mut transaction_write = Vec::new();
     let mut transaction_params = String::new();
     let mut read_onto_params = Vec::new();
+    let mut borrow_decls = Vec::new();
 
     for arg in &method.arg_list {
         let generator = arg.to_generator()?;
@@ -735,7 +734,29 @@ fn make_fn_member(method: &parser::MethodDecl, crate_name: &str) -> Result<FnMem
             }
         }
 
-        transaction_decls.push(format!("let {};", generator.transaction_decl("_reader")));
+        // `in String` is not borrowed: the wire carries UTF-16, so the
+        // `&str` the service gets needs one decode, which `read()` already
+        // does straight from the transaction buffer.
+        let borrowed = generator.borrowable_slice_element();
+        if let Some(elem) = &borrowed {
+            // Skipped here and borrowed once the remaining arguments are
+            // read: the views borrow `_reader`, which those reads need
+            // mutably.
+            let ident = &generator.identifier;
+            transaction_decls.push(format!(
+                "let {ident}_pos = _reader.skip_slice::<{elem}>()?;"
+            ));
+            let null_check = if generator.is_nullable {
+                String::new()
+            } else {
+                format!(".ok_or({crate_name}::StatusCode::UnexpectedNull)?")
+            };
+            borrow_decls.push(format!(
+                "let {ident} = _reader.borrowed_reader({ident}_pos).read_slice_ref::<{elem}>()?{null_check};"
+            ));
+        } else {
+            transaction_decls.push(format!("let {};", generator.transaction_decl("_reader")));
+        }
         if matches!(arg.direction, Direction::Out) && generator.is_variable_array() {
             if generator.is_nullable {
                 transaction_decls.push(format!(
@@ -757,8 +778,15 @@ fn make_fn_member(method: &parser::MethodDecl, crate_name: &str) -> Result<FnMem
             });
             read_onto_params.push(generator.identifier.to_owned());
         }
-        transaction_params += &format!("{}, ", generator.func_call_param());
+        match borrowed {
+            Some(_) if generator.is_nullable => {
+                transaction_params += &format!("{}.as_deref(), ", generator.identifier)
+            }
+            Some(_) => transaction_params += &format!("&{}, ", generator.identifier),
+            None => transaction_params += &format!("{}, ", generator.func_call_param()),
+        }
     }
+    transaction_decls.append(&mut borrow_decls);
 
     let func_call_params = if func_call_params.chars().count() > 2 {
         func_call_params
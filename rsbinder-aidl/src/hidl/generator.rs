@@ -0,0 +1,1319 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rust code generation for parsed `.hal` documents.
+//!
+//! Type names are resolved the way `hidl-gen` does: nested scopes first,
+//! then the package (every `types.hal` definition is a direct child of it),
+//! then explicit imports. Each package version becomes a module
+//! (`android::hidl::manager::V1_0`) and, like the AIDL output, each type a
+//! `pub mod Name` holding the item of the same name.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use miette::SourceSpan;
+use serde::Serialize;
+use tera::Tera;
+
+use super::parser::{
+    BaseType, ConstExpr, Definition, Document, Enum, FqName, Import, Interface, Method, Package,
+    Struct, TypeRef,
+};
+use crate::error::{AidlError, ResolutionError, SemanticError};
+use crate::{add_indent, escape_rust_keyword, Namespace};
+
+const ENUM_TEMPLATE: &str = r##"
+pub mod {{mod}} {
+    #![allow(non_upper_case_globals, non_snake_case)]
+    {{crate}}::declare_hidl_enum! {
+        r#{{name}} : [{{backing}}; {{len}}] {
+    {%- for member in members %}
+            r#{{ member.0 }} = {{ member.1 }},
+    {%- endfor %}
+        }
+    }
+}
+"##;
+
+const STRUCT_TEMPLATE: &str = r#"
+pub mod {{mod}} {
+    #![allow(non_upper_case_globals, non_snake_case, dead_code)]
+    #[derive({{ derive }})]
+    pub struct {{name}} {
+    {%- for field in fields %}
+        pub r#{{ field.name }}: {{ field.ty }},
+    {%- endfor %}
+    }
+    impl Default for {{name}} {
+        fn default() -> Self {
+            Self {
+            {%- for field in fields %}
+                r#{{ field.name }}: {{ field.default }},
+            {%- endfor %}
+            }
+        }
+    }
+    impl {{name}} {
+    {%- for field in fields %}
+        const OFFSET_{{ field.name }}: usize = {{ field.offset }};
+    {%- endfor %}
+    }
+    impl {{crate}}::hidl::HidlType for {{name}} {
+        const SIZE: usize = {{ size }};
+        const ALIGN: usize = {{ align }};
+        fn write_layout(&self, _out: &mut [u8]) {
+        {%- for field in fields %}
+            {{crate}}::hidl::HidlType::write_layout(&self.r#{{ field.name }}, &mut _out[Self::OFFSET_{{ field.name }}..Self::OFFSET_{{ field.name }} + <{{ field.ty }} as {{crate}}::hidl::HidlType>::SIZE]);
+        {%- endfor %}
+        }
+        fn write_embedded(&self, _parcel: &mut {{crate}}::Parcel, _parent: usize, _offset: usize) -> {{crate}}::Result<()> {
+        {%- for field in fields %}
+            {{crate}}::hidl::HidlType::write_embedded(&self.r#{{ field.name }}, _parcel, _parent, _offset + Self::OFFSET_{{ field.name }})?;
+        {%- endfor %}
+            Ok(())
+        }
+    }
+    impl {{crate}}::hidl::HidlRead for {{name}} {
+        fn read_embedded(_parcel: &mut {{crate}}::Parcel, _layout: &[u8], _parent: usize, _offset: usize) -> {{crate}}::Result<Self> {
+            Ok(Self {
+            {%- for field in fields %}
+                r#{{ field.name }}: {{crate}}::hidl::HidlRead::read_embedded(_parcel, &_layout[Self::OFFSET_{{ field.name }}..Self::OFFSET_{{ field.name }} + <{{ field.ty }} as {{crate}}::hidl::HidlType>::SIZE], _parent, _offset + Self::OFFSET_{{ field.name }})?,
+            {%- endfor %}
+            })
+        }
+    }
+    {%- if nested|length>0 %}
+    {{nested}}
+    {%- endif %}
+}
+"#;
+
+const INTERFACE_TEMPLATE: &str = r#"
+pub mod {{mod}} {
+    #![allow(non_upper_case_globals, non_snake_case, dead_code, unused_imports)]
+    use {{crate}}::hidl::HwParcel as _;
+    pub const DESCRIPTOR: &str = "{{ descriptor }}";
+    /// Descriptors reported by `IBase::interfaceChain`, most derived first.
+    pub const INTERFACE_CHAIN: &[&str] = &[
+    {%- for descriptor in chain %}
+        "{{ descriptor }}",
+    {%- endfor %}
+    ];
+    pub trait {{name}}: {{ supertrait }} {
+        {%- for member in fn_members %}
+        fn r#{{ member.identifier }}(&self{{ member.args }}) -> {{crate}}::BinderResult<{{ member.return_type }}>;
+        {%- endfor %}
+    }
+    pub(crate) mod transactions {
+        {%- for code in transactions %}
+        pub(crate) const r#{{ code.0 }}: {{crate}}::TransactionCode = {{crate}}::FIRST_CALL_TRANSACTION + {{ code.1 }};
+        {%- endfor %}
+    }
+    {{crate}}::declare_hidl_interface! {
+        {{name}}[DESCRIPTOR] {
+            native: {{ bn_name }}(on_transact),
+            proxy: {{ bp_name }},
+        }
+    }
+    {%- for parent in chain_impls %}
+    impl {{ parent.trait_path }} for {{ bp_name }} {
+        {%- for member in parent.fn_members %}
+        fn r#{{ member.identifier }}(&self{{ member.args }}) -> {{crate}}::BinderResult<{{ member.return_type }}> {
+            let _hidl_remote = self.binder.as_remote().ok_or({{crate}}::StatusCode::BadType)?;
+            let mut _hidl_data = _hidl_remote.prepare_transact(false)?;
+            _hidl_data.write_hidl_token("{{ parent.descriptor }}")?;
+            {%- for func in member.write_funcs %}
+            {{ func }}
+            {%- endfor %}
+            let _hidl_reply = _hidl_remote.submit_transact(transactions::r#{{ member.identifier }}, &_hidl_data, {% if member.oneway %}{{crate}}::FLAG_ONEWAY{% else %}0{% endif %});
+            {%- if member.oneway %}
+            _hidl_reply?; // propagate transport errors (e.g. dead object); oneway has no reply body
+            Ok(())
+            {%- else %}
+            let mut _hidl_reply = _hidl_reply?.ok_or({{crate}}::StatusCode::UnexpectedNull)?;
+            let _status = _hidl_reply.read_hidl_status()?;
+            if !_status.is_ok() { return Err(_status); }
+            {%- for func in member.read_funcs %}
+            {{ func }}
+            {%- endfor %}
+            Ok({{ member.return_value }})
+            {%- endif %}
+        }
+        {%- endfor %}
+    }
+    impl {{ parent.trait_path }} for {{crate}}::Binder<{{ bn_name }}> {
+        {%- for member in parent.fn_members %}
+        fn r#{{ member.identifier }}(&self{{ member.args }}) -> {{crate}}::BinderResult<{{ member.return_type }}> {
+            {{ parent.trait_path }}::r#{{ member.identifier }}(&*self.0{{ member.func_call_params }})
+        }
+        {%- endfor %}
+    }
+    {%- endfor %}
+    fn on_transact(
+        _service: &dyn {{ name }}, _code: {{crate}}::TransactionCode, _reader: &mut {{crate}}::Parcel, _reply: &mut {{crate}}::Parcel) -> {{crate}}::Result<()> {
+        match _code {
+        {%- for parent in chain_impls %}
+        {%- for member in parent.fn_members %}
+            transactions::r#{{ member.identifier }} => {
+                _reader.enforce_hidl_token("{{ parent.descriptor }}")?;
+            {%- for decl in member.transaction_decls %}
+                {{ decl }}
+            {%- endfor %}
+                let _hidl_return = {{ parent.trait_path }}::r#{{ member.identifier }}(_service{{ member.transaction_params }});
+            {%- if not member.oneway %}
+                match &_hidl_return {
+                    Ok(_hidl_return) => {
+                        _reply.write_hidl_status(&{{crate}}::Status::from({{crate}}::StatusCode::Ok))?;
+                        {%- for func in member.transaction_write %}
+                        {{ func }}
+                        {%- endfor %}
+                    }
+                    Err(_hidl_status) => {
+                        _reply.write_hidl_status(_hidl_status)?;
+                    }
+                }
+            {%- endif %}
+                Ok(())
+            }
+        {%- endfor %}
+        {%- endfor %}
+            _ => {{crate}}::hidl::on_base_transact(INTERFACE_CHAIN, _code, _reader, _reply, |_writer, _args| _service.dump(_writer, _args)),
+        }
+    }
+    {%- if nested|length>0 %}
+    {{nested}}
+    {%- endif %}
+}
+"#;
+
+fn template() -> &'static tera::Tera {
+    static TEMPLATES: std::sync::OnceLock<tera::Tera> = std::sync::OnceLock::new();
+
+    TEMPLATES.get_or_init(|| {
+        let mut tera = Tera::default();
+        tera.add_raw_template("hidl_enum", ENUM_TEMPLATE)
+            .expect("Failed to add HIDL enum template");
+        tera.add_raw_template("hidl_struct", STRUCT_TEMPLATE)
+            .expect("Failed to add HIDL struct template");
+        tera.add_raw_template("hidl_interface", INTERFACE_TEMPLATE)
+            .expect("Failed to add HIDL interface template");
+        tera
+    })
+}
+
+const IBASE_PACKAGE: &str = "android.hidl.base";
+const IBASE_NAME: &str = "IBase";
+const IBASE_DESCRIPTOR: &str = "android.hidl.base@1.0::IBase";
+
+/// A resolved HIDL type.
+#[derive(Debug, Clone)]
+enum Ty {
+    /// A scalar, with its Rust type (`bitfield<E>` is E's storage type).
+    Scalar(&'static str),
+    Enum(String),
+    Struct(String),
+    String,
+    Handle,
+    Vec(Box<Ty>),
+    /// Element type and dimensions, outermost first.
+    Array(Box<Ty>, Vec<usize>),
+    /// A typed interface, or `None` for `interface` / `IBase`.
+    Interface(Option<String>),
+}
+
+impl Ty {
+    /// Passed by value rather than by reference.
+    fn is_copy(&self) -> bool {
+        matches!(self, Ty::Scalar(_) | Ty::Enum(_))
+    }
+}
+
+/// A definition registered under its fully-qualified key
+/// (`pkg@M.m::Outer.Inner`).
+#[derive(Debug, Clone)]
+struct TypeEntry {
+    doc: usize,
+    path: Vec<String>,
+    def: Definition,
+}
+
+/// Where a name is looked up from: a document and the path of the
+/// definition being generated (empty at package level).
+#[derive(Debug, Clone)]
+struct Scope {
+    doc: usize,
+    path: Vec<String>,
+}
+
+impl Scope {
+    fn child(&self, name: &str) -> Scope {
+        let mut path = self.path.clone();
+        path.push(name.to_owned());
+        Scope {
+            doc: self.doc,
+            path,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StructField {
+    name: String,
+    ty: String,
+    default: String,
+    offset: String,
+}
+
+#[derive(Serialize)]
+struct FnMember {
+    identifier: String,
+    oneway: bool,
+    args: String,
+    return_type: String,
+    func_call_params: String,
+    write_funcs: Vec<String>,
+    read_funcs: Vec<String>,
+    return_value: String,
+    transaction_decls: Vec<String>,
+    transaction_params: String,
+    transaction_write: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ChainImpl {
+    trait_path: String,
+    descriptor: String,
+    fn_members: Vec<FnMember>,
+}
+
+fn type_key(package: &Package, path: &[String]) -> String {
+    format!("{package}::{}", path.join("."))
+}
+
+fn integer_range(backing: &str) -> Option<(i128, i128)> {
+    Some(match backing {
+        "i8" => (i8::MIN.into(), i8::MAX.into()),
+        "u8" => (0, u8::MAX.into()),
+        "i16" => (i16::MIN.into(), i16::MAX.into()),
+        "u16" => (0, u16::MAX.into()),
+        "i32" => (i32::MIN.into(), i32::MAX.into()),
+        "u32" => (0, u32::MAX.into()),
+        "i64" => (i64::MIN.into(), i64::MAX.into()),
+        "u64" => (0, u64::MAX.into()),
+        _ => return None,
+    })
+}
+
+/// Generates Rust for a set of parsed `.hal` documents that reference
+/// each other.
+pub struct Generator<'a> {
+    documents: &'a [Document],
+    types: HashMap<String, TypeEntry>,
+    is_crate: bool,
+    enum_values: RefCell<HashMap<String, Vec<(String, i128)>>>,
+    enum_stack: RefCell<Vec<String>>,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(documents: &'a [Document], is_crate: bool) -> Self {
+        let mut types = HashMap::new();
+        fn register(
+            types: &mut HashMap<String, TypeEntry>,
+            package: &Package,
+            doc: usize,
+            parent: &[String],
+            def: &Definition,
+        ) {
+            let mut path = parent.to_vec();
+            path.push(def.name().to_owned());
+            let nested = match def {
+                Definition::Interface(decl) => decl.types.as_slice(),
+                Definition::Struct(decl) => decl.types.as_slice(),
+                _ => &[],
+            };
+            for child in nested {
+                register(types, package, doc, &path, child);
+            }
+            types.insert(
+                type_key(package, &path),
+                TypeEntry {
+                    doc,
+                    path,
+                    def: def.clone(),
+                },
+            );
+        }
+        for (doc, document) in documents.iter().enumerate() {
+            for def in &document.definitions {
+                register(&mut types, &document.package, doc, &[], def);
+            }
+        }
+        Self {
+            documents,
+            types,
+            is_crate,
+            enum_values: RefCell::new(HashMap::new()),
+            enum_stack: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn crate_name(&self) -> &'static str {
+        if self.is_crate {
+            "crate"
+        } else {
+            "rsbinder"
+        }
+    }
+
+    fn new_context(&self) -> tera::Context {
+        let mut context = tera::Context::new();
+        context.insert("crate", self.crate_name());
+        context
+    }
+
+    fn invalid(&self, doc: usize, span: SourceSpan, message: String) -> AidlError {
+        SemanticError::InvalidOperation {
+            message,
+            src: self.documents[doc].named_source(),
+            span,
+        }
+        .into()
+    }
+
+    fn unsupported(&self, doc: usize, span: SourceSpan, type_name: &str, help: &str) -> AidlError {
+        SemanticError::UnsupportedType {
+            type_name: type_name.to_owned(),
+            help: Some(help.to_owned()),
+            src: self.documents[doc].named_source(),
+            span,
+        }
+        .into()
+    }
+
+    fn unknown(&self, doc: usize, name: &FqName) -> AidlError {
+        ResolutionError::UnknownType {
+            name: name.to_string(),
+            src: self.documents[doc].named_source(),
+            span: name.span,
+        }
+        .into()
+    }
+
+    /// Generate every document. Returns `(module path, code, name)` triples
+    /// for [`crate::generate_all`].
+    pub fn generate(&self) -> Result<Vec<(String, String, String)>, AidlError> {
+        let mut package_list = Vec::new();
+        let mut errors = Vec::new();
+        for (doc, document) in self.documents.iter().enumerate() {
+            let namespace = format!(
+                "{}.{}",
+                document.package.name,
+                document.package.version_mod()
+            );
+            for def in &document.definitions {
+                let scope = Scope {
+                    doc,
+                    path: Vec::new(),
+                };
+                match self.definition(&scope, def, 0) {
+                    Ok(code) => package_list.push((namespace.clone(), code, def.name().to_owned())),
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        match AidlError::collect(errors) {
+            Some(err) => Err(err),
+            None => Ok(package_list),
+        }
+    }
+
+    fn definition(
+        &self,
+        parent: &Scope,
+        def: &Definition,
+        indent: usize,
+    ) -> Result<String, AidlError> {
+        let scope = parent.child(def.name());
+        match def {
+            Definition::Interface(decl) => self.interface(&scope, decl, indent),
+            Definition::Struct(decl) => self.structure(&scope, decl, indent),
+            Definition::Enum(decl) => self.enumeration(&scope, decl, indent),
+            Definition::Typedef(decl) => {
+                let ty = self.resolve_type(parent, &decl.ty, 0)?;
+                let module = self.module_of(parent);
+                Ok(add_indent(
+                    indent,
+                    &format!(
+                        "pub type {} = {};",
+                        escape_rust_keyword(&decl.name),
+                        self.owned_type(&module, &ty)
+                    ),
+                ))
+            }
+            Definition::Union { name, span } => Err(self.unsupported(
+                scope.doc,
+                *span,
+                name,
+                "HIDL unions and safe_unions are not supported by rsbinder",
+            )),
+        }
+    }
+
+    fn nested(&self, scope: &Scope, types: &[Definition]) -> Result<String, AidlError> {
+        let mut nested = String::new();
+        for def in types {
+            nested += &self.definition(scope, def, 1)?;
+        }
+        Ok(nested.trim().to_owned())
+    }
+
+    /// The Rust module a scope generates into.
+    fn module_of(&self, scope: &Scope) -> Namespace {
+        let package = &self.documents[scope.doc].package;
+        let mut ns = Namespace::new(&package.name, Namespace::AIDL);
+        ns.push(&package.version_mod());
+        for name in &scope.path {
+            ns.push(name);
+        }
+        ns
+    }
+
+    /// Path of the item `key` names, relative to module `from`.
+    fn item_path(&self, from: &Namespace, key: &str) -> String {
+        let entry = &self.types[key];
+        let module = self.module_of(&Scope {
+            doc: entry.doc,
+            path: entry.path.clone(),
+        });
+        let name = escape_rust_keyword(entry.path.last().expect("non-empty path"));
+        let relative = from.relative_mod(&module);
+        if relative.is_empty() {
+            name.into_owned()
+        } else if relative.ends_with("::") {
+            relative + &name
+        } else {
+            format!("{relative}::{name}")
+        }
+    }
+
+    /// Look `name` up from `scope`: enclosing scopes innermost first, the
+    /// package, then imports. Returns the type key.
+    fn lookup(&self, scope: &Scope, name: &FqName) -> Option<String> {
+        let document = &self.documents[scope.doc];
+        let current = &document.package;
+
+        if let Some((major, minor)) = name.version {
+            let package = Package {
+                name: name.package.clone().unwrap_or_else(|| current.name.clone()),
+                major,
+                minor,
+            };
+            let key = type_key(&package, &name.path);
+            return self.types.contains_key(&key).then_some(key);
+        }
+
+        for depth in (0..=scope.path.len()).rev() {
+            let mut path = scope.path[..depth].to_vec();
+            path.extend(name.path.iter().cloned());
+            let key = type_key(current, &path);
+            if self.types.contains_key(&key) {
+                return Some(key);
+            }
+        }
+
+        for import in &document.imports {
+            match import {
+                Import::Type(imported) => {
+                    if imported.path.last() != name.path.first() {
+                        continue;
+                    }
+                    let package = match imported.version {
+                        Some((major, minor)) => Package {
+                            name: imported
+                                .package
+                                .clone()
+                                .unwrap_or_else(|| current.name.clone()),
+                            major,
+                            minor,
+                        },
+                        None => current.clone(),
+                    };
+                    let mut path = imported.path.clone();
+                    path.extend(name.path.iter().skip(1).cloned());
+                    let key = type_key(&package, &path);
+                    if self.types.contains_key(&key) {
+                        return Some(key);
+                    }
+                }
+                Import::Package(package) => {
+                    let key = type_key(package, &name.path);
+                    if self.types.contains_key(&key) {
+                        return Some(key);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn is_ibase(&self, scope: &Scope, name: &FqName) -> bool {
+        let current = &self.documents[scope.doc].package;
+        let package = name.package.as_deref().unwrap_or(&current.name);
+        name.path == [IBASE_NAME]
+            && (name.version.is_none()
+                || (package == IBASE_PACKAGE && name.version == Some((1, 0))))
+            && self.lookup(scope, name).is_none()
+    }
+
+    fn resolve_type(&self, scope: &Scope, ty: &TypeRef, depth: usize) -> Result<Ty, AidlError> {
+        if depth > crate::parser::MAX_NESTING_DEPTH {
+            return Err(self.invalid(scope.doc, ty.span, "typedef chain too deep".into()));
+        }
+        let mut base = match &ty.base {
+            BaseType::Vec(inner) => {
+                Ty::Vec(Box::new(self.resolve_type(scope, inner, depth + 1)?))
+            }
+            BaseType::Bitfield(name) => match self.resolve_name(scope, name, depth)? {
+                Ty::Enum(key) => Ty::Scalar(self.enum_backing(&key)?),
+                _ => {
+                    return Err(self.invalid(
+                        scope.doc,
+                        name.span,
+                        format!("bitfield<{name}> requires an enum type"),
+                    ))
+                }
+            },
+            BaseType::Named(name) => self.resolve_name(scope, name, depth)?,
+        };
+        if !ty.dims.is_empty() {
+            let mut dims = Vec::new();
+            for dim in &ty.dims {
+                let value = self.eval(scope, dim, &[], ty.span)?;
+                if value <= 0 || value > u32::MAX as i128 {
+                    return Err(self.invalid(
+                        scope.doc,
+                        ty.span,
+                        format!("invalid array size {value}"),
+                    ));
+                }
+                dims.push(value as usize);
+            }
+            base = Ty::Array(Box::new(base), dims);
+        }
+        Ok(base)
+    }
+
+    fn resolve_name(&self, scope: &Scope, name: &FqName, depth: usize) -> Result<Ty, AidlError> {
+        if name.version.is_none() && name.path.len() == 1 {
+            let scalar = match name.path[0].as_str() {
+                "int8_t" => Some("i8"),
+                "uint8_t" => Some("u8"),
+                "int16_t" => Some("i16"),
+                "uint16_t" => Some("u16"),
+                "int32_t" => Some("i32"),
+                "uint32_t" => Some("u32"),
+                "int64_t" => Some("i64"),
+                "uint64_t" => Some("u64"),
+                "bool" => Some("bool"),
+                "float" => Some("f32"),
+                "double" => Some("f64"),
+                _ => None,
+            };
+            if let Some(scalar) = scalar {
+                return Ok(Ty::Scalar(scalar));
+            }
+            match name.path[0].as_str() {
+                "string" => return Ok(Ty::String),
+                "handle" => return Ok(Ty::Handle),
+                "interface" => return Ok(Ty::Interface(None)),
+                "memory" | "pointer" => {
+                    return Err(self.unsupported(
+                        scope.doc,
+                        name.span,
+                        &name.path[0],
+                        "HIDL memory and pointer types are not supported by rsbinder",
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        if self.is_ibase(scope, name) {
+            return Ok(Ty::Interface(None));
+        }
+
+        let key = self
+            .lookup(scope, name)
+            .ok_or_else(|| self.unknown(scope.doc, name))?;
+        let entry = &self.types[&key];
+        match &entry.def {
+            Definition::Interface(_) => Ok(Ty::Interface(Some(key))),
+            Definition::Struct(_) => Ok(Ty::Struct(key)),
+            Definition::Enum(_) => Ok(Ty::Enum(key)),
+            Definition::Typedef(decl) => {
+                let parent = Scope {
+                    doc: entry.doc,
+                    path: entry.path[..entry.path.len() - 1].to_vec(),
+                };
+                self.resolve_type(&parent, &decl.ty, depth + 1)
+            }
+            Definition::Union { .. } => Err(self.unsupported(
+                scope.doc,
+                name.span,
+                &name.to_string(),
+                "HIDL unions and safe_unions are not supported by rsbinder",
+            )),
+        }
+    }
+
+    fn entry_scope(&self, key: &str) -> Scope {
+        let entry = &self.types[key];
+        Scope {
+            doc: entry.doc,
+            path: entry.path.clone(),
+        }
+    }
+
+    /// Whether values of `ty` own file descriptors (and so are not `Clone`).
+    fn contains_handle(&self, ty: &Ty, seen: &mut Vec<String>) -> bool {
+        match ty {
+            Ty::Handle => true,
+            Ty::Vec(inner) | Ty::Array(inner, _) => self.contains_handle(inner, seen),
+            Ty::Struct(key) => {
+                if seen.contains(key) {
+                    return false;
+                }
+                seen.push(key.clone());
+                let Definition::Struct(decl) = &self.types[key].def else {
+                    return false;
+                };
+                let scope = self.entry_scope(key);
+                decl.fields.iter().any(|field| {
+                    self.resolve_type(&scope, &field.ty, 0)
+                        .is_ok_and(|ty| self.contains_handle(&ty, seen))
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// The parent enum of `decl`, if its storage type names one.
+    fn enum_parent(&self, scope: &Scope, decl: &Enum) -> Result<Option<String>, AidlError> {
+        match self.resolve_type(scope, &decl.storage, 0)? {
+            Ty::Enum(key) => Ok(Some(key)),
+            Ty::Scalar(backing) if integer_range(backing).is_some() => Ok(None),
+            _ => Err(self.invalid(
+                scope.doc,
+                decl.storage.span,
+                format!(
+                    "enum {} must be backed by an integer or enum type",
+                    decl.name
+                ),
+            )),
+        }
+    }
+
+    fn enum_backing(&self, key: &str) -> Result<&'static str, AidlError> {
+        let mut key = key.to_owned();
+        for _ in 0..=crate::parser::MAX_NESTING_DEPTH {
+            let Definition::Enum(decl) = &self.types[&key].def else {
+                unreachable!("enum key");
+            };
+            let scope = self.entry_scope(&key);
+            match self.resolve_type(&scope, &decl.storage, 0)? {
+                Ty::Enum(parent) => key = parent,
+                Ty::Scalar(backing) if integer_range(backing).is_some() => return Ok(backing),
+                _ => {
+                    return Err(self.invalid(
+                        scope.doc,
+                        decl.storage.span,
+                        format!(
+                            "enum {} must be backed by an integer or enum type",
+                            decl.name
+                        ),
+                    ))
+                }
+            }
+        }
+        let scope = self.entry_scope(&key);
+        Err(self.invalid(
+            scope.doc,
+            self.types[&key].def_span(),
+            "enum inheritance cycle".into(),
+        ))
+    }
+
+    /// All values of enum `key`, inherited ones first.
+    fn enum_values(&self, key: &str) -> Result<Vec<(String, i128)>, AidlError> {
+        if let Some(values) = self.enum_values.borrow().get(key) {
+            return Ok(values.clone());
+        }
+        let scope = self.entry_scope(key);
+        let Definition::Enum(decl) = &self.types[key].def else {
+            unreachable!("enum key");
+        };
+        if self.enum_stack.borrow().iter().any(|k| k == key) {
+            return Err(self.invalid(
+                scope.doc,
+                decl.span,
+                format!("enum {} depends on its own values", decl.name),
+            ));
+        }
+        self.enum_stack.borrow_mut().push(key.to_owned());
+        let values = self.compute_enum_values(&scope, decl);
+        self.enum_stack.borrow_mut().pop();
+        let values = values?;
+        self.enum_values
+            .borrow_mut()
+            .insert(key.to_owned(), values.clone());
+        Ok(values)
+    }
+
+    fn compute_enum_values(
+        &self,
+        scope: &Scope,
+        decl: &Enum,
+    ) -> Result<Vec<(String, i128)>, AidlError> {
+        let (mut values, backing) = match self.enum_parent(scope, decl)? {
+            Some(parent) => (self.enum_values(&parent)?, self.enum_backing(&parent)?),
+            None => match self.resolve_type(scope, &decl.storage, 0)? {
+                Ty::Scalar(backing) => (Vec::new(), backing),
+                _ => unreachable!("checked by enum_parent"),
+            },
+        };
+        let (min, max) = integer_range(backing).expect("integer backing");
+        for enumerator in &decl.values {
+            let value = match &enumerator.value {
+                Some(expr) => self.eval(scope, expr, &values, decl.span)?,
+                None => values
+                    .last()
+                    .map_or(Some(0), |(_, last)| last.checked_add(1))
+                    .unwrap_or(i128::MAX),
+            };
+            if value < min || value > max {
+                return Err(self.invalid(
+                    scope.doc,
+                    decl.span,
+                    format!(
+                        "value {value} of {}.{} does not fit in {backing}",
+                        decl.name, enumerator.name
+                    ),
+                ));
+            }
+            values.push((enumerator.name.clone(), value));
+        }
+        Ok(values)
+    }
+
+    /// Evaluate a constant expression. Bare names refer to `locals` (the
+    /// enumerators defined so far); `Type:NAME` to a value of enum `Type`.
+    fn eval(
+        &self,
+        scope: &Scope,
+        expr: &ConstExpr,
+        locals: &[(String, i128)],
+        span: SourceSpan,
+    ) -> Result<i128, AidlError> {
+        let overflow = || self.invalid(scope.doc, span, "constant expression overflows".into());
+        Ok(match expr {
+            ConstExpr::Number(value) => *value,
+            ConstExpr::Ref {
+                scope: None,
+                name,
+                span,
+            } => locals
+                .iter()
+                .rev()
+                .find(|(local, _)| local == name)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| {
+                    self.invalid(scope.doc, *span, format!("unknown constant {name}"))
+                })?,
+            ConstExpr::Ref {
+                scope: Some(type_name),
+                name,
+                span,
+            } => {
+                let fq = FqName {
+                    package: None,
+                    version: None,
+                    path: type_name.split('.').map(str::to_owned).collect(),
+                    span: *span,
+                };
+                let key = self
+                    .lookup(scope, &fq)
+                    .filter(|key| matches!(self.types[key].def, Definition::Enum(_)))
+                    .ok_or_else(|| self.unknown(scope.doc, &fq))?;
+                self.enum_values(&key)?
+                    .iter()
+                    .find(|(value_name, _)| value_name == name)
+                    .map(|(_, value)| *value)
+                    .ok_or_else(|| {
+                        self.invalid(scope.doc, *span, format!("{type_name} has no value {name}"))
+                    })?
+            }
+            ConstExpr::Unary(op, operand) => {
+                let value = self.eval(scope, operand, locals, span)?;
+                match op.as_str() {
+                    "-" => value.checked_neg().ok_or_else(overflow)?,
+                    "~" => !value,
+                    "!" => (value == 0) as i128,
+                    _ => value,
+                }
+            }
+            ConstExpr::Binary(lhs, op, rhs) => {
+                let lhs = self.eval(scope, lhs, locals, span)?;
+                let rhs = self.eval(scope, rhs, locals, span)?;
+                let shift = || u32::try_from(rhs).ok().filter(|s| *s < 128);
+                match op.as_str() {
+                    "+" => lhs.checked_add(rhs).ok_or_else(overflow)?,
+                    "-" => lhs.checked_sub(rhs).ok_or_else(overflow)?,
+                    "*" => lhs.checked_mul(rhs).ok_or_else(overflow)?,
+                    "/" | "%" => {
+                        if rhs == 0 {
+                            return Err(self.invalid(scope.doc, span, "division by zero".into()));
+                        }
+                        if op == "/" {
+                            lhs / rhs
+                        } else {
+                            lhs % rhs
+                        }
+                    }
+                    "<<" => lhs
+                        .checked_shl(shift().ok_or_else(overflow)?)
+                        .ok_or_else(overflow)?,
+                    ">>" => lhs >> shift().ok_or_else(overflow)?,
+                    "&" => lhs & rhs,
+                    "|" => lhs | rhs,
+                    "^" => lhs ^ rhs,
+                    "&&" => (lhs != 0 && rhs != 0) as i128,
+                    "||" => (lhs != 0 || rhs != 0) as i128,
+                    "==" => (lhs == rhs) as i128,
+                    "!=" => (lhs != rhs) as i128,
+                    "<" => (lhs < rhs) as i128,
+                    ">" => (lhs > rhs) as i128,
+                    "<=" => (lhs <= rhs) as i128,
+                    ">=" => (lhs >= rhs) as i128,
+                    _ => unreachable!("grammar operator {op}"),
+                }
+            }
+        })
+    }
+
+    fn owned_type(&self, from: &Namespace, ty: &Ty) -> String {
+        let krate = self.crate_name();
+        match ty {
+            Ty::Scalar(name) => (*name).to_owned(),
+            Ty::Enum(key) | Ty::Struct(key) => self.item_path(from, key),
+            Ty::String => "String".into(),
+            Ty::Handle => format!("Option<{krate}::hidl::NativeHandle>"),
+            Ty::Vec(inner) => format!("Vec<{}>", self.owned_type(from, inner)),
+            Ty::Array(inner, dims) => dims
+                .iter()
+                .rev()
+                .fold(self.owned_type(from, inner), |ty, dim| {
+                    format!("[{ty}; {dim}]")
+                }),
+            Ty::Interface(Some(key)) => {
+                format!("Option<{krate}::Strong<dyn {}>>", self.item_path(from, key))
+            }
+            Ty::Interface(None) => format!("Option<{krate}::SIBinder>"),
+        }
+    }
+
+    fn arg_type(&self, from: &Namespace, ty: &Ty) -> String {
+        let krate = self.crate_name();
+        match ty {
+            Ty::String => "&str".into(),
+            Ty::Vec(inner) => format!("&[{}]", self.owned_type(from, inner)),
+            Ty::Handle => format!("Option<&{krate}::hidl::NativeHandle>"),
+            Ty::Interface(Some(key)) => {
+                format!(
+                    "Option<&{krate}::Strong<dyn {}>>",
+                    self.item_path(from, key)
+                )
+            }
+            Ty::Interface(None) => format!("Option<&{krate}::SIBinder>"),
+            _ if ty.is_copy() => self.owned_type(from, ty),
+            _ => format!("&{}", self.owned_type(from, ty)),
+        }
+    }
+
+    fn default_value(ty: &Ty) -> String {
+        match ty {
+            Ty::Array(_, dims) => dims
+                .iter()
+                .fold("Default::default()".to_owned(), |value, _| {
+                    format!("std::array::from_fn(|_| {value})")
+                }),
+            _ => "Default::default()".into(),
+        }
+    }
+
+    /// Statement writing `value` (an owned value when `owned`, otherwise a
+    /// method argument) into `parcel`.
+    fn write_func(&self, parcel: &str, value: &str, ty: &Ty, owned: bool) -> String {
+        let krate = self.crate_name();
+        match (ty, owned) {
+            (Ty::Interface(Some(_)), false) => format!(
+                "{parcel}.write_hidl_binder({value}.map(|_b| {krate}::Interface::as_binder(&**_b)).as_ref())?;"
+            ),
+            (Ty::Interface(Some(_)), true) => format!(
+                "{parcel}.write_hidl_binder({value}.as_ref().map(|_b| {krate}::Interface::as_binder(&**_b)).as_ref())?;"
+            ),
+            (Ty::Interface(None), false) => format!("{parcel}.write_hidl_binder({value})?;"),
+            (Ty::Interface(None), true) => format!("{parcel}.write_hidl_binder({value}.as_ref())?;"),
+            (Ty::Handle, false) => format!("{parcel}.write_native_handle({value})?;"),
+            (_, false) if ty.is_copy() => format!("{parcel}.write_hidl(&{value})?;"),
+            _ => format!("{parcel}.write_hidl({value})?;"),
+        }
+    }
+
+    fn read_func(parcel: &str, ty: &Ty) -> String {
+        match ty {
+            Ty::Interface(Some(_)) => {
+                format!("{parcel}.read_hidl_binder()?.map(|_b| _b.into_interface()).transpose()?")
+            }
+            Ty::Interface(None) => format!("{parcel}.read_hidl_binder()?"),
+            _ => format!("{parcel}.read_hidl()?"),
+        }
+    }
+
+    /// The argument form of an owned local, for calling the service.
+    fn borrow_arg(name: &str, ty: &Ty) -> String {
+        match ty {
+            Ty::Handle | Ty::Interface(_) => format!("{name}.as_ref()"),
+            _ if ty.is_copy() => name.to_owned(),
+            _ => format!("&{name}"),
+        }
+    }
+
+    fn method(
+        &self,
+        scope: &Scope,
+        module: &Namespace,
+        method: &Method,
+    ) -> Result<FnMember, AidlError> {
+        if method.oneway && !method.results.is_empty() {
+            return Err(self.invalid(
+                scope.doc,
+                method.span,
+                format!("oneway method {} cannot generate results", method.name),
+            ));
+        }
+        let mut member = FnMember {
+            identifier: method.name.clone(),
+            oneway: method.oneway,
+            args: String::new(),
+            return_type: String::new(),
+            func_call_params: String::new(),
+            write_funcs: Vec::new(),
+            read_funcs: Vec::new(),
+            return_value: String::new(),
+            transaction_decls: Vec::new(),
+            transaction_params: String::new(),
+            transaction_write: Vec::new(),
+        };
+
+        for arg in &method.args {
+            let ty = self.resolve_type(scope, &arg.ty, 0)?;
+            let name = format!("_arg_{}", arg.name);
+            member.args += &format!(", {name}: {}", self.arg_type(module, &ty));
+            member.func_call_params += &format!(", {name}");
+            member
+                .write_funcs
+                .push(self.write_func("_hidl_data", &name, &ty, false));
+            member.transaction_decls.push(format!(
+                "let {name}: {} = {};",
+                self.owned_type(module, &ty),
+                Self::read_func("_reader", &ty)
+            ));
+            member.transaction_params += &format!(", {}", Self::borrow_arg(&name, &ty));
+        }
+
+        let mut results = Vec::new();
+        for result in &method.results {
+            results.push((
+                format!("_ret_{}", result.name),
+                self.resolve_type(scope, &result.ty, 0)?,
+            ));
+        }
+        let types: Vec<String> = results
+            .iter()
+            .map(|(_, ty)| self.owned_type(module, ty))
+            .collect();
+        member.return_type = match types.len() {
+            0 => "()".into(),
+            1 => types[0].clone(),
+            _ => format!("({})", types.join(", ")),
+        };
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        member.return_value = match names.len() {
+            0 => "()".into(),
+            1 => names[0].to_owned(),
+            _ => format!("({})", names.join(", ")),
+        };
+        for (index, ((name, ty), rust_type)) in results.iter().zip(&types).enumerate() {
+            member.read_funcs.push(format!(
+                "let {name}: {rust_type} = {};",
+                Self::read_func("_hidl_reply", ty)
+            ));
+            let value = if results.len() == 1 {
+                "_hidl_return".to_owned()
+            } else {
+                format!("(&_hidl_return.{index})")
+            };
+            member
+                .transaction_write
+                .push(self.write_func("_reply", &value, ty, true));
+        }
+        Ok(member)
+    }
+
+    /// The interface `decl` extends, or `None` for `IBase`.
+    fn parent_interface(
+        &self,
+        scope: &Scope,
+        decl: &Interface,
+    ) -> Result<Option<String>, AidlError> {
+        let Some(extends) = &decl.extends else {
+            return Ok(None);
+        };
+        if self.is_ibase(scope, extends) {
+            return Ok(None);
+        }
+        match self.lookup(scope, extends) {
+            Some(key) if matches!(self.types[&key].def, Definition::Interface(_)) => Ok(Some(key)),
+            Some(_) => Err(self.invalid(
+                scope.doc,
+                extends.span,
+                format!("{extends} is not an interface"),
+            )),
+            None => Err(self.unknown(scope.doc, extends)),
+        }
+    }
+
+    fn descriptor(&self, key: &str) -> String {
+        let entry = &self.types[key];
+        format!(
+            "{}::{}",
+            self.documents[entry.doc].package,
+            entry.path.join(".")
+        )
+    }
+
+    fn interface(
+        &self,
+        scope: &Scope,
+        decl: &Interface,
+        indent: usize,
+    ) -> Result<String, AidlError> {
+        if scope.path.len() != 1 {
+            return Err(self.invalid(
+                scope.doc,
+                decl.span,
+                format!("interface {} must be declared at package level", decl.name),
+            ));
+        }
+        let module = self.module_of(scope);
+        let key = type_key(&self.documents[scope.doc].package, &scope.path);
+
+        // Chain, most derived first.
+        let mut chain = vec![key.clone()];
+        while let Some(parent) = {
+            let last = chain.last().expect("non-empty chain");
+            let Definition::Interface(decl) = &self.types[last].def else {
+                unreachable!("interface key");
+            };
+            self.parent_interface(&self.entry_scope(last), decl)?
+        } {
+            if chain.contains(&parent) || chain.len() > crate::parser::MAX_NESTING_DEPTH {
+                return Err(self.invalid(
+                    scope.doc,
+                    decl.span,
+                    format!("interface {} inherits from itself", decl.name),
+                ));
+            }
+            chain.push(parent);
+        }
+
+        // Serial ids count user methods from the root of the chain.
+        let mut transactions = Vec::new();
+        let mut chain_impls = Vec::new();
+        for key in chain.iter().rev() {
+            let Definition::Interface(parent) = &self.types[key].def else {
+                unreachable!("interface key");
+            };
+            let parent_scope = self.entry_scope(key);
+            let mut fn_members = Vec::new();
+            for method in &parent.methods {
+                if transactions.iter().any(|(name, _)| name == &method.name) {
+                    return Err(self.invalid(
+                        parent_scope.doc,
+                        method.span,
+                        format!(
+                            "method {} is already defined in the interface chain",
+                            method.name
+                        ),
+                    ));
+                }
+                transactions.push((method.name.clone(), transactions.len()));
+                fn_members.push(self.method(&parent_scope, &module, method)?);
+            }
+            chain_impls.push(ChainImpl {
+                trait_path: self.item_path(&module, key),
+                descriptor: self.descriptor(key),
+                fn_members,
+            });
+        }
+
+        let mut descriptors: Vec<String> = chain.iter().map(|key| self.descriptor(key)).collect();
+        descriptors.push(IBASE_DESCRIPTOR.into());
+
+        let short = decl
+            .name
+            .strip_prefix('I')
+            .filter(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()))
+            .unwrap_or(&decl.name);
+
+        let own = chain_impls.pop().expect("own methods");
+        let supertrait = match chain.get(1) {
+            Some(parent) => self.item_path(&module, parent),
+            None => format!("{}::Interface + Send", self.crate_name()),
+        };
+
+        let mut context = self.new_context();
+        context.insert("mod", &escape_rust_keyword(&decl.name));
+        context.insert("name", &escape_rust_keyword(&decl.name));
+        context.insert("descriptor", &self.descriptor(&key));
+        context.insert("chain", &descriptors);
+        context.insert("supertrait", &supertrait);
+        context.insert("fn_members", &own.fn_members);
+        context.insert("transactions", &transactions);
+        context.insert("bn_name", &format!("BnHw{short}"));
+        context.insert("bp_name", &format!("BpHw{short}"));
+        chain_impls.push(own);
+        context.insert("chain_impls", &chain_impls);
+        context.insert("nested", &self.nested(scope, &decl.types)?);
+
+        let rendered =
+            template()
+                .render("hidl_interface", &context)
+                .map_err(|e| AidlError::Template {
+                    message: format!("Failed to render HIDL interface template: {e}"),
+                })?;
+
+        Ok(add_indent(indent, rendered.trim()))
+    }
+
+    fn structure(&self, scope: &Scope, decl: &Struct, indent: usize) -> Result<String, AidlError> {
+        let module = self.module_of(scope);
+        let krate = self.crate_name();
+        let mut fields: Vec<StructField> = Vec::new();
+        let mut has_handle = false;
+        for field in &decl.fields {
+            let ty = self.resolve_type(scope, &field.ty, 0)?;
+            if let Ty::Interface(_) = ty {
+                return Err(self.unsupported(
+                    scope.doc,
+                    field.ty.span,
+                    &format!("{}.{}", decl.name, field.name),
+                    "interfaces cannot be struct fields in HIDL; pass them as method arguments",
+                ));
+            }
+            has_handle |= self.contains_handle(&ty, &mut Vec::new());
+            let rust_type = self.owned_type(&module, &ty);
+            let offset = match fields.last() {
+                None => "0".to_owned(),
+                Some(prev) => format!(
+                    "{krate}::hidl::align_up(Self::OFFSET_{} + <{} as {krate}::hidl::HidlType>::SIZE, <{rust_type} as {krate}::hidl::HidlType>::ALIGN)",
+                    prev.name, prev.ty
+                ),
+            };
+            fields.push(StructField {
+                name: field.name.clone(),
+                default: Self::default_value(&ty),
+                ty: rust_type,
+                offset,
+            });
+        }
+
+        let align = fields.iter().rev().fold("1".to_owned(), |align, field| {
+            format!(
+                "{krate}::hidl::max_align(<{} as {krate}::hidl::HidlType>::ALIGN, {align})",
+                field.ty
+            )
+        });
+        let size = match fields.last() {
+            None => "1".to_owned(),
+            Some(last) => format!(
+                "{krate}::hidl::align_up(Self::OFFSET_{} + <{} as {krate}::hidl::HidlType>::SIZE, Self::ALIGN)",
+                last.name, last.ty
+            ),
+        };
+
+        let mut context = self.new_context();
+        context.insert("mod", &escape_rust_keyword(&decl.name));
+        context.insert("name", &escape_rust_keyword(&decl.name));
+        context.insert(
+            "derive",
+            if has_handle {
+                "Debug"
+            } else {
+                "Debug, Clone, PartialEq"
+            },
+        );
+        context.insert("fields", &fields);
+        context.insert("size", &size);
+        context.insert("align", &align);
+        context.insert("nested", &self.nested(scope, &decl.types)?);
+
+        let rendered =
+            template()
+                .render("hidl_struct", &context)
+                .map_err(|e| AidlError::Template {
+                    message: format!("Failed to render HIDL struct template: {e}"),
+                })?;
+
+        Ok(add_indent(indent, rendered.trim()))
+    }
+
+    fn enumeration(&self, scope: &Scope, decl: &Enum, indent: usize) -> Result<String, AidlError> {
+        let key = type_key(&self.documents[scope.doc].package, &scope.path);
+        let backing = self.enum_backing(&key)?;
+        let members: Vec<(String, String)> = self
+            .enum_values(&key)?
+            .into_iter()
+            .map(|(name, value)| (name, value.to_string()))
+            .collect();
+
+        let mut context = self.new_context();
+        context.insert("mod", &escape_rust_keyword(&decl.name));
+        context.insert("name", &decl.name);
+        context.insert("backing", backing);
+        context.insert("len", &members.len());
+        context.insert("members", &members);
+
+        let rendered =
+            template()
+                .render("hidl_enum", &context)
+                .map_err(|e| AidlError::Template {
+                    message: format!("Failed to render HIDL enum template: {e}"),
+                })?;
+
+        Ok(add_indent(indent, rendered.trim()))
+    }
+}
+
+impl TypeEntry {
+    fn def_span(&self) -> SourceSpan {
+        match &self.def {
+            Definition::Interface(decl) => decl.span,
+            Definition::Struct(decl) => decl.span,
+            Definition::Enum(decl) => decl.span,
+            Definition::Typedef(decl) => decl.span,
+            Definition::Union { span, .. } => *span,
+        }
+    }
+}
@@ -0,0 +1,371 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! HIDL (`.hal`) compiler for rsbinder's hwbinder support.
+//!
+//! Generates proxies, stubs, structs and enums in the same shape as the
+//! AIDL output, on top of `rsbinder::hidl`. Driven from a `build.rs`:
+//!
+//! ```no_run
+//! rsbinder_aidl::hidl::Builder::new()
+//!     .source("hal/vendor/foo/1.0") // a file or a directory
+//!     .output("foo_hal.rs")         // under OUT_DIR
+//!     .generate()
+//!     .unwrap_or_else(|err| {
+//!         eprintln!("{:?}", miette::Report::new(err));
+//!         std::process::exit(1);
+//!     });
+//! ```
+//!
+//! Packages map to directories as in AOSP (`<root>/<package path>/<M.m>/`).
+//! The root of each source is inferred from its path and `package`
+//! declaration; [`Builder::package_root`] adds one explicitly, like
+//! `hidl-gen -r`. Imported packages (`import android.hidl.base@1.0;`,
+//! `@1.0::IFoo`) are loaded from the matching root. `IBase` and the
+//! `interface` type are built in.
+//!
+//! Unions, `safe_union`, `memory`, `pointer` and fmq types are not
+//! supported and are reported as errors.
+
+use std::collections::HashSet;
+use std::fs;
+use std::mem::take;
+use std::path::{Path, PathBuf};
+
+use miette::{NamedSource, SourceSpan};
+
+use crate::error::{AidlError, ResolutionError};
+use crate::parser::SourceContext;
+
+mod generator;
+mod parser;
+
+pub use generator::Generator;
+pub use parser::{parse_document, Document, Package};
+
+use parser::{BaseType, Definition, FqName, Import, TypeRef};
+
+pub struct Builder {
+    sources: Vec<PathBuf>,
+    roots: Vec<(String, PathBuf)>,
+    dest_dir: PathBuf,
+    output: PathBuf,
+    is_crate: bool,
+    dependencies: Vec<PathBuf>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            roots: Vec::new(),
+            dest_dir: PathBuf::from(std::env::var_os("OUT_DIR").unwrap_or("aidl_gen".into())),
+            output: "rsbinder_generated_hidl.rs".into(),
+            is_crate: false,
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Add a `.hal` file, or a directory scanned recursively for `*.hal`.
+    pub fn source(mut self, source: impl AsRef<Path>) -> Self {
+        self.sources.push(source.as_ref().into());
+        self
+    }
+
+    /// Map the package prefix `prefix` (e.g. `android.hardware`) to `dir`,
+    /// like `hidl-gen -r android.hardware:hardware/interfaces`.
+    pub fn package_root(mut self, prefix: impl Into<String>, dir: impl AsRef<Path>) -> Self {
+        self.roots.push((prefix.into(), dir.as_ref().into()));
+        self
+    }
+
+    pub fn output(mut self, output: impl AsRef<Path>) -> Self {
+        let mut output = output.as_ref().to_owned();
+
+        if output.extension().is_none() {
+            output.set_extension("rs");
+        }
+
+        self.output = output;
+
+        self
+    }
+
+    /// It must be used in rsbinder's build.rs.
+    /// It generates the rust output file with crate::??? instead of rsbinder::???.
+    pub fn set_crate_support(mut self, enable: bool) -> Self {
+        self.is_crate = enable;
+        self
+    }
+
+    pub fn generate(mut self) -> Result<(), AidlError> {
+        let documents = self.parse_sources()?;
+        if documents.is_empty() {
+            return Err(AidlError::Config {
+                message: "no .hal sources found: add Builder::source(<file-or-dir>) entries \
+                          (directories are scanned recursively for *.hal)"
+                    .into(),
+            });
+        }
+
+        let mut deps = take(&mut self.dependencies);
+        deps.sort();
+        deps.dedup();
+        for path in deps {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+
+        let package_list = Generator::new(&documents, self.is_crate).generate()?;
+        let content = crate::generate_all(package_list);
+
+        fs::write(self.dest_dir.join(&self.output), content)?;
+
+        Ok(())
+    }
+
+    fn parse_file(path: &Path) -> Result<Document, AidlError> {
+        println!("Parsing: {path:?}");
+        let source = fs::read_to_string(path)?;
+        let ctx = SourceContext::new(path.to_string_lossy().as_ref(), source);
+        parse_document(&ctx)
+    }
+
+    fn collect_files(&mut self, path: &Path, files: &mut Vec<PathBuf>) -> Result<(), AidlError> {
+        if path.is_file() {
+            files.push(path.to_owned());
+            return Ok(());
+        }
+        self.dependencies.push(path.to_owned());
+        let entries = fs::read_dir(path).map_err(|err| {
+            std::io::Error::new(
+                err.kind(),
+                format!("parse_sources: fs::read_dir({path:?}) failed: {err}"),
+            )
+        })?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.collect_files(&path, files)?;
+            } else if path.extension().unwrap_or_default() == "hal" {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// The root a file's package lives under: strip the version directory,
+    /// then as many trailing package components as the path matches. The
+    /// unmatched leading components form the root's prefix.
+    fn infer_root(path: &Path, package: &Package) -> Option<(String, PathBuf)> {
+        let version = path.parent()?;
+        if version.file_name()?.to_str()? != format!("{}.{}", package.major, package.minor) {
+            return None;
+        }
+        let mut dir = version.parent()?.to_owned();
+        let mut components: Vec<&str> = package.name.split('.').collect();
+        while let Some(last) = components.last() {
+            if dir.file_name().and_then(|name| name.to_str()) != Some(*last) {
+                break;
+            }
+            components.pop();
+            dir = dir.parent()?.to_owned();
+        }
+        Some((components.join("."), dir))
+    }
+
+    /// The directory holding `package`, from the longest matching root.
+    fn package_dir(&self, package: &Package) -> Option<PathBuf> {
+        self.roots
+            .iter()
+            .filter_map(|(prefix, dir)| {
+                let rest = if prefix.is_empty() {
+                    package.name.as_str()
+                } else if package.name == *prefix {
+                    ""
+                } else {
+                    package.name.strip_prefix(prefix)?.strip_prefix('.')?
+                };
+                let mut dir = dir.clone();
+                dir.extend(rest.split('.').filter(|c| !c.is_empty()));
+                dir.push(format!("{}.{}", package.major, package.minor));
+                Some((prefix.len(), dir))
+            })
+            .filter(|(_, dir)| dir.is_dir())
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, dir)| dir)
+    }
+
+    fn parse_sources(&mut self) -> Result<Vec<Document>, AidlError> {
+        let mut files = Vec::new();
+        for source in take(&mut self.sources) {
+            self.collect_files(&source, &mut files)?;
+        }
+        files.sort();
+
+        let mut seen = HashSet::new();
+        let mut documents = Vec::new();
+        let mut errors = Vec::new();
+        let mut pending = files;
+        let mut loaded = HashSet::new();
+        // `IBase` is built in, so its package need not exist on disk.
+        let builtin = Package {
+            name: "android.hidl.base".into(),
+            major: 1,
+            minor: 0,
+        };
+
+        while !pending.is_empty() {
+            for path in take(&mut pending) {
+                if !seen.insert(path.clone()) {
+                    continue;
+                }
+                match Self::parse_file(&path) {
+                    Ok(document) => {
+                        self.dependencies.push(path.clone());
+                        if let Some(root) = Self::infer_root(&path, &document.package) {
+                            if !self.roots.contains(&root) {
+                                self.roots.push(root);
+                            }
+                        }
+                        documents.push(document);
+                    }
+                    Err(err) => errors.push(err),
+                }
+            }
+            if !errors.is_empty() {
+                break;
+            }
+
+            // Load the rest of every package seen so far and every package
+            // referenced by a versioned name.
+            let mut wanted: Vec<(Package, Option<(usize, SourceSpan)>)> = documents
+                .iter()
+                .map(|document| (document.package.clone(), None))
+                .collect();
+            for (index, document) in documents.iter().enumerate() {
+                referenced_packages(document, &mut |package, span| {
+                    wanted.push((package, Some((index, span))));
+                });
+            }
+            for (package, origin) in wanted {
+                if !loaded.insert(package.clone()) {
+                    continue;
+                }
+                match self.package_dir(&package) {
+                    Some(dir) => {
+                        self.dependencies.push(dir.clone());
+                        let mut files = Vec::new();
+                        for entry in fs::read_dir(&dir)? {
+                            let path = entry?.path();
+                            if path.is_file() && path.extension().unwrap_or_default() == "hal" {
+                                files.push(path);
+                            }
+                        }
+                        files.sort();
+                        pending.extend(files.into_iter().filter(|path| !seen.contains(path)));
+                    }
+                    None if package == builtin => {}
+                    None => {
+                        if let Some((index, span)) = origin {
+                            let document: &Document = &documents[index];
+                            errors.push(
+                                ResolutionError::ImportNotFound {
+                                    import: package.to_string(),
+                                    src: NamedSource::new(
+                                        &document.ctx.filename,
+                                        document.ctx.source.clone(),
+                                    ),
+                                    span,
+                                }
+                                .into(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(err) = AidlError::collect(errors) {
+            return Err(err);
+        }
+        Ok(documents)
+    }
+}
+
+/// Call `found` for every package `document` names with a version, with
+/// the span of the reference.
+fn referenced_packages(document: &Document, found: &mut impl FnMut(Package, SourceSpan)) {
+    for import in &document.imports {
+        if let Import::Package(package) = import {
+            // Import statements keep no span; point at the text.
+            let text = package.to_string();
+            let offset = document.ctx.source.find(&text).unwrap_or(0);
+            found(package.clone(), SourceSpan::new(offset.into(), text.len()));
+        }
+    }
+
+    let current = &document.package;
+    let mut fq_name = |name: &FqName| {
+        if let Some((major, minor)) = name.version {
+            found(
+                Package {
+                    name: name.package.clone().unwrap_or_else(|| current.name.clone()),
+                    major,
+                    minor,
+                },
+                name.span,
+            );
+        }
+    };
+
+    fn type_ref(ty: &TypeRef, fq_name: &mut impl FnMut(&FqName)) {
+        match &ty.base {
+            BaseType::Named(name) | BaseType::Bitfield(name) => fq_name(name),
+            BaseType::Vec(inner) => type_ref(inner, fq_name),
+        }
+    }
+
+    fn definition(def: &Definition, fq_name: &mut impl FnMut(&FqName)) {
+        match def {
+            Definition::Interface(decl) => {
+                if let Some(extends) = &decl.extends {
+                    fq_name(extends);
+                }
+                for method in &decl.methods {
+                    for param in method.args.iter().chain(&method.results) {
+                        type_ref(&param.ty, fq_name);
+                    }
+                }
+                for child in &decl.types {
+                    definition(child, fq_name);
+                }
+            }
+            Definition::Struct(decl) => {
+                for field in &decl.fields {
+                    type_ref(&field.ty, fq_name);
+                }
+                for child in &decl.types {
+                    definition(child, fq_name);
+                }
+            }
+            Definition::Enum(decl) => type_ref(&decl.storage, fq_name),
+            Definition::Typedef(decl) => type_ref(&decl.ty, fq_name),
+            Definition::Union { .. } => {}
+        }
+    }
+
+    for import in &document.imports {
+        if let Import::Type(name) = import {
+            fq_name(name);
+        }
+    }
+    for def in &document.definitions {
+        definition(def, &mut fq_name);
+    }
+}
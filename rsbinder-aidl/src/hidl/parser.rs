@@ -0,0 +1,550 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `.hal` parser: turns a HIDL source file into a [`Document`].
+
+use miette::{NamedSource, SourceSpan};
+use pest::iterators::Pair;
+use pest::Parser;
+
+use crate::error::{pest_error_to_diagnostic, AidlError, ParseError};
+use crate::parser::{check_nesting_depth, SourceContext, MAX_NESTING_DEPTH};
+
+#[derive(pest_derive::Parser)]
+#[grammar = "hidl/hidl.pest"]
+struct HidlParser;
+
+/// A HIDL package with its version, e.g. `android.hidl.manager@1.0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Package {
+    pub name: String,
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Package {
+    fn parse(text: &str) -> Option<Self> {
+        let (name, version) = text.split_once('@')?;
+        let (major, minor) = version.split_once('.')?;
+        Some(Self {
+            name: name.to_owned(),
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        })
+    }
+
+    /// The Rust module holding the version, e.g. `V1_0`.
+    pub fn version_mod(&self) -> String {
+        format!("V{}_{}", self.major, self.minor)
+    }
+}
+
+impl std::fmt::Display for Package {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}.{}", self.name, self.major, self.minor)
+    }
+}
+
+/// A possibly package-qualified type name: `Name`, `Outer.Inner`,
+/// `@1.0::Name` (same package, other version) or `pkg@1.0::Name`.
+#[derive(Debug, Clone)]
+pub struct FqName {
+    /// Package name of a qualified reference; `None` for `@M.m::` and
+    /// unqualified names.
+    pub package: Option<String>,
+    pub version: Option<(u32, u32)>,
+    pub path: Vec<String>,
+    pub span: SourceSpan,
+}
+
+impl std::fmt::Display for FqName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((major, minor)) = self.version {
+            write!(
+                f,
+                "{}@{major}.{minor}::",
+                self.package.as_deref().unwrap_or("")
+            )?;
+        }
+        write!(f, "{}", self.path.join("."))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BaseType {
+    Named(FqName),
+    Vec(Box<TypeRef>),
+    Bitfield(FqName),
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeRef {
+    pub base: BaseType,
+    /// Array dimensions, outermost first (`T[2][3]` is two arrays of three).
+    pub dims: Vec<ConstExpr>,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConstExpr {
+    Number(i128),
+    /// `NAME` or `Type:NAME`.
+    Ref {
+        scope: Option<String>,
+        name: String,
+        span: SourceSpan,
+    },
+    Unary(String, Box<ConstExpr>),
+    Binary(Box<ConstExpr>, String, Box<ConstExpr>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub ty: TypeRef,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub name: String,
+    pub oneway: bool,
+    pub args: Vec<Param>,
+    pub results: Vec<Param>,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub name: String,
+    pub extends: Option<FqName>,
+    pub methods: Vec<Method>,
+    pub types: Vec<Definition>,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub ty: TypeRef,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Struct {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub types: Vec<Definition>,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone)]
+pub struct Enumerator {
+    pub name: String,
+    pub value: Option<ConstExpr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Enum {
+    pub name: String,
+    pub storage: TypeRef,
+    pub values: Vec<Enumerator>,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone)]
+pub struct Typedef {
+    pub name: String,
+    pub ty: TypeRef,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone)]
+pub enum Definition {
+    Interface(Interface),
+    Struct(Struct),
+    Enum(Enum),
+    Typedef(Typedef),
+    /// `union` / `safe_union`; parsed so the generator can reject it by name.
+    Union {
+        name: String,
+        span: SourceSpan,
+    },
+}
+
+impl Definition {
+    pub fn name(&self) -> &str {
+        match self {
+            Definition::Interface(decl) => &decl.name,
+            Definition::Struct(decl) => &decl.name,
+            Definition::Enum(decl) => &decl.name,
+            Definition::Typedef(decl) => &decl.name,
+            Definition::Union { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Import {
+    /// `import pkg@M.m;`
+    Package(Package),
+    /// `import Name;`, `import @M.m::Name;`, `import pkg@M.m::Name.Inner;`
+    Type(FqName),
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub package: Package,
+    pub imports: Vec<Import>,
+    pub definitions: Vec<Definition>,
+    pub ctx: SourceContext,
+}
+
+impl Document {
+    pub fn named_source(&self) -> NamedSource<String> {
+        NamedSource::new(&self.ctx.filename, self.ctx.source.clone())
+    }
+}
+
+fn span_of(pair: &Pair<Rule>) -> SourceSpan {
+    let span = pair.as_span();
+    SourceSpan::new(span.start().into(), span.end() - span.start())
+}
+
+/// Parse a `.hal` source.
+pub fn parse_document(ctx: &SourceContext) -> Result<Document, AidlError> {
+    if let Some(offset) = check_nesting_depth(&ctx.source) {
+        return Err(ParseError::nesting_too_deep(
+            &ctx.filename,
+            &ctx.source,
+            offset,
+            MAX_NESTING_DEPTH,
+        )
+        .into());
+    }
+
+    let document = HidlParser::parse(Rule::document, &ctx.source)
+        .map_err(|err| pest_error_to_diagnostic(err, &ctx.filename, &ctx.source))?
+        .next()
+        .expect("document rule");
+
+    let mut package = None;
+    let mut imports = Vec::new();
+    let mut definitions = Vec::new();
+    for pair in document.into_inner() {
+        match pair.as_rule() {
+            Rule::package => {
+                let text = pair.into_inner().next().expect("fq_package").as_str();
+                package = Some(Package::parse(text).ok_or_else(|| ParseError {
+                    src: NamedSource::new(&ctx.filename, ctx.source.clone()),
+                    span: SourceSpan::new(0.into(), 0),
+                    message: format!("invalid package version: {text}"),
+                    help: None,
+                })?);
+            }
+            Rule::import => {
+                let inner = pair.into_inner().next().expect("import body");
+                match inner.as_rule() {
+                    Rule::import_package => {
+                        if let Some(package) = Package::parse(inner.as_str()) {
+                            imports.push(Import::Package(package));
+                        }
+                    }
+                    _ => imports.push(Import::Type(parse_fq_name(inner))),
+                }
+            }
+            Rule::EOI | Rule::annotation => {}
+            _ => definitions.push(parse_definition(pair)),
+        }
+    }
+
+    Ok(Document {
+        package: package.expect("grammar requires a package"),
+        imports,
+        definitions,
+        ctx: ctx.clone(),
+    })
+}
+
+fn parse_definition(pair: Pair<Rule>) -> Definition {
+    let span = span_of(&pair);
+    match pair.as_rule() {
+        Rule::interface_decl => {
+            let mut decl = Interface {
+                name: String::new(),
+                extends: None,
+                methods: Vec::new(),
+                types: Vec::new(),
+                span,
+            };
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::identifier => decl.name = pair.as_str().to_owned(),
+                    Rule::fq_name => decl.extends = Some(parse_fq_name(pair)),
+                    Rule::method_decl => decl.methods.push(parse_method(pair)),
+                    Rule::annotation => {}
+                    _ => decl.types.push(parse_definition(pair)),
+                }
+            }
+            Definition::Interface(decl)
+        }
+        Rule::struct_decl => {
+            let mut decl = Struct {
+                name: String::new(),
+                fields: Vec::new(),
+                types: Vec::new(),
+                span,
+            };
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::identifier => decl.name = pair.as_str().to_owned(),
+                    Rule::field_decl => {
+                        let (ty, name) = parse_typed_name(pair);
+                        decl.fields.push(Field { ty, name });
+                    }
+                    Rule::annotation => {}
+                    _ => decl.types.push(parse_definition(pair)),
+                }
+            }
+            Definition::Struct(decl)
+        }
+        Rule::union_decl => {
+            let name = pair
+                .into_inner()
+                .find(|p| p.as_rule() == Rule::identifier)
+                .map(|p| p.as_str().to_owned())
+                .unwrap_or_default();
+            Definition::Union { name, span }
+        }
+        Rule::enum_decl => {
+            let mut name = String::new();
+            let mut storage = None;
+            let mut values = Vec::new();
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::identifier => name = pair.as_str().to_owned(),
+                    Rule::type_ref => storage = Some(parse_type_ref(pair)),
+                    Rule::enumerator => {
+                        let mut enumerator = Enumerator {
+                            name: String::new(),
+                            value: None,
+                        };
+                        for pair in pair.into_inner() {
+                            match pair.as_rule() {
+                                Rule::identifier => enumerator.name = pair.as_str().to_owned(),
+                                Rule::const_expr => {
+                                    enumerator.value = Some(parse_const_expr(pair));
+                                }
+                                _ => {}
+                            }
+                        }
+                        values.push(enumerator);
+                    }
+                    _ => {}
+                }
+            }
+            Definition::Enum(Enum {
+                name,
+                storage: storage.expect("grammar requires an enum storage type"),
+                values,
+                span,
+            })
+        }
+        Rule::typedef_decl => {
+            let (ty, name) = parse_typed_name(pair);
+            Definition::Typedef(Typedef { name, ty, span })
+        }
+        rule => unreachable!("unexpected definition rule {rule:?}"),
+    }
+}
+
+fn parse_method(pair: Pair<Rule>) -> Method {
+    let mut method = Method {
+        name: String::new(),
+        oneway: false,
+        args: Vec::new(),
+        results: Vec::new(),
+        span: span_of(&pair),
+    };
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::oneway => method.oneway = true,
+            Rule::identifier => method.name = pair.as_str().to_owned(),
+            Rule::param => method.args.push(parse_param(pair)),
+            Rule::generates => {
+                method.results = pair.into_inner().map(parse_param).collect();
+            }
+            _ => {}
+        }
+    }
+    method
+}
+
+fn parse_param(pair: Pair<Rule>) -> Param {
+    let (ty, name) = parse_typed_name(pair);
+    Param { ty, name }
+}
+
+fn parse_typed_name(pair: Pair<Rule>) -> (TypeRef, String) {
+    let mut ty = None;
+    let mut name = String::new();
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::type_ref => ty = Some(parse_type_ref(pair)),
+            Rule::identifier => name = pair.as_str().to_owned(),
+            _ => {}
+        }
+    }
+    (ty.expect("grammar requires a type"), name)
+}
+
+fn parse_type_ref(pair: Pair<Rule>) -> TypeRef {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner();
+    let base = inner.next().expect("type_ref base");
+    let base = match base.as_rule() {
+        Rule::vec_type => BaseType::Vec(Box::new(parse_type_ref(
+            base.into_inner().next().expect("vec element type"),
+        ))),
+        Rule::bitfield_type => BaseType::Bitfield(parse_fq_name(
+            base.into_inner().next().expect("bitfield enum"),
+        )),
+        _ => BaseType::Named(parse_fq_name(base)),
+    };
+    let dims = inner
+        .map(|dim| parse_const_expr(dim.into_inner().next().expect("array size")))
+        .collect();
+    TypeRef { base, dims, span }
+}
+
+fn parse_fq_name(pair: Pair<Rule>) -> FqName {
+    let span = span_of(&pair);
+    let mut name = FqName {
+        package: None,
+        version: None,
+        path: Vec::new(),
+        span,
+    };
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::fq_package_prefix => {
+                for pair in pair.into_inner() {
+                    match pair.as_rule() {
+                        Rule::package_name => name.package = Some(pair.as_str().to_owned()),
+                        Rule::version => {
+                            let (major, minor) =
+                                pair.as_str().split_once('.').expect("version grammar");
+                            name.version = Some((
+                                major.parse().unwrap_or_default(),
+                                minor.parse().unwrap_or_default(),
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Rule::scoped_name => {
+                name.path = pair.as_str().split('.').map(str::to_owned).collect();
+            }
+            _ => {}
+        }
+    }
+    name
+}
+
+fn parse_const_expr(pair: Pair<Rule>) -> ConstExpr {
+    // Flatten `unary* primary (op unary* primary)*` into operands and
+    // operators, then fold by C precedence.
+    let mut operands = Vec::new();
+    let mut operators = Vec::new();
+    let mut unary = Vec::new();
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::unary_op => unary.push(pair.as_str().to_owned()),
+            Rule::binary_op => operators.push(pair.as_str().to_owned()),
+            _ => {
+                let mut operand = parse_const_primary(pair);
+                for op in unary.drain(..).rev() {
+                    operand = ConstExpr::Unary(op, Box::new(operand));
+                }
+                operands.push(operand);
+            }
+        }
+    }
+    fold_binary(operands, operators)
+}
+
+fn parse_const_primary(pair: Pair<Rule>) -> ConstExpr {
+    match pair.as_rule() {
+        Rule::number => {
+            let text = pair.as_str().trim_end_matches(['u', 'U', 'l', 'L']);
+            let value = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+                Some(hex) => i128::from_str_radix(hex, 16),
+                None => text.parse(),
+            };
+            // The grammar only admits digits; overflow is far beyond any
+            // HIDL storage type and is rejected by the range check later.
+            ConstExpr::Number(value.unwrap_or(i128::MAX))
+        }
+        Rule::const_expr => parse_const_expr(pair),
+        Rule::enum_ref => {
+            let span = span_of(&pair);
+            let mut inner = pair.into_inner();
+            let first = inner.next().expect("enum_ref name").as_str().to_owned();
+            match inner.next() {
+                Some(name) => ConstExpr::Ref {
+                    scope: Some(first),
+                    name: name.as_str().to_owned(),
+                    span,
+                },
+                None => ConstExpr::Ref {
+                    scope: None,
+                    name: first,
+                    span,
+                },
+            }
+        }
+        rule => unreachable!("unexpected const_expr rule {rule:?}"),
+    }
+}
+
+fn precedence(op: &str) -> u8 {
+    match op {
+        "||" => 1,
+        "&&" => 2,
+        "|" => 3,
+        "^" => 4,
+        "&" => 5,
+        "==" | "!=" => 6,
+        "<" | ">" | "<=" | ">=" => 7,
+        "<<" | ">>" => 8,
+        "+" | "-" => 9,
+        _ => 10,
+    }
+}
+
+fn fold_binary(operands: Vec<ConstExpr>, operators: Vec<String>) -> ConstExpr {
+    let mut output: Vec<ConstExpr> = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    let reduce = |output: &mut Vec<ConstExpr>, op: String| {
+        let rhs = output.pop().expect("rhs operand");
+        let lhs = output.pop().expect("lhs operand");
+        output.push(ConstExpr::Binary(Box::new(lhs), op, Box::new(rhs)));
+    };
+
+    let mut operands = operands.into_iter();
+    output.push(operands.next().expect("at least one operand"));
+    for (op, operand) in operators.into_iter().zip(operands) {
+        while stack
+            .last()
+            .is_some_and(|top| precedence(top) >= precedence(&op))
+        {
+            let top = stack.pop().expect("checked");
+            reduce(&mut output, top);
+        }
+        stack.push(op);
+        output.push(operand);
+    }
+    while let Some(op) = stack.pop() {
+        reduce(&mut output, op);
+    }
+    output.pop().expect("folded expression")
+}
@@ -67,6 +67,7 @@ use std::path::{Path, PathBuf};
 mod const_expr;
 pub mod error;
 mod generator;
+pub mod hidl;
 mod parser;
 mod type_generator;
 pub use error::AidlError;
@@ -143,7 +144,13 @@ impl Namespace {
 /// Only fully-qualified names listed here are exempted; any unknown
 /// import still surfaces as `ResolutionError::ImportNotFound`.
 pub(crate) fn is_builtin_aidl_type(fqcn: &str) -> bool {
-    matches!(fqcn, "android.os.ParcelFileDescriptor")
+    matches!(
+        fqcn,
+        "android.os.ParcelFileDescriptor"
+            | "android.hardware.common.fmq.MQDescriptor"
+            | "android.hardware.common.fmq.SynchronizedReadWrite"
+            | "android.hardware.common.fmq.UnsynchronizedWrite"
+    )
 }
 
 /// Wrap `ident` as a Rust raw identifier (`r#ident`) iff it is a Rust keyword
@@ -197,6 +204,65 @@ pub fn add_indent(step: usize, source: &str) -> String {
     content
 }
 
+/// Nest generated items into `pub mod` blocks following their dotted
+/// namespace. `package_list` holds `(namespace, code, name)` triples.
+pub(crate) fn generate_all(mut package_list: Vec<(String, String, String)>) -> String {
+    let mut content = String::new();
+    let mut namespace = String::new();
+    let mut mod_count: usize = 0;
+
+    content += "#[allow(clippy::all)]\n";
+    content += "#[allow(unused_imports)]\n\n";
+
+    package_list.sort();
+
+    for package in package_list {
+        if namespace != package.0 {
+            let namespace_split: Vec<&str> = namespace.split('.').collect();
+            let mod_list: Vec<&str> = package.0.split('.').collect();
+
+            let cmp_len = std::cmp::min(namespace_split.len(), mod_list.len());
+            let mut start = 0;
+
+            for i in 0..cmp_len {
+                if namespace_split[i] == mod_list[i] {
+                    start += 1;
+                } else {
+                    break;
+                }
+            }
+
+            for i in (start..mod_count).rev() {
+                content += &indent_space(i);
+                content += "}\n";
+            }
+
+            namespace = package.0.clone();
+            mod_count = start;
+
+            for r#mod in &mod_list[start..] {
+                content += &indent_space(mod_count);
+                // HIDL package versions generate into `V1_0`-style modules.
+                if r#mod.chars().any(|c| c.is_ascii_uppercase()) {
+                    content += "#[allow(non_snake_case)]\n";
+                    content += &indent_space(mod_count);
+                }
+                content += &format!("pub mod {mod} {{\n");
+                mod_count += 1;
+            }
+        }
+
+        content += &add_indent(mod_count, &package.1);
+    }
+
+    for i in (0..mod_count).rev() {
+        content += &indent_space(i);
+        content += "}\n";
+    }
+
+    content
+}
+
 /// Per-source frozen-API metadata, populated by [`Builder::version`] /
 /// [`Builder::hash`]. Mirrors AOSP `aidl --version N --hash <s>` semantics:
 /// the version int and hash string are echoed verbatim through the
@@ -367,61 +433,6 @@ impl Builder {
         Ok((name, document, ctx))
     }
 
-    fn generate_all(
-        &self,
-        mut package_list: Vec<(String, String, String)>,
-    ) -> Result<String, AidlError> {
-        let mut content = String::new();
-        let mut namespace = String::new();
-        let mut mod_count: usize = 0;
-
-        content += "#[allow(clippy::all)]\n";
-        content += "#[allow(unused_imports)]\n\n";
-
-        package_list.sort();
-
-        for package in package_list {
-            if namespace != package.0 {
-                let namespace_split: Vec<&str> = namespace.split('.').collect();
-                let mod_list: Vec<&str> = package.0.split('.').collect();
-
-                let cmp_len = std::cmp::min(namespace_split.len(), mod_list.len());
-                let mut start = 0;
-
-                for i in 0..cmp_len {
-                    if namespace_split[i] == mod_list[i] {
-                        start += 1;
-                    } else {
-                        break;
-                    }
-                }
-
-                for i in (start..mod_count).rev() {
-                    content += &indent_space(i);
-                    content += "}\n";
-                }
-
-                namespace = package.0.clone();
-                mod_count = start;
-
-                for r#mod in &mod_list[start..] {
-                    content += &indent_space(mod_count);
-                    content += &format!("pub mod {mod} {{\n");
-                    mod_count += 1;
-                }
-            }
-
-            content += &add_indent(mod_count, &package.1);
-        }
-
-        for i in (0..mod_count).rev() {
-            content += &indent_space(i);
-            content += "}\n";
-        }
-
-        Ok(content)
-    }
-
     fn parse_sources(
         &mut self,
     ) -> Result<Vec<(String, parser::Document, parser::SourceContext)>, AidlError> {
@@ -656,7 +667,7 @@ impl Builder {
             return Err(err);
         }
 
-        let content = self.generate_all(package_list)?;
+        let content = generate_all(package_list);
 
         fs::write(self.dest_dir.join(&self.output), content)?;
 
@@ -2287,7 +2287,7 @@ fn calculate_namespace(
 /// Orders of magnitude above any legitimate AIDL, well below the stack-
 /// overflow threshold of the recursive parser/walkers — see
 /// [`check_nesting_depth`].
-const MAX_NESTING_DEPTH: usize = 256;
+pub(crate) const MAX_NESTING_DEPTH: usize = 256;
 
 /// Maximum number of operator tokens accepted in a single statement/element
 /// (reset at `; , ( ) [ ] { }`). A const expression made of a long operator
@@ -2310,7 +2310,7 @@ const MAX_OPERATOR_RUN: usize = 1024;
 /// diagnostic. Angle-bracket depth is reset at `;` (a generic type never
 /// crosses a statement boundary) so shift/comparison operators in const
 /// expressions cannot drift the count into a false positive.
-fn check_nesting_depth(source: &str) -> Option<usize> {
+pub(crate) fn check_nesting_depth(source: &str) -> Option<usize> {
     let bytes = source.as_bytes();
     let mut i = 0;
     let mut bracket_depth: usize = 0; // () [] {}
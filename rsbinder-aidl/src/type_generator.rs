@@ -206,6 +206,11 @@ impl TypeGenerator {
             }
             "ParcelFileDescriptor" => ValueType::FileDescriptor,
             "ParcelableHolder" => ValueType::Holder,
+            "MQDescriptor" | "android.hardware.common.fmq.MQDescriptor"
+                if aidl_type.generic.is_some() =>
+            {
+                Self::queue_descriptor(aidl_type)?
+            }
             _ => ValueType::UserDefined(aidl_type.name.to_owned()),
         };
 
@@ -263,6 +268,46 @@ impl TypeGenerator {
         }
     }
 
+    /// `MQDescriptor<T, Flavor>` maps to the runtime crate's
+    /// `fmq::MQDescriptor`, so (as in AOSP, where libfmq owns the type) the
+    /// stable AIDL sources of `android.hardware.common.fmq` need not be
+    /// vendored. `T` may be any non-array type; `Flavor` must name one of
+    /// the two libfmq flavors.
+    fn queue_descriptor(aidl_type: &NonArrayType) -> Result<ValueType, AidlError> {
+        let args = match aidl_type.generic.as_deref() {
+            Some(parser::Generic::Type3 { type_args }) if type_args.len() == 2 => type_args,
+            _ => {
+                return Err(make_type_error(
+                    "MQDescriptor must have two type arguments: <T, Flavor>",
+                    aidl_type.name_span,
+                ))
+            }
+        };
+        let element = Self::new_with_type(&args[0])?;
+        if !element.array_types.is_empty() {
+            return Err(make_type_error(
+                "MQDescriptor element type cannot be an array",
+                aidl_type.name_span,
+            ));
+        }
+        let flavor = args[1].non_array_type.name.as_str();
+        let flavor = match flavor.strip_prefix("android.hardware.common.fmq.").unwrap_or(flavor) {
+            flavor @ ("SynchronizedReadWrite" | "UnsynchronizedWrite") => flavor.to_owned(),
+            _ => {
+                return Err(make_type_error(
+                    format!(
+                        "MQDescriptor flavor must be SynchronizedReadWrite or UnsynchronizedWrite, not {flavor}"
+                    ),
+                    aidl_type.name_span,
+                ))
+            }
+        };
+        Ok(ValueType::QueueDescriptor {
+            element: Box::new(element.value_type),
+            flavor,
+        })
+    }
+
     /// Verify that every user-defined type this generator references resolves
     /// to a known declaration in the current namespace context.
     ///
@@ -273,6 +318,10 @@ impl TypeGenerator {
     /// invoked while the owning declaration's `NamespaceGuard` is active.
     pub fn ensure_resolvable(&self) -> Result<(), AidlError> {
         let check = |value_type: &ValueType| -> Result<(), AidlError> {
+            let value_type = match value_type {
+                ValueType::QueueDescriptor { element, .. } => element,
+                _ => value_type,
+            };
             if let ValueType::UserDefined(name) = value_type {
                 // `lookup_decl_from_name` falls back to the *current* namespace's
                 // own declaration when nothing matches, so an undefined type does
@@ -305,7 +354,8 @@ impl TypeGenerator {
             ValueType::String(_)
             | ValueType::Array(_)
             | ValueType::FileDescriptor
-            | ValueType::IBinder => true,
+            | ValueType::IBinder
+            | ValueType::QueueDescriptor { .. } => true,
             ValueType::UserDefined(name) => {
                 match lookup_decl_from_name(name, crate::Namespace::AIDL) {
                     Some(lookup_decl) => !matches!(lookup_decl.decl, Declaration::Enum(_)),
@@ -377,6 +427,30 @@ impl TypeGenerator {
         false
     }
 
+    /// Element type of an `in` one-dimensional `T[]` whose wire layout is
+    /// its in-memory layout (`byte`, `int`, `long`, `float`, `double`), so
+    /// `on_transact` can borrow it from the transaction buffer instead of
+    /// copying it into a `Vec`.
+    pub fn borrowable_slice_element(&self) -> Option<String> {
+        if matches!(self.direction, Direction::Out | Direction::Inout) || !self.is_variable_array()
+        {
+            return None;
+        }
+        let info = self.array_types.first()?;
+        if info.sizes.len() != 1 {
+            return None;
+        }
+        matches!(
+            info.value_type,
+            ValueType::Byte(_)
+                | ValueType::Int32(_)
+                | ValueType::Int64(_)
+                | ValueType::Float(_)
+                | ValueType::Double(_)
+        )
+        .then(|| self.array_type_name(&info.value_type))
+    }
+
     // Check if this type can be initialized with Default::default().
     pub fn can_be_defaulted(value_type: &ValueType, is_struct: bool) -> bool {
         if is_struct {
@@ -385,7 +459,8 @@ impl TypeGenerator {
                     ValueType::String(_)
                     | ValueType::Array(_)
                     | ValueType::Map(_, _)
-                    | ValueType::Holder => true,
+                    | ValueType::Holder
+                    | ValueType::QueueDescriptor { .. } => true,
                     ValueType::UserDefined(name) => {
                         match lookup_decl_from_name(name, crate::Namespace::AIDL) {
                             // Strong<dyn IFoo> has no sensible Default, so struct
@@ -404,7 +479,8 @@ impl TypeGenerator {
                     ValueType::String(_)
                     | ValueType::Array(_)
                     | ValueType::Map(_, _)
-                    | ValueType::Holder => true,
+                    | ValueType::Holder
+                    | ValueType::QueueDescriptor { .. } => true,
                     ValueType::UserDefined(name) => {
                         match lookup_decl_from_name(name, crate::Namespace::AIDL) {
                             Some(lookup_decl) => matches!(
@@ -626,6 +702,11 @@ impl TypeGenerator {
             ValueType::FileDescriptor => format!("{}::ParcelFileDescriptor", crate_name()),
             ValueType::Holder => format!("{}::ParcelableHolder", crate_name()),
             ValueType::UserDefined(name) => self.make_user_defined_type_name(name),
+            ValueType::QueueDescriptor { element, flavor } => format!(
+                "{crate}::fmq::MQDescriptor<{}, {crate}::fmq::{flavor}>",
+                self.type_decl(element),
+                crate = crate_name()
+            ),
             _ => unreachable!(),
         }
     }
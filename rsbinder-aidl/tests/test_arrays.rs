@@ -84,7 +84,7 @@ pub mod ITestService {
             let mut data = self.binder.as_remote().ok_or(rsbinder::StatusCode::BadType)?.prepare_transact(true)?;
             data.write(_arg_input)?;
             data.write_slice_size(Some(_arg_repeated))?;
-            Ok(data)
+            rsbinder::trace::client_call(data, "ReverseBoolean")
         }
         fn read_response_ReverseBoolean(&self, _arg_input: &[bool], _arg_repeated: &mut Vec<bool>, _aidl_reply: rsbinder::Result<Option<rsbinder::Parcel>>) -> rsbinder::BinderResult<Vec<bool>> {
             if let Err(rsbinder::StatusCode::UnknownTransaction) = _aidl_reply {
@@ -102,7 +102,7 @@ pub mod ITestService {
         fn build_parcel_RepeatNullableIntArray(&self, _arg_input: Option<&[i32]>) -> rsbinder::Result<rsbinder::Parcel> {
             let mut data = self.binder.as_remote().ok_or(rsbinder::StatusCode::BadType)?.prepare_transact(true)?;
             data.write(&_arg_input)?;
-            Ok(data)
+            rsbinder::trace::client_call(data, "RepeatNullableIntArray")
         }
         fn read_response_RepeatNullableIntArray(&self, _arg_input: Option<&[i32]>, _aidl_reply: rsbinder::Result<Option<rsbinder::Parcel>>) -> rsbinder::BinderResult<Option<Vec<i32>>> {
             if let Err(rsbinder::StatusCode::UnknownTransaction) = _aidl_reply {
@@ -119,7 +119,7 @@ pub mod ITestService {
         fn build_parcel_FillOutStructuredParcelable(&self, _arg_parcel: &mut super::StructuredParcelable::StructuredParcelable) -> rsbinder::Result<rsbinder::Parcel> {
             let mut data = self.binder.as_remote().ok_or(rsbinder::StatusCode::BadType)?.prepare_transact(true)?;
             data.write(_arg_parcel)?;
-            Ok(data)
+            rsbinder::trace::client_call(data, "FillOutStructuredParcelable")
         }
         fn read_response_FillOutStructuredParcelable(&self, _arg_parcel: &mut super::StructuredParcelable::StructuredParcelable, _aidl_reply: rsbinder::Result<Option<rsbinder::Parcel>>) -> rsbinder::BinderResult<()> {
             if let Err(rsbinder::StatusCode::UnknownTransaction) = _aidl_reply {
@@ -166,6 +166,7 @@ pub mod ITestService {
         _service: &dyn ITestService, _code: rsbinder::TransactionCode, _reader: &mut rsbinder::Parcel, _reply: &mut rsbinder::Parcel) -> rsbinder::Result<()> {
         match _code {
             transactions::r#ReverseBoolean => {
+                rsbinder::trace::server_method("ReverseBoolean");
                 let _arg_input: Vec<bool> = _reader.read()?;
                 let mut _arg_repeated: Vec<bool> = Default::default();
                 _reader.resize_out_vec(&mut _arg_repeated)?;
@@ -183,7 +184,9 @@ pub mod ITestService {
                 Ok(())
             }
             transactions::r#RepeatNullableIntArray => {
-                let _arg_input: Option<Vec<i32>> = _reader.read()?;
+                rsbinder::trace::server_method("RepeatNullableIntArray");
+                let _arg_input_pos = _reader.skip_slice::<i32>()?;
+                let _arg_input = _reader.borrowed_reader(_arg_input_pos).read_slice_ref::<i32>()?;
                 let _aidl_return = _service.r#RepeatNullableIntArray(_arg_input.as_deref());
                 match &_aidl_return {
                     Ok(_aidl_return) => {
@@ -197,6 +200,7 @@ pub mod ITestService {
                 Ok(())
             }
             transactions::r#FillOutStructuredParcelable => {
+                rsbinder::trace::server_method("FillOutStructuredParcelable");
                 let mut _arg_parcel: super::StructuredParcelable::StructuredParcelable = _reader.read()?;
                 let _aidl_return = _service.r#FillOutStructuredParcelable(&mut _arg_parcel);
                 match &_aidl_return {
@@ -364,7 +368,7 @@ pub mod FixedSizeArrayExample {
             fn build_parcel_Repeat2dParcelables(&self, _arg_input: &[[super::IntParcelable::IntParcelable; 3]; 2], _arg_repeated: &mut [[super::IntParcelable::IntParcelable; 3]; 2]) -> rsbinder::Result<rsbinder::Parcel> {
                 let mut data = self.binder.as_remote().ok_or(rsbinder::StatusCode::BadType)?.prepare_transact(true)?;
                 data.write(_arg_input)?;
-                Ok(data)
+                rsbinder::trace::client_call(data, "Repeat2dParcelables")
             }
             fn read_response_Repeat2dParcelables(&self, _arg_input: &[[super::IntParcelable::IntParcelable; 3]; 2], _arg_repeated: &mut [[super::IntParcelable::IntParcelable; 3]; 2], _aidl_reply: rsbinder::Result<Option<rsbinder::Parcel>>) -> rsbinder::BinderResult<[[super::IntParcelable::IntParcelable; 3]; 2]> {
                 if let Err(rsbinder::StatusCode::UnknownTransaction) = _aidl_reply {
@@ -396,6 +400,7 @@ pub mod FixedSizeArrayExample {
             _service: &dyn IRepeatFixedSizeArray, _code: rsbinder::TransactionCode, _reader: &mut rsbinder::Parcel, _reply: &mut rsbinder::Parcel) -> rsbinder::Result<()> {
             match _code {
                 transactions::r#Repeat2dParcelables => {
+                    rsbinder::trace::server_method("Repeat2dParcelables");
                     let _arg_input: [[super::IntParcelable::IntParcelable; 3]; 2] = _reader.read()?;
                     let mut _arg_repeated: [[super::IntParcelable::IntParcelable; 3]; 2] = Default::default();
                     let _aidl_return = _service.r#Repeat2dParcelables(&_arg_input, &mut _arg_repeated);
@@ -500,3 +505,31 @@ pub mod FixedSizeArrayExample {
         "##,
     )
 }
+
+#[test]
+fn test_in_string_reaches_service_as_str() -> Result<(), Box<dyn Error>> {
+    let input = r##"
+        package android.aidl.test;
+        interface IBlob {
+            void put(in String key, in @nullable String note, in byte[] data);
+        }
+    "##;
+    let ctx = rsbinder_aidl::SourceContext::new("test.aidl", input);
+    let document = rsbinder_aidl::parse_document(&ctx)?;
+    let gen = rsbinder_aidl::Generator::new(false, false);
+    let res = gen.document(&document)?;
+
+    // The service gets `&str` views; AIDL strings are UTF-16 on the wire,
+    // so `on_transact` decodes each one once and lends it out.
+    assert!(res.1.contains(
+        "fn r#put(&self, _arg_key: &str, _arg_note: Option<&str>, _arg_data: &[u8]) -> rsbinder::BinderResult<()>;"
+    ));
+    assert!(res.1.contains("let _arg_key: String = _reader.read()?;"));
+    assert!(res
+        .1
+        .contains("let _arg_note: Option<String> = _reader.read()?;"));
+    assert!(res
+        .1
+        .contains("_service.r#put(_arg_key.as_str(), _arg_note.as_deref(), &_arg_data);"));
+    Ok(())
+}
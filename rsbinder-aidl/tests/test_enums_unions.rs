@@ -395,7 +395,7 @@ pub mod ITestService {
         fn build_parcel_RepeatByteEnum(&self, _arg_token: super::ByteEnum::ByteEnum) -> rsbinder::Result<rsbinder::Parcel> {
             let mut data = self.binder.as_remote().ok_or(rsbinder::StatusCode::BadType)?.prepare_transact(true)?;
             data.write(&_arg_token)?;
-            Ok(data)
+            rsbinder::trace::client_call(data, "RepeatByteEnum")
         }
         fn read_response_RepeatByteEnum(&self, _arg_token: super::ByteEnum::ByteEnum, _aidl_reply: rsbinder::Result<Option<rsbinder::Parcel>>) -> rsbinder::BinderResult<super::ByteEnum::ByteEnum> {
             if let Err(rsbinder::StatusCode::UnknownTransaction) = _aidl_reply {
@@ -426,6 +426,7 @@ pub mod ITestService {
         _service: &dyn ITestService, _code: rsbinder::TransactionCode, _reader: &mut rsbinder::Parcel, _reply: &mut rsbinder::Parcel) -> rsbinder::Result<()> {
         match _code {
             transactions::r#RepeatByteEnum => {
+                rsbinder::trace::server_method("RepeatByteEnum");
                 let _arg_token: super::ByteEnum::ByteEnum = _reader.read()?;
                 let _aidl_return = _service.r#RepeatByteEnum(_arg_token);
                 match &_aidl_return {
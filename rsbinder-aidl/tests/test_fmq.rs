@@ -0,0 +1,57 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `android.hardware.common.fmq.MQDescriptor<T, Flavor>` maps to the
+//! runtime's `rsbinder::fmq::MQDescriptor` instead of a generated type.
+
+fn generate(input: &str) -> Result<String, rsbinder_aidl::AidlError> {
+    let ctx = rsbinder_aidl::SourceContext::new("test.aidl", input);
+    let document = rsbinder_aidl::parse_document(&ctx)?;
+    let gen = rsbinder_aidl::Generator::new(false, false);
+    Ok(gen.document(&document)?.1)
+}
+
+#[test]
+fn mq_descriptor_maps_to_the_runtime_type() {
+    let out = generate(
+        r#"
+package test.fmq;
+import android.hardware.common.fmq.MQDescriptor;
+import android.hardware.common.fmq.SynchronizedReadWrite;
+import android.hardware.common.fmq.UnsynchronizedWrite;
+
+interface IQueues {
+    MQDescriptor<int, SynchronizedReadWrite> samples();
+    void events(in MQDescriptor<byte, UnsynchronizedWrite> queue,
+                out MQDescriptor<long, android.hardware.common.fmq.SynchronizedReadWrite> reply);
+}
+
+parcelable Config {
+    MQDescriptor<float, SynchronizedReadWrite> queue;
+    @nullable MQDescriptor<int, UnsynchronizedWrite> spare;
+}
+"#,
+    )
+    .expect("must generate");
+    let packed = out.replace([' ', '\n'], "");
+    for expected in [
+        "->rsbinder::BinderResult<rsbinder::fmq::MQDescriptor<i32,rsbinder::fmq::SynchronizedReadWrite>>",
+        "_arg_queue:&rsbinder::fmq::MQDescriptor<i8,rsbinder::fmq::UnsynchronizedWrite>",
+        "_arg_reply:&mutrsbinder::fmq::MQDescriptor<i64,rsbinder::fmq::SynchronizedReadWrite>",
+        "pubr#queue:rsbinder::fmq::MQDescriptor<f32,rsbinder::fmq::SynchronizedReadWrite>",
+        "pubr#spare:Option<rsbinder::fmq::MQDescriptor<i32,rsbinder::fmq::UnsynchronizedWrite>>",
+    ] {
+        assert!(packed.contains(expected), "missing {expected} in: {out}");
+    }
+}
+
+#[test]
+fn mq_descriptor_requires_a_known_flavor() {
+    for src in [
+        "parcelable P { MQDescriptor<int, Whatever> q; }",
+        "parcelable P { MQDescriptor<int> q; }",
+        "parcelable P { MQDescriptor<int[], SynchronizedReadWrite> q; }",
+    ] {
+        assert!(generate(src).is_err(), "expected an error for {src:?}");
+    }
+}
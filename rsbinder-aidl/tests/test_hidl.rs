@@ -0,0 +1,204 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `hidl::Builder` end to end: `.hal` packages laid out as in AOSP are
+//! generated into a temp dir and the output is fed to `syn::parse_file`,
+//! plus the errors for unsupported or unresolvable input.
+
+use rsbinder_aidl::hidl::Builder;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn write_hal(root: &Path, relative: &str, contents: &str) {
+    let path = root.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+fn generate(root: &Path, source: &str) -> Result<String, String> {
+    let output = root.join("out.rs");
+    Builder::new()
+        .source(root.join(source))
+        .output(&output)
+        .generate()
+        .map_err(|err| format!("{:?}", miette::Report::new(err)))?;
+    let code = fs::read_to_string(output).unwrap();
+    syn::parse_file(&code).unwrap_or_else(|err| panic!("invalid Rust: {err}\n{code}"));
+    Ok(code)
+}
+
+const TYPES_HAL: &str = r#"
+package vendor.foo@1.0;
+
+enum Mode : uint32_t {
+    OFF,
+    ON = 1 << 4,
+    AUTO,
+};
+
+struct Config {
+    string name;
+    vec<int32_t> values;
+    Mode mode;
+    uint8_t[4] tag;
+    handle fence;
+};
+"#;
+
+const IFOO_HAL: &str = r#"
+package vendor.foo@1.0;
+
+interface IFoo {
+    struct Point { int32_t x; int32_t y; };
+
+    configure(Config config) generates (bool ok, Mode mode);
+    move(Point p);
+    oneway notify(vec<string> events);
+    callback(IFoo peer, interface any);
+};
+"#;
+
+#[test]
+fn generates_package() {
+    let tmp = TempDir::new().unwrap();
+    write_hal(tmp.path(), "vendor/foo/1.0/types.hal", TYPES_HAL);
+    write_hal(tmp.path(), "vendor/foo/1.0/IFoo.hal", IFOO_HAL);
+
+    let code = generate(tmp.path(), "vendor/foo/1.0/IFoo.hal").unwrap();
+    assert!(code.contains("pub mod V1_0"), "{code}");
+    assert!(code.contains("pub trait IFoo"), "{code}");
+    assert!(code.contains("BnHwFoo"), "{code}");
+    assert!(code.contains("BpHwFoo"), "{code}");
+    assert!(code.contains("\"vendor.foo@1.0::IFoo\""), "{code}");
+    // types.hal is loaded with the rest of the package.
+    assert!(code.contains("pub struct Config"), "{code}");
+    assert!(code.contains("declare_hidl_enum!"), "{code}");
+    assert!(code.contains("AUTO = 17"), "{code}");
+}
+
+#[test]
+fn extends_across_versions() {
+    let tmp = TempDir::new().unwrap();
+    write_hal(tmp.path(), "vendor/foo/1.0/types.hal", TYPES_HAL);
+    write_hal(tmp.path(), "vendor/foo/1.0/IFoo.hal", IFOO_HAL);
+    write_hal(
+        tmp.path(),
+        "vendor/foo/1.1/IFoo.hal",
+        r#"
+package vendor.foo@1.1;
+
+import @1.0::IFoo;
+import @1.0::Mode;
+
+interface IFoo extends @1.0::IFoo {
+    reset(Mode mode) generates (int64_t when);
+};
+"#,
+    );
+
+    let code = generate(tmp.path(), "vendor/foo/1.1").unwrap();
+    assert!(code.contains("pub mod V1_1"), "{code}");
+    let chain: String = code
+        .split("pub const INTERFACE_CHAIN")
+        .nth(2)
+        .and_then(|rest| rest.split("];").next())
+        .unwrap()
+        .split_whitespace()
+        .collect();
+    assert_eq!(
+        chain,
+        ":&[&str]=&[\"vendor.foo@1.1::IFoo\",\"vendor.foo@1.0::IFoo\",\"android.hidl.base@1.0::IBase\","
+    );
+}
+
+#[test]
+fn missing_import_is_reported() {
+    let tmp = TempDir::new().unwrap();
+    write_hal(
+        tmp.path(),
+        "vendor/foo/1.0/IFoo.hal",
+        r#"
+package vendor.foo@1.0;
+
+import vendor.bar@2.0::IBar;
+
+interface IFoo {
+    get() generates (IBar bar);
+};
+"#,
+    );
+
+    let err = generate(tmp.path(), "vendor/foo/1.0").unwrap_err();
+    assert!(err.contains("vendor.bar@2.0"), "{err}");
+}
+
+#[test]
+fn unions_are_rejected() {
+    let tmp = TempDir::new().unwrap();
+    write_hal(
+        tmp.path(),
+        "vendor/foo/1.0/types.hal",
+        r#"
+package vendor.foo@1.0;
+
+safe_union Value {
+    int32_t i;
+    string s;
+};
+"#,
+    );
+
+    let err = generate(tmp.path(), "vendor/foo/1.0").unwrap_err();
+    assert!(err.contains("not supported"), "{err}");
+}
+
+#[test]
+fn enum_value_out_of_range() {
+    let tmp = TempDir::new().unwrap();
+    write_hal(
+        tmp.path(),
+        "vendor/foo/1.0/types.hal",
+        r#"
+package vendor.foo@1.0;
+
+enum Small : uint8_t {
+    A = 255,
+    B,
+};
+"#,
+    );
+
+    assert!(generate(tmp.path(), "vendor/foo/1.0").is_err());
+}
+
+#[test]
+fn unknown_type_is_reported() {
+    let tmp = TempDir::new().unwrap();
+    write_hal(
+        tmp.path(),
+        "vendor/foo/1.0/types.hal",
+        r#"
+package vendor.foo@1.0;
+
+struct S {
+    Missing m;
+};
+"#,
+    );
+
+    let err = generate(tmp.path(), "vendor/foo/1.0").unwrap_err();
+    assert!(err.contains("Missing"), "{err}");
+}
+
+#[test]
+fn syntax_error_is_reported() {
+    let tmp = TempDir::new().unwrap();
+    write_hal(
+        tmp.path(),
+        "vendor/foo/1.0/types.hal",
+        "package vendor.foo@1.0;\n\nstruct S { int32_t x }\n",
+    );
+
+    assert!(generate(tmp.path(), "vendor/foo/1.0").is_err());
+}
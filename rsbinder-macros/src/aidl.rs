@@ -0,0 +1,193 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rendering `.aidl` declarations equivalent to the macro input, so
+//! Java/C++ peers can generate matching stubs.
+
+use std::path::PathBuf;
+
+use syn::spanned::Spanned;
+use syn::{GenericArgument, LitStr, PathArguments, Type};
+
+/// The AIDL spelling of `ty`, e.g. `@nullable String` for `Option<&str>`.
+pub(crate) fn type_name(ty: &Type) -> syn::Result<String> {
+    match ty {
+        Type::Reference(r) => type_name(&r.elem),
+        Type::Paren(p) => type_name(&p.elem),
+        Type::Group(g) => type_name(&g.elem),
+        Type::Slice(s) => array_of(&s.elem),
+        Type::Array(a) => array_of(&a.elem),
+        Type::TraitObject(t) => match t.bounds.first() {
+            Some(syn::TypeParamBound::Trait(bound)) => last_ident(&bound.path, ty),
+            _ => Err(unsupported(ty)),
+        },
+        Type::Path(p) if p.qself.is_none() => {
+            let segment = p.path.segments.last().ok_or_else(|| unsupported(ty))?;
+            let name = match segment.ident.to_string().as_str() {
+                "bool" => "boolean".into(),
+                "i8" | "u8" => "byte".into(),
+                "u16" => "char".into(),
+                "i32" => "int".into(),
+                "i64" => "long".into(),
+                "f32" => "float".into(),
+                "f64" => "double".into(),
+                "str" | "String" => "String".into(),
+                "SIBinder" => "IBinder".into(),
+                "Vec" => array_of(generic_arg(segment, ty)?)?,
+                "Box" | "Strong" => type_name(generic_arg(segment, ty)?)?,
+                "Option" => {
+                    let inner = type_name(generic_arg(segment, ty)?)?;
+                    if inner.starts_with('@') {
+                        return Err(unsupported(ty));
+                    }
+                    format!("@nullable {inner}")
+                }
+                "i16" | "u32" | "u64" | "i128" | "u128" | "isize" | "usize" | "char" => {
+                    return Err(unsupported(ty))
+                }
+                _ if matches!(segment.arguments, PathArguments::None) => segment.ident.to_string(),
+                _ => return Err(unsupported(ty)),
+            };
+            Ok(name)
+        }
+        _ => Err(unsupported(ty)),
+    }
+}
+
+/// Whether an argument of this AIDL type needs an explicit `in` direction.
+pub(crate) fn needs_direction(aidl_type: &str) -> bool {
+    let base = aidl_type.trim_start_matches("@nullable ");
+    base.ends_with("[]")
+        || !matches!(
+            base,
+            "boolean"
+                | "byte"
+                | "char"
+                | "int"
+                | "long"
+                | "float"
+                | "double"
+                | "String"
+                | "IBinder"
+        ) && !is_interface_name(base)
+}
+
+// AIDL interfaces are conventionally `IFoo`; they take no direction.
+fn is_interface_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next() == Some('I') && chars.next().is_some_and(|c| c.is_ascii_uppercase())
+}
+
+fn array_of(elem: &Type) -> syn::Result<String> {
+    let elem_name = type_name(elem)?;
+    if elem_name.starts_with('@') || elem_name.ends_with("[]") {
+        return Err(unsupported(elem));
+    }
+    Ok(format!("{elem_name}[]"))
+}
+
+fn generic_arg<'a>(segment: &'a syn::PathSegment, ty: &Type) -> syn::Result<&'a Type> {
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(inner)) => Ok(inner),
+            _ => Err(unsupported(ty)),
+        },
+        _ => Err(unsupported(ty)),
+    }
+}
+
+fn last_ident(path: &syn::Path, ty: &Type) -> syn::Result<String> {
+    path.segments
+        .last()
+        .map(|segment| segment.ident.to_string())
+        .ok_or_else(|| unsupported(ty))
+}
+
+fn unsupported(ty: &impl Spanned) -> syn::Error {
+    syn::Error::new(ty.span(), "this type has no AIDL equivalent")
+}
+
+/// Split `pkg.name.IFoo` into its package (if any) and simple name.
+pub(crate) fn split_descriptor(descriptor: &str) -> (Option<&str>, &str) {
+    match descriptor.rsplit_once('.') {
+        Some((package, name)) => (Some(package), name),
+        None => (None, descriptor),
+    }
+}
+
+/// The file header and `package` line shared by every declaration.
+pub(crate) fn header(package: Option<&str>, source: &str) -> String {
+    let mut out = format!("// Generated by rsbinder-macros from `{source}`. Do not edit.\n\n");
+    if let Some(package) = package {
+        out.push_str(&format!("package {package};\n\n"));
+    }
+    out
+}
+
+/// Write `contents` to `path`, relative to the crate being compiled. The
+/// file is left untouched when it is already current.
+pub(crate) fn write(path: &LitStr, contents: &str) -> syn::Result<()> {
+    let mut full = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+    full.push(path.value());
+
+    if std::fs::read_to_string(&full).is_ok_and(|current| current == contents) {
+        return Ok(());
+    }
+    let result = match full.parent() {
+        Some(dir) => std::fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+    .and_then(|_| std::fs::write(&full, contents));
+    result.map_err(|err| {
+        syn::Error::new(
+            path.span(),
+            format!("failed to write {}: {err}", full.display()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(ty: Type) -> String {
+        type_name(&ty).unwrap()
+    }
+
+    #[test]
+    fn test_type_names() {
+        assert_eq!(name(syn::parse_quote!(i32)), "int");
+        assert_eq!(name(syn::parse_quote!(&str)), "String");
+        assert_eq!(name(syn::parse_quote!(Vec<u8>)), "byte[]");
+        assert_eq!(name(syn::parse_quote!(&[String])), "String[]");
+        assert_eq!(name(syn::parse_quote!(Option<&str>)), "@nullable String");
+        assert_eq!(name(syn::parse_quote!(rsbinder::SIBinder)), "IBinder");
+        assert_eq!(
+            name(syn::parse_quote!(rsbinder::Strong<dyn IListener>)),
+            "IListener"
+        );
+        assert_eq!(name(syn::parse_quote!(&Point)), "Point");
+        assert!(type_name(&syn::parse_quote!(u32)).is_err());
+        assert!(type_name(&syn::parse_quote!(Vec<Vec<i32>>)).is_err());
+        assert!(type_name(&syn::parse_quote!(Option<Option<i32>>)).is_err());
+    }
+
+    #[test]
+    fn test_directions() {
+        assert!(!needs_direction("int"));
+        assert!(!needs_direction("@nullable String"));
+        assert!(!needs_direction("IListener"));
+        assert!(needs_direction("int[]"));
+        assert!(needs_direction("Point"));
+        assert!(needs_direction("ParcelFileDescriptor"));
+    }
+
+    #[test]
+    fn test_split_descriptor() {
+        assert_eq!(
+            split_descriptor("com.example.IFoo"),
+            (Some("com.example"), "IFoo")
+        );
+        assert_eq!(split_descriptor("IFoo"), (None, "IFoo"));
+    }
+}
@@ -0,0 +1,937 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `#[binder_interface]`: the AIDL generator's interface template, driven
+//! by a Rust trait.
+
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::visit_mut::VisitMut;
+use syn::{
+    FnArg, GenericArgument, Ident, ItemTrait, LitStr, Pat, PathArguments, ReturnType, TraitItem,
+    TraitItemFn, Type,
+};
+
+use crate::aidl;
+
+/// Highest code offset a method may use; the meta transactions
+/// (`getInterfaceVersion`, `getInterfaceHash`) sit just above it.
+const MAX_CODE_OFFSET: u32 = 16777212;
+
+#[derive(Default)]
+pub(crate) struct InterfaceArgs {
+    descriptor: Option<LitStr>,
+    vintf: bool,
+    aidl: Option<LitStr>,
+}
+
+impl InterfaceArgs {
+    pub(crate) fn parse(&mut self, meta: syn::meta::ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("descriptor") {
+            self.descriptor = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("vintf") {
+            self.vintf = true;
+        } else if meta.path.is_ident("aidl") {
+            self.aidl = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `descriptor`, `vintf` or `aidl`"));
+        }
+        Ok(())
+    }
+}
+
+/// How `on_transact` hands a deserialized argument to the service.
+enum Pass {
+    Value,
+    Ref,
+    AsDeref,
+    AsRef,
+}
+
+struct Arg {
+    ident: Ident,
+    ty: Type,
+    owned: Type,
+    pass: Pass,
+}
+
+struct Method {
+    ident: Ident,
+    args: Vec<Arg>,
+    // `None` for `BinderResult<()>`.
+    ret: Option<Type>,
+    oneway: bool,
+    code: u32,
+}
+
+pub(crate) fn expand(args: InterfaceArgs, mut item: ItemTrait) -> syn::Result<TokenStream> {
+    if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "generic binder interfaces are not supported",
+        ));
+    }
+
+    let name = item.ident.clone();
+    let descriptor = args
+        .descriptor
+        .as_ref()
+        .map(LitStr::value)
+        .unwrap_or_else(|| name.to_string());
+
+    let mut methods = Vec::new();
+    let mut explicit = Vec::new();
+    for trait_item in &mut item.items {
+        match trait_item {
+            TraitItem::Fn(func) => {
+                let (method, explicit_code) = parse_method(func)?;
+                explicit.push(explicit_code);
+                methods.push(method);
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "binder interfaces may only contain methods",
+                ))
+            }
+        }
+    }
+    assign_codes(&item, &mut methods, &explicit)?;
+
+    if let Some(path) = &args.aidl {
+        aidl::write(path, &render_aidl(&name, &descriptor, &args, &methods)?)?;
+    }
+
+    item.supertraits
+        .push(syn::parse_quote!(::rsbinder::Interface));
+    item.supertraits.push(syn::parse_quote!(Send));
+    item.items.insert(
+        0,
+        syn::parse_quote! {
+            fn descriptor() -> &'static str where Self: Sized { #descriptor }
+        },
+    );
+
+    let base = name.to_string();
+    let base = base
+        .strip_prefix('I')
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(&base);
+    let bn = format_ident!("Bn{}", base);
+    let bp = format_ident!("Bp{}", base);
+    let stability = if args.vintf {
+        quote!(::rsbinder::Stability::Vintf)
+    } else {
+        quote!(::rsbinder::Stability::default())
+    };
+
+    let async_parts = cfg!(feature = "async").then(|| AsyncNames::new(&name, &bn));
+    let declare = match &async_parts {
+        Some(AsyncNames {
+            adapter,
+            client,
+            service,
+        }) => quote! {
+            ::rsbinder::declare_binder_interface! {
+                #name[#descriptor] {
+                    native: {
+                        #bn(#bn::__on_transact),
+                        adapter: #adapter,
+                        r#async: #service,
+                    },
+                    proxy: #bp,
+                    r#async: #client,
+                    stability: #stability,
+                }
+            }
+        },
+        None => quote! {
+            ::rsbinder::declare_binder_interface! {
+                #name[#descriptor] {
+                    native: {
+                        #bn(#bn::__on_transact),
+                    },
+                    proxy: #bp,
+                    stability: #stability,
+                }
+            }
+        },
+    };
+
+    let codes = methods.iter().map(|m| {
+        let constant = transaction_const(&m.ident);
+        let code = m.code;
+        quote! {
+            #[allow(non_upper_case_globals)]
+            pub const #constant: ::rsbinder::TransactionCode = ::rsbinder::FIRST_CALL_TRANSACTION + #code;
+        }
+    });
+    let dispatch = methods.iter().map(|m| dispatch_arm(&bn, m));
+    let proxy_helpers = methods.iter().map(proxy_helpers);
+    let proxy_methods = methods.iter().map(|m| {
+        let sig = sync_sig(m);
+        let (build, read) = helper_names(&m.ident);
+        let constant = transaction_const(&m.ident);
+        let call_args = call_args(m);
+        let flags = flags(m);
+        quote! {
+            #sig {
+                let _aidl_data = self.#build(#(#call_args),*)?;
+                let _aidl_reply = self.binder.as_remote().ok_or(::rsbinder::StatusCode::BadType)?.submit_transact(#bn::#constant, &_aidl_data, #flags);
+                self.#read(_aidl_reply)
+            }
+        }
+    });
+    let native_target = if async_parts.is_some() {
+        quote!(self.0.as_sync())
+    } else {
+        quote!(self.0)
+    };
+    let native_methods = methods.iter().map(|m| {
+        let sig = sync_sig(m);
+        let ident = &m.ident;
+        let call_args = call_args(m);
+        quote! {
+            #sig {
+                #native_target.#ident(#(#call_args),*)
+            }
+        }
+    });
+    let async_items = async_parts
+        .as_ref()
+        .map(|names| expand_async(&name, &descriptor, &bn, &bp, &stability, names, &methods));
+
+    Ok(quote! {
+        #item
+        #async_items
+        #declare
+        impl #bn {
+            #(#codes)*
+
+            #[doc(hidden)]
+            fn __on_transact(
+                _service: &dyn #name,
+                _code: ::rsbinder::TransactionCode,
+                _reader: &mut ::rsbinder::Parcel,
+                _reply: &mut ::rsbinder::Parcel,
+            ) -> ::rsbinder::Result<()> {
+                match _code {
+                    #(#dispatch)*
+                    _ => Err(::rsbinder::StatusCode::UnknownTransaction),
+                }
+            }
+        }
+        impl #bp {
+            #(#proxy_helpers)*
+        }
+        impl #name for #bp {
+            #(#proxy_methods)*
+        }
+        impl #name for ::rsbinder::Binder<#bn> {
+            #(#native_methods)*
+        }
+    })
+}
+
+fn parse_method(func: &mut TraitItemFn) -> syn::Result<(Method, Option<u32>)> {
+    let mut oneway = false;
+    let mut code = None;
+    let mut result = Ok(());
+    func.attrs.retain(|attr| {
+        if !attr.path().is_ident("binder") {
+            return true;
+        }
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("oneway") {
+                oneway = true;
+            } else if meta.path.is_ident("code") {
+                let lit: syn::LitInt = meta.value()?.parse()?;
+                let value: u32 = lit.base10_parse()?;
+                if value > MAX_CODE_OFFSET {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!("transaction code offsets must be at most {MAX_CODE_OFFSET}"),
+                    ));
+                }
+                code = Some(value);
+            } else {
+                return Err(meta.error("expected `oneway` or `code = N`"));
+            }
+            Ok(())
+        });
+        if let Err(err) = parsed {
+            result = Err(err);
+        }
+        false
+    });
+    result?;
+
+    let sig = &func.sig;
+    if !sig.generics.params.is_empty() || sig.asyncness.is_some() {
+        return Err(syn::Error::new_spanned(
+            sig,
+            "binder methods must be plain, non-generic, non-async functions",
+        ));
+    }
+
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(recv)) if recv.reference.is_some() && recv.mutability.is_none() => {}
+        _ => {
+            return Err(syn::Error::new_spanned(
+                sig,
+                "binder methods must take `&self`",
+            ))
+        }
+    }
+
+    let mut args = Vec::new();
+    for input in inputs {
+        let FnArg::Typed(pat_type) = input else {
+            unreachable!("receiver handled above")
+        };
+        let Pat::Ident(pat) = &*pat_type.pat else {
+            return Err(syn::Error::new_spanned(
+                &pat_type.pat,
+                "binder method arguments must be plain identifiers",
+            ));
+        };
+        let (owned, pass) = owned_type(&pat_type.ty)?;
+        args.push(Arg {
+            ident: pat.ident.clone(),
+            ty: (*pat_type.ty).clone(),
+            owned,
+            pass,
+        });
+    }
+
+    let ret = return_type(&sig.output)?;
+    if oneway && ret.is_some() {
+        return Err(syn::Error::new_spanned(
+            &sig.output,
+            "oneway methods must return `BinderResult<()>`",
+        ));
+    }
+
+    Ok((
+        Method {
+            ident: sig.ident.clone(),
+            args,
+            ret,
+            oneway,
+            code: 0,
+        },
+        code,
+    ))
+}
+
+// AIDL numbering: explicit codes as given, the rest by declaration order.
+fn assign_codes(
+    item: &ItemTrait,
+    methods: &mut [Method],
+    explicit: &[Option<u32>],
+) -> syn::Result<()> {
+    let mut counter = 0;
+    let mut seen = HashMap::new();
+    for (method, code) in methods.iter_mut().zip(explicit) {
+        method.code = match code {
+            Some(code) => *code,
+            None => {
+                counter += 1;
+                counter - 1
+            }
+        };
+        if let Some(other) = seen.insert(method.code, method.ident.clone()) {
+            return Err(syn::Error::new_spanned(
+                &item.ident,
+                format!(
+                    "`{}` and `{}` share transaction code offset {}",
+                    other, method.ident, method.code
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn return_type(output: &ReturnType) -> syn::Result<Option<Type>> {
+    let err = || {
+        syn::Error::new_spanned(
+            output,
+            "binder methods must return `rsbinder::BinderResult<T>`",
+        )
+    };
+    let ReturnType::Type(_, ty) = output else {
+        return Err(err());
+    };
+    let Type::Path(path) = &**ty else {
+        return Err(err());
+    };
+    let segment = path.path.segments.last().ok_or_else(err)?;
+    if segment.ident != "BinderResult" {
+        return Err(err());
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return Err(err());
+    };
+    match args.args.first() {
+        Some(GenericArgument::Type(Type::Tuple(unit))) if unit.elems.is_empty() => Ok(None),
+        Some(GenericArgument::Type(inner)) => Ok(Some(inner.clone())),
+        _ => Err(err()),
+    }
+}
+
+// The type `on_transact` reads for an argument, and how it is passed on.
+fn owned_type(ty: &Type) -> syn::Result<(Type, Pass)> {
+    match ty {
+        Type::Reference(r) if r.mutability.is_some() => Err(syn::Error::new_spanned(
+            ty,
+            "binder arguments are `in` only; `&mut` is not supported",
+        )),
+        Type::Reference(r) => Ok((borrowed_owner(&r.elem), Pass::Ref)),
+        Type::Path(path) => {
+            let segment = path.path.segments.last();
+            let inner = segment.and_then(|segment| match &segment.arguments {
+                PathArguments::AngleBracketed(args) if segment.ident == "Option" => {
+                    match args.args.first() {
+                        Some(GenericArgument::Type(Type::Reference(r))) => Some(r),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            });
+            match inner {
+                Some(r) if r.mutability.is_some() => Err(syn::Error::new_spanned(
+                    ty,
+                    "binder arguments are `in` only; `&mut` is not supported",
+                )),
+                Some(r) => {
+                    let owner = borrowed_owner(&r.elem);
+                    let pass = if matches!(&*r.elem, Type::Slice(_)) || is_str(&r.elem) {
+                        Pass::AsDeref
+                    } else {
+                        Pass::AsRef
+                    };
+                    Ok((syn::parse_quote!(Option<#owner>), pass))
+                }
+                None => Ok((ty.clone(), Pass::Value)),
+            }
+        }
+        _ => Ok((ty.clone(), Pass::Value)),
+    }
+}
+
+fn is_str(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("str"))
+}
+
+fn borrowed_owner(elem: &Type) -> Type {
+    match elem {
+        Type::Slice(slice) => {
+            let elem = &slice.elem;
+            syn::parse_quote!(Vec<#elem>)
+        }
+        _ if is_str(elem) => syn::parse_quote!(String),
+        _ => elem.clone(),
+    }
+}
+
+fn transaction_const(ident: &Ident) -> Ident {
+    format_ident!("TRANSACTION_{}", ident.unraw())
+}
+
+fn helper_names(ident: &Ident) -> (Ident, Ident) {
+    let ident = ident.unraw();
+    (
+        format_ident!("__build_parcel_{}", ident),
+        format_ident!("__read_response_{}", ident),
+    )
+}
+
+fn call_args(m: &Method) -> Vec<&Ident> {
+    m.args.iter().map(|arg| &arg.ident).collect()
+}
+
+fn flags(m: &Method) -> TokenStream {
+    if m.oneway {
+        quote!(
+            ::rsbinder::FLAG_ONEWAY | ::rsbinder::FLAG_CLEAR_BUF | ::rsbinder::FLAG_PRIVATE_LOCAL
+        )
+    } else {
+        quote!(::rsbinder::FLAG_CLEAR_BUF | ::rsbinder::FLAG_PRIVATE_LOCAL)
+    }
+}
+
+fn return_tokens(m: &Method) -> TokenStream {
+    match &m.ret {
+        Some(ty) => quote!(#ty),
+        None => quote!(()),
+    }
+}
+
+fn sync_sig(m: &Method) -> TokenStream {
+    let ident = &m.ident;
+    let params = m.args.iter().map(|arg| {
+        let (ident, ty) = (&arg.ident, &arg.ty);
+        quote!(#ident: #ty)
+    });
+    let ret = return_tokens(m);
+    quote! {
+        fn #ident(&self, #(#params),*) -> ::rsbinder::BinderResult<#ret>
+    }
+}
+
+fn dispatch_arm(bn: &Ident, m: &Method) -> TokenStream {
+    let ident = &m.ident;
+    let constant = transaction_const(ident);
+    let locals: Vec<_> = m
+        .args
+        .iter()
+        .map(|arg| format_ident!("_arg_{}", arg.ident.unraw()))
+        .collect();
+    let owned = m.args.iter().map(|arg| &arg.owned);
+    let passed = m
+        .args
+        .iter()
+        .zip(&locals)
+        .map(|(arg, local)| match arg.pass {
+            Pass::Value => quote!(#local),
+            Pass::Ref => quote!(&#local),
+            Pass::AsDeref => quote!(#local.as_deref()),
+            Pass::AsRef => quote!(#local.as_ref()),
+        });
+    let reply = if m.oneway {
+        quote!()
+    } else {
+        let write_return = m
+            .ret
+            .is_some()
+            .then(|| quote!(_reply.write(_aidl_return)?;));
+        quote! {
+            match &_aidl_return {
+                Ok(_aidl_return) => {
+                    _reply.write(&::rsbinder::Status::from(::rsbinder::StatusCode::Ok))?;
+                    #write_return
+                }
+                Err(_aidl_status) => {
+                    _reply.write(_aidl_status)?;
+                }
+            }
+        }
+    };
+    let method = ident.unraw().to_string();
+    quote! {
+        #bn::#constant => {
+            ::rsbinder::trace::server_method(#method);
+            #( let #locals: #owned = _reader.read()?; )*
+            let _aidl_return = _service.#ident(#(#passed),*);
+            #reply
+            Ok(())
+        }
+    }
+}
+
+fn proxy_helpers(m: &Method) -> TokenStream {
+    let (build, read) = helper_names(&m.ident);
+    let params = m.args.iter().map(|arg| {
+        let (ident, ty) = (&arg.ident, &arg.ty);
+        quote!(#ident: #ty)
+    });
+    let idents = call_args(m);
+    let data = if m.args.is_empty() {
+        quote!(let _aidl_data)
+    } else {
+        quote!(let mut _aidl_data)
+    };
+    let ret = return_tokens(m);
+    let method = m.ident.unraw().to_string();
+    let response = if m.oneway {
+        quote! {
+            _aidl_reply?; // propagate transport errors (e.g. dead object); oneway has no reply body
+            Ok(())
+        }
+    } else {
+        let value = match &m.ret {
+            Some(ty) => quote! {
+                let _aidl_return: #ty = _aidl_reply.read()?;
+                Ok(_aidl_return)
+            },
+            None => quote!(Ok(())),
+        };
+        quote! {
+            let mut _aidl_reply = _aidl_reply?.ok_or(::rsbinder::StatusCode::UnexpectedNull)?;
+            let _status = _aidl_reply.read::<::rsbinder::Status>()?;
+            if !_status.is_ok() { return Err(_status); }
+            #value
+        }
+    };
+    quote! {
+        fn #build(&self, #(#params),*) -> ::rsbinder::Result<::rsbinder::Parcel> {
+            #data = self.binder.as_remote().ok_or(::rsbinder::StatusCode::BadType)?.prepare_transact(true)?;
+            #( _aidl_data.write(&#idents)?; )*
+            ::rsbinder::trace::client_call(_aidl_data, #method)
+        }
+        fn #read(&self, _aidl_reply: ::rsbinder::Result<Option<::rsbinder::Parcel>>) -> ::rsbinder::BinderResult<#ret> {
+            #response
+        }
+    }
+}
+
+struct AsyncNames {
+    adapter: Ident,
+    client: Ident,
+    service: Ident,
+}
+
+impl AsyncNames {
+    fn new(name: &Ident, bn: &Ident) -> Self {
+        Self {
+            adapter: format_ident!("{}Adapter", bn),
+            client: format_ident!("{}Async", name),
+            service: format_ident!("{}AsyncService", name),
+        }
+    }
+}
+
+// Gives every elided reference in an argument type the `'a` lifetime of
+// the returned `BoxFuture`.
+struct FutureLifetime;
+
+impl VisitMut for FutureLifetime {
+    fn visit_type_reference_mut(&mut self, r: &mut syn::TypeReference) {
+        if r.lifetime.as_ref().is_none_or(|l| l.ident == "_") {
+            r.lifetime = Some(syn::parse_quote!('a));
+        }
+        syn::visit_mut::visit_type_reference_mut(self, r);
+    }
+}
+
+fn async_sig(m: &Method) -> TokenStream {
+    let ident = &m.ident;
+    let params = m.args.iter().map(|arg| {
+        let ident = &arg.ident;
+        let mut ty = arg.ty.clone();
+        FutureLifetime.visit_type_mut(&mut ty);
+        quote!(#ident: #ty)
+    });
+    let ret = return_tokens(m);
+    quote! {
+        fn #ident<'a>(&'a self, #(#params),*) -> ::rsbinder::BoxFuture<'a, ::rsbinder::BinderResult<#ret>>
+    }
+}
+
+fn expand_async(
+    name: &Ident,
+    descriptor: &str,
+    bn: &Ident,
+    bp: &Ident,
+    stability: &TokenStream,
+    names: &AsyncNames,
+    methods: &[Method],
+) -> TokenStream {
+    let AsyncNames {
+        adapter,
+        client,
+        service,
+    } = names;
+    let client_doc = format!(
+        " Asynchronous **client** view of `{name}` (`.await`-able methods); `P` selects the async pool."
+    );
+    let service_doc = format!(
+        " Asynchronous **server** view of `{name}`: implement it with `#[async_trait]` and publish it with [`{bn}::new_async_binder`]."
+    );
+
+    let client_sigs = methods.iter().map(async_sig);
+    let service_sigs = methods.iter().map(|m| {
+        let ident = &m.ident;
+        let params = m.args.iter().map(|arg| {
+            let (ident, ty) = (&arg.ident, &arg.ty);
+            quote!(#ident: #ty)
+        });
+        let ret = return_tokens(m);
+        quote! {
+            async fn #ident(&self, #(#params),*) -> ::rsbinder::BinderResult<#ret>;
+        }
+    });
+    let wrapper_methods = methods.iter().map(|m| {
+        let sig = sync_sig(m);
+        let ident = &m.ident;
+        let call_args = call_args(m);
+        quote! {
+            #sig {
+                self._rt.block_on(self._inner.#ident(#(#call_args),*))
+            }
+        }
+    });
+    let proxy_methods = methods.iter().map(|m| {
+        let sig = async_sig(m);
+        let (build, read) = helper_names(&m.ident);
+        let constant = transaction_const(&m.ident);
+        let call_args = call_args(m);
+        let flags = flags(m);
+        quote! {
+            #sig {
+                let _aidl_data = match self.#build(#(#call_args),*) {
+                    Ok(_aidl_data) => _aidl_data,
+                    Err(err) => return Box::pin(std::future::ready(Err(err.into()))),
+                };
+                let _aidl_reply = P::transact(self.binder.clone(), #bn::#constant, _aidl_data, #flags);
+                Box::pin(async move {
+                    let _aidl_reply = _aidl_reply.await;
+                    self.#read(_aidl_reply)
+                })
+            }
+        }
+    });
+    let native_methods = methods.iter().map(|m| {
+        let sig = async_sig(m);
+        let ident = &m.ident;
+        let call_args = call_args(m);
+        quote! {
+            #sig {
+                self.0.as_async().#ident(#(#call_args),*)
+            }
+        }
+    });
+
+    quote! {
+        #[doc = #client_doc]
+        pub trait #client<P>: ::rsbinder::Interface + Send {
+            fn descriptor() -> &'static str where Self: Sized { #descriptor }
+            #( #client_sigs; )*
+        }
+        #[doc = #service_doc]
+        #[::async_trait::async_trait]
+        pub trait #service: ::rsbinder::Interface + Send {
+            fn descriptor() -> &'static str where Self: Sized { #descriptor }
+            #(#service_sigs)*
+        }
+        impl #bn {
+            /// Wrap an async service impl into a binder, driving each inbound
+            /// call to completion with `rt.block_on(..)`.
+            pub fn new_async_binder<T, R>(inner: T, rt: R) -> ::rsbinder::Strong<dyn #name>
+            where
+                T: #service + Sync + Send + 'static,
+                R: ::rsbinder::BinderAsyncRuntime + Send + Sync + 'static,
+            {
+                Self::new_async_binder_with_features(inner, rt, ::rsbinder::BinderFeatures::default())
+            }
+
+            /// Like [`Self::new_async_binder`] but with explicit binder features.
+            pub fn new_async_binder_with_features<T, R>(
+                inner: T,
+                rt: R,
+                features: ::rsbinder::BinderFeatures,
+            ) -> ::rsbinder::Strong<dyn #name>
+            where
+                T: #service + Sync + Send + 'static,
+                R: ::rsbinder::BinderAsyncRuntime + Send + Sync + 'static,
+            {
+                struct Wrapper<T, R> {
+                    _inner: T,
+                    _rt: R,
+                }
+                impl<T, R> ::rsbinder::Interface for Wrapper<T, R> where T: ::rsbinder::Interface, R: Send + Sync {
+                    fn as_binder(&self) -> ::rsbinder::SIBinder { self._inner.as_binder() }
+                    fn dump(&self, _writer: &mut dyn std::io::Write, _args: &[String]) -> ::rsbinder::Result<()> { self._inner.dump(_writer, _args) }
+                    fn shell_command(&self, _command: &mut ::rsbinder::shell_command::ShellCommand) -> ::rsbinder::Result<()> { self._inner.shell_command(_command) }
+                }
+                impl<T, R> #adapter for Wrapper<T, R>
+                where
+                    T: #service + Sync + Send + 'static,
+                    R: ::rsbinder::BinderAsyncRuntime + Send + Sync + 'static,
+                {
+                    fn as_sync(&self) -> &dyn #name {
+                        self
+                    }
+                    fn as_async(&self) -> &dyn #service {
+                        &self._inner
+                    }
+                    fn try_as_async(&self) -> Option<&dyn #service> {
+                        Some(&self._inner)
+                    }
+                }
+                impl<T, R> #name for Wrapper<T, R>
+                where
+                    T: #service + Sync + Send + 'static,
+                    R: ::rsbinder::BinderAsyncRuntime + Send + Sync + 'static,
+                {
+                    #(#wrapper_methods)*
+                }
+                let wrapped = Wrapper { _inner: inner, _rt: rt };
+                let binder = ::rsbinder::native::Binder::new_with_stability_and_features(#bn(Box::new(wrapped)), #stability, features);
+                ::rsbinder::Strong::new(Box::new(binder))
+            }
+        }
+        impl<P: ::rsbinder::BinderAsyncPool> #client<P> for #bp {
+            #(#proxy_methods)*
+        }
+        impl<P: ::rsbinder::BinderAsyncPool> #client<P> for ::rsbinder::Binder<#bn> {
+            #(#native_methods)*
+        }
+    }
+}
+
+fn render_aidl(
+    name: &Ident,
+    descriptor: &str,
+    args: &InterfaceArgs,
+    methods: &[Method],
+) -> syn::Result<String> {
+    let (package, simple) = aidl::split_descriptor(descriptor);
+    let mut out = aidl::header(package, &name.to_string());
+    if args.vintf {
+        out.push_str("@VintfStability\n");
+    }
+    out.push_str(&format!("interface {simple} {{\n"));
+
+    // AIDL requires all or none of the methods to carry an id; spell them
+    // all out unless every code is implicit.
+    let implicit = methods.iter().enumerate().all(|(i, m)| m.code == i as u32);
+    for m in methods {
+        let ret = match &m.ret {
+            Some(ty) => aidl::type_name(ty)?,
+            None => "void".into(),
+        };
+        let params = m
+            .args
+            .iter()
+            .map(|arg| {
+                let ty = aidl::type_name(&arg.ty)?;
+                let direction = if aidl::needs_direction(&ty) {
+                    "in "
+                } else {
+                    ""
+                };
+                Ok(format!("{direction}{ty} {}", arg.ident.unraw()))
+            })
+            .collect::<syn::Result<Vec<_>>>()?
+            .join(", ");
+        let oneway = if m.oneway { "oneway " } else { "" };
+        let id = if implicit {
+            String::new()
+        } else {
+            format!(" = {}", m.code)
+        };
+        out.push_str(&format!(
+            "    {oneway}{ret} {}({params}){id};\n",
+            m.ident.unraw()
+        ));
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn methods(item: &mut ItemTrait) -> syn::Result<Vec<Method>> {
+        let mut methods = Vec::new();
+        let mut explicit = Vec::new();
+        for trait_item in &mut item.items {
+            if let TraitItem::Fn(func) = trait_item {
+                let (method, code) = parse_method(func)?;
+                methods.push(method);
+                explicit.push(code);
+            }
+        }
+        assign_codes(item, &mut methods, &explicit)?;
+        Ok(methods)
+    }
+
+    #[test]
+    fn test_codes_follow_aidl_numbering() {
+        let mut item: ItemTrait = syn::parse_quote! {
+            trait IFoo {
+                fn a(&self) -> BinderResult<()>;
+                #[binder(code = 10)]
+                fn b(&self) -> BinderResult<()>;
+                fn c(&self) -> BinderResult<()>;
+            }
+        };
+        let codes: Vec<_> = methods(&mut item).unwrap().iter().map(|m| m.code).collect();
+        assert_eq!(codes, [0, 10, 1]);
+
+        let mut clash: ItemTrait = syn::parse_quote! {
+            trait IFoo {
+                fn a(&self) -> BinderResult<()>;
+                #[binder(code = 0)]
+                fn b(&self) -> BinderResult<()>;
+            }
+        };
+        assert!(methods(&mut clash).is_err());
+    }
+
+    #[test]
+    fn test_rejects_unsupported_methods() {
+        for item in [
+            quote!(
+                trait IFoo {
+                    fn a(&mut self) -> BinderResult<()>;
+                }
+            ),
+            quote!(
+                trait IFoo {
+                    fn a(&self) -> i32;
+                }
+            ),
+            quote!(
+                trait IFoo {
+                    fn a(&self, v: &mut Vec<i32>) -> BinderResult<()>;
+                }
+            ),
+            quote!(
+                trait IFoo {
+                    #[binder(oneway)]
+                    fn a(&self) -> BinderResult<i32>;
+                }
+            ),
+            quote!(
+                trait IFoo {
+                    fn a<T>(&self, v: T) -> BinderResult<()>;
+                }
+            ),
+        ] {
+            let mut item: ItemTrait = syn::parse2(item).unwrap();
+            assert!(methods(&mut item).is_err());
+        }
+    }
+
+    #[test]
+    fn test_render_aidl() {
+        let mut item: ItemTrait = syn::parse_quote! {
+            trait ICalc {
+                fn add(&self, a: i32, b: i32) -> BinderResult<i32>;
+                fn join(&self, parts: &[String], sep: Option<&str>) -> BinderResult<String>;
+                fn centroid(&self, points: &[Point]) -> BinderResult<Point>;
+                #[binder(oneway)]
+                fn notify(&self, listener: &Strong<dyn IListener>) -> BinderResult<()>;
+            }
+        };
+        let methods = methods(&mut item).unwrap();
+        let aidl = render_aidl(
+            &item.ident,
+            "com.example.ICalc",
+            &InterfaceArgs::default(),
+            &methods,
+        )
+        .unwrap();
+        assert_eq!(
+            aidl,
+            "// Generated by rsbinder-macros from `ICalc`. Do not edit.\n\
+             \n\
+             package com.example;\n\
+             \n\
+             interface ICalc {\n    \
+                 int add(int a, int b);\n    \
+                 String join(in String[] parts, @nullable String sep);\n    \
+                 Point centroid(in Point[] points);\n    \
+                 oneway void notify(IListener listener);\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Derive and attribute macros for declaring binder types in Rust.
+//!
+//! Services that only ever talk Rust-to-Rust can skip `.aidl` files and the
+//! `rsbinder_aidl::Builder` build step: the macros here expand to the same
+//! trait impls and `Bn*`/`Bp*` machinery the AIDL generator emits, so the
+//! result interoperates with generated code on the other end of the wire.
+//! They are re-exported by `rsbinder` behind its `macros` feature.
+//!
+//! ```ignore
+//! use rsbinder::{binder_interface, BinderResult, Parcelable};
+//!
+//! #[derive(Debug, Default, Parcelable)]
+//! #[parcelable(descriptor = "com.example.Point")]
+//! pub struct Point {
+//!     pub x: i32,
+//!     pub y: i32,
+//! }
+//!
+//! #[binder_interface(descriptor = "com.example.IShapes", aidl = "aidl/com/example/IShapes.aidl")]
+//! pub trait IShapes {
+//!     fn centroid(&self, points: &[Point]) -> BinderResult<Point>;
+//!     #[binder(oneway)]
+//!     fn reset(&self) -> BinderResult<()>;
+//! }
+//! ```
+//!
+//! Generated code refers to the runtime as `::rsbinder`, and (with the
+//! `async` feature) to `::async_trait`, exactly like AIDL-generated code.
+
+use proc_macro::TokenStream;
+
+mod aidl;
+mod interface;
+mod parcelable;
+
+/// Derive `Parcelable`, `Serialize`, `Deserialize` and their array/option
+/// companions.
+///
+/// - A struct becomes a structured parcelable: a sized body with the fields
+///   in declaration order. It must implement `Default`.
+/// - An enum whose variants all carry no data becomes an `int`-backed AIDL
+///   enum. Each variant is written as its discriminant (`Variant as i32`);
+///   an unknown value is rejected with `BadValue`.
+/// - An enum whose variants all carry exactly one unnamed field becomes an
+///   AIDL union: an `i32` tag (the variant index) and the value. The derive
+///   also implements `Default` as the first variant holding its default.
+///
+/// Container attributes, `#[parcelable(...)]`:
+///
+/// - `descriptor = "pkg.Name"` — the `ParcelableMetadata` descriptor and the
+///   name in the written `.aidl` file. Defaults to the type name.
+/// - `vintf` — report `Stability::Vintf`.
+/// - `aidl = "path/Name.aidl"` — write an equivalent `.aidl` declaration to
+///   this path (relative to the crate's `Cargo.toml`) at expansion time.
+#[proc_macro_derive(Parcelable, attributes(parcelable))]
+pub fn derive_parcelable(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    parcelable::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Turn a Rust trait into a binder interface.
+///
+/// Every method takes `&self` and returns `rsbinder::BinderResult<T>`;
+/// arguments are `in` parameters. The trait gains `rsbinder::Interface +
+/// Send` as supertraits, and the macro emits, as the AIDL generator does
+/// for `IFoo`:
+///
+/// - `BnFoo` (native side) with `new_binder` / `new_binder_with_features`,
+///   and `BpFoo` (proxy), via `rsbinder::declare_binder_interface!`;
+/// - `BnFoo::TRANSACTION_<method>` constants holding the transaction codes;
+/// - with the `async` feature, `IFooAsync<P>`, `IFooAsyncService` and
+///   `BnFoo::new_async_binder`.
+///
+/// Transaction codes follow AIDL: `FIRST_CALL_TRANSACTION` plus the
+/// method's position among the methods without an explicit code, so they
+/// only change when methods are inserted or reordered. Pin a code with
+/// `#[binder(code = N)]` (AIDL's `= N`).
+///
+/// Arguments, `#[binder_interface(...)]`:
+///
+/// - `descriptor = "pkg.IFoo"` — the interface descriptor. Defaults to the
+///   trait name.
+/// - `vintf` — publish native binders with `Stability::Vintf`.
+/// - `aidl = "path/IFoo.aidl"` — write an equivalent `.aidl` interface to
+///   this path (relative to the crate's `Cargo.toml`) at expansion time.
+///   Imports of user-defined types are left to the reader.
+///
+/// Method attributes, `#[binder(...)]`: `oneway` (fire-and-forget; must
+/// return `BinderResult<()>`) and `code = N`.
+#[proc_macro_attribute]
+pub fn binder_interface(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut attrs = interface::InterfaceArgs::default();
+    let parser = syn::meta::parser(|meta| attrs.parse(meta));
+    syn::parse_macro_input!(args with parser);
+    let item = syn::parse_macro_input!(item as syn::ItemTrait);
+    interface::expand(attrs, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,272 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `#[derive(Parcelable)]`: the parcelable, enum and union templates of
+//! the AIDL generator, driven by a Rust type definition.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr};
+
+use crate::aidl;
+
+#[derive(Default)]
+struct Attrs {
+    descriptor: Option<LitStr>,
+    vintf: bool,
+    aidl: Option<LitStr>,
+}
+
+impl Attrs {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("parcelable"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("descriptor") {
+                    attrs.descriptor = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("vintf") {
+                    attrs.vintf = true;
+                } else if meta.path.is_ident("aidl") {
+                    attrs.aidl = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `descriptor`, `vintf` or `aidl`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(attrs)
+    }
+
+    fn descriptor(&self, input: &DeriveInput) -> String {
+        self.descriptor
+            .as_ref()
+            .map(LitStr::value)
+            .unwrap_or_else(|| input.ident.to_string())
+    }
+}
+
+enum Kind {
+    Struct,
+    Enum,
+    Union,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "generic parcelables are not supported",
+        ));
+    }
+    let attrs = Attrs::parse(&input)?;
+
+    let (kind, body) = match &input.data {
+        Data::Struct(data) => (Kind::Struct, expand_struct(&input, &data.fields)),
+        Data::Enum(data) if data.variants.is_empty() => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "an empty enum cannot be a parcelable",
+            ))
+        }
+        Data::Enum(data) if data.variants.iter().all(|v| v.fields.is_empty()) => {
+            (Kind::Enum, expand_enum(&input, data))
+        }
+        Data::Enum(data) => (Kind::Union, expand_union(&input, data)?),
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "`union` types cannot be parcelables; use an enum",
+            ))
+        }
+    };
+
+    if let Some(path) = &attrs.aidl {
+        aidl::write(path, &render_aidl(&input, &attrs, &kind)?)?;
+    }
+
+    let name = &input.ident;
+    let metadata = match kind {
+        Kind::Enum => quote!(),
+        Kind::Struct | Kind::Union => {
+            let descriptor = attrs.descriptor(&input);
+            let stability = attrs.vintf.then(|| {
+                quote! {
+                    fn stability(&self) -> ::rsbinder::Stability { ::rsbinder::Stability::Vintf }
+                }
+            });
+            quote! {
+                ::rsbinder::impl_serialize_for_parcelable!(#name);
+                ::rsbinder::impl_deserialize_for_parcelable!(#name);
+                impl ::rsbinder::ParcelableMetadata for #name {
+                    fn descriptor() -> &'static str { #descriptor }
+                    #stability
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        #body
+        #metadata
+    })
+}
+
+fn field_members(fields: &Fields) -> Vec<syn::Member> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(index.into()),
+        })
+        .collect()
+}
+
+fn expand_struct(input: &DeriveInput, fields: &Fields) -> TokenStream {
+    let name = &input.ident;
+    let members = field_members(fields);
+    quote! {
+        impl ::rsbinder::Parcelable for #name {
+            fn write_to_parcel(&self, _parcel: &mut ::rsbinder::Parcel) -> ::rsbinder::Result<()> {
+                _parcel.sized_write(|_sub_parcel| {
+                    #( _sub_parcel.write(&self.#members)?; )*
+                    Ok(())
+                })
+            }
+            fn read_from_parcel(&mut self, _parcel: &mut ::rsbinder::Parcel) -> ::rsbinder::Result<()> {
+                _parcel.sized_read(|_sub_parcel| {
+                    #(
+                        if !_sub_parcel.has_more_data() { return Ok(()); }
+                        self.#members = _sub_parcel.read()?;
+                    )*
+                    Ok(())
+                })
+            }
+        }
+    }
+}
+
+fn expand_enum(input: &DeriveInput, data: &syn::DataEnum) -> TokenStream {
+    let name = &input.ident;
+    let variants: Vec<_> = data.variants.iter().map(|v| &v.ident).collect();
+    quote! {
+        impl ::rsbinder::Serialize for #name {
+            fn serialize(&self, parcel: &mut ::rsbinder::Parcel) -> ::rsbinder::Result<()> {
+                let value: i32 = match self {
+                    #( Self::#variants => Self::#variants as i32, )*
+                };
+                parcel.write(&value)
+            }
+        }
+        impl ::rsbinder::SerializeArray for #name {}
+        impl ::rsbinder::Deserialize for #name {
+            fn deserialize(parcel: &mut ::rsbinder::Parcel) -> ::rsbinder::Result<Self> {
+                let value: i32 = parcel.read()?;
+                #( if value == Self::#variants as i32 { return Ok(Self::#variants); } )*
+                Err(::rsbinder::StatusCode::BadValue)
+            }
+        }
+        impl ::rsbinder::DeserializeArray for #name {}
+    }
+}
+
+fn expand_union(input: &DeriveInput, data: &syn::DataEnum) -> syn::Result<TokenStream> {
+    for variant in &data.variants {
+        if !matches!(&variant.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "union parcelable variants must hold exactly one unnamed field",
+            ));
+        }
+    }
+
+    let name = &input.ident;
+    let variants: Vec<_> = data.variants.iter().map(|v| &v.ident).collect();
+    let tags: Vec<_> = (0..variants.len() as i32).collect();
+    let first = variants[0];
+    Ok(quote! {
+        impl Default for #name {
+            fn default() -> Self {
+                Self::#first(Default::default())
+            }
+        }
+        impl ::rsbinder::Parcelable for #name {
+            fn write_to_parcel(&self, parcel: &mut ::rsbinder::Parcel) -> ::rsbinder::Result<()> {
+                match self {
+                    #(
+                        Self::#variants(v) => {
+                            parcel.write(&#tags)?;
+                            parcel.write(v)
+                        }
+                    )*
+                }
+            }
+            fn read_from_parcel(&mut self, parcel: &mut ::rsbinder::Parcel) -> ::rsbinder::Result<()> {
+                let tag: i32 = parcel.read()?;
+                match tag {
+                    #(
+                        #tags => {
+                            *self = Self::#variants(parcel.read()?);
+                            Ok(())
+                        }
+                    )*
+                    _ => Err(::rsbinder::StatusCode::BadValue),
+                }
+            }
+        }
+    })
+}
+
+fn render_aidl(input: &DeriveInput, attrs: &Attrs, kind: &Kind) -> syn::Result<String> {
+    let descriptor = attrs.descriptor(input);
+    let (package, name) = aidl::split_descriptor(&descriptor);
+    let mut out = aidl::header(package, &input.ident.to_string());
+    if attrs.vintf {
+        out.push_str("@VintfStability\n");
+    }
+
+    match (&input.data, kind) {
+        (Data::Struct(data), _) => {
+            out.push_str(&format!("parcelable {name} {{\n"));
+            for (index, field) in data.fields.iter().enumerate() {
+                let field_name = match &field.ident {
+                    Some(ident) => ident.to_string(),
+                    None => format!("_{index}"),
+                };
+                out.push_str(&format!(
+                    "    {} {field_name};\n",
+                    aidl::type_name(&field.ty)?
+                ));
+            }
+        }
+        (Data::Enum(data), Kind::Enum) => {
+            out.push_str(&format!("@Backing(type=\"int\")\nenum {name} {{\n"));
+            for variant in &data.variants {
+                match &variant.discriminant {
+                    Some((_, value)) => {
+                        out.push_str(&format!("    {} = {},\n", variant.ident, quote!(#value)))
+                    }
+                    None => out.push_str(&format!("    {},\n", variant.ident)),
+                }
+            }
+        }
+        (Data::Enum(data), _) => {
+            out.push_str(&format!("union {name} {{\n"));
+            for variant in &data.variants {
+                let ty = &variant.fields.iter().next().expect("checked above").ty;
+                out.push_str(&format!(
+                    "    {} {};\n",
+                    aidl::type_name(ty)?,
+                    variant.ident
+                ));
+            }
+        }
+        (Data::Union(_), _) => unreachable!("rejected above"),
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
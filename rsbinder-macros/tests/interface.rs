@@ -0,0 +1,225 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `#[binder_interface]` services called across processes of the fake
+//! binder driver.
+//!
+//! The service process is the context manager; the test threads are the
+//! client process.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use rsbinder::fake_driver::{FakeProcess, FAKE_BINDER_PATH};
+use rsbinder::{
+    binder_interface, BinderResult, ExceptionCode, FromIBinder, Interface, Parcelable,
+    ProcessState, Status, Strong, Tokio, TokioRuntime, FIRST_CALL_TRANSACTION,
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Parcelable)]
+#[parcelable(descriptor = "com.example.Point")]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[binder_interface(descriptor = "com.example.IListener")]
+pub trait IListener {
+    fn on_value(&self, value: i32) -> BinderResult<i32>;
+}
+
+#[binder_interface(descriptor = "com.example.ICalc")]
+pub trait ICalc {
+    fn add(&self, a: i32, b: i32) -> BinderResult<i32>;
+    fn join(&self, parts: &[String], sep: Option<&str>) -> BinderResult<String>;
+    fn centroid(&self, points: &[Point]) -> BinderResult<Point>;
+    fn check(&self, value: i32) -> BinderResult<()>;
+    #[binder(oneway)]
+    fn push(&self, value: i32) -> BinderResult<()>;
+    #[binder(code = 20)]
+    fn pushed(&self) -> BinderResult<Vec<i32>>;
+    fn call_back(&self, listener: &Strong<dyn IListener>, value: i32) -> BinderResult<i32>;
+    fn stall(&self, millis: i32) -> BinderResult<i32>;
+}
+
+#[derive(Default)]
+struct Calc {
+    pushed: Mutex<Vec<i32>>,
+}
+
+impl Interface for Calc {}
+
+impl ICalc for Calc {
+    fn add(&self, a: i32, b: i32) -> BinderResult<i32> {
+        Ok(a + b)
+    }
+    fn join(&self, parts: &[String], sep: Option<&str>) -> BinderResult<String> {
+        Ok(parts.join(sep.unwrap_or(",")))
+    }
+    fn centroid(&self, points: &[Point]) -> BinderResult<Point> {
+        let n = points.len().max(1) as i32;
+        Ok(Point {
+            x: points.iter().map(|p| p.x).sum::<i32>() / n,
+            y: points.iter().map(|p| p.y).sum::<i32>() / n,
+        })
+    }
+    fn check(&self, value: i32) -> BinderResult<()> {
+        if value < 0 {
+            return Err(Status::new_service_specific_error(value, None));
+        }
+        Ok(())
+    }
+    fn push(&self, value: i32) -> BinderResult<()> {
+        self.pushed.lock().unwrap().push(value);
+        Ok(())
+    }
+    fn pushed(&self) -> BinderResult<Vec<i32>> {
+        Ok(self.pushed.lock().unwrap().clone())
+    }
+    fn call_back(&self, listener: &Strong<dyn IListener>, value: i32) -> BinderResult<i32> {
+        listener.on_value(value)
+    }
+    fn stall(&self, millis: i32) -> BinderResult<i32> {
+        std::thread::sleep(Duration::from_millis(millis as u64));
+        Ok(millis)
+    }
+}
+
+struct Doubler;
+
+impl Interface for Doubler {}
+
+#[async_trait::async_trait]
+impl IListenerAsyncService for Doubler {
+    async fn on_value(&self, value: i32) -> BinderResult<i32> {
+        tokio::task::yield_now().await;
+        Ok(value * 2)
+    }
+}
+
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Runtime::new().unwrap())
+}
+
+fn calc() -> Strong<dyn ICalc> {
+    static SERVICE: OnceLock<FakeProcess> = OnceLock::new();
+    SERVICE.get_or_init(|| {
+        let _ = env_logger::builder().is_test(true).try_init();
+        ProcessState::init(FAKE_BINDER_PATH, 4).expect("fake ProcessState");
+        ProcessState::start_thread_pool();
+
+        let service = FakeProcess::new(1000).unwrap();
+        service.start_thread_pool();
+        service
+            .spawn(|| {
+                let binder = BnCalc::new_binder(Calc::default());
+                ProcessState::as_self()
+                    .become_context_manager(binder.as_binder())
+                    .expect("context manager");
+            })
+            .join()
+            .unwrap();
+        service
+    });
+    let binder = ProcessState::as_self().context_object().unwrap();
+    <dyn ICalc as FromIBinder>::try_from(binder).unwrap()
+}
+
+#[test]
+fn test_transaction_codes() {
+    assert_eq!(BnCalc::TRANSACTION_add, FIRST_CALL_TRANSACTION);
+    assert_eq!(BnCalc::TRANSACTION_call_back, FIRST_CALL_TRANSACTION + 5);
+    assert_eq!(BnCalc::TRANSACTION_pushed, FIRST_CALL_TRANSACTION + 20);
+    assert_eq!(<BpCalc as ICalc>::descriptor(), "com.example.ICalc");
+}
+
+#[test]
+fn test_calls() {
+    let calc = calc();
+    assert_eq!(calc.add(2, 3).unwrap(), 5);
+    assert_eq!(
+        calc.join(&["a".into(), "b".into()], Some("-")).unwrap(),
+        "a-b"
+    );
+    assert_eq!(calc.join(&["a".into(), "b".into()], None).unwrap(), "a,b");
+    assert_eq!(
+        calc.centroid(&[Point { x: 0, y: 0 }, Point { x: 4, y: 2 }])
+            .unwrap(),
+        Point { x: 2, y: 1 }
+    );
+}
+
+#[test]
+fn test_error_status_propagates() {
+    let calc = calc();
+    calc.check(1).unwrap();
+    let err = calc.check(-7).unwrap_err();
+    assert_eq!(err.exception_code(), ExceptionCode::ServiceSpecific);
+    assert_eq!(err.service_specific_error(), -7);
+}
+
+#[test]
+fn test_oneway() {
+    let calc = calc();
+    calc.push(41).unwrap();
+    calc.push(42).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        // Other tests push concurrently; only the relative order matters.
+        let pushed = calc.pushed().unwrap();
+        let ours: Vec<_> = pushed.iter().filter(|v| [41, 42].contains(*v)).collect();
+        if ours == [&41, &42] {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "oneway calls not delivered: {pushed:?}"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_async_service_as_argument() {
+    let calc = calc();
+    let listener = BnListener::new_async_binder(Doubler, TokioRuntime(runtime().handle().clone()));
+    assert_eq!(calc.call_back(&listener, 21).unwrap(), 42);
+}
+
+#[test]
+fn test_async_client() {
+    let calc = calc().into_async::<Tokio>();
+    runtime().block_on(async move {
+        assert_eq!(calc.add(20, 22).await.unwrap(), 42);
+        assert_eq!(
+            calc.centroid(&[Point { x: 2, y: 2 }]).await.unwrap(),
+            Point { x: 2, y: 2 }
+        );
+        assert_eq!(
+            calc.check(-1).await.unwrap_err().service_specific_error(),
+            -1
+        );
+        calc.push(7).await.unwrap();
+    });
+}
+
+#[test]
+fn test_with_timeout() {
+    let calc = calc();
+    let started = Instant::now();
+    let err = calc
+        .with_timeout(Duration::from_millis(50))
+        .stall(1000)
+        .unwrap_err();
+    assert_eq!(err.transaction_error(), rsbinder::StatusCode::TimedOut);
+    assert!(started.elapsed() < Duration::from_millis(900));
+
+    // The calling thread is not stuck behind the abandoned call.
+    assert_eq!(calc.add(1, 2).unwrap(), 3);
+    let timed = calc.with_timeout(Duration::from_secs(5));
+    assert_eq!(timed.stall(1).unwrap(), 1);
+    // A nested callback reaches the helper thread that made the call.
+    let listener = BnListener::new_async_binder(Doubler, TokioRuntime(runtime().handle().clone()));
+    assert_eq!(timed.call_back(&listener, 4).unwrap(), 8);
+}
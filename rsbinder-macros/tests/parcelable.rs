@@ -0,0 +1,160 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `#[derive(Parcelable)]` round trips, and wire compatibility with the
+//! layout the AIDL generator writes.
+
+use rsbinder::{Parcel, Parcelable, ParcelableMetadata, Result, Stability, StatusCode};
+
+#[derive(Debug, Default, Clone, PartialEq, Parcelable)]
+#[parcelable(descriptor = "com.example.Point")]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, Default, PartialEq, Parcelable)]
+#[parcelable(descriptor = "com.example.Shape", vintf)]
+struct Shape {
+    name: String,
+    points: Vec<Point>,
+    origin: Option<Point>,
+    tags: Option<Vec<String>>,
+    kind: Kind,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Parcelable)]
+enum Kind {
+    #[default]
+    Polygon = 1,
+    Circle = 7,
+}
+
+#[derive(Debug, PartialEq, Parcelable)]
+#[parcelable(descriptor = "com.example.Value")]
+enum Value {
+    Int(i32),
+    Text(String),
+    Point(Point),
+}
+
+fn round_trip<T: rsbinder::Serialize + rsbinder::Deserialize>(value: &T) -> T {
+    let mut parcel = Parcel::new();
+    parcel.write(value).unwrap();
+    parcel.set_data_position(0);
+    parcel.read().unwrap()
+}
+
+#[test]
+fn test_struct_round_trip() {
+    let shape = Shape {
+        name: "triangle".into(),
+        points: vec![
+            Point { x: 0, y: 0 },
+            Point { x: 4, y: 0 },
+            Point { x: 0, y: 3 },
+        ],
+        origin: None,
+        tags: Some(vec!["right".into()]),
+        kind: Kind::Circle,
+    };
+    assert_eq!(round_trip(&shape), shape);
+    assert_eq!(
+        round_trip(&Some(Point { x: 1, y: 2 })),
+        Some(Point { x: 1, y: 2 })
+    );
+    assert_eq!(round_trip(&None::<Point>), None);
+}
+
+#[test]
+fn test_metadata() {
+    assert_eq!(
+        <Point as ParcelableMetadata>::descriptor(),
+        "com.example.Point"
+    );
+    assert_eq!(Point::default().stability(), Stability::default());
+    assert_eq!(Shape::default().stability(), Stability::Vintf);
+    assert_eq!(Value::default(), Value::Int(0));
+}
+
+// What the AIDL generator emits for `parcelable Point { int x; int y; }`.
+struct AidlPoint {
+    x: i32,
+    y: i32,
+}
+
+impl AidlPoint {
+    fn write_to_parcel(&self, parcel: &mut Parcel) -> Result<()> {
+        parcel.sized_write(|sub| {
+            sub.write(&self.x)?;
+            sub.write(&self.y)?;
+            Ok(())
+        })
+    }
+}
+
+#[test]
+fn test_struct_matches_aidl_layout() {
+    let mut derived = Parcel::new();
+    Point { x: 3, y: -4 }.write_to_parcel(&mut derived).unwrap();
+
+    let mut generated = Parcel::new();
+    AidlPoint { x: 3, y: -4 }
+        .write_to_parcel(&mut generated)
+        .unwrap();
+    assert_eq!(derived.data_bytes(), generated.data_bytes());
+}
+
+#[test]
+fn test_struct_tolerates_older_writer() {
+    // A peer built against an older `Point` that only had `x`.
+    let mut parcel = Parcel::new();
+    parcel.write(&rsbinder::NON_NULL_PARCELABLE_FLAG).unwrap();
+    parcel.sized_write(|sub| sub.write(&9i32)).unwrap();
+    parcel.set_data_position(0);
+    let point: Option<Point> = parcel.read().unwrap();
+    assert_eq!(point, Some(Point { x: 9, y: 0 }));
+}
+
+#[test]
+fn test_enum_uses_discriminants() {
+    let mut parcel = Parcel::new();
+    parcel.write(&Kind::Circle).unwrap();
+    parcel.write(&vec![Kind::Polygon, Kind::Circle]).unwrap();
+    parcel.write(&3i32).unwrap();
+    parcel.set_data_position(0);
+
+    assert_eq!(parcel.read::<i32>().unwrap(), 7);
+    parcel.set_data_position(0);
+    assert_eq!(parcel.read::<Kind>().unwrap(), Kind::Circle);
+    assert_eq!(
+        parcel.read::<Vec<Kind>>().unwrap(),
+        vec![Kind::Polygon, Kind::Circle]
+    );
+    assert_eq!(parcel.read::<Kind>(), Err(StatusCode::BadValue));
+}
+
+#[test]
+fn test_union_round_trip() {
+    for value in [
+        Value::Int(-1),
+        Value::Text("hello".into()),
+        Value::Point(Point { x: 5, y: 6 }),
+    ] {
+        assert_eq!(round_trip(&value), value);
+    }
+
+    // Tag, then the value; an unknown tag is rejected.
+    let mut parcel = Parcel::new();
+    Value::Text("x".into())
+        .write_to_parcel(&mut parcel)
+        .unwrap();
+    parcel.set_data_position(0);
+    assert_eq!(parcel.read::<i32>().unwrap(), 1);
+
+    let mut bad = Parcel::new();
+    bad.write(&rsbinder::NON_NULL_PARCELABLE_FLAG).unwrap();
+    bad.write(&9i32).unwrap();
+    bad.set_data_position(0);
+    assert_eq!(bad.read::<Value>(), Err(StatusCode::BadValue));
+}
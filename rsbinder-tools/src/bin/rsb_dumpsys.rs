@@ -0,0 +1,579 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use env_logger::Env;
+use rsbinder::*;
+use rustix::event::{PollFd, PollFlags, Timespec};
+use rustix::pipe::PipeFlags;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const SEPARATOR: &str =
+    "-------------------------------------------------------------------------------";
+
+/// Priority levels of `--priority`, with the flags `listServices` filters
+/// by. The level is also passed to the services as
+/// `--dump-priority <LEVEL>`, as AOSP's `PriorityDumper` expects.
+const PRIORITIES: [(&str, i32); 3] = [
+    ("CRITICAL", hub::DUMP_FLAG_PRIORITY_CRITICAL),
+    ("HIGH", hub::DUMP_FLAG_PRIORITY_HIGH),
+    ("NORMAL", hub::DUMP_FLAG_PRIORITY_NORMAL),
+];
+
+/// How a dump ended.
+enum Outcome {
+    Done(Duration),
+    Failed(StatusCode),
+    TimedOut,
+}
+
+/// Wait until `fd` is readable or `timeout` passes; false on timeout.
+fn wait_readable(fd: BorrowedFd<'_>, timeout: Duration) -> Result<bool> {
+    let timeout = Timespec::try_from(timeout).unwrap_or(Timespec {
+        tv_sec: i64::MAX,
+        tv_nsec: 0,
+    });
+    let mut fds = [PollFd::new(&fd, PollFlags::IN)];
+    loop {
+        match rustix::event::poll(&mut fds, Some(&timeout)) {
+            Ok(0) => return Ok(false),
+            Ok(_) => return Ok(true),
+            Err(rustix::io::Errno::INTR) => {}
+            Err(err) => return Err(format!("poll: {err}").into()),
+        }
+    }
+}
+
+/// Copy what is written to `reader` to `out` until the writer closes it;
+/// false if `deadline` passes first.
+fn copy_until(reader: &mut std::fs::File, deadline: Instant, out: &mut impl Write) -> Result<bool> {
+    let mut buf = [0u8; 4096];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || !wait_readable(reader.as_fd(), remaining)? {
+            return Ok(false);
+        }
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(true),
+            Ok(n) => out.write_all(&buf[..n])?,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(format!("reading dump: {e}").into()),
+        }
+    }
+}
+
+/// Send `DUMP_TRANSACTION` to `binder` and copy what it writes to `out`
+/// until it closes the pipe, giving up after `timeout`. The call runs on a
+/// thread of its own, which is left behind if the service hangs.
+fn dump(
+    binder: &SIBinder,
+    args: &[String],
+    timeout: Duration,
+    out: &mut impl Write,
+) -> Result<Outcome> {
+    let start = Instant::now();
+    let deadline = start + timeout;
+    let (read_end, write_end) = rustix::pipe::pipe_with(PipeFlags::CLOEXEC)?;
+
+    let (result_tx, result) = mpsc::channel();
+    let binder = binder.clone();
+    let args = args.to_vec();
+    std::thread::Builder::new()
+        .name("rsb_dumpsys:dump".into())
+        .spawn(move || {
+            let sent = match binder.as_proxy() {
+                Some(proxy) => proxy.dump(write_end, &args),
+                None => Err(StatusCode::BadType),
+            };
+            let _ = result_tx.send(sent);
+        })?;
+
+    if !copy_until(&mut std::fs::File::from(read_end), deadline, out)? {
+        return Ok(Outcome::TimedOut);
+    }
+
+    // The service closed the pipe; its reply is due at once.
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    Ok(match result.recv_timeout(remaining) {
+        Ok(Ok(())) => Outcome::Done(start.elapsed()),
+        Ok(Err(err)) => Outcome::Failed(err),
+        Err(_) => Outcome::TimedOut,
+    })
+}
+
+/// Dump `name`, reporting a hang or failure the way AOSP dumpsys does.
+fn dump_service(
+    name: &str,
+    args: &[String],
+    timeout: Duration,
+    out: &mut impl Write,
+) -> Result<Option<Duration>> {
+    let Some(binder) = hub::check_service(name) else {
+        out.flush()?;
+        eprintln!("Can't find service: {name}");
+        return Ok(None);
+    };
+    match dump(&binder, args, timeout, out)? {
+        Outcome::Done(elapsed) => Ok(Some(elapsed)),
+        Outcome::Failed(err) => {
+            out.flush()?;
+            eprintln!("Error dumping service info: ({err:?}) {name}");
+            Ok(None)
+        }
+        Outcome::TimedOut => {
+            write_timed_out(out, name, timeout)?;
+            Ok(None)
+        }
+    }
+}
+
+fn write_timed_out(out: &mut impl Write, name: &str, timeout: Duration) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "\n*** SERVICE '{name}' DUMP TIMEOUT ({}ms) EXPIRED ***\n",
+        timeout.as_millis()
+    )
+}
+
+fn write_heading(out: &mut impl Write, priority: Option<&str>, name: &str) -> std::io::Result<()> {
+    writeln!(out, "{SEPARATOR}")?;
+    match priority {
+        Some(level) => writeln!(out, "DUMP OF SERVICE {level} {name}:"),
+        None => writeln!(out, "DUMP OF SERVICE {name}:"),
+    }
+}
+
+fn write_duration(out: &mut impl Write, name: &str, elapsed: Duration) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "--------- {:.3}s was the duration of dumpsys {name}",
+        elapsed.as_secs_f64()
+    )
+}
+
+/// The pid hosting each service, from `getServiceDebugInfo`.
+fn service_pids() -> Result<HashMap<String, i32>> {
+    let infos = hub::get_service_debug_info()
+        .map_err(|e| format!("cannot get service debug info: {e:?}"))?;
+    Ok(infos
+        .into_iter()
+        .map(|info| (info.name, info.debugPid))
+        .collect())
+}
+
+fn print_pid(pids: &HashMap<String, i32>, name: &str, out: &mut impl Write) -> Result<()> {
+    match pids.get(name) {
+        Some(pid) => writeln!(out, "{pid}")?,
+        None => {
+            out.flush()?;
+            eprintln!("Can't find service: {name}");
+        }
+    }
+    Ok(())
+}
+
+/// List `services` with the pid hosting each, where `pids` knows it, and
+/// the ones `skip` leaves out marked.
+fn write_list(
+    out: &mut impl Write,
+    services: &[String],
+    skip: &[String],
+    pids: &HashMap<String, i32>,
+) -> std::io::Result<()> {
+    writeln!(out, "Currently running services:")?;
+    for name in services {
+        write!(out, "  {name}")?;
+        if let Some(pid) = pids.get(name) {
+            write!(out, " (pid {pid})")?;
+        }
+        if skip.contains(name) {
+            write!(out, " (skipped)")?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// The command line, as given.
+#[derive(Debug, PartialEq)]
+struct Options {
+    device: String,
+    list: bool,
+    priority: Option<String>,
+    proto: bool,
+    pid: bool,
+    skip: Vec<String>,
+    timeout: Duration,
+    service: Option<String>,
+    args: Vec<String>,
+}
+
+impl Options {
+    fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        let options = Options {
+            device: matches
+                .get_one::<String>("device")
+                .expect("device has a default value")
+                .clone(),
+            list: matches.get_flag("list"),
+            priority: matches.get_one::<String>("priority").cloned(),
+            proto: matches.get_flag("proto"),
+            pid: matches.get_flag("pid"),
+            skip: matches
+                .get_many::<String>("skip")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect(),
+            timeout: match matches.get_one::<u64>("timeout_ms") {
+                Some(&millis) => Duration::from_millis(millis),
+                None => Duration::from_secs(
+                    *matches
+                        .get_one::<u64>("timeout")
+                        .expect("timeout has a default value"),
+                ),
+            },
+            service: matches.get_one::<String>("service").cloned(),
+            args: matches
+                .get_many::<String>("args")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect(),
+        };
+        if options.list && options.service.is_some() {
+            return Err("-l lists every service; it takes no service name".into());
+        }
+        Ok(options)
+    }
+
+    /// The `listServices` dump flags the services are selected by.
+    fn dump_flags(&self) -> i32 {
+        let mut flags = match &self.priority {
+            Some(level) => PRIORITIES
+                .iter()
+                .find(|(name, _)| name == level)
+                .map(|(_, flag)| *flag)
+                .expect("clap checks the level"),
+            None => hub::DUMP_FLAG_PRIORITY_ALL,
+        };
+        if self.proto {
+            flags |= hub::DUMP_FLAG_PROTO;
+        }
+        flags
+    }
+
+    /// The arguments every service is dumped with when all are.
+    fn dump_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(level) = &self.priority {
+            args.extend(["--dump-priority".to_owned(), level.clone()]);
+        }
+        if self.proto {
+            args.push("--proto".to_owned());
+        }
+        args
+    }
+
+    /// The services of `services` to dump.
+    fn dumped<'a>(&'a self, services: &'a [String]) -> impl Iterator<Item = &'a String> {
+        services.iter().filter(|name| !self.skip.contains(name))
+    }
+}
+
+fn command() -> clap::Command {
+    clap::Command::new("rsb_dumpsys")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Dumps the state of binder services, like Android's dumpsys")
+        .arg(
+            clap::Arg::new("device")
+                .short('d')
+                .long("device")
+                .value_name("NAME")
+                .help("Name of the binder device to use (e.g., 'binder', 'mybinder')")
+                .default_value("binder"),
+        )
+        .arg(
+            clap::Arg::new("list")
+                .short('l')
+                .action(clap::ArgAction::SetTrue)
+                .help("Only list the services, without dumping them"),
+        )
+        .arg(
+            clap::Arg::new("priority")
+                .long("priority")
+                .value_name("LEVEL")
+                .value_parser(PRIORITIES.map(|(level, _)| level))
+                .help("Only the services registered with this dump priority"),
+        )
+        .arg(
+            clap::Arg::new("proto")
+                .long("proto")
+                .action(clap::ArgAction::SetTrue)
+                .help("Only the services that can dump in protobuf format, asked to"),
+        )
+        .arg(
+            clap::Arg::new("pid")
+                .long("pid")
+                .action(clap::ArgAction::SetTrue)
+                .help("Show the pid hosting the services instead of dumping them"),
+        )
+        .arg(
+            clap::Arg::new("skip")
+                .long("skip")
+                .value_name("SERVICES")
+                .value_delimiter(',')
+                .conflicts_with("service")
+                .help("Dump every service except these (comma-separated)"),
+        )
+        .arg(
+            clap::Arg::new("timeout")
+                .short('t')
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("10")
+                .help("Time a service may take to dump"),
+        )
+        .arg(
+            clap::Arg::new("timeout_ms")
+                .short('T')
+                .value_name("MILLIS")
+                .value_parser(clap::value_parser!(u64))
+                .conflicts_with("timeout")
+                .help("Time a service may take to dump, in milliseconds"),
+        )
+        .arg(
+            clap::Arg::new("service")
+                .help("The service to dump; every service if omitted")
+                .index(1),
+        )
+        .arg(
+            clap::Arg::new("args")
+                .help("Arguments passed to the service's dump")
+                .index(2)
+                .num_args(0..)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true),
+        )
+        .after_help(
+            "Examples:\n    \
+            List the services:\n    \
+            $ rsb_dumpsys -l\n\n    \
+            Dump every service but two, allowing each 3 seconds:\n    \
+            $ rsb_dumpsys -t 3 --skip my.first,my.second\n\n    \
+            Dump one service, passing it arguments:\n    \
+            $ rsb_dumpsys my.service --verbose\n\n    \
+            Show the pid hosting a service:\n    \
+            $ rsb_dumpsys --pid my.service",
+        )
+}
+
+fn main() -> Result<()> {
+    let options = Options::from_matches(&command().get_matches())?;
+
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+
+    ProcessState::init(&format!("{DEFAULT_BINDERFS_PATH}/{}", options.device), 0)?;
+
+    // `--pid` needs them; the listing shows them where the service
+    // manager can tell (not on Android 10 and 11).
+    let pids = if options.pid {
+        service_pids()?
+    } else if options.service.is_none() {
+        service_pids().unwrap_or_else(|err| {
+            log::info!("Listing the services without their pids: {err}");
+            HashMap::new()
+        })
+    } else {
+        HashMap::new()
+    };
+    let mut out = std::io::stdout().lock();
+
+    if let Some(name) = &options.service {
+        if options.pid {
+            return print_pid(&pids, name, &mut out);
+        }
+        dump_service(name, &options.args, options.timeout, &mut out)?;
+        return Ok(());
+    }
+
+    let mut services = hub::list_services(options.dump_flags());
+    services.sort();
+    write_list(&mut out, &services, &options.skip, &pids)?;
+    if options.list {
+        return Ok(());
+    }
+
+    let args = options.dump_args();
+    for name in options.dumped(&services) {
+        write_heading(&mut out, options.priority.as_deref(), name)?;
+        if options.pid {
+            print_pid(&pids, name, &mut out)?;
+            continue;
+        }
+        if let Some(elapsed) = dump_service(name, &args, options.timeout, &mut out)? {
+            write_duration(&mut out, name, elapsed)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options> {
+        let matches = command()
+            .try_get_matches_from(std::iter::once("rsb_dumpsys").chain(args.iter().copied()))?;
+        Options::from_matches(&matches)
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| (*s).to_owned()).collect()
+    }
+
+    #[test]
+    fn parses_defaults() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(
+            options,
+            Options {
+                device: "binder".to_owned(),
+                list: false,
+                priority: None,
+                proto: false,
+                pid: false,
+                skip: Vec::new(),
+                timeout: Duration::from_secs(10),
+                service: None,
+                args: Vec::new(),
+            }
+        );
+        assert_eq!(options.dump_flags(), hub::DUMP_FLAG_PRIORITY_ALL);
+        assert!(options.dump_args().is_empty());
+    }
+
+    #[test]
+    fn parses_a_service_and_its_arguments() {
+        let options = parse(&["-d", "vndbinder", "my.service", "--verbose", "-a", "x"]).unwrap();
+        assert_eq!(options.device, "vndbinder");
+        assert_eq!(options.service.as_deref(), Some("my.service"));
+        assert_eq!(options.args, strings(&["--verbose", "-a", "x"]));
+    }
+
+    #[test]
+    fn parses_timeouts() {
+        assert_eq!(parse(&["-t", "3"]).unwrap().timeout, Duration::from_secs(3));
+        assert_eq!(
+            parse(&["-T", "250"]).unwrap().timeout,
+            Duration::from_millis(250)
+        );
+        assert!(parse(&["-t", "3", "-T", "250"]).is_err());
+        assert!(parse(&["-t", "soon"]).is_err());
+    }
+
+    #[test]
+    fn rejects_conflicting_arguments() {
+        assert!(parse(&["-l", "my.service"]).is_err());
+        assert!(parse(&["--skip", "a", "my.service"]).is_err());
+        assert!(parse(&["--priority", "LOW"]).is_err());
+    }
+
+    #[test]
+    fn priority_selects_flags_and_arguments() {
+        let options = parse(&["--priority", "CRITICAL"]).unwrap();
+        assert_eq!(options.dump_flags(), hub::DUMP_FLAG_PRIORITY_CRITICAL);
+        assert_eq!(
+            options.dump_args(),
+            strings(&["--dump-priority", "CRITICAL"])
+        );
+
+        let options = parse(&["--priority", "NORMAL", "--proto"]).unwrap();
+        assert_eq!(
+            options.dump_flags(),
+            hub::DUMP_FLAG_PRIORITY_NORMAL | hub::DUMP_FLAG_PROTO
+        );
+        assert_eq!(
+            options.dump_args(),
+            strings(&["--dump-priority", "NORMAL", "--proto"])
+        );
+
+        let options = parse(&["--proto"]).unwrap();
+        assert_eq!(
+            options.dump_flags(),
+            hub::DUMP_FLAG_PRIORITY_ALL | hub::DUMP_FLAG_PROTO
+        );
+        assert_eq!(options.dump_args(), strings(&["--proto"]));
+    }
+
+    #[test]
+    fn skip_leaves_services_out() {
+        let options = parse(&["--skip", "b,d"]).unwrap();
+        assert_eq!(options.skip, strings(&["b", "d"]));
+        let services = strings(&["a", "b", "c", "d"]);
+        assert_eq!(options.dumped(&services).collect::<Vec<_>>(), ["a", "c"]);
+    }
+
+    #[test]
+    fn lists_pids_and_skipped_services() {
+        let services = strings(&["a", "b", "c"]);
+        let pids = HashMap::from([("a".to_owned(), 12), ("b".to_owned(), 34)]);
+        let mut out = Vec::new();
+        write_list(&mut out, &services, &strings(&["b"]), &pids).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Currently running services:\n  a (pid 12)\n  b (pid 34) (skipped)\n  c\n"
+        );
+    }
+
+    #[test]
+    fn formats_dump_sections() {
+        let mut out = Vec::new();
+        write_heading(&mut out, Some("HIGH"), "a").unwrap();
+        write_duration(&mut out, "a", Duration::from_millis(1234)).unwrap();
+        write_heading(&mut out, None, "b").unwrap();
+        write_timed_out(&mut out, "b", Duration::from_millis(500)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{SEPARATOR}\nDUMP OF SERVICE HIGH a:\n\
+                 --------- 1.234s was the duration of dumpsys a\n\
+                 {SEPARATOR}\nDUMP OF SERVICE b:\n\
+                 \n*** SERVICE 'b' DUMP TIMEOUT (500ms) EXPIRED ***\n\n"
+            )
+        );
+    }
+
+    #[test]
+    fn copies_a_dump_until_the_writer_closes() {
+        let (read_end, write_end) = rustix::pipe::pipe_with(PipeFlags::CLOEXEC).unwrap();
+        let mut writer = std::fs::File::from(write_end);
+        writer.write_all(b"state\n").unwrap();
+        drop(writer);
+        let mut out = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert!(copy_until(&mut std::fs::File::from(read_end), deadline, &mut out).unwrap());
+        assert_eq!(out, b"state\n");
+    }
+
+    #[test]
+    fn gives_up_on_a_dump_at_the_deadline() {
+        let (read_end, write_end) = rustix::pipe::pipe_with(PipeFlags::CLOEXEC).unwrap();
+        let mut writer = std::fs::File::from(write_end);
+        writer.write_all(b"partial").unwrap();
+        let mut out = Vec::new();
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(100);
+        assert!(!copy_until(&mut std::fs::File::from(read_end), deadline, &mut out).unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(out, b"partial", "what came before the deadline is kept");
+        drop(writer);
+    }
+}
@@ -0,0 +1,271 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! On-demand service launching: the Linux counterpart of init's
+//! `ctl.interface_start`, which AOSP's servicemanager asks for when
+//! `getService` misses a lazy service.
+//!
+//! Launch entries come from the `*.toml` files of a directory, one
+//! `[[service]]` table per executable, listing the service names it
+//! registers like an init `interface` line:
+//!
+//! ```toml
+//! [[service]]
+//! names = ["com.example.IStorage/default"]
+//! exec = "/usr/libexec/storaged"
+//! args = ["--lazy"]
+//! uid = 1000                         # optional; gid defaults to uid
+//! env = { RUST_LOG = "info" }        # added to rsb_hub's environment
+//! ```
+//!
+//! A lookup of an absent name starts its executable unless it is already
+//! running. The caller gets no binder yet; `wait_for_service` callers are
+//! woken by their registration callback once the service calls
+//! `addService`. Together with `LazyServiceRegistrar`, which exits the
+//! process when it has no clients, this makes fully lazy daemons.
+
+use std::collections::{BTreeMap, HashMap};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Minimum time between two launches of the same executable, so a daemon
+/// that exits before registering is not restarted on every lookup (clients
+/// waiting for it re-poll every second).
+const LAUNCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    names: Vec<String>,
+    exec: PathBuf,
+    #[serde(default)]
+    args: Vec<String>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LaunchFile {
+    #[serde(default)]
+    service: Vec<Entry>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Pid of the running process started from the entry.
+    running: Option<u32>,
+    last_launch: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Launchable {
+    entry: Entry,
+    state: Mutex<State>,
+}
+
+/// The launch entries of the `--launch-dir` directories.
+#[derive(Debug, Default)]
+pub(crate) struct Launcher {
+    by_name: HashMap<String, Arc<Launchable>>,
+}
+
+impl Launcher {
+    /// Load every `*.toml` file of `dir`, in file name order.
+    pub(crate) fn load_dir(&mut self, dir: &Path) -> Result<()> {
+        let mut paths = std::fs::read_dir(dir)
+            .map_err(|e| format!("cannot read launch directory {}: {e}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|e| format!("cannot read launch directory {}: {e}", dir.display()))?;
+        paths.sort();
+
+        for path in paths {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+                log::debug!("skipping {}: not a launch file", path.display());
+                continue;
+            }
+            let text =
+                std::fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+            self.add(&text)
+                .map_err(|e| format!("{}: {e}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, text: &str) -> Result<()> {
+        let file: LaunchFile = toml::from_str(text)?;
+        for entry in file.service {
+            if entry.names.is_empty() {
+                return Err(format!("{} registers no service name", entry.exec.display()).into());
+            }
+            if !entry.exec.is_absolute() {
+                return Err(
+                    format!("exec must be an absolute path: {}", entry.exec.display()).into(),
+                );
+            }
+            if entry.gid.is_some() && entry.uid.is_none() {
+                return Err(format!("{}: gid without uid", entry.exec.display()).into());
+            }
+            let launchable = Arc::new(Launchable {
+                entry,
+                state: Mutex::new(State::default()),
+            });
+            for name in &launchable.entry.names {
+                if self
+                    .by_name
+                    .insert(name.clone(), Arc::clone(&launchable))
+                    .is_some()
+                {
+                    return Err(format!("{name} has more than one launch entry").into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of launchable service names.
+    pub(crate) fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    /// Start the executable registering `name`, unless it is running or was
+    /// launched less than [`LAUNCH_INTERVAL`] ago. Returns whether `name`
+    /// has a launch entry.
+    pub(crate) fn start(&self, name: &str) -> bool {
+        let Some(launchable) = self.by_name.get(name) else {
+            return false;
+        };
+        let mut state = launchable.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(pid) = state.running {
+            log::debug!("{name} is starting in pid {pid}");
+            return true;
+        }
+        if state
+            .last_launch
+            .is_some_and(|last| last.elapsed() < LAUNCH_INTERVAL)
+        {
+            log::debug!("{name} was launched less than {LAUNCH_INTERVAL:?} ago");
+            return true;
+        }
+
+        let entry = &launchable.entry;
+        let mut command = std::process::Command::new(&entry.exec);
+        command
+            .args(&entry.args)
+            .envs(&entry.env)
+            .stdin(std::process::Stdio::null());
+        if let Some(uid) = entry.uid {
+            command.uid(uid).gid(entry.gid.unwrap_or(uid));
+        }
+
+        state.last_launch = Some(Instant::now());
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                log::error!("failed to launch {} for {name}: {e}", entry.exec.display());
+                return true;
+            }
+        };
+        let pid = child.id();
+        state.running = Some(pid);
+        log::info!("launched {} (pid {pid}) for {name}", entry.exec.display());
+
+        // Reap the process and allow the next launch once it exits.
+        let reaped = Arc::clone(launchable);
+        let spawn_result = std::thread::Builder::new()
+            .name(format!("rsb_hub:{pid}"))
+            .spawn(move || {
+                match child.wait() {
+                    Ok(status) => log::info!(
+                        "{} (pid {pid}) exited: {status}",
+                        reaped.entry.exec.display()
+                    ),
+                    Err(e) => log::error!("failed to wait for pid {pid}: {e}"),
+                }
+                reaped
+                    .state
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .running = None;
+            });
+        if let Err(e) = spawn_result {
+            // The process runs unreaped and is never launched again; better
+            // than a second copy racing to register the same names.
+            log::error!("Failed to spawn reaper thread for pid {pid}: {e}");
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_until(mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn launches_once_while_running() {
+        let out = std::env::temp_dir().join(format!("rsb_hub-launch-{}", std::process::id()));
+        let _ = std::fs::remove_file(&out);
+        let mut launcher = Launcher::default();
+        launcher
+            .add(&format!(
+                r#"
+[[service]]
+names = ["com.example.IFoo/default", "com.example.IBar/default"]
+exec = "/bin/sh"
+args = ["-c", "echo $RSB_HUB_TEST >> {}; sleep 0.5"]
+env = {{ RSB_HUB_TEST = "started" }}
+"#,
+                out.display()
+            ))
+            .unwrap();
+        assert_eq!(launcher.len(), 2);
+
+        assert!(launcher.start("com.example.IFoo/default"));
+        // Running: another name of the same executable does not launch it
+        // again.
+        assert!(launcher.start("com.example.IBar/default"));
+        assert!(!launcher.start("com.example.IOther/default"));
+
+        let launchable = &launcher.by_name["com.example.IFoo/default"];
+        wait_until(|| launchable.state.lock().unwrap().running.is_none());
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "started\n");
+
+        // Exited: the next lookup after the interval launches it again.
+        std::thread::sleep(LAUNCH_INTERVAL);
+        assert!(launcher.start("com.example.IBar/default"));
+        wait_until(|| launchable.state.lock().unwrap().running.is_none());
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "started\nstarted\n");
+        std::fs::remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        for text in [
+            "[[service]]\nnames = []\nexec = \"/bin/true\"\n",
+            "[[service]]\nnames = [\"a\"]\nexec = \"true\"\n",
+            "[[service]]\nnames = [\"a\"]\nexec = \"/bin/true\"\ngid = 1\n",
+            "[[service]]\nnames = [\"a\"]\nexec = \"/bin/true\"\nuser = \"root\"\n",
+            "[[service]]\nnames = [\"a\"]\nexec = \"/bin/true\"\n\
+             [[service]]\nnames = [\"a\"]\nexec = \"/bin/false\"\n",
+        ] {
+            assert!(Launcher::default().add(text).is_err(), "{text}");
+        }
+    }
+}
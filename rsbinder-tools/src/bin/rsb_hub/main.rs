@@ -2,12 +2,20 @@
 // SPDX-License-Identifier: Apache-2.0
 #![allow(non_snake_case)]
 
+mod launcher;
+mod manifest;
+mod policy;
+
 use env_logger::Env;
 use hub::android_16::{BnServiceManager, IServiceManager, DUMP_FLAG_PRIORITY_DEFAULT};
+use launcher::Launcher;
+use manifest::{Connection, Manifest};
+use policy::{Action, Caller, Policy};
 use rsbinder::*;
 use std::{
     collections::HashMap,
-    sync::{mpsc, Arc, Mutex},
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex, RwLock, RwLockReadGuard},
     time::Duration,
 };
 
@@ -350,7 +358,6 @@ impl Inner {
     fn try_get_binder(
         &mut self,
         name: &str,
-        _start_if_not_found: bool,
         pending: &mut Vec<PendingCallback>,
     ) -> rsbinder::status::Result<Option<(SIBinder, bool)>> {
         let service = if let Some(service) = self.name_to_service.get_mut(name) {
@@ -423,15 +430,31 @@ struct ServiceManager {
     /// service-name hijack. `--allow-cross-uid-overwrite` sets it `true`
     /// for deployments that intentionally re-register across UIDs.
     allow_cross_uid_overwrite: bool,
+    /// Instances declared by the `--manifest-dir` manifests; answers
+    /// `isDeclared`, `getDeclaredInstances` and `getConnectionInfo`.
+    manifest: Manifest,
+    /// The `--policy` file, reloaded on SIGHUP. `None` allows every
+    /// caller to add, find and list every service.
+    policy: Option<Arc<RwLock<Policy>>>,
+    /// The `--launch-dir` entries, started by `getService` misses.
+    launcher: Launcher,
 }
 
 impl ServiceManager {
-    fn new(allow_cross_uid_overwrite: bool) -> Self {
+    fn new(
+        allow_cross_uid_overwrite: bool,
+        manifest: Manifest,
+        policy: Option<Arc<RwLock<Policy>>>,
+        launcher: Launcher,
+    ) -> Self {
         let (death_sender, death_receiver) = mpsc::channel();
 
         let this = Self {
             inner: Arc::new(Mutex::new(Inner::new(death_sender))),
             allow_cross_uid_overwrite,
+            manifest,
+            policy,
+            launcher,
         };
 
         this.run_death_receiver(death_receiver);
@@ -575,10 +598,102 @@ impl ServiceManager {
     ) -> bool {
         !allow_cross_uid_overwrite && !same_binder && existing_uid != caller_uid
     }
+
+    /// Look up `name` for a client, firing the client callbacks the lookup
+    /// queues. A miss with `start_if_not_found` (the `getService`s, not the
+    /// `checkService`s) launches the service from its `--launch-dir`
+    /// entry, as AOSP's `tryGetBinder` asks init to
+    /// (`ctl.interface_start`). The caller still gets `None`; a client
+    /// waiting for the service is woken by its registration callback.
+    fn lookup(
+        &self,
+        name: &str,
+        start_if_not_found: bool,
+    ) -> rsbinder::status::Result<Option<(SIBinder, bool)>> {
+        let mut pending = Vec::new();
+        let lookup = {
+            let mut inner = lock_recover(&self.inner);
+            inner.try_get_binder(name, &mut pending)?
+        };
+        fire_pending(pending);
+        if lookup.is_none() && start_if_not_found && !self.launcher.start(name) {
+            log::debug!("{name} is not registered and has no launch entry");
+        }
+        Ok(lookup)
+    }
+
+    /// The policy to enforce on the current caller, with the caller's
+    /// identity. `None` when everything is allowed: without `--policy`,
+    /// and outside a transaction, i.e. for the calls rsb_hub makes on
+    /// itself (registering `manager` at startup). See the
+    /// [`policy`] module docs.
+    fn caller_policy(&self) -> Option<(RwLockReadGuard<'_, Policy>, Caller)> {
+        let policy = self
+            .policy
+            .as_ref()?
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        if !rsbinder::is_handling_transaction() {
+            return None;
+        }
+        let context = rsbinder::thread_state::CallingContext::default();
+        let caller = Caller {
+            uid: context.uid,
+            gids: if policy.uses_gids() {
+                Caller::gids_of(context.pid)
+            } else {
+                Vec::new()
+            },
+            sid: context.sid.map(|sid| sid.to_string_lossy().into_owned()),
+        };
+        Some((policy, caller))
+    }
+
+    /// Enforce the `--policy` file on `action` of `name` by the current
+    /// caller, logging a denial with the caller's identity.
+    fn check_access(&self, action: Action, name: &str) -> rsbinder::status::Result<()> {
+        let Some((policy, caller)) = self.caller_policy() else {
+            return Ok(());
+        };
+        if policy.allows(action, name, &caller) {
+            return Ok(());
+        }
+        Err(denied(action, name))
+    }
+
+    /// Keep the names the current caller may list.
+    fn filter_listable<'a>(&self, names: impl Iterator<Item = &'a String>) -> Vec<&'a String> {
+        let Some((policy, caller)) = self.caller_policy() else {
+            return names.collect();
+        };
+        names
+            .filter(|name| {
+                let allowed = policy.allows(Action::List, name, &caller);
+                if !allowed {
+                    log::debug!("hiding '{name}' from uid={}", caller.uid);
+                }
+                allowed
+            })
+            .collect()
+    }
 }
 
 impl Interface for ServiceManager {}
 
+/// Log a `--policy` denial of `action` of `name` to the current caller,
+/// with the caller's identity, and make the `Security` error returned
+/// for it.
+fn denied(action: Action, name: &str) -> Status {
+    log::warn!(
+        "denied {action} of '{name}' to uid={} pid={} sid={:?}",
+        rsbinder::thread_state::get_calling_uid(),
+        rsbinder::thread_state::get_calling_pid(),
+        rsbinder::thread_state::get_calling_sid()
+    );
+    let msg = format!("{action} of '{name}' denied by policy");
+    (ExceptionCode::Security, msg.as_str()).into()
+}
+
 /// Convert a `Inner::try_get_binder` lookup result
 /// into the `Service` union arm shape returned by
 /// `getService2`/`checkService2`. Routes `is_accessor=true`
@@ -615,42 +730,29 @@ fn classify_for_service_union(
 }
 
 impl IServiceManager for ServiceManager {
-    /// Linux note: this is semantically equivalent to
-    /// [`checkService`](Self::checkService). AOSP's servicemanager
-    /// distinguishes the two — `getService` calls `tryGetBinder(name,
-    /// /*startIfNotFound=*/true)` and triggers a lazy-service start via
-    /// `ctl.interface_start_<name>` init property — but lazy service
-    /// activation depends on Android's init system and has no
-    /// equivalent on a plain Linux host. The `start_if_not_found`
-    /// argument is therefore hardcoded to `false`. Accessor routing for
+    /// Like AOSP's servicemanager, which calls `tryGetBinder(name,
+    /// /*startIfNotFound=*/true)` and has init start a lazy service via
+    /// `ctl.interface_start_<name>`, a miss starts the service from its
+    /// `--launch-dir` entry (see [`ServiceManager::lookup`]); otherwise
+    /// this is [`checkService`](Self::checkService). Accessor routing for
     /// callers that want it lives in
     /// [`getService2`](Self::getService2)/[`checkService2`](Self::checkService2).
     fn getService(&self, name: &str) -> rsbinder::status::Result<Option<rsbinder::SIBinder>> {
-        let mut pending = Vec::new();
-        let result = {
-            let mut inner = lock_recover(&self.inner);
-            inner
-                .try_get_binder(name, false, &mut pending)?
-                .map(|(b, _)| b)
-        };
-        fire_pending(pending);
-        Ok(result)
+        self.check_access(Action::Find, name)?;
+        Ok(self.lookup(name, true)?.map(|(b, _)| b))
     }
 
-    /// Security note (Linux): unlike AOSP's `servicemanager`, this
-    /// implementation performs **no add-time access control**. AOSP
-    /// rejects app UIDs (`multiuser_get_app_id(uid) >= AID_APP`) and
-    /// runs the SELinux `canAddService` hook; both depend on Android's
-    /// UID model / policy and have no equivalent on a plain Linux host.
-    /// Consequently any client can register, or silently overwrite, any
-    /// service name — and a binder that reports the
-    /// `android.os.IAccessor` descriptor is trusted as an accessor on
-    /// the registrant's word alone (AOSP instead derives the accessor
-    /// relationship from a signed VINTF `<accessor>` manifest entry).
-    /// As a minimal mitigation we log a loud warning when a registration
-    /// overwrites an entry owned by a different uid/pid (the signature of
-    /// a hijack). Vendors needing real enforcement must wrap this with
-    /// their own authorization layer.
+    /// Security note (Linux): AOSP rejects app UIDs
+    /// (`multiuser_get_app_id(uid) >= AID_APP`) and runs the SELinux
+    /// `canAddService` hook; the Linux equivalent is the `--policy` file's
+    /// `add` rules. Without one, any client can register any service name
+    /// — and a binder that reports the `android.os.IAccessor` descriptor
+    /// is trusted as an accessor on the registrant's word alone (AOSP
+    /// instead derives the accessor relationship from a signed VINTF
+    /// `<accessor>` manifest entry). Independently of the policy, a
+    /// registration that would overwrite an entry owned by a different
+    /// uid (the signature of a hijack) is rejected unless
+    /// `--allow-cross-uid-overwrite` is set.
     fn addService(
         &self,
         name: &str,
@@ -661,6 +763,7 @@ impl IServiceManager for ServiceManager {
         if !Self::is_valid_service_name(name) {
             return Err(ExceptionCode::IllegalArgument.into());
         }
+        self.check_access(Action::Add, name)?;
 
         // Detect `IAccessor` binders by interface descriptor at registration.
         // Hardcoding the AOSP-stable `android.os.IAccessor` string (instead of
@@ -830,34 +933,23 @@ impl IServiceManager for ServiceManager {
         fire_pending_propagate(reg_pending)
     }
 
-    /// Linux note: identical to [`getService`](Self::getService) —
-    /// `start_if_not_found` is always `false` on this implementation
-    /// (no lazy-service infrastructure). See `getService`'s rustdoc for
-    /// the rationale.
+    /// [`getService`](Self::getService) without starting an absent
+    /// service.
     fn checkService(&self, name: &str) -> rsbinder::status::Result<Option<SIBinder>> {
-        let mut pending = Vec::new();
-        let result = {
-            let mut inner = lock_recover(&self.inner);
-            inner
-                .try_get_binder(name, false, &mut pending)?
-                .map(|(b, _)| b)
-        };
-        fire_pending(pending);
-        Ok(result)
+        self.check_access(Action::Find, name)?;
+        Ok(self.lookup(name, false)?.map(|(b, _)| b))
     }
 
     fn listServices(&self, dump_priority: i32) -> rsbinder::status::Result<Vec<String>> {
         let inner = lock_recover(&self.inner);
 
-        let mut services = Vec::new();
-
-        for (name, service) in inner.name_to_service.iter() {
-            if (service.dump_priority & dump_priority) != 0 {
-                services.push(name.clone());
-            }
-        }
+        let names = inner
+            .name_to_service
+            .iter()
+            .filter(|(_, service)| (service.dump_priority & dump_priority) != 0)
+            .map(|(name, _)| name);
 
-        Ok(services)
+        Ok(self.filter_listable(names).into_iter().cloned().collect())
     }
 
     fn registerForNotifications(
@@ -870,6 +962,8 @@ impl IServiceManager for ServiceManager {
         if !Self::is_valid_service_name(name) {
             return Err(ExceptionCode::IllegalArgument.into());
         }
+        // The callback is handed the binder, so it needs find access.
+        self.check_access(Action::Find, name)?;
 
         let mut pending = Vec::new();
         {
@@ -964,26 +1058,40 @@ impl IServiceManager for ServiceManager {
         }
     }
 
-    /// rsb_hub has no static service declaration system on Linux —
-    /// AOSP's servicemanager answers this from VINTF manifests
-    /// (`/system/etc/vintf/...`, `/system_ext/etc/vintf/...`), which
-    /// are an Android-specific build artifact with no equivalent on a
-    /// plain Linux host. Returning `false` is the truthful answer:
-    /// "no, this name has no pre-declared availability — fall back to
-    /// dynamic lookup via `getService`/`checkService`". Vendors that
-    /// need VINTF-equivalent declaration semantics should layer that
-    /// on top in their own service-manager (or run rsb_hub on a host
-    /// that ships VINTF files plus a parser, which is out of scope
-    /// here).
-    fn isDeclared(&self, _arg_name: &str) -> rsbinder::status::Result<bool> {
-        Ok(false)
+    /// Answered from the `--manifest-dir` manifests, as AOSP's
+    /// servicemanager answers it from the VINTF manifests
+    /// (`/system/etc/vintf/...`, `/vendor/etc/vintf/...`). Without
+    /// manifests every name is undeclared: "no pre-declared
+    /// availability — fall back to dynamic lookup via
+    /// `getService`/`checkService`".
+    ///
+    /// Needs `find` on `name`, as in AOSP.
+    fn isDeclared(&self, name: &str) -> rsbinder::status::Result<bool> {
+        self.check_access(Action::Find, name)?;
+        Ok(self.manifest.is_declared(name))
     }
 
-    /// See [`isDeclared`](Self::isDeclared) — same VINTF-on-Linux
-    /// rationale. An empty `Vec` reports "no declared instances for
-    /// this interface" which on a VINTF-free system is always true.
-    fn getDeclaredInstances(&self, _arg_iface: &str) -> rsbinder::status::Result<Vec<String>> {
-        Ok(vec![])
+    /// The instances the manifests declare for `iface`
+    /// (`package.IFoo`), e.g. `["default"]`. See
+    /// [`isDeclared`](Self::isDeclared).
+    ///
+    /// Only the instances the caller may `find` (as `iface/instance`) are
+    /// listed; if it may find none of several, the call is denied, as in
+    /// AOSP.
+    fn getDeclaredInstances(&self, iface: &str) -> rsbinder::status::Result<Vec<String>> {
+        let declared = self.manifest.instances(iface);
+        let Some((policy, caller)) = self.caller_policy() else {
+            return Ok(declared);
+        };
+        let allowed: Vec<String> = declared
+            .iter()
+            .filter(|instance| policy.allows(Action::Find, &format!("{iface}/{instance}"), &caller))
+            .cloned()
+            .collect();
+        if allowed.is_empty() && !declared.is_empty() {
+            return Err(denied(Action::Find, &format!("{iface}/*")));
+        }
+        Ok(allowed)
     }
 
     /// APEX (Android Pony EXpress) is an Android-only packaging
@@ -996,22 +1104,42 @@ impl IServiceManager for ServiceManager {
         Ok(None)
     }
 
-    /// AOSP's servicemanager surfaces the VINTF `<ip>`+`<port>` of an
-    /// AIDL service for inet-style RPC accessor connection info (see
-    /// `getVintfConnectionInfo` in `frameworks/native/cmds/servicemanager/
-    /// ServiceManager.cpp`). rsb_hub has no VINTF infrastructure on
-    /// Linux, so the only honest reply is `None` — callers should
-    /// either go through an `IAccessor` they obtained out-of-band
-    /// (e.g., via the consume-side accessor arm of `getService2` +
-    /// process-local `add_accessor_provider`) or fall back to a
-    /// vendor-supplied lookup. Same design choice as [`isDeclared`](Self::isDeclared).
+    /// The `inet` transport a manifest declares for `name`, as AOSP's
+    /// servicemanager surfaces the VINTF `ip`+`port` of an AIDL service
+    /// for inet-style RPC (see `getVintfConnectionInfo` in
+    /// `frameworks/native/cmds/servicemanager/ServiceManager.cpp`).
+    /// `ConnectionInfo` carries an IP address and port only, so a `unix`
+    /// transport is reported as `None`, like an undeclared name or one
+    /// served over kernel binder — those callers go through an
+    /// `IAccessor` they obtained out-of-band (e.g., via the consume-side
+    /// accessor arm of `getService2` + process-local
+    /// `add_accessor_provider`) or a vendor-supplied lookup.
+    ///
+    /// Needs `find` on `name`, as in AOSP.
     fn getConnectionInfo(
         &self,
-        _arg_name: &str,
+        name: &str,
     ) -> rsbinder::status::Result<
         Option<hub::android_16::android::os::ConnectionInfo::ConnectionInfo>,
     > {
-        Ok(None)
+        self.check_access(Action::Find, name)?;
+        match self.manifest.connection(name) {
+            Some(Connection::Inet { ip, port }) => Ok(Some(
+                hub::android_16::android::os::ConnectionInfo::ConnectionInfo {
+                    ipAddress: ip.clone(),
+                    port: i32::from(*port),
+                },
+            )),
+            Some(Connection::Unix { path }) => {
+                log::debug!(
+                    "getConnectionInfo: {name} is declared on unix socket {}, which \
+                     ConnectionInfo cannot carry",
+                    path.display()
+                );
+                Ok(None)
+            }
+            None => Ok(None),
+        }
     }
 
     fn registerClientCallback(
@@ -1228,11 +1356,11 @@ impl IServiceManager for ServiceManager {
 
         let mut out = Vec::with_capacity(inner.name_to_service.len());
 
-        for (name, service) in inner.name_to_service.iter() {
+        for name in self.filter_listable(inner.name_to_service.keys()) {
             out.push(
                 hub::android_16::android::os::ServiceDebugInfo::ServiceDebugInfo {
                     name: name.clone(),
-                    debugPid: service.context.pid,
+                    debugPid: inner.name_to_service[name].context.pid,
                 },
             );
         }
@@ -1247,13 +1375,8 @@ impl IServiceManager for ServiceManager {
         // Routing logic lives in `classify_for_service_union` so
         // `checkService2` stays byte-identical without re-stating the
         // match arms.
-        let mut pending = Vec::new();
-        let lookup = {
-            let mut inner = lock_recover(&self.inner);
-            inner.try_get_binder(name, false, &mut pending)?
-        };
-        fire_pending(pending);
-        Ok(classify_for_service_union(lookup))
+        self.check_access(Action::Find, name)?;
+        Ok(classify_for_service_union(self.lookup(name, true)?))
     }
 
     fn checkService2(
@@ -1262,13 +1385,8 @@ impl IServiceManager for ServiceManager {
     ) -> rsbinder::status::Result<hub::android_16::android::os::Service::Service> {
         // See `getService2` — both route through
         // `classify_for_service_union`.
-        let mut pending = Vec::new();
-        let lookup = {
-            let mut inner = lock_recover(&self.inner);
-            inner.try_get_binder(name, false, &mut pending)?
-        };
-        fire_pending(pending);
-        Ok(classify_for_service_union(lookup))
+        self.check_access(Action::Find, name)?;
+        Ok(classify_for_service_union(self.lookup(name, false)?))
     }
 
     /// See [`updatableViaApex`](Self::updatableViaApex) — same
@@ -1280,6 +1398,71 @@ impl IServiceManager for ServiceManager {
     }
 }
 
+fn sighup_set() -> libc::sigset_t {
+    // SAFETY: `sigemptyset` initializes the zeroed set before `sigaddset`
+    // adds a valid signal to it.
+    unsafe {
+        let mut set = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, libc::SIGHUP);
+        set
+    }
+}
+
+/// Block SIGHUP in the calling thread, and so in the threads it spawns.
+fn block_sighup() -> std::io::Result<()> {
+    let set = sighup_set();
+    // SAFETY: `set` is an initialized signal set; the old mask is not
+    // requested.
+    match unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) } {
+        0 => Ok(()),
+        err => Err(std::io::Error::from_raw_os_error(err)),
+    }
+}
+
+/// Reload the policy file at `path` into `policy` on every SIGHUP. A file
+/// that no longer loads is reported and the previous policy kept.
+/// `requesting_sid` tells whether callers' SELinux contexts were requested
+/// at startup; a reload cannot change that.
+fn run_policy_reloader(path: PathBuf, policy: Arc<RwLock<Policy>>, requesting_sid: bool) {
+    let spawn_result = std::thread::Builder::new()
+        .name("rsb_hub:policy".to_owned())
+        .spawn(move || {
+            let set = sighup_set();
+            loop {
+                let mut signal = 0;
+                // SAFETY: `set` is an initialized signal set and `signal`
+                // a valid out-pointer.
+                let err = unsafe { libc::sigwait(&set, &mut signal) };
+                if err != 0 {
+                    log::error!(
+                        "policy reloader exiting: sigwait failed: {}",
+                        std::io::Error::from_raw_os_error(err)
+                    );
+                    return;
+                }
+                match Policy::load(&path) {
+                    Ok(reloaded) => {
+                        if reloaded.uses_contexts() && !requesting_sid {
+                            log::warn!(
+                                "policy {} now grants by SELinux context, but callers' \
+                                 contexts are only requested if the policy rsb_hub starts \
+                                 with does; restart rsb_hub to enforce those rules",
+                                path.display()
+                            );
+                        }
+                        *policy.write().unwrap_or_else(|e| e.into_inner()) = reloaded;
+                        log::info!("reloaded policy {}", path.display());
+                    }
+                    Err(e) => log::error!("keeping the previous policy: {e}"),
+                }
+            }
+        });
+    if let Err(e) = spawn_result {
+        log::error!("Failed to spawn policy reloader thread: {e}");
+    }
+}
+
 fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let matches = clap::Command::new("rsb_hub")
         .version(env!("CARGO_PKG_VERSION"))
@@ -1304,6 +1487,45 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
                      this flag is set.",
                 ),
         )
+        .arg(
+            clap::Arg::new("manifest-dir")
+                .short('m')
+                .long("manifest-dir")
+                .value_name("DIR")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(clap::ArgAction::Append)
+                .help(
+                    "Directory of VINTF-style manifests (*.xml) or their TOML \
+                     equivalent (*.toml) declaring AIDL instances. Answers \
+                     isDeclared, getDeclaredInstances and getConnectionInfo. \
+                     May be given more than once.",
+                ),
+        )
+        .arg(
+            clap::Arg::new("policy")
+                .short('p')
+                .long("policy")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help(
+                    "TOML access policy mapping service-name patterns to the uids, \
+                     gids and SELinux contexts allowed to add, find and list them. \
+                     Reloaded on SIGHUP. Without it every caller may do all three.",
+                ),
+        )
+        .arg(
+            clap::Arg::new("launch-dir")
+                .short('l')
+                .long("launch-dir")
+                .value_name("DIR")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(clap::ArgAction::Append)
+                .help(
+                    "Directory of *.toml launch entries mapping service names to an \
+                     executable, arguments, uid and environment. getService starts \
+                     an absent service from its entry. May be given more than once.",
+                ),
+        )
         .after_help(
             "Examples:\n    \
             Run with the default binder device:\n    \
@@ -1311,6 +1533,12 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
             Run with a custom binder device:\n    \
             $ rsb_hub --device mybinder\n    \
             $ rsb_hub -d mybinder\n\n    \
+            Declare the instances listed in /etc/rsbinder/manifest:\n    \
+            $ rsb_hub --manifest-dir /etc/rsbinder/manifest\n\n    \
+            Enforce an access policy (reload it with `kill -HUP`):\n    \
+            $ rsb_hub --policy /etc/rsbinder/policy.toml\n\n    \
+            Start lazy services on demand:\n    \
+            $ rsb_hub --launch-dir /etc/rsbinder/launch\n\n    \
             Note: The binder device must be created first using rsb_device.",
         )
         .get_matches();
@@ -1329,12 +1557,57 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         );
     }
 
+    let mut manifest = Manifest::default();
+    for dir in matches
+        .get_many::<PathBuf>("manifest-dir")
+        .into_iter()
+        .flatten()
+    {
+        manifest.load_dir(dir)?;
+    }
+    log::info!("{} declared instances", manifest.len());
+
+    let mut launcher = Launcher::default();
+    for dir in matches
+        .get_many::<PathBuf>("launch-dir")
+        .into_iter()
+        .flatten()
+    {
+        launcher.load_dir(dir)?;
+    }
+    log::info!("{} launchable services", launcher.len());
+
+    let policy_path = matches.get_one::<PathBuf>("policy");
+    let policy = match policy_path {
+        Some(path) => Some(Arc::new(RwLock::new(Policy::load(path)?))),
+        None => None,
+    };
+    // Callers' SELinux contexts cost the driver a lookup per transaction,
+    // so they are only requested when the policy grants by context.
+    let requesting_sid = policy.as_ref().is_some_and(|policy| {
+        policy
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .uses_contexts()
+    });
+    if let (Some(path), Some(policy)) = (policy_path, &policy) {
+        // Block SIGHUP before any thread starts so every thread inherits
+        // the mask and only the reloader's `sigwait` receives it.
+        block_sighup()?;
+        run_policy_reloader(path.clone(), Arc::clone(policy), requesting_sid);
+    }
+
     log::info!("Starting rsb_hub with binder device: {}", binder_path);
 
     ProcessState::init(&binder_path, 0)?;
 
     // Create a binder service.
-    let service = BnServiceManager::new_binder(ServiceManager::new(allow_cross_uid_overwrite));
+    let mut features = BinderFeatures::default();
+    features.set_requesting_sid = requesting_sid;
+    let service = BnServiceManager::new_binder_with_features(
+        ServiceManager::new(allow_cross_uid_overwrite, manifest, policy, launcher),
+        features,
+    );
     service.addService(
         "manager",
         &service.as_binder(),
@@ -0,0 +1,473 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Declared service instances, loaded from a directory of manifests. They
+//! answer `isDeclared`, `getDeclaredInstances` and `getConnectionInfo` the
+//! way AOSP's servicemanager answers them from the VINTF manifests.
+//!
+//! Two formats are read, by extension. `*.xml` is the AIDL subset of a
+//! VINTF manifest; other `<hal>` formats and unknown elements are skipped,
+//! so an Android manifest can be used as is:
+//!
+//! ```xml
+//! <manifest version="1.0" type="device">
+//!     <hal format="aidl">
+//!         <name>android.hardware.light</name>
+//!         <version>2</version>
+//!         <fqname>ILights/default</fqname>
+//!     </hal>
+//!     <hal format="aidl">
+//!         <name>com.example.sensor</name>
+//!         <transport ip="127.0.0.1" port="5000">inet</transport>
+//!         <interface>
+//!             <name>ISensor</name>
+//!             <instance>front</instance>
+//!             <instance>rear</instance>
+//!         </interface>
+//!     </hal>
+//! </manifest>
+//! ```
+//!
+//! `*.toml` declares the same with one `[[hal]]` table per `<hal>`:
+//!
+//! ```toml
+//! [[hal]]
+//! name = "com.example.sensor"
+//! version = 1
+//! fqname = ["ISensor/front", "ISensor/rear"]
+//! transport = "unix"
+//! path = "/run/sensor.sock"
+//! ```
+//!
+//! `transport` is `inet` (with `ip` and `port`) or `unix` (with `path`, an
+//! rsbinder extension). Instances are named `<name>.<interface>/<instance>`,
+//! e.g. `android.hardware.light.ILights/default`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Where an instance served over RPC accepts connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Connection {
+    Inet { ip: String, port: u16 },
+    Unix { path: PathBuf },
+}
+
+/// One declared instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Instance {
+    pub(crate) version: u32,
+    pub(crate) connection: Option<Connection>,
+}
+
+/// A `<hal>` entry (or `[[hal]]` table) before it is split into instances.
+struct Hal {
+    name: String,
+    version: u32,
+    fqnames: Vec<String>,
+    connection: Option<Connection>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlManifest {
+    #[serde(default)]
+    hal: Vec<TomlHal>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlHal {
+    name: String,
+    version: Option<u32>,
+    fqname: Vec<String>,
+    transport: Option<String>,
+    ip: Option<String>,
+    port: Option<u16>,
+    path: Option<PathBuf>,
+}
+
+/// All instances declared by the loaded manifests.
+#[derive(Debug, Default)]
+pub(crate) struct Manifest {
+    /// Keyed by full instance name, `<name>.<interface>/<instance>`.
+    instances: BTreeMap<String, Instance>,
+}
+
+impl Manifest {
+    /// Load every `*.xml` and `*.toml` file of `dir`, in file name order.
+    /// Other files are ignored; a file that does not parse is an error.
+    pub(crate) fn load_dir(&mut self, dir: &Path) -> Result<()> {
+        let mut paths = std::fs::read_dir(dir)
+            .map_err(|e| format!("cannot read manifest directory {}: {e}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|e| format!("cannot read manifest directory {}: {e}", dir.display()))?;
+        paths.sort();
+
+        for path in paths {
+            let hals = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("xml") => parse_xml(&read(&path)?),
+                Some("toml") => parse_toml(&read(&path)?),
+                _ => {
+                    log::debug!("skipping {}: not a manifest", path.display());
+                    continue;
+                }
+            }
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+
+            for hal in hals {
+                self.declare(hal)
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn declare(&mut self, hal: Hal) -> Result<()> {
+        for fqname in &hal.fqnames {
+            let name = format!("{}.{fqname}", hal.name);
+            let instance = Instance {
+                version: hal.version,
+                connection: hal.connection.clone(),
+            };
+            match self.instances.get(&name) {
+                Some(existing) if *existing != instance => {
+                    return Err(format!(
+                        "{name} is already declared with another version or transport"
+                    )
+                    .into());
+                }
+                Some(_) => {}
+                None => {
+                    log::info!("declared {name} (version {})", instance.version);
+                    self.instances.insert(name, instance);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of declared instances.
+    pub(crate) fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Whether `name` (`<name>.<interface>/<instance>`) is declared.
+    pub(crate) fn is_declared(&self, name: &str) -> bool {
+        self.instances.contains_key(name)
+    }
+
+    /// The instance names declared for `interface` (`<name>.<interface>`),
+    /// e.g. `["default"]`.
+    pub(crate) fn instances(&self, interface: &str) -> Vec<String> {
+        self.instances
+            .keys()
+            .filter_map(|name| {
+                let (iface, instance) = name.rsplit_once('/')?;
+                (iface == interface).then(|| instance.to_owned())
+            })
+            .collect()
+    }
+
+    /// How to connect to `name` if it is served over RPC.
+    pub(crate) fn connection(&self, name: &str) -> Option<&Connection> {
+        self.instances.get(name)?.connection.as_ref()
+    }
+}
+
+fn read(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()).into())
+}
+
+fn parse_xml(text: &str) -> Result<Vec<Hal>> {
+    let doc = roxmltree::Document::parse(text)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("manifest") {
+        return Err(format!("expected <manifest>, found <{}>", root.tag_name().name()).into());
+    }
+
+    let mut hals = Vec::new();
+    for node in root.children().filter(|n| n.has_tag_name("hal")) {
+        // VINTF defaults `format` to "hidl"; HIDL and native HALs are not
+        // served through this service manager.
+        if node.attribute("format") != Some("aidl") {
+            continue;
+        }
+
+        let mut name = None;
+        let mut version = None;
+        let mut fqnames = Vec::new();
+        let mut transport = None;
+        for child in node.children().filter(|n| n.is_element()) {
+            match child.tag_name().name() {
+                "name" => name = Some(text_of(child)?),
+                "version" => {
+                    let text = text_of(child)?;
+                    version = Some(
+                        text.parse::<u32>()
+                            .map_err(|_| format!("invalid <version> {text:?}"))?,
+                    );
+                }
+                "fqname" => fqnames.push(text_of(child)?),
+                "interface" => {
+                    let mut iface = None;
+                    let mut instances = Vec::new();
+                    for item in child.children().filter(|n| n.is_element()) {
+                        match item.tag_name().name() {
+                            "name" => iface = Some(text_of(item)?),
+                            "instance" => instances.push(text_of(item)?),
+                            _ => {}
+                        }
+                    }
+                    let iface = iface.ok_or("<interface> without <name>")?;
+                    fqnames.extend(instances.into_iter().map(|i| format!("{iface}/{i}")));
+                }
+                "transport" => {
+                    let port = child
+                        .attribute("port")
+                        .map(|port| {
+                            port.parse::<u16>()
+                                .map_err(|_| format!("invalid transport port {port:?}"))
+                        })
+                        .transpose()?;
+                    transport = Some((
+                        text_of(child)?,
+                        child.attribute("ip").map(str::to_owned),
+                        port,
+                        child.attribute("path").map(PathBuf::from),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        let name = name.ok_or("<hal> without <name>")?;
+        let connection = match transport {
+            Some((transport, ip, port, path)) => connection(&transport, ip, port, path)?,
+            None => None,
+        };
+        hals.push(hal(name, version, fqnames, connection)?);
+    }
+    Ok(hals)
+}
+
+fn parse_toml(text: &str) -> Result<Vec<Hal>> {
+    let manifest: TomlManifest = toml::from_str(text)?;
+    manifest
+        .hal
+        .into_iter()
+        .map(|h| {
+            let connection = match h.transport {
+                Some(transport) => connection(&transport, h.ip, h.port, h.path)?,
+                None => None,
+            };
+            hal(h.name, h.version, h.fqname, connection)
+        })
+        .collect()
+}
+
+fn text_of(node: roxmltree::Node<'_, '_>) -> Result<String> {
+    match node.text().map(str::trim) {
+        Some(text) if !text.is_empty() => Ok(text.to_owned()),
+        _ => Err(format!("empty <{}>", node.tag_name().name()).into()),
+    }
+}
+
+/// Validate a `<hal>`'s fields; the version defaults to 1, as in VINTF.
+fn hal(
+    name: String,
+    version: Option<u32>,
+    fqnames: Vec<String>,
+    connection: Option<Connection>,
+) -> Result<Hal> {
+    if name.is_empty() || name.contains('/') {
+        return Err(format!("invalid hal name {name:?}").into());
+    }
+    if fqnames.is_empty() {
+        return Err(format!("{name} declares no instance").into());
+    }
+    for fqname in &fqnames {
+        match fqname.split_once('/') {
+            Some((iface, instance))
+                if !iface.is_empty()
+                    && !instance.is_empty()
+                    && !iface.contains(['@', '.'])
+                    && !instance.contains('/') => {}
+            _ => {
+                return Err(
+                    format!("invalid fqname {fqname:?} for {name}: expected IFoo/instance").into(),
+                )
+            }
+        }
+    }
+    Ok(Hal {
+        name,
+        version: version.unwrap_or(1),
+        fqnames,
+        connection,
+    })
+}
+
+fn connection(
+    transport: &str,
+    ip: Option<String>,
+    port: Option<u16>,
+    path: Option<PathBuf>,
+) -> Result<Option<Connection>> {
+    match (transport, ip, port, path) {
+        ("inet", Some(ip), Some(port), None) => Ok(Some(Connection::Inet { ip, port })),
+        ("unix", None, None, Some(path)) => Ok(Some(Connection::Unix { path })),
+        ("inet", ..) => Err("inet transport needs ip and port (and no path)".into()),
+        ("unix", ..) => Err("unix transport needs path (and no ip or port)".into()),
+        (other, ..) => Err(format!("unsupported transport {other:?}").into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<manifest version="1.0" type="device">
+    <hal format="hidl">
+        <name>android.hardware.old</name>
+        <transport>hwbinder</transport>
+        <fqname>@1.0::IOld/default</fqname>
+    </hal>
+    <hal format="aidl">
+        <name>android.hardware.light</name>
+        <version>2</version>
+        <fqname>ILights/default</fqname>
+    </hal>
+    <hal format="aidl">
+        <name>com.example.sensor</name>
+        <transport ip="127.0.0.1" port="5000">inet</transport>
+        <interface>
+            <name>ISensor</name>
+            <instance>front</instance>
+            <instance>rear</instance>
+        </interface>
+    </hal>
+    <sepolicy><version>202404</version></sepolicy>
+</manifest>"#;
+
+    const TOML: &str = r#"
+[[hal]]
+name = "com.example.storage"
+fqname = ["IStorage/default"]
+transport = "unix"
+path = "/run/storage.sock"
+"#;
+
+    fn load(files: &[(&str, &str)]) -> Result<Manifest> {
+        let dir = std::env::temp_dir().join(format!(
+            "rsb_hub-manifest-{}-{:?}",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        for (name, text) in files {
+            std::fs::write(dir.join(name), text)?;
+        }
+        let mut manifest = Manifest::default();
+        let result = manifest.load_dir(&dir);
+        std::fs::remove_dir_all(&dir)?;
+        result.map(|()| manifest)
+    }
+
+    #[test]
+    fn loads_xml_and_toml() {
+        let manifest = load(&[
+            ("device.xml", XML),
+            ("storage.toml", TOML),
+            ("README", "not a manifest"),
+        ])
+        .unwrap();
+        assert_eq!(manifest.len(), 4);
+
+        assert!(manifest.is_declared("android.hardware.light.ILights/default"));
+        assert!(manifest.is_declared("com.example.sensor.ISensor/rear"));
+        assert!(manifest.is_declared("com.example.storage.IStorage/default"));
+        assert!(!manifest.is_declared("android.hardware.light.ILights/other"));
+        assert!(!manifest.is_declared("android.hardware.old.IOld/default"));
+
+        assert_eq!(
+            manifest.instances("com.example.sensor.ISensor"),
+            ["front", "rear"]
+        );
+        assert!(manifest.instances("com.example.sensor").is_empty());
+
+        assert_eq!(
+            manifest.instances["android.hardware.light.ILights/default"].version,
+            2
+        );
+        assert_eq!(
+            manifest.connection("android.hardware.light.ILights/default"),
+            None
+        );
+        assert_eq!(
+            manifest.connection("com.example.sensor.ISensor/front"),
+            Some(&Connection::Inet {
+                ip: "127.0.0.1".to_owned(),
+                port: 5000
+            })
+        );
+        assert_eq!(
+            manifest.connection("com.example.storage.IStorage/default"),
+            Some(&Connection::Unix {
+                path: PathBuf::from("/run/storage.sock")
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_declarations_must_agree() {
+        // The same declaration in two files is fine...
+        let manifest = load(&[("a.xml", XML), ("b.xml", XML)]).unwrap();
+        assert_eq!(manifest.len(), 3);
+
+        // ...a different one is not.
+        let other = XML.replace("<version>2</version>", "<version>3</version>");
+        let err = load(&[("a.xml", XML), ("b.xml", &other)]).unwrap_err();
+        assert!(err.to_string().contains("b.xml"), "{err}");
+    }
+
+    #[test]
+    fn rejects_malformed_manifests() {
+        for (file, text) in [
+            ("root.xml", "<hals/>"),
+            ("syntax.xml", "<manifest><hal format=\"aidl\">"),
+            (
+                "fqname.xml",
+                "<manifest><hal format=\"aidl\"><name>a.b</name>\
+                 <fqname>@1.0::IFoo/default</fqname></hal></manifest>",
+            ),
+            (
+                "noinstance.xml",
+                "<manifest><hal format=\"aidl\"><name>a.b</name></hal></manifest>",
+            ),
+            (
+                "port.xml",
+                "<manifest><hal format=\"aidl\"><name>a.b</name><fqname>IFoo/default</fqname>\
+                 <transport ip=\"::1\">inet</transport></hal></manifest>",
+            ),
+            (
+                "transport.toml",
+                "[[hal]]\nname = \"a.b\"\nfqname = [\"IFoo/x\"]\ntransport = \"hwbinder\"\n",
+            ),
+            (
+                "unknown.toml",
+                "[[hal]]\nname = \"a.b\"\nfqname = [\"IFoo/x\"]\nfoo = 1\n",
+            ),
+        ] {
+            let err = load(&[(file, text)]).unwrap_err();
+            assert!(err.to_string().contains(file), "{file}: {err}");
+        }
+    }
+}
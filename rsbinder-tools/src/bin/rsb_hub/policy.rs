@@ -0,0 +1,345 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Access control for adding, finding and listing services: the Linux
+//! counterpart of Android's `service_contexts` and the servicemanager's
+//! SELinux checks.
+//!
+//! A policy is a TOML file with one `[[service]]` table per name pattern.
+//! Each grants the `add`, `find` and `list` actions to callers by uid, by
+//! gid (effective or supplementary, read from `/proc/<pid>/status`) or by
+//! SELinux context; `any = true` grants an action to every caller:
+//!
+//! ```toml
+//! [[service]]
+//! name = "com.example.*"
+//! add = { uids = [1000], contexts = ["u:r:example_server:s0"] }
+//! find = { gids = [1000, 1001] }
+//! list = { gids = [1000, 1001] }
+//!
+//! [[service]]
+//! name = "*"
+//! add = { uids = [0] }
+//! find = { any = true }
+//! list = { any = true }
+//! ```
+//!
+//! `name` is an exact service name or a prefix ending in `*`. A name is
+//! governed by its exact rule if there is one, otherwise by the longest
+//! matching prefix. Names no rule matches, and actions a rule leaves out,
+//! are denied.
+//!
+//! The service manager calls check these actions, as AOSP's do:
+//!
+//! - `add`: `addService`.
+//! - `find`: `getService(2)`, `checkService(2)`,
+//!   `registerForNotifications`, `isDeclared` and `getConnectionInfo` on
+//!   the name; `getDeclaredInstances` lists only the instances found as
+//!   `iface/instance`, and is denied if none of them is.
+//! - `list`: `listServices` and `getServiceDebugInfo` leave out the names
+//!   the caller may not list.
+//!
+//! The policy applies to binder transactions. Calls rsb_hub makes on
+//! itself, outside one (registering `manager` at startup), are not
+//! checked; a transaction from rsb_hub's own process is.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// What a caller asks to do with a service name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Add,
+    Find,
+    List,
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Action::Add => "add",
+            Action::Find => "find",
+            Action::List => "list",
+        })
+    }
+}
+
+/// The identity an access decision is made on.
+#[derive(Debug, Default)]
+pub(crate) struct Caller {
+    pub(crate) uid: u32,
+    pub(crate) gids: Vec<u32>,
+    pub(crate) sid: Option<String>,
+}
+
+impl Caller {
+    /// The groups of process `pid`: its effective gid and supplementary
+    /// groups. Empty if `/proc/<pid>/status` cannot be read (e.g. the
+    /// caller has exited, or came over RPC without a pid).
+    pub(crate) fn gids_of(pid: i32) -> Vec<u32> {
+        let Ok(status) = std::fs::read_to_string(format!("/proc/{pid}/status")) else {
+            return Vec::new();
+        };
+        let mut gids = Vec::new();
+        for line in status.lines() {
+            if let Some(ids) = line.strip_prefix("Gid:") {
+                // Real, effective, saved set, filesystem.
+                gids.extend(
+                    ids.split_whitespace()
+                        .nth(1)
+                        .and_then(|g| g.parse::<u32>().ok()),
+                );
+            } else if let Some(ids) = line.strip_prefix("Groups:") {
+                gids.extend(ids.split_whitespace().filter_map(|g| g.parse::<u32>().ok()));
+            }
+        }
+        gids
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Callers {
+    #[serde(default)]
+    any: bool,
+    #[serde(default)]
+    uids: Vec<u32>,
+    #[serde(default)]
+    gids: Vec<u32>,
+    #[serde(default)]
+    contexts: Vec<String>,
+}
+
+impl Callers {
+    fn allows(&self, caller: &Caller) -> bool {
+        self.any
+            || self.uids.contains(&caller.uid)
+            || caller.gids.iter().any(|gid| self.gids.contains(gid))
+            || caller
+                .sid
+                .as_ref()
+                .is_some_and(|sid| self.contexts.contains(sid))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    name: String,
+    #[serde(default)]
+    add: Callers,
+    #[serde(default)]
+    find: Callers,
+    #[serde(default)]
+    list: Callers,
+}
+
+impl Rule {
+    fn callers(&self, action: Action) -> &Callers {
+        match action {
+            Action::Add => &self.add,
+            Action::Find => &self.find,
+            Action::List => &self.list,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    service: Vec<Rule>,
+}
+
+/// A loaded policy file.
+#[derive(Debug)]
+pub(crate) struct Policy {
+    exact: HashMap<String, Rule>,
+    /// Prefix rules (the pattern without its `*`), longest first.
+    prefixes: Vec<(String, Rule)>,
+}
+
+impl Policy {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let file: PolicyFile = toml::from_str(text)?;
+        let mut exact = HashMap::new();
+        let mut prefixes = Vec::new();
+        for rule in file.service {
+            let duplicate = match rule.name.strip_suffix('*') {
+                Some(prefix) if prefix.contains('*') => {
+                    return Err(format!("'*' may only end a pattern: {:?}", rule.name).into())
+                }
+                Some(prefix) => {
+                    let prefix = prefix.to_owned();
+                    let duplicate = prefixes.iter().any(|(p, _)| *p == prefix);
+                    prefixes.push((prefix, rule));
+                    duplicate
+                }
+                None if rule.name.is_empty() || rule.name.contains('*') => {
+                    return Err(format!("invalid service pattern {:?}", rule.name).into())
+                }
+                None => exact.insert(rule.name.clone(), rule).is_some(),
+            };
+            if duplicate {
+                return Err("a service pattern is listed twice".into());
+            }
+        }
+        prefixes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Ok(Policy { exact, prefixes })
+    }
+
+    fn rule(&self, name: &str) -> Option<&Rule> {
+        self.exact.get(name).or_else(|| {
+            self.prefixes
+                .iter()
+                .find(|(prefix, _)| name.starts_with(prefix.as_str()))
+                .map(|(_, rule)| rule)
+        })
+    }
+
+    /// Whether `caller` may perform `action` on `name`.
+    pub(crate) fn allows(&self, action: Action, name: &str, caller: &Caller) -> bool {
+        self.rule(name)
+            .is_some_and(|rule| rule.callers(action).allows(caller))
+    }
+
+    /// Whether any rule grants by gid, i.e. callers' groups must be read.
+    pub(crate) fn uses_gids(&self) -> bool {
+        self.rules().any(|rule| {
+            [&rule.add, &rule.find, &rule.list]
+                .iter()
+                .any(|callers| !callers.gids.is_empty())
+        })
+    }
+
+    /// Whether any rule grants by SELinux context, which rsb_hub only sees
+    /// if it asks the driver for its callers' contexts.
+    pub(crate) fn uses_contexts(&self) -> bool {
+        self.rules().any(|rule| {
+            [&rule.add, &rule.find, &rule.list]
+                .iter()
+                .any(|callers| !callers.contexts.is_empty())
+        })
+    }
+
+    fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.exact
+            .values()
+            .chain(self.prefixes.iter().map(|(_, rule)| rule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+[[service]]
+name = "*"
+add = { uids = [0] }
+find = { any = true }
+list = { any = true }
+
+[[service]]
+name = "com.example.*"
+add = { uids = [1000], contexts = ["u:r:example_server:s0"] }
+find = { gids = [2000] }
+
+[[service]]
+name = "com.example.public"
+add = { uids = [1000] }
+find = { any = true }
+list = { any = true }
+"#;
+
+    fn caller(uid: u32, gids: &[u32], sid: Option<&str>) -> Caller {
+        Caller {
+            uid,
+            gids: gids.to_vec(),
+            sid: sid.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn most_specific_rule_decides() {
+        let policy = Policy::parse(POLICY).unwrap();
+        let root = caller(0, &[0], None);
+        let server = caller(1000, &[1000], None);
+        let member = caller(3000, &[3000, 2000], None);
+        let other = caller(3000, &[3000], None);
+
+        // "*" governs names no other rule matches.
+        assert!(policy.allows(Action::Add, "misc", &root));
+        assert!(!policy.allows(Action::Add, "misc", &server));
+        assert!(policy.allows(Action::Find, "misc", &other));
+
+        // The prefix rule overrides "*", even for root.
+        assert!(policy.allows(Action::Add, "com.example.foo", &server));
+        assert!(!policy.allows(Action::Add, "com.example.foo", &root));
+        assert!(policy.allows(Action::Find, "com.example.foo", &member));
+        assert!(!policy.allows(Action::Find, "com.example.foo", &other));
+        // An action the rule leaves out is denied.
+        assert!(!policy.allows(Action::List, "com.example.foo", &member));
+
+        // The exact rule overrides the prefix.
+        assert!(policy.allows(Action::Find, "com.example.public", &other));
+        assert!(policy.allows(Action::List, "com.example.public", &other));
+    }
+
+    #[test]
+    fn grants_by_selinux_context() {
+        let policy = Policy::parse(POLICY).unwrap();
+        assert!(policy.uses_contexts());
+        assert!(policy.uses_gids());
+        let labelled = caller(4000, &[], Some("u:r:example_server:s0"));
+        assert!(policy.allows(Action::Add, "com.example.foo", &labelled));
+        let unlabelled = caller(4000, &[], Some("u:r:untrusted_app:s0"));
+        assert!(!policy.allows(Action::Add, "com.example.foo", &unlabelled));
+    }
+
+    #[test]
+    fn unmatched_names_are_denied() {
+        let policy =
+            Policy::parse("[[service]]\nname = \"com.example.foo\"\nfind = { any = true }\n")
+                .unwrap();
+        assert!(!policy.uses_contexts());
+        assert!(!policy.uses_gids());
+        let anyone = caller(0, &[0], None);
+        assert!(policy.allows(Action::Find, "com.example.foo", &anyone));
+        assert!(!policy.allows(Action::Find, "com.example.foobar", &anyone));
+        assert!(!policy.allows(Action::Find, "other", &anyone));
+    }
+
+    #[test]
+    fn rejects_malformed_policies() {
+        for text in [
+            "[[service]]\nname = \"a*b\"\n",
+            "[[service]]\nname = \"**\"\n",
+            "[[service]]\nname = \"\"\n",
+            "[[service]]\nname = \"a\"\n[[service]]\nname = \"a\"\n",
+            "[[service]]\nname = \"a*\"\n[[service]]\nname = \"a*\"\n",
+            "[[service]]\nname = \"a\"\nfind = { user = 1 }\n",
+            "[[service]]\nname = \"a\"\nremove = { any = true }\n",
+        ] {
+            assert!(Policy::parse(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn reads_groups_from_proc() {
+        let gids = Caller::gids_of(std::process::id() as i32);
+        // SAFETY: `getegid` has no preconditions.
+        let egid = unsafe { libc::getegid() };
+        assert!(gids.contains(&egid), "{gids:?}");
+        assert!(Caller::gids_of(0).is_empty());
+    }
+}
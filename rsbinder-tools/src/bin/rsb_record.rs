@@ -0,0 +1,189 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs::{File, OpenOptions};
+use std::os::fd::AsFd;
+
+use env_logger::Env;
+use rsbinder::recorded_transaction::RecordedTransaction;
+use rsbinder::*;
+
+fn find_service(name: &str) -> std::result::Result<SIBinder, Box<dyn std::error::Error>> {
+    hub::check_service(name).ok_or_else(|| format!("service '{name}' not found").into())
+}
+
+fn proxy(binder: &SIBinder) -> std::result::Result<&ProxyHandle, Box<dyn std::error::Error>> {
+    binder
+        .as_proxy()
+        .ok_or_else(|| "service is not a kernel binder proxy".into())
+}
+
+fn start(service: &str, path: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let binder = find_service(service)?;
+    let file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|err| format!("{path}: {err}"))?;
+    proxy(&binder)?.start_recording_binder(file.as_fd())?;
+    println!("Recording transactions of '{service}' to {path}");
+    Ok(())
+}
+
+fn stop(service: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let binder = find_service(service)?;
+    proxy(&binder)?.stop_recording_binder()?;
+    println!("Stopped recording '{service}'");
+    Ok(())
+}
+
+fn read_recording(
+    path: &str,
+) -> std::result::Result<Vec<RecordedTransaction>, Box<dyn std::error::Error>> {
+    let mut file = File::open(path).map_err(|err| format!("{path}: {err}"))?;
+    Ok(RecordedTransaction::read_all(
+        &mut std::io::BufReader::new(&mut file),
+    )?)
+}
+
+fn inspect(path: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    for (index, transaction) in read_recording(path)?.iter().enumerate() {
+        println!(
+            "#{index} {}.{:09} {} code={} flags={:#x} status={:?} data={}B reply={}B{}",
+            transaction.timestamp_seconds,
+            transaction.timestamp_nanoseconds,
+            transaction.interface_name,
+            transaction.code,
+            transaction.flags,
+            transaction.status(),
+            transaction.data.len(),
+            transaction.reply.len(),
+            if transaction.version != 0 {
+                " (rpc)"
+            } else {
+                ""
+            },
+        );
+    }
+    Ok(())
+}
+
+fn replay(service: &str, path: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let binder = find_service(service)?;
+    let transactions = read_recording(path)?;
+    let mut mismatches = 0;
+    for (index, transaction) in transactions.iter().enumerate() {
+        if transaction.interface_name != binder.descriptor() {
+            log::warn!(
+                "#{index}: recorded on '{}', replaying on '{}'",
+                transaction.interface_name,
+                binder.descriptor()
+            );
+        }
+        let result = transaction.replay(&binder);
+        let status = result.as_ref().map(|_| ()).map_err(|err| *err);
+        let same_reply = match &result {
+            Ok(Some(reply)) => reply.data_bytes() == transaction.reply.as_slice(),
+            Ok(None) | Err(_) => true,
+        };
+        if status == transaction.status() && same_reply {
+            println!("#{index} code={}: ok", transaction.code);
+        } else {
+            mismatches += 1;
+            println!(
+                "#{index} code={}: status {:?} (recorded {:?}){}",
+                transaction.code,
+                status,
+                transaction.status(),
+                if same_reply { "" } else { ", reply differs" },
+            );
+        }
+    }
+    println!(
+        "Replayed {} transactions, {mismatches} mismatched",
+        transactions.len()
+    );
+    Ok(())
+}
+
+fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let service = || {
+        clap::Arg::new("service")
+            .help("Service name")
+            .required(true)
+    };
+    let file = || clap::Arg::new("file").help("Recording file").required(true);
+    let matches = clap::Command::new("rsb_record")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Records the transactions a binder service serves and replays them")
+        .arg(
+            clap::Arg::new("device")
+                .short('d')
+                .long("device")
+                .value_name("NAME")
+                .help("Name of the binder device to use (e.g., 'binder', 'mybinder')")
+                .default_value("binder")
+                .global(true),
+        )
+        .subcommand_required(true)
+        .subcommand(
+            clap::Command::new("start")
+                .about("Start recording a service's transactions to a file")
+                .arg(service())
+                .arg(file()),
+        )
+        .subcommand(
+            clap::Command::new("stop")
+                .about("Stop recording a service")
+                .arg(service()),
+        )
+        .subcommand(
+            clap::Command::new("inspect")
+                .about("Print the transactions in a recording")
+                .arg(file()),
+        )
+        .subcommand(
+            clap::Command::new("replay")
+                .about("Send the recorded transactions to a service again")
+                .arg(service())
+                .arg(file()),
+        )
+        .after_help(
+            "Examples:\n    \
+            $ rsb_record start my.service /tmp/my.rec\n    \
+            $ rsb_record stop my.service\n    \
+            $ rsb_record inspect /tmp/my.rec\n    \
+            $ rsb_record replay my.service /tmp/my.rec\n\n    \
+            Recording requires root or the service's own uid. Only parcel\n    \
+            bytes are recorded, so transactions carrying binders or file\n    \
+            descriptors do not replay faithfully.",
+        )
+        .get_matches();
+
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+
+    let arg = |matches: &clap::ArgMatches, name: &str| {
+        matches
+            .get_one::<String>(name)
+            .expect("required argument")
+            .clone()
+    };
+
+    let (command, sub) = matches.subcommand().expect("subcommand is required");
+    if command == "inspect" {
+        return inspect(&arg(sub, "file"));
+    }
+
+    let device_name = matches
+        .get_one::<String>("device")
+        .expect("device has a default value");
+    ProcessState::init(&format!("{DEFAULT_BINDERFS_PATH}/{device_name}"), 0)?;
+
+    match command {
+        "start" => start(&arg(sub, "service"), &arg(sub, "file")),
+        "stop" => stop(&arg(sub, "service")),
+        "replay" => replay(&arg(sub, "service"), &arg(sub, "file")),
+        _ => unreachable!("unknown subcommand {command}"),
+    }
+}
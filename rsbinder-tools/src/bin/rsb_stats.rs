@@ -0,0 +1,249 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::Path;
+
+use env_logger::Env;
+use rsbinder::binderfs::logs::*;
+use rsbinder::*;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// The pid hosting service `name`: look up our own ref on it, then the
+/// process that owns the node the ref points at.
+fn service_pid(binderfs: &Path, device: &str, name: &str) -> Result<i32> {
+    ProcessState::init(&binderfs.join(device).to_string_lossy(), 0)?;
+    let logs = BinderLogs::new(binderfs);
+    let binder = hub::check_service(name).ok_or_else(|| format!("service '{name}' not found"))?;
+    let Some(proxy) = binder.as_proxy() else {
+        return Ok(std::process::id() as i32);
+    };
+    let handle = proxy.handle();
+    let own = logs.proc_state(std::process::id() as i32)?;
+    let node = own
+        .iter()
+        .filter(|proc| proc.context == device)
+        .flat_map(|proc| &proc.refs)
+        .find(|r| r.desc == handle)
+        .map(|r| r.node)
+        .ok_or_else(|| format!("no ref for handle {handle} in binder_logs"))?;
+    let state = logs.state()?;
+    let (owner, _) = state
+        .node_owner(node)
+        .ok_or_else(|| format!("owner of node {node} not found (dead?)"))?;
+    Ok(owner.pid)
+}
+
+fn describe(transaction: &Transaction) -> String {
+    let kind = match transaction.kind {
+        TransactionKind::Outgoing => "outgoing",
+        TransactionKind::Incoming => "incoming",
+        TransactionKind::Bad => "bad",
+        TransactionKind::Pending => "pending",
+        TransactionKind::PendingAsync => "pending async",
+    };
+    let mut text = format!(
+        "{kind} transaction {}: {}:{} -> {}:{} code {} {}",
+        transaction.debug_id,
+        transaction.from_pid,
+        transaction.from_tid,
+        transaction.to_pid,
+        transaction.to_tid,
+        transaction.code,
+        if transaction.is_oneway() {
+            "oneway"
+        } else {
+            "sync"
+        },
+    );
+    if let Some(node) = transaction.node {
+        text += &format!(" node {node}");
+    }
+    if let Some((data, offsets)) = transaction.size {
+        text += &format!(" size {data}:{offsets}");
+    }
+    if let Some(elapsed) = transaction.elapsed_ms {
+        text += &format!(" ({elapsed}ms)");
+    }
+    text
+}
+
+fn print_summary(logs: &BinderLogs, device: Option<&str>) -> Result<()> {
+    let stats = logs.stats()?;
+    println!(
+        "{:>8} {:<10} {:>7} {:>5} {:>9} {:>12} {:>5} {:>5} {:>7} {:>7}",
+        "PID",
+        "CONTEXT",
+        "THREADS",
+        "READY",
+        "REQUESTED",
+        "ASYNC FREE",
+        "NODES",
+        "REFS",
+        "BUFFERS",
+        "PENDING"
+    );
+    for proc in &stats.procs {
+        if device.is_some_and(|device| device != proc.context) {
+            continue;
+        }
+        println!(
+            "{:>8} {:<10} {:>7} {:>5} {:>9} {:>12} {:>5} {:>5} {:>7} {:>7}",
+            proc.pid,
+            proc.context,
+            proc.threads,
+            proc.ready_threads,
+            format!(
+                "{}+{}/{}",
+                proc.requested_threads, proc.requested_threads_started, proc.max_threads
+            ),
+            proc.free_async_space,
+            proc.nodes,
+            proc.refs,
+            proc.buffers,
+            proc.pending_transactions,
+        );
+    }
+    Ok(())
+}
+
+fn print_proc(logs: &BinderLogs, pid: i32, device: Option<&str>) -> Result<()> {
+    let stats = logs.stats()?;
+    let states = logs.proc_state(pid)?;
+    for state in &states {
+        if device.is_some_and(|device| device != state.context) {
+            continue;
+        }
+        println!("proc {pid} ({})", state.context);
+        if let Some(stats) = stats
+            .procs
+            .iter()
+            .find(|proc| proc.pid == pid && proc.context == state.context)
+        {
+            println!(
+                "  threads: {} (ready {}, requested {}+{}/{})",
+                stats.threads,
+                stats.ready_threads,
+                stats.requested_threads,
+                stats.requested_threads_started,
+                stats.max_threads
+            );
+            println!("  free async space: {} bytes", stats.free_async_space);
+            println!(
+                "  nodes: {}, refs: {} (strong {}, weak {}), buffers: {}, pending: {}",
+                stats.nodes,
+                stats.refs,
+                stats.strong_refs,
+                stats.weak_refs,
+                stats.buffers,
+                stats.pending_transactions
+            );
+        }
+        for thread in &state.threads {
+            if thread.transactions.is_empty() {
+                continue;
+            }
+            println!("  thread {}:", thread.tid);
+            for transaction in &thread.transactions {
+                println!("    {}", describe(transaction));
+            }
+        }
+        for transaction in &state.pending {
+            println!("  {}", describe(transaction));
+        }
+        let dead: Vec<_> = state.refs.iter().filter(|r| r.dead).collect();
+        if !dead.is_empty() {
+            println!("  dead refs:");
+            for r in dead {
+                println!("    handle {} -> node {}", r.desc, r.node);
+            }
+        }
+    }
+
+    let failed: Vec<_> = logs
+        .failed_transaction_log()?
+        .into_iter()
+        .filter(|entry| entry.from_pid == pid || entry.to_pid == pid)
+        .collect();
+    if !failed.is_empty() {
+        println!("failed transactions:");
+        for entry in failed {
+            println!(
+                "  {}: {:?} {}:{} -> {}:{} ({}) handle {} size {}:{} error {}/{} at line {}",
+                entry.debug_id,
+                entry.call_type,
+                entry.from_pid,
+                entry.from_tid,
+                entry.to_pid,
+                entry.to_tid,
+                entry.context,
+                entry.handle,
+                entry.data_size,
+                entry.offsets_size,
+                entry.return_error,
+                entry.return_error_param,
+                entry.return_error_line,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let matches = clap::Command::new("rsb_stats")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Shows binder driver state from the binderfs debug logs")
+        .arg(
+            clap::Arg::new("target")
+                .help("A pid, or the name of a service to show the hosting process of")
+                .index(1),
+        )
+        .arg(
+            clap::Arg::new("device")
+                .short('d')
+                .long("device")
+                .value_name("NAME")
+                .help("Binder device to look services up on and to filter by (e.g., 'binder')"),
+        )
+        .arg(
+            clap::Arg::new("binderfs")
+                .long("binderfs")
+                .value_name("PATH")
+                .help("Mount point of binderfs")
+                .default_value(DEFAULT_BINDERFS_PATH),
+        )
+        .after_help(
+            "Examples:\n    \
+            List every process with a binder context:\n    \
+            $ sudo rsb_stats\n\n    \
+            Show threads, pending work and failed calls of a process:\n    \
+            $ sudo rsb_stats 1234\n\n    \
+            Show the process hosting a service:\n    \
+            $ sudo rsb_stats my.service --device binder",
+        )
+        .get_matches();
+
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+
+    let binderfs = Path::new(
+        matches
+            .get_one::<String>("binderfs")
+            .expect("binderfs has a default value"),
+    );
+    let logs = BinderLogs::new(binderfs);
+    let device = matches.get_one::<String>("device").map(String::as_str);
+
+    let Some(target) = matches.get_one::<String>("target") else {
+        return print_summary(&logs, device);
+    };
+    let pid = match target.parse() {
+        Ok(pid) => pid,
+        Err(_) => {
+            let pid = service_pid(binderfs, device.unwrap_or("binder"), target)?;
+            println!("service '{target}' is hosted by pid {pid}");
+            pid
+        }
+    };
+    print_proc(&logs, pid, device)
+}
@@ -76,4 +76,21 @@ fn main() {
         .output(PathBuf::from("permission_controller.rs"))
         .generate()
         .unwrap();
+
+    // `IShellCallback` / `IResultReceiver`, the callback binders carried
+    // by `SHELL_COMMAND_TRANSACTION`; see `src/shell_command.rs`.
+    new_builder()
+        .source(PathBuf::from("aidl/shell/com/android/internal/os"))
+        .output(PathBuf::from("shell_command.rs"))
+        .generate()
+        .unwrap();
+
+    // hwservicemanager client (`android.hidl.manager@1.0..1.2`) plus the
+    // `android.hidl.base@1.0` types it uses; see `src/hidl/manager.rs`.
+    rsbinder_aidl::hidl::Builder::new()
+        .set_crate_support(true)
+        .source(PathBuf::from("hidl/android/hidl/manager"))
+        .output(PathBuf::from("hidl_manager.rs"))
+        .generate()
+        .unwrap();
 }
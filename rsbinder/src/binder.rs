@@ -159,6 +159,15 @@ pub trait Interface: Send + Sync {
     fn dump(&self, _writer: &mut dyn std::io::Write, _args: &[String]) -> Result<()> {
         Ok(())
     }
+
+    /// Shell command handler for this Binder object (`cmd <service> ...`).
+    ///
+    /// The returned status is reported to the caller's result receiver.
+    /// Returns [`StatusCode::InvalidOperation`] by default, like AOSP
+    /// `BBinder`; see [`crate::shell_command`].
+    fn shell_command(&self, _command: &mut crate::shell_command::ShellCommand) -> Result<()> {
+        Err(StatusCode::InvalidOperation)
+    }
 }
 
 /// Trait for converting a generic Binder object into a specific Binder
@@ -219,6 +228,45 @@ pub trait DeathRecipient: Send + Sync {
     fn binder_died(&self, who: &WIBinder);
 }
 
+/// Freeze state of the process hosting a remote binder, as reported by
+/// the kernel's `BR_FROZEN_BINDER`. AOSP
+/// `IBinder::FrozenStateChangeCallback::State`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrozenState {
+    /// The hosting process is frozen; synchronous calls fail and oneway
+    /// calls are queued until it is thawed.
+    Frozen,
+    /// The hosting process is running normally.
+    Unfrozen,
+}
+
+impl From<bool> for FrozenState {
+    fn from(is_frozen: bool) -> Self {
+        if is_frozen {
+            FrozenState::Frozen
+        } else {
+            FrozenState::Unfrozen
+        }
+    }
+}
+
+/// Callback interface for freeze-state notifications on a remote binder.
+///
+/// Corresponds to AOSP `IBinder::FrozenStateChangeCallback`. Register with
+/// [`IBinder::add_frozen_state_change_callback`]; the kernel reports the
+/// current state right after the first registration on a proxy and then
+/// once per freeze/thaw transition of the hosting process.
+///
+/// # Panic safety
+///
+/// `on_state_changed` is invoked from the binder worker thread that
+/// processes `BR_FROZEN_BINDER`, with the same per-callback panic
+/// isolation as [`DeathRecipient::binder_died`].
+pub trait FrozenStateChangeCallback: Send + Sync {
+    /// Called when the hosting process of `who` is frozen or thawed.
+    fn on_state_changed(&self, who: &WIBinder, state: FrozenState);
+}
+
 /// Core interface for binder objects, both local and remote.
 ///
 /// This trait corresponds to the public interface of the C++ `IBinder` class,
@@ -251,6 +299,34 @@ pub trait IBinder: Any + Send + Sync {
     /// dies.
     fn unlink_to_death(&self, recipient: sync::Weak<dyn DeathRecipient>) -> Result<()>;
 
+    /// Register `callback` for freeze-state changes of the process hosting
+    /// this binder. AOSP `BpBinder::addFrozenStateChangeCallback`.
+    ///
+    /// Only kernel proxies support this, and only when the binder driver
+    /// advertises the `freeze_notification` feature (kernel 6.12+);
+    /// otherwise `Err(StatusCode::InvalidOperation)` is returned. If the
+    /// kernel has already reported a state for this proxy, `callback` is
+    /// invoked with it before this call returns.
+    ///
+    /// Like [`Self::link_to_death`], only a weak reference is kept — the
+    /// caller owns the strong `Arc`.
+    fn add_frozen_state_change_callback(
+        &self,
+        _callback: sync::Weak<dyn FrozenStateChangeCallback>,
+    ) -> Result<()> {
+        Err(StatusCode::InvalidOperation)
+    }
+
+    /// Remove a callback registered with
+    /// [`Self::add_frozen_state_change_callback`]. Returns
+    /// `Err(StatusCode::NameNotFound)` if it is not registered.
+    fn remove_frozen_state_change_callback(
+        &self,
+        _callback: sync::Weak<dyn FrozenStateChangeCallback>,
+    ) -> Result<()> {
+        Err(StatusCode::InvalidOperation)
+    }
+
     /// Send a ping transaction to this object
     fn ping_binder(&self) -> Result<()>;
 
@@ -513,6 +589,22 @@ pub trait Remotable: Send + Sync {
     /// Handle a request to invoke the dump transaction on this
     /// object.
     fn on_dump(&self, writer: &mut dyn std::io::Write, args: &[String]) -> Result<()>;
+
+    /// Handle a `SHELL_COMMAND_TRANSACTION`. Generated stubs forward this
+    /// to [`Interface::shell_command`].
+    fn on_shell_command(&self, _command: &mut crate::shell_command::ShellCommand) -> Result<()> {
+        Err(StatusCode::InvalidOperation)
+    }
+
+    /// Whether this is a HIDL stub. HIDL stubs check their own interface
+    /// token in each method, so the AIDL interface header is not checked
+    /// for them. See [`crate::hidl`].
+    fn is_hidl() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
 }
 
 /// A transactable object that can be used to process Binder commands.
@@ -776,8 +868,7 @@ impl SIBinder {
             // return DeadObject, which is the correct contract.
             let handle = proxy_handle.handle();
             let stability = proxy_handle.stability();
-            let generation =
-                crate::process_state::ProcessState::as_self().cache_generation_for(handle);
+            let generation = proxy_handle.process_state().cache_generation_for(handle);
             match generation {
                 Some(generation) => WIBinder {
                     inner: WIBinderInner::Proxy {
@@ -1117,6 +1208,19 @@ impl<I: FromIBinder + ?Sized> Strong<I> {
             .expect("ToSyncInterface guarantees binder compatibility")
     }
 
+    /// Borrow this interface with a deadline for its calls, as in
+    /// `service.with_timeout(d).method(...)`.
+    ///
+    /// While the returned guard lives, this thread's synchronous calls to
+    /// the remote object fail with [`StatusCode::TimedOut`] if no reply
+    /// arrives within `timeout`; see [`proxy::ProxyHandle::set_timeout`]
+    /// for how a timed-out call is cleaned up. Local binders are called
+    /// directly and RPC proxies follow their session's timeout, so for
+    /// those this is a plain borrow.
+    pub fn with_timeout(&self, timeout: std::time::Duration) -> proxy::WithTimeout<'_, I> {
+        proxy::WithTimeout::new(self, timeout)
+    }
+
     /// Register a death notification on the underlying binder, taking the
     /// concrete `Arc<R>` recipient directly. Convenience for
     /// `self.as_binder().link_to_death_arc(recipient)`; see
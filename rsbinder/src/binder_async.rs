@@ -70,6 +70,29 @@ pub trait BinderAsyncPool {
         A: Send + 'static,
         B: Send + 'a,
         E: From<crate::StatusCode>;
+
+    /// Send a transaction through `binder` and resolve to its reply
+    /// (`None` for oneway calls). Generated async proxies call this.
+    ///
+    /// The default runs the blocking `submit_transact` through
+    /// [`Self::spawn`]; pools that can wait for replies without a thread
+    /// per call, like [`TokioReactor`](crate::TokioReactor), override it.
+    fn transact(
+        binder: crate::SIBinder,
+        code: crate::TransactionCode,
+        data: crate::Parcel,
+        flags: crate::TransactionFlags,
+    ) -> BoxFuture<'static, crate::Result<Option<crate::Parcel>>> {
+        Self::spawn(
+            move || {
+                binder
+                    .as_remote()
+                    .ok_or(crate::StatusCode::BadType)?
+                    .submit_transact(code, &data, flags)
+            },
+            |reply| async move { reply },
+        )
+    }
 }
 
 /// A runtime for executing an async binder server.
@@ -5,7 +5,9 @@ use std::sync::Arc;
 
 use rustix::fd::{BorrowedFd, FromRawFd, OwnedFd};
 
-pub(crate) use crate::sys::binder::flat_binder_object;
+pub(crate) use crate::sys::binder::{
+    binder_buffer_object, binder_fd_array_object, flat_binder_object,
+};
 use crate::{binder::*, error::*, process_state, sys::*};
 
 impl Default for flat_binder_object {
@@ -120,7 +122,11 @@ impl flat_binder_object {
         self.cookie = cookie;
     }
 
-    pub(crate) fn acquire(&self) -> Result<()> {
+    /// Take the reference a parcel of `context` holds on the object.
+    pub(crate) fn acquire(
+        &self,
+        context: Option<&'static process_state::ProcessState>,
+    ) -> Result<()> {
         match self.hdr.type_ {
             BINDER_TYPE_BINDER => {
                 // Native binder: bump publish_count for this buffer
@@ -136,7 +142,7 @@ impl flat_binder_object {
                 // is preserved without any per-object bookkeeping.
                 if self.pointer() != 0 {
                     let id = self.pointer();
-                    if !process_state::ProcessState::as_self().incref_publish(id) {
+                    if !process_state::ProcessState::of(context).incref_publish(id) {
                         log::error!("flat_binder_object::acquire: unknown native id {id}");
                         debug_assert!(false, "acquire on unknown native id {id}");
                     }
@@ -144,13 +150,16 @@ impl flat_binder_object {
 
                 Ok(())
             }
-            BINDER_TYPE_HANDLE => process_state::ProcessState::as_self()
+            BINDER_TYPE_HANDLE => process_state::ProcessState::of(context)
                 .strong_proxy_for_handle(self.handle())?
                 .increase(),
             BINDER_TYPE_FD => {
                 // Notion to do.
                 Ok(())
             }
+            // Scatter-gather objects hold no kernel reference; the parcel
+            // owns their backing buffers (and any dup'd array fds).
+            BINDER_TYPE_PTR | BINDER_TYPE_FDA => Ok(()),
             _ => {
                 log::error!("Invalid object type {:08x}", self.hdr.type_);
                 Err(StatusCode::InvalidOperation)
@@ -158,7 +167,11 @@ impl flat_binder_object {
         }
     }
 
-    pub(crate) fn release(&self) -> Result<()> {
+    /// Drop the reference taken by [`Self::acquire`].
+    pub(crate) fn release(
+        &self,
+        context: Option<&'static process_state::ProcessState>,
+    ) -> Result<()> {
         match self.hdr.type_ {
             BINDER_TYPE_BINDER => {
                 // Native binder: decrement publish_count. If both
@@ -171,14 +184,14 @@ impl flat_binder_object {
                 // removal).
                 if self.pointer() != 0 {
                     let id = self.pointer();
-                    if !process_state::ProcessState::as_self().decref_publish(id) {
+                    if !process_state::ProcessState::of(context).decref_publish(id) {
                         log::error!("flat_binder_object::release: unknown native id {id}");
                         debug_assert!(false, "release on unknown native id {id}");
                     }
                 }
                 Ok(())
             }
-            BINDER_TYPE_HANDLE => process_state::ProcessState::as_self()
+            BINDER_TYPE_HANDLE => process_state::ProcessState::of(context)
                 .strong_proxy_for_handle(self.handle())?
                 .decrease(),
             BINDER_TYPE_FD => {
@@ -189,6 +202,7 @@ impl flat_binder_object {
 
                 Ok(())
             }
+            BINDER_TYPE_PTR | BINDER_TYPE_FDA => Ok(()),
             _ => {
                 log::error!("Invalid object type {:08x}", self.hdr.type_);
                 Err(StatusCode::InvalidOperation)
@@ -197,6 +211,53 @@ impl flat_binder_object {
     }
 }
 
+impl binder_buffer_object {
+    /// Creates a `BINDER_TYPE_PTR` object describing `buffer`.
+    ///
+    /// `parent` is `(object index, byte offset)` of the pointer slot in an
+    /// earlier buffer object that the kernel must patch with this buffer's
+    /// address in the receiver (`BINDER_BUFFER_FLAG_HAS_PARENT`). The caller
+    /// keeps `buffer` alive until the transaction has been sent.
+    pub(crate) fn new(buffer: &[u8], parent: Option<(usize, usize)>) -> Self {
+        let (flags, parent, parent_offset) = match parent {
+            Some((index, offset)) => (BINDER_BUFFER_FLAG_HAS_PARENT, index as _, offset as _),
+            None => (0, 0, 0),
+        };
+        binder_buffer_object {
+            hdr: binder_object_header {
+                type_: BINDER_TYPE_PTR,
+            },
+            flags,
+            buffer: buffer.as_ptr() as _,
+            length: buffer.len() as _,
+            parent,
+            parent_offset,
+        }
+    }
+
+    pub(crate) fn has_parent(&self) -> bool {
+        self.flags & BINDER_BUFFER_FLAG_HAS_PARENT != 0
+    }
+}
+
+impl binder_fd_array_object {
+    /// Creates a `BINDER_TYPE_FDA` object for `num_fds` consecutive `u32`
+    /// fds stored at `parent_offset` inside the buffer object at index
+    /// `parent`. The kernel translates every fd in place for the receiver.
+    pub(crate) fn new(num_fds: usize, parent: usize, parent_offset: usize) -> Self {
+        binder_fd_array_object {
+            hdr: binder_object_header {
+                type_: BINDER_TYPE_FDA,
+            },
+            // `pad` is copied onto the wire; zero it (see `new_handle`).
+            pad: 0,
+            num_fds: num_fds as _,
+            parent: parent as _,
+            parent_offset: parent_offset as _,
+        }
+    }
+}
+
 const SCHED_NORMAL: u32 = 0;
 const FLAT_BINDER_FLAG_SCHED_POLICY_SHIFT: u32 = 9;
 /// 2-bit field for the scheduling policy embedded in `flat_binder_object.flags`.
@@ -299,6 +360,43 @@ pub(crate) fn read_flat_binder(data: &[u8], offset: usize) -> Result<flat_binder
     Ok(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const flat_binder_object) })
 }
 
+/// Reads the `binder_object_header` type of the object at `offset`.
+///
+/// Every kernel object starts with this header, so the object table can be
+/// walked without knowing the concrete object size up front.
+pub(crate) fn read_object_type(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + std::mem::size_of::<u32>())
+        .ok_or(StatusCode::NotEnoughData)?;
+    Ok(u32::from_ne_bytes(bytes.try_into().expect("4-byte slice")))
+}
+
+/// Reads a binder_buffer_object from a potentially unaligned buffer position.
+/// See [`read_flat_binder`] for the alignment rationale.
+pub(crate) fn read_buffer_object(data: &[u8], offset: usize) -> Result<binder_buffer_object> {
+    let size = std::mem::size_of::<binder_buffer_object>();
+    let bytes = data
+        .get(offset..offset + size)
+        .ok_or(StatusCode::NotEnoughData)?;
+    // SAFETY: `bytes` is exactly `size_of::<binder_buffer_object>()` readable
+    // bytes; the bindgen `#[repr(C)]` POD has no invalid bit patterns and
+    // `read_unaligned` returns an owned stack copy.
+    Ok(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const binder_buffer_object) })
+}
+
+/// Reads a binder_fd_array_object from a potentially unaligned buffer position.
+/// See [`read_flat_binder`] for the alignment rationale.
+pub(crate) fn read_fd_array_object(data: &[u8], offset: usize) -> Result<binder_fd_array_object> {
+    let size = std::mem::size_of::<binder_fd_array_object>();
+    let bytes = data
+        .get(offset..offset + size)
+        .ok_or(StatusCode::NotEnoughData)?;
+    // SAFETY: `bytes` is exactly `size_of::<binder_fd_array_object>()`
+    // readable bytes; the bindgen `#[repr(C)]` POD has no invalid bit
+    // patterns and `read_unaligned` returns an owned stack copy.
+    Ok(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const binder_fd_array_object) })
+}
+
 /// Writes a flat_binder_object to a potentially unaligned buffer position.
 pub(crate) fn write_flat_binder(
     data: &mut [u8],
@@ -0,0 +1,900 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Typed readers for the binder driver's debug logs.
+//!
+//! binderfs publishes the driver's debugfs files under `binder_logs/` in
+//! its mount: `stats`, `state`, `transactions`, `transaction_log`,
+//! `failed_transaction_log` and one `proc/<pid>` file per process. They are
+//! the main way to find out why a call is stuck: which threads are busy,
+//! which transactions are outstanding, how much async buffer is left.
+//!
+//! ```no_run
+//! use rsbinder::binderfs::logs::BinderLogs;
+//!
+//! let logs = BinderLogs::default();
+//! for proc in logs.state()?.procs {
+//!     let busy = proc.threads.iter().filter(|t| !t.transactions.is_empty());
+//!     println!("{} ({}): {} busy threads", proc.pid, proc.context, busy.count());
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! The files are text meant for people and their layout has moved between
+//! kernel versions (`pri`, `elapsed`, `offset` fields come and go). The
+//! parsers follow `drivers/android/binder.c` and skip lines and fields they
+//! do not know, so a newer kernel degrades to missing fields rather than
+//! errors. Reading the files usually needs root.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Reader for the `binder_logs` directory of a binderfs mount.
+#[derive(Debug, Clone)]
+pub struct BinderLogs {
+    dir: PathBuf,
+}
+
+impl Default for BinderLogs {
+    /// The logs of the binderfs mounted at [`crate::DEFAULT_BINDERFS_PATH`].
+    fn default() -> Self {
+        Self::new(crate::DEFAULT_BINDERFS_PATH)
+    }
+}
+
+impl BinderLogs {
+    /// The logs of the binderfs mounted at `binderfs`.
+    pub fn new(binderfs: impl AsRef<Path>) -> Self {
+        Self {
+            dir: binderfs.as_ref().join("binder_logs"),
+        }
+    }
+
+    fn read(&self, name: impl AsRef<Path>) -> io::Result<String> {
+        let path = self.dir.join(name);
+        fs::read_to_string(&path).inspect_err(|e| {
+            log::error!("Reading '{}' failed: {e}", path.display());
+        })
+    }
+
+    /// `binder_logs/stats`: command counters and per-process totals.
+    pub fn stats(&self) -> io::Result<Stats> {
+        Ok(parse_stats(&self.read("stats")?))
+    }
+
+    /// `binder_logs/state`: every process's threads, nodes, refs,
+    /// buffers and transactions.
+    pub fn state(&self) -> io::Result<State> {
+        Ok(parse_state(&self.read("state")?))
+    }
+
+    /// `binder_logs/transactions`: like [`Self::state`], but only the
+    /// threads and work that involve a transaction.
+    pub fn transactions(&self) -> io::Result<State> {
+        Ok(parse_state(&self.read("transactions")?))
+    }
+
+    /// `binder_logs/proc/<pid>`: the state of one process, one entry per
+    /// binder context it has open. Fails with `NotFound` if `pid` has no
+    /// binder context on this mount.
+    pub fn proc_state(&self, pid: i32) -> io::Result<Vec<ProcState>> {
+        Ok(parse_state(&self.read(Path::new("proc").join(pid.to_string()))?).procs)
+    }
+
+    /// `binder_logs/transaction_log`: the last transactions, oldest first.
+    pub fn transaction_log(&self) -> io::Result<Vec<LogEntry>> {
+        Ok(parse_transaction_log(&self.read("transaction_log")?))
+    }
+
+    /// `binder_logs/failed_transaction_log`: the last failed transactions.
+    pub fn failed_transaction_log(&self) -> io::Result<Vec<LogEntry>> {
+        Ok(parse_transaction_log(&self.read("failed_transaction_log")?))
+    }
+}
+
+/// Counters printed by the driver's `print_binder_stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// `BC_*` commands received, by name.
+    pub commands: BTreeMap<String, u64>,
+    /// `BR_*` returns sent, by name.
+    pub returns: BTreeMap<String, u64>,
+    /// Object counts (`proc`, `thread`, `node`, `ref`, `death`,
+    /// `transaction`, `transaction_complete`).
+    pub objects: BTreeMap<String, ObjectCount>,
+}
+
+/// Live and lifetime count of a driver object type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ObjectCount {
+    pub active: u64,
+    pub total: u64,
+}
+
+impl Counters {
+    /// Take `line` if it is a counter line.
+    fn parse_line(&mut self, line: &str) -> bool {
+        let Some((name, value)) = line.split_once(": ") else {
+            return false;
+        };
+        if name.starts_with("BC_") || name.starts_with("BR_") {
+            let Ok(value) = value.trim().parse() else {
+                return false;
+            };
+            let map = if name.starts_with("BC_") {
+                &mut self.commands
+            } else {
+                &mut self.returns
+            };
+            map.insert(name.to_owned(), value);
+            return true;
+        }
+        let mut words = value.split_whitespace();
+        match (words.next(), words.next(), words.next(), words.next()) {
+            (Some("active"), Some(active), Some("total"), Some(total)) => {
+                match (active.parse(), total.parse()) {
+                    (Ok(active), Ok(total)) => {
+                        self.objects
+                            .insert(name.to_owned(), ObjectCount { active, total });
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parsed `binder_logs/stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Driver-wide counters.
+    pub global: Counters,
+    /// One entry per process and binder context.
+    pub procs: Vec<ProcStats>,
+}
+
+/// A process's section of `binder_logs/stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcStats {
+    pub pid: i32,
+    /// Binder device name (`binder`, `hwbinder`, ...).
+    pub context: String,
+    pub threads: u32,
+    /// Looper threads the driver has asked the process to spawn and not
+    /// yet seen register.
+    pub requested_threads: u32,
+    /// Threads started in answer to those requests.
+    pub requested_threads_started: u32,
+    /// The process's `BINDER_SET_MAX_THREADS`.
+    pub max_threads: u32,
+    /// Threads idle and waiting for work.
+    pub ready_threads: u32,
+    /// Bytes left for oneway transactions.
+    pub free_async_space: u64,
+    pub nodes: u32,
+    pub refs: u32,
+    pub strong_refs: u32,
+    pub weak_refs: u32,
+    /// Allocated transaction buffers.
+    pub buffers: u32,
+    /// Buffer pages in use, on the LRU and free (`active:lru:free`).
+    pub pages: Option<(u32, u32, u32)>,
+    pub pending_transactions: u32,
+    pub counters: Counters,
+}
+
+/// Parse the text of `binder_logs/stats`.
+pub fn parse_stats(text: &str) -> Stats {
+    let mut stats = Stats::default();
+    for line in text.lines() {
+        if let Some(pid) = line
+            .strip_prefix("proc ")
+            .and_then(|pid| pid.trim().parse().ok())
+        {
+            stats.procs.push(ProcStats {
+                pid,
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some(proc) = stats.procs.last_mut() else {
+            stats.global.parse_line(line);
+            continue;
+        };
+        let line = line.trim();
+        if let Some(context) = line.strip_prefix("context ") {
+            proc.context = context.to_owned();
+        } else if let Some(value) = line.strip_prefix("threads: ") {
+            proc.threads = parse_or_default(value);
+        } else if let Some(value) = line.strip_prefix("requested threads: ") {
+            // "%d+%d/%d": requested + started / max.
+            if let Some((requested, rest)) = value.split_once('+') {
+                proc.requested_threads = parse_or_default(requested);
+                if let Some((started, max)) = rest.split_once('/') {
+                    proc.requested_threads_started = parse_or_default(started);
+                    proc.max_threads = parse_or_default(max);
+                }
+            }
+        } else if let Some(value) = line.strip_prefix("ready threads ") {
+            proc.ready_threads = parse_or_default(value);
+        } else if let Some(value) = line.strip_prefix("free async space ") {
+            proc.free_async_space = parse_or_default(value);
+        } else if let Some(value) = line.strip_prefix("nodes: ") {
+            proc.nodes = parse_or_default(value);
+        } else if let Some(value) = line.strip_prefix("refs: ") {
+            // "%d s %d w %d".
+            let words: Vec<&str> = value.split_whitespace().collect();
+            if let [refs, "s", strong, "w", weak] = words[..] {
+                proc.refs = parse_or_default(refs);
+                proc.strong_refs = parse_or_default(strong);
+                proc.weak_refs = parse_or_default(weak);
+            }
+        } else if let Some(value) = line.strip_prefix("buffers: ") {
+            proc.buffers = parse_or_default(value);
+        } else if let Some(value) = line.strip_prefix("pages: ") {
+            let parts: Vec<u32> = value.split(':').filter_map(|v| v.parse().ok()).collect();
+            if let [active, lru, free] = parts[..] {
+                proc.pages = Some((active, lru, free));
+            }
+        } else if let Some(value) = line.strip_prefix("pending transactions: ") {
+            proc.pending_transactions = parse_or_default(value);
+        } else if !proc.counters.parse_line(line) {
+            log::debug!("binder stats: skipping '{line}'");
+        }
+    }
+    stats
+}
+
+/// Parsed `binder_logs/state`, `transactions` or `proc/<pid>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct State {
+    /// Nodes whose owner died while references remained.
+    pub dead_nodes: Vec<Node>,
+    /// One entry per process and binder context.
+    pub procs: Vec<ProcState>,
+}
+
+impl State {
+    /// The sections of process `pid`.
+    pub fn proc(&self, pid: i32) -> impl Iterator<Item = &ProcState> {
+        self.procs.iter().filter(move |proc| proc.pid == pid)
+    }
+
+    /// The process owning node `debug_id`, and the node.
+    pub fn node_owner(&self, debug_id: u32) -> Option<(&ProcState, &Node)> {
+        self.procs.iter().find_map(|proc| {
+            proc.nodes
+                .iter()
+                .find(|node| node.debug_id == debug_id)
+                .map(|node| (proc, node))
+        })
+    }
+}
+
+/// A process's section of the state files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcState {
+    pub pid: i32,
+    /// Binder device name (`binder`, `hwbinder`, ...).
+    pub context: String,
+    pub threads: Vec<Thread>,
+    /// Binder objects this process hosts.
+    pub nodes: Vec<Node>,
+    /// Handles this process holds on other processes' nodes.
+    pub refs: Vec<Ref>,
+    /// Transaction buffers allocated in this process's mapping.
+    pub buffers: Vec<Buffer>,
+    /// Transactions queued on the process, not yet picked up by a thread
+    /// (including oneway transactions queued on its nodes).
+    pub pending: Vec<Transaction>,
+}
+
+/// A binder thread (`print_binder_thread_ilocked`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Thread {
+    pub tid: i32,
+    /// `BINDER_LOOPER_STATE_*` bits.
+    pub looper: u32,
+    pub need_return: bool,
+    /// Temporary references the driver holds on the thread.
+    pub tmp_refs: u32,
+    /// Transactions on the thread's stack and in its queue.
+    pub transactions: Vec<Transaction>,
+}
+
+impl Thread {
+    /// `BINDER_LOOPER_STATE_WAITING`: blocked waiting for work.
+    pub fn is_waiting(&self) -> bool {
+        self.looper & 0x10 != 0
+    }
+}
+
+/// Where a transaction was listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionKind {
+    /// Sent by this thread and awaiting a reply.
+    Outgoing,
+    /// Being served by this thread.
+    Incoming,
+    /// On the thread's stack but involving neither end (a driver bug).
+    Bad,
+    /// Queued and not yet picked up.
+    Pending,
+    /// Oneway transaction queued behind another on the same node.
+    PendingAsync,
+}
+
+/// A transaction (`print_binder_transaction_ilocked`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub kind: TransactionKind,
+    pub debug_id: u32,
+    pub from_pid: i32,
+    pub from_tid: i32,
+    pub to_pid: i32,
+    pub to_tid: i32,
+    pub code: u32,
+    pub flags: u32,
+    /// Time since the transaction was sent (kernels 6.1+).
+    pub elapsed_ms: Option<u64>,
+    /// Target node, if the transaction still has its buffer.
+    pub node: Option<u32>,
+    /// Data and offsets size, if the transaction still has its buffer.
+    pub size: Option<(u64, u64)>,
+}
+
+impl Transaction {
+    pub fn is_oneway(&self) -> bool {
+        self.flags & crate::FLAG_ONEWAY != 0
+    }
+
+    fn parse(kind: TransactionKind, rest: &str) -> Option<Self> {
+        let (debug_id, rest) = rest.split_once(':')?;
+        let mut transaction = Self {
+            kind,
+            debug_id: debug_id.trim().parse().ok()?,
+            from_pid: 0,
+            from_tid: 0,
+            to_pid: 0,
+            to_tid: 0,
+            code: 0,
+            flags: 0,
+            elapsed_ms: None,
+            node: None,
+            size: None,
+        };
+        let mut words = rest.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "from" => (transaction.from_pid, transaction.from_tid) = pair(words.next()?)?,
+                "to" => (transaction.to_pid, transaction.to_tid) = pair(words.next()?)?,
+                "code" => transaction.code = hex(words.next()?)?,
+                "flags" => transaction.flags = hex(words.next()?)?,
+                "elapsed" => {
+                    transaction.elapsed_ms = words.next()?.trim_end_matches("ms").parse().ok()
+                }
+                "node" => transaction.node = words.next()?.parse().ok(),
+                "size" => transaction.size = pair(words.next()?),
+                _ => {}
+            }
+        }
+        Some(transaction)
+    }
+}
+
+/// A binder object (`print_binder_node_nilocked`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Node {
+    pub debug_id: u32,
+    /// The owner's `binder` pointer (its object id).
+    pub ptr: u64,
+    pub cookie: u64,
+    pub has_strong_ref: bool,
+    pub has_weak_ref: bool,
+    pub local_strong_refs: u32,
+    pub local_weak_refs: u32,
+    pub internal_strong_refs: u32,
+    /// Number of refs on the node.
+    pub refs: u32,
+    pub tmp_refs: u32,
+    /// Processes holding refs on the node.
+    pub ref_procs: Vec<i32>,
+}
+
+impl Node {
+    fn parse(rest: &str) -> Option<Self> {
+        let (debug_id, rest) = rest.split_once(':')?;
+        let mut node = Self {
+            debug_id: debug_id.trim().parse().ok()?,
+            ..Default::default()
+        };
+        let mut words = rest.split_whitespace();
+        while let Some(word) = words.next() {
+            if let Some(ptr) = word.strip_prefix('u') {
+                if let Ok(ptr) = u64::from_str_radix(ptr, 16) {
+                    node.ptr = ptr;
+                    continue;
+                }
+            }
+            if let Some(cookie) = word.strip_prefix('c') {
+                if let Ok(cookie) = u64::from_str_radix(cookie, 16) {
+                    node.cookie = cookie;
+                    continue;
+                }
+            }
+            match word {
+                "hs" => node.has_strong_ref = words.next()? != "0",
+                "hw" => node.has_weak_ref = words.next()? != "0",
+                "ls" => node.local_strong_refs = words.next()?.parse().ok()?,
+                "lw" => node.local_weak_refs = words.next()?.parse().ok()?,
+                "is" => node.internal_strong_refs = words.next()?.parse().ok()?,
+                "iw" => node.refs = words.next()?.parse().ok()?,
+                "tr" => node.tmp_refs = words.next()?.parse().ok()?,
+                "proc" => {
+                    node.ref_procs = words.by_ref().filter_map(|pid| pid.parse().ok()).collect()
+                }
+                "pri" => {
+                    words.next();
+                }
+                _ => {}
+            }
+        }
+        Some(node)
+    }
+}
+
+/// A handle on another process's node (`print_binder_ref_olocked`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ref {
+    pub debug_id: u32,
+    /// The handle value the holder uses.
+    pub desc: u32,
+    /// `debug_id` of the referenced [`Node`].
+    pub node: u32,
+    /// The node's owner has died.
+    pub dead: bool,
+    pub strong: u32,
+    pub weak: u32,
+    /// A death notification is registered.
+    pub death_notification: bool,
+}
+
+impl Ref {
+    fn parse(rest: &str) -> Option<Self> {
+        let (debug_id, rest) = rest.split_once(':')?;
+        let mut r = Self {
+            debug_id: debug_id.trim().parse().ok()?,
+            ..Default::default()
+        };
+        let mut words = rest.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "desc" => r.desc = words.next()?.parse().ok()?,
+                "dead" => r.dead = true,
+                "node" => r.node = words.next()?.parse().ok()?,
+                "s" => r.strong = words.next()?.parse().ok()?,
+                "w" => r.weak = words.next()?.parse().ok()?,
+                "d" => r.death_notification = words.next()?.chars().any(|c| c != '0' && c != 'x'),
+                _ => {}
+            }
+        }
+        Some(r)
+    }
+}
+
+/// A transaction buffer (`print_binder_buffer`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Buffer {
+    pub debug_id: u32,
+    pub data_size: u64,
+    pub offsets_size: u64,
+    pub extra_buffers_size: u64,
+    /// Handed to user space (`delivered`) rather than still owned by a
+    /// transaction in flight (`active`).
+    pub delivered: bool,
+}
+
+impl Buffer {
+    fn parse(rest: &str) -> Option<Self> {
+        let (debug_id, rest) = rest.split_once(':')?;
+        let mut buffer = Self {
+            debug_id: debug_id.trim().parse().ok()?,
+            ..Default::default()
+        };
+        let mut words = rest.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "size" => {
+                    let sizes: Vec<u64> = words
+                        .next()?
+                        .split(':')
+                        .filter_map(|v| v.parse().ok())
+                        .collect();
+                    if let [data, offsets, extra] = sizes[..] {
+                        buffer.data_size = data;
+                        buffer.offsets_size = offsets;
+                        buffer.extra_buffers_size = extra;
+                    }
+                }
+                "delivered" => buffer.delivered = true,
+                _ => {}
+            }
+        }
+        Some(buffer)
+    }
+}
+
+/// Parse the text of `binder_logs/state`, `transactions` or `proc/<pid>`.
+pub fn parse_state(text: &str) -> State {
+    let mut state = State::default();
+    // Indentation tells a thread's transactions from the process's.
+    let mut in_thread = false;
+    for raw in text.lines() {
+        let line = raw.trim();
+        let indent = raw.len() - raw.trim_start().len();
+        if let Some(pid) = line.strip_prefix("proc ").and_then(|pid| pid.parse().ok()) {
+            if indent == 0 {
+                state.procs.push(ProcState {
+                    pid,
+                    ..Default::default()
+                });
+                in_thread = false;
+                continue;
+            }
+        }
+        if let Some(rest) = line.strip_prefix("node ") {
+            let Some(node) = Node::parse(rest) else {
+                log::debug!("binder state: skipping '{line}'");
+                continue;
+            };
+            match state.procs.last_mut() {
+                Some(proc) => proc.nodes.push(node),
+                None => state.dead_nodes.push(node),
+            }
+            in_thread = false;
+            continue;
+        }
+        let Some(proc) = state.procs.last_mut() else {
+            continue;
+        };
+        if let Some(context) = line.strip_prefix("context ") {
+            proc.context = context.to_owned();
+        } else if let Some(rest) = line.strip_prefix("thread ") {
+            if let Some(thread) = parse_thread(rest) {
+                proc.threads.push(thread);
+                in_thread = true;
+            }
+        } else if let Some(rest) = line.strip_prefix("ref ") {
+            proc.refs.extend(Ref::parse(rest));
+            in_thread = false;
+        } else if let Some(rest) = line.strip_prefix("buffer ") {
+            proc.buffers.extend(Buffer::parse(rest));
+            in_thread = false;
+        } else if let Some((kind, rest)) = transaction_line(line) {
+            let Some(transaction) = Transaction::parse(kind, rest) else {
+                log::debug!("binder state: skipping '{line}'");
+                continue;
+            };
+            // Thread work is indented deeper than the thread line; process
+            // work sits at the thread line's level.
+            match proc.threads.last_mut() {
+                Some(thread) if in_thread && indent > 2 => thread.transactions.push(transaction),
+                _ => proc.pending.push(transaction),
+            }
+        } else if !line.is_empty() && !line.ends_with(':') {
+            log::debug!("binder state: skipping '{line}'");
+        }
+    }
+    state
+}
+
+fn parse_thread(rest: &str) -> Option<Thread> {
+    let (tid, rest) = rest.split_once(':')?;
+    let mut thread = Thread {
+        tid: tid.trim().parse().ok()?,
+        ..Default::default()
+    };
+    let mut words = rest.split_whitespace();
+    while let Some(word) = words.next() {
+        match word {
+            "l" => thread.looper = hex(words.next()?)?,
+            "need_return" => thread.need_return = words.next()? != "0",
+            "tr" => thread.tmp_refs = words.next()?.parse().ok()?,
+            _ => {}
+        }
+    }
+    Some(thread)
+}
+
+fn transaction_line(line: &str) -> Option<(TransactionKind, &str)> {
+    const KINDS: [(&str, TransactionKind); 5] = [
+        ("outgoing transaction ", TransactionKind::Outgoing),
+        ("incoming transaction ", TransactionKind::Incoming),
+        ("bad transaction ", TransactionKind::Bad),
+        ("pending transaction ", TransactionKind::Pending),
+        ("pending async transaction ", TransactionKind::PendingAsync),
+    ];
+    KINDS
+        .iter()
+        .find_map(|(prefix, kind)| line.strip_prefix(prefix).map(|rest| (*kind, rest)))
+}
+
+/// How a logged transaction was sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallType {
+    Call,
+    Async,
+    Reply,
+}
+
+/// An entry of `transaction_log` or `failed_transaction_log`
+/// (`print_binder_transaction_log_entry`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub debug_id: u32,
+    pub call_type: CallType,
+    pub from_pid: i32,
+    pub from_tid: i32,
+    pub to_pid: i32,
+    pub to_tid: i32,
+    pub context: String,
+    /// Target node's `debug_id`.
+    pub to_node: u32,
+    /// Handle the sender used (meaningless for replies).
+    pub handle: i32,
+    pub data_size: u64,
+    pub offsets_size: u64,
+    /// `BR_*` code the sender got, `BR_OK` (`0`) if it succeeded.
+    pub return_error: u32,
+    /// Errno behind `return_error`.
+    pub return_error_param: i32,
+    /// Line in `binder.c` that failed the transaction.
+    pub return_error_line: u32,
+    /// The transaction was still in flight when the log was read.
+    pub incomplete: bool,
+}
+
+impl LogEntry {
+    pub fn failed(&self) -> bool {
+        self.return_error != 0 && self.return_error != crate::sys::binder::BR_OK
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let (debug_id, rest) = line.split_once(':')?;
+        let mut words = rest.split_whitespace();
+        let call_type = match words.next()? {
+            "call" => CallType::Call,
+            "async" => CallType::Async,
+            "reply" => CallType::Reply,
+            _ => return None,
+        };
+        let mut entry = Self {
+            debug_id: debug_id.trim().parse().ok()?,
+            call_type,
+            from_pid: 0,
+            from_tid: 0,
+            to_pid: 0,
+            to_tid: 0,
+            context: String::new(),
+            to_node: 0,
+            handle: 0,
+            data_size: 0,
+            offsets_size: 0,
+            return_error: 0,
+            return_error_param: 0,
+            return_error_line: 0,
+            incomplete: rest.trim_end().ends_with("(incomplete)"),
+        };
+        while let Some(word) = words.next() {
+            match word {
+                "from" => (entry.from_pid, entry.from_tid) = pair(words.next()?)?,
+                "to" => (entry.to_pid, entry.to_tid) = pair(words.next()?)?,
+                "context" => entry.context = words.next()?.to_owned(),
+                "node" => entry.to_node = words.next()?.parse().ok()?,
+                "handle" => entry.handle = words.next()?.parse().ok()?,
+                "size" => (entry.data_size, entry.offsets_size) = pair(words.next()?)?,
+                "ret" => {
+                    let (error, param) = words.next()?.split_once('/')?;
+                    entry.return_error = error.parse().ok()?;
+                    entry.return_error_param = param.parse().ok()?;
+                }
+                _ => {
+                    if let Some(line) = word.strip_prefix("l=") {
+                        entry.return_error_line = line.parse().ok()?;
+                    }
+                }
+            }
+        }
+        Some(entry)
+    }
+}
+
+/// Parse the text of `transaction_log` or `failed_transaction_log`.
+pub fn parse_transaction_log(text: &str) -> Vec<LogEntry> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let entry = LogEntry::parse(line);
+            if entry.is_none() {
+                log::debug!("binder transaction log: skipping '{line}'");
+            }
+            entry
+        })
+        .collect()
+}
+
+fn parse_or_default<T: FromStr + Default>(value: &str) -> T {
+    value.trim().parse().unwrap_or_default()
+}
+
+fn pair<T: FromStr>(value: &str) -> Option<(T, T)> {
+    let (a, b) = value.split_once(':')?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+}
+
+fn hex(value: &str) -> Option<u32> {
+    u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATS: &str = "\
+binder stats:
+BC_TRANSACTION: 245
+BC_REPLY: 120
+BR_TRANSACTION_COMPLETE: 245
+proc: active 3 total 12
+thread: active 9 total 40
+proc 812
+context binder
+  threads: 4
+  requested threads: 0+2/15
+  ready threads 3
+  free async space 520192
+  nodes: 2
+  refs: 1 s 1 w 1
+  buffers: 1
+  pages: 1:2:61
+  pages high watermark: 4
+  pending transactions: 0
+  BC_TRANSACTION: 10
+  BR_REPLY: 10
+proc 900
+context hwbinder
+  threads: 1
+";
+
+    #[test]
+    fn stats() {
+        let stats = parse_stats(STATS);
+        assert_eq!(stats.global.commands["BC_TRANSACTION"], 245);
+        assert_eq!(stats.global.returns["BR_TRANSACTION_COMPLETE"], 245);
+        assert_eq!(
+            stats.global.objects["thread"],
+            ObjectCount {
+                active: 9,
+                total: 40
+            }
+        );
+        assert_eq!(stats.procs.len(), 2);
+        let proc = &stats.procs[0];
+        assert_eq!(proc.pid, 812);
+        assert_eq!(proc.context, "binder");
+        assert_eq!(
+            (
+                proc.threads,
+                proc.requested_threads,
+                proc.requested_threads_started,
+                proc.max_threads,
+                proc.ready_threads
+            ),
+            (4, 0, 2, 15, 3)
+        );
+        assert_eq!(proc.free_async_space, 520192);
+        assert_eq!((proc.refs, proc.strong_refs, proc.weak_refs), (1, 1, 1));
+        assert_eq!(proc.pages, Some((1, 2, 61)));
+        assert_eq!(proc.counters.commands["BC_TRANSACTION"], 10);
+        assert_eq!(stats.procs[1].context, "hwbinder");
+    }
+
+    const STATE: &str = "\
+binder state:
+dead nodes:
+  node 77: u0000000000000000 c0000000000000000 hs 1 hw 1 ls 0 lw 0 is 1 iw 1 tr 1 proc 812
+proc 812
+context binder
+  thread 812: l 12 need_return 0 tr 0
+    outgoing transaction 4021: 0000000000000000 from 812:812 to 900:905 code 3 flags 10 pri 0:120 r1 elapsed 5032ms node 41 size 96:8 offset 0
+  thread 813: l 11 need_return 0 tr 0
+  node 12: u00007f12345678a0 c00007f1234567900 pri 0:139 hs 1 hw 1 ls 0 lw 0 is 2 iw 2 tr 1 proc 900 901
+    pending async transaction 4030: 0000000000000000 from 901:0 to 812:0 code 1 flags 11 pri 0:120 r0 node 12 size 4:0 data 0000000000000000
+  ref 20: desc 0 node 1 s 1 w 1 d 0000000000000000
+  ref 21: desc 1 dead node 77 s 1 w 1 d 00000000a1b2c3d4
+  buffer 4012: 0000000000000000 size 16:0:0 delivered
+  pending transaction 4031: 0000000000000000 from 901:902 to 812:0 code 2 flags 10 pri 0:120 r1 node 12 size 8:0 data 0000000000000000
+proc 900
+context binder
+  thread 905: l 01 need_return 0 tr 0
+    incoming transaction 4021: 0000000000000000 from 812:812 to 900:905 code 3 flags 10 pri 0:120 r1 node 41 size 96:8 data 0000000000000000
+  node 41: u0000000000001000 c0000000000002000 hs 1 hw 1 ls 0 lw 0 is 1 iw 1 tr 1 proc 812
+";
+
+    #[test]
+    fn state() {
+        let state = parse_state(STATE);
+        assert_eq!(state.dead_nodes.len(), 1);
+        assert_eq!(state.dead_nodes[0].ref_procs, [812]);
+
+        let proc = state.proc(812).next().unwrap();
+        assert_eq!(proc.context, "binder");
+        assert_eq!(proc.threads.len(), 2);
+        let outgoing = &proc.threads[0].transactions[0];
+        assert_eq!(outgoing.kind, TransactionKind::Outgoing);
+        assert_eq!(
+            (outgoing.to_pid, outgoing.to_tid, outgoing.code),
+            (900, 905, 3)
+        );
+        assert_eq!(outgoing.elapsed_ms, Some(5032));
+        assert_eq!(outgoing.size, Some((96, 8)));
+        assert!(!outgoing.is_oneway());
+        assert!(proc.threads[1].transactions.is_empty());
+
+        assert_eq!(proc.nodes.len(), 1);
+        let node = &proc.nodes[0];
+        assert_eq!(node.ptr, 0x7f12345678a0);
+        assert_eq!(node.cookie, 0x7f1234567900);
+        assert_eq!(node.ref_procs, [900, 901]);
+
+        assert_eq!(proc.refs.len(), 2);
+        assert!(!proc.refs[0].dead && !proc.refs[0].death_notification);
+        assert!(proc.refs[1].dead && proc.refs[1].death_notification);
+        assert_eq!(proc.refs[1].node, 77);
+
+        assert_eq!(proc.buffers[0].data_size, 16);
+        assert!(proc.buffers[0].delivered);
+
+        let kinds: Vec<_> = proc.pending.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            [TransactionKind::PendingAsync, TransactionKind::Pending]
+        );
+        assert!(proc.pending[0].is_oneway());
+
+        let incoming = &state.proc(900).next().unwrap().threads[0].transactions[0];
+        assert_eq!(incoming.kind, TransactionKind::Incoming);
+        assert_eq!(incoming.elapsed_ms, None);
+        let (owner, _) = state.node_owner(41).unwrap();
+        assert_eq!(owner.pid, 900);
+    }
+
+    #[test]
+    fn transaction_log() {
+        let log = parse_transaction_log(
+            "\
+4021: call  from 812:812 to 900:905 context binder node 41 handle 3 size 96:8 ret 0/0 l=0
+4022: reply from 900:905 to 812:812 context binder node 0 handle -1 size 4:0 ret 0/0 l=0
+4023: async from 812:813 to 900:0 context binder node 41 handle 3 size 4:0 ret 29201/-28 l=3412 (incomplete)
+garbage
+",
+        );
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].call_type, CallType::Call);
+        assert_eq!((log[0].to_pid, log[0].handle), (900, 3));
+        assert!(!log[0].failed());
+        assert_eq!(log[1].call_type, CallType::Reply);
+        assert_eq!(log[1].handle, -1);
+        let failed = &log[2];
+        assert_eq!(failed.call_type, CallType::Async);
+        assert!(failed.failed());
+        assert_eq!(failed.return_error_param, -28);
+        assert_eq!(failed.return_error_line, 3412);
+        assert!(failed.incomplete);
+    }
+}
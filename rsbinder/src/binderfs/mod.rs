@@ -4,7 +4,8 @@
 //! BinderFS filesystem utilities.
 //!
 //! This module provides functions for managing binder devices in the binderfs
-//! filesystem, including adding new binder devices dynamically.
+//! filesystem, including adding new binder devices dynamically, and typed
+//! readers for the driver's debug logs in [`logs`].
 
 use crate::sys::binder;
 use log;
@@ -12,6 +13,8 @@ use std::ffi::CString;
 use std::fs::File;
 use std::path::Path;
 
+pub mod logs;
+
 /// Add a new binder device to the binderfs.
 ///
 /// Creates a new binder device node in the binderfs with the specified name.
@@ -0,0 +1,175 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Additional binder contexts: more than one binder device in one process.
+
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::hub::{self, ServiceManager};
+use crate::process_state::EnteredContext;
+use crate::{ProcessState, Result, SIBinder};
+
+/// An additional binder context: a [`ProcessState`] of its own — driver
+/// fd, mapping, thread pool, handle table and context manager — for a
+/// second device, e.g. a `vndbinder`-like binderfs node next to
+/// `/dev/binderfs/binder`. The [`ProcessState::init`] singleton stays the
+/// default context. Cheap to copy.
+///
+/// The context's service manager is [`BinderContext::service_manager`];
+/// its methods, and the proxies they return, call through this context
+/// from any thread. A generated proxy is made from a binder
+/// (`from_binder`, [`crate::Strong`] conversions) and calls through the
+/// binder's context, so typed interfaces need no entry point of their
+/// own:
+///
+/// ```no_run
+/// use rsbinder::{hub, BinderContext, ProcessState};
+///
+/// ProcessState::init_default().unwrap();
+/// ProcessState::start_thread_pool();
+/// let vendor = BinderContext::open("/dev/binderfs/vndbinder", 0).unwrap();
+/// vendor.start_thread_pool();
+///
+/// let system_services = hub::list_services(hub::DUMP_FLAG_PRIORITY_ALL);
+/// let vendor_services = vendor
+///     .service_manager()
+///     .unwrap()
+///     .list_services(hub::DUMP_FLAG_PRIORITY_ALL);
+/// ```
+///
+/// Everything else finds the context through [`ProcessState::as_self`],
+/// which returns the context of the calling thread, the default one
+/// unless:
+///
+/// - the thread is one of a context's pool threads, which serve its
+///   transactions in it;
+/// - [`BinderContext::enter`] (or [`BinderContext::run`]) made a context
+///   current for a scope, so the free functions of [`crate::hub`],
+///   `as_self` and everything built on them use it.
+///
+/// A proxy remembers the context it was obtained in, and a parcel the
+/// context it is sent or was received in: binders read from a reply are
+/// proxies of the context the call went through, wherever the reply is
+/// read. Writing a proxy into a parcel of another context fails with
+/// [`crate::StatusCode::BadValue`]; a gateway passes on what the proxy
+/// refers to, e.g. a local binder forwarding to it. Local binders can be
+/// passed through any context. A thread keeps separate binder state per
+/// context it uses, so a call into one context from a transaction served
+/// by another nests as expected.
+///
+/// # Lifetime
+///
+/// A context is never closed. Its proxies, parcels and pool threads refer
+/// to it by `&'static` reference, as they do to the singleton, so its
+/// driver fd, mapping, handle table and pool threads stay for the rest of
+/// the process; no call stops the pool. Open the contexts a process needs
+/// once, at startup, not per use: each [`BinderContext::open`] takes
+/// another set.
+///
+/// The async (`tokio`) pool serves the default context only.
+#[derive(Clone, Copy)]
+pub struct BinderContext {
+    state: &'static ProcessState,
+}
+
+impl BinderContext {
+    /// Open `driver_name` with at most `max_threads` pool threads (`0`:
+    /// the default), as [`ProcessState::init`] does for the default
+    /// context. Each call opens a separate context, kept for the rest of
+    /// the process; see [Lifetime](BinderContext#lifetime).
+    pub fn open(
+        driver_name: &str,
+        max_threads: u32,
+    ) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        Ok(BinderContext {
+            state: ProcessState::open(driver_name, max_threads)?,
+        })
+    }
+
+    /// Make this the context of the calling thread until the guard is
+    /// dropped. Guards nest; dropping one restores the previous context.
+    pub fn enter(&self) -> ContextGuard {
+        ContextGuard {
+            _entered: ProcessState::enter(Some(self.state)),
+        }
+    }
+
+    /// Run `f` with this context entered.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _guard = self.enter();
+        f()
+    }
+
+    /// The context's `ProcessState`, for its settings (spam detection,
+    /// large-transaction reports, ...). Calls that talk to the driver
+    /// should be made inside [`Self::enter`].
+    pub fn process_state(&self) -> &'static ProcessState {
+        self.state
+    }
+
+    /// [`ProcessState::start_thread_pool`] for this context.
+    pub fn start_thread_pool(&self) {
+        self.run(ProcessState::start_thread_pool)
+    }
+
+    /// [`ProcessState::join_thread_pool`] for this context: serve its
+    /// transactions on the calling thread.
+    pub fn join_thread_pool(&self) -> Result<()> {
+        self.run(ProcessState::join_thread_pool)
+    }
+
+    /// The service manager of this context, [`hub::default`] inside
+    /// [`Self::enter`]: cached and watched for restarts per context.
+    pub fn service_manager(&self) -> Result<Arc<ServiceManager>> {
+        self.run(hub::default)
+    }
+
+    /// [`hub::set_restart_callback`] for this context's service manager.
+    pub fn set_restart_callback(&self, callback: Option<hub::RestartCallback>) {
+        self.run(|| hub::set_restart_callback(callback))
+    }
+
+    /// The context manager (handle 0) of this context's device.
+    pub fn context_object(&self) -> Result<SIBinder> {
+        self.run(|| self.state.context_object())
+    }
+
+    /// Register `binder` as the context manager of this context's device;
+    /// see [`ProcessState::become_context_manager`].
+    pub fn become_context_manager(
+        &self,
+        binder: SIBinder,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        self.run(|| self.state.become_context_manager(binder))
+    }
+
+    /// The device this context was opened on.
+    pub fn driver_name(&self) -> &Path {
+        self.state.driver_name()
+    }
+}
+
+impl PartialEq for BinderContext {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.state, other.state)
+    }
+}
+
+impl Eq for BinderContext {}
+
+impl Debug for BinderContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BinderContext")
+            .field("driver_name", &self.driver_name())
+            .finish()
+    }
+}
+
+/// Keeps a [`BinderContext`] current on this thread; see
+/// [`BinderContext::enter`].
+#[must_use = "the context is left when the guard is dropped"]
+pub struct ContextGuard {
+    _entered: EnteredContext,
+}
@@ -0,0 +1,356 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deadlines for synchronous kernel binder calls.
+//!
+//! A thread whose transaction is awaiting its reply cannot abandon it:
+//! the driver keeps the transaction on the thread's stack, and every
+//! later synchronous call from that thread fails until the reply
+//! arrives. A call with a deadline is therefore sent and awaited by a
+//! helper thread while the caller waits for the helper with a timeout.
+//! The driver copies the request before the helper reports it sent, so
+//! the caller's `Parcel` is no longer read once the timed wait starts;
+//! a reply that arrives after the caller gave up is dropped by the
+//! helper, which frees its buffer.
+//!
+//! Helpers are parked in a process-wide idle list and reused. A helper
+//! stuck behind a hung service is simply not idle, so the list only
+//! grows to the number of timed calls outstanding at once. A helper
+//! whose caller gave up cannot be reclaimed before the reply comes, so
+//! at most [`MAX_ABANDONED`] of them wait on one object: further timed
+//! calls to it fail with [`StatusCode::TimedOut`] at once, without
+//! reaching the driver, until one of those replies arrives.
+//!
+//! The helper is the thread the driver sees as the caller. A nested
+//! call the remote side makes back into this process while handling a
+//! timed call is served on the helper, not on the calling thread, so it
+//! does not see the caller's thread-locals and must not need locks the
+//! caller holds across the call. Likewise, a timed call made while
+//! serving a transaction is a fresh transaction to the driver, so calls
+//! back into the original client need a free thread there.
+
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::thread_state::{self, ExtendedError};
+use crate::{Parcel, ProcessState, Result, StatusCode, TransactionCode, TransactionFlags};
+
+/// Helpers left waiting for a reply their caller gave up on, per object,
+/// beyond which timed calls to that object fail without being sent.
+pub(crate) const MAX_ABANDONED: usize = 4;
+
+/// Idle helpers, waiting for their next call.
+static IDLE: Mutex<Vec<Helper>> = Mutex::new(Vec::new());
+
+/// Helpers still waiting for a reply their caller gave up on, per target.
+static ABANDONED: Mutex<Vec<(Target, usize)>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// `(scope, handle, timeout)` of the live `WithTimeout` guards of
+    /// this thread, innermost last.
+    static SCOPED: RefCell<Vec<(u64, u32, Duration)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_SCOPE: Cell<u64> = const { Cell::new(0) };
+}
+
+struct Helper {
+    /// The fake-driver process the helper's thread belongs to.
+    process: u32,
+    /// The binder context whose driver it calls.
+    context: Option<&'static ProcessState>,
+    jobs: mpsc::Sender<Job>,
+}
+
+/// The object a timed call goes to: fake-driver process, binder context
+/// and handle.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Target {
+    process: u32,
+    /// Address of the context's `ProcessState`, `0` for the singleton.
+    context: usize,
+    handle: u32,
+}
+
+/// [`Job::state`] values.
+const WAITING: u8 = 0;
+const ABANDONED_BY_CALLER: u8 = 1;
+const DONE: u8 = 2;
+
+struct Job {
+    target: Target,
+    code: TransactionCode,
+    data: Request,
+    flags: TransactionFlags,
+    events: mpsc::Sender<Event>,
+    /// `WAITING` until the helper has the reply (`DONE`) or the caller
+    /// gave up (`ABANDONED_BY_CALLER`); changed under the `ABANDONED` lock.
+    state: Arc<AtomicU8>,
+}
+
+/// The caller's request parcel, borrowed by the helper only until it
+/// sends its first [`Event`].
+struct Request(*const Parcel);
+
+// SAFETY: `transact` creates the `Request` from a `&Parcel` it holds for
+// its whole body. Once `submit` has handed the job to a helper,
+// `transact` does not return before its first `recv` yields the
+// helper's first event or reports the helper gone (the job, and with it
+// the event sender, dropped). The helper dereferences the pointer only
+// in `run` before sending that first event, and a job dropped unread
+// (failed `submit`) never dereferences it. So the parcel outlives every
+// access, and while the helper reads it the caller is blocked in `recv`
+// and does not touch it.
+unsafe impl Send for Request {}
+
+enum Event {
+    /// The driver has the request; the caller's parcel is free.
+    Sent,
+    /// The reply, and the driver's detail of a failure. Boxed: a
+    /// `Parcel` dwarfs the other variant.
+    Done(Box<(Result<Option<Parcel>>, Option<ExtendedError>)>),
+}
+
+/// [`thread_state::transact`] for a synchronous call that fails with
+/// [`StatusCode::TimedOut`] when no reply arrives within `timeout`.
+pub(crate) fn transact(
+    handle: u32,
+    code: TransactionCode,
+    data: &Parcel,
+    flags: TransactionFlags,
+    timeout: Duration,
+) -> Result<Option<Parcel>> {
+    let Some(deadline) = Instant::now().checked_add(timeout) else {
+        return thread_state::transact(handle, code, data, flags);
+    };
+    // The helper's transaction starts with its own detail cleared; so
+    // does this thread's, as if it sent the call itself.
+    thread_state::set_extended_error(None);
+    let target = Target {
+        process: current_process(),
+        context: context_id(ProcessState::current()),
+        handle,
+    };
+    if abandoned(target) >= MAX_ABANDONED {
+        log::warn!(
+            "binder call {code} to handle {handle} not sent: {MAX_ABANDONED} earlier \
+             timed-out calls are still waiting for replies"
+        );
+        return Err(StatusCode::TimedOut);
+    }
+    let (events, receiver) = mpsc::channel();
+    let state = Arc::new(AtomicU8::new(WAITING));
+    submit(Job {
+        target,
+        code,
+        data: Request(data),
+        flags,
+        events,
+        state: state.clone(),
+    })?;
+    // Not timed: the helper reads `data` until this event, and sending
+    // never waits for the remote side.
+    match receiver.recv() {
+        Ok(Event::Sent) => {}
+        Ok(Event::Done(done)) => return finish(*done),
+        Err(_) => return Err(StatusCode::FailedTransaction),
+    }
+    match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(Event::Done(done)) => finish(*done),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            if !abandon(target, &state) {
+                // The reply came in just now.
+                if let Ok(Event::Done(done)) = receiver.recv() {
+                    return finish(*done);
+                }
+            }
+            log::warn!("binder call {code} to handle {handle} timed out after {timeout:?}");
+            Err(StatusCode::TimedOut)
+        }
+        // The helper only goes away without a reply if it panicked.
+        Ok(Event::Sent) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Err(StatusCode::FailedTransaction)
+        }
+    }
+}
+
+/// Return the helper's reply on the calling thread, along with the
+/// failure detail `get_extended_error` reports.
+fn finish(
+    (reply, extended_error): (Result<Option<Parcel>>, Option<ExtendedError>),
+) -> Result<Option<Parcel>> {
+    if reply.is_err() {
+        thread_state::set_extended_error(extended_error);
+    }
+    reply
+}
+
+fn context_id(context: Option<&'static ProcessState>) -> usize {
+    context.map_or(0, |context| std::ptr::from_ref(context) as usize)
+}
+
+fn abandoned_lock() -> std::sync::MutexGuard<'static, Vec<(Target, usize)>> {
+    ABANDONED
+        .lock()
+        .expect("deadline abandoned-call lock poisoned")
+}
+
+/// Helpers waiting on `target` for replies their callers gave up on.
+fn abandoned(target: Target) -> usize {
+    abandoned_lock()
+        .iter()
+        .find(|entry| entry.0 == target)
+        .map_or(0, |entry| entry.1)
+}
+
+/// Count the job as abandoned by its caller; false if its reply is
+/// already in.
+fn abandon(target: Target, state: &AtomicU8) -> bool {
+    let mut abandoned = abandoned_lock();
+    if state
+        .compare_exchange(
+            WAITING,
+            ABANDONED_BY_CALLER,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        )
+        .is_err()
+    {
+        return false;
+    }
+    match abandoned.iter_mut().find(|entry| entry.0 == target) {
+        Some(entry) => entry.1 += 1,
+        None => abandoned.push((target, 1)),
+    }
+    true
+}
+
+/// Mark the job's reply as in, uncounting it if its caller gave up.
+fn complete(target: Target, state: &AtomicU8) {
+    let mut abandoned = abandoned_lock();
+    if state.swap(DONE, Ordering::Relaxed) != ABANDONED_BY_CALLER {
+        return;
+    }
+    if let Some(index) = abandoned.iter().position(|entry| entry.0 == target) {
+        abandoned[index].1 -= 1;
+        if abandoned[index].1 == 0 {
+            abandoned.swap_remove(index);
+        }
+    }
+}
+
+/// Hand `job` to an idle helper of the calling thread's process and
+/// binder context, or to a new one.
+fn submit(job: Job) -> Result<()> {
+    let process = job.target.process;
+    let context = ProcessState::current();
+    let idle = {
+        let mut idle = IDLE.lock().expect("deadline helper lock poisoned");
+        idle.iter()
+            .position(|helper| {
+                helper.process == process && context_id(helper.context) == job.target.context
+            })
+            .map(|index| idle.swap_remove(index))
+    };
+    let jobs = match idle {
+        Some(helper) => helper.jobs,
+        None => spawn(process, context)?,
+    };
+    // A failed send drops the job unread; the caller sees the
+    // disconnected event channel.
+    jobs.send(job).map_err(|_| StatusCode::FailedTransaction)
+}
+
+fn spawn(process: u32, context: Option<&'static ProcessState>) -> Result<mpsc::Sender<Job>> {
+    let (jobs, receiver) = mpsc::channel();
+    let parked = jobs.clone();
+    thread::Builder::new()
+        .name("binder:deadline".to_owned())
+        .spawn(move || run(process, context, parked, receiver))
+        .map_err(|err| {
+            log::error!("failed to spawn a binder deadline helper: {err}");
+            StatusCode::NoMemory
+        })?;
+    Ok(jobs)
+}
+
+fn run(
+    process: u32,
+    context: Option<&'static ProcessState>,
+    parked: mpsc::Sender<Job>,
+    receiver: mpsc::Receiver<Job>,
+) {
+    enter_process(process);
+    let _context = ProcessState::enter(context);
+    for job in receiver {
+        let started = {
+            // SAFETY: see `Request`; `data` does not outlive this block.
+            let data = unsafe { &*job.data.0 };
+            thread_state::start_transact(job.target.handle, job.code, data, job.flags)
+        };
+        let reply = match started {
+            Ok(pending) => {
+                let _ = job.events.send(Event::Sent);
+                pending.wait()
+            }
+            Err(err) => Err(err),
+        };
+        let extended_error = thread_state::take_extended_error();
+        complete(job.target, &job.state);
+        // If the caller timed out the reply comes back with the error
+        // and is dropped here, freeing its buffer.
+        let _ = job
+            .events
+            .send(Event::Done(Box::new((reply, extended_error))));
+        IDLE.lock()
+            .expect("deadline helper lock poisoned")
+            .push(Helper {
+                process,
+                context,
+                jobs: parked.clone(),
+            });
+    }
+}
+
+#[cfg(feature = "fake-driver")]
+fn current_process() -> u32 {
+    crate::fake_driver::current_process()
+}
+
+#[cfg(not(feature = "fake-driver"))]
+fn current_process() -> u32 {
+    0
+}
+
+#[cfg(feature = "fake-driver")]
+fn enter_process(process: u32) {
+    crate::fake_driver::enter_process(process);
+}
+
+#[cfg(not(feature = "fake-driver"))]
+fn enter_process(_process: u32) {}
+
+/// Apply `timeout` to this thread's calls to `handle` until
+/// [`pop_scoped`] is called with the returned scope.
+pub(crate) fn push_scoped(handle: u32, timeout: Duration) -> u64 {
+    let scope = NEXT_SCOPE.with(|next| next.replace(next.get() + 1));
+    SCOPED.with(|scoped| scoped.borrow_mut().push((scope, handle, timeout)));
+    scope
+}
+
+pub(crate) fn pop_scoped(scope: u64) {
+    SCOPED.with(|scoped| scoped.borrow_mut().retain(|entry| entry.0 != scope));
+}
+
+/// The innermost scoped timeout of this thread for `handle`.
+pub(crate) fn scoped_timeout(handle: u32) -> Option<Duration> {
+    SCOPED.with(|scoped| {
+        scoped
+            .borrow()
+            .iter()
+            .rev()
+            .find(|entry| entry.1 == handle)
+            .map(|entry| entry.2)
+    })
+}
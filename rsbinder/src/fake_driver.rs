@@ -0,0 +1,1489 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! An in-process emulation of the binder kernel driver, for hermetic tests.
+//!
+//! Initializing [`ProcessState`] with [`FAKE_BINDER_PATH`] routes every
+//! binder round-trip of the process to a userspace driver instead of a
+//! binderfs device, so service logic runs without root, the binder kernel
+//! module or a service manager process. The rest of rsbinder is unchanged:
+//! the same `BC_*` commands go in and the same `BR_*` returns come out.
+//!
+//! One OS process hosts several simulated binder processes. A thread
+//! belongs to the [`FakeProcess`] that spawned it ([`FakeProcess::spawn`],
+//! or a pool thread of [`FakeProcess::start_thread_pool`]); every other
+//! thread, including the main thread and the pool of
+//! [`ProcessState::start_thread_pool`], belongs to the default process,
+//! which reports the real pid and euid to the services it calls.
+//! A [`crate::BinderContext`] opened on [`FAKE_BINDER_PATH`] gets a fake
+//! driver of its own, as if it were a second binder device.
+//!
+//! What the driver emulates:
+//!
+//! - handles and reference counting, including the `BR_INCREFS` /
+//!   `BR_ACQUIRE` / `BR_RELEASE` / `BR_DECREFS` traffic that keeps
+//!   published natives alive while another process holds them;
+//! - synchronous calls, with nested calls routed back to the thread that
+//!   is waiting on the outer one, and the caller's pid and euid;
+//! - per-node oneway queues: a node sees its next oneway call only after
+//!   the buffer of the previous one is freed;
+//! - death notifications, fired when a [`FakeProcess`] is killed or
+//!   dropped; calls into a dead process fail with
+//!   [`StatusCode::DeadObject`];
+//! - file descriptors, duplicated into the receiving parcel;
+//! - a context manager (handle 0), which another process may become once
+//!   the process of the previous one is killed;
+//! - each process's transaction buffer space ([`BUFFER_SPACE`], half of
+//!   it for oneway calls): a transaction that does not fit fails with
+//!   [`StatusCode::FailedTransaction`] and an extended error of
+//!   `-ENOSPC`, as with the kernel.
+//!
+//! Scatter-gather objects (`BINDER_TYPE_PTR` / `BINDER_TYPE_FDA`, i.e.
+//! HIDL), freezing, security contexts and oneway spam detection are not
+//! emulated. Simulated processes share the address space
+//! and the [`ProcessState`] singleton, so handle numbers are unique across
+//! the OS process and there is one context manager binder per OS process.
+//! As with the kernel, a process receives calls only while it has loopers:
+//! start its thread pool before publishing services from it.
+//!
+//! ```no_run
+//! use rsbinder::fake_driver::{FakeProcess, FAKE_BINDER_PATH};
+//! use rsbinder::ProcessState;
+//!
+//! ProcessState::init(FAKE_BINDER_PATH, 0).unwrap();
+//! ProcessState::start_thread_pool();
+//!
+//! // A second "process" with its own uid and binder threads.
+//! let server = FakeProcess::new(1000).unwrap();
+//! server.start_thread_pool();
+//! let registered = server.spawn(|| {
+//!     // Publish services from inside the simulated process here.
+//! });
+//! registered.join().unwrap();
+//!
+//! // Dropping the process kills it: its services' death recipients fire.
+//! drop(server);
+//! ```
+
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, BorrowedFd, IntoRawFd, OwnedFd};
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+use rustix::io::Errno;
+
+use crate::process_state::Driver;
+use crate::sys::binder::{self, binder_size_t, binder_transaction_data, flat_binder_object};
+use crate::{thread_state, ProcessState, Result, StatusCode};
+
+/// The driver name that makes [`ProcessState::init`] use the fake driver.
+pub const FAKE_BINDER_PATH: &str = "fake:binder";
+
+/// Transaction buffer space of every process: the size of the mapping
+/// [`ProcessState::init`] makes on a 4 KiB-page kernel.
+pub const BUFFER_SPACE: usize = 1024 * 1024 - 2 * 4096;
+
+/// First pid handed to a [`FakeProcess`]. Kept below 2^30 so it survives
+/// the calling-identity token packing.
+const SIMULATED_PID_BASE: i32 = 0x1000_0000;
+
+type ProcId = u32;
+type NodeId = u64;
+
+/// The process of threads not started by a [`FakeProcess`].
+const DEFAULT_PROCESS: ProcId = 0;
+
+thread_local! {
+    static CURRENT_PROCESS: Cell<ProcId> = const { Cell::new(DEFAULT_PROCESS) };
+}
+
+/// The process of the calling thread, for a helper thread that makes
+/// calls on its behalf to [`enter_process`].
+pub(crate) fn current_process() -> ProcId {
+    CURRENT_PROCESS.with(Cell::get)
+}
+
+/// Make the calling thread, before its first driver call, a thread of
+/// `process`.
+pub(crate) fn enter_process(process: ProcId) {
+    CURRENT_PROCESS.with(|current| current.set(process));
+}
+
+/// A unit of work waiting to be read by a thread.
+enum Work {
+    Transaction(Transaction),
+    Reply(Transaction),
+    /// A return without payload (`BR_TRANSACTION_COMPLETE`,
+    /// `BR_DEAD_REPLY`, `BR_FAILED_REPLY`).
+    Return(u32),
+    /// `BR_INCREFS` / `BR_ACQUIRE` / `BR_RELEASE` / `BR_DECREFS`.
+    Node {
+        cmd: u32,
+        ptr: u64,
+        cookie: u64,
+    },
+    /// `BR_DEAD_BINDER` / `BR_CLEAR_DEATH_NOTIFICATION_DONE`.
+    Cookie {
+        cmd: u32,
+        cookie: u64,
+    },
+}
+
+struct Transaction {
+    /// The thread waiting for the reply; `None` for oneway calls and
+    /// replies.
+    from: Option<ThreadId>,
+    sender_pid: i32,
+    sender_euid: u32,
+    target: u64,
+    cookie: u64,
+    code: u32,
+    flags: u32,
+    buffer: u64,
+}
+
+/// A transaction buffer, keyed by the address of its data.
+struct Buffer {
+    /// `u64` storage keeps the data 8-byte aligned like the kernel's.
+    data: Vec<u64>,
+    data_size: usize,
+    offsets: Vec<binder_size_t>,
+    /// Refs (handle, strong) held for the receiver until the buffer is
+    /// freed.
+    refs: Vec<(u32, bool)>,
+    /// Node refs (node, strong) held for objects that came home.
+    nodes: Vec<(NodeId, bool)>,
+    /// Duplicated fds, owned here until the buffer is delivered.
+    fds: Vec<OwnedFd>,
+    /// The node whose oneway queue waits for this buffer to be freed.
+    async_node: Option<NodeId>,
+    /// The process whose buffer space holds this buffer.
+    owner: ProcId,
+    /// Bytes charged to the owner's buffer space.
+    space: usize,
+    /// Whether `space` is also charged to its oneway share.
+    oneway: bool,
+}
+
+impl Buffer {
+    fn key(&self) -> u64 {
+        self.data.as_ptr() as u64
+    }
+}
+
+struct Process {
+    pid: i32,
+    euid: u32,
+    dead: bool,
+    todo: VecDeque<Work>,
+    pool_started: bool,
+    idle_loopers: usize,
+    spawned_loopers: u32,
+    /// Unallocated bytes of the process's buffer space.
+    free_space: usize,
+    /// What is left of the oneway half of it.
+    free_async_space: usize,
+    /// The binder context of the [`FakeProcess`], entered by its loopers.
+    /// `None` for the default process, whose loopers are the
+    /// `ProcessState` pool.
+    context: Option<&'static ProcessState>,
+}
+
+impl Process {
+    fn new(pid: i32, euid: u32, context: Option<&'static ProcessState>) -> Self {
+        Process {
+            pid,
+            euid,
+            dead: false,
+            todo: VecDeque::new(),
+            pool_started: false,
+            idle_loopers: 0,
+            spawned_loopers: 0,
+            free_space: BUFFER_SPACE,
+            free_async_space: BUFFER_SPACE / 2,
+            context,
+        }
+    }
+}
+
+struct Thread {
+    process: ProcId,
+    looper: bool,
+    todo: VecDeque<Work>,
+    /// Callers of the synchronous transactions this thread is serving,
+    /// innermost last.
+    incoming: Vec<ThreadId>,
+    /// Synchronous transactions this thread is waiting on.
+    outgoing: usize,
+    /// The socket behind the thread's `poll_fd`. Polling threads never
+    /// block in a read.
+    poll: Option<Poll>,
+    /// Detail of the thread's last failed transaction, until read by
+    /// `BINDER_GET_EXTENDED_ERROR`.
+    extended_error: binder::binder_extended_error,
+}
+
+/// Both ends of a `poll_fd` socket: the driver writes a byte for new
+/// work and drains the reader when the thread reads, so the socket reads
+/// as ready exactly while the thread has work.
+struct Poll {
+    reader: UnixStream,
+    writer: UnixStream,
+}
+
+impl Thread {
+    fn push(&mut self, work: Work) {
+        self.todo.push_back(work);
+        self.signal();
+    }
+
+    fn signal(&self) {
+        if let Some(poll) = &self.poll {
+            // A full socket already reads as ready.
+            let _ = (&poll.writer).write(&[1]);
+        }
+    }
+
+    fn drain(&self) {
+        if let Some(poll) = &self.poll {
+            let mut buf = [0u8; 64];
+            while matches!((&poll.reader).read(&mut buf), Ok(n) if n > 0) {}
+        }
+    }
+}
+
+struct Node {
+    owner: ProcId,
+    ptr: u64,
+    cookie: u64,
+    dead: bool,
+    strong: u32,
+    weak: u32,
+    /// Whether the owner was told about a strong / weak reference.
+    has_strong: bool,
+    has_weak: bool,
+    async_busy: bool,
+    async_todo: VecDeque<Transaction>,
+}
+
+struct Ref {
+    process: ProcId,
+    node: NodeId,
+    strong: u32,
+    weak: u32,
+}
+
+struct Death {
+    handle: u32,
+    cookie: u64,
+    process: ProcId,
+    fired: bool,
+}
+
+struct State {
+    max_threads: u32,
+    processes: HashMap<ProcId, Process>,
+    threads: HashMap<ThreadId, Thread>,
+    nodes: HashMap<NodeId, Node>,
+    node_ids: HashMap<(ProcId, u64), NodeId>,
+    refs: HashMap<u32, Ref>,
+    handles: HashMap<(ProcId, NodeId), u32>,
+    deaths: Vec<Death>,
+    buffers: HashMap<u64, Buffer>,
+    context_manager: Option<NodeId>,
+    next_process: ProcId,
+    next_node: NodeId,
+    next_handle: u32,
+    /// Id of the next extended error, like the kernel's transaction
+    /// debug ids.
+    next_error_id: u32,
+}
+
+/// The emulated driver shared by all threads of the OS process.
+pub(crate) struct FakeDriver {
+    state: Mutex<State>,
+    wakeup: Condvar,
+}
+
+impl FakeDriver {
+    pub(crate) fn new(max_threads: u32) -> Arc<Self> {
+        let mut processes = HashMap::new();
+        processes.insert(
+            DEFAULT_PROCESS,
+            Process::new(
+                std::process::id() as i32,
+                rustix::process::geteuid().as_raw(),
+                None,
+            ),
+        );
+        Arc::new(FakeDriver {
+            state: Mutex::new(State {
+                max_threads,
+                processes,
+                threads: HashMap::new(),
+                nodes: HashMap::new(),
+                node_ids: HashMap::new(),
+                refs: HashMap::new(),
+                handles: HashMap::new(),
+                deaths: Vec::new(),
+                buffers: HashMap::new(),
+                context_manager: None,
+                next_process: DEFAULT_PROCESS + 1,
+                next_node: 1,
+                next_handle: 1,
+                next_error_id: 1,
+            }),
+            wakeup: Condvar::new(),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// `BINDER_WRITE_READ`: run the queued commands, then block until
+    /// there is work for the calling thread if a read was requested.
+    pub(crate) fn write_read(
+        &self,
+        bwr: &mut binder::binder_write_read,
+    ) -> std::result::Result<(), Errno> {
+        let tid = thread::current().id();
+        let mut state = self.lock();
+        state.register_thread(tid);
+
+        if bwr.write_size > bwr.write_consumed {
+            // SAFETY: `write_buffer`/`write_size` describe the calling
+            // thread's out parcel, which `talk_with_driver` keeps alive and
+            // unmodified for the duration of this call.
+            let bytes = unsafe {
+                std::slice::from_raw_parts(bwr.write_buffer as *const u8, bwr.write_size as usize)
+            };
+            let mut commands = Commands {
+                bytes,
+                pos: bwr.write_consumed as usize,
+            };
+            let result = state.run_commands(tid, &mut commands);
+            bwr.write_consumed = commands.pos as _;
+            self.wakeup.notify_all();
+            result?;
+        }
+
+        if bwr.read_size > bwr.read_consumed {
+            let (mut state, work) = self.wait_for_work(state, tid)?;
+            let Some(work) = work else {
+                return Ok(());
+            };
+            let bytes = state.deliver(tid, work);
+            let thread = &state.threads[&tid];
+            if !thread.todo.is_empty() {
+                // `wait_for_work` drained the socket; keep it ready while
+                // work is left, like the kernel's poll.
+                thread.signal();
+            }
+            let available = (bwr.read_size - bwr.read_consumed) as usize;
+            if bytes.len() > available {
+                log::error!("fake binder driver: read buffer of {available} bytes is too small");
+                return Err(Errno::INVAL);
+            }
+            // SAFETY: `read_buffer`/`read_size` describe the calling
+            // thread's in parcel, writable for `read_size` bytes and not
+            // otherwise accessed during this call.
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    bytes.as_ptr(),
+                    (bwr.read_buffer as *mut u8).add(bwr.read_consumed as usize),
+                    bytes.len(),
+                );
+            }
+            bwr.read_consumed += bytes.len() as binder_size_t;
+        }
+        Ok(())
+    }
+
+    fn wait_for_work<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, State>,
+        tid: ThreadId,
+    ) -> std::result::Result<(MutexGuard<'a, State>, Option<Work>), Errno> {
+        loop {
+            let process = state.threads[&tid].process;
+            if state.processes[&process].dead {
+                return Err(Errno::CONNREFUSED);
+            }
+            let thread = state.threads.get_mut(&tid).expect("thread registered");
+            thread.drain();
+            if let Some(work) = thread.todo.pop_front() {
+                return Ok((state, Some(work)));
+            }
+            let available = thread.looper && thread.incoming.is_empty() && thread.outgoing == 0;
+            let polling = thread.poll.is_some();
+            if available {
+                let process = state.processes.get_mut(&process).expect("process exists");
+                if let Some(work) = process.todo.pop_front() {
+                    return Ok((state, Some(work)));
+                }
+                if !polling {
+                    process.idle_loopers += 1;
+                }
+            }
+            if polling {
+                return Ok((state, None));
+            }
+            state = self.wakeup.wait(state).unwrap_or_else(|e| e.into_inner());
+            if available {
+                let process = state.processes.get_mut(&process).expect("process exists");
+                process.idle_loopers -= 1;
+            }
+        }
+    }
+
+    /// `BINDER_SET_CONTEXT_MGR(_EXT)` from the calling thread's process.
+    pub(crate) fn set_context_mgr(&self) -> std::result::Result<(), Errno> {
+        let mut state = self.lock();
+        if let Some(node) = state.context_manager {
+            if !state.nodes[&node].dead {
+                return Err(Errno::BUSY);
+            }
+        }
+        let process = CURRENT_PROCESS.with(Cell::get);
+        let node = state.node_for(process, 0, 0);
+        state.context_manager = Some(node);
+        Ok(())
+    }
+
+    /// Whether the process of the context manager was killed.
+    pub(crate) fn context_manager_died(&self) -> bool {
+        let state = self.lock();
+        state
+            .context_manager
+            .is_some_and(|node| state.nodes[&node].dead)
+    }
+
+    /// `BINDER_GET_EXTENDED_ERROR`: the calling thread's last failure,
+    /// which reading resets.
+    pub(crate) fn get_extended_error(&self, ee: &mut binder::binder_extended_error) {
+        let tid = thread::current().id();
+        let mut state = self.lock();
+        state.register_thread(tid);
+        let reset = binder::binder_extended_error {
+            command: binder::BR_OK,
+            ..Default::default()
+        };
+        *ee = std::mem::replace(&mut state.thread(tid).extended_error, reset);
+    }
+
+    /// `BINDER_GET_NODE_INFO_FOR_REF`.
+    pub(crate) fn get_node_info_for_ref(
+        &self,
+        info: &mut binder::binder_node_info_for_ref,
+    ) -> std::result::Result<(), Errno> {
+        let state = self.lock();
+        let node = state.resolve(info.handle).ok_or(Errno::INVAL)?;
+        let refs = state.refs.values().filter(|r| r.node == node);
+        info.strong_count = refs.clone().filter(|r| r.strong > 0).count() as u32;
+        info.weak_count = refs.count() as u32;
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    /// A socket that reads as ready while the calling thread has work.
+    /// From then on reads by the thread return at once when it has none.
+    pub(crate) fn poll_fd(&self) -> Result<OwnedFd> {
+        let (reader, writer) = UnixStream::pair().map_err(|_| StatusCode::NoMemory)?;
+        reader
+            .set_nonblocking(true)
+            .and_then(|()| writer.set_nonblocking(true))
+            .map_err(|_| StatusCode::NoMemory)?;
+        let mut state = self.lock();
+        let tid = thread::current().id();
+        state.register_thread(tid);
+        let thread = state.thread(tid);
+        thread.poll = Some(Poll {
+            reader: reader.try_clone().map_err(|_| StatusCode::NoMemory)?,
+            writer,
+        });
+        if !thread.todo.is_empty() {
+            thread.signal();
+        }
+        Ok(reader.into())
+    }
+
+    fn new_process(&self, euid: u32) -> (ProcId, i32) {
+        let mut state = self.lock();
+        let id = state.next_process;
+        state.next_process += 1;
+        let pid = SIMULATED_PID_BASE + id as i32;
+        state
+            .processes
+            .insert(id, Process::new(pid, euid, ProcessState::current()));
+        (id, pid)
+    }
+
+    fn start_thread_pool(&self, process: ProcId) {
+        let mut state = self.lock();
+        let entry = state.processes.get_mut(&process).expect("process exists");
+        if entry.dead || entry.pool_started {
+            return;
+        }
+        entry.pool_started = true;
+        state.spawn_looper(process, true);
+    }
+
+    fn is_alive(&self, process: ProcId) -> bool {
+        !self.lock().processes[&process].dead
+    }
+
+    fn kill(&self, process: ProcId) {
+        let mut state = self.lock();
+        state.kill(process);
+        self.wakeup.notify_all();
+    }
+}
+
+/// Cursor over the `BC_*` commands of a write buffer.
+struct Commands<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Commands<'_> {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    /// Only used with plain-old-data types (integers and the `repr(C)`
+    /// binder structs), for which any bit pattern is valid.
+    fn read<T: Copy>(&mut self) -> std::result::Result<T, Errno> {
+        let size = std::mem::size_of::<T>();
+        if self.bytes.len() - self.pos < size {
+            log::error!("fake binder driver: truncated command");
+            return Err(Errno::INVAL);
+        }
+        // SAFETY: `size` bytes are in bounds (checked above) and `T` is
+        // plain old data; `read_unaligned` copes with the 4-byte command
+        // alignment.
+        let value =
+            unsafe { std::ptr::read_unaligned(self.bytes[self.pos..].as_ptr() as *const T) };
+        self.pos += size;
+        Ok(value)
+    }
+}
+
+fn as_bytes<T: Copy>(value: &T) -> &[u8] {
+    // SAFETY: `T` is one of the padding-free `repr(C)` binder structs or
+    // an integer, so all `size_of::<T>()` bytes are initialized.
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>()) }
+}
+
+impl State {
+    fn register_thread(&mut self, tid: ThreadId) {
+        self.threads.entry(tid).or_insert_with(|| Thread {
+            process: CURRENT_PROCESS.with(Cell::get),
+            looper: false,
+            todo: VecDeque::new(),
+            incoming: Vec::new(),
+            outgoing: 0,
+            poll: None,
+            extended_error: binder::binder_extended_error {
+                command: binder::BR_OK,
+                ..Default::default()
+            },
+        });
+    }
+
+    fn thread(&mut self, tid: ThreadId) -> &mut Thread {
+        self.threads.get_mut(&tid).expect("thread registered")
+    }
+
+    fn push_thread(&mut self, tid: ThreadId, work: Work) {
+        self.thread(tid).push(work);
+    }
+
+    fn run_commands(
+        &mut self,
+        tid: ThreadId,
+        commands: &mut Commands<'_>,
+    ) -> std::result::Result<(), Errno> {
+        while !commands.is_empty() {
+            let cmd = commands.read::<u32>()?;
+            let dead = self.processes[&self.threads[&tid].process].dead;
+            match cmd {
+                binder::BC_TRANSACTION | binder::BC_TRANSACTION_SG => {
+                    let tr = commands.read::<binder_transaction_data>()?;
+                    if cmd == binder::BC_TRANSACTION_SG {
+                        commands.read::<binder_size_t>()?;
+                    }
+                    if dead {
+                        self.push_thread(tid, Work::Return(binder::BR_DEAD_REPLY));
+                    } else {
+                        self.transaction(tid, &tr);
+                    }
+                }
+                binder::BC_REPLY | binder::BC_REPLY_SG => {
+                    let tr = commands.read::<binder_transaction_data>()?;
+                    if cmd == binder::BC_REPLY_SG {
+                        commands.read::<binder_size_t>()?;
+                    }
+                    if !dead {
+                        self.reply(tid, &tr);
+                    }
+                }
+                binder::BC_FREE_BUFFER => {
+                    let ptr = commands.read::<u64>()?;
+                    match self.buffers.remove(&ptr) {
+                        Some(buffer) => self.release_buffer(buffer),
+                        None => log::warn!("fake binder driver: free of unknown buffer {ptr:#x}"),
+                    }
+                }
+                binder::BC_INCREFS
+                | binder::BC_ACQUIRE
+                | binder::BC_RELEASE
+                | binder::BC_DECREFS => {
+                    let handle = commands.read::<u32>()?;
+                    let strong = matches!(cmd, binder::BC_ACQUIRE | binder::BC_RELEASE);
+                    let increment = matches!(cmd, binder::BC_INCREFS | binder::BC_ACQUIRE);
+                    self.update_ref(handle, strong, increment);
+                }
+                binder::BC_INCREFS_DONE | binder::BC_ACQUIRE_DONE => {
+                    commands.read::<u64>()?;
+                    commands.read::<u64>()?;
+                }
+                binder::BC_ENTER_LOOPER | binder::BC_REGISTER_LOOPER => {
+                    let thread = self.thread(tid);
+                    thread.looper = true;
+                    let process = thread.process;
+                    self.processes
+                        .get_mut(&process)
+                        .expect("process exists")
+                        .pool_started = true;
+                }
+                binder::BC_EXIT_LOOPER => {
+                    self.thread(tid).looper = false;
+                }
+                binder::BC_REQUEST_DEATH_NOTIFICATION => {
+                    let handle = commands.read::<u32>()?;
+                    let cookie = commands.read::<u64>()?;
+                    if !dead {
+                        self.request_death(tid, handle, cookie);
+                    }
+                }
+                binder::BC_CLEAR_DEATH_NOTIFICATION => {
+                    let handle = commands.read::<u32>()?;
+                    let cookie = commands.read::<u64>()?;
+                    self.deaths
+                        .retain(|d| !(d.handle == handle && d.cookie == cookie));
+                    self.push_thread(
+                        tid,
+                        Work::Cookie {
+                            cmd: binder::BR_CLEAR_DEATH_NOTIFICATION_DONE,
+                            cookie,
+                        },
+                    );
+                }
+                binder::BC_DEAD_BINDER_DONE => {
+                    commands.read::<u64>()?;
+                }
+                _ => {
+                    log::error!("fake binder driver: unsupported command {cmd:#x}");
+                    return Err(Errno::INVAL);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn transaction(&mut self, tid: ThreadId, tr: &binder_transaction_data) {
+        // SAFETY: `BC_TRANSACTION` addresses its target by handle.
+        let handle = unsafe { tr.target.handle };
+        let Some(node_id) = self.resolve(handle) else {
+            log::warn!("fake binder driver: transaction to unknown handle {handle}");
+            self.push_thread(tid, Work::Return(binder::BR_FAILED_REPLY));
+            return;
+        };
+        let node = &self.nodes[&node_id];
+        let target_process = node.owner;
+        if node.dead || self.processes[&target_process].dead {
+            self.push_thread(tid, Work::Return(binder::BR_DEAD_REPLY));
+            return;
+        }
+        let (target, cookie) = (node.ptr, node.cookie);
+        let buffer = match self.copy_buffer(tid, target_process, tr) {
+            Ok(buffer) => buffer,
+            Err(cmd) => {
+                self.push_thread(tid, Work::Return(cmd));
+                return;
+            }
+        };
+        let sender = &self.processes[&self.threads[&tid].process];
+        let oneway = tr.flags & binder::transaction_flags_TF_ONE_WAY != 0;
+        let transaction = Transaction {
+            from: (!oneway).then_some(tid),
+            sender_pid: sender.pid,
+            sender_euid: sender.euid,
+            target,
+            cookie,
+            code: tr.code,
+            flags: tr.flags,
+            buffer,
+        };
+
+        if oneway {
+            self.buffers
+                .get_mut(&buffer)
+                .expect("buffer just added")
+                .async_node = Some(node_id);
+            let node = self.nodes.get_mut(&node_id).expect("node exists");
+            if node.async_busy {
+                node.async_todo.push_back(transaction);
+            } else {
+                node.async_busy = true;
+                self.queue_process(target_process, Work::Transaction(transaction));
+            }
+        } else {
+            // A call back into a process that is waiting on us goes to the
+            // waiting thread, as the kernel does for nested transactions.
+            let waiting = self.threads[&tid]
+                .incoming
+                .iter()
+                .rev()
+                .find(|caller| {
+                    self.threads
+                        .get(caller)
+                        .is_some_and(|t| t.process == target_process)
+                })
+                .copied();
+            self.thread(tid).outgoing += 1;
+            match waiting {
+                Some(caller) => self.push_thread(caller, Work::Transaction(transaction)),
+                None => self.queue_process(target_process, Work::Transaction(transaction)),
+            }
+        }
+        self.push_thread(tid, Work::Return(binder::BR_TRANSACTION_COMPLETE));
+    }
+
+    fn reply(&mut self, tid: ThreadId, tr: &binder_transaction_data) {
+        let Some(caller) = self.thread(tid).incoming.pop() else {
+            log::error!("fake binder driver: BC_REPLY without a transaction");
+            self.push_thread(tid, Work::Return(binder::BR_FAILED_REPLY));
+            return;
+        };
+        let caller_process = match self.threads.get(&caller) {
+            Some(thread) if !self.processes[&thread.process].dead => thread.process,
+            // The caller died; drop the reply.
+            _ => {
+                self.push_thread(tid, Work::Return(binder::BR_TRANSACTION_COMPLETE));
+                return;
+            }
+        };
+        let work = match self.copy_buffer(tid, caller_process, tr) {
+            Ok(buffer) => {
+                let replier = &self.processes[&self.threads[&tid].process];
+                Work::Reply(Transaction {
+                    from: None,
+                    sender_pid: replier.pid,
+                    sender_euid: replier.euid,
+                    target: 0,
+                    cookie: 0,
+                    code: tr.code,
+                    flags: tr.flags,
+                    buffer,
+                })
+            }
+            Err(cmd) => Work::Return(cmd),
+        };
+        let caller = self.thread(caller);
+        caller.outgoing = caller.outgoing.saturating_sub(1);
+        caller.push(work);
+        // After the copy, so the replier handles any `BR_ACQUIRE` for the
+        // binders it sent before it returns to its loop.
+        self.push_thread(tid, Work::Return(binder::BR_TRANSACTION_COMPLETE));
+    }
+
+    /// Copy the sender's parcel into a new buffer for `target_process`,
+    /// translating binder objects and duplicating fds. On failure returns
+    /// the `BR_*` to report to the sender.
+    fn copy_buffer(
+        &mut self,
+        tid: ThreadId,
+        target_process: ProcId,
+        tr: &binder_transaction_data,
+    ) -> std::result::Result<u64, u32> {
+        let data_size = tr.data_size as usize;
+        let count = tr.offsets_size as usize / std::mem::size_of::<binder_size_t>();
+        // SAFETY: `BC_TRANSACTION`/`BC_REPLY` carry buffer pointers; they
+        // point into the sender's parcel (or status word), which stays
+        // alive until the command has been written to the driver.
+        let (src, src_offsets) = unsafe {
+            (
+                if data_size > 0 {
+                    std::slice::from_raw_parts(tr.data.ptr.buffer as *const u8, data_size)
+                } else {
+                    &[][..]
+                },
+                if count > 0 {
+                    std::slice::from_raw_parts(tr.data.ptr.offsets as *const binder_size_t, count)
+                } else {
+                    &[][..]
+                },
+            )
+        };
+
+        // Sizes aligned as by the kernel's `binder_alloc_new_buf`.
+        let space = data_size.next_multiple_of(8) + (tr.offsets_size as usize).next_multiple_of(8);
+        let oneway = tr.flags & binder::transaction_flags_TF_ONE_WAY != 0;
+        let target = self
+            .processes
+            .get_mut(&target_process)
+            .expect("process exists");
+        if space > target.free_space || (oneway && space > target.free_async_space) {
+            log::warn!(
+                "fake binder driver: no buffer space for {space} bytes in pid {} \
+                 ({} free, {} free for oneway)",
+                target.pid,
+                target.free_space,
+                target.free_async_space
+            );
+            let id = self.next_error_id;
+            self.next_error_id += 1;
+            self.thread(tid).extended_error = binder::binder_extended_error {
+                id,
+                command: binder::BR_FAILED_REPLY,
+                param: -Errno::NOSPC.raw_os_error(),
+            };
+            return Err(binder::BR_FAILED_REPLY);
+        }
+        target.free_space -= space;
+        if oneway {
+            target.free_async_space -= space;
+        }
+
+        let mut data = vec![0u64; data_size.div_ceil(8).max(1)];
+        // SAFETY: `data` holds at least `data_size` bytes.
+        let bytes =
+            unsafe { std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, data_size) };
+        bytes.copy_from_slice(src);
+
+        let mut buffer = Buffer {
+            data: Vec::new(),
+            data_size,
+            offsets: src_offsets.to_vec(),
+            refs: Vec::new(),
+            nodes: Vec::new(),
+            fds: Vec::new(),
+            async_node: None,
+            owner: target_process,
+            space,
+            oneway,
+        };
+        let sender_process = self.threads[&tid].process;
+        let object_size = std::mem::size_of::<flat_binder_object>();
+        for &offset in src_offsets {
+            let offset = offset as usize;
+            if offset
+                .checked_add(object_size)
+                .is_none_or(|end| end > data_size)
+            {
+                log::error!("fake binder driver: object offset {offset} out of bounds");
+                self.release_buffer(buffer);
+                return Err(binder::BR_FAILED_REPLY);
+            }
+            let slot = &mut bytes[offset..offset + object_size];
+            // SAFETY: in bounds (checked above); `flat_binder_object` is
+            // plain old data.
+            let mut obj =
+                unsafe { std::ptr::read_unaligned(slot.as_ptr() as *const flat_binder_object) };
+            let translated = match obj.hdr.type_ {
+                binder::BINDER_TYPE_BINDER | binder::BINDER_TYPE_WEAK_BINDER => {
+                    // SAFETY: local binder objects carry `binder`.
+                    let ptr = unsafe { obj.__bindgen_anon_1.binder };
+                    let node = self.node_for(sender_process, ptr, obj.cookie);
+                    let strong = obj.hdr.type_ == binder::BINDER_TYPE_BINDER;
+                    self.translate_node(tid, node, target_process, strong, &mut obj, &mut buffer);
+                    true
+                }
+                binder::BINDER_TYPE_HANDLE | binder::BINDER_TYPE_WEAK_HANDLE => {
+                    // SAFETY: handle objects carry `handle`.
+                    let handle = unsafe { obj.__bindgen_anon_1.handle };
+                    match self.resolve(handle) {
+                        Some(node) => {
+                            let strong = obj.hdr.type_ == binder::BINDER_TYPE_HANDLE;
+                            self.translate_node(
+                                tid,
+                                node,
+                                target_process,
+                                strong,
+                                &mut obj,
+                                &mut buffer,
+                            );
+                            true
+                        }
+                        None => {
+                            log::error!("fake binder driver: unknown handle {handle} in parcel");
+                            false
+                        }
+                    }
+                }
+                binder::BINDER_TYPE_FD => {
+                    // SAFETY: `binder_fd_object.fd` shares the offset of
+                    // `flat_binder_object.handle`.
+                    let fd = unsafe { obj.__bindgen_anon_1.handle } as i32;
+                    // SAFETY: the sender's parcel owns `fd` until the
+                    // command has been written.
+                    let borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+                    match rustix::io::fcntl_dupfd_cloexec(borrowed, 0) {
+                        Ok(dup) => {
+                            obj.__bindgen_anon_1.binder = 0;
+                            obj.__bindgen_anon_1.handle = dup.as_raw_fd() as u32;
+                            buffer.fds.push(dup);
+                            true
+                        }
+                        Err(e) => {
+                            log::error!("fake binder driver: dup of fd {fd} failed: {e}");
+                            false
+                        }
+                    }
+                }
+                other => {
+                    log::error!("fake binder driver: object type {other:#x} is not supported");
+                    false
+                }
+            };
+            if !translated {
+                self.release_buffer(buffer);
+                return Err(binder::BR_FAILED_REPLY);
+            }
+            slot.copy_from_slice(as_bytes(&obj));
+        }
+
+        buffer.data = data;
+        let key = buffer.key();
+        self.buffers.insert(key, buffer);
+        Ok(key)
+    }
+
+    /// Rewrite `obj` for `target_process`: a handle to `node`, or the
+    /// local binder when the node comes home. Either way the buffer holds
+    /// a reference until it is freed.
+    fn translate_node(
+        &mut self,
+        tid: ThreadId,
+        node_id: NodeId,
+        target_process: ProcId,
+        strong: bool,
+        obj: &mut flat_binder_object,
+        buffer: &mut Buffer,
+    ) {
+        let node = self.nodes.get_mut(&node_id).expect("node exists");
+        if node.owner == target_process {
+            obj.hdr.type_ = if strong {
+                binder::BINDER_TYPE_BINDER
+            } else {
+                binder::BINDER_TYPE_WEAK_BINDER
+            };
+            obj.__bindgen_anon_1.binder = node.ptr;
+            obj.cookie = node.cookie;
+            if strong {
+                node.strong += 1;
+            } else {
+                node.weak += 1;
+            }
+            buffer.nodes.push((node_id, strong));
+        } else {
+            let handle = self.ref_for(target_process, node_id);
+            obj.hdr.type_ = if strong {
+                binder::BINDER_TYPE_HANDLE
+            } else {
+                binder::BINDER_TYPE_WEAK_HANDLE
+            };
+            obj.__bindgen_anon_1.binder = 0;
+            obj.__bindgen_anon_1.handle = handle;
+            obj.cookie = 0;
+            self.change_ref(handle, strong, true);
+            buffer.refs.push((handle, strong));
+        }
+        self.update_node(node_id, Some(tid));
+    }
+
+    fn node_for(&mut self, owner: ProcId, ptr: u64, cookie: u64) -> NodeId {
+        if let Some(&node) = self.node_ids.get(&(owner, ptr)) {
+            return node;
+        }
+        let node = self.next_node;
+        self.next_node += 1;
+        self.nodes.insert(
+            node,
+            Node {
+                owner,
+                ptr,
+                cookie,
+                dead: false,
+                strong: 0,
+                weak: 0,
+                has_strong: false,
+                has_weak: false,
+                async_busy: false,
+                async_todo: VecDeque::new(),
+            },
+        );
+        self.node_ids.insert((owner, ptr), node);
+        node
+    }
+
+    /// The handle `process` knows `node` by, created on first use.
+    fn ref_for(&mut self, process: ProcId, node: NodeId) -> u32 {
+        if self.context_manager == Some(node) {
+            return 0;
+        }
+        if let Some(&handle) = self.handles.get(&(process, node)) {
+            return handle;
+        }
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.refs.insert(
+            handle,
+            Ref {
+                process,
+                node,
+                strong: 0,
+                weak: 0,
+            },
+        );
+        self.handles.insert((process, node), handle);
+        handle
+    }
+
+    fn resolve(&self, handle: u32) -> Option<NodeId> {
+        if handle == 0 {
+            self.context_manager
+        } else {
+            self.refs.get(&handle).map(|r| r.node)
+        }
+    }
+
+    /// Adjust a ref count without telling the owner; see [`Self::update_ref`].
+    fn change_ref(&mut self, handle: u32, strong: bool, increment: bool) -> Option<NodeId> {
+        // Handle 0 is shared by every process and never released.
+        if handle == 0 {
+            return None;
+        }
+        let Some(r) = self.refs.get_mut(&handle) else {
+            log::warn!("fake binder driver: ref count change on unknown handle {handle}");
+            return None;
+        };
+        let node = self.nodes.get_mut(&r.node).expect("node exists");
+        let (count, total) = if strong {
+            (&mut r.strong, &mut node.strong)
+        } else {
+            (&mut r.weak, &mut node.weak)
+        };
+        if increment {
+            *count += 1;
+            *total += 1;
+        } else if *count > 0 {
+            *count -= 1;
+            *total -= 1;
+        }
+        let node = r.node;
+        if r.strong == 0 && r.weak == 0 {
+            let process = r.process;
+            self.refs.remove(&handle);
+            self.handles.remove(&(process, node));
+            self.deaths.retain(|d| d.handle != handle);
+        }
+        Some(node)
+    }
+
+    fn update_ref(&mut self, handle: u32, strong: bool, increment: bool) {
+        if let Some(node) = self.change_ref(handle, strong, increment) {
+            self.update_node(node, None);
+        }
+    }
+
+    /// Tell the owner when `node` gains its first or loses its last
+    /// strong / weak reference. Increments go to `tid` when it is one of
+    /// the owner's threads (the sender of the node), like the kernel does.
+    fn update_node(&mut self, node_id: NodeId, tid: Option<ThreadId>) {
+        let node = self.nodes.get_mut(&node_id).expect("node exists");
+        // The context manager is not in the published-native table.
+        if node.ptr == 0 || node.dead {
+            return;
+        }
+        let want_strong = node.strong > 0;
+        let want_weak = want_strong || node.weak > 0;
+        let mut increments = Vec::new();
+        let mut decrements = Vec::new();
+        if want_weak && !node.has_weak {
+            increments.push(binder::BR_INCREFS);
+        }
+        if want_strong && !node.has_strong {
+            increments.push(binder::BR_ACQUIRE);
+        }
+        if !want_strong && node.has_strong {
+            decrements.push(binder::BR_RELEASE);
+        }
+        if !want_weak && node.has_weak {
+            decrements.push(binder::BR_DECREFS);
+        }
+        node.has_strong = want_strong;
+        node.has_weak = want_weak;
+        let (owner, ptr, cookie) = (node.owner, node.ptr, node.cookie);
+
+        let owner_thread = tid.filter(|tid| self.threads[tid].process == owner);
+        for cmd in increments {
+            let work = Work::Node { cmd, ptr, cookie };
+            match owner_thread {
+                Some(tid) => self.push_thread(tid, work),
+                None => self.queue_process(owner, work),
+            }
+        }
+        for cmd in decrements {
+            self.queue_process(owner, Work::Node { cmd, ptr, cookie });
+        }
+    }
+
+    fn release_buffer(&mut self, buffer: Buffer) {
+        if let Some(owner) = self.processes.get_mut(&buffer.owner) {
+            owner.free_space += buffer.space;
+            if buffer.oneway {
+                owner.free_async_space += buffer.space;
+            }
+        }
+        for &(handle, strong) in &buffer.refs {
+            self.update_ref(handle, strong, false);
+        }
+        for &(node_id, strong) in &buffer.nodes {
+            let node = self.nodes.get_mut(&node_id).expect("node exists");
+            if strong {
+                node.strong -= 1;
+            } else {
+                node.weak -= 1;
+            }
+            self.update_node(node_id, None);
+        }
+        if let Some(node_id) = buffer.async_node {
+            let node = self.nodes.get_mut(&node_id).expect("node exists");
+            match node.async_todo.pop_front() {
+                Some(next) if !node.dead => {
+                    let owner = node.owner;
+                    self.queue_process(owner, Work::Transaction(next));
+                }
+                _ => node.async_busy = false,
+            }
+        }
+        // Undelivered fds are closed with `buffer.fds`.
+    }
+
+    fn request_death(&mut self, tid: ThreadId, handle: u32, cookie: u64) {
+        let Some(node) = self.resolve(handle) else {
+            log::warn!("fake binder driver: death notification for unknown handle {handle}");
+            return;
+        };
+        let process = self.threads[&tid].process;
+        let dead = self.nodes[&node].dead;
+        self.deaths.push(Death {
+            handle,
+            cookie,
+            process,
+            fired: dead,
+        });
+        if dead {
+            self.queue_process(
+                process,
+                Work::Cookie {
+                    cmd: binder::BR_DEAD_BINDER,
+                    cookie,
+                },
+            );
+        }
+    }
+
+    fn queue_process(&mut self, process: ProcId, work: Work) {
+        let entry = self.processes.get_mut(&process).expect("process exists");
+        if entry.dead {
+            self.abort(work);
+            return;
+        }
+        entry.todo.push_back(work);
+        if entry.pool_started && entry.idle_loopers == 0 && entry.spawned_loopers < self.max_threads
+        {
+            self.spawn_looper(process, false);
+        }
+    }
+
+    fn spawn_looper(&mut self, process: ProcId, is_main: bool) {
+        let entry = self.processes.get_mut(&process).expect("process exists");
+        entry.spawned_loopers += 1;
+        let name = format!("fake:{}_{:X}", entry.pid, entry.spawned_loopers);
+        let context = entry.context;
+        let spawned = thread::Builder::new().name(name).spawn(move || {
+            CURRENT_PROCESS.with(|current| current.set(process));
+            let _context = ProcessState::enter(context);
+            if let Err(e) = thread_state::join_thread_pool(is_main) {
+                log::debug!("fake binder looper left the thread pool: {e}");
+            }
+        });
+        if let Err(e) = spawned {
+            log::error!("fake binder driver: failed to spawn a looper: {e}");
+        }
+    }
+
+    /// Fail work that can no longer be delivered.
+    fn abort(&mut self, work: Work) {
+        match work {
+            Work::Transaction(transaction) | Work::Reply(transaction) => {
+                if let Some(from) = transaction.from {
+                    if let Some(thread) = self.threads.get_mut(&from) {
+                        thread.outgoing = thread.outgoing.saturating_sub(1);
+                        thread.push(Work::Return(binder::BR_DEAD_REPLY));
+                    }
+                }
+                if let Some(buffer) = self.buffers.remove(&transaction.buffer) {
+                    self.release_buffer(buffer);
+                }
+            }
+            Work::Return(_) | Work::Node { .. } | Work::Cookie { .. } => {}
+        }
+    }
+
+    fn kill(&mut self, process: ProcId) {
+        let entry = self.processes.get_mut(&process).expect("process exists");
+        if entry.dead {
+            return;
+        }
+        entry.dead = true;
+
+        // Fail what was queued for the process and what its threads were
+        // serving.
+        let mut pending: Vec<Work> = entry.todo.drain(..).collect();
+        let mut callers = Vec::new();
+        for thread in self.threads.values_mut() {
+            if thread.process == process {
+                pending.extend(thread.todo.drain(..));
+                callers.append(&mut thread.incoming);
+            }
+        }
+        for work in pending {
+            self.abort(work);
+        }
+        for caller in callers {
+            if let Some(thread) = self.threads.get_mut(&caller) {
+                thread.outgoing = thread.outgoing.saturating_sub(1);
+                thread.push(Work::Return(binder::BR_DEAD_REPLY));
+            }
+        }
+
+        // Its nodes die.
+        let nodes: Vec<NodeId> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.owner == process)
+            .map(|(&id, _)| id)
+            .collect();
+        for node_id in &nodes {
+            let node = self.nodes.get_mut(node_id).expect("node exists");
+            node.dead = true;
+            let queued: Vec<Transaction> = node.async_todo.drain(..).collect();
+            for transaction in queued {
+                self.abort(Work::Transaction(transaction));
+            }
+        }
+        let mut obituaries = Vec::new();
+        for death in &mut self.deaths {
+            let node = if death.handle == 0 {
+                self.context_manager
+            } else {
+                self.refs.get(&death.handle).map(|r| r.node)
+            };
+            if !death.fired && node.is_some_and(|node| nodes.contains(&node)) {
+                death.fired = true;
+                obituaries.push((death.process, death.cookie));
+            }
+        }
+        for (target, cookie) in obituaries {
+            self.queue_process(
+                target,
+                Work::Cookie {
+                    cmd: binder::BR_DEAD_BINDER,
+                    cookie,
+                },
+            );
+        }
+
+        // And it lets go of the nodes it referenced.
+        self.deaths.retain(|d| d.process != process);
+        let handles: Vec<u32> = self
+            .refs
+            .iter()
+            .filter(|(_, r)| r.process == process)
+            .map(|(&handle, _)| handle)
+            .collect();
+        for handle in handles {
+            let r = self.refs.remove(&handle).expect("ref exists");
+            self.handles.remove(&(process, r.node));
+            self.deaths.retain(|d| d.handle != handle);
+            let node = self.nodes.get_mut(&r.node).expect("node exists");
+            node.strong -= r.strong;
+            node.weak -= r.weak;
+            self.update_node(r.node, None);
+        }
+    }
+
+    /// Encode `work` for the reading thread.
+    fn deliver(&mut self, tid: ThreadId, work: Work) -> Vec<u8> {
+        let mut out = Vec::new();
+        let (cmd, transaction) = match work {
+            Work::Transaction(transaction) => (binder::BR_TRANSACTION, transaction),
+            Work::Reply(transaction) => (binder::BR_REPLY, transaction),
+            Work::Return(cmd) => {
+                out.extend_from_slice(as_bytes(&cmd));
+                return out;
+            }
+            Work::Node { cmd, ptr, cookie } => {
+                out.extend_from_slice(as_bytes(&cmd));
+                out.extend_from_slice(as_bytes(&ptr));
+                out.extend_from_slice(as_bytes(&cookie));
+                return out;
+            }
+            Work::Cookie { cmd, cookie } => {
+                out.extend_from_slice(as_bytes(&cmd));
+                out.extend_from_slice(as_bytes(&cookie));
+                return out;
+            }
+        };
+
+        let buffer = self
+            .buffers
+            .get_mut(&transaction.buffer)
+            .expect("undelivered buffer exists");
+        // The receiver owns the duplicated fds from here on: it closes
+        // them when it frees the buffer.
+        for fd in buffer.fds.drain(..) {
+            let _ = fd.into_raw_fd();
+        }
+        let tr = binder_transaction_data {
+            target: binder::binder_transaction_data__bindgen_ty_1 {
+                ptr: transaction.target,
+            },
+            cookie: transaction.cookie,
+            code: transaction.code,
+            flags: transaction.flags,
+            sender_pid: transaction.sender_pid,
+            sender_euid: transaction.sender_euid,
+            data_size: buffer.data_size as _,
+            offsets_size: (buffer.offsets.len() * std::mem::size_of::<binder_size_t>()) as _,
+            data: binder::binder_transaction_data__bindgen_ty_2 {
+                ptr: binder::binder_transaction_data__bindgen_ty_2__bindgen_ty_1 {
+                    buffer: buffer.key(),
+                    offsets: buffer.offsets.as_ptr() as _,
+                },
+            },
+        };
+        if let Some(from) = transaction.from {
+            self.thread(tid).incoming.push(from);
+        }
+        out.extend_from_slice(as_bytes(&cmd));
+        out.extend_from_slice(as_bytes(&tr));
+        out
+    }
+}
+
+/// A simulated binder process of the fake driver.
+///
+/// Dropping it kills the process, like [`FakeProcess::kill`].
+pub struct FakeProcess {
+    driver: Arc<FakeDriver>,
+    /// The binder context the process was created in.
+    context: Option<&'static ProcessState>,
+    id: ProcId,
+    pid: i32,
+    euid: u32,
+}
+
+impl FakeProcess {
+    /// Create a process that calls services as `euid`, on the driver of
+    /// the current binder context (see [`crate::BinderContext`]).
+    ///
+    /// Fails with [`StatusCode::NoInit`] before [`ProcessState::init`]
+    /// and with [`StatusCode::InvalidOperation`] when the process state
+    /// was initialized with a real binder device.
+    pub fn new(euid: u32) -> Result<Self> {
+        if !ProcessState::is_initialized() {
+            return Err(StatusCode::NoInit);
+        }
+        let driver = match ProcessState::as_self().binder_driver() {
+            Driver::Fake(driver) => driver.clone(),
+            Driver::Kernel(_) => return Err(StatusCode::InvalidOperation),
+        };
+        let (id, pid) = driver.new_process(euid);
+        Ok(FakeProcess {
+            driver,
+            context: ProcessState::current(),
+            id,
+            pid,
+            euid,
+        })
+    }
+
+    /// The pid services see for calls from this process.
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// The euid services see for calls from this process.
+    pub fn euid(&self) -> u32 {
+        self.euid
+    }
+
+    /// Run `f` on a new thread of this process.
+    pub fn spawn<F, T>(&self, f: F) -> thread::JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let id = self.id;
+        let context = self.context;
+        thread::spawn(move || {
+            CURRENT_PROCESS.with(|current| current.set(id));
+            let _context = ProcessState::enter(context);
+            f()
+        })
+    }
+
+    /// Start the binder thread pool of this process: one looper now, more
+    /// on demand up to the max threads of [`ProcessState::init`].
+    /// Idempotent.
+    pub fn start_thread_pool(&self) {
+        self.driver.start_thread_pool(self.id);
+    }
+
+    /// Kill the process: its nodes die, death recipients linked to them
+    /// fire, calls in flight to it fail with [`StatusCode::DeadObject`]
+    /// and its loopers leave the thread pool.
+    pub fn kill(&self) {
+        self.driver.kill(self.id);
+    }
+
+    /// Whether [`Self::kill`] has not been called yet.
+    pub fn is_alive(&self) -> bool {
+        self.driver.is_alive(self.id)
+    }
+}
+
+impl Drop for FakeProcess {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+impl std::fmt::Debug for FakeProcess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakeProcess")
+            .field("pid", &self.pid)
+            .field("euid", &self.euid)
+            .field("alive", &self.is_alive())
+            .finish()
+    }
+}
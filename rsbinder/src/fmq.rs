@@ -0,0 +1,1137 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fast Message Queue (FMQ): shared-memory producer/consumer channels.
+//!
+//! A port of AOSP `libfmq`'s AIDL flavor
+//! ([`AidlMessageQueue.h`](https://cs.android.com/android/platform/superproject/+/android-16.0.0_r4:system/libfmq/include/fmq/AidlMessageQueue.h)).
+//! The creator allocates a ring buffer in a sealed `memfd` and describes it
+//! with an [`MQDescriptor`], the Rust form of the stable AIDL parcelable
+//! [`android.hardware.common.fmq.MQDescriptor`](https://cs.android.com/android/platform/superproject/+/android-16.0.0_r4:hardware/interfaces/common/fmq/aidl/android/hardware/common/fmq/MQDescriptor.aidl).
+//! The descriptor travels through any AIDL method (the generator maps
+//! `MQDescriptor<T, Flavor>` to this type) and the peer opens the same
+//! queue with [`MessageQueue::from_descriptor`]. After setup, messages move
+//! through shared memory without a binder transaction.
+//!
+//! ```text
+//! // Service: create the queue and hand its descriptor to the client.
+//! let mut queue = MessageQueue::<i32, SynchronizedReadWrite>::new(1024, true)?;
+//! let desc = queue.dupe_desc()?;          // return it from an AIDL method
+//! queue.write_blocking(&samples, Some(Duration::from_millis(100)))?;
+//!
+//! // Client: open the queue from the received descriptor.
+//! let mut queue = MessageQueue::<i32, SynchronizedReadWrite>::from_descriptor(&desc, false)?;
+//! queue.read_blocking(&mut samples, None)?;
+//! ```
+//!
+//! The shared memory layout is libfmq's, so a queue can be shared with
+//! AOSP C++/Rust/Java peers:
+//!
+//! | grantor | offset | extent |
+//! |---|---|---|
+//! | read pointer (`u64`, bytes) | 0 | 8 |
+//! | write pointer (`u64`, bytes) | 8 | 8 |
+//! | ring buffer | 16 | `quantum * count` |
+//! | event flag word (`u32`, optional) | ring end, 8-aligned | 4 |
+//!
+//! [`SynchronizedReadWrite`] queues have one reader and one writer; the
+//! writer never overwrites unread data. [`UnsynchronizedWrite`] queues let
+//! the writer overwrite old data; every reader keeps its own read pointer,
+//! and a reader that falls a whole ring behind loses its place (the read
+//! fails once with [`StatusCode::BadIndex`] and resumes at the write
+//! pointer).
+//!
+//! Blocking reads and writes park on the optional [`EventFlag`] (a shared
+//! futex word, Linux/Android only) using libfmq's default
+//! [`FMQ_NOT_EMPTY`] / [`FMQ_NOT_FULL`] bits.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::os::fd::OwnedFd;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, StatusCode};
+use crate::shared_memory::MemoryHeapBase;
+use crate::{Parcel, ParcelFileDescriptor, Parcelable, ParcelableMetadata, Stability};
+
+/// libfmq `kSynchronizedReadWrite` (`MQDescriptor::flags` of a
+/// [`SynchronizedReadWrite`] queue).
+pub const FLAG_SYNCHRONIZED_READ_WRITE: i32 = 0x01;
+/// libfmq `kUnsynchronizedWrite` (`MQDescriptor::flags` of an
+/// [`UnsynchronizedWrite`] queue).
+pub const FLAG_UNSYNCHRONIZED_WRITE: i32 = 0x02;
+
+/// libfmq `FMQ_NOT_EMPTY`: set by a writer after data became readable.
+pub const FMQ_NOT_EMPTY: u32 = 1 << 0;
+/// libfmq `FMQ_NOT_FULL`: set by a reader after space became writable.
+pub const FMQ_NOT_FULL: u32 = 1 << 1;
+
+const READ_PTR_POS: usize = 0;
+const WRITE_PTR_POS: usize = 1;
+const DATA_PTR_POS: usize = 2;
+const EVENT_FLAG_POS: usize = 3;
+
+/// Size of a libfmq `RingBufferPosition`.
+const POSITION_SIZE: usize = std::mem::size_of::<u64>();
+
+/// A type that can travel through a [`MessageQueue`] as raw bytes.
+///
+/// # Safety
+///
+/// The peer writes arbitrary bytes into the ring, so every bit pattern of
+/// `size_of::<Self>()` bytes must be a valid value, and the type must not
+/// hold pointers, references or padding. Plain integers, floats and arrays
+/// of them qualify; a `#[repr(C)]` struct of such fields with no padding
+/// does too.
+pub unsafe trait QueueElement: Copy + Send + 'static {}
+
+macro_rules! impl_queue_element {
+    ($($ty:ty),*) => {
+        // SAFETY: primitive numbers accept every bit pattern.
+        $(unsafe impl QueueElement for $ty {})*
+    };
+}
+
+impl_queue_element!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+// SAFETY: an array of elements has no padding between them and accepts
+// every bit pattern its elements accept.
+unsafe impl<T: QueueElement, const N: usize> QueueElement for [T; N] {}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// The queue flavor: [`SynchronizedReadWrite`] or [`UnsynchronizedWrite`].
+pub trait Flavor: private::Sealed + Send + 'static {
+    /// `MQDescriptor::flags` value of this flavor.
+    const FLAGS: i32;
+}
+
+/// AIDL `android.hardware.common.fmq.SynchronizedReadWrite`: one reader,
+/// one writer, and the writer never overwrites unread data.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SynchronizedReadWrite;
+
+/// AIDL `android.hardware.common.fmq.UnsynchronizedWrite`: the writer
+/// never blocks and may overwrite data readers have not seen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UnsynchronizedWrite;
+
+impl private::Sealed for SynchronizedReadWrite {}
+impl private::Sealed for UnsynchronizedWrite {}
+
+impl Flavor for SynchronizedReadWrite {
+    const FLAGS: i32 = FLAG_SYNCHRONIZED_READ_WRITE;
+}
+
+impl Flavor for UnsynchronizedWrite {
+    const FLAGS: i32 = FLAG_UNSYNCHRONIZED_WRITE;
+}
+
+/// AIDL `android.hardware.common.fmq.GrantorDescriptor`: one region of the
+/// queue within `handle.fds[fd_index]`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GrantorDescriptor {
+    /// Index of the fd in [`NativeHandle::fds`].
+    pub fd_index: i32,
+    /// Byte offset of the region within the fd.
+    pub offset: i32,
+    /// Byte length of the region.
+    pub extent: i64,
+}
+
+impl Parcelable for GrantorDescriptor {
+    fn write_to_parcel(&self, parcel: &mut Parcel) -> Result<()> {
+        parcel.sized_write(|sub_parcel| {
+            sub_parcel.write(&self.fd_index)?;
+            sub_parcel.write(&self.offset)?;
+            sub_parcel.write(&self.extent)?;
+            Ok(())
+        })
+    }
+
+    fn read_from_parcel(&mut self, parcel: &mut Parcel) -> Result<()> {
+        parcel.sized_read(|sub_parcel| {
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.fd_index = sub_parcel.read()?;
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.offset = sub_parcel.read()?;
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.extent = sub_parcel.read()?;
+            Ok(())
+        })
+    }
+}
+
+crate::impl_serialize_for_parcelable!(GrantorDescriptor);
+crate::impl_deserialize_for_parcelable!(GrantorDescriptor);
+
+impl ParcelableMetadata for GrantorDescriptor {
+    fn descriptor() -> &'static str {
+        "android.hardware.common.fmq.GrantorDescriptor"
+    }
+    fn stability(&self) -> Stability {
+        Stability::Vintf
+    }
+}
+
+/// AIDL `android.hardware.common.NativeHandle`: the fds and ints of a
+/// `native_handle_t`.
+#[derive(Debug, Default)]
+pub struct NativeHandle {
+    /// The handle's file descriptors.
+    pub fds: Vec<ParcelFileDescriptor>,
+    /// The handle's integers.
+    pub ints: Vec<i32>,
+}
+
+impl Parcelable for NativeHandle {
+    fn write_to_parcel(&self, parcel: &mut Parcel) -> Result<()> {
+        parcel.sized_write(|sub_parcel| {
+            sub_parcel.write(&self.fds)?;
+            sub_parcel.write(&self.ints)?;
+            Ok(())
+        })
+    }
+
+    fn read_from_parcel(&mut self, parcel: &mut Parcel) -> Result<()> {
+        parcel.sized_read(|sub_parcel| {
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.fds = sub_parcel.read()?;
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.ints = sub_parcel.read()?;
+            Ok(())
+        })
+    }
+}
+
+crate::impl_serialize_for_parcelable!(NativeHandle);
+crate::impl_deserialize_for_parcelable!(NativeHandle);
+
+impl ParcelableMetadata for NativeHandle {
+    fn descriptor() -> &'static str {
+        "android.hardware.common.NativeHandle"
+    }
+    fn stability(&self) -> Stability {
+        Stability::Vintf
+    }
+}
+
+/// AIDL `android.hardware.common.fmq.MQDescriptor<T, Flavor>`: everything
+/// a peer needs to open a [`MessageQueue`].
+///
+/// `T` and `F` only tag the descriptor; the wire format does not carry
+/// them, and [`MessageQueue::from_descriptor`] checks `quantum` and
+/// `flags` against them instead.
+pub struct MQDescriptor<T, F> {
+    /// Regions of the queue, indexed read pointer, write pointer, ring
+    /// buffer and (optionally) event flag word.
+    pub grantors: Vec<GrantorDescriptor>,
+    /// The fds the grantors point into.
+    pub handle: NativeHandle,
+    /// Size of one element in bytes.
+    pub quantum: i32,
+    /// [`FLAG_SYNCHRONIZED_READ_WRITE`] or [`FLAG_UNSYNCHRONIZED_WRITE`].
+    pub flags: i32,
+    _marker: PhantomData<fn() -> (T, F)>,
+}
+
+impl<T, F> MQDescriptor<T, F> {
+    /// Build a descriptor from its AIDL fields.
+    pub fn new(
+        grantors: Vec<GrantorDescriptor>,
+        handle: NativeHandle,
+        quantum: i32,
+        flags: i32,
+    ) -> Self {
+        MQDescriptor {
+            grantors,
+            handle,
+            quantum,
+            flags,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F> Default for MQDescriptor<T, F> {
+    fn default() -> Self {
+        Self::new(Vec::new(), NativeHandle::default(), 0, 0)
+    }
+}
+
+impl<T, F> fmt::Debug for MQDescriptor<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MQDescriptor")
+            .field("grantors", &self.grantors)
+            .field("handle", &self.handle)
+            .field("quantum", &self.quantum)
+            .field("flags", &self.flags)
+            .finish()
+    }
+}
+
+impl<T, F> Parcelable for MQDescriptor<T, F> {
+    fn write_to_parcel(&self, parcel: &mut Parcel) -> Result<()> {
+        parcel.sized_write(|sub_parcel| {
+            sub_parcel.write(&self.grantors)?;
+            sub_parcel.write(&self.handle)?;
+            sub_parcel.write(&self.quantum)?;
+            sub_parcel.write(&self.flags)?;
+            Ok(())
+        })
+    }
+
+    fn read_from_parcel(&mut self, parcel: &mut Parcel) -> Result<()> {
+        parcel.sized_read(|sub_parcel| {
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.grantors = sub_parcel.read()?;
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.handle = sub_parcel.read()?;
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.quantum = sub_parcel.read()?;
+            if !sub_parcel.has_more_data() {
+                return Ok(());
+            }
+            self.flags = sub_parcel.read()?;
+            Ok(())
+        })
+    }
+}
+
+// `impl_{de,}serialize_for_parcelable!` take a plain ident, so the
+// generic descriptor spells the same impls out.
+impl<T, F> crate::Serialize for MQDescriptor<T, F> {
+    fn serialize(&self, parcel: &mut Parcel) -> Result<()> {
+        <Self as crate::SerializeOption>::serialize_option(Some(self), parcel)
+    }
+}
+
+impl<T, F> crate::SerializeArray for MQDescriptor<T, F> {}
+
+impl<T, F> crate::SerializeOption for MQDescriptor<T, F> {
+    fn serialize_option(this: Option<&Self>, parcel: &mut Parcel) -> Result<()> {
+        if let Some(this) = this {
+            parcel.write(&crate::NON_NULL_PARCELABLE_FLAG)?;
+            this.write_to_parcel(parcel)
+        } else {
+            parcel.write(&crate::NULL_PARCELABLE_FLAG)
+        }
+    }
+}
+
+impl<T, F> crate::Deserialize for MQDescriptor<T, F> {
+    fn deserialize(parcel: &mut Parcel) -> Result<Self> {
+        crate::DeserializeOption::deserialize_option(parcel)
+            .transpose()
+            .unwrap_or(Err(StatusCode::UnexpectedNull))
+    }
+    fn deserialize_from(&mut self, parcel: &mut Parcel) -> Result<()> {
+        let status: i32 = parcel.read()?;
+        if status == crate::NON_NULL_PARCELABLE_FLAG {
+            self.read_from_parcel(parcel)
+        } else {
+            Err(StatusCode::UnexpectedNull)
+        }
+    }
+}
+
+impl<T, F> crate::DeserializeArray for MQDescriptor<T, F> {}
+
+impl<T, F> crate::DeserializeOption for MQDescriptor<T, F> {
+    fn deserialize_option(parcel: &mut Parcel) -> Result<Option<Self>> {
+        let mut result = None;
+        Self::deserialize_option_from(&mut result, parcel)?;
+        Ok(result)
+    }
+    fn deserialize_option_from(this: &mut Option<Self>, parcel: &mut Parcel) -> Result<()> {
+        let status: i32 = parcel.read()?;
+        if status == crate::NULL_PARCELABLE_FLAG {
+            *this = None;
+            Ok(())
+        } else if status == crate::NON_NULL_PARCELABLE_FLAG {
+            this.get_or_insert_with(Self::default)
+                .read_from_parcel(parcel)
+        } else {
+            Err(StatusCode::UnexpectedNull)
+        }
+    }
+}
+
+impl<T, F> ParcelableMetadata for MQDescriptor<T, F> {
+    fn descriptor() -> &'static str {
+        "android.hardware.common.fmq.MQDescriptor"
+    }
+    fn stability(&self) -> Stability {
+        Stability::Vintf
+    }
+}
+
+/// A futex word in shared memory used to wake blocked peers (libfmq
+/// `EventFlag`).
+///
+/// Waiters name the bits they wait for; a [`wake`](Self::wake) sets bits
+/// and wakes the waiters for them. Bits stay set until a waiter consumes
+/// them, so a wake that races ahead of a wait is not lost. The futex is
+/// process-shared, so peers in other processes (including AOSP ones)
+/// interoperate.
+#[derive(Clone)]
+pub struct EventFlag {
+    word: *const AtomicU32,
+    _mapping: MemoryHeapBase,
+}
+
+// SAFETY: `word` points into `_mapping`, which lives as long as `self`;
+// it is only accessed atomically.
+unsafe impl Send for EventFlag {}
+// SAFETY: see `Send`.
+unsafe impl Sync for EventFlag {}
+
+impl EventFlag {
+    fn word(&self) -> &AtomicU32 {
+        // SAFETY: `word` is 4-byte aligned and mapped for as long as
+        // `_mapping` is alive (see `MessageQueue::map_grantor`).
+        unsafe { &*self.word }
+    }
+
+    /// Wait until a bit of `bitmask` is set, then clear those bits and
+    /// return them (libfmq `EventFlag::wait`).
+    ///
+    /// Returns `Ok(0)` on a spurious wakeup, so callers re-check their
+    /// condition in a loop. `None` waits forever; an elapsed timeout is
+    /// [`StatusCode::TimedOut`]. A zero `bitmask` is
+    /// [`StatusCode::BadValue`].
+    pub fn wait(&self, bitmask: u32, timeout: Option<Duration>) -> Result<u32> {
+        let bitmask = std::num::NonZeroU32::new(bitmask).ok_or(StatusCode::BadValue)?;
+        let old = self.word().fetch_and(!bitmask.get(), Ordering::SeqCst);
+        let set = old & bitmask.get();
+        if set != 0 {
+            return Ok(set);
+        }
+        self.futex_wait(old, bitmask, timeout)?;
+        Ok(self.word().fetch_and(!bitmask.get(), Ordering::SeqCst) & bitmask.get())
+    }
+
+    /// Set the bits of `bitmask` and wake the waiters for them (libfmq
+    /// `EventFlag::wake`). A zero `bitmask` is [`StatusCode::BadValue`].
+    pub fn wake(&self, bitmask: u32) -> Result<()> {
+        let bitmask = std::num::NonZeroU32::new(bitmask).ok_or(StatusCode::BadValue)?;
+        let old = self.word().fetch_or(bitmask.get(), Ordering::SeqCst);
+        // Only a bit that was clear can have waiters parked on it.
+        if !old & bitmask.get() != 0 {
+            self.futex_wake(bitmask)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn futex_wait(
+        &self,
+        expected: u32,
+        bitmask: std::num::NonZeroU32,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        use rustix::thread::futex;
+
+        // FUTEX_WAIT_BITSET takes an absolute CLOCK_MONOTONIC deadline.
+        let deadline = timeout.map(|timeout| {
+            let now = rustix::time::clock_gettime(rustix::time::ClockId::Monotonic);
+            let nanos = now.tv_nsec as u64 + u64::from(timeout.subsec_nanos());
+            futex::Timespec {
+                tv_sec: now
+                    .tv_sec
+                    .saturating_add(timeout.as_secs().min(i64::MAX as u64) as i64)
+                    .saturating_add((nanos / 1_000_000_000) as i64),
+                tv_nsec: (nanos % 1_000_000_000) as _,
+            }
+        });
+        match futex::wait_bitset(
+            self.word(),
+            futex::Flags::empty(),
+            expected,
+            deadline.as_ref(),
+            bitmask,
+        ) {
+            // The word changed before we slept, or a signal arrived; the
+            // caller re-checks either way.
+            Ok(()) | Err(rustix::io::Errno::AGAIN) | Err(rustix::io::Errno::INTR) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn futex_wait(
+        &self,
+        _expected: u32,
+        _bitmask: std::num::NonZeroU32,
+        _timeout: Option<Duration>,
+    ) -> Result<()> {
+        Err(StatusCode::InvalidOperation)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn futex_wake(&self, bitmask: std::num::NonZeroU32) -> Result<()> {
+        use rustix::thread::futex;
+
+        futex::wake_bitset(self.word(), futex::Flags::empty(), i32::MAX as u32, bitmask)?;
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn futex_wake(&self, _bitmask: std::num::NonZeroU32) -> Result<()> {
+        Err(StatusCode::InvalidOperation)
+    }
+}
+
+impl fmt::Debug for EventFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventFlag")
+            .field("word", &self.word().load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// A ring buffer of `T` in shared memory (libfmq `AidlMessageQueue`).
+///
+/// Each handle is either the creator ([`new`](Self::new)) or a peer
+/// ([`from_descriptor`](Self::from_descriptor)); either end may read or
+/// write. Reads and writes take `&mut self`: a synchronized queue has one
+/// reader and one writer, so open a second handle from
+/// [`dupe_desc`](Self::dupe_desc) to read and write from two threads.
+pub struct MessageQueue<T: QueueElement, F: Flavor> {
+    grantors: Vec<GrantorDescriptor>,
+    fds: Vec<OwnedFd>,
+    ints: Vec<i32>,
+    // Keep the grantor mappings alive; the pointers below point into them.
+    _mappings: Vec<MemoryHeapBase>,
+    read_ptr: *const AtomicU64,
+    write_ptr: *const AtomicU64,
+    ring: *mut u8,
+    ring_size: usize,
+    // An unsynchronized reader's private read pointer.
+    local_read_ptr: Option<Box<AtomicU64>>,
+    event_flag: Option<EventFlag>,
+    _marker: PhantomData<fn() -> (T, F)>,
+}
+
+// SAFETY: the raw pointers point into mappings owned by the queue, which
+// move with it; shared-memory positions are only accessed atomically and
+// ring bytes only through `&mut self`.
+unsafe impl<T: QueueElement, F: Flavor> Send for MessageQueue<T, F> {}
+
+impl<T: QueueElement, F: Flavor> MessageQueue<T, F> {
+    /// Create a queue holding `num_elements` elements of `T` in a new
+    /// sealed `memfd`, with an [`EventFlag`] word if `configure_event_flag`
+    /// is set (libfmq `AidlMessageQueue(numElementsInQueue,
+    /// configureEventFlagWord)`).
+    ///
+    /// Fails with [`StatusCode::BadValue`] for zero elements or a
+    /// zero-sized `T`, and with [`StatusCode::InvalidOperation`] on
+    /// platforms without `memfd_create`.
+    pub fn new(num_elements: usize, configure_event_flag: bool) -> Result<Self> {
+        let quantum = std::mem::size_of::<T>();
+        let ring_size = num_elements
+            .checked_mul(quantum)
+            .filter(|&size| size > 0 && i64::try_from(size).is_ok())
+            .ok_or(StatusCode::BadValue)?;
+        let data_offset = 2 * POSITION_SIZE;
+        let ring_end = data_offset
+            .checked_add(ring_size)
+            .ok_or(StatusCode::BadValue)?;
+        let mut grantors = vec![
+            Self::grantor(0, POSITION_SIZE)?,
+            Self::grantor(POSITION_SIZE, POSITION_SIZE)?,
+            Self::grantor(data_offset, ring_size)?,
+        ];
+        let mut total = ring_end;
+        if configure_event_flag {
+            let flag_offset = ring_end
+                .checked_next_multiple_of(POSITION_SIZE)
+                .ok_or(StatusCode::BadValue)?;
+            let flag_size = std::mem::size_of::<u32>();
+            grantors.push(Self::grantor(flag_offset, flag_size)?);
+            total = flag_offset + flag_size;
+        }
+
+        let heap = MemoryHeapBase::new(total, 0)?;
+        let fd = rustix::io::fcntl_dupfd_cloexec(heap.fd(), 0)?;
+        let base = heap.as_mut_ptr();
+        let region = |pos: usize| {
+            // SAFETY: every grantor lies within the `total` bytes of `heap`.
+            unsafe { base.add(grantors[pos].offset as usize) }
+        };
+        let read_ptr = region(READ_PTR_POS) as *const AtomicU64;
+        let write_ptr = region(WRITE_PTR_POS) as *const AtomicU64;
+        let ring = region(DATA_PTR_POS);
+        let event_flag = configure_event_flag.then(|| EventFlag {
+            word: region(EVENT_FLAG_POS) as *const AtomicU32,
+            _mapping: heap.clone(),
+        });
+
+        let queue = MessageQueue {
+            grantors,
+            fds: vec![fd],
+            ints: Vec::new(),
+            _mappings: vec![heap],
+            read_ptr,
+            write_ptr,
+            ring,
+            ring_size,
+            local_read_ptr: Self::local_read_ptr(),
+            event_flag,
+            _marker: PhantomData,
+        };
+        queue.reset_pointers();
+        Ok(queue)
+    }
+
+    /// Open the queue `desc` describes (libfmq `AidlMessageQueue(desc,
+    /// resetPointers)`). With `reset_pointers` the queue is emptied; the
+    /// creator usually opens it that way already, so peers pass `false`.
+    ///
+    /// Fails with [`StatusCode::BadValue`] if `desc` does not describe a
+    /// queue of `T` of this flavor, or its grantors do not fit its fds.
+    pub fn from_descriptor(desc: &MQDescriptor<T, F>, reset_pointers: bool) -> Result<Self> {
+        if desc.flags != F::FLAGS {
+            log::error!(
+                "fmq: descriptor flavor {} does not match the queue's {}",
+                desc.flags,
+                F::FLAGS
+            );
+            return Err(StatusCode::BadValue);
+        }
+        let quantum = std::mem::size_of::<T>();
+        if usize::try_from(desc.quantum).ok() != Some(quantum) {
+            log::error!(
+                "fmq: descriptor quantum {} does not match the element size {quantum}",
+                desc.quantum
+            );
+            return Err(StatusCode::BadValue);
+        }
+        if desc.grantors.len() <= DATA_PTR_POS {
+            return Err(StatusCode::BadValue);
+        }
+
+        let fds = desc
+            .handle
+            .fds
+            .iter()
+            .map(|fd| rustix::io::fcntl_dupfd_cloexec(fd, 0).map_err(StatusCode::from))
+            .collect::<Result<Vec<_>>>()?;
+        let mut mappings = Vec::new();
+        let mut map = |pos: usize, min_extent: usize, align: usize| -> Result<*mut u8> {
+            let (heap, ptr) = Self::map_grantor(&fds, &desc.grantors[pos], min_extent, align)?;
+            mappings.push(heap);
+            Ok(ptr)
+        };
+        let read_ptr = map(READ_PTR_POS, POSITION_SIZE, POSITION_SIZE)? as *const AtomicU64;
+        let write_ptr = map(WRITE_PTR_POS, POSITION_SIZE, POSITION_SIZE)? as *const AtomicU64;
+        let ring = map(DATA_PTR_POS, quantum, 1)?;
+        let event_flag = match desc.grantors.get(EVENT_FLAG_POS) {
+            Some(_) => {
+                let flag_size = std::mem::size_of::<u32>();
+                let word = map(EVENT_FLAG_POS, flag_size, flag_size)? as *const AtomicU32;
+                Some(word)
+            }
+            None => None,
+        };
+        let ring_size = desc.grantors[DATA_PTR_POS].extent as usize;
+        if ring_size % quantum != 0 {
+            return Err(StatusCode::BadValue);
+        }
+        let event_flag = event_flag.map(|word| EventFlag {
+            word,
+            _mapping: mappings[EVENT_FLAG_POS].clone(),
+        });
+
+        let queue = MessageQueue {
+            grantors: desc.grantors.clone(),
+            fds,
+            ints: desc.handle.ints.clone(),
+            _mappings: mappings,
+            read_ptr,
+            write_ptr,
+            ring,
+            ring_size,
+            local_read_ptr: Self::local_read_ptr(),
+            event_flag,
+            _marker: PhantomData,
+        };
+        if reset_pointers {
+            queue.reset_pointers();
+        }
+        Ok(queue)
+    }
+
+    /// A new descriptor of this queue, with duplicated fds, to send to a
+    /// peer (libfmq `dupeDesc`).
+    pub fn dupe_desc(&self) -> Result<MQDescriptor<T, F>> {
+        let fds = self
+            .fds
+            .iter()
+            .map(|fd| {
+                Ok(ParcelFileDescriptor::new(rustix::io::fcntl_dupfd_cloexec(
+                    fd, 0,
+                )?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(MQDescriptor::new(
+            self.grantors.clone(),
+            NativeHandle {
+                fds,
+                ints: self.ints.clone(),
+            },
+            std::mem::size_of::<T>() as i32,
+            F::FLAGS,
+        ))
+    }
+
+    /// Size of one element in bytes (libfmq `getQuantumSize`).
+    pub fn quantum_size(&self) -> usize {
+        std::mem::size_of::<T>()
+    }
+
+    /// Capacity of the queue in elements (libfmq `getQuantumCount`).
+    pub fn quantum_count(&self) -> usize {
+        self.ring_size / self.quantum_size()
+    }
+
+    /// The queue's event flag, if it was created with one (libfmq
+    /// `getEventFlagWord`), for waits on custom bits.
+    pub fn event_flag(&self) -> Option<&EventFlag> {
+        self.event_flag.as_ref()
+    }
+
+    /// Elements that can be written without overwriting unread data
+    /// (libfmq `availableToWrite`).
+    pub fn available_to_write(&self) -> usize {
+        self.quantum_count()
+            .saturating_sub(self.available_to_read_bytes() / self.quantum_size())
+    }
+
+    /// Elements waiting to be read (libfmq `availableToRead`). For an
+    /// unsynchronized reader that fell behind, this exceeds
+    /// [`quantum_count`](Self::quantum_count) until the next read.
+    pub fn available_to_read(&self) -> usize {
+        self.available_to_read_bytes() / self.quantum_size()
+    }
+
+    /// Write all of `data` without blocking (libfmq `write`), waking
+    /// readers blocked on [`FMQ_NOT_EMPTY`].
+    ///
+    /// A synchronized queue without room for all of `data` fails with
+    /// [`StatusCode::WouldBlock`] and writes nothing. More elements than
+    /// the queue holds is [`StatusCode::BadValue`].
+    pub fn write(&mut self, data: &[T]) -> Result<()> {
+        if data.len() > self.quantum_count() {
+            return Err(StatusCode::BadValue);
+        }
+        let bytes = std::mem::size_of_val(data);
+        let write_pos = self.write_ptr().load(Ordering::Relaxed);
+        if F::FLAGS == FLAG_SYNCHRONIZED_READ_WRITE {
+            let read_pos = self.read_ptr().load(Ordering::Acquire);
+            let used = write_pos.wrapping_sub(read_pos);
+            if used > self.ring_size as u64 {
+                log::error!("fmq: read pointer is ahead of the write pointer");
+                return Err(StatusCode::BadIndex);
+            }
+            if bytes as u64 > self.ring_size as u64 - used {
+                return Err(StatusCode::WouldBlock);
+            }
+        }
+
+        let start = (write_pos % self.ring_size as u64) as usize;
+        let first = bytes.min(self.ring_size - start);
+        let src = data.as_ptr() as *const u8;
+        // SAFETY: `start + first <= ring_size` and `bytes - first < ring_size`
+        // stay within the ring; `src` holds `bytes` bytes of plain data.
+        unsafe {
+            std::ptr::copy_nonoverlapping(src, self.ring.add(start), first);
+            std::ptr::copy_nonoverlapping(src.add(first), self.ring, bytes - first);
+        }
+        self.write_ptr()
+            .store(write_pos.wrapping_add(bytes as u64), Ordering::Release);
+
+        if let Some(flag) = &self.event_flag {
+            flag.wake(FMQ_NOT_EMPTY)?;
+        }
+        Ok(())
+    }
+
+    /// Fill all of `data` without blocking (libfmq `read`), waking writers
+    /// blocked on [`FMQ_NOT_FULL`].
+    ///
+    /// Fewer elements queued than `data` holds fails with
+    /// [`StatusCode::WouldBlock`] and reads nothing. A reader the writer
+    /// lapped fails with [`StatusCode::BadIndex`] and skips to the newest
+    /// data; more elements than the queue holds is
+    /// [`StatusCode::BadValue`].
+    pub fn read(&mut self, data: &mut [T]) -> Result<()> {
+        if data.len() > self.quantum_count() {
+            return Err(StatusCode::BadValue);
+        }
+        let bytes = std::mem::size_of_val(data);
+        let write_pos = self.write_ptr().load(Ordering::Acquire);
+        let read_pos = self.read_ptr().load(Ordering::Relaxed);
+        let available = write_pos.wrapping_sub(read_pos);
+        if available > self.ring_size as u64 {
+            // The writer overwrote data we had not read yet (or the peer
+            // corrupted the pointers): resume from the newest data.
+            log::error!("fmq: read failed after an overflow, resetting the read pointer");
+            self.read_ptr().store(write_pos, Ordering::Release);
+            return Err(StatusCode::BadIndex);
+        }
+        if (bytes as u64) > available {
+            return Err(StatusCode::WouldBlock);
+        }
+
+        let start = (read_pos % self.ring_size as u64) as usize;
+        let first = bytes.min(self.ring_size - start);
+        let dst = data.as_mut_ptr() as *mut u8;
+        // SAFETY: the ring ranges are in bounds as in `write`; `dst` holds
+        // `bytes` bytes, and `T: QueueElement` accepts any bytes.
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.ring.add(start), dst, first);
+            std::ptr::copy_nonoverlapping(self.ring, dst.add(first), bytes - first);
+        }
+        if F::FLAGS == FLAG_UNSYNCHRONIZED_WRITE
+            && self
+                .write_ptr()
+                .load(Ordering::Acquire)
+                .wrapping_sub(read_pos)
+                > self.ring_size as u64
+        {
+            // The writer lapped us while we copied; the copy may be torn.
+            self.read_ptr()
+                .store(self.write_ptr().load(Ordering::Acquire), Ordering::Release);
+            return Err(StatusCode::BadIndex);
+        }
+        self.read_ptr()
+            .store(read_pos.wrapping_add(bytes as u64), Ordering::Release);
+
+        if let Some(flag) = &self.event_flag {
+            flag.wake(FMQ_NOT_FULL)?;
+        }
+        Ok(())
+    }
+
+    fn grantor(offset: usize, extent: usize) -> Result<GrantorDescriptor> {
+        Ok(GrantorDescriptor {
+            fd_index: 0,
+            offset: i32::try_from(offset).map_err(|_| StatusCode::BadValue)?,
+            extent: extent as i64,
+        })
+    }
+
+    /// Map one grantor of a received descriptor, checking that it points
+    /// at an fd of the handle and is big and aligned enough for its use.
+    fn map_grantor(
+        fds: &[OwnedFd],
+        grantor: &GrantorDescriptor,
+        min_extent: usize,
+        align: usize,
+    ) -> Result<(MemoryHeapBase, *mut u8)> {
+        let fd = usize::try_from(grantor.fd_index)
+            .ok()
+            .and_then(|index| fds.get(index))
+            .ok_or(StatusCode::BadValue)?;
+        let offset = usize::try_from(grantor.offset).map_err(|_| StatusCode::BadValue)?;
+        let extent = usize::try_from(grantor.extent).map_err(|_| StatusCode::BadValue)?;
+        if extent < min_extent || offset % align != 0 {
+            log::error!("fmq: invalid grantor {grantor:?}");
+            return Err(StatusCode::BadValue);
+        }
+        let page_offset = offset - offset % rustix::param::page_size();
+        let length = offset - page_offset + extent;
+        let heap = MemoryHeapBase::from_fd(
+            rustix::io::fcntl_dupfd_cloexec(fd, 0)?,
+            length,
+            0,
+            page_offset,
+        )?;
+        // SAFETY: `offset - page_offset < length`, the size of the mapping.
+        let ptr = unsafe { heap.as_mut_ptr().add(offset - page_offset) };
+        Ok((heap, ptr))
+    }
+
+    fn local_read_ptr() -> Option<Box<AtomicU64>> {
+        (F::FLAGS == FLAG_UNSYNCHRONIZED_WRITE).then(|| Box::new(AtomicU64::new(0)))
+    }
+
+    fn reset_pointers(&self) {
+        self.write_ptr().store(0, Ordering::Release);
+        // SAFETY: as for `read_ptr`. An unsynchronized reader's private
+        // pointer already starts at 0.
+        unsafe { &*self.read_ptr }.store(0, Ordering::Release);
+    }
+
+    fn read_ptr(&self) -> &AtomicU64 {
+        match &self.local_read_ptr {
+            Some(local) => local,
+            // SAFETY: the read grantor is 8-byte aligned and mapped for as
+            // long as `_mappings` is alive.
+            None => unsafe { &*self.read_ptr },
+        }
+    }
+
+    fn write_ptr(&self) -> &AtomicU64 {
+        // SAFETY: as for `read_ptr`.
+        unsafe { &*self.write_ptr }
+    }
+
+    fn available_to_read_bytes(&self) -> usize {
+        let write_pos = self.write_ptr().load(Ordering::Acquire);
+        let read_pos = self.read_ptr().load(Ordering::Acquire);
+        usize::try_from(write_pos.wrapping_sub(read_pos)).unwrap_or(usize::MAX)
+    }
+}
+
+impl<T: QueueElement> MessageQueue<T, SynchronizedReadWrite> {
+    /// Write all of `data`, waiting on [`FMQ_NOT_FULL`] until there is room
+    /// (libfmq `writeBlocking(data, count, timeOutNanos)`).
+    ///
+    /// `None` waits forever; when the timeout elapses first nothing is
+    /// written and the result is [`StatusCode::TimedOut`]. A queue without
+    /// an event flag cannot block and fails with
+    /// [`StatusCode::InvalidOperation`].
+    pub fn write_blocking(&mut self, data: &[T], timeout: Option<Duration>) -> Result<()> {
+        let flag = self
+            .event_flag
+            .clone()
+            .ok_or(StatusCode::InvalidOperation)?;
+        Self::block_on(&flag, FMQ_NOT_FULL, timeout, || self.write(data))
+    }
+
+    /// Fill all of `data`, waiting on [`FMQ_NOT_EMPTY`] until enough
+    /// elements are queued (libfmq `readBlocking(data, count,
+    /// timeOutNanos)`). Timeouts and errors as for
+    /// [`write_blocking`](Self::write_blocking).
+    pub fn read_blocking(&mut self, data: &mut [T], timeout: Option<Duration>) -> Result<()> {
+        let flag = self
+            .event_flag
+            .clone()
+            .ok_or(StatusCode::InvalidOperation)?;
+        Self::block_on(&flag, FMQ_NOT_EMPTY, timeout, || self.read(data))
+    }
+
+    fn block_on(
+        flag: &EventFlag,
+        bit: u32,
+        timeout: Option<Duration>,
+        mut attempt: impl FnMut() -> Result<()>,
+    ) -> Result<()> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match attempt() {
+                Err(StatusCode::WouldBlock) => {}
+                result => return result,
+            }
+            let remaining = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) if !remaining.is_zero() => Some(remaining),
+                    _ => return Err(StatusCode::TimedOut),
+                },
+                None => None,
+            };
+            match flag.wait(bit, remaining) {
+                Ok(_) | Err(StatusCode::TimedOut) => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl<T: QueueElement, F: Flavor> fmt::Debug for MessageQueue<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageQueue")
+            .field("quantum_size", &self.quantum_size())
+            .field("quantum_count", &self.quantum_count())
+            .field("flags", &F::FLAGS)
+            .field("event_flag", &self.event_flag.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flavor_flags_match_libfmq() {
+        assert_eq!(SynchronizedReadWrite::FLAGS, 0x01);
+        assert_eq!(UnsynchronizedWrite::FLAGS, 0x02);
+        assert_eq!((FMQ_NOT_EMPTY, FMQ_NOT_FULL), (1, 2));
+    }
+
+    /// The descriptor round-trips through a parcel with its fd, in the
+    /// field order of the AIDL parcelable.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn descriptor_round_trips_through_a_parcel() {
+        let queue = MessageQueue::<u16, SynchronizedReadWrite>::new(10, true).unwrap();
+        let desc = queue.dupe_desc().unwrap();
+        assert_eq!(desc.quantum, 2);
+        assert_eq!(desc.flags, FLAG_SYNCHRONIZED_READ_WRITE);
+        assert_eq!(
+            desc.grantors
+                .iter()
+                .map(|g| (g.offset, g.extent))
+                .collect::<Vec<_>>(),
+            [(0, 8), (8, 8), (16, 20), (40, 4)]
+        );
+
+        let mut parcel = Parcel::new();
+        parcel.write(&desc).unwrap();
+        parcel.set_data_position(0);
+        let read: MQDescriptor<u16, SynchronizedReadWrite> = parcel.read().unwrap();
+        assert_eq!(read.grantors, desc.grantors);
+        assert_eq!(read.handle.fds.len(), 1);
+        assert_eq!(
+            (read.quantum, read.flags),
+            (2, FLAG_SYNCHRONIZED_READ_WRITE)
+        );
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn synchronized_queue_wraps_and_refuses_to_overwrite() {
+        let mut writer = MessageQueue::<i32, SynchronizedReadWrite>::new(4, false).unwrap();
+        let mut reader =
+            MessageQueue::from_descriptor(&writer.dupe_desc().unwrap(), false).unwrap();
+
+        writer.write(&[1, 2, 3]).unwrap();
+        assert_eq!(writer.write(&[4, 5]).unwrap_err(), StatusCode::WouldBlock);
+        assert_eq!(writer.write(&[1; 5]).unwrap_err(), StatusCode::BadValue);
+        assert_eq!(
+            (reader.available_to_read(), reader.available_to_write()),
+            (3, 1)
+        );
+
+        let mut out = [0; 2];
+        reader.read(&mut out).unwrap();
+        assert_eq!(out, [1, 2]);
+        // Wraps around the end of the ring.
+        writer.write(&[4, 5, 6]).unwrap();
+        let mut out = [0; 4];
+        reader.read(&mut out).unwrap();
+        assert_eq!(out, [3, 4, 5, 6]);
+        assert_eq!(
+            reader.read(&mut out[..1]).unwrap_err(),
+            StatusCode::WouldBlock
+        );
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn unsynchronized_reader_recovers_from_overflow() {
+        let mut writer = MessageQueue::<u8, UnsynchronizedWrite>::new(4, false).unwrap();
+        let mut reader =
+            MessageQueue::from_descriptor(&writer.dupe_desc().unwrap(), false).unwrap();
+
+        writer.write(&[1, 2, 3]).unwrap();
+        writer.write(&[4, 5, 6]).unwrap();
+        let mut out = [0; 1];
+        assert_eq!(reader.read(&mut out).unwrap_err(), StatusCode::BadIndex);
+        writer.write(&[7]).unwrap();
+        reader.read(&mut out).unwrap();
+        assert_eq!(out, [7]);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn mismatched_descriptors_are_rejected() {
+        let queue = MessageQueue::<i32, SynchronizedReadWrite>::new(4, false).unwrap();
+        let desc = queue.dupe_desc().unwrap();
+        let as_unsync = MQDescriptor::<i32, UnsynchronizedWrite>::new(
+            desc.grantors.clone(),
+            NativeHandle::default(),
+            desc.quantum,
+            desc.flags,
+        );
+        assert_eq!(
+            MessageQueue::from_descriptor(&as_unsync, false).unwrap_err(),
+            StatusCode::BadValue
+        );
+        let as_i64 = MQDescriptor::<i64, SynchronizedReadWrite>::new(
+            desc.grantors.clone(),
+            NativeHandle::default(),
+            desc.quantum,
+            desc.flags,
+        );
+        assert_eq!(
+            MessageQueue::from_descriptor(&as_i64, false).unwrap_err(),
+            StatusCode::BadValue
+        );
+        // The grantors point at an fd the handle does not have.
+        let no_fds = MQDescriptor::<i32, SynchronizedReadWrite>::new(
+            desc.grantors.clone(),
+            NativeHandle::default(),
+            desc.quantum,
+            desc.flags,
+        );
+        assert_eq!(
+            MessageQueue::from_descriptor(&no_fds, false).unwrap_err(),
+            StatusCode::BadValue
+        );
+        assert_eq!(
+            MessageQueue::<i32, SynchronizedReadWrite>::new(0, false).unwrap_err(),
+            StatusCode::BadValue
+        );
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn blocking_read_waits_for_the_writer() {
+        let mut reader = MessageQueue::<u32, SynchronizedReadWrite>::new(2, true).unwrap();
+        let mut writer =
+            MessageQueue::from_descriptor(&reader.dupe_desc().unwrap(), false).unwrap();
+
+        let mut out = [0; 2];
+        assert_eq!(
+            reader
+                .read_blocking(&mut out, Some(Duration::from_millis(10)))
+                .unwrap_err(),
+            StatusCode::TimedOut
+        );
+
+        let producer = std::thread::spawn(move || {
+            for value in 0..8 {
+                writer.write_blocking(&[value], None).unwrap();
+            }
+        });
+        let mut received = Vec::new();
+        while received.len() < 8 {
+            let mut one = [0];
+            reader
+                .read_blocking(&mut one, Some(Duration::from_secs(5)))
+                .unwrap();
+            received.push(one[0]);
+        }
+        producer.join().unwrap();
+        assert_eq!(received, (0..8).collect::<Vec<_>>());
+
+        let mut no_flag = MessageQueue::<u32, SynchronizedReadWrite>::new(2, false).unwrap();
+        assert_eq!(
+            no_flag.read_blocking(&mut out, None).unwrap_err(),
+            StatusCode::InvalidOperation
+        );
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Client for `hwservicemanager` (`android.hidl.manager@1.x::IServiceManager`).
+//!
+//! The interfaces are generated from the `.hal` files under
+//! `hidl/android/hidl/` (vendored from AOSP `system/libhidl/transport`),
+//! together with the `android.hidl.base@1.0` types they use. On a
+//! hwbinder [`ProcessState`] the context object
+//! (handle 0) is the service manager; [`default`] casts it to the 1.0
+//! interface, which every `hwservicemanager` implements. Cast the same
+//! binder to [`V1_1`] or [`V1_2`] for the later methods.
+//!
+//! ```no_run
+//! use rsbinder::hidl::manager;
+//!
+//! rsbinder::ProcessState::init("/dev/hwbinder", 0);
+//! for name in manager::list()? {
+//!     println!("{name}");
+//! }
+//! # Ok::<(), rsbinder::StatusCode>(())
+//! ```
+
+include!(concat!(env!("OUT_DIR"), "/hidl_manager.rs"));
+
+pub use android::hidl::base::V1_0::DebugInfo::{Architecture::Architecture, DebugInfo};
+pub use android::hidl::manager::{V1_0, V1_1, V1_2};
+pub use V1_0::IServiceManager::{IServiceManager, Transport::Transport};
+
+use crate::error::{Result, StatusCode};
+use crate::{FromIBinder, ProcessState, SIBinder, Strong};
+
+/// The service manager of the current hwbinder process.
+///
+/// Fails with [`StatusCode::InvalidOperation`] if [`ProcessState`] was not
+/// initialized on a hwbinder node.
+pub fn default() -> Result<Strong<dyn IServiceManager>> {
+    let process = ProcessState::as_self();
+    if !process.is_hwbinder() {
+        log::error!("HIDL: hwservicemanager requires a hwbinder ProcessState");
+        return Err(StatusCode::InvalidOperation);
+    }
+    <dyn IServiceManager>::try_from(process.context_object()?)
+}
+
+/// Look up instance `name` of interface `fq_name`
+/// (e.g. `"android.hardware.foo@1.0::IFoo"`, `"default"`).
+pub fn get_service(fq_name: &str, name: &str) -> Result<Option<SIBinder>> {
+    default()?.get(fq_name, name).map_err(StatusCode::from)
+}
+
+/// Look up instance `name` and cast it to `T`, whose descriptor names the
+/// interface to ask for.
+pub fn get_interface<T: FromIBinder + ?Sized>(fq_name: &str, name: &str) -> Result<Strong<T>> {
+    match get_service(fq_name, name)? {
+        Some(binder) => T::try_from(binder),
+        None => Err(StatusCode::NameNotFound),
+    }
+}
+
+/// Register `service` as instance `name`. The service manager asks the
+/// service for its interface chain, so it is listed under every interface
+/// it extends.
+pub fn add_service(name: &str, service: &SIBinder) -> Result<()> {
+    if default()?
+        .add(name, Some(service))
+        .map_err(StatusCode::from)?
+    {
+        Ok(())
+    } else {
+        Err(StatusCode::PermissionDenied)
+    }
+}
+
+/// Every registered instance, as `"fqName/instanceName"`.
+pub fn list() -> Result<Vec<String>> {
+    default()?.list().map_err(StatusCode::from)
+}
+
+/// The transport the device manifest declares for an instance.
+pub fn get_transport(fq_name: &str, name: &str) -> Result<Transport> {
+    default()?
+        .getTransport(fq_name, name)
+        .map_err(StatusCode::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hidl::HwParcel;
+    use crate::status::Result as BinderResult;
+    use crate::{Interface, Parcel};
+    use V1_0::IServiceManager::{
+        transactions, BnHwServiceManager, InstanceDebugInfo::InstanceDebugInfo,
+    };
+
+    struct MockManager;
+
+    impl Interface for MockManager {}
+
+    impl IServiceManager for MockManager {
+        fn get(&self, _fq_name: &str, _name: &str) -> BinderResult<Option<SIBinder>> {
+            Ok(None)
+        }
+        fn add(&self, _name: &str, _service: Option<&SIBinder>) -> BinderResult<bool> {
+            Ok(false)
+        }
+        fn getTransport(&self, fq_name: &str, _name: &str) -> BinderResult<Transport> {
+            Ok(if fq_name.starts_with("android.hardware.") {
+                Transport::HWBINDER
+            } else {
+                Transport::EMPTY
+            })
+        }
+        fn list(&self) -> BinderResult<Vec<String>> {
+            Ok(vec!["android.hardware.foo@1.0::IFoo/default".into()])
+        }
+        fn listByInterface(&self, _fq_name: &str) -> BinderResult<Vec<String>> {
+            Ok(vec!["default".into(), "slot1".into()])
+        }
+        fn registerForNotifications(
+            &self,
+            _fq_name: &str,
+            _name: &str,
+            _callback: Option<&Strong<dyn V1_0::IServiceNotification::IServiceNotification>>,
+        ) -> BinderResult<bool> {
+            Ok(false)
+        }
+        fn debugDump(&self) -> BinderResult<Vec<InstanceDebugInfo>> {
+            Ok(vec![InstanceDebugInfo {
+                interfaceName: "android.hardware.foo@1.0::IFoo".into(),
+                instanceName: "default".into(),
+                pid: 42,
+                clientPids: vec![7, 8],
+                arch: Architecture::IS_64BIT,
+            }])
+        }
+        fn registerPassthroughClient(&self, _fq_name: &str, _name: &str) -> BinderResult<()> {
+            Ok(())
+        }
+    }
+
+    fn call(code: crate::TransactionCode, data: &mut Parcel) -> Parcel {
+        let binder = BnHwServiceManager::new_binder(MockManager);
+        let mut reply = Parcel::new();
+        binder
+            .as_binder()
+            .as_transactable()
+            .unwrap()
+            .transact(code, data, &mut reply)
+            .unwrap();
+        reply.set_data_position(0);
+        reply
+    }
+
+    #[test]
+    fn stub_dispatches_methods() {
+        let mut data = Parcel::new();
+        data.write_hidl_token(V1_0::IServiceManager::DESCRIPTOR)
+            .unwrap();
+        data.write_hidl("android.hardware.foo@1.0::IFoo").unwrap();
+        data.write_hidl("default").unwrap();
+        let mut reply = call(transactions::r#getTransport, &mut data);
+        assert!(reply.read_hidl_status().unwrap().is_ok());
+        assert_eq!(reply.read_hidl::<Transport>().unwrap(), Transport::HWBINDER);
+
+        let mut data = Parcel::new();
+        data.write_hidl_token(V1_0::IServiceManager::DESCRIPTOR)
+            .unwrap();
+        let mut reply = call(transactions::r#debugDump, &mut data);
+        assert!(reply.read_hidl_status().unwrap().is_ok());
+        let info: Vec<InstanceDebugInfo> = reply.read_hidl().unwrap();
+        assert_eq!(info.len(), 1);
+        assert_eq!(info[0].instanceName, "default");
+        assert_eq!(info[0].clientPids, vec![7, 8]);
+        assert_eq!(info[0].arch, Architecture::IS_64BIT);
+    }
+
+    #[test]
+    fn stub_rejects_wrong_token() {
+        let binder = BnHwServiceManager::new_binder(MockManager);
+        let mut data = Parcel::new();
+        data.write_hidl_token("android.hidl.manager@1.0::IServiceNotification")
+            .unwrap();
+        let mut reply = Parcel::new();
+        let result = binder.as_binder().as_transactable().unwrap().transact(
+            transactions::r#list,
+            &mut data,
+            &mut reply,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn stub_serves_ibase() {
+        let mut data = Parcel::new();
+        data.write_hidl_token(crate::hidl::IBASE_DESCRIPTOR)
+            .unwrap();
+        let mut reply = call(crate::hidl::INTERFACE_CHAIN_TRANSACTION, &mut data);
+        assert!(reply.read_hidl_status().unwrap().is_ok());
+        let chain: Vec<String> = reply.read_hidl().unwrap();
+        assert_eq!(
+            chain,
+            vec![
+                V1_0::IServiceManager::DESCRIPTOR.to_owned(),
+                crate::hidl::IBASE_DESCRIPTOR.to_owned()
+            ]
+        );
+
+        let mut data = Parcel::new();
+        data.write_hidl_token(crate::hidl::IBASE_DESCRIPTOR)
+            .unwrap();
+        let mut reply = call(crate::hidl::GET_DEBUG_INFO_TRANSACTION, &mut data);
+        assert!(reply.read_hidl_status().unwrap().is_ok());
+        let info: DebugInfo = reply.read_hidl().unwrap();
+        assert_eq!(info.pid, std::process::id() as i32);
+    }
+}
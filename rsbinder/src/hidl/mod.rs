@@ -0,0 +1,911 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! HIDL (`hwbinder`) wire support.
+//!
+//! HIDL interfaces use the same kernel driver as AIDL, normally through the
+//! `/dev/hwbinder` node, but marshal data differently (AOSP
+//! `system/libhwbinder/Parcel.cpp` and `system/libhidl`):
+//!
+//! - The interface token is a NUL-terminated 8-bit string, not the AIDL
+//!   header, and every method checks the token of the interface that
+//!   declared it.
+//! - Scalars and enums are written inline; strings, vectors, structs and
+//!   arrays are written as C-layout scatter-gather buffers, with the
+//!   out-of-line parts (string bytes, vector elements) embedded as child
+//!   buffers whose pointers the driver patches in the receiver.
+//! - File descriptors travel as `native_handle_t` buffers covered by an
+//!   fd array object.
+//! - Binder objects are plain `flat_binder_object`s with no stability word.
+//! - Every binder implements `android.hidl.base@1.0::IBase`, whose methods
+//!   use reserved transaction codes above the user range.
+//!
+//! [`HwParcel`] adds the HIDL encodings to [`Parcel`]; [`HidlType`] and
+//! [`HidlRead`] describe how a value is laid out. `rsbinder_aidl::hidl`
+//! generates interfaces, structs and enums from `.hal` files on top of
+//! these, and [`manager`] is the generated client for
+//! `android.hidl.manager@1.x::IServiceManager`.
+//!
+//! A process uses HIDL mode when it initializes
+//! [`ProcessState`](crate::ProcessState) on a driver node named `hwbinder`
+//! (see [`ProcessState::is_hwbinder`](crate::ProcessState::is_hwbinder)).
+
+use std::os::fd::{AsRawFd, OwnedFd};
+
+use crate::binder::{SIBinder, TransactionCode};
+use crate::binder_object::flat_binder_object;
+use crate::error::{Result, StatusCode};
+use crate::parcel::Parcel;
+use crate::parcelable::{binder_from_flat, check_binder_context};
+use crate::status::{ExceptionCode, Status};
+use crate::Stability;
+
+pub mod manager;
+
+/// Descriptor of `android.hidl.base@1.0::IBase`, the root of every HIDL
+/// interface chain.
+pub const IBASE_DESCRIPTOR: &str = "android.hidl.base@1.0::IBase";
+
+/// `IBase::interfaceChain` (`0x0f` followed by `"CHN"`).
+pub const INTERFACE_CHAIN_TRANSACTION: TransactionCode = 0x0f43484e;
+/// `IBase::debug` (`0x0f` followed by `"DBG"`).
+pub const DEBUG_TRANSACTION: TransactionCode = 0x0f444247;
+/// `IBase::interfaceDescriptor` (`0x0f` followed by `"DSC"`).
+pub const INTERFACE_DESCRIPTOR_TRANSACTION: TransactionCode = 0x0f445343;
+/// `IBase::getHashChain` (`0x0f` followed by `"HSH"`).
+pub const GET_HASH_CHAIN_TRANSACTION: TransactionCode = 0x0f485348;
+/// `IBase::setHALInstrumentation` (`0x0f` followed by `"INT"`), oneway.
+pub const SET_HAL_INSTRUMENTATION_TRANSACTION: TransactionCode = 0x0f494e54;
+/// `IBase::ping` (`0x0f` followed by `"PNG"`).
+pub const PING_TRANSACTION: TransactionCode = 0x0f504e47;
+/// `IBase::getDebugInfo` (`0x0f` followed by `"REF"`).
+pub const GET_DEBUG_INFO_TRANSACTION: TransactionCode = 0x0f524546;
+/// `IBase::notifySyspropsChanged` (`0x0f` followed by `"SYS"`), oneway.
+pub const NOTIFY_SYSPROPS_CHANGED_TRANSACTION: TransactionCode = 0x0f535953;
+
+/// `sizeof(native_handle_t)` header: `version`, `numFds`, `numInts`.
+const NATIVE_HANDLE_HEADER: usize = 3 * std::mem::size_of::<i32>();
+
+/// Round `value` up to a multiple of `align` (a power of two). Used by
+/// generated structs to compute C field offsets.
+pub const fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+/// The larger of two alignments, usable in constant expressions.
+pub const fn max_align(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// A `native_handle_t`: file descriptors plus opaque integers, the HIDL
+/// `handle` type.
+///
+/// The fds are owned; sending a handle dups them into the parcel, and a
+/// received handle holds dups of the fds the driver installed.
+#[derive(Debug, Default)]
+pub struct NativeHandle {
+    pub fds: Vec<OwnedFd>,
+    pub ints: Vec<i32>,
+}
+
+impl NativeHandle {
+    pub fn new(fds: Vec<OwnedFd>, ints: Vec<i32>) -> Self {
+        Self { fds, ints }
+    }
+
+    /// Duplicate the handle, dup'ing every fd.
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        let fds = self
+            .fds
+            .iter()
+            .map(|fd| fd.try_clone())
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Self {
+            fds,
+            ints: self.ints.clone(),
+        })
+    }
+}
+
+impl PartialEq for NativeHandle {
+    /// Handles compare by fd number and ints, like AOSP `hidl_handle`
+    /// comparisons of the underlying `native_handle_t`.
+    fn eq(&self, other: &Self) -> bool {
+        self.ints == other.ints
+            && self.fds.len() == other.fds.len()
+            && self
+                .fds
+                .iter()
+                .zip(&other.fds)
+                .all(|(a, b)| a.as_raw_fd() == b.as_raw_fd())
+    }
+}
+
+/// A value with a HIDL C layout.
+///
+/// `SIZE`/`ALIGN` describe the in-buffer representation (e.g. 16 bytes for
+/// `hidl_string`). [`HidlType::write_layout`] fills that representation;
+/// [`HidlType::write_embedded`] then writes any out-of-line children as
+/// buffers embedded in the one holding the value, at `offset` within
+/// object `parent`.
+pub trait HidlType {
+    /// Size of the C representation in bytes.
+    const SIZE: usize;
+    /// Alignment of the C representation in bytes.
+    const ALIGN: usize;
+
+    /// Write the C representation into `out` (exactly `SIZE` bytes,
+    /// zero-initialized). Pointer slots are left zero; the driver patches
+    /// them in the receiver.
+    fn write_layout(&self, out: &mut [u8]);
+
+    /// Write the out-of-line children of a value stored at `offset` in the
+    /// buffer object `parent`.
+    fn write_embedded(&self, _parcel: &mut Parcel, _parent: usize, _offset: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Write the value as a top-level argument: a buffer holding the
+    /// layout, followed by its children.
+    fn write_top_level(&self, parcel: &mut Parcel) -> Result<()> {
+        let mut layout = vec![0u8; Self::SIZE];
+        self.write_layout(&mut layout);
+        let parent = parcel.write_buffer(&layout)?;
+        self.write_embedded(parcel, parent, 0)
+    }
+}
+
+/// The read side of [`HidlType`].
+pub trait HidlRead: HidlType + Sized {
+    /// Rebuild a value from its C representation `layout` (exactly `SIZE`
+    /// bytes), reading its children embedded at `offset` in `parent`.
+    fn read_embedded(
+        parcel: &mut Parcel,
+        layout: &[u8],
+        parent: usize,
+        offset: usize,
+    ) -> Result<Self>;
+
+    /// Read a value written by [`HidlType::write_top_level`].
+    fn read_top_level(parcel: &mut Parcel) -> Result<Self> {
+        let (parent, buffer) = parcel.read_buffer()?;
+        if buffer.len() != Self::SIZE {
+            log::error!(
+                "HIDL: top-level buffer is {} bytes, expected {}",
+                buffer.len(),
+                Self::SIZE
+            );
+            return Err(StatusCode::BadValue);
+        }
+        let layout = buffer.to_vec();
+        Self::read_embedded(parcel, &layout, parent, 0)
+    }
+}
+
+macro_rules! hidl_scalar {
+    ($($ty:ty),*) => {
+        $(
+            impl HidlType for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+                const ALIGN: usize = std::mem::align_of::<$ty>();
+
+                fn write_layout(&self, out: &mut [u8]) {
+                    out.copy_from_slice(&self.to_ne_bytes());
+                }
+
+                fn write_top_level(&self, parcel: &mut Parcel) -> Result<()> {
+                    parcel.write_aligned_data(&self.to_ne_bytes())
+                }
+            }
+
+            impl HidlRead for $ty {
+                fn read_embedded(
+                    _parcel: &mut Parcel,
+                    layout: &[u8],
+                    _parent: usize,
+                    _offset: usize,
+                ) -> Result<Self> {
+                    Ok(<$ty>::from_ne_bytes(layout.try_into()?))
+                }
+
+                fn read_top_level(parcel: &mut Parcel) -> Result<Self> {
+                    let bytes = parcel.read_aligned_data(std::mem::size_of::<$ty>())?;
+                    Ok(<$ty>::from_ne_bytes(bytes.try_into()?))
+                }
+            }
+        )*
+    };
+}
+
+hidl_scalar!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+impl HidlType for bool {
+    const SIZE: usize = 1;
+    const ALIGN: usize = 1;
+
+    fn write_layout(&self, out: &mut [u8]) {
+        out[0] = *self as u8;
+    }
+
+    fn write_top_level(&self, parcel: &mut Parcel) -> Result<()> {
+        parcel.write_aligned_data(&[*self as u8])
+    }
+}
+
+impl HidlRead for bool {
+    fn read_embedded(
+        _parcel: &mut Parcel,
+        layout: &[u8],
+        _parent: usize,
+        _offset: usize,
+    ) -> Result<Self> {
+        Ok(layout[0] != 0)
+    }
+
+    fn read_top_level(parcel: &mut Parcel) -> Result<Self> {
+        Ok(parcel.read_aligned_data(1)?[0] != 0)
+    }
+}
+
+/// `hidl_string` / `hidl_vec` header: buffer pointer, element count and an
+/// ownership flag, padded to 16 bytes.
+const HIDL_POINTER_LAYOUT_SIZE: usize = 16;
+const HIDL_SIZE_OFFSET: usize = 8;
+
+fn write_pointer_layout(len: usize, out: &mut [u8]) {
+    out[HIDL_SIZE_OFFSET..HIDL_SIZE_OFFSET + 4].copy_from_slice(&(len as u32).to_ne_bytes());
+}
+
+fn read_pointer_layout_len(layout: &[u8]) -> Result<usize> {
+    let len = u32::from_ne_bytes(layout[HIDL_SIZE_OFFSET..HIDL_SIZE_OFFSET + 4].try_into()?);
+    Ok(len as usize)
+}
+
+impl HidlType for str {
+    const SIZE: usize = HIDL_POINTER_LAYOUT_SIZE;
+    const ALIGN: usize = 8;
+
+    fn write_layout(&self, out: &mut [u8]) {
+        write_pointer_layout(self.len(), out);
+    }
+
+    fn write_embedded(&self, parcel: &mut Parcel, parent: usize, offset: usize) -> Result<()> {
+        if u32::try_from(self.len()).is_err() {
+            return Err(StatusCode::BadValue);
+        }
+        let mut bytes = Vec::with_capacity(self.len() + 1);
+        bytes.extend_from_slice(self.as_bytes());
+        bytes.push(0);
+        parcel.write_embedded_buffer(&bytes, parent, offset)?;
+        Ok(())
+    }
+}
+
+impl HidlType for String {
+    const SIZE: usize = <str as HidlType>::SIZE;
+    const ALIGN: usize = <str as HidlType>::ALIGN;
+
+    fn write_layout(&self, out: &mut [u8]) {
+        self.as_str().write_layout(out)
+    }
+
+    fn write_embedded(&self, parcel: &mut Parcel, parent: usize, offset: usize) -> Result<()> {
+        self.as_str().write_embedded(parcel, parent, offset)
+    }
+}
+
+impl HidlRead for String {
+    fn read_embedded(
+        parcel: &mut Parcel,
+        layout: &[u8],
+        parent: usize,
+        offset: usize,
+    ) -> Result<Self> {
+        let len = read_pointer_layout_len(layout)?;
+        let (_, bytes) = parcel.read_embedded_buffer(parent, offset)?;
+        if bytes.len() != len + 1 || bytes[len] != 0 {
+            log::error!(
+                "HIDL: string buffer of {} bytes for length {len}",
+                bytes.len()
+            );
+            return Err(StatusCode::BadValue);
+        }
+        String::from_utf8(bytes[..len].to_vec()).map_err(|err| {
+            log::error!("HIDL: string is not UTF-8: {err}");
+            StatusCode::BadValue
+        })
+    }
+}
+
+impl<T: HidlType> HidlType for [T] {
+    const SIZE: usize = HIDL_POINTER_LAYOUT_SIZE;
+    const ALIGN: usize = 8;
+
+    fn write_layout(&self, out: &mut [u8]) {
+        write_pointer_layout(self.len(), out);
+    }
+
+    fn write_embedded(&self, parcel: &mut Parcel, parent: usize, offset: usize) -> Result<()> {
+        if u32::try_from(self.len()).is_err() {
+            return Err(StatusCode::BadValue);
+        }
+        let len = self
+            .len()
+            .checked_mul(T::SIZE)
+            .ok_or(StatusCode::BadValue)?;
+        let mut elements = vec![0u8; len];
+        for (value, out) in self.iter().zip(elements.chunks_exact_mut(T::SIZE.max(1))) {
+            value.write_layout(out);
+        }
+        let child = parcel.write_embedded_buffer(&elements, parent, offset)?;
+        for (index, value) in self.iter().enumerate() {
+            value.write_embedded(parcel, child, index * T::SIZE)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: HidlType> HidlType for Vec<T> {
+    const SIZE: usize = <[T] as HidlType>::SIZE;
+    const ALIGN: usize = <[T] as HidlType>::ALIGN;
+
+    fn write_layout(&self, out: &mut [u8]) {
+        self.as_slice().write_layout(out)
+    }
+
+    fn write_embedded(&self, parcel: &mut Parcel, parent: usize, offset: usize) -> Result<()> {
+        self.as_slice().write_embedded(parcel, parent, offset)
+    }
+}
+
+impl<T: HidlRead> HidlRead for Vec<T> {
+    fn read_embedded(
+        parcel: &mut Parcel,
+        layout: &[u8],
+        parent: usize,
+        offset: usize,
+    ) -> Result<Self> {
+        let len = read_pointer_layout_len(layout)?;
+        let (child, bytes) = parcel.read_embedded_buffer(parent, offset)?;
+        if Some(bytes.len()) != len.checked_mul(T::SIZE) {
+            log::error!(
+                "HIDL: vector buffer of {} bytes for {len} elements of {}",
+                bytes.len(),
+                T::SIZE
+            );
+            return Err(StatusCode::BadValue);
+        }
+        let elements = bytes.to_vec();
+        (0..len)
+            .map(|index| {
+                let start = index * T::SIZE;
+                T::read_embedded(parcel, &elements[start..start + T::SIZE], child, start)
+            })
+            .collect()
+    }
+}
+
+impl<T: HidlType, const N: usize> HidlType for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+    const ALIGN: usize = T::ALIGN;
+
+    fn write_layout(&self, out: &mut [u8]) {
+        for (value, out) in self.iter().zip(out.chunks_exact_mut(T::SIZE.max(1))) {
+            value.write_layout(out);
+        }
+    }
+
+    fn write_embedded(&self, parcel: &mut Parcel, parent: usize, offset: usize) -> Result<()> {
+        for (index, value) in self.iter().enumerate() {
+            value.write_embedded(parcel, parent, offset + index * T::SIZE)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: HidlRead, const N: usize> HidlRead for [T; N] {
+    fn read_embedded(
+        parcel: &mut Parcel,
+        layout: &[u8],
+        parent: usize,
+        offset: usize,
+    ) -> Result<Self> {
+        let values = (0..N)
+            .map(|index| {
+                let start = index * T::SIZE;
+                T::read_embedded(
+                    parcel,
+                    &layout[start..start + T::SIZE],
+                    parent,
+                    offset + start,
+                )
+            })
+            .collect::<Result<Vec<T>>>()?;
+        values.try_into().map_err(|_| StatusCode::BadValue)
+    }
+}
+
+/// `hidl_handle`: a `native_handle_t` pointer and an ownership flag, padded
+/// to 16 bytes. `None` is the null handle.
+impl HidlType for Option<NativeHandle> {
+    const SIZE: usize = HIDL_POINTER_LAYOUT_SIZE;
+    const ALIGN: usize = 8;
+
+    fn write_layout(&self, _out: &mut [u8]) {}
+
+    fn write_embedded(&self, parcel: &mut Parcel, parent: usize, offset: usize) -> Result<()> {
+        write_native_handle(parcel, self.as_ref(), Some((parent, offset)))
+    }
+
+    fn write_top_level(&self, parcel: &mut Parcel) -> Result<()> {
+        write_native_handle(parcel, self.as_ref(), None)
+    }
+}
+
+impl HidlRead for Option<NativeHandle> {
+    fn read_embedded(
+        parcel: &mut Parcel,
+        _layout: &[u8],
+        parent: usize,
+        offset: usize,
+    ) -> Result<Self> {
+        read_native_handle(parcel, Some((parent, offset)))
+    }
+
+    fn read_top_level(parcel: &mut Parcel) -> Result<Self> {
+        read_native_handle(parcel, None)
+    }
+}
+
+/// AOSP libhwbinder `Parcel::writeNativeHandleNoDup`: the handle as a
+/// buffer, plus an fd array over its `data[0..numFds]` when it has fds.
+fn write_native_handle(
+    parcel: &mut Parcel,
+    handle: Option<&NativeHandle>,
+    parent: Option<(usize, usize)>,
+) -> Result<()> {
+    let write = |parcel: &mut Parcel, bytes: &[u8]| match parent {
+        Some((parent, offset)) => parcel.write_embedded_buffer(bytes, parent, offset),
+        None => parcel.write_buffer(bytes),
+    };
+
+    let Some(handle) = handle else {
+        write(parcel, &[])?;
+        return Ok(());
+    };
+
+    let num_fds = i32::try_from(handle.fds.len()).map_err(|_| StatusCode::BadValue)?;
+    let num_ints = i32::try_from(handle.ints.len()).map_err(|_| StatusCode::BadValue)?;
+    let fds = handle
+        .fds
+        .iter()
+        .map(|fd| rustix::io::fcntl_dupfd_cloexec(fd, 0).map_err(std::io::Error::from))
+        .collect::<std::io::Result<Vec<OwnedFd>>>()?;
+
+    let mut bytes = Vec::with_capacity(NATIVE_HANDLE_HEADER + 4 * (fds.len() + handle.ints.len()));
+    bytes.extend_from_slice(&(NATIVE_HANDLE_HEADER as i32).to_ne_bytes());
+    bytes.extend_from_slice(&num_fds.to_ne_bytes());
+    bytes.extend_from_slice(&num_ints.to_ne_bytes());
+    for fd in &fds {
+        bytes.extend_from_slice(&fd.as_raw_fd().to_ne_bytes());
+    }
+    for value in &handle.ints {
+        bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+
+    let buffer = write(parcel, &bytes)?;
+    if !fds.is_empty() {
+        parcel.write_embedded_fd_array(fds.len(), buffer, NATIVE_HANDLE_HEADER)?;
+        parcel.hold_fds(fds);
+    }
+    Ok(())
+}
+
+/// AOSP libhwbinder `Parcel::readNullableNativeHandleNoDup`.
+fn read_native_handle(
+    parcel: &mut Parcel,
+    parent: Option<(usize, usize)>,
+) -> Result<Option<NativeHandle>> {
+    let (buffer, bytes) = match parent {
+        Some((parent, offset)) => parcel.read_embedded_buffer(parent, offset)?,
+        None => parcel.read_buffer()?,
+    };
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+
+    let words = bytes
+        .chunks_exact(4)
+        .map(|word| i32::from_ne_bytes(word.try_into().expect("4-byte chunk")))
+        .collect::<Vec<_>>();
+    let valid = bytes.len() % 4 == 0
+        && words.len() >= 3
+        && words[0] == NATIVE_HANDLE_HEADER as i32
+        && words[1] >= 0
+        && words[2] >= 0
+        && words.len() == 3 + words[1] as usize + words[2] as usize;
+    if !valid {
+        log::error!("HIDL: malformed native_handle_t of {} bytes", bytes.len());
+        return Err(StatusCode::BadValue);
+    }
+    let num_fds = words[1] as usize;
+    let ints = words[3 + num_fds..].to_vec();
+
+    let fds = if num_fds > 0 {
+        parcel.read_embedded_fd_array(buffer, NATIVE_HANDLE_HEADER)?
+    } else {
+        Vec::new()
+    };
+    if fds.len() != num_fds {
+        log::error!(
+            "HIDL: native handle has {num_fds} fds, fd array {}",
+            fds.len()
+        );
+        return Err(StatusCode::BadValue);
+    }
+    Ok(Some(NativeHandle { fds, ints }))
+}
+
+/// HIDL encodings on top of [`Parcel`] (the AOSP libhwbinder
+/// `hardware::Parcel` surface used by `hidl-gen` output).
+pub trait HwParcel {
+    /// Write the interface token of `descriptor` (a C string).
+    fn write_hidl_token(&mut self, descriptor: &str) -> Result<()>;
+
+    /// Read an interface token and check that it names `descriptor`.
+    fn enforce_hidl_token(&mut self, descriptor: &str) -> Result<()>;
+
+    /// Write a value as a top-level argument.
+    fn write_hidl<T: HidlType + ?Sized>(&mut self, value: &T) -> Result<()>;
+
+    /// Read a value written by [`HwParcel::write_hidl`].
+    fn read_hidl<T: HidlRead>(&mut self) -> Result<T>;
+
+    /// Write a HIDL `Status`: the exception code, then the message when
+    /// it is not `EX_NONE`. A transport failure is returned as the error
+    /// instead, like AOSP `writeToParcel(const Status&)`.
+    fn write_hidl_status(&mut self, status: &Status) -> Result<()>;
+
+    /// Read a HIDL `Status` written by [`HwParcel::write_hidl_status`].
+    fn read_hidl_status(&mut self) -> Result<Status>;
+
+    /// Write a binder object (`writeStrongBinder`, no stability word).
+    fn write_hidl_binder(&mut self, binder: Option<&SIBinder>) -> Result<()>;
+
+    /// Read a binder object written by [`HwParcel::write_hidl_binder`].
+    fn read_hidl_binder(&mut self) -> Result<Option<SIBinder>>;
+
+    /// Write a top-level `handle` argument.
+    fn write_native_handle(&mut self, handle: Option<&NativeHandle>) -> Result<()>;
+
+    /// Read a top-level `handle` argument.
+    fn read_native_handle(&mut self) -> Result<Option<NativeHandle>>;
+}
+
+impl HwParcel for Parcel {
+    fn write_hidl_token(&mut self, descriptor: &str) -> Result<()> {
+        self.write_cstring(descriptor)
+    }
+
+    fn enforce_hidl_token(&mut self, descriptor: &str) -> Result<()> {
+        let token = self.read_cstring()?;
+        if token != descriptor {
+            log::error!("HIDL: expected interface token {descriptor:?}, got {token:?}");
+            return Err(StatusCode::BadType);
+        }
+        Ok(())
+    }
+
+    fn write_hidl<T: HidlType + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.write_top_level(self)
+    }
+
+    fn read_hidl<T: HidlRead>(&mut self) -> Result<T> {
+        T::read_top_level(self)
+    }
+
+    fn write_hidl_status(&mut self, status: &Status) -> Result<()> {
+        let exception = status.exception_code();
+        if exception == ExceptionCode::TransactionFailed {
+            return Err(status.transaction_error());
+        }
+        self.write(&(exception as i32))?;
+        if exception != ExceptionCode::None {
+            self.write(status.message().unwrap_or(""))?;
+        }
+        Ok(())
+    }
+
+    fn read_hidl_status(&mut self) -> Result<Status> {
+        let exception: ExceptionCode = self.read()?;
+        if exception == ExceptionCode::None {
+            return Ok(Status::from(StatusCode::Ok));
+        }
+        let message: String = self.read()?;
+        Ok(Status::from((exception, message.as_str())))
+    }
+
+    fn write_hidl_binder(&mut self, binder: Option<&SIBinder>) -> Result<()> {
+        match binder {
+            Some(binder) => {
+                check_binder_context(binder, self)?;
+                self.write::<flat_binder_object>(&binder.into())?;
+                binder.set_parceled();
+                Ok(())
+            }
+            None => self.write(&flat_binder_object::default()),
+        }
+    }
+
+    fn read_hidl_binder(&mut self) -> Result<Option<SIBinder>> {
+        let flat: flat_binder_object = self.read()?;
+        binder_from_flat(&flat, Stability::Local.into(), self.context())
+    }
+
+    fn write_native_handle(&mut self, handle: Option<&NativeHandle>) -> Result<()> {
+        write_native_handle(self, handle, None)
+    }
+
+    fn read_native_handle(&mut self) -> Result<Option<NativeHandle>> {
+        read_native_handle(self, None)
+    }
+}
+
+/// An `IBase` request: just the `IBase` token.
+pub(crate) fn base_request() -> Result<Parcel> {
+    let mut data = Parcel::new();
+    data.write_hidl_token(IBASE_DESCRIPTOR)?;
+    Ok(data)
+}
+
+fn read_reply_status(reply: Option<Parcel>) -> Result<Parcel> {
+    let mut reply = reply.ok_or(StatusCode::UnexpectedNull)?;
+    let status = reply.read_hidl_status()?;
+    if !status.is_ok() {
+        return Err(status.into());
+    }
+    Ok(reply)
+}
+
+/// Parse the reply of a call with no return values (e.g. `IBase::ping`).
+pub(crate) fn read_empty_reply(reply: Option<Parcel>) -> Result<()> {
+    read_reply_status(reply).map(|_| ())
+}
+
+/// Parse the reply of `IBase::interfaceDescriptor`.
+pub(crate) fn read_descriptor_reply(reply: Option<Parcel>) -> Result<String> {
+    read_reply_status(reply)?.read_hidl()
+}
+
+fn call_base(binder: &SIBinder, code: TransactionCode) -> Result<Parcel> {
+    let remote = binder.as_remote().ok_or(StatusCode::BadType)?;
+    let mut data = remote.prepare_transact(false)?;
+    data.write_hidl_token(IBASE_DESCRIPTOR)?;
+    read_reply_status(remote.submit_transact(code, &data, 0)?)
+}
+
+/// `IBase::interfaceChain` on a remote binder: its descriptors, most
+/// derived first, ending with [`IBASE_DESCRIPTOR`].
+pub fn interface_chain(binder: &SIBinder) -> Result<Vec<String>> {
+    call_base(binder, INTERFACE_CHAIN_TRANSACTION)?.read_hidl()
+}
+
+/// `IBase::interfaceDescriptor` on a remote binder.
+pub fn interface_descriptor(binder: &SIBinder) -> Result<String> {
+    read_descriptor_reply(Some(call_base(binder, INTERFACE_DESCRIPTOR_TRANSACTION)?))
+}
+
+/// `IBase::ping` on a remote binder.
+pub fn ping(binder: &SIBinder) -> Result<()> {
+    call_base(binder, PING_TRANSACTION).map(|_| ())
+}
+
+/// `IBase::getDebugInfo` on a remote binder.
+pub fn debug_info(binder: &SIBinder) -> Result<manager::DebugInfo> {
+    call_base(binder, GET_DEBUG_INFO_TRANSACTION)?.read_hidl()
+}
+
+/// Serve the `IBase` reserved transactions for a native HIDL binder.
+///
+/// Generated stubs call this for every code they do not declare. `chain`
+/// is the interface chain of the service, most derived first and ending
+/// with [`IBASE_DESCRIPTOR`]; `dump` backs `IBase::debug`, writing into the
+/// first fd of the handle it is given. Returns
+/// [`StatusCode::UnknownTransaction`] for any other code.
+pub fn on_base_transact(
+    chain: &[&str],
+    code: TransactionCode,
+    reader: &mut Parcel,
+    reply: &mut Parcel,
+    dump: impl FnOnce(&mut dyn std::io::Write, &[String]) -> Result<()>,
+) -> Result<()> {
+    let ok = Status::from(StatusCode::Ok);
+    match code {
+        INTERFACE_CHAIN_TRANSACTION => {
+            reader.enforce_hidl_token(IBASE_DESCRIPTOR)?;
+            let chain: Vec<String> = chain.iter().map(|d| d.to_string()).collect();
+            reply.write_hidl_status(&ok)?;
+            reply.write_hidl(&chain)
+        }
+        INTERFACE_DESCRIPTOR_TRANSACTION => {
+            reader.enforce_hidl_token(IBASE_DESCRIPTOR)?;
+            reply.write_hidl_status(&ok)?;
+            reply.write_hidl(chain.first().copied().unwrap_or(IBASE_DESCRIPTOR))
+        }
+        GET_HASH_CHAIN_TRANSACTION => {
+            reader.enforce_hidl_token(IBASE_DESCRIPTOR)?;
+            // No released-interface hashes are known; `hidl-gen` emits an
+            // all-zero hash for interfaces outside `current.txt` too.
+            let hashes = vec![[0u8; 32]; chain.len()];
+            reply.write_hidl_status(&ok)?;
+            reply.write_hidl(&hashes)
+        }
+        PING_TRANSACTION => {
+            reader.enforce_hidl_token(IBASE_DESCRIPTOR)?;
+            reply.write_hidl_status(&ok)
+        }
+        GET_DEBUG_INFO_TRANSACTION => {
+            reader.enforce_hidl_token(IBASE_DESCRIPTOR)?;
+            let info = manager::DebugInfo {
+                pid: rustix::process::getpid().as_raw_nonzero().get(),
+                ptr: 0,
+                arch: if cfg!(target_pointer_width = "64") {
+                    manager::Architecture::IS_64BIT
+                } else {
+                    manager::Architecture::IS_32BIT
+                },
+            };
+            reply.write_hidl_status(&ok)?;
+            reply.write_hidl(&info)
+        }
+        DEBUG_TRANSACTION => {
+            reader.enforce_hidl_token(IBASE_DESCRIPTOR)?;
+            let handle = reader.read_native_handle()?;
+            let args: Vec<String> = reader.read_hidl()?;
+            if let Some(fd) = handle.and_then(|h| h.fds.into_iter().next()) {
+                let mut file = std::fs::File::from(fd);
+                if let Err(err) = dump(&mut file, &args) {
+                    log::warn!("HIDL: debug() dump failed: {err}");
+                }
+            }
+            reply.write_hidl_status(&ok)
+        }
+        SET_HAL_INSTRUMENTATION_TRANSACTION | NOTIFY_SYSPROPS_CHANGED_TRANSACTION => {
+            // Oneway; rsbinder has no HAL instrumentation or cached
+            // sysprops to refresh.
+            reader.enforce_hidl_token(IBASE_DESCRIPTOR)
+        }
+        _ => Err(StatusCode::UnknownTransaction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `hidl_string` is a 16-byte header buffer plus a NUL-terminated
+    /// child buffer; the header's size field holds the length without the
+    /// terminator.
+    #[test]
+    fn string_layout_and_round_trip() {
+        let mut parcel = Parcel::new();
+        parcel.write_hidl("hello").unwrap();
+
+        parcel.set_data_position(0);
+        let (parent, header) = parcel.read_buffer().unwrap();
+        assert_eq!(header.len(), 16);
+        assert_eq!(&header[8..12], &5u32.to_ne_bytes());
+        let (_, bytes) = parcel.read_embedded_buffer(parent, 0).unwrap();
+        assert_eq!(bytes, b"hello\0");
+
+        parcel.set_data_position(0);
+        assert_eq!(parcel.read_hidl::<String>().unwrap(), "hello");
+    }
+
+    #[test]
+    fn nested_values_round_trip() {
+        let strings = vec!["a".to_owned(), String::new(), "ccc".to_owned()];
+        let nested: Vec<Vec<u16>> = vec![vec![1, 2], vec![], vec![3]];
+        let array = [[1i64, -1], [i64::MAX, i64::MIN]];
+
+        let mut parcel = Parcel::new();
+        parcel.write_hidl(&7u8).unwrap();
+        parcel.write_hidl(&strings).unwrap();
+        parcel.write_hidl(&nested).unwrap();
+        parcel.write_hidl(&array).unwrap();
+        parcel.write_hidl(&true).unwrap();
+
+        parcel.set_data_position(0);
+        assert_eq!(parcel.read_hidl::<u8>().unwrap(), 7);
+        assert_eq!(parcel.read_hidl::<Vec<String>>().unwrap(), strings);
+        assert_eq!(parcel.read_hidl::<Vec<Vec<u16>>>().unwrap(), nested);
+        assert_eq!(parcel.read_hidl::<[[i64; 2]; 2]>().unwrap(), array);
+        assert!(parcel.read_hidl::<bool>().unwrap());
+    }
+
+    #[test]
+    fn native_handle_round_trip() {
+        use std::os::unix::fs::MetadataExt;
+
+        let null = std::fs::File::open("/dev/null").unwrap();
+        let handle = NativeHandle::new(vec![OwnedFd::from(null.try_clone().unwrap())], vec![3, 4]);
+
+        let mut parcel = Parcel::new();
+        parcel.write_native_handle(Some(&handle)).unwrap();
+        parcel.write_native_handle(None).unwrap();
+
+        parcel.set_data_position(0);
+        let read = parcel.read_native_handle().unwrap().unwrap();
+        assert_eq!(read.ints, vec![3, 4]);
+        assert_eq!(read.fds.len(), 1);
+        let got = std::fs::File::from(read.fds.into_iter().next().unwrap())
+            .metadata()
+            .unwrap();
+        let want = null.metadata().unwrap();
+        assert_eq!((got.dev(), got.ino()), (want.dev(), want.ino()));
+        assert!(parcel.read_native_handle().unwrap().is_none());
+    }
+
+    #[test]
+    fn status_round_trip() {
+        let mut parcel = Parcel::new();
+        parcel
+            .write_hidl_status(&Status::from(StatusCode::Ok))
+            .unwrap();
+        parcel
+            .write_hidl_status(&Status::new_service_specific_error(-5, Some("nope".into())))
+            .unwrap();
+
+        parcel.set_data_position(0);
+        assert!(parcel.read_hidl_status().unwrap().is_ok());
+        let status = parcel.read_hidl_status().unwrap();
+        assert!(!status.is_ok());
+        assert_eq!(status.message(), Some("nope"));
+    }
+
+    #[test]
+    fn token_is_checked() {
+        let mut parcel = Parcel::new();
+        parcel.write_hidl_token(IBASE_DESCRIPTOR).unwrap();
+        parcel.set_data_position(0);
+        parcel.enforce_hidl_token(IBASE_DESCRIPTOR).unwrap();
+
+        parcel.set_data_position(0);
+        assert_eq!(
+            parcel.enforce_hidl_token("android.hidl.manager@1.0::IServiceManager"),
+            Err(StatusCode::BadType)
+        );
+    }
+
+    #[test]
+    fn base_transactions() {
+        let chain = [
+            "vendor.foo@1.1::IFoo",
+            "vendor.foo@1.0::IFoo",
+            IBASE_DESCRIPTOR,
+        ];
+        let call = |code| {
+            let mut data = base_request().unwrap();
+            data.set_data_position(0);
+            let mut reply = Parcel::new();
+            on_base_transact(&chain, code, &mut data, &mut reply, |_, _| Ok(())).map(|_| {
+                reply.set_data_position(0);
+                reply
+            })
+        };
+
+        let reply = call(INTERFACE_DESCRIPTOR_TRANSACTION).unwrap();
+        assert_eq!(read_descriptor_reply(Some(reply)).unwrap(), chain[0]);
+
+        let mut reply = call(GET_HASH_CHAIN_TRANSACTION).unwrap();
+        assert!(reply.read_hidl_status().unwrap().is_ok());
+        assert_eq!(reply.read_hidl::<Vec<[u8; 32]>>().unwrap().len(), 3);
+
+        read_empty_reply(Some(call(PING_TRANSACTION).unwrap())).unwrap();
+        assert_eq!(call(0x0f000000).err(), Some(StatusCode::UnknownTransaction));
+    }
+}
@@ -77,7 +77,7 @@
 //! }
 //! ```
 
-use std::sync::{Arc, OnceLock};
+use std::sync::{Arc, RwLock};
 
 /// The common body of every per-version `servicemanager_N` module
 /// (Android 11 through 14). Each call expands to the same
@@ -375,24 +375,123 @@ pub enum ServiceManager {
     Android16(android_16::BpServiceManager),
 }
 
+/// Called after the service manager of a binder context came back from a
+/// restart and this process's registrations were re-issued to it.
+/// See [`set_restart_callback`].
+pub type RestartCallback = Arc<dyn Fn() + Send + Sync>;
+
+/// How long after its death a restarted service manager is first looked
+/// for. The wait doubles after each miss, up to
+/// [`RESTART_POLL_MAX_INTERVAL`].
+const RESTART_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// The longest wait between two looks for a restarted service manager.
+const RESTART_POLL_MAX_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// The service manager of one binder context, and what this process
+/// registered through it, to be re-issued if it restarts.
+struct Connection {
+    /// Address of the context's `ProcessState`.
+    key: usize,
+    /// The context to enter when restoring; `None` for the singleton.
+    context: Option<&'static ProcessState>,
+    /// `None` from the service manager's death until it is acquired again.
+    sm: Option<Arc<ServiceManager>>,
+    /// Linked to the binder of `sm`.
+    death: Option<Arc<ManagerDeath>>,
+    /// Whether a thread is waiting for the service manager to come back.
+    restoring: bool,
+    services: Vec<(String, SIBinder)>,
+    notifications: Vec<(String, Strong<dyn IServiceCallback>)>,
+    restart_callback: Option<RestartCallback>,
+}
+
+impl Connection {
+    /// Whether a restart of the service manager has anything to restore.
+    fn has_restore_work(&self) -> bool {
+        !self.services.is_empty()
+            || !self.notifications.is_empty()
+            || self.restart_callback.is_some()
+    }
+}
+
+/// One per binder context used with this module.
+static CONNECTIONS: RwLock<Vec<Connection>> = RwLock::new(Vec::new());
+
+fn connection_mut(connections: &mut Vec<Connection>, key: usize) -> &mut Connection {
+    match connections.iter().position(|c| c.key == key) {
+        Some(index) => &mut connections[index],
+        None => {
+            connections.push(Connection {
+                key,
+                context: ProcessState::current(),
+                sm: None,
+                death: None,
+                restoring: false,
+                services: Vec::new(),
+                notifications: Vec::new(),
+                restart_callback: None,
+            });
+            connections.last_mut().expect("just pushed")
+        }
+    }
+}
+
+fn context_key(process: &ProcessState) -> usize {
+    process as *const ProcessState as usize
+}
+
 /// Returns the global ServiceManager instance appropriate for the current Android version.
 ///
 /// The singleton is created on first call and reused afterwards. The correct
 /// version-specific implementation is selected from the detected Android SDK
-/// version.
+/// version. Inside [`crate::BinderContext::enter`] this is the service
+/// manager of that context, also kept for reuse and returned by
+/// [`crate::BinderContext::service_manager`]; the free functions of this
+/// module go through it.
 ///
 /// Returns an error instead of panicking when the context object cannot be
 /// obtained, the proxy cannot be created, or the SDK version is unsupported.
 /// A failed initialization is not cached, so a later call may retry.
+///
+/// # Service manager restarts
+///
+/// The instance watches the service manager for death. Once it dies, the
+/// next call acquires handle 0 afresh, and a background thread waits for
+/// the new service manager to answer, then re-issues the
+/// [`add_service`](ServiceManager::add_service) and
+/// [`register_for_notifications`](ServiceManager::register_for_notifications)
+/// calls this process made through the instance (and not since undone with
+/// `try_unregister_service` or `unregister_for_notifications`), and runs
+/// the [`set_restart_callback`] callback. Other state kept by the service
+/// manager, e.g. client callbacks, is for that callback to restore. An
+/// `Arc` kept from before the death stays dead; call this again instead.
+/// Deaths are noticed by a binder thread, so start the thread pool.
+///
+/// The thread looks again after a wait that grows from 250 ms to 30 s,
+/// for as long as it takes. It is not started, and stops waiting, when
+/// there is nothing to restore: no registrations and no restart callback.
+///
+/// To re-issue them, the instance keeps the registered binders and
+/// notification callbacks alive until they are unregistered, as the
+/// service manager does while it is up; a service that is dropped
+/// without [`try_unregister_service`](ServiceManager::try_unregister_service)
+/// lives on for the rest of the process.
 pub fn default() -> Result<Arc<ServiceManager>> {
-    static GLOBAL_SM: OnceLock<Arc<ServiceManager>> = OnceLock::new();
-
-    if let Some(sm) = GLOBAL_SM.get() {
-        return Ok(sm.clone());
+    let process = ProcessState::as_self();
+    let key = context_key(process);
+    if let Some(sm) = CONNECTIONS
+        .read()
+        .expect("Service manager cache lock poisoned")
+        .iter()
+        .find(|c| c.key == key)
+        .and_then(|c| c.sm.clone())
+    {
+        return Ok(sm);
     }
 
-    let process = ProcessState::as_self();
     let context = process.context_object()?;
+    let binder = context.clone();
     #[cfg(target_os = "android")]
     let sdk_version = crate::get_android_sdk_version();
 
@@ -443,10 +542,145 @@ pub fn default() -> Result<Arc<ServiceManager>> {
         android_16::BpServiceManager::from_binder(context).ok_or(StatusCode::BadType)?,
     );
 
+    let death = Arc::new(ManagerDeath);
+    if let Err(err) = binder.link_to_death_arc(&death) {
+        log::warn!("Cannot watch the service manager for restarts: {err:?}");
+    }
+
     // Cache only on success; a failed init returned above is not stored,
-    // so a later call may retry. If two threads race here, get_or_init
-    // keeps the first stored instance and the extra one is dropped.
-    Ok(GLOBAL_SM.get_or_init(|| Arc::new(service_manager)).clone())
+    // so a later call may retry. If two threads race here, the first
+    // stored instance is kept and the extra one is dropped.
+    let mut connections = CONNECTIONS
+        .write()
+        .expect("Service manager cache lock poisoned");
+    let connection = connection_mut(&mut connections, key);
+    if let Some(sm) = &connection.sm {
+        return Ok(sm.clone());
+    }
+    // Died before it could be cached, where `ManagerDeath` finds nothing
+    // to evict.
+    if binder.as_proxy().is_some_and(|proxy| proxy.is_dead()) {
+        return Err(StatusCode::DeadObject);
+    }
+    let sm = Arc::new(service_manager);
+    connection.sm = Some(sm.clone());
+    connection.death = Some(death);
+    Ok(sm)
+}
+
+/// Install (or with `None`, remove) the callback run when the service
+/// manager of the calling thread's binder context has restarted, after this
+/// process's services and notification callbacks were registered with it
+/// again. It runs on a thread of its own; see [`default`].
+pub fn set_restart_callback(callback: Option<RestartCallback>) {
+    let key = context_key(ProcessState::as_self());
+    connection_mut(
+        &mut CONNECTIONS
+            .write()
+            .expect("Service manager cache lock poisoned"),
+        key,
+    )
+    .restart_callback = callback;
+}
+
+/// Linked to the service manager cached by [`default`].
+struct ManagerDeath;
+
+impl DeathRecipient for ManagerDeath {
+    fn binder_died(&self, _who: &WIBinder) {
+        let mut connections = CONNECTIONS
+            .write()
+            .expect("Service manager cache lock poisoned");
+        // A recipient of a service manager already replaced has nothing to do.
+        let Some(connection) = connections.iter_mut().find(|c| {
+            c.death
+                .as_ref()
+                .is_some_and(|death| std::ptr::eq(Arc::as_ptr(death), self))
+        }) else {
+            return;
+        };
+        log::warn!("The service manager died; waiting for it to restart");
+        let dead = (connection.sm.take(), connection.death.take());
+        let start = !connection.restoring && connection.has_restore_work();
+        connection.restoring |= start;
+        let (key, context) = (connection.key, connection.context);
+        drop(connections);
+        drop(dead);
+
+        if start {
+            let spawned = std::thread::Builder::new()
+                .name("rsbinder:hub".into())
+                .spawn(move || restore(key, context));
+            if let Err(err) = spawned {
+                log::error!("Failed to spawn the service manager restore thread: {err}");
+                if let Some(connection) = CONNECTIONS
+                    .write()
+                    .expect("Service manager cache lock poisoned")
+                    .iter_mut()
+                    .find(|c| c.key == key)
+                {
+                    connection.restoring = false;
+                }
+            }
+        }
+    }
+}
+
+/// Wait for the service manager of `context` to come back, then re-issue
+/// this process's registrations and run the restart callback.
+fn restore(key: usize, context: Option<&'static ProcessState>) {
+    let _context = ProcessState::enter(context);
+    let mut interval = RESTART_POLL_INTERVAL;
+    let sm = loop {
+        std::thread::sleep(interval);
+        match default() {
+            Ok(sm) => break sm,
+            Err(err) => log::trace!("Service manager not back yet: {err:?}"),
+        }
+        {
+            let mut connections = CONNECTIONS
+                .write()
+                .expect("Service manager cache lock poisoned");
+            let connection = connection_mut(&mut connections, key);
+            if !connection.has_restore_work() {
+                log::debug!("Nothing left to restore; no longer waiting for the service manager");
+                connection.restoring = false;
+                return;
+            }
+        }
+        interval = (interval * 2).min(RESTART_POLL_MAX_INTERVAL);
+    };
+
+    let (services, notifications, callback) = {
+        let mut connections = CONNECTIONS
+            .write()
+            .expect("Service manager cache lock poisoned");
+        let connection = connection_mut(&mut connections, key);
+        connection.restoring = false;
+        (
+            connection.services.clone(),
+            connection.notifications.clone(),
+            connection.restart_callback.clone(),
+        )
+    };
+    log::info!(
+        "The service manager restarted; registering {} services and {} notification callbacks again",
+        services.len(),
+        notifications.len()
+    );
+    for (name, binder) in services {
+        if let Err(err) = sm.add_service(&name, binder) {
+            log::error!("Failed to register {name} again: {err:?}");
+        }
+    }
+    for (name, callback) in notifications {
+        if let Err(err) = sm.register_for_notifications(&name, &callback) {
+            log::error!("Failed to register for notifications of {name} again: {err:?}");
+        }
+    }
+    if let Some(callback) = callback {
+        callback();
+    }
 }
 
 /// Forwards an existing `IServiceCallback` to a per-version
@@ -792,19 +1026,38 @@ impl ServiceManager {
         binder: impl Into<SIBinder>,
     ) -> std::result::Result<(), Status> {
         let binder = binder.into();
-        match self {
+        let result = match self {
             #[cfg(all(target_os = "android", feature = "android_10"))]
-            ServiceManager::Android10(sm) => android_10::add_service(sm, identifier, binder),
+            ServiceManager::Android10(sm) => {
+                android_10::add_service(sm, identifier, binder.clone())
+            }
             #[cfg(all(target_os = "android", feature = "android_11"))]
-            ServiceManager::Android11(sm) => android_11::add_service(sm, identifier, binder),
+            ServiceManager::Android11(sm) => {
+                android_11::add_service(sm, identifier, binder.clone())
+            }
             #[cfg(all(target_os = "android", feature = "android_12"))]
-            ServiceManager::Android12(sm) => android_12::add_service(sm, identifier, binder),
+            ServiceManager::Android12(sm) => {
+                android_12::add_service(sm, identifier, binder.clone())
+            }
             #[cfg(all(target_os = "android", feature = "android_13"))]
-            ServiceManager::Android13(sm) => android_13::add_service(sm, identifier, binder),
+            ServiceManager::Android13(sm) => {
+                android_13::add_service(sm, identifier, binder.clone())
+            }
             #[cfg(all(target_os = "android", feature = "android_14"))]
-            ServiceManager::Android14(sm) => android_14::add_service(sm, identifier, binder),
-            ServiceManager::Android16(sm) => android_16::add_service(sm, identifier, binder),
+            ServiceManager::Android14(sm) => {
+                android_14::add_service(sm, identifier, binder.clone())
+            }
+            ServiceManager::Android16(sm) => {
+                android_16::add_service(sm, identifier, binder.clone())
+            }
+        };
+        if result.is_ok() {
+            self.record(|c| {
+                c.services.retain(|(name, _)| name != identifier);
+                c.services.push((identifier.to_owned(), binder));
+            });
         }
+        result
     }
 
     /// Retrieves debug information about all currently registered services.
@@ -846,7 +1099,7 @@ impl ServiceManager {
         name: &str,
         callback: &crate::Strong<dyn IServiceCallback>,
     ) -> Result<()> {
-        match self {
+        let result = match self {
             #[cfg(all(target_os = "android", feature = "android_10"))]
             ServiceManager::Android10(_) => {
                 log::error!("register_for_notifications: not supported on Android 10");
@@ -879,7 +1132,20 @@ impl ServiceManager {
             ServiceManager::Android16(sm) => {
                 android_16::register_for_notifications(sm, name, callback)
             }
+        };
+        if result.is_ok() {
+            self.record(|c| {
+                let callback_binder = callback.as_binder();
+                if !c
+                    .notifications
+                    .iter()
+                    .any(|(n, cb)| n == name && cb.as_binder() == callback_binder)
+                {
+                    c.notifications.push((name.to_owned(), callback.clone()));
+                }
+            });
         }
+        result
     }
 
     /// Unregisters from notifications for a service.
@@ -890,7 +1156,7 @@ impl ServiceManager {
         name: &str,
         callback: &crate::Strong<dyn IServiceCallback>,
     ) -> Result<()> {
-        match self {
+        let result = match self {
             #[cfg(all(target_os = "android", feature = "android_10"))]
             ServiceManager::Android10(_) => {
                 log::error!("unregister_for_notifications: not supported on Android 10");
@@ -923,7 +1189,15 @@ impl ServiceManager {
             ServiceManager::Android16(sm) => {
                 android_16::unregister_for_notifications(sm, name, callback)
             }
-        }
+        };
+        // Forgotten even on failure: a dead service manager has no
+        // registration left to undo.
+        self.record(|c| {
+            let callback_binder = callback.as_binder();
+            c.notifications
+                .retain(|(n, cb)| !(n == name && cb.as_binder() == callback_binder));
+        });
+        result
     }
 
     /// Registers a callback that fires when the set of clients holding a
@@ -987,7 +1261,7 @@ impl ServiceManager {
     ///
     /// Note: not supported on Android 10 — returns an error on that version.
     pub fn try_unregister_service(&self, name: &str, service: &SIBinder) -> Result<()> {
-        match self {
+        let result = match self {
             #[cfg(all(target_os = "android", feature = "android_10"))]
             ServiceManager::Android10(_) => {
                 log::error!("try_unregister_service: not supported on Android 10");
@@ -1002,7 +1276,14 @@ impl ServiceManager {
             #[cfg(all(target_os = "android", feature = "android_14"))]
             ServiceManager::Android14(sm) => android_14::try_unregister_service(sm, name, service),
             ServiceManager::Android16(sm) => android_16::try_unregister_service(sm, name, service),
+        };
+        if result.is_ok() {
+            self.record(|c| {
+                c.services
+                    .retain(|(n, binder)| !(n == name && binder == service))
+            });
         }
+        result
     }
 
     /// Error-preserving, non-blocking lookup: `Ok(Some)` found, `Ok(None)` not
@@ -1171,6 +1452,20 @@ impl ServiceManager {
         }
     }
 
+    /// Apply `f` to the registrations kept for [`default`] if this is the
+    /// instance it returns; other instances keep none.
+    fn record(&self, f: impl FnOnce(&mut Connection)) {
+        let mut connections = CONNECTIONS
+            .write()
+            .expect("Service manager cache lock poisoned");
+        if let Some(connection) = connections
+            .iter_mut()
+            .find(|c| c.sm.as_ref().is_some_and(|sm| std::ptr::eq(&**sm, self)))
+        {
+            f(connection);
+        }
+    }
+
     /// Unbounded fallback poll used by
     /// [`wait_for_service`](Self::wait_for_service) when the service manager
     /// has no registration notifications (Android 10) or the notification
@@ -36,6 +36,20 @@
 //!   Linux and Android targets only).
 //! - `rpc-tls` — TLS backend over rustls (implies `rpc`). rsbinder never
 //!   invents crypto; the caller supplies the `rustls` configuration.
+//! - `fake-driver` — an in-process emulation of the binder kernel driver
+//!   (`fake_driver` module) so kernel-binder code can be tested without
+//!   root, the binder module or a binderfs mount.
+//! - `serde` — `Parcel::write_serde` / `read_serde` for any
+//!   `serde::Serialize` / `Deserialize` type (`parcel_serde` module).
+//! - `macros` — `#[derive(Parcelable)]` and `#[binder_interface]` from
+//!   `rsbinder-macros`, for declaring parcelables and interfaces in Rust
+//!   without `.aidl` files.
+//! - `tracing` — a `tracing` span for every kernel and RPC transaction,
+//!   and optional cross-process trace-context propagation (`trace`
+//!   module).
+//! - `metrics` — per-interface, per-method call counts, latency
+//!   histograms, error counts and in-flight gauges, with a Prometheus
+//!   text renderer (`metrics` module).
 //! - `android_10` … `android_16`, plus the `android_*_plus` ranges (e.g.
 //!   `android_11_plus`) — select which Android service-manager protocol
 //!   versions to support. Android 10 uses the legacy C service-manager
@@ -163,12 +177,22 @@ mod binder;
 #[cfg(feature = "async")]
 pub mod binder_async;
 mod binder_object;
-/// BinderFS filesystem utilities
+mod context;
+// In-process binder driver for hermetic tests; plain comment for the same
+// reason as `service` below.
+#[cfg(feature = "fake-driver")]
+pub mod fake_driver;
+// BinderFS filesystem utilities and debug-log readers; plain comment for
+// the same reason as `service` below.
 pub mod binderfs;
+mod deadline;
 /// Error types and result handling
 pub mod error;
 /// File descriptor wrapper for IPC
 pub mod file_descriptor;
+// Fast Message Queue channels (AOSP `libfmq`); plain comment for the
+// same reason as `service` below.
+pub mod fmq;
 /// `LazyServiceRegistrar` skeleton (state machine +
 /// `IClientCallback::onClients` dispatch). AOSP
 /// `frameworks/native/libs/binder/LazyServiceRegistrar.cpp`. Hub
@@ -177,10 +201,18 @@ pub mod file_descriptor;
 /// module docs.
 pub mod lazy_service;
 mod macros;
+// Per-interface call metrics; plain comment for the same reason as
+// `service` below.
+#[cfg(feature = "metrics")]
+pub mod metrics;
 /// Native service implementation helpers
 pub mod native;
 /// Data serialization for IPC
 pub mod parcel;
+// `serde` data format over `Parcel`; plain comment for the same reason
+// as `service` below.
+#[cfg(feature = "serde")]
+pub mod parcel_serde;
 /// Parcelable trait for serializable types
 pub mod parcelable;
 /// Holder for parcelable objects
@@ -193,21 +225,24 @@ pub mod proxy;
 /// (`getBinderProxyCount` / `setBinderProxyCountWatermarks` /
 /// `setBinderProxyCountEventCallback` / `enableCountByUid`).
 pub mod proxy_count;
+// `START/STOP_RECORDING_TRANSACTION` recording and replay; plain comment
+// for the same reason as `service` below.
+pub mod recorded_transaction;
 mod ref_counter;
-/// Shared-memory IPC trait skeleton
-/// (`IMemoryHeap` / `IMemory` / `MemoryHeapBase`). AOSP
-/// `frameworks/native/libs/binder/include/binder/IMemory.h`. A future
-/// `memfd_create(2)`-backed `MemoryHeapBase` impl on Linux/Android and
-/// a `MemoryDealer` chunk allocator are not yet implemented. macOS
-/// host build compiles the trait surface but `MemoryHeapBase::new`
-/// permanently returns `Err(StatusCode::InvalidOperation)` — see the
-/// module docs.
+// Shared memory over binder (`IMemoryHeap` / `IMemory`); plain comment
+// for the same reason as `service` below.
 pub mod shared_memory;
+// `SHELL_COMMAND_TRANSACTION` (`cmd <service> ...`) support; plain comment
+// for the same reason as `service` below.
+pub mod shell_command;
 /// Status and exception handling
 pub mod status;
 mod sys;
 /// Thread-local binder state
 pub mod thread_state;
+// Transaction spans and trace-context propagation; plain comment for the
+// same reason as `service` below.
+pub mod trace;
 
 /// RPC transport (binder-over-socket) — a separate stack from the
 /// kernel binder path. Present only with the `rpc` feature.
@@ -217,6 +252,10 @@ pub mod rpc;
 /// Service hub and manager implementations
 pub mod hub;
 
+// HIDL (hwbinder) support — see the module's own docs (a plain comment
+// for the same reason as `service` below).
+pub mod hidl;
+
 /// Client stub for Android's `PermissionManagerService`
 /// (`android.os.IPermissionController`). See module doc for the
 /// AOSP-faithful surface and fail-closed `check_permission` helper.
@@ -238,20 +277,23 @@ pub mod service;
 
 // From `binder` — core binder identity, transaction codes, traits.
 pub use binder::{
-    DeathRecipient, FromIBinder, IBinder, Interface, Remotable, RemoteProxy, SIBinder, Stability,
-    Strong, ToAsyncInterface, ToSyncInterface, Transactable, TransactionCode, TransactionFlags,
-    WIBinder, Weak, DEBUG_PID_TRANSACTION, DUMP_TRANSACTION, EXTENSION_TRANSACTION,
-    FIRST_CALL_TRANSACTION, FLAG_CLEAR_BUF, FLAG_COLLECT_NOTED_APP_OPS, FLAG_ONEWAY,
-    FLAG_PRIVATE_LOCAL, FLAG_PRIVATE_VENDOR, FLAG_UPDATE_TXN, INTERFACE_HEADER,
-    INTERFACE_TRANSACTION, LAST_CALL_TRANSACTION, LIKE_TRANSACTION, PING_TRANSACTION,
-    SET_RPC_CLIENT_TRANSACTION, SHELL_COMMAND_TRANSACTION, START_RECORDING_TRANSACTION,
-    STOP_RECORDING_TRANSACTION, SYSPROPS_TRANSACTION, TWEET_TRANSACTION,
+    DeathRecipient, FromIBinder, FrozenState, FrozenStateChangeCallback, IBinder, Interface,
+    Remotable, RemoteProxy, SIBinder, Stability, Strong, ToAsyncInterface, ToSyncInterface,
+    Transactable, TransactionCode, TransactionFlags, WIBinder, Weak, DEBUG_PID_TRANSACTION,
+    DUMP_TRANSACTION, EXTENSION_TRANSACTION, FIRST_CALL_TRANSACTION, FLAG_CLEAR_BUF,
+    FLAG_COLLECT_NOTED_APP_OPS, FLAG_ONEWAY, FLAG_PRIVATE_LOCAL, FLAG_PRIVATE_VENDOR,
+    FLAG_UPDATE_TXN, INTERFACE_HEADER, INTERFACE_TRANSACTION, LAST_CALL_TRANSACTION,
+    LIKE_TRANSACTION, PING_TRANSACTION, SET_RPC_CLIENT_TRANSACTION, SHELL_COMMAND_TRANSACTION,
+    START_RECORDING_TRANSACTION, STOP_RECORDING_TRANSACTION, SYSPROPS_TRANSACTION,
+    TWEET_TRANSACTION,
 };
 // `declare_binder_interface!` expands to `$crate::__rpc_stamp_descriptor(...)`
 // in consumer crates, so this helper must stay reachable at the crate root.
 #[doc(hidden)]
 pub use binder::__rpc_stamp_descriptor;
 
+pub use context::{BinderContext, ContextGuard};
+
 #[cfg(feature = "async")]
 pub use binder_async::{BinderAsyncPool, BinderAsyncRuntime, BoxFuture};
 pub use error::{Result, StatusCode};
@@ -279,15 +321,23 @@ pub use parcelable::{
 };
 
 pub use parcelable_holder::ParcelableHolder;
-pub use process_state::ProcessState;
+
+pub use process_state::{
+    FrozenInfo, LargeTransaction, LargeTransactionCallback, OnewaySpamCallback, OnewaySpamSuspect,
+    ProcessState,
+};
+
+// Proc macros expanding to the same code the AIDL generator emits.
+#[cfg(feature = "macros")]
+pub use rsbinder_macros::{binder_interface, Parcelable};
 
 // From `proxy` — client-side handle types.
-pub use proxy::{Proxy, ProxyHandle};
+pub use proxy::{Proxy, ProxyHandle, WithTimeout};
 
 // Explicit (not glob) so a newly-added `pub` item in `rt` can't silently leak
 // to the crate root without semver review — the policy stated above.
 #[cfg(feature = "tokio")]
-pub use rt::{get_interface, Tokio, TokioRuntime};
+pub use rt::{get_interface, BinderReactor, Tokio, TokioReactor, TokioRuntime};
 pub use status::{BinderResult, ExceptionCode, Status};
 
 /// Default path to the binder control device
@@ -101,6 +101,10 @@ macro_rules! __declare_binder_interface {
                 fn on_dump(&self, _writer: &mut dyn std::io::Write, _args: &[String]) -> $crate::Result<()> {
                     self.0.as_sync().dump(_writer, _args)
                 }
+
+                fn on_shell_command(&self, _command: &mut $crate::shell_command::ShellCommand) -> $crate::Result<()> {
+                    self.0.as_sync().shell_command(_command)
+                }
             }
         )?
 
@@ -225,6 +229,10 @@ macro_rules! __declare_binder_interface {
             fn on_dump(&self, _writer: &mut dyn std::io::Write, _args: &[String]) -> $crate::Result<()> {
                 self.0.dump(_writer, _args)
             }
+
+            fn on_shell_command(&self, _command: &mut $crate::shell_command::ShellCommand) -> $crate::Result<()> {
+                self.0.shell_command(_command)
+            }
         }
     };
 }
@@ -668,6 +676,170 @@ macro_rules! declare_binder_enum {
     };
 }
 
+/// Declare a HIDL interface.
+///
+/// The HIDL counterpart of [`declare_binder_interface!`], used by the code
+/// `rsbinder_aidl::hidl` generates from `.hal` files. The proxy accepts any
+/// remote binder whose interface chain contains `$descriptor`, so a service
+/// implementing a derived interface (e.g. `@1.2::IFoo`) can be used through
+/// the base one, like `IFoo::castFrom` in libhidl.
+#[macro_export]
+macro_rules! declare_hidl_interface {
+    {
+        $interface:path[$descriptor:expr] {
+            native: $native:ident($on_transact:path),
+            proxy: $proxy:ident,
+        }
+    } => {
+        #[doc = concat!("A hwbinder `Proxy` that holds an [`", stringify!($interface), "`] remote interface.")]
+        pub struct $proxy {
+            binder: $crate::SIBinder,
+        }
+
+        impl $crate::Interface for $proxy {
+            fn as_binder(&self) -> $crate::SIBinder {
+                self.binder.clone()
+            }
+        }
+
+        impl $crate::Proxy for $proxy
+        where
+            $proxy: $interface,
+        {
+            fn descriptor() -> &'static str {
+                $descriptor
+            }
+
+            fn from_binder(binder: $crate::SIBinder) -> std::option::Option<Self> {
+                binder.as_remote()?;
+                // `descriptor()` is the most derived interface; only ask for
+                // the whole chain when that is not the one wanted.
+                if binder.descriptor() != $descriptor {
+                    let chain = $crate::hidl::interface_chain(&binder).ok()?;
+                    if !chain.iter().any(|descriptor| descriptor == $descriptor) {
+                        return None;
+                    }
+                }
+                Some(Self { binder })
+            }
+        }
+
+        #[doc = concat!("A hwbinder `Remotable` that holds an [`", stringify!($interface), "`] object.")]
+        pub struct $native(Box<dyn $interface + Send + Sync + 'static>);
+
+        impl $native {
+            /// Create a new HIDL service.
+            pub fn new_binder<T: $interface + Sync + Send + 'static>(inner: T) -> $crate::Strong<dyn $interface> {
+                let binder = $crate::native::Binder::new($native(Box::new(inner)));
+                $crate::Strong::new(Box::new(binder))
+            }
+        }
+
+        impl $crate::Remotable for $native {
+            fn descriptor() -> &'static str where Self: Sized {
+                $descriptor
+            }
+
+            fn on_transact(&self, code: $crate::TransactionCode, reader: &mut $crate::Parcel, reply: &mut $crate::Parcel) -> $crate::Result<()> {
+                $on_transact(&*self.0, code, reader, reply)
+            }
+
+            fn on_dump(&self, _writer: &mut dyn std::io::Write, _args: &[String]) -> $crate::Result<()> {
+                self.0.dump(_writer, _args)
+            }
+
+            fn on_shell_command(&self, _command: &mut $crate::shell_command::ShellCommand) -> $crate::Result<()> {
+                self.0.shell_command(_command)
+            }
+
+            fn is_hidl() -> bool where Self: Sized {
+                true
+            }
+        }
+
+        impl $crate::FromIBinder for dyn $interface {
+            fn try_from(binder: $crate::SIBinder) -> $crate::Result<$crate::Strong<dyn $interface>> {
+                match <$proxy as $crate::Proxy>::from_binder(binder.clone()) {
+                    Some(proxy) => Ok($crate::Strong::new(Box::new(proxy))),
+                    None => {
+                        match $crate::native::Binder::<$native>::try_from(binder) {
+                            Ok(native) => Ok($crate::Strong::new(Box::new(native))),
+                            Err(err) => Err(err),
+                        }
+                    }
+                }
+            }
+        }
+
+        impl std::fmt::Debug for dyn $interface + '_ {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.pad(stringify!($interface))
+            }
+        }
+    };
+}
+
+/// Declare a HIDL enumeration.
+///
+/// Like [`declare_binder_enum!`], but the value is marshalled with the HIDL
+/// layout of its storage type. This is mainly used internally by the HIDL
+/// compiler.
+#[macro_export]
+macro_rules! declare_hidl_enum {
+    {
+        $enum:ident : [$backing:ty; $size:expr] {
+            $( $name:ident = $value:expr, )*
+        }
+    } => {
+        #[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+        #[allow(missing_docs)]
+        pub struct $enum(pub $backing);
+        impl $enum {
+            $( #[allow(missing_docs)] pub const $name: Self = Self($value); )*
+
+            #[inline(always)]
+            #[allow(missing_docs)]
+            pub const fn enum_values() -> [Self; $size] {
+                [$(Self::$name),*]
+            }
+
+            #[inline(always)]
+            #[allow(missing_docs)]
+            pub const fn get(&self) -> $backing {
+                self.0
+            }
+        }
+
+        impl $crate::hidl::HidlType for $enum {
+            const SIZE: usize = <$backing as $crate::hidl::HidlType>::SIZE;
+            const ALIGN: usize = <$backing as $crate::hidl::HidlType>::ALIGN;
+
+            fn write_layout(&self, out: &mut [u8]) {
+                $crate::hidl::HidlType::write_layout(&self.0, out)
+            }
+
+            fn write_top_level(&self, parcel: &mut $crate::Parcel) -> $crate::Result<()> {
+                $crate::hidl::HidlType::write_top_level(&self.0, parcel)
+            }
+        }
+
+        impl $crate::hidl::HidlRead for $enum {
+            fn read_embedded(
+                parcel: &mut $crate::Parcel,
+                layout: &[u8],
+                parent: usize,
+                offset: usize,
+            ) -> $crate::Result<Self> {
+                <$backing as $crate::hidl::HidlRead>::read_embedded(parcel, layout, parent, offset).map(Self)
+            }
+
+            fn read_top_level(parcel: &mut $crate::Parcel) -> $crate::Result<Self> {
+                <$backing as $crate::hidl::HidlRead>::read_top_level(parcel).map(Self)
+            }
+        }
+    };
+}
+
 /// Include AIDL-generated Rust and (optionally) flatten an interface's items
 /// into the current module — the one-call form of the
 /// `include!(concat!(env!("OUT_DIR"), …))` + `pub use …::*` pair that every
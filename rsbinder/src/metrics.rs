@@ -0,0 +1,655 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Per-interface call metrics with a Prometheus text renderer.
+//!
+//! With the `metrics` feature, every transaction a client sends through a
+//! `ProxyHandle` or `RpcProxy`, and every transaction a local binder
+//! serves, is counted in a process-wide registry keyed by
+//! ([`Side`], interface descriptor, method):
+//!
+//! - completed calls and a latency histogram ([`LATENCY_BUCKETS`]);
+//! - transport errors by `StatusCode`, and AIDL exceptions by
+//!   `ExceptionCode` (read from the reply's `Status` header);
+//! - calls in flight;
+//! - request and reply parcel sizes, total and largest.
+//!
+//! Method names come from AIDL-generated and `#[binder_interface]` code
+//! (the same `trace::client_call` / `trace::server_method` hooks that name
+//! tracing spans). Calls through hand-written stubs are labelled with
+//! the decimal transaction code and get no exception counts, since their
+//! replies need not start with a `Status`. A server call is in flight
+//! from the moment its generated `on_transact` arm starts.
+//!
+//! [`snapshot`] copies the registry out; [`render_prometheus`] turns a
+//! snapshot into the text exposition format, e.g. from `on_dump`:
+//!
+//! ```rust,ignore
+//! fn on_dump(&self, writer: &mut dyn std::io::Write, _args: &[String]) -> rsbinder::Result<()> {
+//!     let text = rsbinder::metrics::render_prometheus(&rsbinder::metrics::snapshot());
+//!     writer.write_all(text.as_bytes()).map_err(|_| rsbinder::StatusCode::FailedTransaction)
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+use std::time::Duration;
+
+use crate::error::StatusCode;
+use crate::parcel::Parcel;
+use crate::status::{ExceptionCode, Status};
+
+/// Upper bounds of the latency histogram buckets, in seconds. Every
+/// histogram also has an implicit `+Inf` bucket.
+pub const LATENCY_BUCKETS: [f64; 14] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0,
+];
+
+/// [`LATENCY_BUCKETS`] in microseconds, for the hot path.
+const BUCKET_MICROS: [u64; 14] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 5_000_000,
+];
+
+/// Which end of a transaction the metrics describe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Side {
+    /// Calls this process made on a remote binder.
+    Client,
+    /// Calls this process served.
+    Server,
+}
+
+impl Side {
+    fn label(self) -> &'static str {
+        match self {
+            Side::Client => "client",
+            Side::Server => "server",
+        }
+    }
+}
+
+/// A latency histogram in a [`CallMetrics`] snapshot.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    /// Calls per bucket (not cumulative): `buckets[i]` counts calls no
+    /// slower than `LATENCY_BUCKETS[i]` and slower than the bucket
+    /// before; the last entry is the `+Inf` bucket.
+    pub buckets: Vec<u64>,
+    /// Total time of all calls.
+    pub sum: Duration,
+}
+
+/// Parcel sizes in a [`CallMetrics`] snapshot, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sizes {
+    /// Sum over all recorded parcels.
+    pub total: u64,
+    /// The largest recorded parcel.
+    pub max: u64,
+}
+
+/// Metrics of one method of one interface, as of [`snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallMetrics {
+    /// Client or server.
+    pub side: Side,
+    /// Interface descriptor.
+    pub descriptor: String,
+    /// Method name, or the decimal transaction code when unknown.
+    pub method: String,
+    /// Completed calls, successful or not.
+    pub calls: u64,
+    /// Calls started and not yet completed.
+    pub in_flight: i64,
+    /// Calls that failed in transport, by status.
+    pub errors: BTreeMap<StatusCode, u64>,
+    /// Calls whose reply carried an AIDL exception, by exception code.
+    pub exceptions: BTreeMap<ExceptionCode, u64>,
+    /// Latency of completed calls.
+    pub latency: Histogram,
+    /// Request data of completed calls.
+    pub request_bytes: Sizes,
+    /// Reply data of completed calls that got a reply.
+    pub reply_bytes: Sizes,
+}
+
+#[derive(Default)]
+pub(crate) struct Entry {
+    calls: AtomicU64,
+    in_flight: AtomicI64,
+    buckets: [AtomicU64; BUCKET_MICROS.len() + 1],
+    sum_micros: AtomicU64,
+    errors: Mutex<BTreeMap<StatusCode, u64>>,
+    exceptions: Mutex<BTreeMap<ExceptionCode, u64>>,
+    request_bytes: SizeCounters,
+    reply_bytes: SizeCounters,
+}
+
+#[derive(Default)]
+struct SizeCounters {
+    total: AtomicU64,
+    max: AtomicU64,
+}
+
+impl SizeCounters {
+    fn record(&self, size: usize) {
+        self.total.fetch_add(size as u64, Ordering::Relaxed);
+        self.max.fetch_max(size as u64, Ordering::Relaxed);
+    }
+
+    fn load(&self) -> Sizes {
+        Sizes {
+            total: self.total.load(Ordering::Relaxed),
+            max: self.max.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Entry {
+    fn record(
+        &self,
+        status: StatusCode,
+        exception: Option<ExceptionCode>,
+        elapsed: Duration,
+        request_size: usize,
+        reply_size: Option<usize>,
+    ) {
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = BUCKET_MICROS
+            .iter()
+            .position(|&bound| micros <= bound)
+            .unwrap_or(BUCKET_MICROS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        if status != StatusCode::Ok {
+            *lock(&self.errors).entry(status).or_default() += 1;
+        }
+        if let Some(exception) = exception.filter(|e| *e != ExceptionCode::None) {
+            *lock(&self.exceptions).entry(exception).or_default() += 1;
+        }
+        self.request_bytes.record(request_size);
+        if let Some(size) = reply_size {
+            self.reply_bytes.record(size);
+        }
+        self.calls.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// descriptor → method → entry, one map per [`Side`].
+type Methods = HashMap<String, HashMap<String, Arc<Entry>>>;
+
+static REGISTRY: LazyLock<[RwLock<Methods>; 2]> = LazyLock::new(Default::default);
+
+fn entry(side: Side, descriptor: &str, method: &str) -> Arc<Entry> {
+    let registry = &REGISTRY[side as usize];
+    if let Some(entry) = registry
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(descriptor)
+        .and_then(|methods| methods.get(method))
+    {
+        return entry.clone();
+    }
+    registry
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .entry(descriptor.to_owned())
+        .or_default()
+        .entry(method.to_owned())
+        .or_default()
+        .clone()
+}
+
+/// The exception in a generated reply's leading `Status`. Leaves the
+/// parcel position where it was.
+fn reply_exception(reply: &mut Parcel) -> Option<ExceptionCode> {
+    if reply.data_size() == 0 {
+        return None;
+    }
+    let pos = reply.data_position();
+    reply.set_data_position(0);
+    let status = reply.read::<Status>();
+    reply.set_data_position(pos);
+    status.ok().map(|status| status.exception_code())
+}
+
+/// A server call being dispatched on this thread; nested for re-entrant
+/// calls.
+struct ServerFrame {
+    descriptor: String,
+    /// Set by [`server_method`], which also counts the call in flight.
+    entry: Option<Arc<Entry>>,
+}
+
+thread_local! {
+    static SERVER_CALLS: RefCell<Vec<ServerFrame>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Name the method of the server call this thread is dispatching.
+pub(crate) fn server_method(method: &str) {
+    SERVER_CALLS.with(|calls| {
+        if let Some(frame) = calls.borrow_mut().last_mut() {
+            if frame.entry.is_none() {
+                let entry = entry(Side::Server, &frame.descriptor, method);
+                entry.in_flight.fetch_add(1, Ordering::Relaxed);
+                frame.entry = Some(entry);
+            }
+        }
+    });
+}
+
+/// The metrics half of `trace::Transaction`.
+pub(crate) enum Call {
+    Client {
+        entry: Arc<Entry>,
+        named: bool,
+        data_size: usize,
+    },
+    Server {
+        code: u32,
+        data_size: usize,
+    },
+}
+
+impl Call {
+    pub(crate) fn client(
+        descriptor: &str,
+        method: Option<&str>,
+        code: u32,
+        data_size: usize,
+    ) -> Self {
+        let entry = match method {
+            Some(method) => entry(Side::Client, descriptor, method),
+            None => entry(Side::Client, descriptor, &code.to_string()),
+        };
+        entry.in_flight.fetch_add(1, Ordering::Relaxed);
+        Call::Client {
+            entry,
+            named: method.is_some(),
+            data_size,
+        }
+    }
+
+    pub(crate) fn server(descriptor: &str, code: u32, data_size: usize) -> Self {
+        SERVER_CALLS.with(|calls| {
+            calls.borrow_mut().push(ServerFrame {
+                descriptor: descriptor.to_owned(),
+                entry: None,
+            })
+        });
+        Call::Server { code, data_size }
+    }
+
+    pub(crate) fn finish(self, status: StatusCode, reply: Option<&mut Parcel>, elapsed: Duration) {
+        let reply_size = reply.as_ref().map(|reply| reply.data_size());
+        match self {
+            Call::Client {
+                entry,
+                named,
+                data_size,
+            } => {
+                let exception = reply.filter(|_| named).and_then(reply_exception);
+                entry.record(status, exception, elapsed, data_size, reply_size);
+                entry.in_flight.fetch_sub(1, Ordering::Relaxed);
+            }
+            Call::Server { code, data_size } => {
+                let Some(frame) = SERVER_CALLS.with(|calls| calls.borrow_mut().pop()) else {
+                    return;
+                };
+                match frame.entry {
+                    Some(entry) => {
+                        let exception = reply.and_then(reply_exception);
+                        entry.record(status, exception, elapsed, data_size, reply_size);
+                        entry.in_flight.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    None => entry(Side::Server, &frame.descriptor, &code.to_string())
+                        .record(status, None, elapsed, data_size, reply_size),
+                }
+            }
+        }
+    }
+}
+
+/// Copy every recorded method out of the registry, ordered by side,
+/// descriptor and method.
+pub fn snapshot() -> Vec<CallMetrics> {
+    let mut out = Vec::new();
+    for side in [Side::Client, Side::Server] {
+        let registry = REGISTRY[side as usize]
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        for (descriptor, methods) in registry.iter() {
+            for (method, entry) in methods {
+                out.push(CallMetrics {
+                    side,
+                    descriptor: descriptor.clone(),
+                    method: method.clone(),
+                    calls: entry.calls.load(Ordering::Relaxed),
+                    in_flight: entry.in_flight.load(Ordering::Relaxed),
+                    errors: lock(&entry.errors).clone(),
+                    exceptions: lock(&entry.exceptions).clone(),
+                    latency: Histogram {
+                        buckets: entry
+                            .buckets
+                            .iter()
+                            .map(|b| b.load(Ordering::Relaxed))
+                            .collect(),
+                        sum: Duration::from_micros(entry.sum_micros.load(Ordering::Relaxed)),
+                    },
+                    request_bytes: entry.request_bytes.load(),
+                    reply_bytes: entry.reply_bytes.load(),
+                });
+            }
+        }
+    }
+    out.sort_by(|a, b| (a.side, &a.descriptor, &a.method).cmp(&(b.side, &b.descriptor, &b.method)));
+    out
+}
+
+/// Forget everything recorded so far. Calls in flight are still
+/// completed, into fresh entries.
+pub fn reset() {
+    for registry in REGISTRY.iter() {
+        registry
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clear();
+    }
+}
+
+/// Render `metrics` in the Prometheus text exposition format (version
+/// 0.0.4): `rsbinder_calls_total`, `rsbinder_call_errors_total`,
+/// `rsbinder_call_exceptions_total`, `rsbinder_calls_in_flight`, the
+/// `rsbinder_call_duration_seconds` histogram, and
+/// `rsbinder_call_{request,reply}_bytes_{total,max}`, each labelled with
+/// `side`, `descriptor` and `method`.
+pub fn render_prometheus(metrics: &[CallMetrics]) -> String {
+    let mut out = String::new();
+    let labels: Vec<String> = metrics
+        .iter()
+        .map(|m| {
+            format!(
+                "side=\"{}\",descriptor=\"{}\",method=\"{}\"",
+                m.side.label(),
+                escape(&m.descriptor),
+                escape(&m.method)
+            )
+        })
+        .collect();
+
+    header(
+        &mut out,
+        "rsbinder_calls_total",
+        "counter",
+        "Completed binder calls.",
+    );
+    for (m, labels) in metrics.iter().zip(&labels) {
+        let _ = writeln!(out, "rsbinder_calls_total{{{labels}}} {}", m.calls);
+    }
+    header(
+        &mut out,
+        "rsbinder_call_errors_total",
+        "counter",
+        "Binder calls that failed in transport, by status.",
+    );
+    for (m, labels) in metrics.iter().zip(&labels) {
+        for (status, count) in &m.errors {
+            let _ = writeln!(
+                out,
+                "rsbinder_call_errors_total{{{labels},status=\"{status}\"}} {count}"
+            );
+        }
+    }
+    header(
+        &mut out,
+        "rsbinder_call_exceptions_total",
+        "counter",
+        "Binder calls whose reply carried an exception, by exception code.",
+    );
+    for (m, labels) in metrics.iter().zip(&labels) {
+        for (exception, count) in &m.exceptions {
+            let _ = writeln!(
+                out,
+                "rsbinder_call_exceptions_total{{{labels},exception=\"{exception}\"}} {count}"
+            );
+        }
+    }
+    header(
+        &mut out,
+        "rsbinder_calls_in_flight",
+        "gauge",
+        "Binder calls started and not yet completed.",
+    );
+    for (m, labels) in metrics.iter().zip(&labels) {
+        let _ = writeln!(out, "rsbinder_calls_in_flight{{{labels}}} {}", m.in_flight);
+    }
+    header(
+        &mut out,
+        "rsbinder_call_duration_seconds",
+        "histogram",
+        "Binder call latency.",
+    );
+    for (m, labels) in metrics.iter().zip(&labels) {
+        let mut cumulative = 0;
+        for (i, count) in m.latency.buckets.iter().enumerate() {
+            cumulative += count;
+            let le = match LATENCY_BUCKETS.get(i) {
+                Some(bound) => bound.to_string(),
+                None => "+Inf".to_owned(),
+            };
+            let _ = writeln!(
+                out,
+                "rsbinder_call_duration_seconds_bucket{{{labels},le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "rsbinder_call_duration_seconds_sum{{{labels}}} {}",
+            m.latency.sum.as_secs_f64()
+        );
+        let _ = writeln!(
+            out,
+            "rsbinder_call_duration_seconds_count{{{labels}}} {cumulative}"
+        );
+    }
+    for (direction, sizes) in [
+        (
+            "request",
+            (|m: &CallMetrics| m.request_bytes) as fn(&CallMetrics) -> Sizes,
+        ),
+        ("reply", |m| m.reply_bytes),
+    ] {
+        let name = format!("rsbinder_call_{direction}_bytes_total");
+        header(
+            &mut out,
+            &name,
+            "counter",
+            &format!("Binder call {direction} data, in bytes."),
+        );
+        for (m, labels) in metrics.iter().zip(&labels) {
+            let _ = writeln!(out, "{name}{{{labels}}} {}", sizes(m).total);
+        }
+        let name = format!("rsbinder_call_{direction}_bytes_max");
+        header(
+            &mut out,
+            &name,
+            "gauge",
+            &format!("Largest binder call {direction}, in bytes."),
+        );
+        for (m, labels) in metrics.iter().zip(&labels) {
+            let _ = writeln!(out, "{name}{{{labels}}} {}", sizes(m).max);
+        }
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Escape a label value: backslash, double quote and newline.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics_of(side: Side, descriptor: &str, method: &str) -> CallMetrics {
+        snapshot()
+            .into_iter()
+            .find(|m| m.side == side && m.descriptor == descriptor && m.method == method)
+            .expect("recorded")
+    }
+
+    #[test]
+    fn test_client_call_records_latency_and_errors() {
+        let desc = "metrics.test.IClient";
+        let call = Call::client(desc, Some("echo"), 1, 40);
+        assert_eq!(metrics_of(Side::Client, desc, "echo").in_flight, 1);
+        let mut reply = Parcel::new();
+        reply.write(&Status::from(StatusCode::Ok)).unwrap();
+        call.finish(StatusCode::Ok, Some(&mut reply), Duration::from_micros(700));
+        assert_eq!(reply.data_position(), reply.data_size());
+        let mut reply_bytes = reply.data_size() as u64;
+
+        let mut reply = Parcel::new();
+        reply
+            .write(&Status::new_service_specific_error(3, None))
+            .unwrap();
+        reply_bytes += reply.data_size() as u64;
+        Call::client(desc, Some("echo"), 1, 100).finish(
+            StatusCode::Ok,
+            Some(&mut reply),
+            Duration::from_secs(9),
+        );
+        Call::client(desc, Some("echo"), 1, 60).finish(
+            StatusCode::DeadObject,
+            None,
+            Duration::ZERO,
+        );
+
+        let m = metrics_of(Side::Client, desc, "echo");
+        assert_eq!(m.calls, 3);
+        assert_eq!(m.in_flight, 0);
+        assert_eq!(m.errors, BTreeMap::from([(StatusCode::DeadObject, 1)]));
+        assert_eq!(
+            m.exceptions,
+            BTreeMap::from([(ExceptionCode::ServiceSpecific, 1)])
+        );
+        let mut expected = vec![0; LATENCY_BUCKETS.len() + 1];
+        expected[0] = 1; // 0s
+        expected[3] = 1; // 700us <= 1ms
+        expected[LATENCY_BUCKETS.len()] = 1; // 9s, +Inf
+        assert_eq!(m.latency.buckets, expected);
+        assert_eq!(m.latency.sum, Duration::from_micros(9_000_700));
+        assert_eq!(
+            m.request_bytes,
+            Sizes {
+                total: 200,
+                max: 100
+            }
+        );
+        // Only the two calls that got a reply.
+        assert_eq!(m.reply_bytes.total, reply_bytes);
+    }
+
+    #[test]
+    fn test_unnamed_calls_use_code_and_skip_exceptions() {
+        let desc = "metrics.test.IRaw";
+        let mut reply = Parcel::new();
+        reply.write(&-1i32).unwrap(); // would read as `Security`
+        Call::client(desc, None, 7, 0).finish(StatusCode::Ok, Some(&mut reply), Duration::ZERO);
+        Call::server(desc, 8, 0).finish(StatusCode::BadValue, None, Duration::ZERO);
+
+        let client = metrics_of(Side::Client, desc, "7");
+        assert_eq!(client.calls, 1);
+        assert!(client.exceptions.is_empty());
+        let server = metrics_of(Side::Server, desc, "8");
+        assert_eq!(server.errors, BTreeMap::from([(StatusCode::BadValue, 1)]));
+    }
+
+    #[test]
+    fn test_server_method_nests() {
+        let (outer, inner) = ("metrics.test.IOuter", "metrics.test.IInner");
+        let outer_call = Call::server(outer, 1, 0);
+        server_method("run");
+        assert_eq!(metrics_of(Side::Server, outer, "run").in_flight, 1);
+
+        // A callback served while `run` is still dispatching.
+        let inner_call = Call::server(inner, 1, 0);
+        server_method("ping");
+        inner_call.finish(StatusCode::Ok, Some(&mut Parcel::new()), Duration::ZERO);
+        outer_call.finish(StatusCode::Ok, Some(&mut Parcel::new()), Duration::ZERO);
+
+        assert_eq!(metrics_of(Side::Server, inner, "ping").calls, 1);
+        let run = metrics_of(Side::Server, outer, "run");
+        assert_eq!((run.calls, run.in_flight), (1, 0));
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let metrics = [CallMetrics {
+            side: Side::Server,
+            descriptor: "a.\"quoted\"\\IFoo".into(),
+            method: "get".into(),
+            calls: 2,
+            in_flight: 1,
+            errors: BTreeMap::from([(StatusCode::PermissionDenied, 1)]),
+            exceptions: BTreeMap::from([(ExceptionCode::IllegalArgument, 1)]),
+            latency: Histogram {
+                buckets: {
+                    let mut b = vec![0; LATENCY_BUCKETS.len() + 1];
+                    b[0] = 1;
+                    b[2] = 1;
+                    b
+                },
+                sum: Duration::from_micros(550),
+            },
+            request_bytes: Sizes { total: 96, max: 64 },
+            reply_bytes: Sizes { total: 8, max: 4 },
+        }];
+        let text = render_prometheus(&metrics);
+        let labels = r#"side="server",descriptor="a.\"quoted\"\\IFoo",method="get""#;
+        for line in [
+            "# TYPE rsbinder_calls_total counter".to_owned(),
+            format!("rsbinder_calls_total{{{labels}}} 2"),
+            format!("rsbinder_call_errors_total{{{labels},status=\"PermissionDenied\"}} 1"),
+            format!("rsbinder_call_exceptions_total{{{labels},exception=\"IllegalArgument\"}} 1"),
+            format!("rsbinder_calls_in_flight{{{labels}}} 1"),
+            "# TYPE rsbinder_call_duration_seconds histogram".to_owned(),
+            format!("rsbinder_call_duration_seconds_bucket{{{labels},le=\"0.0001\"}} 1"),
+            format!("rsbinder_call_duration_seconds_bucket{{{labels},le=\"0.00025\"}} 1"),
+            format!("rsbinder_call_duration_seconds_bucket{{{labels},le=\"0.0005\"}} 2"),
+            format!("rsbinder_call_duration_seconds_bucket{{{labels},le=\"+Inf\"}} 2"),
+            format!("rsbinder_call_duration_seconds_sum{{{labels}}} 0.00055"),
+            format!("rsbinder_call_duration_seconds_count{{{labels}}} 2"),
+            "# TYPE rsbinder_call_request_bytes_total counter".to_owned(),
+            format!("rsbinder_call_request_bytes_total{{{labels}}} 96"),
+            format!("rsbinder_call_request_bytes_max{{{labels}}} 64"),
+            format!("rsbinder_call_reply_bytes_total{{{labels}}} 8"),
+            "# TYPE rsbinder_call_reply_bytes_max gauge".to_owned(),
+            format!("rsbinder_call_reply_bytes_max{{{labels}}} 4"),
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {line:?} in\n{text}"
+            );
+        }
+    }
+}
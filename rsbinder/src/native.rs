@@ -33,6 +33,7 @@ use std::os::fd::FromRawFd;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex, RwLock, Weak};
 
+use crate::recorded_transaction::RecordedTransaction;
 use crate::{
     binder::*, error::*, parcel::*, parcelable::SerializeOption, ref_counter::RefCounter,
     thread_state,
@@ -218,6 +219,11 @@ struct Inner<T: Remotable + Send + Sync> {
     /// binder. Callers that need multiple attachments of the same
     /// concrete type should wrap them in distinct newtype shells.
     objects: Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+    /// `START_RECORDING_TRANSACTION` target, AOSP
+    /// `BBinder::Extras::mRecordingFd`.
+    recording: Mutex<Option<File>>,
+    /// AOSP `BBinder::mRecordingOn`: lets unrecorded binders skip the lock.
+    is_recording: AtomicBool,
 }
 
 impl<T: Remotable> Inner<T> {
@@ -235,6 +241,72 @@ impl<T: Remotable> Inner<T> {
         Ok(())
     }
 
+    /// AOSP `BBinder::startRecordingTransactions`. Only root and the
+    /// service's own uid may record; one recording at a time.
+    fn start_recording(&self, file: File) -> Result<()> {
+        let mut recording = self.recording.lock().expect("recording lock poisoned");
+        if recording.is_some() {
+            log::info!("Could not attach binder recorder because one is already attached");
+            return Err(StatusCode::InvalidOperation);
+        }
+        *recording = Some(file);
+        self.is_recording.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// AOSP `BBinder::stopRecordingTransactions`. Not recording is not an
+    /// error.
+    fn stop_recording(&self) {
+        let mut recording = self.recording.lock().expect("recording lock poisoned");
+        *recording = None;
+        self.is_recording.store(false, Ordering::Release);
+    }
+
+    fn check_recording_caller() -> Result<()> {
+        let uid = thread_state::get_calling_uid();
+        if uid != 0 && uid != rustix::process::getuid().as_raw() {
+            log::error!("Binder recording not allowed because client {uid} is not root");
+            return Err(StatusCode::PermissionDenied);
+        }
+        Ok(())
+    }
+
+    fn on_recording_transaction(&self, code: TransactionCode, reader: &mut Parcel) -> Result<()> {
+        Self::check_recording_caller()?;
+        if code == START_RECORDING_TRANSACTION {
+            self.start_recording(File::from(reader.read_file_descriptor()?))
+        } else {
+            self.stop_recording();
+            Ok(())
+        }
+    }
+
+    /// Append a served transaction to the recording, if one is running.
+    /// `version` is `0` for kernel binder and non-zero for RPC, as in
+    /// AOSP `RecordedTransaction`.
+    fn record(
+        &self,
+        code: TransactionCode,
+        version: u32,
+        data: &Parcel,
+        reply: &Parcel,
+        result: &Result<()>,
+    ) {
+        if !self.is_recording.load(Ordering::Acquire) || code == START_RECORDING_TRANSACTION {
+            return;
+        }
+        let mut recording = self.recording.lock().expect("recording lock poisoned");
+        let Some(file) = recording.as_mut() else {
+            return;
+        };
+        let flags = thread_state::last_transaction_binder_flags();
+        let transaction =
+            RecordedTransaction::new(T::descriptor(), code, flags, version, data, reply, result);
+        if let Err(err) = transaction.write_to(file) {
+            log::info!("Failed to dump RecordedTransaction to file with error {err}");
+        }
+    }
+
     // The following functions can be redefined depending on the service.
     fn on_transact(
         &self,
@@ -268,10 +340,9 @@ impl<T: Remotable> Inner<T> {
 
                 self.remotable.on_dump(file.deref_mut(), argv.as_slice())
             }
-            SHELL_COMMAND_TRANSACTION => {
-                log::error!("SHELL_COMMAND_TRANSACTION is not supported.");
-                Err(StatusCode::InvalidOperation)
-            }
+            SHELL_COMMAND_TRANSACTION => crate::shell_command::on_transaction(_reader, |command| {
+                self.remotable.on_shell_command(command)
+            }),
             SYSPROPS_TRANSACTION => {
                 log::error!("SYSPROPS_TRANSACTION is not supported.");
                 Err(StatusCode::InvalidOperation)
@@ -367,30 +438,15 @@ impl<T: 'static + Remotable> IBinder for Inner<T> {
         reader: &mut Parcel,
         reply: &mut Parcel,
     ) -> Result<()> {
-        match code {
-            PING_TRANSACTION => Ok(()),
-            EXTENSION_TRANSACTION => {
-                let ext = self.extension.read().expect("Extension lock poisoned");
-                SerializeOption::serialize_option(ext.as_ref(), reply)?;
-                Ok(())
-            }
-            STOP_RECORDING_TRANSACTION | START_RECORDING_TRANSACTION => {
-                log::error!("recording transactions are not supported over RPC");
-                Err(StatusCode::InvalidOperation)
-            }
-            DEBUG_PID_TRANSACTION => {
-                reply.write::<i32>(&rustix::process::getpid().as_raw_nonzero().get())
-            }
-            _ => match self.remotable.on_transact(code, reader, reply) {
-                Ok(_) => Ok(()),
-                Err(StatusCode::UnknownTransaction) => {
-                    // Same fallback as `Inner::transact`: handle
-                    // INTERFACE_TRANSACTION etc. via `Inner::on_transact`.
-                    self.on_transact(code, reader, reply)
-                }
-                Err(err) => Err(err),
-            },
-        }
+        let result = self.rpc_dispatch(code, reader, reply);
+        self.record(
+            code,
+            crate::recorded_transaction::RPC_RECORDING_VERSION,
+            reader,
+            reply,
+            &result,
+        );
+        result
     }
 
     fn descriptor(&self) -> &str {
@@ -433,6 +489,51 @@ impl<T: Remotable> Transactable for Inner<T> {
         code: TransactionCode,
         reader: &mut Parcel,
         reply: &mut Parcel,
+    ) -> Result<()> {
+        let result = self.dispatch(code, reader, reply);
+        self.record(code, 0, reader, reply, &result);
+        result
+    }
+}
+
+impl<T: Remotable> Inner<T> {
+    #[cfg(feature = "rpc")]
+    fn rpc_dispatch(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            PING_TRANSACTION => Ok(()),
+            EXTENSION_TRANSACTION => {
+                let ext = self.extension.read().expect("Extension lock poisoned");
+                SerializeOption::serialize_option(ext.as_ref(), reply)?;
+                Ok(())
+            }
+            STOP_RECORDING_TRANSACTION | START_RECORDING_TRANSACTION => {
+                self.on_recording_transaction(code, reader)
+            }
+            DEBUG_PID_TRANSACTION => {
+                reply.write::<i32>(&rustix::process::getpid().as_raw_nonzero().get())
+            }
+            _ => match self.remotable.on_transact(code, reader, reply) {
+                Ok(_) => Ok(()),
+                Err(StatusCode::UnknownTransaction) => {
+                    // Same fallback as `Inner::transact`: handle
+                    // INTERFACE_TRANSACTION etc. via `Inner::on_transact`.
+                    self.on_transact(code, reader, reply)
+                }
+                Err(err) => Err(err),
+            },
+        }
+    }
+
+    fn dispatch(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
     ) -> Result<()> {
         reader.set_data_position(0);
         match code {
@@ -446,14 +547,8 @@ impl<T: Remotable> Transactable for Inner<T> {
                 Ok(())
             }
 
-            STOP_RECORDING_TRANSACTION => {
-                log::error!("STOP_RECORDING_TRANSACTION is not supported.");
-                Err(StatusCode::InvalidOperation)
-            }
-
-            START_RECORDING_TRANSACTION => {
-                log::error!("START_RECORDING_TRANSACTION is not supported.");
-                Err(StatusCode::InvalidOperation)
+            STOP_RECORDING_TRANSACTION | START_RECORDING_TRANSACTION => {
+                self.on_recording_transaction(code, reader)
             }
 
             DEBUG_PID_TRANSACTION => {
@@ -461,7 +556,9 @@ impl<T: Remotable> Transactable for Inner<T> {
             }
 
             _ => {
+                // HIDL stubs check their own (C string) token per method.
                 if (FIRST_CALL_TRANSACTION..=LAST_CALL_TRANSACTION).contains(&code)
+                    && !T::is_hidl()
                     && !(thread_state::check_interface(reader, T::descriptor())?)
                 {
                     // BAD_TYPE as the transaction *status* (the dispatcher
@@ -544,6 +641,8 @@ impl<T: 'static + Remotable> Binder<T> {
                 weak: Default::default(),
                 extension: RwLock::new(None),
                 objects: Mutex::new(HashMap::new()),
+                recording: Mutex::new(None),
+                is_recording: AtomicBool::new(false),
             }),
         }
     }
@@ -608,6 +707,19 @@ impl<T: 'static + Remotable> Binder<T> {
     pub fn get_extension(&self) -> Result<Option<SIBinder>> {
         self.inner.get_extension()
     }
+
+    /// Record every transaction this binder serves to `file`, in the
+    /// format read by [`crate::recorded_transaction::RecordedTransaction`].
+    /// The in-process equivalent of a remote `START_RECORDING_TRANSACTION`.
+    /// Fails with [`StatusCode::InvalidOperation`] if already recording.
+    pub fn start_recording(&self, file: File) -> Result<()> {
+        self.inner.start_recording(file)
+    }
+
+    /// Stop recording and close the recording file.
+    pub fn stop_recording(&self) {
+        self.inner.stop_recording()
+    }
 }
 
 impl<T: 'static + Remotable> Interface for Binder<T> {
@@ -1081,3 +1193,105 @@ mod stability_mutation_tests {
         );
     }
 }
+
+/// In-process `START_RECORDING_TRANSACTION` behavior (AOSP
+/// `BBinder::startRecordingTransactions` / `RecordedTransaction`).
+#[cfg(test)]
+mod recording_tests {
+    use super::*;
+    use std::io::Read;
+    use std::os::fd::OwnedFd;
+
+    use crate::recorded_transaction::RecordedTransaction;
+
+    /// Outside the user range, so the kernel path skips `check_interface`.
+    const DOUBLE: TransactionCode = LAST_CALL_TRANSACTION + 1;
+
+    struct Doubler;
+    impl crate::Remotable for Doubler {
+        fn descriptor() -> &'static str
+        where
+            Self: Sized,
+        {
+            "test.recording"
+        }
+        fn on_transact(
+            &self,
+            code: crate::TransactionCode,
+            reader: &mut crate::Parcel,
+            reply: &mut crate::Parcel,
+        ) -> crate::Result<()> {
+            if code != DOUBLE {
+                return Err(StatusCode::UnknownTransaction);
+            }
+            let value: i32 = reader.read()?;
+            reply.write(&(value * 2))
+        }
+        fn on_dump(&self, _: &mut dyn std::io::Write, _: &[String]) -> crate::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn call(binder: &Binder<Doubler>, value: i32) -> Result<i32> {
+        let mut data = Parcel::new();
+        data.write(&value)?;
+        let mut reply = Parcel::new();
+        binder.inner.transact(DOUBLE, &mut data, &mut reply)?;
+        reply.set_data_position(0);
+        reply.read()
+    }
+
+    #[test]
+    fn records_served_transactions() {
+        let (read, write) = std::os::unix::net::UnixStream::pair().unwrap();
+        let binder = Binder::new(Doubler);
+
+        assert_eq!(call(&binder, 1).unwrap(), 2);
+        binder
+            .start_recording(File::from(OwnedFd::from(write)))
+            .unwrap();
+        assert_eq!(call(&binder, 21).unwrap(), 42);
+        let mut data = Parcel::new();
+        let mut reply = Parcel::new();
+        assert_eq!(
+            binder.inner.transact(DOUBLE, &mut data, &mut reply),
+            Err(StatusCode::NotEnoughData)
+        );
+        binder.stop_recording();
+        assert_eq!(call(&binder, 5).unwrap(), 10);
+
+        let mut bytes = Vec::new();
+        File::from(OwnedFd::from(read))
+            .read_to_end(&mut bytes)
+            .unwrap();
+        let recorded = RecordedTransaction::read_all(&mut bytes.as_slice()).unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].interface_name, "test.recording");
+        assert_eq!(recorded[0].code, DOUBLE);
+        assert_eq!(recorded[0].version, 0);
+        assert_eq!(recorded[0].status(), Ok(()));
+        assert_eq!(recorded[0].data, 21i32.to_ne_bytes());
+        assert_eq!(recorded[0].reply, 42i32.to_ne_bytes());
+        assert_eq!(recorded[1].status(), Err(StatusCode::NotEnoughData));
+
+        // Replaying against the binder reproduces the recorded reply.
+        let reply = recorded[0].replay(&binder.as_binder()).unwrap().unwrap();
+        assert_eq!(reply.data_bytes(), recorded[0].reply.as_slice());
+    }
+
+    #[test]
+    fn one_recording_at_a_time() {
+        let binder = Binder::new(Doubler);
+        let (first, _) = std::os::unix::net::UnixStream::pair().unwrap();
+        let (second, _) = std::os::unix::net::UnixStream::pair().unwrap();
+        binder
+            .start_recording(File::from(OwnedFd::from(first)))
+            .unwrap();
+        assert_eq!(
+            binder.start_recording(File::from(OwnedFd::from(second))),
+            Err(StatusCode::InvalidOperation)
+        );
+        binder.stop_recording();
+        binder.stop_recording();
+    }
+}
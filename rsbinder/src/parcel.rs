@@ -23,19 +23,26 @@
 //! in binder transactions. Parcels handle the low-level details of data layout,
 //! alignment, and object references required for cross-process communication.
 
+use std::borrow::Cow;
 use std::default::Default;
 use std::vec::Vec;
 
 use pretty_hex::*;
-use rustix::fd::IntoRawFd;
+use rustix::fd::{AsRawFd, BorrowedFd, IntoRawFd, OwnedFd};
 
 use crate::{
     binder,
-    binder_object::{read_flat_binder, write_flat_binder},
+    binder_object::{
+        read_buffer_object, read_fd_array_object, read_flat_binder, read_object_type,
+        write_flat_binder,
+    },
     error::{Result, StatusCode},
     parcelable::*,
-    sys::binder::{binder_size_t, flat_binder_object},
-    sys::{binder_uintptr_t, BINDER_TYPE_FD},
+    process_state::ProcessState,
+    sys::binder::{
+        binder_buffer_object, binder_fd_array_object, binder_size_t, flat_binder_object,
+    },
+    sys::{binder_uintptr_t, BINDER_TYPE_FD, BINDER_TYPE_FDA, BINDER_TYPE_HANDLE, BINDER_TYPE_PTR},
     thread_state,
 };
 
@@ -124,8 +131,6 @@ impl<T: Clone + Default> ParcelData<T> {
         ParcelData::Vec(Vec::with_capacity(capacity))
     }
 
-    // Only the RPC stack adopts a ready-made byte buffer as a parcel.
-    #[cfg(feature = "rpc")]
     fn from_vec(data: Vec<T>) -> Self {
         ParcelData::Vec(data)
     }
@@ -211,6 +216,19 @@ impl<T: Clone + Default> ParcelData<T> {
         }
     }
 
+    /// Shorten to `len` elements; for a kernel buffer only the view
+    /// shrinks, the buffer itself is freed whole.
+    fn truncate(&mut self, len: usize) {
+        match self {
+            ParcelData::Vec(v) => v.truncate(len),
+            ParcelData::Slice(s) => {
+                let slice = std::mem::take(s);
+                let len = len.min(slice.len());
+                *s = &mut slice[..len];
+            }
+        }
+    }
+
     fn capacity(&self) -> usize {
         match self {
             ParcelData::Vec(v) => v.capacity(),
@@ -362,6 +380,18 @@ impl RpcFields {
 /// nesting; conforming traffic never reaches it.
 const MAX_NESTED_READ_DEPTH: usize = 1000;
 
+/// Scatter-gather state of a locally built [`Parcel`].
+#[derive(Default)]
+struct SgState {
+    /// Out-of-line payloads referenced by the `BINDER_TYPE_PTR` objects
+    /// written into the parcel. Boxed so each payload keeps a stable
+    /// address while the parcel grows; the driver copies them when the
+    /// parcel is sent with `BC_TRANSACTION_SG` / `BC_REPLY_SG`.
+    buffers: Vec<Box<[u8]>>,
+    /// Dup'd fds backing [`Parcel::write_fd_array`], closed on drop.
+    fds: Vec<OwnedFd>,
+}
+
 /// Parcel converts data into a byte stream (serialization), making it transferable.
 /// The receiving side then transforms this byte stream back into its original data form (deserialization).
 ///
@@ -390,6 +420,9 @@ pub struct Parcel {
     request_header_present: bool,
     work_source_request_header_pos: usize,
     free_buffer: Option<FnFreeBuffer>,
+    /// Scatter-gather payloads written into this parcel; allocated by the
+    /// first one so plain parcels stay small. See [`SgState`].
+    sg: Option<Box<SgState>>,
     /// RPC serialization state, or `None` for the kernel path
     /// (byte-identical to the kernel wire). `Some` is the former
     /// `is_for_rpc == true`. Only object marshalling and the
@@ -397,6 +430,20 @@ pub struct Parcel {
     /// unaffected. See [`RpcFields`].
     #[cfg(feature = "rpc")]
     rpc: Option<RpcFields>,
+    /// Method (and client span) [`crate::trace::client_call`] attached
+    /// to the request this parcel carries; picked up when it is submitted.
+    /// Boxed so parcels without one stay small.
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    client_call: Option<Box<crate::trace::ClientCall>>,
+    /// Whether the target of this request opted in to receiving a
+    /// trace-context trailer; set by the proxy's `prepare_transact`.
+    #[cfg(feature = "tracing")]
+    propagates_trace: bool,
+    /// The binder context whose handle table the handles in this parcel
+    /// belong to, `None` for the [`ProcessState::init`] singleton: the
+    /// context current where the parcel was made, or the target proxy's
+    /// for a request.
+    context: Option<&'static ProcessState>,
 }
 
 impl Default for Parcel {
@@ -423,8 +470,14 @@ impl Parcel {
             request_header_present: false,
             work_source_request_header_pos: 0,
             free_buffer: None,
+            sg: None,
             #[cfg(feature = "rpc")]
             rpc: None,
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            client_call: None,
+            #[cfg(feature = "tracing")]
+            propagates_trace: false,
+            context: ProcessState::current(),
         }
     }
 
@@ -455,12 +508,19 @@ impl Parcel {
             request_header_present: false,
             work_source_request_header_pos: 0,
             free_buffer: Some(free_buffer),
+            sg: None,
             #[cfg(feature = "rpc")]
             rpc: None,
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            client_call: None,
+            #[cfg(feature = "tracing")]
+            propagates_trace: false,
+            context: ProcessState::current(),
         }
     }
 
-    #[cfg(feature = "rpc")]
+    /// A kernel-mode parcel holding `data` and no objects (RPC wire
+    /// bodies, recorded transactions).
     pub(crate) fn from_vec(data: Vec<u8>) -> Self {
         Parcel {
             data: ParcelData::from_vec(data),
@@ -474,11 +534,55 @@ impl Parcel {
             request_header_present: false,
             work_source_request_header_pos: 0,
             free_buffer: None,
+            sg: None,
             #[cfg(feature = "rpc")]
             rpc: None,
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            client_call: None,
+            #[cfg(feature = "tracing")]
+            propagates_trace: false,
+            context: ProcessState::current(),
         }
     }
 
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    pub(crate) fn set_client_call(&mut self, call: crate::trace::ClientCall) {
+        self.client_call = Some(Box::new(call));
+    }
+
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    pub(crate) fn client_call(&self) -> Option<&crate::trace::ClientCall> {
+        self.client_call.as_deref()
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn set_propagates_trace(&mut self, propagates: bool) {
+        self.propagates_trace = propagates;
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn propagates_trace(&self) -> bool {
+        self.propagates_trace
+    }
+
+    /// Bind the parcel to the binder context of the proxy it is sent
+    /// through; set by the proxy's `prepare_transact`.
+    pub(crate) fn set_context(&mut self, context: Option<&'static ProcessState>) {
+        self.context = context;
+    }
+
+    /// The binder context the parcel's handles belong to; see
+    /// [`ProcessState::of`].
+    pub(crate) fn context(&self) -> Option<&'static ProcessState> {
+        self.context
+    }
+
+    /// The parcel's raw bytes, AOSP `Parcel::data()`. Binder objects
+    /// and fds appear in their flattened form.
+    pub fn data_bytes(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
     pub(crate) fn as_mut_ptr(&mut self) -> *mut u8 {
         self.data.as_mut_ptr()
     }
@@ -732,6 +836,24 @@ impl Parcel {
         Ok(())
     }
 
+    /// Drop the bytes from `len` on, e.g. a trailer that is not part of
+    /// the payload. Fails with `BadValue`, leaving the parcel as it was,
+    /// if an object would be cut.
+    pub(crate) fn truncate_data(&mut self, len: usize) -> Result<()> {
+        let object_size = std::mem::size_of::<flat_binder_object>();
+        if self
+            .objects
+            .as_slice()
+            .iter()
+            .any(|&offset| offset as usize + object_size > len)
+        {
+            return Err(StatusCode::BadValue);
+        }
+        self.data.truncate(len);
+        self.pos = self.pos.min(self.data.len());
+        Ok(())
+    }
+
     pub(crate) fn close_file_descriptors(&self) {
         // RPC-mode parcels never carry kernel FD objects (FD over RPC
         // is rejected by default / opt-in via Unix mode); nothing to close here.
@@ -745,6 +867,8 @@ impl Parcel {
                 log::error!("Parcel: unable to read object at offset {offset}");
                 continue;
             };
+            // `BINDER_TYPE_FDA` fds are closed by the driver itself when
+            // the buffer is freed, so only plain FD objects are closed here.
             if obj.header_type() == BINDER_TYPE_FD {
                 // Close the file descriptor
                 obj.owned_fd();
@@ -773,6 +897,12 @@ impl Parcel {
         D::deserialize(self)
     }
 
+    /// Read a `serde::Deserialize` value written by [`Parcel::write_serde`].
+    #[cfg(feature = "serde")]
+    pub fn read_serde<T: serde::de::DeserializeOwned>(&mut self) -> Result<T> {
+        T::deserialize(&mut crate::parcel_serde::Deserializer::new(self))
+    }
+
     /// Attempt to read a type that implements [`Deserialize`] from this parcel
     /// onto an existing value. This operation will overwrite the old value
     /// partially or completely, depending on how much data is available.
@@ -882,6 +1012,134 @@ impl Parcel {
         }
     }
 
+    /// A read-only cursor over this parcel's data starting at `pos`.
+    ///
+    /// Values borrowed through it live as long as the parcel, not the
+    /// cursor, so several can be held at once — e.g. after skipping them
+    /// with [`Parcel::skip_slice`] while reading the values around them.
+    pub fn borrowed_reader(&self, pos: usize) -> BorrowedReader<'_> {
+        BorrowedReader {
+            data: self.data.as_slice(),
+            pos,
+        }
+    }
+
+    // Run `f` on a cursor at the current position and advance past what
+    // it read.
+    fn read_borrowed<'p, T>(
+        &'p mut self,
+        f: impl FnOnce(&mut BorrowedReader<'p>) -> Result<T>,
+    ) -> Result<T> {
+        let mut reader = BorrowedReader {
+            data: self.data.as_slice(),
+            pos: self.pos,
+        };
+        let value = f(&mut reader)?;
+        self.pos = reader.pos;
+        Ok(value)
+    }
+
+    /// Borrow a `byte[]` (written as `&[u8]` or `&[i8]`) from the parcel
+    /// instead of copying it out. `None` for a null array.
+    pub fn read_bytes_ref(&mut self) -> Result<Option<&[u8]>> {
+        self.read_borrowed(BorrowedReader::read_bytes_ref)
+    }
+
+    /// Borrow a UTF-16 `String` from the parcel. `None` for a null string.
+    pub fn read_utf16_ref(&mut self) -> Result<Option<Utf16Str<'_>>> {
+        self.read_borrowed(BorrowedReader::read_utf16_ref)
+    }
+
+    /// Borrow a string written by [`Parcel::write_str8`]. `None` for a
+    /// null string.
+    pub fn read_str8_ref(&mut self) -> Result<Option<&str>> {
+        self.read_borrowed(BorrowedReader::read_str8_ref)
+    }
+
+    /// Borrow a primitive array. The view is zero-copy when the elements
+    /// are suitably aligned in the parcel's buffer (always for bytes) and
+    /// a copy otherwise. `None` for a null array.
+    pub fn read_slice_ref<T: PodElement>(&mut self) -> Result<Option<Cow<'_, [T]>>> {
+        self.read_borrowed(BorrowedReader::read_slice_ref)
+    }
+
+    /// Validate and step over a primitive array, returning its position
+    /// for a later [`Parcel::borrowed_reader`].
+    pub fn skip_slice<T: PodElement>(&mut self) -> Result<usize> {
+        let pos = self.pos;
+        self.read_borrowed(|reader| reader.slice_span::<T>().map(|_| ()))?;
+        Ok(pos)
+    }
+
+    /// Write `value` as an 8-bit string (AOSP `Parcel::writeString8`): the
+    /// byte length, the UTF-8 bytes and a NUL, padded to 4 bytes. `None`
+    /// writes a null string.
+    pub fn write_str8(&mut self, value: Option<&str>) -> Result<()> {
+        match value {
+            None => self.write(&-1i32),
+            Some(text) => {
+                let len = i32::try_from(text.len()).map_err(|_| StatusCode::BadValue)?;
+                self.write(&len)?;
+                let mut bytes = Vec::with_capacity(text.len() + 1);
+                bytes.extend_from_slice(text.as_bytes());
+                bytes.push(0);
+                self.write_aligned_data(&bytes)
+            }
+        }
+    }
+
+    /// Write a bare file descriptor object without taking ownership of
+    /// `fd` (AOSP `Parcel::writeFileDescriptor(fd, false)`); the driver
+    /// dups it into the receiver. Over RPC the fd is dup'd into the
+    /// out-of-band table, which needs the v1+ `Unix` fd mode.
+    pub(crate) fn write_file_descriptor(&mut self, fd: BorrowedFd<'_>) -> Result<()> {
+        #[cfg(feature = "rpc")]
+        if self.is_for_rpc() {
+            use crate::rpc::FileDescriptorTransportMode as M;
+            if self.rpc_fd_mode() != M::Unix || !self.rpc_record_fd_positions() {
+                return Err(StatusCode::BadType);
+            }
+            let idx = self.rpc_push_out_fd(rustix::io::fcntl_dupfd_cloexec(fd, 0)?);
+            let pos = self.data_position();
+            self.write::<i32>(&crate::rpc::wire_android13::TYPE_NATIVE_FILE_DESCRIPTOR)?;
+            self.write::<i32>(&idx)?;
+            self.rpc_record_object_position(pos);
+            return Ok(());
+        }
+
+        let obj = flat_binder_object::new_with_fd(fd.as_raw_fd(), false);
+        self.write_object(&obj, true)
+    }
+
+    /// Read a bare file descriptor object and return a dup of it (AOSP
+    /// `Parcel::readUniqueFileDescriptor`). The original stays with the
+    /// parcel, which closes it when a received buffer is freed.
+    pub(crate) fn read_file_descriptor(&mut self) -> Result<OwnedFd> {
+        #[cfg(feature = "rpc")]
+        if self.is_for_rpc() {
+            use crate::rpc::FileDescriptorTransportMode as M;
+            if self.rpc_fd_mode() != M::Unix || !self.rpc_record_fd_positions() {
+                return Err(StatusCode::BadType);
+            }
+            // AOSP `readFileDescriptor`: the object must be in the
+            // position table, else BAD_TYPE.
+            if !self.rpc_object_position_present(self.data_position()) {
+                return Err(StatusCode::BadType);
+            }
+            if self.read::<i32>()? != crate::rpc::wire_android13::TYPE_NATIVE_FILE_DESCRIPTOR {
+                return Err(StatusCode::BadType);
+            }
+            let idx = usize::try_from(self.read::<i32>()?).map_err(|_| StatusCode::BadValue)?;
+            return self.rpc_take_in_fd(idx).ok_or(StatusCode::BadValue);
+        }
+
+        let obj = self.read_object(true)?;
+        if obj.header_type() != BINDER_TYPE_FD {
+            return Err(StatusCode::BadType);
+        }
+        Ok(rustix::io::fcntl_dupfd_cloexec(obj.borrowed_fd(), 0)?)
+    }
+
     pub(crate) fn read_object(&mut self, null_meta: bool) -> Result<flat_binder_object> {
         // The kernel offset-table scan below is meaningless for an
         // RPC-mode parcel (RPC carries `RpcAddress`, not
@@ -1181,6 +1439,13 @@ impl Parcel {
         parcelable.serialize(self)
     }
 
+    /// Write any `serde::Serialize` value in the [`parcel_serde`](crate::parcel_serde)
+    /// layout.
+    #[cfg(feature = "serde")]
+    pub fn write_serde<T: serde::Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut crate::parcel_serde::Serializer::new(self))
+    }
+
     pub(crate) fn write_array<S: Serialize + Sized>(&mut self, parcelable: &[S]) -> Result<()> {
         let len = parcelable.len();
         // The wire length word is an `i32`; a slice too large to fit is a
@@ -1369,7 +1634,7 @@ impl Parcel {
         self.write_aligned(obj)?;
 
         if null_meta || obj.pointer() != 0 {
-            obj.acquire()?;
+            obj.acquire(self.context)?;
             self.objects.push(data_pos as _);
         }
 
@@ -1480,6 +1745,18 @@ impl Parcel {
 
             for (i, &off) in objects.iter().enumerate() {
                 if off >= offset as _ && (off + object_size) <= (offset + size) as u64 {
+                    // Scatter-gather objects point at `other`'s own buffers
+                    // and address their parents by table index, so neither
+                    // survives relocation into another parcel.
+                    if matches!(
+                        read_object_type(other.data.as_slice(), off as usize)?,
+                        BINDER_TYPE_PTR | BINDER_TYPE_FDA
+                    ) {
+                        log::error!(
+                            "Parcel::append_from: cannot copy a scatter-gather object at {off}"
+                        );
+                        return Err(StatusCode::InvalidOperation);
+                    }
                     if first_idx == -1 {
                         first_idx = i as i32;
                     }
@@ -1556,7 +1833,14 @@ impl Parcel {
             for i in first_idx..=last_idx {
                 let off = src_objects[i as usize] as usize - offset + start_pos;
                 let mut flat = read_flat_binder(self.data.as_slice(), off)?;
-                flat.acquire()?;
+                // A handle names a reference in one driver's table only.
+                if flat.header_type() == BINDER_TYPE_HANDLE
+                    && !ProcessState::same_context(self.context, other.context)
+                {
+                    log::error!("Parcel::append_from: a handle of another binder context");
+                    return Err(StatusCode::BadValue);
+                }
+                flat.acquire(self.context)?;
                 if flat.header_type() == BINDER_TYPE_FD {
                     let newfd = match rustix::io::fcntl_dupfd_cloexec(flat.borrowed_fd(), 0) {
                         Ok(newfd) => newfd,
@@ -1578,6 +1862,326 @@ impl Parcel {
         Ok(())
     }
 
+    /// Write `data` as an out-of-line buffer object (`BINDER_TYPE_PTR`,
+    /// AOSP libhwbinder `Parcel::writeBuffer`).
+    ///
+    /// The bytes are copied into the parcel and delivered by the driver as
+    /// a separate scatter-gather buffer instead of inline parcel data.
+    /// Returns the object index to pass as `parent` when embedding further
+    /// buffers or fd arrays inside this one.
+    pub fn write_buffer(&mut self, data: &[u8]) -> Result<usize> {
+        self.write_sg_buffer(data, None)
+    }
+
+    /// Write `data` as a buffer object embedded in the buffer at object
+    /// index `parent` (AOSP libhwbinder `Parcel::writeEmbeddedBuffer`).
+    ///
+    /// The driver patches the pointer slot at `parent_offset` inside the
+    /// receiver's copy of the parent so it points at this buffer. Fixups
+    /// must be written in increasing `parent_offset` order per parent, as
+    /// the kernel enforces.
+    pub fn write_embedded_buffer(
+        &mut self,
+        data: &[u8],
+        parent: usize,
+        parent_offset: usize,
+    ) -> Result<usize> {
+        let slot = std::mem::size_of::<binder_uintptr_t>();
+        self.check_sg_parent(parent, parent_offset, slot)?;
+        self.write_sg_buffer(data, Some((parent, parent_offset)))
+    }
+
+    /// Write a `BINDER_TYPE_FDA` object for `num_fds` `u32` fds stored at
+    /// `parent_offset` in the buffer at object index `parent` (AOSP
+    /// libhwbinder `Parcel::writeEmbeddedNativeHandle` fd part).
+    ///
+    /// The driver installs each fd in the receiver and rewrites the array in
+    /// place. The fds are not dup'd: the caller keeps them open until the
+    /// parcel has been sent.
+    pub fn write_embedded_fd_array(
+        &mut self,
+        num_fds: usize,
+        parent: usize,
+        parent_offset: usize,
+    ) -> Result<()> {
+        if parent_offset % std::mem::size_of::<u32>() != 0 {
+            log::error!("Parcel: fd array offset {parent_offset} is not u32-aligned");
+            return Err(StatusCode::BadValue);
+        }
+        let len = num_fds
+            .checked_mul(std::mem::size_of::<u32>())
+            .ok_or(StatusCode::BadValue)?;
+        self.check_sg_parent(parent, parent_offset, len)?;
+
+        let data_pos = self.pos;
+        self.write_aligned(&binder_fd_array_object::new(num_fds, parent, parent_offset))?;
+        self.objects.push(data_pos as _);
+        Ok(())
+    }
+
+    /// Write `fds` as a single fd array instead of one FD object per fd.
+    ///
+    /// The fds are dup'd and owned by this parcel until it is dropped. The
+    /// array is laid out as a buffer object followed by a
+    /// `BINDER_TYPE_FDA` object covering the whole buffer; read it back
+    /// with [`Parcel::read_fd_array`].
+    pub fn write_fd_array(&mut self, fds: &[BorrowedFd<'_>]) -> Result<()> {
+        let mut owned = Vec::with_capacity(fds.len());
+        let mut raw = Vec::with_capacity(fds.len() * std::mem::size_of::<u32>());
+        for fd in fds {
+            let dup = rustix::io::fcntl_dupfd_cloexec(fd, 0).map_err(std::io::Error::from)?;
+            raw.extend_from_slice(&(dup.as_raw_fd() as u32).to_ne_bytes());
+            owned.push(dup);
+        }
+
+        let parent = self.write_buffer(&raw)?;
+        self.write_embedded_fd_array(fds.len(), parent, 0)?;
+        self.sg_state().fds.extend(owned);
+        Ok(())
+    }
+
+    /// Read a buffer object written by [`Parcel::write_buffer`].
+    ///
+    /// Returns the object index (for validating embedded children) and the
+    /// buffer contents, borrowed from the transaction buffer.
+    pub fn read_buffer(&mut self) -> Result<(usize, &[u8])> {
+        let (index, obj) = self.read_sg_object(BINDER_TYPE_PTR, read_buffer_object)?;
+        if obj.has_parent() {
+            log::error!("Parcel: expected a top-level buffer, found an embedded one");
+            return Err(StatusCode::BadValue);
+        }
+        Ok((index, self.sg_buffer_slice(&obj)?))
+    }
+
+    /// Read a buffer object written by [`Parcel::write_embedded_buffer`],
+    /// checking that it is embedded at `parent_offset` in the buffer at
+    /// object index `parent`.
+    pub fn read_embedded_buffer(
+        &mut self,
+        parent: usize,
+        parent_offset: usize,
+    ) -> Result<(usize, &[u8])> {
+        let (index, obj) = self.read_sg_object(BINDER_TYPE_PTR, read_buffer_object)?;
+        if !obj.has_parent()
+            || obj.parent != parent as binder_size_t
+            || obj.parent_offset != parent_offset as binder_size_t
+        {
+            log::error!(
+                "Parcel: buffer {index} is not embedded at {parent}:{parent_offset} ({}:{})",
+                obj.parent,
+                obj.parent_offset
+            );
+            return Err(StatusCode::BadValue);
+        }
+        Ok((index, self.sg_buffer_slice(&obj)?))
+    }
+
+    /// Read an fd array written by [`Parcel::write_embedded_fd_array`],
+    /// checking that it lives at `parent_offset` in the buffer at object
+    /// index `parent`.
+    ///
+    /// The fds are dup'd; the originals belong to the transaction buffer
+    /// and are closed by the driver when it is freed.
+    pub fn read_embedded_fd_array(
+        &mut self,
+        parent: usize,
+        parent_offset: usize,
+    ) -> Result<Vec<OwnedFd>> {
+        let (_, obj) = self.read_sg_object(BINDER_TYPE_FDA, read_fd_array_object)?;
+        if obj.parent != parent as binder_size_t
+            || obj.parent_offset != parent_offset as binder_size_t
+        {
+            log::error!(
+                "Parcel: fd array is not embedded at {parent}:{parent_offset} ({}:{})",
+                obj.parent,
+                obj.parent_offset
+            );
+            return Err(StatusCode::BadValue);
+        }
+
+        let num_fds = usize::try_from(obj.num_fds).map_err(|_| StatusCode::BadValue)?;
+        let len = num_fds
+            .checked_mul(std::mem::size_of::<u32>())
+            .ok_or(StatusCode::BadValue)?;
+        let parent_obj = self.sg_parent(parent)?;
+        let raw = self
+            .sg_buffer_slice(&parent_obj)?
+            .get(parent_offset..)
+            .and_then(|tail| tail.get(..len))
+            .ok_or(StatusCode::NotEnoughData)?;
+
+        raw.chunks_exact(std::mem::size_of::<u32>())
+            .map(|bytes| {
+                let fd = u32::from_ne_bytes(bytes.try_into().expect("4-byte chunk")) as i32;
+                if fd < 0 {
+                    return Err(StatusCode::BadValue);
+                }
+                // SAFETY: the fd was installed for this transaction by the
+                // driver (or is one of this parcel's own dups) and stays open
+                // until the parcel is dropped; it is only borrowed for the
+                // dup below.
+                let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+                Ok(rustix::io::fcntl_dupfd_cloexec(fd, 0).map_err(std::io::Error::from)?)
+            })
+            .collect()
+    }
+
+    /// Read an fd array written by [`Parcel::write_fd_array`].
+    pub fn read_fd_array(&mut self) -> Result<Vec<OwnedFd>> {
+        let (parent, _) = self.read_buffer()?;
+        self.read_embedded_fd_array(parent, 0)
+    }
+
+    /// Keep `fds` open until this parcel is dropped. Used when the fd
+    /// numbers were already written into a scatter-gather buffer covered by
+    /// an fd array object.
+    pub(crate) fn hold_fds(&mut self, fds: Vec<OwnedFd>) {
+        self.sg_state().fds.extend(fds);
+    }
+
+    /// Write `value` as a NUL-terminated 8-bit string padded to 4 bytes
+    /// (AOSP `Parcel::writeCString`).
+    pub(crate) fn write_cstring(&mut self, value: &str) -> Result<()> {
+        if value.as_bytes().contains(&0) {
+            log::error!("Parcel: C string contains an interior NUL");
+            return Err(StatusCode::BadValue);
+        }
+        let mut bytes = Vec::with_capacity(value.len() + 1);
+        bytes.extend_from_slice(value.as_bytes());
+        bytes.push(0);
+        self.write_aligned_data(&bytes)
+    }
+
+    /// Read a string written by [`Parcel::write_cstring`]
+    /// (AOSP `Parcel::readCString`).
+    pub(crate) fn read_cstring(&mut self) -> Result<String> {
+        let avail = &self.data.as_slice()[self.pos.min(self.data.len())..];
+        let len = avail.iter().position(|&b| b == 0).ok_or_else(|| {
+            log::error!("Parcel: C string is not NUL-terminated");
+            StatusCode::NotEnoughData
+        })?;
+        let bytes = self.read_aligned_data(len + 1)?;
+        String::from_utf8(bytes[..len].to_vec()).map_err(|err| {
+            log::error!("Parcel: C string is not UTF-8: {err}");
+            StatusCode::BadValue
+        })
+    }
+
+    /// Total size the driver must reserve for this parcel's scatter-gather
+    /// buffers (`binder_transaction_data_sg::buffers_size`): each buffer is
+    /// padded to 8 bytes, as the kernel lays them out.
+    pub(crate) fn sg_buffers_size(&self) -> usize {
+        self.sg.as_ref().map_or(0, |sg| {
+            sg.buffers
+                .iter()
+                .map(|b| b.len().next_multiple_of(std::mem::size_of::<u64>()))
+                .sum()
+        })
+    }
+
+    fn sg_state(&mut self) -> &mut SgState {
+        self.sg.get_or_insert_with(Default::default)
+    }
+
+    fn ensure_kernel_parcel(&self) -> Result<()> {
+        // Scatter-gather objects are a kernel driver feature; RPC parcels
+        // have no object table to carry them.
+        #[cfg(feature = "rpc")]
+        if self.rpc.is_some() {
+            return Err(StatusCode::BadType);
+        }
+        Ok(())
+    }
+
+    fn write_sg_buffer(&mut self, data: &[u8], parent: Option<(usize, usize)>) -> Result<usize> {
+        self.ensure_kernel_parcel()?;
+
+        let buffer: Box<[u8]> = data.into();
+        let data_pos = self.pos;
+        self.write_aligned(&binder_buffer_object::new(&buffer, parent))?;
+        self.sg_state().buffers.push(buffer);
+        self.objects.push(data_pos as _);
+        Ok(self.objects.len() - 1)
+    }
+
+    /// The buffer object at table index `parent`.
+    fn sg_parent(&self, parent: usize) -> Result<binder_buffer_object> {
+        let Some(&offset) = self.objects.as_slice().get(parent) else {
+            log::error!("Parcel: no parent object at index {parent}");
+            return Err(StatusCode::BadValue);
+        };
+        if read_object_type(self.data.as_slice(), offset as usize)? != BINDER_TYPE_PTR {
+            log::error!("Parcel: parent object {parent} is not a buffer");
+            return Err(StatusCode::BadType);
+        }
+        read_buffer_object(self.data.as_slice(), offset as usize)
+    }
+
+    /// Checks that `len` bytes at `parent_offset` fit in the parent buffer.
+    fn check_sg_parent(&self, parent: usize, parent_offset: usize, len: usize) -> Result<()> {
+        self.ensure_kernel_parcel()?;
+        let parent_obj = self.sg_parent(parent)?;
+        match parent_offset.checked_add(len) {
+            Some(end) if end as binder_size_t <= parent_obj.length => Ok(()),
+            _ => {
+                log::error!(
+                    "Parcel: {len} bytes at offset {parent_offset} exceed parent buffer of {}",
+                    parent_obj.length
+                );
+                Err(StatusCode::BadValue)
+            }
+        }
+    }
+
+    /// Reads the scatter-gather object of `type_` at the cursor, requiring
+    /// it to be in the object table, and returns its table index.
+    fn read_sg_object<T>(
+        &mut self,
+        type_: u32,
+        read: fn(&[u8], usize) -> Result<T>,
+    ) -> Result<(usize, T)> {
+        self.ensure_kernel_parcel()?;
+
+        let data_pos = self.pos;
+        if read_object_type(self.data.as_slice(), data_pos)? != type_ {
+            log::error!("Parcel: expected object type {type_:08x} at {data_pos}");
+            return Err(StatusCode::BadType);
+        }
+        let Ok(index) = self.objects.as_slice().binary_search(&(data_pos as u64)) else {
+            log::error!("Parcel: unable to find object at index {data_pos}");
+            return Err(StatusCode::BadType);
+        };
+        let obj = read(self.data.as_slice(), data_pos)?;
+        self.pos = data_pos + pad_size(std::mem::size_of::<T>());
+        Ok((index, obj))
+    }
+
+    /// The payload of a received or locally written buffer object.
+    fn sg_buffer_slice(&self, obj: &binder_buffer_object) -> Result<&[u8]> {
+        let len = usize::try_from(obj.length).map_err(|_| StatusCode::BadValue)?;
+        if self.free_buffer.is_none() {
+            // A locally built parcel may only reference its own payloads.
+            return self
+                .sg
+                .iter()
+                .flat_map(|sg| &sg.buffers)
+                .find(|b| b.as_ptr() as binder_uintptr_t == obj.buffer && b.len() == len)
+                .map(|b| &b[..])
+                .ok_or_else(|| {
+                    log::error!("Parcel: buffer object does not reference this parcel");
+                    StatusCode::BadValue
+                });
+        }
+        if len == 0 {
+            return Ok(&[]);
+        }
+        // SAFETY: for a driver-delivered parcel the kernel validated the
+        // object and rewrote `buffer` to point at the scatter-gather copy
+        // inside this transaction's mmap'd buffer, which stays mapped until
+        // `free_buffer` runs in `Drop`; the slice is tied to `&self`.
+        Ok(unsafe { std::slice::from_raw_parts(obj.buffer as *const u8, len) })
+    }
+
     fn release_objects(&self) {
         // An RPC-mode parcel must never run kernel `release()` /
         // `decref_publish` — RPC objects have a different
@@ -1597,7 +2201,7 @@ impl Parcel {
                 log::error!("Parcel: unable to read object at position {pos}");
                 continue;
             };
-            obj.release()
+            obj.release(self.context)
                 .map_err(|e| log::error!("Parcel: unable to release object: {e:?}"))
                 .ok();
         }
@@ -1653,6 +2257,220 @@ impl std::fmt::Debug for Parcel {
     }
 }
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Primitive array elements whose wire layout is their in-memory layout,
+/// so [`Parcel::read_slice_ref`] can hand out a view of the buffer.
+/// `bool`, `char` and `i16`/`u16` are widened to 4 bytes on the wire and
+/// are not included.
+pub trait PodElement: sealed::Sealed + Copy + 'static {}
+
+macro_rules! pod_elements {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+            impl PodElement for $ty {}
+        )*
+    };
+}
+
+pod_elements!(i8, u8, i32, u32, i64, u64, f32, f64);
+
+/// A read-only cursor over a parcel's data, from
+/// [`Parcel::borrowed_reader`]. It only decodes values that can be
+/// borrowed; binder objects and file descriptors still go through the
+/// parcel.
+#[derive(Debug, Clone)]
+pub struct BorrowedReader<'p> {
+    data: &'p [u8],
+    pos: usize,
+}
+
+impl<'p> BorrowedReader<'p> {
+    /// The position of the next read in the parcel's data.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn read_aligned(&mut self, len: usize) -> Result<&'p [u8]> {
+        let aligned = pad_size(len);
+        let avail = self.data.len().saturating_sub(self.pos);
+        if aligned > avail {
+            log::error!("Not enough data to read aligned data.: {aligned} <= {avail}");
+            return Err(StatusCode::NotEnoughData);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += aligned;
+        Ok(bytes)
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        let bytes = self.read_aligned(std::mem::size_of::<i32>())?;
+        Ok(i32::from_ne_bytes(bytes.try_into().expect("4 bytes")))
+    }
+
+    // The bytes of an array written by `write_array`, without the padding.
+    fn slice_span<T: PodElement>(&mut self) -> Result<Option<&'p [u8]>> {
+        let len = self.read_i32()?;
+        if len < -1 {
+            log::error!("Parcel: bad array length: {len}");
+            return Err(StatusCode::UnexpectedNull);
+        }
+        if len == -1 {
+            return Ok(None);
+        }
+        // Same 32-bit overflow guard as `Parcel::read_array`.
+        let (size, _) = checked_array_layout(len, std::mem::size_of::<T>())?;
+        self.read_aligned(size).map(Some)
+    }
+
+    /// See [`Parcel::read_bytes_ref`].
+    pub fn read_bytes_ref(&mut self) -> Result<Option<&'p [u8]>> {
+        self.slice_span::<u8>()
+    }
+
+    /// See [`Parcel::read_slice_ref`].
+    pub fn read_slice_ref<T: PodElement>(&mut self) -> Result<Option<Cow<'p, [T]>>> {
+        let Some(bytes) = self.slice_span::<T>()? else {
+            return Ok(None);
+        };
+        // SAFETY: `PodElement` types are plain integers and floats, valid
+        // for any bit pattern.
+        let (prefix, elems, _) = unsafe { bytes.align_to::<T>() };
+        if prefix.is_empty() && std::mem::size_of_val(elems) == bytes.len() {
+            return Ok(Some(Cow::Borrowed(elems)));
+        }
+
+        let len = bytes.len() / std::mem::size_of::<T>();
+        let mut owned = Vec::<T>::with_capacity(len);
+        // SAFETY: `bytes` holds exactly `len` elements' worth of bytes,
+        // `owned` has room for `len` elements, and every bit pattern is a
+        // valid `T`.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                owned.as_mut_ptr() as *mut u8,
+                bytes.len(),
+            );
+            owned.set_len(len);
+        }
+        Ok(Some(Cow::Owned(owned)))
+    }
+
+    /// See [`Parcel::read_utf16_ref`].
+    pub fn read_utf16_ref(&mut self) -> Result<Option<Utf16Str<'p>>> {
+        let len = self.read_i32()?;
+        if len == -1 {
+            return Ok(None);
+        }
+        if !(0..i32::MAX).contains(&len) {
+            return Err(StatusCode::UnexpectedNull);
+        }
+        // String16 wire = `len + 1` UTF-16 code units (the trailing NUL is
+        // sent too); `checked_array_layout` keeps `(len + 1) * 2` from
+        // wrapping on 32-bit targets.
+        let (byte_count, _) = checked_array_layout(len + 1, std::mem::size_of::<u16>())?;
+        let bytes = self.read_aligned(byte_count)?;
+        Ok(Some(Utf16Str(
+            &bytes[..len as usize * std::mem::size_of::<u16>()],
+        )))
+    }
+
+    /// See [`Parcel::read_str8_ref`].
+    pub fn read_str8_ref(&mut self) -> Result<Option<&'p str>> {
+        let len = self.read_i32()?;
+        if len == -1 {
+            return Ok(None);
+        }
+        if !(0..i32::MAX).contains(&len) {
+            return Err(StatusCode::UnexpectedNull);
+        }
+        let bytes = self.read_aligned(len as usize + 1)?;
+        let (text, nul) = bytes.split_at(len as usize);
+        if nul != [0] {
+            log::error!("Parcel: String8 is not NUL-terminated");
+            return Err(StatusCode::BadValue);
+        }
+        std::str::from_utf8(text).map(Some).map_err(|err| {
+            log::error!("Parcel: String8 is not UTF-8: {err}");
+            StatusCode::BadValue
+        })
+    }
+}
+
+/// A UTF-16 string borrowed from a parcel, without its NUL terminator.
+///
+/// The buffer is only byte-aligned, so code units are decoded on the fly
+/// rather than exposed as `&[u16]`.
+#[derive(Clone, Copy)]
+pub struct Utf16Str<'p>(&'p [u8]);
+
+impl<'p> Utf16Str<'p> {
+    /// Length in UTF-16 code units.
+    pub fn len(&self) -> usize {
+        self.0.len() / std::mem::size_of::<u16>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The raw native-endian UTF-16 bytes.
+    pub fn as_bytes(&self) -> &'p [u8] {
+        self.0
+    }
+
+    /// The UTF-16 code units.
+    pub fn units(&self) -> impl Iterator<Item = u16> + 'p {
+        self.0
+            .chunks_exact(std::mem::size_of::<u16>())
+            .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+    }
+
+    /// The decoded characters; unpaired surrogates are errors.
+    pub fn chars(
+        &self,
+    ) -> impl Iterator<Item = std::result::Result<char, std::char::DecodeUtf16Error>> + 'p {
+        char::decode_utf16(self.units())
+    }
+
+    /// Decode into a `String` in one allocation. Fails with `BadValue` on
+    /// an unpaired surrogate.
+    pub fn decode(&self) -> Result<String> {
+        let mut text = String::with_capacity(self.len());
+        for c in self.chars() {
+            text.push(c.map_err(|err| {
+                log::error!("Utf16Str::decode: {err}");
+                StatusCode::BadValue
+            })?);
+        }
+        Ok(text)
+    }
+}
+
+impl PartialEq<str> for Utf16Str<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.units().eq(other.encode_utf16())
+    }
+}
+
+impl PartialEq<&str> for Utf16Str<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl std::fmt::Debug for Utf16Str<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let text: String = char::decode_utf16(self.units())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+        std::fmt::Debug::fmt(&text, f)
+    }
+}
+
 impl<const N: usize> TryFrom<&mut Parcel> for [u8; N] {
     type Error = StatusCode;
 
@@ -2352,4 +3170,213 @@ mod tests {
             o => panic!("expected Transact, got {o:?}"),
         }
     }
+
+    /// Scatter-gather round trip on a locally built parcel: a top-level
+    /// buffer plus one embedded child read back with their table indices,
+    /// and `buffers_size` padding each payload to 8 bytes as the kernel
+    /// lays them out (5 -> 8, 12 -> 16).
+    #[test]
+    fn sg_buffer_round_trip_and_buffers_size() {
+        let mut parcel = Parcel::new();
+        parcel.write(&7i32).unwrap();
+        let parent = parcel.write_buffer(&[0u8; 12]).unwrap();
+        let child = parcel.write_embedded_buffer(b"hello", parent, 0).unwrap();
+        assert_eq!((parent, child), (0, 1));
+        assert_eq!(parcel.sg_buffers_size(), 16 + 8);
+
+        parcel.set_data_position(0);
+        assert_eq!(parcel.read::<i32>().unwrap(), 7);
+        let (index, data) = parcel.read_buffer().unwrap();
+        assert_eq!((index, data), (parent, &[0u8; 12][..]));
+        let (index, data) = parcel.read_embedded_buffer(parent, 0).unwrap();
+        assert_eq!((index, data), (child, &b"hello"[..]));
+    }
+
+    /// Embedded writes are validated against the parent before anything
+    /// reaches the wire: the pointer slot / fd array must fit inside the
+    /// parent, fd arrays must be u32-aligned, and the parent must be a
+    /// buffer object. On the read side, a child claiming a different parent
+    /// offset is rejected.
+    #[test]
+    fn sg_embedded_objects_validate_parent() {
+        let mut parcel = Parcel::new();
+        let parent = parcel.write_buffer(&[0u8; 8]).unwrap();
+
+        assert_eq!(
+            parcel.write_embedded_buffer(b"x", parent, 4),
+            Err(StatusCode::BadValue)
+        );
+        assert_eq!(
+            parcel.write_embedded_buffer(b"x", 9, 0),
+            Err(StatusCode::BadValue)
+        );
+        assert_eq!(
+            parcel.write_embedded_fd_array(3, parent, 0),
+            Err(StatusCode::BadValue)
+        );
+        assert_eq!(
+            parcel.write_embedded_fd_array(1, parent, 2),
+            Err(StatusCode::BadValue)
+        );
+
+        parcel.write_embedded_fd_array(0, parent, 8).unwrap();
+        assert_eq!(
+            parcel.write_embedded_buffer(b"x", 1, 0),
+            Err(StatusCode::BadType),
+            "an fd array cannot be a parent"
+        );
+
+        let child = parcel.write_embedded_buffer(b"x", parent, 0).unwrap();
+        parcel.set_data_position(0);
+        parcel.read_buffer().unwrap();
+        parcel.read_embedded_fd_array(parent, 8).unwrap();
+        let pos = parcel.data_position();
+        assert_eq!(
+            parcel.read_embedded_buffer(parent, 8).err(),
+            Some(StatusCode::BadValue)
+        );
+        parcel.set_data_position(pos);
+        assert_eq!(parcel.read_embedded_buffer(parent, 0).unwrap().0, child);
+    }
+
+    /// `write_fd_array` dups the fds into a single buffer + FDA pair, and
+    /// `read_fd_array` hands back fresh dups of the same open files.
+    #[test]
+    fn sg_fd_array_round_trip() {
+        use std::os::fd::AsFd;
+        use std::os::unix::fs::MetadataExt;
+
+        let null = std::fs::File::open("/dev/null").unwrap();
+        let this = std::fs::File::open("/dev/zero").unwrap();
+        let mut parcel = Parcel::new();
+        parcel
+            .write_fd_array(&[null.as_fd(), this.as_fd()])
+            .unwrap();
+        assert_eq!(parcel.objects.len(), 2, "one buffer + one FDA object");
+        assert_eq!(parcel.sg_buffers_size(), 8);
+
+        parcel.set_data_position(0);
+        let fds = parcel.read_fd_array().unwrap();
+        assert_eq!(fds.len(), 2);
+        for (fd, orig) in fds.into_iter().zip([&null, &this]) {
+            let got = std::fs::File::from(fd).metadata().unwrap();
+            let want = orig.metadata().unwrap();
+            assert_eq!((got.dev(), got.ino()), (want.dev(), want.ino()));
+        }
+    }
+
+    /// Scatter-gather objects reference the source parcel's buffers and
+    /// address parents by index, so `append_from` refuses to copy them
+    /// rather than emit dangling pointers.
+    #[test]
+    fn append_from_rejects_sg_objects() {
+        let mut src = Parcel::new();
+        src.write_buffer(b"payload").unwrap();
+        let mut dst = Parcel::new();
+        assert_eq!(
+            dst.append_all_from(&mut src),
+            Err(StatusCode::InvalidOperation)
+        );
+        assert_eq!(dst.data_size(), 0, "nothing copied on rejection");
+    }
+
+    #[test]
+    fn borrowed_reads_match_owned_reads() {
+        let blob: Vec<u8> = (0..=255).collect();
+        let longs = vec![1i64, -2, i64::MAX];
+        let mut parcel = Parcel::new();
+        parcel.write(&blob).unwrap();
+        parcel.write("héllo, 世界 🦀").unwrap();
+        parcel.write_str8(Some("utf-8 text")).unwrap();
+        parcel.write(&longs).unwrap();
+        parcel.write(&None::<Vec<u8>>).unwrap();
+        parcel.write(&None::<String>).unwrap();
+        parcel.write_str8(None).unwrap();
+        let end = parcel.data_size();
+
+        parcel.set_data_position(0);
+        assert_eq!(parcel.read_bytes_ref().unwrap(), Some(blob.as_slice()));
+        let text = parcel.read_utf16_ref().unwrap().unwrap();
+        assert_eq!(text, "héllo, 世界 🦀");
+        assert_eq!(text.decode().unwrap(), "héllo, 世界 🦀");
+        assert_eq!(text.len(), "héllo, 世界 🦀".encode_utf16().count());
+        assert_eq!(parcel.read_str8_ref().unwrap(), Some("utf-8 text"));
+        assert_eq!(
+            parcel.read_slice_ref::<i64>().unwrap().as_deref(),
+            Some(longs.as_slice())
+        );
+        assert_eq!(parcel.read_bytes_ref().unwrap(), None);
+        assert!(parcel.read_utf16_ref().unwrap().is_none());
+        assert_eq!(parcel.read_str8_ref().unwrap(), None);
+        assert_eq!(parcel.data_position(), end);
+    }
+
+    #[test]
+    fn byte_slices_are_borrowed() {
+        let mut parcel = Parcel::new();
+        parcel.write(&vec![1i8, -1, 3]).unwrap();
+        parcel.set_data_position(0);
+        let data = parcel.read_slice_ref::<i8>().unwrap().unwrap();
+        assert!(matches!(data, std::borrow::Cow::Borrowed([1, -1, 3])));
+    }
+
+    #[test]
+    fn misaligned_slices_are_copied() {
+        // A leading i32 leaves the i64 payload 4 bytes off an 8-byte
+        // boundary in at least one of the two layouts below.
+        for skew in [0usize, 1] {
+            let mut parcel = Parcel::new();
+            for _ in 0..skew {
+                parcel.write(&0i32).unwrap();
+            }
+            parcel.write(&vec![7i64, 8]).unwrap();
+            parcel.set_data_position(skew * 4);
+            let values = parcel.read_slice_ref::<i64>().unwrap().unwrap();
+            assert_eq!(&*values, &[7, 8]);
+        }
+    }
+
+    #[test]
+    fn borrowed_reader_holds_several_views() {
+        let mut parcel = Parcel::new();
+        parcel.write(&vec![1u8, 2]).unwrap();
+        parcel.write(&5i32).unwrap();
+        parcel.write(&vec![3u8]).unwrap();
+        parcel.set_data_position(0);
+
+        let first = parcel.skip_slice::<u8>().unwrap();
+        assert_eq!(parcel.read::<i32>().unwrap(), 5);
+        let second = parcel.skip_slice::<u8>().unwrap();
+
+        let a = parcel.borrowed_reader(first).read_bytes_ref().unwrap();
+        let b = parcel.borrowed_reader(second).read_bytes_ref().unwrap();
+        assert_eq!((a, b), (Some(&[1u8, 2][..]), Some(&[3u8][..])));
+    }
+
+    #[test]
+    fn borrowed_reads_reject_bad_input() {
+        let mut parcel = Parcel::new();
+        parcel.write(&100i32).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(parcel.read_bytes_ref(), Err(StatusCode::NotEnoughData));
+
+        // A String8 must be NUL-terminated UTF-8.
+        let mut parcel = Parcel::new();
+        parcel.write(&2i32).unwrap();
+        parcel.write_aligned_data(&[0xff, 0xfe, 0]).unwrap();
+        parcel.write(&2i32).unwrap();
+        parcel.write_aligned_data(b"ok!").unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(parcel.read_str8_ref(), Err(StatusCode::BadValue));
+        assert_eq!(parcel.read_str8_ref(), Err(StatusCode::BadValue));
+
+        // An unpaired surrogate fails only when decoded.
+        let mut parcel = Parcel::new();
+        parcel.write(&1i32).unwrap();
+        let [a, b] = 0xd800u16.to_ne_bytes();
+        parcel.write_aligned_data(&[a, b, 0, 0]).unwrap();
+        parcel.set_data_position(0);
+        let text = parcel.read_utf16_ref().unwrap().unwrap();
+        assert_eq!(text.decode(), Err(StatusCode::BadValue));
+    }
 }
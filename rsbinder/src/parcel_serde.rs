@@ -0,0 +1,1378 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! A `serde` data format that encodes into a [`Parcel`].
+//!
+//! Rust-only types that never appear in an AIDL file can cross binder by
+//! deriving `serde::Serialize` / `serde::Deserialize` and using
+//! [`Parcel::write_serde`] / [`Parcel::read_serde`] instead of hand-written
+//! [`Serialize`](crate::Serialize) / [`Deserialize`](crate::Deserialize)
+//! impls. The encoding reuses the layouts from [`parcelable`](crate::parcelable),
+//! so a derived struct is byte-for-byte what an AIDL parcelable with the same
+//! fields would be:
+//!
+//! | serde type                   | wire layout                                              |
+//! |------------------------------|----------------------------------------------------------|
+//! | `bool`, `i8`, `u8`, `i16`    | `i32`                                                    |
+//! | `u16`, `char`                | `u32`                                                    |
+//! | other integers, floats       | native size (`i128` as `u128`)                           |
+//! | `str`                        | String16: `i32` length, UTF-16, NUL, padded              |
+//! | `bytes`, seq of `u8` / `i8`  | `i32` length, packed bytes, padded                       |
+//! | seq, tuple, map              | `i32` length, then each element (key, value)             |
+//! | `Option`                     | `NULL_PARCELABLE_FLAG` or `NON_NULL_PARCELABLE_FLAG`, then the value |
+//! | struct, tuple struct         | `NON_NULL_PARCELABLE_FLAG`, then a sized body            |
+//! | enum                         | `i32` variant index, then the variant's fields inline    |
+//! | unit, unit struct            | nothing                                                  |
+//! | newtype struct               | the inner value                                          |
+//!
+//! Tuples, including serde's encoding of `[u8; N]`, never pack: each byte
+//! takes an `i32` word.
+//!
+//! Struct bodies are sized like parcelables, so a reader tolerates trailing
+//! fields written by a newer peer and stops early on an older one (missing
+//! fields then need `#[serde(default)]`).
+//!
+//! The format is not self-describing: `deserialize_any` and
+//! `deserialize_ignored_any` fail, so untagged / internally tagged enums and
+//! `#[serde(flatten)]` are not supported. `Option<struct>` shares a single
+//! flag word, as `Option<Parcelable>` does.
+
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser;
+
+use crate::error::{Result, StatusCode};
+use crate::parcel::Parcel;
+use crate::parcelable::{NON_NULL_PARCELABLE_FLAG, NULL_PARCELABLE_FLAG};
+
+impl ser::Error for StatusCode {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        log::error!("parcel serde: {msg}");
+        StatusCode::BadValue
+    }
+}
+
+impl de::Error for StatusCode {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        log::error!("parcel serde: {msg}");
+        StatusCode::BadValue
+    }
+}
+
+fn wire_len(len: usize) -> Result<i32> {
+    i32::try_from(len).map_err(|_| {
+        log::error!("parcel serde: length {len} does not fit the wire length word");
+        StatusCode::BadValue
+    })
+}
+
+fn mixed_byte_sequence() -> StatusCode {
+    log::error!("parcel serde: a sequence that starts with bytes cannot hold other values");
+    StatusCode::BadValue
+}
+
+/// Serializes a value into a [`Parcel`]. See the [module docs](self) for
+/// the wire layout.
+pub struct Serializer<'p> {
+    parcel: &'p mut Parcel,
+    // Set by `serialize_some`: the next value writes the non-null flag
+    // before itself (structs fold it into their own flag).
+    some: bool,
+}
+
+impl<'p> Serializer<'p> {
+    pub fn new(parcel: &'p mut Parcel) -> Self {
+        Self {
+            parcel,
+            some: false,
+        }
+    }
+
+    fn begin(&mut self) -> Result<()> {
+        if std::mem::take(&mut self.some) {
+            self.parcel.write(&NON_NULL_PARCELABLE_FLAG)?;
+        }
+        Ok(())
+    }
+
+    // Flag + size placeholder, patched by `end_sized` like `Parcel::sized_write`.
+    fn begin_sized(&mut self) -> Result<usize> {
+        self.some = false;
+        self.parcel.write(&NON_NULL_PARCELABLE_FLAG)?;
+        let start = self.parcel.data_position();
+        self.parcel.write(&0i32)?;
+        Ok(start)
+    }
+
+    fn end_sized(&mut self, start: usize) -> Result<()> {
+        let end = self.parcel.data_position();
+        self.parcel.set_data_position(start);
+        self.parcel.write(&wire_len(end - start)?)?;
+        self.parcel.set_data_position(end);
+        Ok(())
+    }
+
+    // Length placeholder, patched with the element count on `end`.
+    fn begin_len(&mut self) -> Result<usize> {
+        self.begin()?;
+        let pos = self.parcel.data_position();
+        self.parcel.write(&0i32)?;
+        Ok(pos)
+    }
+
+    fn end_len(&mut self, pos: usize, count: usize) -> Result<()> {
+        let end = self.parcel.data_position();
+        self.parcel.set_data_position(pos);
+        self.parcel.write(&wire_len(count)?)?;
+        self.parcel.set_data_position(end);
+        Ok(())
+    }
+
+    fn write_value<S: crate::Serialize + ?Sized>(&mut self, value: &S) -> Result<()> {
+        self.begin()?;
+        self.parcel.write(value)
+    }
+
+    fn variant_index(&mut self, index: u32) -> Result<()> {
+        self.begin()?;
+        self.parcel.write(&(index as i32))
+    }
+}
+
+impl<'a, 'p> ser::Serializer for &'a mut Serializer<'p> {
+    type Ok = ();
+    type Error = StatusCode;
+    type SerializeSeq = Seq<'a, 'p>;
+    type SerializeTuple = Seq<'a, 'p>;
+    type SerializeTupleStruct = Fields<'a, 'p>;
+    type SerializeTupleVariant = Fields<'a, 'p>;
+    type SerializeMap = Map<'a, 'p>;
+    type SerializeStruct = Fields<'a, 'p>;
+    type SerializeStructVariant = Fields<'a, 'p>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.write_value(&(v as u128))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.write_value(&v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.write_value(&(v as u32))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write_value(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_value(v)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.write_value(&NULL_PARCELABLE_FLAG)
+    }
+
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<()> {
+        self.begin()?;
+        self.some = true;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.begin()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.begin()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.variant_index(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.variant_index(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Seq<'a, 'p>> {
+        let len_pos = self.begin_len()?;
+        Ok(Seq {
+            ser: self,
+            len_pos,
+            count: 0,
+            pack: true,
+            bytes: None,
+        })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Seq<'a, 'p>> {
+        let len_pos = self.begin_len()?;
+        Ok(Seq {
+            ser: self,
+            len_pos,
+            count: 0,
+            pack: false,
+            bytes: None,
+        })
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Fields<'a, 'p>> {
+        let start = self.begin_sized()?;
+        Ok(Fields {
+            ser: self,
+            start: Some(start),
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Fields<'a, 'p>> {
+        self.variant_index(variant_index)?;
+        Ok(Fields {
+            ser: self,
+            start: None,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Map<'a, 'p>> {
+        let len_pos = self.begin_len()?;
+        Ok(Map {
+            ser: self,
+            len_pos,
+            count: 0,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Fields<'a, 'p>> {
+        let start = self.begin_sized()?;
+        Ok(Fields {
+            ser: self,
+            start: Some(start),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Fields<'a, 'p>> {
+        self.variant_index(variant_index)?;
+        Ok(Fields {
+            ser: self,
+            start: None,
+        })
+    }
+}
+
+/// [`Serializer`] state for sequences and tuples.
+///
+/// A sequence (not a tuple) whose first element is a `u8` / `i8` is written
+/// packed, like `Vec<u8>`; the bytes are buffered and flushed on `end`.
+pub struct Seq<'a, 'p> {
+    ser: &'a mut Serializer<'p>,
+    len_pos: usize,
+    count: usize,
+    // Only sequences pack; tuples are heterogeneous.
+    pack: bool,
+    bytes: Option<Vec<u8>>,
+}
+
+impl Seq<'_, '_> {
+    fn push_byte(&mut self, byte: u8) -> Result<()> {
+        match &mut self.bytes {
+            Some(bytes) => bytes.push(byte),
+            None if self.pack && self.count == 1 => self.bytes = Some(vec![byte]),
+            // A byte after non-byte elements (e.g. `(String, u8)`) keeps
+            // the element layout.
+            None => self.ser.parcel.write(&byte)?,
+        }
+        Ok(())
+    }
+
+    fn end_seq(self) -> Result<()> {
+        if let Some(bytes) = &self.bytes {
+            self.ser.parcel.write_aligned_data(bytes)?;
+        }
+        self.ser.end_len(self.len_pos, self.count)
+    }
+}
+
+impl ser::SerializeSeq for Seq<'_, '_> {
+    type Ok = ();
+    type Error = StatusCode;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.count += 1;
+        value.serialize(Element { seq: self })
+    }
+
+    fn end(self) -> Result<()> {
+        self.end_seq()
+    }
+}
+
+impl ser::SerializeTuple for Seq<'_, '_> {
+    type Ok = ();
+    type Error = StatusCode;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.end_seq()
+    }
+}
+
+/// [`Serializer`] state for structs and enum variants: fields follow each
+/// other with no per-field framing.
+pub struct Fields<'a, 'p> {
+    ser: &'a mut Serializer<'p>,
+    // Size word position for sized bodies; `None` for inline variant fields.
+    start: Option<usize>,
+}
+
+impl Fields<'_, '_> {
+    fn field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end_fields(self) -> Result<()> {
+        match self.start {
+            Some(start) => self.ser.end_sized(start),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ser::SerializeStruct for Fields<'_, '_> {
+    type Ok = ();
+    type Error = StatusCode;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.end_fields()
+    }
+}
+
+impl ser::SerializeStructVariant for Fields<'_, '_> {
+    type Ok = ();
+    type Error = StatusCode;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.end_fields()
+    }
+}
+
+impl ser::SerializeTupleStruct for Fields<'_, '_> {
+    type Ok = ();
+    type Error = StatusCode;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.end_fields()
+    }
+}
+
+impl ser::SerializeTupleVariant for Fields<'_, '_> {
+    type Ok = ();
+    type Error = StatusCode;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.end_fields()
+    }
+}
+
+/// [`Serializer`] state for maps.
+pub struct Map<'a, 'p> {
+    ser: &'a mut Serializer<'p>,
+    len_pos: usize,
+    count: usize,
+}
+
+impl ser::SerializeMap for Map<'_, '_> {
+    type Ok = ();
+    type Error = StatusCode;
+
+    fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<()> {
+        self.count += 1;
+        key.serialize(&mut *self.ser)
+    }
+
+    fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.ser.end_len(self.len_pos, self.count)
+    }
+}
+
+/// Serializes one sequence element: bytes go to the packed buffer, every
+/// other value is forwarded to the parent [`Serializer`].
+struct Element<'s, 'a, 'p> {
+    seq: &'s mut Seq<'a, 'p>,
+}
+
+macro_rules! forward_element {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<$ret> {
+                if self.seq.bytes.is_some() {
+                    return Err(mixed_byte_sequence());
+                }
+                ser::Serializer::$method(&mut *self.seq.ser, $($arg),*)
+            }
+        )*
+    };
+}
+
+impl<'s, 'p> ser::Serializer for Element<'s, '_, 'p> {
+    type Ok = ();
+    type Error = StatusCode;
+    type SerializeSeq = Seq<'s, 'p>;
+    type SerializeTuple = Seq<'s, 'p>;
+    type SerializeTupleStruct = Fields<'s, 'p>;
+    type SerializeTupleVariant = Fields<'s, 'p>;
+    type SerializeMap = Map<'s, 'p>;
+    type SerializeStruct = Fields<'s, 'p>;
+    type SerializeStructVariant = Fields<'s, 'p>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.seq.push_byte(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        if self.seq.bytes.is_some() || (self.seq.pack && self.seq.count == 1) {
+            self.seq.push_byte(v as u8)
+        } else {
+            self.seq.ser.parcel.write(&v)
+        }
+    }
+
+    forward_element! {
+        serialize_bool(v: bool) -> ();
+        serialize_i16(v: i16) -> ();
+        serialize_i32(v: i32) -> ();
+        serialize_i64(v: i64) -> ();
+        serialize_i128(v: i128) -> ();
+        serialize_u16(v: u16) -> ();
+        serialize_u32(v: u32) -> ();
+        serialize_u64(v: u64) -> ();
+        serialize_u128(v: u128) -> ();
+        serialize_f32(v: f32) -> ();
+        serialize_f64(v: f64) -> ();
+        serialize_char(v: char) -> ();
+        serialize_str(v: &str) -> ();
+        serialize_bytes(v: &[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(name: &'static str) -> ();
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str) -> ();
+        serialize_seq(len: Option<usize>) -> Seq<'s, 'p>;
+        serialize_tuple(len: usize) -> Seq<'s, 'p>;
+        serialize_tuple_struct(name: &'static str, len: usize) -> Fields<'s, 'p>;
+        serialize_tuple_variant(
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            len: usize
+        ) -> Fields<'s, 'p>;
+        serialize_map(len: Option<usize>) -> Map<'s, 'p>;
+        serialize_struct(name: &'static str, len: usize) -> Fields<'s, 'p>;
+        serialize_struct_variant(
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            len: usize
+        ) -> Fields<'s, 'p>;
+    }
+
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<()> {
+        if self.seq.bytes.is_some() {
+            return Err(mixed_byte_sequence());
+        }
+        self.seq.ser.serialize_some(value)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        // Transparent, so `Vec<Wrapper(u8)>` still packs.
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        if self.seq.bytes.is_some() {
+            return Err(mixed_byte_sequence());
+        }
+        self.seq
+            .ser
+            .serialize_newtype_variant(name, index, variant, value)
+    }
+}
+
+/// Deserializes a value from a [`Parcel`] written by [`Serializer`].
+pub struct Deserializer<'p> {
+    parcel: &'p mut Parcel,
+    // Set by `deserialize_option` once it has consumed a non-null flag, so a
+    // struct value does not read a second one.
+    some: bool,
+}
+
+impl<'p> Deserializer<'p> {
+    pub fn new(parcel: &'p mut Parcel) -> Self {
+        Self {
+            parcel,
+            some: false,
+        }
+    }
+
+    fn read_value<D: crate::Deserialize>(&mut self) -> Result<D> {
+        self.some = false;
+        self.parcel.read()
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let len: i32 = self.read_value()?;
+        usize::try_from(len).map_err(|_| {
+            log::error!("parcel serde: negative sequence length {len}");
+            StatusCode::UnexpectedNull
+        })
+    }
+
+    fn read_sized<'de, V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value> {
+        if !std::mem::take(&mut self.some) {
+            let flag: i32 = self.parcel.read()?;
+            if flag != NON_NULL_PARCELABLE_FLAG {
+                log::error!("parcel serde: expected a non-null struct, found flag {flag}");
+                return Err(StatusCode::UnexpectedNull);
+            }
+        }
+
+        let mut value = None;
+        self.parcel.sized_read(|parcel| {
+            let mut de = Deserializer::new(parcel);
+            value = Some(visitor.visit_seq(FieldsAccess {
+                de: &mut de,
+                remaining: len,
+                sized: true,
+            })?);
+            Ok(())
+        })?;
+        value.ok_or(StatusCode::BadValue)
+    }
+}
+
+macro_rules! deserialize_value {
+    ($($method:ident => $visit:ident($ty:ty);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.$visit(self.read_value::<$ty>()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'_> {
+    type Error = StatusCode;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        log::error!("parcel serde: the parcel format is not self-describing");
+        Err(StatusCode::BadValue)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    deserialize_value! {
+        deserialize_bool => visit_bool(bool);
+        deserialize_i8 => visit_i8(i8);
+        deserialize_i16 => visit_i16(i16);
+        deserialize_i32 => visit_i32(i32);
+        deserialize_i64 => visit_i64(i64);
+        deserialize_u8 => visit_u8(u8);
+        deserialize_u16 => visit_u16(u16);
+        deserialize_u32 => visit_u32(u32);
+        deserialize_u64 => visit_u64(u64);
+        deserialize_u128 => visit_u128(u128);
+        deserialize_f32 => visit_f32(f32);
+        deserialize_f64 => visit_f64(f64);
+        deserialize_string => visit_string(String);
+        deserialize_byte_buf => visit_byte_buf(Vec<u8>);
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128(self.read_value::<u128>()? as i128)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let v: u32 = self.read_value()?;
+        let c = char::from_u32(v).ok_or_else(|| {
+            log::error!("parcel serde: invalid char {v:#x}");
+            StatusCode::BadValue
+        })?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.read_value::<i32>()? {
+            NULL_PARCELABLE_FLAG => visitor.visit_none(),
+            NON_NULL_PARCELABLE_FLAG => {
+                self.some = true;
+                visitor.visit_some(self)
+            }
+            flag => {
+                log::error!("parcel serde: bad option flag {flag}");
+                Err(StatusCode::UnexpectedNull)
+            }
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.some = false;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_seq(SeqAccess {
+            de: self,
+            len,
+            index: 0,
+            pack: true,
+            bytes: None,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        let wire = self.read_len()?;
+        if wire != len {
+            log::error!("parcel serde: expected a {len}-tuple, found {wire} elements");
+            return Err(StatusCode::BadValue);
+        }
+        visitor.visit_seq(SeqAccess {
+            de: self,
+            len,
+            index: 0,
+            pack: false,
+            bytes: None,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.read_sized(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let remaining = self.read_len()?;
+        visitor.visit_map(MapAccess {
+            de: self,
+            remaining,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.read_sized(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.some = false;
+        visitor.visit_enum(EnumAccess { de: self })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+}
+
+// Sequence elements; a leading `u8` / `i8` switches to the packed layout.
+struct SeqAccess<'a, 'p> {
+    de: &'a mut Deserializer<'p>,
+    len: usize,
+    index: usize,
+    pack: bool,
+    bytes: Option<std::vec::IntoIter<u8>>,
+}
+
+impl SeqAccess<'_, '_> {
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        if self.pack && self.bytes.is_none() && self.index == 1 {
+            let data = self.de.parcel.read_aligned_data(self.len)?;
+            self.bytes = Some(Vec::from(data).into_iter());
+        }
+        Ok(self
+            .bytes
+            .as_mut()
+            .map(|bytes| bytes.next().unwrap_or_default()))
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, '_> {
+    type Error = StatusCode;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        self.index += 1;
+        seed.deserialize(ElementAccess { seq: self }).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.index)
+    }
+}
+
+struct ElementAccess<'s, 'a, 'p> {
+    seq: &'s mut SeqAccess<'a, 'p>,
+}
+
+macro_rules! forward_element_access {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+                if self.seq.bytes.is_some() {
+                    return Err(mixed_byte_sequence());
+                }
+                de::Deserializer::$method(&mut *self.seq.de, $($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ElementAccess<'_, '_, '_> {
+    type Error = StatusCode;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.seq.next_byte()? {
+            Some(byte) => visitor.visit_u8(byte),
+            None => de::Deserializer::deserialize_u8(&mut *self.seq.de, visitor),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.seq.next_byte()? {
+            Some(byte) => visitor.visit_i8(byte as i8),
+            None => de::Deserializer::deserialize_i8(&mut *self.seq.de, visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_element_access! {
+        deserialize_any();
+        deserialize_ignored_any();
+        deserialize_bool();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+    }
+}
+
+// Struct and variant fields. A sized struct body ends early when an older
+// peer wrote fewer fields.
+struct FieldsAccess<'a, 'p> {
+    de: &'a mut Deserializer<'p>,
+    remaining: usize,
+    sized: bool,
+}
+
+impl<'de> de::SeqAccess<'de> for FieldsAccess<'_, '_> {
+    type Error = StatusCode;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        if self.sized && !self.de.parcel.has_more_data() {
+            // Zero-sized fields (`()`, `PhantomData`) still read; anything
+            // else is missing and left to `#[serde(default)]`.
+            return Ok(seed.deserialize(EndOfBody).ok());
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+// The end of a sized body: only values that occupy no bytes deserialize.
+struct EndOfBody;
+
+impl<'de> de::Deserializer<'de> for EndOfBody {
+    type Error = StatusCode;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(StatusCode::NotEnoughData)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+struct MapAccess<'a, 'p> {
+    de: &'a mut Deserializer<'p>,
+    remaining: usize,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, '_> {
+    type Error = StatusCode;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+struct EnumAccess<'a, 'p> {
+    de: &'a mut Deserializer<'p>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'_, '_> {
+    type Error = StatusCode;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index: i32 = self.de.parcel.read()?;
+        let index = u32::try_from(index).map_err(|_| {
+            log::error!("parcel serde: negative enum variant index {index}");
+            StatusCode::BadValue
+        })?;
+        let variant: de::value::U32Deserializer<StatusCode> = index.into_deserializer();
+        Ok((seed.deserialize(variant)?, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for EnumAccess<'_, '_> {
+    type Error = StatusCode;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(FieldsAccess {
+            de: self.de,
+            remaining: len,
+            sized: false,
+        })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.tuple_variant(fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+    {
+        let mut parcel = Parcel::new();
+        parcel.write_serde(value).unwrap();
+        parcel.set_data_position(0);
+        let out = parcel.read_serde().unwrap();
+        assert!(!parcel.has_more_data(), "trailing data after read_serde");
+        out
+    }
+
+    fn serde_bytes<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+        let mut parcel = Parcel::new();
+        parcel.write_serde(value).unwrap();
+        parcel.data_bytes().to_vec()
+    }
+
+    fn parcel_bytes<T: crate::Serialize + ?Sized>(value: &T) -> Vec<u8> {
+        let mut parcel = Parcel::new();
+        parcel.write(value).unwrap();
+        parcel.data_bytes().to_vec()
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Inner {
+        id: i32,
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Event {
+        Reset,
+        Value(i64),
+        Pair(u8, String),
+        Moved { x: f32, y: f32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Outer {
+        flag: bool,
+        small: i8,
+        wide: u16,
+        c: char,
+        big: i128,
+        blob: Vec<u8>,
+        signed: Vec<i8>,
+        list: Vec<i32>,
+        names: Vec<String>,
+        inner: Inner,
+        maybe: Option<Inner>,
+        nothing: Option<Inner>,
+        nested: Option<Option<i32>>,
+        tuple: (u8, String, u64),
+        array: [u8; 3],
+        map: BTreeMap<String, Vec<u8>>,
+        events: Vec<Event>,
+        unit: (),
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = Outer {
+            flag: true,
+            small: -3,
+            wide: 0xfffe,
+            c: '한',
+            big: -1 << 100,
+            blob: vec![1, 2, 3, 4, 5],
+            signed: vec![-1, 0, 1],
+            list: vec![7, -7],
+            names: vec!["a".into(), String::new(), "binder".into()],
+            inner: Inner {
+                id: 9,
+                name: "inner".into(),
+            },
+            maybe: Some(Inner {
+                id: -1,
+                name: "some".into(),
+            }),
+            nothing: None,
+            nested: Some(None),
+            tuple: (200, "t".into(), u64::MAX),
+            array: [9, 8, 7],
+            map: BTreeMap::from([("k".into(), vec![0xff]), ("empty".into(), vec![])]),
+            events: vec![
+                Event::Reset,
+                Event::Value(i64::MIN),
+                Event::Pair(3, "p".into()),
+                Event::Moved { x: 1.5, y: -2.0 },
+            ],
+            unit: (),
+        };
+        assert_eq!(round_trip(&value), value);
+        assert_eq!(round_trip(&Some(Some(5i32))), Some(Some(5)));
+        assert_eq!(round_trip(&Some(None::<i32>)), Some(None));
+    }
+
+    #[test]
+    fn test_primitive_layouts_match_parcelable() {
+        assert_eq!(serde_bytes(&true), parcel_bytes(&true));
+        assert_eq!(serde_bytes(&-2i8), parcel_bytes(&-2i8));
+        assert_eq!(serde_bytes(&0xabu8), parcel_bytes(&0xabu8));
+        assert_eq!(serde_bytes(&-2i16), parcel_bytes(&-2i16));
+        assert_eq!(serde_bytes(&0xfffeu16), parcel_bytes(&0xfffeu16));
+        assert_eq!(serde_bytes(&1.25f64), parcel_bytes(&1.25f64));
+        assert_eq!(serde_bytes("héllo"), parcel_bytes("héllo"));
+        assert_eq!(serde_bytes(""), parcel_bytes(""));
+        // Byte vectors are packed and padded, not one i32 per element.
+        let blob = vec![1u8, 2, 3, 4, 5];
+        assert_eq!(serde_bytes(&blob), parcel_bytes(&blob));
+        assert_eq!(serde_bytes(&blob).len(), 4 + 8);
+        let signed = vec![-1i8, 2, -3];
+        assert_eq!(serde_bytes(&signed), parcel_bytes(&signed));
+        assert_eq!(
+            serde_bytes(&Vec::<u8>::new()),
+            parcel_bytes(&Vec::<u8>::new())
+        );
+        let list = vec![1i64, -2, 3];
+        assert_eq!(serde_bytes(&list), parcel_bytes(&list));
+        let names = vec!["x".to_owned(), "yz".to_owned()];
+        assert_eq!(serde_bytes(&names), parcel_bytes(&names));
+    }
+
+    #[test]
+    fn test_option_uses_parcelable_flags() {
+        let mut parcel = Parcel::new();
+        parcel.write_serde(&None::<i32>).unwrap();
+        parcel.write_serde(&Some(7i32)).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(parcel.read::<i32>().unwrap(), NULL_PARCELABLE_FLAG);
+        assert_eq!(parcel.read::<i32>().unwrap(), NON_NULL_PARCELABLE_FLAG);
+        assert_eq!(parcel.read::<i32>().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_struct_matches_parcelable_layout() {
+        let value = Inner {
+            id: 42,
+            name: "hub".into(),
+        };
+        let mut expected = Parcel::new();
+        expected.write(&NON_NULL_PARCELABLE_FLAG).unwrap();
+        expected
+            .sized_write(|p| {
+                p.write(&value.id)?;
+                p.write(&value.name)
+            })
+            .unwrap();
+        assert_eq!(serde_bytes(&value), expected.data_bytes());
+        // `Option<struct>` shares the struct's flag word.
+        assert_eq!(serde_bytes(&Some(&value)), expected.data_bytes());
+
+        let mut none = Parcel::new();
+        none.write(&NULL_PARCELABLE_FLAG).unwrap();
+        assert_eq!(serde_bytes(&None::<Inner>), none.data_bytes());
+    }
+
+    #[test]
+    fn test_struct_version_skew() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct V1 {
+            a: i32,
+        }
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct V2 {
+            a: i32,
+            #[serde(default)]
+            b: String,
+            marker: std::marker::PhantomData<u8>,
+        }
+
+        // Newer writer, older reader: trailing fields are skipped.
+        let mut parcel = Parcel::new();
+        parcel
+            .write_serde(&V2 {
+                a: 1,
+                b: "new".into(),
+                marker: std::marker::PhantomData,
+            })
+            .unwrap();
+        parcel.write(&99i32).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(parcel.read_serde::<V1>().unwrap(), V1 { a: 1 });
+        assert_eq!(parcel.read::<i32>().unwrap(), 99);
+
+        // Older writer, newer reader: missing fields take their default.
+        let mut parcel = Parcel::new();
+        parcel.write_serde(&V1 { a: 2 }).unwrap();
+        parcel.write(&99i32).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(
+            parcel.read_serde::<V2>().unwrap(),
+            V2 {
+                a: 2,
+                b: String::new(),
+                marker: std::marker::PhantomData,
+            }
+        );
+        assert_eq!(parcel.read::<i32>().unwrap(), 99);
+    }
+
+    #[test]
+    fn test_rejects_malformed_input() {
+        // Not self-describing.
+        let mut parcel = Parcel::new();
+        parcel.write(&1i32).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(
+            parcel.read_serde::<serde::de::IgnoredAny>().unwrap_err(),
+            StatusCode::BadValue
+        );
+
+        // A null flag where a struct is required.
+        let mut parcel = Parcel::new();
+        parcel.write(&NULL_PARCELABLE_FLAG).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(
+            parcel.read_serde::<Inner>().unwrap_err(),
+            StatusCode::UnexpectedNull
+        );
+
+        // A struct size running past the end of the parcel.
+        let mut parcel = Parcel::new();
+        parcel.write(&NON_NULL_PARCELABLE_FLAG).unwrap();
+        parcel.write(&1024i32).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(
+            parcel.read_serde::<Inner>().unwrap_err(),
+            StatusCode::NotEnoughData
+        );
+
+        // Truncated packed bytes.
+        let mut parcel = Parcel::new();
+        parcel.write(&16i32).unwrap();
+        parcel.write(&0i32).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(
+            parcel.read_serde::<Vec<u8>>().unwrap_err(),
+            StatusCode::NotEnoughData
+        );
+
+        // Tuple arity mismatch and an unknown enum variant.
+        let mut parcel = Parcel::new();
+        parcel.write_serde(&(1i32, 2i32, 3i32)).unwrap();
+        parcel.set_data_position(0);
+        assert!(parcel.read_serde::<(i32, i32)>().is_err());
+
+        let mut parcel = Parcel::new();
+        parcel.write(&17i32).unwrap();
+        parcel.set_data_position(0);
+        assert!(parcel.read_serde::<Event>().is_err());
+    }
+
+    #[test]
+    fn test_mixed_byte_sequence_is_rejected() {
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Mixed {
+            Byte(u8),
+            Word(i32),
+        }
+        let mut parcel = Parcel::new();
+        assert_eq!(
+            parcel
+                .write_serde(&vec![Mixed::Byte(1), Mixed::Word(2)])
+                .unwrap_err(),
+            StatusCode::BadValue
+        );
+        // A byte after other elements keeps the element layout.
+        assert_eq!(round_trip(&("s".to_owned(), 7u8)), ("s".to_owned(), 7));
+    }
+}
@@ -387,6 +387,8 @@ parcelable_struct! {
 
     impl Serialize for binder_transaction_data;
     impl Deserialize for binder_transaction_data;
+
+    impl Deserialize for binder_frozen_state_info;
 }
 
 impl Serialize for String {
@@ -405,44 +407,12 @@ impl SerializeOption for String {
 
 impl DeserializeOption for String {
     fn deserialize_option(parcel: &mut Parcel) -> Result<Option<Self>> {
-        let len = parcel.read::<i32>()?;
-
-        if len == -1 {
-            return Ok(None);
-        }
-
-        if (0..i32::MAX).contains(&len) {
-            // String16 wire = `len + 1` UTF-16 code units (the trailing
-            // NUL terminator is sent too). Route the byte count through
-            // `checked_array_layout` so a hostile near-`i32::MAX` `len`
-            // cannot wrap `(len + 1) * 2` — nor the `+ 3` inside
-            // `read_aligned_data`'s `pad_size` — past `usize::MAX` on
-            // 32-bit targets (armv7 Android, i686 Linux) and slip past
-            // the bounds check into a slice-index panic (remote DoS).
-            // `len + 1` is in `1..=i32::MAX` here, and 64-bit is
-            // byte-identical to the previous unchecked arithmetic.
-            let (byte_count, _) =
-                crate::parcel::checked_array_layout(len + 1, std::mem::size_of::<u16>())?;
-            let data = parcel.read_aligned_data(byte_count)?;
-            // `data` is borrowed from the parcel's `Vec<u8>` whose base is
-            // only 1-byte aligned, so reinterpreting it as `&[u16]` via
-            // `from_raw_parts(_ as *const u16, _)` would construct an
-            // under-aligned reference (UB; Miri-detectable). Copy the bytes
-            // into an aligned `Vec<u16>` instead — mirroring the
-            // `chunks_exact` copy used by `read_array_char`.
-            let u16_data: Vec<u16> = data[..len as usize * std::mem::size_of::<u16>()]
-                .chunks_exact(std::mem::size_of::<u16>())
-                .map(|c| u16::from_ne_bytes([c[0], c[1]]))
-                .collect();
-            let res = String::from_utf16(&u16_data).map_err(|e| {
-                log::error!("Deserialize for Option<String16>: {e}");
-                StatusCode::BadValue
-            })?;
-
-            return Ok(Some(res));
-        }
-
-        Err(StatusCode::UnexpectedNull)
+        // Decode straight from the transaction buffer: one allocation for
+        // the `String`, no intermediate `Vec<u16>`.
+        parcel
+            .read_utf16_ref()?
+            .map(|text| text.decode())
+            .transpose()
     }
 }
 
@@ -491,6 +461,7 @@ impl SerializeOption for SIBinder {
 
         match this {
             Some(binder) => {
+                check_binder_context(binder, parcel)?;
                 parcel.write::<flat_binder_object>(&binder.into())?;
                 if crate::sdk_at_least(30) {
                     parcel.write::<i32>(&binder.stability().into())?;
@@ -549,45 +520,72 @@ impl DeserializeOption for SIBinder {
             Stability::Local.into()
         };
 
-        match flat.header_type() {
-            BINDER_TYPE_BINDER => {
-                // Receiving BINDER_TYPE_BINDER means the kernel routed
-                // a binder back to its original publisher (us).
-                // Cross-process binder transfers reach receivers as
-                // BINDER_TYPE_HANDLE — the kernel only emits
-                // BINDER_TYPE_BINDER on the publisher loopback path.
-                // Look up the id in our sidecar table; an unknown id
-                // here would mean either (a) a kernel bug surfacing a
-                // BINDER_TYPE_BINDER we never published, or (b) the
-                // entry was already torn down (shouldn't happen
-                // because the round-trip ride keeps `kernel_refs > 0`
-                // via the receiving process's outstanding handle).
-                // Either way it's an integrity error → DeadObject.
-                let id = flat.pointer();
-                if id != 0 {
-                    let arc = ProcessState::as_self().lookup_native(id).ok_or_else(|| {
-                        log::error!("BINDER_TYPE_BINDER for unknown native id {id}");
-                        StatusCode::DeadObject
-                    })?;
-                    Ok(Some(SIBinder::from_arc(arc)))
-                } else {
-                    Ok(None)
-                }
-            }
+        binder_from_flat(&flat, stability, parcel.context())
+    }
+}
 
-            BINDER_TYPE_HANDLE => {
-                let res = ProcessState::as_self()
-                    .strong_proxy_for_handle_stability(flat.handle(), stability.try_into()?)?;
-                Ok(Some(res))
+/// Fail with `BadValue` if `binder` is a proxy of another binder context
+/// than `parcel`'s: its handle means nothing to the parcel's driver.
+pub(crate) fn check_binder_context(binder: &SIBinder, parcel: &Parcel) -> Result<()> {
+    match binder.as_proxy() {
+        Some(proxy) if !ProcessState::same_context(proxy.context(), parcel.context()) => {
+            log::error!(
+                "A proxy of another binder context written into a parcel: handle {}",
+                proxy.handle()
+            );
+            Err(StatusCode::BadValue)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resolve a `flat_binder_object` read from a kernel parcel into the
+/// binder it names. `stability` is the wire stability word that followed
+/// the object, or `Stability::Local` when the protocol carries none;
+/// `context` is the parcel's binder context, which a handle belongs to.
+pub(crate) fn binder_from_flat(
+    flat: &flat_binder_object,
+    stability: i32,
+    context: Option<&'static ProcessState>,
+) -> Result<Option<SIBinder>> {
+    match flat.header_type() {
+        BINDER_TYPE_BINDER => {
+            // Receiving BINDER_TYPE_BINDER means the kernel routed
+            // a binder back to its original publisher (us).
+            // Cross-process binder transfers reach receivers as
+            // BINDER_TYPE_HANDLE — the kernel only emits
+            // BINDER_TYPE_BINDER on the publisher loopback path.
+            // Look up the id in our sidecar table; an unknown id
+            // here would mean either (a) a kernel bug surfacing a
+            // BINDER_TYPE_BINDER we never published, or (b) the
+            // entry was already torn down (shouldn't happen
+            // because the round-trip ride keeps `kernel_refs > 0`
+            // via the receiving process's outstanding handle).
+            // Either way it's an integrity error → DeadObject.
+            let id = flat.pointer();
+            if id != 0 {
+                let arc = ProcessState::of(context).lookup_native(id).ok_or_else(|| {
+                    log::error!("BINDER_TYPE_BINDER for unknown native id {id}");
+                    StatusCode::DeadObject
+                })?;
+                Ok(Some(SIBinder::from_arc(arc)))
+            } else {
+                Ok(None)
             }
+        }
 
-            _ => {
-                log::warn!(
-                    "Unknown Binder Type ({}) was delivered.",
-                    flat.header_type()
-                );
-                Err(StatusCode::BadType)
-            }
+        BINDER_TYPE_HANDLE => {
+            let res = ProcessState::of(context)
+                .strong_proxy_for_handle_stability(flat.handle(), stability.try_into()?)?;
+            Ok(Some(res))
+        }
+
+        _ => {
+            log::warn!(
+                "Unknown Binder Type ({}) was delivered.",
+                flat.header_type()
+            );
+            Err(StatusCode::BadType)
         }
     }
 }
@@ -52,7 +52,9 @@ enum ParcelableHolderData {
         parcelable: Arc<dyn AnyParcelable>,
         name: String,
     },
-    Parcel(Parcel),
+    /// Raw data not yet unmarshalled; boxed so an empty or unmarshalled
+    /// holder does not carry a whole `Parcel` inline.
+    Parcel(Box<Parcel>),
 }
 
 /// A type-erased container for any parcelable object.
@@ -332,7 +334,8 @@ impl Parcelable for ParcelableHolder {
         *self
             .data
             .get_mut()
-            .expect("Parcelable holder lock poisoned") = ParcelableHolderData::Parcel(new_parcel);
+            .expect("Parcelable holder lock poisoned") =
+            ParcelableHolderData::Parcel(Box::new(new_parcel));
 
         // `append_from` checks whether `data_size` overflows
         // `parcel` and returns `BAD_VALUE` if that happens. We also
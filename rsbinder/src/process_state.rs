@@ -1,13 +1,16 @@
 // Copyright 2022 Jeff Kim <hiking90@gmail.com>
 // SPDX-License-Identifier: Apache-2.0
 
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fs::File;
+use std::marker::PhantomData;
 use std::os::raw::c_void;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
-use std::sync::{self, Arc, OnceLock, RwLock};
+use std::sync::{self, Arc, LazyLock, OnceLock, RwLock};
 use std::thread;
+use std::time::Duration;
 
 use crate::{binder::*, error::*, proxy::*, sys::binder, thread_state};
 
@@ -296,8 +299,75 @@ pub enum CallRestriction {
     FatalIfNotOneway,
 }
 
+/// A oneway call the driver flagged with `BR_ONEWAY_SPAM_SUSPECT`,
+/// passed to the [`OnewaySpamCallback`].
+///
+/// The driver flags the call that finds the target's async buffer more
+/// than 80% full with more than 50 pending transactions (or 1/4 of the
+/// buffer) from this process. The call itself was still delivered.
+#[derive(Debug)]
+pub struct OnewaySpamSuspect {
+    /// Handle of the target proxy.
+    pub handle: u32,
+    /// Interface descriptor of the target proxy.
+    pub descriptor: String,
+    /// Transaction code of the flagged call.
+    pub code: TransactionCode,
+    /// Where the flagged call was made from, if enabled with
+    /// `RUST_LIB_BACKTRACE` or `RUST_BACKTRACE`; see
+    /// [`std::backtrace::Backtrace::capture`].
+    pub backtrace: std::backtrace::Backtrace,
+}
+
+/// Called on the calling thread, after the flagged oneway call returns.
+/// See [`ProcessState::set_oneway_spam_callback`].
+pub type OnewaySpamCallback = Arc<dyn Fn(&OnewaySpamSuspect) + Send + Sync>;
+
+/// An outgoing kernel transaction passed to the
+/// [`LargeTransactionCallback`]: either one of at least
+/// [`ProcessState::large_transaction_threshold`] bytes, reported before it
+/// is sent, or one the driver rejected because the receiver's buffer was
+/// exhausted.
+#[derive(Debug)]
+pub struct LargeTransaction {
+    /// Handle of the target proxy.
+    pub handle: u32,
+    /// Interface descriptor of the target proxy.
+    pub descriptor: String,
+    /// AIDL method of the call, when the generated proxy named it (with
+    /// the `tracing` or `metrics` feature).
+    pub method: Option<&'static str>,
+    /// Transaction code of the call.
+    pub code: TransactionCode,
+    /// Transaction flags of the call.
+    pub flags: TransactionFlags,
+    /// Bytes of parcel data sent.
+    pub data_size: usize,
+    /// `None` for the size report; for a rejected call, the driver's
+    /// detail, for which [`thread_state::ExtendedError::is_buffer_exhausted`]
+    /// holds.
+    ///
+    /// The receiver's remaining buffer space is not reported: the driver
+    /// tells the sender only that the transaction did not fit, and the
+    /// free space of another process is visible only to root, through
+    /// binderfs ([`crate::binderfs::logs::ProcStats::free_async_space`]).
+    pub failure: Option<thread_state::ExtendedError>,
+    /// Where the call was made from, if enabled with `RUST_LIB_BACKTRACE`
+    /// or `RUST_BACKTRACE`; see [`std::backtrace::Backtrace::capture`].
+    /// Capturing one on every large transaction is costly, so it is off
+    /// unless asked for.
+    pub backtrace: std::backtrace::Backtrace,
+}
+
+/// Called on the calling thread: before sending a large transaction, and
+/// after one failed for lack of buffer space.
+/// See [`ProcessState::set_large_transaction_callback`].
+pub type LargeTransactionCallback = Arc<dyn Fn(&LargeTransaction) + Send + Sync>;
+
 const DEFAULT_MAX_BINDER_THREADS: u32 = 15;
-const DEFAULT_ENABLE_ONEWAY_SPAM_DETECTION: u32 = 1;
+pub(crate) const DEFAULT_ENABLE_ONEWAY_SPAM_DETECTION: bool = true;
+/// AOSP `BpBinder`'s `LOG_TRANSACTIONS_OVER_SIZE`.
+const DEFAULT_LARGE_TRANSACTION_THRESHOLD: usize = 300 * 1024;
 
 struct MemoryMap {
     ptr: *mut c_void,
@@ -321,7 +391,7 @@ unsafe impl Send for MemoryMap {}
 pub struct ProcessState {
     max_threads: u32,
     driver_name: PathBuf,
-    driver: Arc<File>,
+    driver: Driver,
     mmap: RwLock<MemoryMap>,
     context_manager: RwLock<Option<SIBinder>>,
     handle_to_proxy: RwLock<HashMap<u32, CacheEntry>>,
@@ -329,20 +399,14 @@ pub struct ProcessState {
     /// exactly once per case-(a) cache insertion (i.e. per fresh
     /// `BC_INCREFS` pin). Wrap-around is not a practical concern (u64).
     next_generation: AtomicU64,
-    /// Native binders this process has published, keyed by a
-    /// process-monotonic u64 id encoded in `flat_binder_object.binder`
-    /// (replacing the previous fat-pointer encoding). Lookup resolves
-    /// the id to a live `Arc<dyn IBinder>` for `BR_TRANSACTION` /
-    /// `BR_INCREFS` / `BR_ACQUIRE` / `BR_RELEASE` / `BR_DECREFS` /
-    /// `BR_ATTEMPT_ACQUIRE` and for round-trip
-    /// `BINDER_TYPE_BINDER` deserialization. See
-    /// `PublishedNative` for entry-lifecycle invariants.
-    published_natives: RwLock<HashMap<u64, PublishedNative>>,
-    /// Monotonic id allocator for `published_natives`. u64 wrap-around
-    /// is not a practical concern.
-    next_native_id: AtomicU64,
+    /// [`PUBLISHED_NATIVES`], shared by every context.
+    published_natives: &'static RwLock<HashMap<u64, PublishedNative>>,
     disable_background_scheduling: AtomicBool,
     call_restriction: RwLock<CallRestriction>,
+    oneway_spam_detection: AtomicBool,
+    oneway_spam_callback: RwLock<Option<OnewaySpamCallback>>,
+    large_transaction_threshold: AtomicUsize,
+    large_transaction_callback: RwLock<Option<LargeTransactionCallback>>,
     thread_pool_started: AtomicBool,
     thread_pool_seq: AtomicUsize,
     /// Counts pooled-thread spawns driven by kernel `BR_SPAWN_LOOPER`
@@ -363,6 +427,49 @@ pub struct ProcessState {
     /// serial-test workers that stay alive in the process.
     main_thread_spawned: AtomicUsize,
     pub(crate) current_threads: AtomicUsize,
+    /// See [`Self::freeze_notification_supported`].
+    freeze_notification: OnceLock<bool>,
+}
+
+/// Native binders this process has published, keyed by a
+/// process-monotonic u64 id encoded in `flat_binder_object.binder`
+/// (replacing the previous fat-pointer encoding). Lookup resolves
+/// the id to a live `Arc<dyn IBinder>` for `BR_TRANSACTION` /
+/// `BR_INCREFS` / `BR_ACQUIRE` / `BR_RELEASE` / `BR_DECREFS` /
+/// `BR_ATTEMPT_ACQUIRE` and for round-trip
+/// `BINDER_TYPE_BINDER` deserialization. See
+/// `PublishedNative` for entry-lifecycle invariants.
+///
+/// Process-wide rather than per [`ProcessState`]: a native written into
+/// a parcel under one binder context may be sent through another (a
+/// callback handed from one domain to the other), and each driver's
+/// `BR_*` traffic for it must resolve to the same entry. The counts of
+/// all drivers add up, so the entry lives while any of them holds it.
+static PUBLISHED_NATIVES: LazyLock<RwLock<HashMap<u64, PublishedNative>>> =
+    LazyLock::new(Default::default);
+/// Monotonic id allocator for [`PUBLISHED_NATIVES`]. u64 wrap-around
+/// is not a practical concern.
+static NEXT_NATIVE_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// The binder context of this thread, `None` for the
+    /// [`ProcessState::init`] singleton. Set for the pool threads of a
+    /// [`crate::BinderContext`] and while one is entered.
+    static CURRENT: Cell<Option<&'static ProcessState>> = const { Cell::new(None) };
+}
+
+/// Makes a binder context the current one of this thread until dropped,
+/// restoring the previous one.
+pub(crate) struct EnteredContext {
+    previous: Option<&'static ProcessState>,
+    /// The context is per thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for EnteredContext {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+    }
 }
 
 impl ProcessState {
@@ -374,10 +481,70 @@ impl ProcessState {
     /// Get ProcessState instance.
     /// If ProcessState is not initialized, it will panic.
     /// If you want to initialize ProcessState, use init() or init_default().
+    ///
+    /// Inside [`crate::BinderContext::enter`], and on the context's pool
+    /// threads, this is the context's `ProcessState` instead.
     pub fn as_self() -> &'static ProcessState {
-        Self::instance()
-            .get()
-            .expect("ProcessState is not initialized!")
+        CURRENT.with(Cell::get).unwrap_or_else(|| {
+            Self::instance()
+                .get()
+                .expect("ProcessState is not initialized!")
+        })
+    }
+
+    /// The `ProcessState` of `context`, `None` being the singleton.
+    pub(crate) fn of(context: Option<&'static ProcessState>) -> &'static ProcessState {
+        context.unwrap_or_else(|| {
+            Self::instance()
+                .get()
+                .expect("ProcessState is not initialized!")
+        })
+    }
+
+    /// The context of this thread: `None` for the singleton.
+    pub(crate) fn current() -> Option<&'static ProcessState> {
+        CURRENT.with(Cell::get)
+    }
+
+    /// This `ProcessState` as a context: `None` for the singleton.
+    pub(crate) fn context(&'static self) -> Option<&'static ProcessState> {
+        match Self::instance().get() {
+            Some(singleton) if std::ptr::eq(singleton, self) => None,
+            _ => Some(self),
+        }
+    }
+
+    /// Whether two contexts, as returned by [`Self::current`], are the same.
+    pub(crate) fn same_context(
+        a: Option<&'static ProcessState>,
+        b: Option<&'static ProcessState>,
+    ) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => std::ptr::eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+
+    /// Make `context` (`None`: the singleton) current on this thread.
+    pub(crate) fn enter(context: Option<&'static ProcessState>) -> EnteredContext {
+        EnteredContext {
+            previous: CURRENT.with(|current| current.replace(context)),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Open `driver_name` as an additional binder context, independent of
+    /// the singleton. It stays open for the rest of the process, like the
+    /// singleton: its proxies and pool threads refer to it for good.
+    pub(crate) fn open(
+        driver_name: &str,
+        max_threads: u32,
+    ) -> std::result::Result<&'static ProcessState, Box<dyn std::error::Error>> {
+        Ok(Box::leak(Box::new(Self::inner_init(
+            driver_name,
+            max_threads,
+            DEFAULT_ENABLE_ONEWAY_SPAM_DETECTION,
+        )?)))
     }
 
     /// Whether the kernel-binder `ProcessState` singleton has been
@@ -393,7 +560,7 @@ impl ProcessState {
     ///
     /// [`as_self`]: ProcessState::as_self
     pub fn is_initialized() -> bool {
-        Self::instance().get().is_some()
+        Self::current().is_some() || Self::instance().get().is_some()
     }
 
     pub fn set_call_restriction(&self, call_restriction: CallRestriction) {
@@ -411,6 +578,123 @@ impl ProcessState {
             .expect("Call restriction lock poisoned")
     }
 
+    /// Turn the driver's oneway spam detection on or off, AOSP
+    /// `ProcessState::enableOnewaySpamDetection`. On by default. Fails if
+    /// the driver lacks `BINDER_ENABLE_ONEWAY_SPAM_DETECTION`.
+    pub fn set_oneway_spam_detection(&self, enable: bool) -> Result<()> {
+        self.driver
+            .enable_oneway_spam_detection(enable as u32)
+            .inspect_err(|&e| {
+                log::error!("Binder ioctl(BINDER_ENABLE_ONEWAY_SPAM_DETECTION) failed: {e:?}");
+            })?;
+        self.oneway_spam_detection.store(enable, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Whether oneway spam detection was last requested on.
+    pub fn oneway_spam_detection(&self) -> bool {
+        self.oneway_spam_detection.load(Ordering::Relaxed)
+    }
+
+    /// Install (or with `None`, remove) the callback run for oneway calls
+    /// the driver flags as spam. Without one, flagged calls are logged
+    /// with a backtrace.
+    pub fn set_oneway_spam_callback(&self, callback: Option<OnewaySpamCallback>) {
+        *self
+            .oneway_spam_callback
+            .write()
+            .expect("Oneway spam callback lock poisoned") = callback;
+    }
+
+    /// Report a flagged oneway call to the callback, or log it.
+    pub(crate) fn report_oneway_spam(&self, handle: u32, descriptor: &str, code: TransactionCode) {
+        let callback = self
+            .oneway_spam_callback
+            .read()
+            .expect("Oneway spam callback lock poisoned")
+            .clone();
+        match callback {
+            Some(callback) => callback(&OnewaySpamSuspect {
+                handle,
+                descriptor: descriptor.to_owned(),
+                code,
+                backtrace: std::backtrace::Backtrace::capture(),
+            }),
+            None => {
+                log::error!(
+                    "Process seems to be sending too many oneway calls \
+                     (handle {handle}, {descriptor}, code {code})."
+                );
+                log::error!("{}", std::backtrace::Backtrace::capture());
+            }
+        }
+    }
+
+    /// Set the size, in bytes of parcel data, from which an outgoing
+    /// kernel transaction is reported as large. 300 KiB by default, where
+    /// AOSP `BpBinder` logs "Large outgoing transaction"; the whole
+    /// buffer a process receives transactions into is about 1 MiB, and
+    /// half of it for oneway calls. `usize::MAX` turns the report off.
+    pub fn set_large_transaction_threshold(&self, bytes: usize) {
+        self.large_transaction_threshold
+            .store(bytes, Ordering::Relaxed);
+    }
+
+    /// See [`Self::set_large_transaction_threshold`].
+    pub fn large_transaction_threshold(&self) -> usize {
+        self.large_transaction_threshold.load(Ordering::Relaxed)
+    }
+
+    /// Install (or with `None`, remove) the callback run for large
+    /// outgoing transactions and for transactions the driver rejected
+    /// because the receiver's buffer was exhausted. Without one, both are
+    /// logged.
+    pub fn set_large_transaction_callback(&self, callback: Option<LargeTransactionCallback>) {
+        *self
+            .large_transaction_callback
+            .write()
+            .expect("Large transaction callback lock poisoned") = callback;
+    }
+
+    /// Report a large or rejected transaction to the callback, or log it.
+    pub(crate) fn report_large_transaction(
+        &self,
+        handle: u32,
+        descriptor: &str,
+        code: TransactionCode,
+        flags: TransactionFlags,
+        data: &crate::Parcel,
+        failure: Option<thread_state::ExtendedError>,
+    ) {
+        let callback = self
+            .large_transaction_callback
+            .read()
+            .expect("Large transaction callback lock poisoned")
+            .clone();
+        let method = crate::trace::method(data);
+        let data_size = data.data_size();
+        match callback {
+            Some(callback) => callback(&LargeTransaction {
+                handle,
+                descriptor: descriptor.to_owned(),
+                method,
+                code,
+                flags,
+                data_size,
+                failure,
+                backtrace: std::backtrace::Backtrace::capture(),
+            }),
+            None if failure.is_some() => log::error!(
+                "Transaction of {data_size} bytes failed, the receiver's binder buffer is \
+                 exhausted (handle {handle}, {descriptor}, code {code})."
+            ),
+            None => log::warn!(
+                "Large outgoing transaction of {data_size} bytes, interface descriptor \
+                 {descriptor}, code {code}."
+            ),
+        }
+    }
+
     /// The effective max-threads `inner_init` will store for a requested
     /// value: `0` and any value `>= DEFAULT_MAX_BINDER_THREADS` clamp to
     /// the default. Shared with the [`crate::service::kernel`] re-init
@@ -427,12 +711,29 @@ impl ProcessState {
     fn inner_init(
         driver_name: &str,
         max_threads: u32,
+        oneway_spam_detection: bool,
     ) -> std::result::Result<ProcessState, Box<dyn std::error::Error>> {
         let max_threads = Self::clamp_max_threads(max_threads);
 
+        #[cfg(feature = "fake-driver")]
+        if driver_name == crate::fake_driver::FAKE_BINDER_PATH {
+            let driver = Driver::Fake(crate::fake_driver::FakeDriver::new(max_threads));
+            let mmap = MemoryMap {
+                ptr: std::ptr::null_mut(),
+                size: 0,
+            };
+            return Ok(Self::with_driver(
+                max_threads,
+                driver_name,
+                driver,
+                mmap,
+                oneway_spam_detection,
+            ));
+        }
+
         let driver_name = PathBuf::from(driver_name);
 
-        let driver = open_driver(&driver_name, max_threads)?;
+        let driver = open_driver(&driver_name, max_threads, oneway_spam_detection)?;
 
         let vm_size = (1024 * 1024) - rustix::param::page_size() * 2;
         // let vm_size = std::num::NonZeroUsize::new(vm_size).ok_or("vm_size is zero!")?;
@@ -456,27 +757,47 @@ impl ProcessState {
             (vm_start, vm_size)
         };
 
-        Ok(ProcessState {
+        Ok(Self::with_driver(
             max_threads,
             driver_name,
-            driver: driver.into(),
-            mmap: RwLock::new(MemoryMap {
+            Driver::Kernel(driver.into()),
+            MemoryMap {
                 ptr: mmap.0,
                 size: mmap.1,
-            }),
+            },
+            oneway_spam_detection,
+        ))
+    }
+
+    fn with_driver(
+        max_threads: u32,
+        driver_name: impl Into<PathBuf>,
+        driver: Driver,
+        mmap: MemoryMap,
+        oneway_spam_detection: bool,
+    ) -> ProcessState {
+        ProcessState {
+            max_threads,
+            driver_name: driver_name.into(),
+            driver,
+            mmap: RwLock::new(mmap),
             context_manager: RwLock::new(None),
             handle_to_proxy: RwLock::new(HashMap::new()),
             next_generation: AtomicU64::new(1),
-            published_natives: RwLock::new(HashMap::new()),
-            next_native_id: AtomicU64::new(1),
+            published_natives: &PUBLISHED_NATIVES,
             disable_background_scheduling: AtomicBool::new(false),
             call_restriction: RwLock::new(CallRestriction::None),
+            oneway_spam_detection: AtomicBool::new(oneway_spam_detection),
+            oneway_spam_callback: RwLock::new(None),
+            large_transaction_threshold: AtomicUsize::new(DEFAULT_LARGE_TRANSACTION_THRESHOLD),
+            large_transaction_callback: RwLock::new(None),
             thread_pool_started: AtomicBool::new(false),
             thread_pool_seq: AtomicUsize::new(1),
             kernel_started_threads: AtomicUsize::new(0),
             main_thread_spawned: AtomicUsize::new(0),
             current_threads: AtomicUsize::new(0),
-        })
+            freeze_notification: OnceLock::new(),
+        }
     }
 
     /// Initialize ProcessState with binder path and max threads.
@@ -485,6 +806,20 @@ impl ProcessState {
     pub fn init(
         driver_name: &str,
         max_threads: u32,
+    ) -> std::result::Result<&'static ProcessState, Box<dyn std::error::Error>> {
+        Self::init_with(
+            driver_name,
+            max_threads,
+            DEFAULT_ENABLE_ONEWAY_SPAM_DETECTION,
+        )
+    }
+
+    /// [`Self::init`] with the driver's oneway spam detection turned on or
+    /// off from the start. An existing instance is returned unchanged.
+    pub(crate) fn init_with(
+        driver_name: &str,
+        max_threads: u32,
+        oneway_spam_detection: bool,
     ) -> std::result::Result<&'static ProcessState, Box<dyn std::error::Error>> {
         let cell = Self::instance();
         if let Some(existing) = cell.get() {
@@ -494,7 +829,7 @@ impl ProcessState {
         // call can retry (this is why `get_or_try_init`, still unstable,
         // is avoided). If two threads race here, `get_or_init` keeps the
         // first stored instance and the extra one is dropped.
-        let instance = Self::inner_init(driver_name, max_threads)?;
+        let instance = Self::inner_init(driver_name, max_threads, oneway_spam_detection)?;
         Ok(cell.get_or_init(|| instance))
     }
 
@@ -503,12 +838,16 @@ impl ProcessState {
     /// DEFAULT_BINDER_PATH is "/dev/binderfs/binder".
     pub fn init_default() -> std::result::Result<&'static ProcessState, Box<dyn std::error::Error>>
     {
-        let path = if Path::new(crate::DEFAULT_BINDER_PATH).exists() {
+        Self::init(Self::default_driver_path(), 0)
+    }
+
+    /// The driver [`Self::init_default`] opens.
+    pub(crate) fn default_driver_path() -> &'static str {
+        if Path::new(crate::DEFAULT_BINDER_PATH).exists() {
             crate::DEFAULT_BINDER_PATH
         } else {
             crate::LEGACY_BINDER_PATH
-        };
-        Self::init(path, 0)
+        }
     }
 
     /// Register `binder` as this process's binder context manager
@@ -516,7 +855,11 @@ impl ProcessState {
     /// process can only be the context manager for one object, and the kernel
     /// registers the *process*, not a specific binder — so a second call with
     /// a different binder is logged and the passed binder dropped rather than
-    /// silently believed to have taken effect.
+    /// silently believed to have taken effect. (With the fake driver, a
+    /// simulated process may take over once the previous context manager's
+    /// process was killed.) A binder created with
+    /// [`crate::BinderFeatures::set_requesting_sid`] receives its callers'
+    /// SELinux contexts here too.
     pub fn become_context_manager(
         &self,
         binder: SIBinder,
@@ -526,20 +869,25 @@ impl ProcessState {
             .write()
             .expect("Context manager lock poisoned");
 
-        if context_manager.is_some() {
+        if context_manager.is_some() && !self.driver.context_manager_died() {
             log::warn!(
                 "become_context_manager called again; keeping the first registration (no-op)"
             );
             return Ok(());
         }
 
-        let obj =
-            binder::flat_binder_object::new_binder_with_flags(binder::FLAT_BINDER_FLAG_ACCEPTS_FDS);
+        // Keep the binder's request for its callers' SELinux contexts
+        // (`BinderFeatures::set_requesting_sid`), as AOSP's servicemanager
+        // does, so the context manager can authorize by context.
+        let obj = binder::flat_binder_object::new_binder_with_flags(
+            binder::FLAT_BINDER_FLAG_ACCEPTS_FDS
+                | (binder.local_binder_flags() & binder::FLAT_BINDER_FLAG_TXN_SECURITY_CTX),
+        );
 
-        if binder::set_context_mgr_ext(&self.driver, obj).is_err() {
+        if self.driver.set_context_mgr_ext(obj).is_err() {
             //     android_errorWriteLog(0x534e4554, "121035042");
             // let unused: i32 = 0;
-            if let Err(e) = binder::set_context_mgr(&self.driver, 0) {
+            if let Err(e) = self.driver.set_context_mgr(0) {
                 return Err(format!("Binder ioctl to become context manager failed: {e}").into());
             }
         }
@@ -556,18 +904,18 @@ impl ProcessState {
     }
 
     /// Get binder service manager.
-    pub fn context_object(&self) -> Result<SIBinder> {
+    pub fn context_object(&'static self) -> Result<SIBinder> {
         self.strong_proxy_for_handle(0)
     }
 
     /// Get binder from handle.
     /// If the binder is not cached, it will create a new binder.
-    pub fn strong_proxy_for_handle(&self, handle: u32) -> Result<SIBinder> {
+    pub fn strong_proxy_for_handle(&'static self, handle: u32) -> Result<SIBinder> {
         self.strong_proxy_for_handle_stability(handle, Default::default())
     }
 
     pub(crate) fn strong_proxy_for_handle_stability(
-        &self,
+        &'static self,
         handle: u32,
         stability: Stability,
     ) -> Result<SIBinder> {
@@ -601,6 +949,9 @@ impl ProcessState {
         //   P3: re-acquire write lock. Re-check case (c) and the
         //        case (a)→(b) cross-thread race, undo any spare pin,
         //        and commit the cache entry.
+        // The commands for `handle`, and the proxy made for it, belong to
+        // this context, whichever one the calling thread is in.
+        let _context = Self::enter(self.context());
         let plan = match self.slow_path_p1(handle)? {
             SlowPathDecision::Cached(arc) => return Ok(arc),
             SlowPathDecision::NeedIpc(plan) => plan,
@@ -683,7 +1034,7 @@ impl ProcessState {
             // CallRestriction::None into later calls on this thread.
             let _restore = RestoreCallRestriction(thread_state::call_restriction());
             thread_state::set_call_restriction(CallRestriction::None);
-            if let Err(err) = thread_state::ping_binder(handle) {
+            if let Err(err) = thread_state::ping_binder(self, handle) {
                 if matches!(plan, SlowPathPlan::CaseA) {
                     undo_case_a_pin(handle);
                 }
@@ -691,7 +1042,7 @@ impl ProcessState {
             }
         }
         match plan {
-            SlowPathPlan::CaseA => match thread_state::query_interface(handle) {
+            SlowPathPlan::CaseA => match thread_state::query_interface(self, handle) {
                 Ok(descriptor) => Ok(SlowPathReady::CaseA { descriptor }),
                 Err(err) => {
                     undo_case_a_pin(handle);
@@ -912,6 +1263,41 @@ impl ProcessState {
         Ok(())
     }
 
+    /// Live cached proxy for `handle` together with a `WIBinder` for it,
+    /// the `who` handed to user callbacks. `None` if no `Arc<ProxyHandle>`
+    /// for the handle is alive.
+    pub(crate) fn cached_proxy_with_who(
+        &self,
+        handle: u32,
+    ) -> Option<(Arc<ProxyHandle>, WIBinder)> {
+        // The read guard is dropped before `downgrade`, which re-acquires
+        // the same (non-reentrant) RwLock read.
+        let arc = self
+            .handle_to_proxy
+            .read()
+            .expect("Handle to proxy lock poisoned")
+            .get(&handle)
+            .and_then(|entry| entry.weak.upgrade())?;
+        let sibinder = SIBinder::from_arc(arc.clone() as Arc<dyn IBinder>);
+        let who = SIBinder::downgrade(&sibinder);
+        Some((arc, who))
+    }
+
+    /// Deliver a kernel `BR_FROZEN_BINDER` report for `handle` to the
+    /// cached proxy's freeze callbacks. A report for a proxy that is no
+    /// longer alive is dropped — its callbacks went with it.
+    ///
+    /// # Borrow discipline (R1)
+    ///
+    /// Same as [`Self::send_obituary_for_handle`]: runs user
+    /// `FrozenStateChangeCallback`s, so no `THREAD_STATE` borrow may be held.
+    pub(crate) fn send_frozen_state_for_handle(&self, handle: u32, state: FrozenState) {
+        match self.cached_proxy_with_who(handle) {
+            Some((arc, who)) => arc.on_frozen_state_changed(&who, state),
+            None => log::trace!("Handle {handle} not alive for frozen state {state:?}"),
+        }
+    }
+
     /// Phase 2 of obituary teardown: release the cache pin
     /// (BC_DECREFS). Called from `thread_state::execute_command`'s
     /// BR_DEAD_BINDER arm AFTER `BC_DEAD_BINDER_DONE` has been queued
@@ -988,7 +1374,7 @@ impl ProcessState {
         let dummy_wi = SIBinder::downgrade(&binder_pin);
         arc.inc_weak(&dummy_wi)
             .expect("inc_weak on Arc<dyn IBinder> must not fail");
-        let id = self.next_native_id.fetch_add(1, Ordering::Relaxed);
+        let id = NEXT_NATIVE_ID.fetch_add(1, Ordering::Relaxed);
         map.insert(
             id,
             PublishedNative {
@@ -1201,8 +1587,18 @@ impl ProcessState {
         self.disable_background_scheduling.load(Ordering::Relaxed)
     }
 
-    pub fn driver(&self) -> Arc<File> {
-        self.driver.clone()
+    /// The opened binder device, or `None` when the process state runs on
+    /// the fake driver, which has no device file.
+    pub fn driver(&self) -> Option<&File> {
+        match &self.driver {
+            Driver::Kernel(file) => Some(file),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(_) => None,
+        }
+    }
+
+    pub(crate) fn binder_driver(&self) -> &Driver {
+        &self.driver
     }
 
     /// The binder driver path this process was initialized with. Used by
@@ -1213,12 +1609,38 @@ impl ProcessState {
         &self.driver_name
     }
 
+    /// Whether this process talks HIDL over the `hwbinder` driver node
+    /// (`/dev/hwbinder`, or a binderfs device of that name).
+    ///
+    /// In this mode the transport-level helpers switch to the HIDL wire
+    /// format: descriptor queries and pings go through the `IBase`
+    /// reserved transactions. See [`crate::hidl`].
+    pub fn is_hwbinder(&self) -> bool {
+        self.driver_name.file_name() == Some(std::ffi::OsStr::new("hwbinder"))
+    }
+
     /// The max-threads value this process was initialized with (`0` =
     /// kernel default). See [`Self::driver_name`].
     pub(crate) fn max_threads(&self) -> u32 {
         self.max_threads
     }
 
+    /// Whether the binder driver supports `BC_REQUEST_FREEZE_NOTIFICATION`.
+    /// AOSP `ProcessState::isDriverFeatureEnabled(FREEZE_NOTIFICATION)`,
+    /// which reads `features/freeze_notification` under the binderfs mount;
+    /// here the mount is taken to be the driver node's parent directory, so
+    /// a legacy `/dev/binder` node reports `false`. Probed once per context.
+    pub(crate) fn freeze_notification_supported(&self) -> bool {
+        *self.freeze_notification.get_or_init(|| {
+            let Some(mount) = self.driver_name.parent() else {
+                return false;
+            };
+            std::fs::read_to_string(mount.join("features").join("freeze_notification"))
+                .map(|s| s.trim() == "1")
+                .unwrap_or(false)
+        })
+    }
+
     /// Start the binder thread pool: spawn one worker now and **enable
     /// kernel-driven spawning** for the rest of the process's life.
     ///
@@ -1271,10 +1693,12 @@ impl ProcessState {
         if self.thread_pool_started.load(Ordering::Relaxed) {
             let name = self.make_binder_thread_name();
             log::info!("Spawning new pooled thread, name={name}");
-            match thread::Builder::new()
-                .name(name)
-                .spawn(move || thread_state::join_thread_pool(is_main))
-            {
+            // The pool threads of a context belong to it for good.
+            let context = Self::current();
+            match thread::Builder::new().name(name).spawn(move || {
+                let _context = Self::enter(context);
+                thread_state::join_thread_pool(is_main)
+            }) {
                 Ok(_) => {
                     // Account into the per-origin counter so tests can verify
                     // the `start_thread_pool` spawn contract without racing
@@ -1311,20 +1735,193 @@ impl ProcessState {
             reserved3: 0,
         };
 
-        binder::get_node_info_for_ref(&self.driver, &mut info).inspect_err(|&e| {
-            log::error!("Binder ioctl(BINDER_GET_NODE_INFO_FOR_REF) failed: {e:?}");
-        })?;
+        self.driver
+            .get_node_info_for_ref(&mut info)
+            .inspect_err(|&e| {
+                log::error!("Binder ioctl(BINDER_GET_NODE_INFO_FOR_REF) failed: {e:?}");
+            })?;
         Ok(info.strong_count as usize)
     }
 
     pub fn join_thread_pool() -> Result<()> {
         thread_state::join_thread_pool(true)
     }
+
+    /// Freeze or unfreeze the binder state of process `pid`, AOSP
+    /// `IPCThreadState::freeze`. Call it before freezing the process
+    /// itself (cgroup freezer) and after thawing it.
+    ///
+    /// While frozen, the driver fails new synchronous transactions to
+    /// `pid` with `BR_FROZEN_REPLY` and queues oneway ones. Freezing
+    /// waits up to `timeout` for transactions already in flight to
+    /// finish; if they do not, it fails with [`StatusCode::WouldBlock`]
+    /// (`EAGAIN`) and `pid` is left unfrozen. A kernel without
+    /// `BINDER_FREEZE` answers [`StatusCode::BadValue`] (`EINVAL`).
+    pub fn freeze(&self, pid: u32, enable: bool, timeout: Duration) -> Result<()> {
+        let info = binder::binder_freeze_info {
+            pid,
+            enable: enable as u32,
+            timeout_ms: u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX),
+        };
+        self.driver.freeze(info).inspect_err(|&e| {
+            log::error!("Binder ioctl(BINDER_FREEZE) for pid {pid} failed: {e:?}");
+        })?;
+        Ok(())
+    }
+
+    /// What frozen process `pid` received while frozen, AOSP
+    /// `IPCThreadState::getProcessFreezeInfo`. The kernel clears nothing
+    /// on read; the flags reset when `pid` is frozen again.
+    pub fn frozen_info(&self, pid: u32) -> Result<FrozenInfo> {
+        let mut info = binder::binder_frozen_status_info {
+            pid,
+            sync_recv: 0,
+            async_recv: 0,
+        };
+        self.driver.get_frozen_info(&mut info).inspect_err(|&e| {
+            log::error!("Binder ioctl(BINDER_GET_FROZEN_INFO) for pid {pid} failed: {e:?}");
+        })?;
+        Ok(FrozenInfo::from(info))
+    }
+}
+
+/// Transactions a frozen process received, from [`ProcessState::frozen_info`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrozenInfo {
+    /// A synchronous transaction arrived while frozen (and was failed
+    /// with `BR_FROZEN_REPLY`).
+    pub sync_received: bool,
+    /// A synchronous transaction to the process is still outstanding.
+    /// Only reported by kernels with `TXNS_PENDING` (5.16+).
+    pub sync_pending: bool,
+    /// A oneway transaction arrived while frozen and is queued.
+    pub async_received: bool,
+}
+
+/// `binder_frozen_status_info.sync_recv` bits.
+const FROZEN_SYNC_RECEIVED: u32 = 1 << 0;
+const FROZEN_TXNS_PENDING: u32 = 1 << 1;
+
+impl From<binder::binder_frozen_status_info> for FrozenInfo {
+    fn from(info: binder::binder_frozen_status_info) -> Self {
+        Self {
+            sync_received: info.sync_recv & FROZEN_SYNC_RECEIVED != 0,
+            sync_pending: info.sync_recv & FROZEN_TXNS_PENDING != 0,
+            async_received: info.async_recv != 0,
+        }
+    }
+}
+
+/// The driver a [`ProcessState`] talks to: the kernel binder device, or
+/// the in-process emulation selected by
+/// [`FAKE_BINDER_PATH`](crate::fake_driver::FAKE_BINDER_PATH).
+#[derive(Clone)]
+pub(crate) enum Driver {
+    Kernel(Arc<File>),
+    #[cfg(feature = "fake-driver")]
+    Fake(Arc<crate::fake_driver::FakeDriver>),
+}
+
+type DriverResult = std::result::Result<(), rustix::io::Errno>;
+
+impl Driver {
+    pub(crate) fn write_read(&self, bwr: &mut binder::binder_write_read) -> DriverResult {
+        match self {
+            Driver::Kernel(file) => binder::write_read(file, bwr),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(fake) => fake.write_read(bwr),
+        }
+    }
+
+    fn set_context_mgr_ext(&self, obj: binder::flat_binder_object) -> DriverResult {
+        match self {
+            Driver::Kernel(file) => binder::set_context_mgr_ext(file, obj),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(fake) => fake.set_context_mgr(),
+        }
+    }
+
+    fn set_context_mgr(&self, pid: i32) -> DriverResult {
+        match self {
+            Driver::Kernel(file) => binder::set_context_mgr(file, pid),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(fake) => fake.set_context_mgr(),
+        }
+    }
+
+    /// Whether the context manager registered through this driver died,
+    /// so another process of the fake driver may take its place. A kernel
+    /// context manager belongs to this process, which cannot outlive it.
+    fn context_manager_died(&self) -> bool {
+        match self {
+            Driver::Kernel(_) => false,
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(fake) => fake.context_manager_died(),
+        }
+    }
+
+    fn enable_oneway_spam_detection(&self, enable: u32) -> DriverResult {
+        match self {
+            Driver::Kernel(file) => binder::enable_oneway_spam_detection(file, enable),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(_) => Ok(()),
+        }
+    }
+
+    fn get_node_info_for_ref(&self, info: &mut binder::binder_node_info_for_ref) -> DriverResult {
+        match self {
+            Driver::Kernel(file) => binder::get_node_info_for_ref(file, info),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(fake) => fake.get_node_info_for_ref(info),
+        }
+    }
+
+    fn freeze(&self, info: binder::binder_freeze_info) -> DriverResult {
+        match self {
+            Driver::Kernel(file) => binder::freeze(file, info),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(_) => Err(rustix::io::Errno::INVAL),
+        }
+    }
+
+    fn get_frozen_info(&self, info: &mut binder::binder_frozen_status_info) -> DriverResult {
+        match self {
+            Driver::Kernel(file) => binder::get_frozen_info(file, info),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(_) => Err(rustix::io::Errno::INVAL),
+        }
+    }
+
+    pub(crate) fn get_extended_error(
+        &self,
+        ee: &mut binder::binder_extended_error,
+    ) -> DriverResult {
+        match self {
+            Driver::Kernel(file) => binder::get_extended_error(file, ee),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(fake) => {
+                fake.get_extended_error(ee);
+                Ok(())
+            }
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    /// A descriptor to poll for the calling thread's work; see
+    /// `thread_state::poll_fd`.
+    pub(crate) fn poll_fd(&self) -> Result<std::os::fd::OwnedFd> {
+        match self {
+            Driver::Kernel(file) => rustix::io::fcntl_dupfd_cloexec(file, 0).map_err(Into::into),
+            #[cfg(feature = "fake-driver")]
+            Driver::Fake(fake) => fake.poll_fd(),
+        }
+    }
 }
 
 fn open_driver(
     driver: &Path,
     max_threads: u32,
+    oneway_spam_detection: bool,
 ) -> std::result::Result<File, Box<dyn std::error::Error>> {
     let fd = File::options()
         .read(true)
@@ -1353,9 +1950,8 @@ fn open_driver(
         .map_err(|e| format!("Binder ioctl to set max threads failed: {e}"))?;
     log::info!("Binder driver max threads set to {max_threads}");
 
-    let enable = DEFAULT_ENABLE_ONEWAY_SPAM_DETECTION;
-    if let Err(e) = binder::enable_oneway_spam_detection(&fd, enable) {
-        log::warn!("Binder ioctl to enable oneway spam detection failed: {e}")
+    if let Err(e) = binder::enable_oneway_spam_detection(&fd, oneway_spam_detection as u32) {
+        log::warn!("Binder ioctl to set oneway spam detection failed: {e}")
     }
 
     Ok(fd)
@@ -1369,6 +1965,10 @@ impl Drop for ProcessState {
         // `ptr`/`size` are a POD pair a panicking reader cannot leave
         // inconsistent — so unmap anyway rather than leak the mapping.
         let mmap = self.mmap.read().unwrap_or_else(|e| e.into_inner());
+        // The fake driver maps nothing.
+        if mmap.ptr.is_null() {
+            return;
+        }
         // SAFETY: `mmap.ptr`/`mmap.size` are exactly the address and length
         // returned by the `mmap` call in `ProcessState::new`. This runs only
         // in `Drop`, so the mapping is still live and is unmapped exactly
@@ -1385,6 +1985,27 @@ impl Drop for ProcessState {
 mod tests {
     use super::*;
 
+    #[test]
+    fn frozen_info_decodes_status_bits() {
+        let info = |sync_recv, async_recv| {
+            FrozenInfo::from(binder::binder_frozen_status_info {
+                pid: 1,
+                sync_recv,
+                async_recv,
+            })
+        };
+        assert_eq!(info(0, 0), FrozenInfo::default());
+        assert_eq!(
+            info(FROZEN_SYNC_RECEIVED | FROZEN_TXNS_PENDING, 1),
+            FrozenInfo {
+                sync_received: true,
+                sync_pending: true,
+                async_received: true,
+            }
+        );
+        assert!(!info(FROZEN_TXNS_PENDING, 0).sync_received);
+    }
+
     /// Shared init + invariant checks for the two tests that assert a
     /// freshly-initialized `ProcessState`. Deliberately NOT `#[serial]`:
     /// both callers already run inside the `binder` serial section, so
@@ -1431,6 +2052,42 @@ mod tests {
         assert!(process.strong_proxy_for_handle(0).is_ok());
     }
 
+    #[test]
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "android")),
+        ignore = "requires /dev/binder"
+    )]
+    #[serial_test::serial(binder)]
+    fn test_oneway_spam_configuration() {
+        let process = ProcessState::init_default().expect("init_default");
+        assert!(process.oneway_spam_detection());
+        process.set_oneway_spam_detection(false).unwrap();
+        assert!(!process.oneway_spam_detection());
+        process.set_oneway_spam_detection(true).unwrap();
+
+        let seen = Arc::new(sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        process.set_oneway_spam_callback(Some(Arc::new(move |suspect: &OnewaySpamSuspect| {
+            sink.lock()
+                .unwrap()
+                .push((suspect.handle, suspect.descriptor.clone(), suspect.code));
+        })));
+        process.report_oneway_spam(3, "my.IFoo", 7);
+        process.set_oneway_spam_callback(None);
+        process.report_oneway_spam(3, "my.IFoo", 8);
+        assert_eq!(*seen.lock().unwrap(), [(3, "my.IFoo".to_owned(), 7)]);
+    }
+
+    #[test]
+    #[cfg(feature = "fake-driver")]
+    fn oneway_spam_detection_is_configured_at_open() {
+        let path = crate::fake_driver::FAKE_BINDER_PATH;
+        let off = ProcessState::inner_init(path, 0, false).expect("fake driver");
+        assert!(!off.oneway_spam_detection());
+        let on = ProcessState::inner_init(path, 0, true).expect("fake driver");
+        assert!(on.oneway_spam_detection());
+    }
+
     /// N threads racing on the same uncached handle (service manager =
     /// 0) must converge on a single cache entry and a single `Arc`
     /// identity. Exercises the lock-decoupled three-phase slow path's
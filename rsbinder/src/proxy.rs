@@ -9,11 +9,15 @@
 
 use std::any::Any;
 use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
 use std::mem::ManuallyDrop;
-use std::os::fd::IntoRawFd;
+use std::ops::Deref;
+use std::os::fd::{BorrowedFd, IntoRawFd};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{self, Arc, RwLock};
+use std::time::Duration;
 
+use crate::shell_command::{self, IResultReceiver, IShellCallback};
 use crate::{
     binder::*, binder_object::*, error::*, parcel::*, parcelable::DeserializeOption, thread_state,
 };
@@ -63,6 +67,18 @@ enum CachedExtension {
     Weak(WIBinder),
 }
 
+/// Freeze-notification bookkeeping for one proxy. Mirrors AOSP
+/// `BpBinder::FrozenStateChange`: the kernel subscription
+/// (`BC_REQUEST_FREEZE_NOTIFICATION`) exists exactly while `callbacks`
+/// is non-empty.
+#[derive(Default)]
+struct FrozenObservers {
+    callbacks: Vec<sync::Weak<dyn FrozenStateChangeCallback>>,
+    /// Last state reported by `BR_FROZEN_BINDER`; `None` until the
+    /// kernel's initial report after subscribing.
+    state: Option<FrozenState>,
+}
+
 /// Handle for a proxy to a remote binder service.
 ///
 /// Owns exactly **one kernel strong ref** (`BC_ACQUIRE` at construction,
@@ -119,7 +135,18 @@ pub struct ProxyHandle {
     /// atomic load can be `Relaxed` there.
     obituary_sent: AtomicBool,
     recipients: RwLock<Vec<sync::Weak<dyn DeathRecipient>>>,
+    frozen: RwLock<FrozenObservers>,
     extension: RwLock<ExtensionCache>,
+    /// Deadline for synchronous calls; see [`ProxyHandle::set_timeout`].
+    timeout: RwLock<Option<Duration>>,
+    /// The binder context `handle` belongs to, `None` for the
+    /// `ProcessState` singleton. Calls go through its driver from any
+    /// thread.
+    context: Option<&'static crate::ProcessState>,
+    /// Whether calls carry a trace context; see
+    /// [`ProxyHandle::set_trace_propagation`].
+    #[cfg(feature = "tracing")]
+    propagate_trace: AtomicBool,
 }
 
 impl ProxyHandle {
@@ -156,10 +183,31 @@ impl ProxyHandle {
             stability,
             obituary_sent: AtomicBool::new(false),
             recipients: RwLock::new(Vec::new()),
+            frozen: RwLock::new(FrozenObservers::default()),
             extension: RwLock::new(ExtensionCache::NotQueried),
+            timeout: RwLock::new(None),
+            context: crate::ProcessState::current(),
+            #[cfg(feature = "tracing")]
+            propagate_trace: AtomicBool::new(false),
         }))
     }
 
+    /// Make the handle's binder context current for the scope, so the
+    /// commands for `handle` reach the driver that issued it.
+    fn enter_context(&self) -> crate::process_state::EnteredContext {
+        crate::ProcessState::enter(self.context)
+    }
+
+    /// The binder context `handle` belongs to, `None` for the singleton.
+    pub(crate) fn context(&self) -> Option<&'static crate::ProcessState> {
+        self.context
+    }
+
+    /// The `ProcessState` whose handle table `handle` belongs to.
+    pub(crate) fn process_state(&self) -> &'static crate::ProcessState {
+        crate::ProcessState::of(self.context)
+    }
+
     /// Get the underlying binder handle number.
     pub fn handle(&self) -> u32 {
         self.handle
@@ -170,6 +218,12 @@ impl ProxyHandle {
         &self.descriptor
     }
 
+    /// Whether the obituary for this proxy has been delivered; see the
+    /// fast-fail in [`Self::submit_transact`].
+    pub(crate) fn is_dead(&self) -> bool {
+        self.obituary_sent.load(Ordering::Acquire)
+    }
+
     /// Pick the right cache representation for an extension binder.
     ///
     /// Returns `CachedExtension::Weak` only when the extension is a
@@ -190,12 +244,83 @@ impl ProxyHandle {
         CachedExtension::Strong(sib.clone())
     }
 
+    /// Set the deadline for synchronous calls through this proxy, or
+    /// `None` (the default) to wait for replies indefinitely.
+    ///
+    /// A call that gets no reply within `timeout` fails with
+    /// [`StatusCode::TimedOut`]. Its transaction cannot be withdrawn from
+    /// the driver, so the call is made from a helper thread that stays
+    /// with it: the calling thread is free for further calls, and a reply
+    /// that arrives late is freed unread. A nested call the remote side
+    /// makes back into this process while handling it runs on that helper
+    /// rather than on the calling thread. Oneway calls never wait and are
+    /// not affected. While four helpers wait on this object for replies
+    /// whose callers timed out, further calls with a deadline fail with
+    /// [`StatusCode::TimedOut`] without being sent.
+    ///
+    /// A process shares one proxy per remote object, so this applies to
+    /// every holder of the object; for single calls use
+    /// [`Strong::with_timeout`] or [`Self::submit_transact_with_timeout`].
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        *self.timeout.write().expect("proxy timeout lock poisoned") = timeout;
+    }
+
+    /// The deadline set by [`Self::set_timeout`].
+    pub fn timeout(&self) -> Option<Duration> {
+        *self.timeout.read().expect("proxy timeout lock poisoned")
+    }
+
+    /// Send the installed [`crate::trace::TracePropagator`]'s context with
+    /// calls made through this proxy (off by default).
+    ///
+    /// Only enable it for objects served by rsbinder: other binder
+    /// implementations reject the trace-context trailer as excess data.
+    /// A process shares one proxy per remote object, so this applies to
+    /// every holder of the object.
+    #[cfg(feature = "tracing")]
+    pub fn set_trace_propagation(&self, enable: bool) {
+        self.propagate_trace.store(enable, Ordering::Relaxed);
+    }
+
+    /// Whether [`Self::set_trace_propagation`] is on.
+    #[cfg(feature = "tracing")]
+    pub fn trace_propagation(&self) -> bool {
+        self.propagate_trace.load(Ordering::Relaxed)
+    }
+
     /// Submit a transaction to the remote service.
+    ///
+    /// A synchronous call is bounded by the innermost
+    /// [`Strong::with_timeout`] of this thread for this object, else by
+    /// [`Self::timeout`].
     pub fn submit_transact(
         &self,
         code: TransactionCode,
         data: &Parcel,
         flags: TransactionFlags,
+    ) -> Result<Option<Parcel>> {
+        let timeout = crate::deadline::scoped_timeout(self.handle).or_else(|| self.timeout());
+        self.transact(code, data, flags, timeout)
+    }
+
+    /// [`Self::submit_transact`] with a deadline for this call only; see
+    /// [`Self::set_timeout`].
+    pub fn submit_transact_with_timeout(
+        &self,
+        code: TransactionCode,
+        data: &Parcel,
+        flags: TransactionFlags,
+        timeout: Duration,
+    ) -> Result<Option<Parcel>> {
+        self.transact(code, data, flags, Some(timeout))
+    }
+
+    fn transact(
+        &self,
+        code: TransactionCode,
+        data: &Parcel,
+        flags: TransactionFlags,
+        timeout: Option<Duration>,
     ) -> Result<Option<Parcel>> {
         // Fast-fail after obituary: avoid a futile kernel round trip
         // that would only return BR_DEAD_REPLY. Mirrors C++
@@ -207,11 +332,61 @@ impl ProxyHandle {
         if self.obituary_sent.load(Ordering::Acquire) {
             return Err(StatusCode::DeadObject);
         }
-        thread_state::transact(self.handle(), code, data, flags)
+        let _context = self.enter_context();
+        let flags = crate::trace::wire_flags(data, flags);
+        let process = crate::ProcessState::as_self();
+        if data.data_size() >= process.large_transaction_threshold() {
+            process.report_large_transaction(
+                self.handle,
+                self.descriptor(),
+                code,
+                flags,
+                data,
+                None,
+            );
+        }
+        let trace = crate::trace::Transaction::client(
+            crate::trace::KERNEL,
+            self.descriptor(),
+            code,
+            flags,
+            data,
+        );
+        let mut reply = match timeout {
+            Some(timeout) if flags & FLAG_ONEWAY == 0 => {
+                crate::deadline::transact(self.handle(), code, data, flags, timeout)
+            }
+            _ => thread_state::transact(self.handle(), code, data, flags),
+        };
+        match &mut reply {
+            Ok(parcel) => trace.finish(StatusCode::Ok, parcel.as_mut()),
+            Err(err) => trace.finish(*err, None),
+        }
+        if reply.as_ref().err() == Some(&StatusCode::FailedTransaction) {
+            if let Some(failure) = thread_state::last_extended_error()
+                .filter(thread_state::ExtendedError::is_buffer_exhausted)
+            {
+                process.report_large_transaction(
+                    self.handle,
+                    self.descriptor(),
+                    code,
+                    flags,
+                    data,
+                    Some(failure),
+                );
+            }
+        }
+        if thread_state::take_oneway_spam_suspect() {
+            process.report_oneway_spam(self.handle(), self.descriptor(), code);
+        }
+        reply
     }
 
     pub fn prepare_transact(&self, write_header: bool) -> Result<Parcel> {
         let mut data = Parcel::new();
+        data.set_context(self.context);
+        #[cfg(feature = "tracing")]
+        data.set_propagates_trace(self.trace_propagation());
 
         if write_header {
             data.write_interface_token(self.descriptor())?;
@@ -292,6 +467,14 @@ impl ProxyHandle {
             // where the mutex unlock publishes the writes).
             self.obituary_sent.store(true, Ordering::Release);
 
+            // The kernel frees the freeze subscription together with the
+            // `binder_ref` once the cache pin is released, so the local
+            // observers are simply dropped — no `BC_CLEAR_FREEZE_NOTIFICATION`
+            // is queued into the `BR_DEAD_BINDER` handshake. Done after the
+            // store so a racing `add_frozen_state_change_callback` either
+            // sees `obituary_sent` or is drained here.
+            *self.frozen.write().expect("Frozen lock poisoned") = FrozenObservers::default();
+
             snapshot
         };
 
@@ -363,6 +546,59 @@ impl ProxyHandle {
         }
     }
 
+    /// Record a kernel `BR_FROZEN_BINDER` report and notify the
+    /// registered callbacks if the state changed. Mirrors AOSP
+    /// `BpBinder::onFrozenStateChanged`: the first report after
+    /// subscribing is always delivered, later ones only on a transition,
+    /// and callbacks whose `Arc` is gone are pruned. Callbacks run with
+    /// the `frozen` lock released so they may re-enter
+    /// `add_`/`remove_frozen_state_change_callback`.
+    pub(crate) fn on_frozen_state_changed(&self, who: &WIBinder, state: FrozenState) {
+        let snapshot: Vec<Arc<dyn FrozenStateChangeCallback>> = {
+            let mut frozen = self.frozen.write().expect("Frozen lock poisoned");
+            if frozen.callbacks.is_empty() || frozen.state == Some(state) {
+                return;
+            }
+            frozen.state = Some(state);
+            let mut live = Vec::with_capacity(frozen.callbacks.len());
+            frozen.callbacks.retain(|weak| match weak.upgrade() {
+                Some(callback) => {
+                    live.push(callback);
+                    true
+                }
+                None => false,
+            });
+            live
+        };
+        self.dispatch_frozen_callbacks(&snapshot, who, state);
+    }
+
+    /// Invoke `on_state_changed` on each callback, isolating panics the
+    /// same way as [`Self::dispatch_obituary_callbacks`].
+    fn dispatch_frozen_callbacks(
+        &self,
+        callbacks: &[Arc<dyn FrozenStateChangeCallback>],
+        who: &WIBinder,
+        state: FrozenState,
+    ) {
+        for callback in callbacks {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                callback.on_state_changed(who, state);
+            }));
+            if let Err(payload) = result {
+                let msg = payload
+                    .downcast_ref::<&'static str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("<non-string panic payload>");
+                log::error!(
+                    "FrozenStateChangeCallback panicked during on_state_changed for handle {:X}: {msg}",
+                    self.handle,
+                );
+            }
+        }
+    }
+
     pub fn dump<F: IntoRawFd>(&self, fd: F, args: &[String]) -> Result<()> {
         // Fast-fail BEFORE consuming the fd. `submit_transact` would
         // also short-circuit on `obituary_sent`, but by the time we
@@ -387,6 +623,53 @@ impl ProxyHandle {
         self.submit_transact(DUMP_TRANSACTION, &send, FLAG_CLEAR_BUF)?;
         Ok(())
     }
+
+    /// Run a shell command on the remote object (`cmd <service> ...`),
+    /// like AOSP `IBinder::shellCommand`.
+    ///
+    /// `input`, `output` and `error` become the command's standard
+    /// streams; they are borrowed, and the driver dups them into the
+    /// target. The command's result code goes to `result_receiver`, and
+    /// `callback` lets it open files in this process's context. See
+    /// [`crate::shell_command`].
+    pub fn shell_command(
+        &self,
+        input: BorrowedFd<'_>,
+        output: BorrowedFd<'_>,
+        error: BorrowedFd<'_>,
+        args: &[String],
+        callback: Option<&Strong<dyn IShellCallback>>,
+        result_receiver: Option<&Strong<dyn IResultReceiver>>,
+    ) -> Result<()> {
+        let mut send = Parcel::new();
+        shell_command::write_request(
+            &mut send,
+            [input, output, error],
+            args,
+            callback,
+            result_receiver,
+        )?;
+        self.submit_transact(SHELL_COMMAND_TRANSACTION, &send, 0)?;
+        Ok(())
+    }
+
+    /// Ask the target to record every transaction it serves to `fd`
+    /// (AOSP `IBinder::startRecordingBinder`). The target must be a local
+    /// binder of a process that trusts the caller (root or the same uid).
+    /// See [`crate::recorded_transaction`].
+    pub fn start_recording_binder(&self, fd: BorrowedFd<'_>) -> Result<()> {
+        let mut send = Parcel::new();
+        send.write_file_descriptor(fd)?;
+        self.submit_transact(START_RECORDING_TRANSACTION, &send, 0)?;
+        Ok(())
+    }
+
+    /// Stop a recording started with [`Self::start_recording_binder`]
+    /// (AOSP `IBinder::stopRecordingBinder`).
+    pub fn stop_recording_binder(&self) -> Result<()> {
+        self.submit_transact(STOP_RECORDING_TRANSACTION, &Parcel::new(), 0)?;
+        Ok(())
+    }
 }
 
 impl Debug for ProxyHandle {
@@ -416,6 +699,21 @@ impl Drop for ProxyHandle {
         // checkService) re-establishes kernel strong via slow-path case (b);
         // a purely in-process `WIBinder::upgrade()` does not (it is weak and
         // returns DeadObject once strong hits 0 — see `WIBinder::upgrade`).
+        // A live freeze subscription outlives this `Arc` in the kernel
+        // (the cache pin keeps the `binder_ref`), and a later proxy for
+        // the same handle could not re-subscribe — the driver rejects a
+        // second `BC_REQUEST_FREEZE_NOTIFICATION` on one ref. Clear it
+        // here; queued ahead of the BC_RELEASE below.
+        let _context = self.enter_context();
+        let frozen = self.frozen.get_mut().expect("Frozen lock poisoned");
+        if !frozen.callbacks.is_empty() {
+            if let Err(err) = thread_state::clear_freeze_notification(self.handle) {
+                log::error!(
+                    "BC_CLEAR_FREEZE_NOTIFICATION for handle {} failed during Drop: {err:?}",
+                    self.handle
+                );
+            }
+        }
         if let Err(err) = thread_state::dec_strong_handle(self.handle) {
             log::error!(
                 "BC_RELEASE for handle {} failed during Drop: {err:?}",
@@ -507,6 +805,7 @@ impl IBinder for ProxyHandle {
         // `send_obituary` sets the flag and drains recipients between
         // our check and our `recipients.write()` acquisition, causing a
         // recipient registered after death to never fire.
+        let _context = self.enter_context();
         let mut recipients = self.recipients.write().expect("Recipients lock poisoned");
         if self.obituary_sent.load(Ordering::Relaxed) {
             return Err(StatusCode::DeadObject);
@@ -559,6 +858,7 @@ impl IBinder for ProxyHandle {
         // Acquire the lock FIRST, then check `obituary_sent` — same
         // ordering as C++ `BpBinder::unlinkToDeath` (BpBinder.cpp:456
         // `if (mObitsSent)` runs inside `AutoMutex _l(mLock)`).
+        let _context = self.enter_context();
         let mut recipients = self.recipients.write().expect("Recipients lock poisoned");
         if self.obituary_sent.load(Ordering::Relaxed) {
             return Err(StatusCode::DeadObject);
@@ -590,9 +890,81 @@ impl IBinder for ProxyHandle {
         Ok(())
     }
 
+    /// Register a freeze-state callback. The first registration on this
+    /// proxy queues `BC_REQUEST_FREEZE_NOTIFICATION`; later ones only join
+    /// the list and, if the kernel already reported a state, are told it
+    /// immediately (AOSP `BpBinder::addFrozenStateChangeCallback`).
+    fn add_frozen_state_change_callback(
+        &self,
+        callback: sync::Weak<dyn FrozenStateChangeCallback>,
+    ) -> Result<()> {
+        let Some(strong) = callback.upgrade() else {
+            return Err(StatusCode::BadValue);
+        };
+        let _context = self.enter_context();
+        let known_state = {
+            let mut frozen = self.frozen.write().expect("Frozen lock poisoned");
+            // `Acquire` pairs with `send_obituary`'s `Release` store; the
+            // obituary drains `frozen` under this lock after that store,
+            // so a registration passing this check is drained, not leaked.
+            if self.obituary_sent.load(Ordering::Acquire) {
+                return Err(StatusCode::DeadObject);
+            }
+            if frozen.callbacks.is_empty() {
+                if !crate::ProcessState::as_self().freeze_notification_supported() {
+                    return Err(StatusCode::InvalidOperation);
+                }
+                // Same propagate-write / ignore-flush split as
+                // `link_to_death`.
+                thread_state::request_freeze_notification(self.handle())?;
+                let _ = thread_state::flush_commands();
+                frozen.state = None;
+            }
+            frozen.callbacks.push(callback);
+            frozen.state
+        };
+        if let Some(state) = known_state {
+            if let Some((_, who)) =
+                crate::ProcessState::as_self().cached_proxy_with_who(self.handle)
+            {
+                self.dispatch_frozen_callbacks(&[strong], &who, state);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a freeze-state callback. Removes only the first matching
+    /// entry, like `unlink_to_death`; the kernel subscription is cleared
+    /// when the last one goes.
+    fn remove_frozen_state_change_callback(
+        &self,
+        callback: sync::Weak<dyn FrozenStateChangeCallback>,
+    ) -> Result<()> {
+        let _context = self.enter_context();
+        let mut frozen = self.frozen.write().expect("Frozen lock poisoned");
+        if self.obituary_sent.load(Ordering::Acquire) {
+            return Err(StatusCode::DeadObject);
+        }
+        let Some(i) = frozen
+            .callbacks
+            .iter()
+            .position(|c| sync::Weak::ptr_eq(c, &callback))
+        else {
+            return Err(StatusCode::NameNotFound);
+        };
+        frozen.callbacks.remove(i);
+        if frozen.callbacks.is_empty() {
+            frozen.state = None;
+            thread_state::clear_freeze_notification(self.handle())?;
+            let _ = thread_state::flush_commands();
+        }
+        Ok(())
+    }
+
     /// Send a ping transaction to this object
     fn ping_binder(&self) -> Result<()> {
-        thread_state::ping_binder(self.handle())
+        let _context = self.enter_context();
+        thread_state::ping_binder(self.process_state(), self.handle())
     }
 
     fn stability(&self) -> Stability {
@@ -658,6 +1030,45 @@ impl IBinder for ProxyHandle {
     }
 }
 
+/// An interface borrowed with a deadline for its calls; see
+/// [`Strong::with_timeout`].
+pub struct WithTimeout<'a, I: FromIBinder + ?Sized> {
+    interface: &'a Strong<I>,
+    scope: Option<u64>,
+    /// The deadline is scoped to the creating thread's calls.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, I: FromIBinder + ?Sized> WithTimeout<'a, I> {
+    pub(crate) fn new(interface: &'a Strong<I>, timeout: Duration) -> Self {
+        let scope = interface
+            .as_binder()
+            .as_proxy()
+            .map(|proxy| crate::deadline::push_scoped(proxy.handle(), timeout));
+        Self {
+            interface,
+            scope,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<I: FromIBinder + ?Sized> Deref for WithTimeout<'_, I> {
+    type Target = I;
+
+    fn deref(&self) -> &I {
+        self.interface
+    }
+}
+
+impl<I: FromIBinder + ?Sized> Drop for WithTimeout<'_, I> {
+    fn drop(&mut self) {
+        if let Some(scope) = self.scope {
+            crate::deadline::pop_scoped(scope);
+        }
+    }
+}
+
 pub trait Proxy: Sized + Interface {
     /// The Binder interface descriptor string.
     ///
@@ -691,7 +1102,12 @@ mod tests {
             stability: Stability::Local,
             obituary_sent: AtomicBool::new(obituary_sent),
             recipients: RwLock::new(Vec::new()),
+            frozen: RwLock::new(FrozenObservers::default()),
             extension: RwLock::new(ExtensionCache::NotQueried),
+            timeout: RwLock::new(None),
+            context: None,
+            #[cfg(feature = "tracing")]
+            propagate_trace: AtomicBool::new(false),
         })
     }
 
@@ -1145,6 +1561,155 @@ mod tests {
         std::mem::forget(proxy);
     }
 
+    /// Records every `on_state_changed` it receives.
+    struct RecordingFrozenCallback {
+        seen: std::sync::Mutex<Vec<FrozenState>>,
+    }
+    impl FrozenStateChangeCallback for RecordingFrozenCallback {
+        fn on_state_changed(&self, _who: &WIBinder, state: FrozenState) {
+            self.seen.lock().expect("seen lock").push(state);
+        }
+    }
+
+    fn recording_frozen_callback() -> Arc<RecordingFrozenCallback> {
+        Arc::new(RecordingFrozenCallback {
+            seen: std::sync::Mutex::new(Vec::new()),
+        })
+    }
+
+    fn mock_who() -> WIBinder {
+        let strong = SIBinder::new(Arc::new(MockBinder)).expect("SIBinder::new");
+        SIBinder::downgrade(&strong)
+    }
+
+    /// `BR_FROZEN_BINDER` reports reach callbacks only on a state
+    /// transition (the first report after subscribing always counts),
+    /// matching AOSP `BpBinder::onFrozenStateChanged`. The callbacks list
+    /// is populated directly so no ProcessState is needed.
+    #[test]
+    fn test_frozen_state_dispatched_only_on_transition() {
+        let proxy = synthetic_proxy(false);
+        let callback = recording_frozen_callback();
+        let weak: sync::Weak<dyn FrozenStateChangeCallback> =
+            Arc::downgrade(&(callback.clone() as Arc<dyn FrozenStateChangeCallback>));
+        proxy
+            .frozen
+            .write()
+            .expect("frozen lock")
+            .callbacks
+            .push(weak);
+
+        let who = mock_who();
+        proxy.on_frozen_state_changed(&who, FrozenState::Unfrozen);
+        proxy.on_frozen_state_changed(&who, FrozenState::Unfrozen);
+        proxy.on_frozen_state_changed(&who, FrozenState::Frozen);
+        proxy.on_frozen_state_changed(&who, FrozenState::Unfrozen);
+
+        assert_eq!(
+            *callback.seen.lock().expect("seen lock"),
+            vec![
+                FrozenState::Unfrozen,
+                FrozenState::Frozen,
+                FrozenState::Unfrozen
+            ]
+        );
+        std::mem::forget(proxy);
+    }
+
+    /// A callback whose `Arc` was dropped is pruned on the next report,
+    /// and a panicking callback does not starve the ones after it.
+    #[test]
+    fn test_frozen_state_prunes_dead_and_isolates_panic() {
+        struct PanickingFrozenCallback;
+        impl FrozenStateChangeCallback for PanickingFrozenCallback {
+            fn on_state_changed(&self, _who: &WIBinder, _state: FrozenState) {
+                panic!("simulated frozen callback panic");
+            }
+        }
+
+        let proxy = synthetic_proxy(false);
+        let dead: Arc<dyn FrozenStateChangeCallback> = recording_frozen_callback();
+        let panicking: Arc<dyn FrozenStateChangeCallback> = Arc::new(PanickingFrozenCallback);
+        let counting = recording_frozen_callback();
+        let counting_dyn: Arc<dyn FrozenStateChangeCallback> = counting.clone();
+        {
+            let mut frozen = proxy.frozen.write().expect("frozen lock");
+            frozen.callbacks.push(Arc::downgrade(&dead));
+            frozen.callbacks.push(Arc::downgrade(&panicking));
+            frozen.callbacks.push(Arc::downgrade(&counting_dyn));
+        }
+        drop(dead);
+
+        proxy.on_frozen_state_changed(&mock_who(), FrozenState::Frozen);
+
+        assert_eq!(
+            *counting.seen.lock().expect("seen lock"),
+            vec![FrozenState::Frozen]
+        );
+        assert_eq!(
+            proxy.frozen.read().expect("frozen lock").callbacks.len(),
+            2,
+            "dead callback must be pruned"
+        );
+        std::mem::forget(proxy);
+    }
+
+    /// Registration after obituary is rejected before any feature probe
+    /// or IPC, as is a callback whose `Arc` is already gone.
+    #[test]
+    fn test_add_frozen_callback_rejects_dead_proxy_and_dead_weak() {
+        let callback: Arc<dyn FrozenStateChangeCallback> = recording_frozen_callback();
+
+        let dead_proxy = synthetic_proxy(true);
+        assert!(matches!(
+            dead_proxy.add_frozen_state_change_callback(Arc::downgrade(&callback)),
+            Err(StatusCode::DeadObject)
+        ));
+        std::mem::forget(dead_proxy);
+
+        let proxy = synthetic_proxy(false);
+        let gone: Arc<dyn FrozenStateChangeCallback> = recording_frozen_callback();
+        let gone_weak = Arc::downgrade(&gone);
+        drop(gone);
+        assert!(matches!(
+            proxy.add_frozen_state_change_callback(gone_weak),
+            Err(StatusCode::BadValue)
+        ));
+        assert!(proxy
+            .frozen
+            .read()
+            .expect("frozen lock")
+            .callbacks
+            .is_empty());
+        std::mem::forget(proxy);
+    }
+
+    /// Removal mirrors `unlink_to_death`: unknown callbacks are
+    /// `NameNotFound`, and a duplicate registration loses only one entry
+    /// (so no `BC_CLEAR_FREEZE_NOTIFICATION` is queued).
+    #[test]
+    fn test_remove_frozen_callback_single_match() {
+        let proxy = synthetic_proxy(false);
+        let callback: Arc<dyn FrozenStateChangeCallback> = recording_frozen_callback();
+        let weak = Arc::downgrade(&callback);
+
+        assert!(matches!(
+            proxy.remove_frozen_state_change_callback(weak.clone()),
+            Err(StatusCode::NameNotFound)
+        ));
+
+        {
+            let mut frozen = proxy.frozen.write().expect("frozen lock");
+            frozen.callbacks.push(weak.clone());
+            frozen.callbacks.push(weak.clone());
+        }
+        assert!(proxy.remove_frozen_state_change_callback(weak).is_ok());
+        assert_eq!(proxy.frozen.read().expect("frozen lock").callbacks.len(), 1);
+        // Drain locally so `Drop` (suppressed anyway) would not clear.
+        proxy.frozen.write().expect("frozen lock").callbacks.clear();
+        std::mem::forget(proxy);
+    }
+
     /// Verifies `ExtensionCache::Queried` admits both a strong-cache
     /// variant (common case) and a weak-cache variant (self-cycle
     /// case) at the type level. The discrimination protects against
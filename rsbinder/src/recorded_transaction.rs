@@ -0,0 +1,356 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Transaction recording and replay, in AOSP's `RecordedTransaction`
+//! file format (`frameworks/native/libs/binder/RecordedTransaction.cpp`).
+//!
+//! `START_RECORDING_TRANSACTION` hands a native binder an fd; from then on
+//! every transaction it serves is appended to that fd until
+//! `STOP_RECORDING_TRANSACTION`. Start one remotely with
+//! [`crate::ProxyHandle::start_recording_binder`] or in-process with
+//! [`crate::Binder::start_recording`]. The `rsb_record` tool in
+//! `rsbinder-tools` drives both ends.
+//!
+//! A recording is a sequence of chunks, each a `{u32 type, u32 size}`
+//! descriptor, `size` bytes of payload padded to 8, and a `u64` checksum
+//! (the XOR of the preceding 8-byte words of the chunk). A transaction is
+//! a header chunk, the interface name, the data parcel, the reply parcel
+//! and an end chunk. Only parcel bytes are recorded: binder objects and
+//! fds cannot be replayed.
+
+use std::io::{ErrorKind, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::binder::{SIBinder, TransactionCode, FLAG_ONEWAY};
+use crate::error::{Result, StatusCode};
+use crate::parcel::Parcel;
+
+const HEADER_CHUNK: u32 = 1;
+const DATA_PARCEL_CHUNK: u32 = 2;
+const REPLY_PARCEL_CHUNK: u32 = 3;
+const INTERFACE_NAME_CHUNK: u32 = 4;
+const END_CHUNK: u32 = 0x00ff_ffff;
+
+/// Header `version` of transactions recorded from RPC sessions (AOSP
+/// stores the session's protocol version; any non-zero value means RPC).
+#[cfg(feature = "rpc")]
+pub(crate) const RPC_RECORDING_VERSION: u32 = 1;
+
+/// AOSP `kMaxChunkDataSize`.
+const MAX_CHUNK_DATA_SIZE: u32 = 0xffff_fff0;
+/// `sizeof(ChunkDescriptor)`.
+const CHUNK_DESCRIPTOR_SIZE: usize = 8;
+/// `sizeof(TransactionHeader)`.
+const TRANSACTION_HEADER_SIZE: usize = 32;
+
+/// One recorded transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedTransaction {
+    /// Descriptor of the binder that served the transaction.
+    pub interface_name: String,
+    pub code: TransactionCode,
+    pub flags: u32,
+    /// The status the binder returned (`0` for success).
+    pub returned_status: i32,
+    /// `0` for a kernel binder transaction, non-zero for RPC.
+    pub version: u32,
+    pub timestamp_seconds: i64,
+    pub timestamp_nanoseconds: i32,
+    /// Bytes of the data parcel.
+    pub data: Vec<u8>,
+    /// Bytes of the reply parcel (empty for oneway calls).
+    pub reply: Vec<u8>,
+}
+
+impl RecordedTransaction {
+    /// Record a transaction served now.
+    pub(crate) fn new(
+        interface_name: &str,
+        code: TransactionCode,
+        flags: u32,
+        version: u32,
+        data: &Parcel,
+        reply: &Parcel,
+        status: &Result<()>,
+    ) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            interface_name: interface_name.to_owned(),
+            code,
+            flags,
+            returned_status: status.err().map_or(0, i32::from),
+            version,
+            timestamp_seconds: now.as_secs() as i64,
+            timestamp_nanoseconds: now.subsec_nanos() as i32,
+            data: data.data_bytes().to_vec(),
+            reply: reply.data_bytes().to_vec(),
+        }
+    }
+
+    /// The recorded status as a `Result`.
+    pub fn status(&self) -> Result<()> {
+        match StatusCode::from(self.returned_status) {
+            StatusCode::Ok => Ok(()),
+            code => Err(code),
+        }
+    }
+
+    /// Append this transaction to `writer` (AOSP `dumpToFile`).
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        let mut header = Vec::with_capacity(TRANSACTION_HEADER_SIZE);
+        header.extend_from_slice(&self.code.to_ne_bytes());
+        header.extend_from_slice(&self.flags.to_ne_bytes());
+        header.extend_from_slice(&self.returned_status.to_ne_bytes());
+        header.extend_from_slice(&self.version.to_ne_bytes());
+        header.extend_from_slice(&self.timestamp_seconds.to_ne_bytes());
+        header.extend_from_slice(&self.timestamp_nanoseconds.to_ne_bytes());
+        header.extend_from_slice(&0i32.to_ne_bytes());
+
+        let mut buffer = Vec::new();
+        write_chunk(&mut buffer, HEADER_CHUNK, &header)?;
+        write_chunk(
+            &mut buffer,
+            INTERFACE_NAME_CHUNK,
+            self.interface_name.as_bytes(),
+        )?;
+        write_chunk(&mut buffer, DATA_PARCEL_CHUNK, &self.data)?;
+        write_chunk(&mut buffer, REPLY_PARCEL_CHUNK, &self.reply)?;
+        write_chunk(&mut buffer, END_CHUNK, &[])?;
+        // One write per transaction, so concurrent readers of the file
+        // never see half a record.
+        writer.write_all(&buffer)?;
+        Ok(())
+    }
+
+    /// Read the next transaction from `reader` (AOSP `fromFile`).
+    /// Returns `None` at a clean end of input.
+    pub fn read_from(reader: &mut impl Read) -> Result<Option<Self>> {
+        let mut header = None;
+        let mut interface_name = String::new();
+        let mut data = Vec::new();
+        let mut reply = Vec::new();
+        let mut first = true;
+
+        loop {
+            let Some((chunk_type, payload)) = read_chunk(reader, first)? else {
+                return Ok(None);
+            };
+            first = false;
+            match chunk_type {
+                HEADER_CHUNK => {
+                    if payload.len() != TRANSACTION_HEADER_SIZE {
+                        log::error!(
+                            "RecordedTransaction: header chunk of {} bytes",
+                            payload.len()
+                        );
+                        return Err(StatusCode::BadValue);
+                    }
+                    header = Some(payload);
+                }
+                INTERFACE_NAME_CHUNK => {
+                    interface_name = String::from_utf8(payload).map_err(|_| {
+                        log::error!("RecordedTransaction: interface name is not UTF-8");
+                        StatusCode::BadValue
+                    })?;
+                }
+                DATA_PARCEL_CHUNK => data = payload,
+                REPLY_PARCEL_CHUNK => reply = payload,
+                END_CHUNK => break,
+                other => log::info!("RecordedTransaction: skipping unknown chunk {other:#x}"),
+            }
+        }
+
+        let Some(header) = header else {
+            log::error!("RecordedTransaction: transaction without a header chunk");
+            return Err(StatusCode::BadValue);
+        };
+        let u32_at = |at: usize| u32::from_ne_bytes(header[at..at + 4].try_into().unwrap());
+        Ok(Some(Self {
+            interface_name,
+            code: u32_at(0),
+            flags: u32_at(4),
+            returned_status: u32_at(8) as i32,
+            version: u32_at(12),
+            timestamp_seconds: i64::from_ne_bytes(header[16..24].try_into().unwrap()),
+            timestamp_nanoseconds: u32_at(24) as i32,
+            data,
+            reply,
+        }))
+    }
+
+    /// Read every transaction in `reader`.
+    pub fn read_all(reader: &mut impl Read) -> Result<Vec<Self>> {
+        let mut transactions = Vec::new();
+        while let Some(transaction) = Self::read_from(reader)? {
+            transactions.push(transaction);
+        }
+        Ok(transactions)
+    }
+
+    /// Send the recorded data parcel to `binder` again, with the recorded
+    /// code and flags. Returns the reply (`None` for oneway calls) or the
+    /// transaction's error, to compare against [`Self::status`].
+    pub fn replay(&self, binder: &SIBinder) -> Result<Option<Parcel>> {
+        let mut data = Parcel::from_vec(self.data.clone());
+        if let Some(remote) = binder.as_remote() {
+            return remote.submit_transact(self.code, &data, self.flags);
+        }
+        let transactable = binder
+            .as_transactable()
+            .ok_or(StatusCode::InvalidOperation)?;
+        let mut reply = Parcel::new();
+        transactable.transact(self.code, &mut data, &mut reply)?;
+        reply.set_data_position(0);
+        Ok((self.flags & FLAG_ONEWAY == 0).then_some(reply))
+    }
+}
+
+fn write_chunk(buffer: &mut Vec<u8>, chunk_type: u32, payload: &[u8]) -> Result<()> {
+    let size = u32::try_from(payload.len())
+        .ok()
+        .filter(|size| *size <= MAX_CHUNK_DATA_SIZE)
+        .ok_or_else(|| {
+            log::error!(
+                "RecordedTransaction: chunk of {} bytes is too large",
+                payload.len()
+            );
+            StatusCode::BadValue
+        })?;
+    let start = buffer.len();
+    buffer.extend_from_slice(&chunk_type.to_ne_bytes());
+    buffer.extend_from_slice(&size.to_ne_bytes());
+    buffer.extend_from_slice(payload);
+    buffer.resize(buffer.len() + padding8(payload.len()), 0);
+    let checksum = checksum(&buffer[start..]);
+    buffer.extend_from_slice(&checksum.to_ne_bytes());
+    Ok(())
+}
+
+/// Read one chunk. `None` if the input ends before the first byte of the
+/// descriptor and `eof_ok` is set.
+fn read_chunk(reader: &mut impl Read, eof_ok: bool) -> Result<Option<(u32, Vec<u8>)>> {
+    let mut descriptor = [0u8; CHUNK_DESCRIPTOR_SIZE];
+    let mut filled = 0;
+    while filled < descriptor.len() {
+        match reader.read(&mut descriptor[filled..]) {
+            Ok(0) if filled == 0 && eof_ok => return Ok(None),
+            Ok(0) => {
+                log::error!("RecordedTransaction: truncated chunk descriptor");
+                return Err(StatusCode::NotEnoughData);
+            }
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let chunk_type = u32::from_ne_bytes(descriptor[0..4].try_into().unwrap());
+    let size = u32::from_ne_bytes(descriptor[4..8].try_into().unwrap());
+    if size > MAX_CHUNK_DATA_SIZE {
+        log::error!("RecordedTransaction: chunk of {size} bytes is too large");
+        return Err(StatusCode::BadValue);
+    }
+
+    let size = size as usize;
+    let mut rest = vec![0u8; size + padding8(size) + 8];
+    reader.read_exact(&mut rest).map_err(|err| {
+        log::error!("RecordedTransaction: truncated chunk: {err}");
+        StatusCode::NotEnoughData
+    })?;
+    let (body, stored) = rest.split_at(rest.len() - 8);
+    let mut chunk = descriptor.to_vec();
+    chunk.extend_from_slice(body);
+    if checksum(&chunk) != u64::from_ne_bytes(stored.try_into().unwrap()) {
+        log::error!("RecordedTransaction: checksum mismatch in chunk {chunk_type:#x}");
+        return Err(StatusCode::BadValue);
+    }
+    rest.truncate(size);
+    Ok(Some((chunk_type, rest)))
+}
+
+fn padding8(len: usize) -> usize {
+    (8 - len % 8) % 8
+}
+
+/// XOR of the 8-byte words of `bytes` (a multiple of 8 long).
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes
+        .chunks_exact(8)
+        .map(|word| u64::from_ne_bytes(word.try_into().unwrap()))
+        .fold(0, |acc, word| acc ^ word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(code: TransactionCode, data: &[u8]) -> RecordedTransaction {
+        RecordedTransaction {
+            interface_name: "my.IFoo".into(),
+            code,
+            flags: 0,
+            returned_status: 0,
+            version: 0,
+            timestamp_seconds: 1_700_000_000,
+            timestamp_nanoseconds: 42,
+            data: data.to_vec(),
+            reply: vec![0, 0, 0, 0],
+        }
+    }
+
+    #[test]
+    fn file_layout() {
+        let mut bytes = Vec::new();
+        sample(1, b"abc").write_to(&mut bytes).unwrap();
+        // header 8+32+8, name 8+8+8, data 8+8+8, reply 8+8+8, end 8+0+8.
+        assert_eq!(bytes.len(), 48 + 24 + 24 + 24 + 16);
+        assert_eq!(&bytes[0..8], &[1, 0, 0, 0, 32, 0, 0, 0]);
+        assert_eq!(
+            &bytes[bytes.len() - 16..bytes.len() - 8],
+            &[0xff, 0xff, 0xff, 0, 0, 0, 0, 0]
+        );
+        // The end chunk's checksum is the XOR of its only word.
+        assert_eq!(
+            &bytes[bytes.len() - 8..],
+            &[0xff, 0xff, 0xff, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn round_trip_several() {
+        let transactions = vec![sample(1, b""), sample(2, b"0123456789"), {
+            let mut t = sample(3, b"x");
+            t.returned_status = StatusCode::BadValue.into();
+            t.flags = FLAG_ONEWAY;
+            t.reply.clear();
+            t
+        }];
+        let mut bytes = Vec::new();
+        for t in &transactions {
+            t.write_to(&mut bytes).unwrap();
+        }
+        let read = RecordedTransaction::read_all(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read, transactions);
+        assert_eq!(read[2].status(), Err(StatusCode::BadValue));
+    }
+
+    #[test]
+    fn corruption_is_detected() {
+        let mut bytes = Vec::new();
+        sample(1, b"payload").write_to(&mut bytes).unwrap();
+
+        let mut flipped = bytes.clone();
+        flipped[60] ^= 1;
+        assert_eq!(
+            RecordedTransaction::read_from(&mut flipped.as_slice()),
+            Err(StatusCode::BadValue)
+        );
+
+        let truncated = &bytes[..bytes.len() - 4];
+        assert_eq!(
+            RecordedTransaction::read_from(&mut &truncated[..]),
+            Err(StatusCode::NotEnoughData)
+        );
+    }
+}
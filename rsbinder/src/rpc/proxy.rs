@@ -51,6 +51,10 @@ pub struct RpcProxy {
     /// publishes the recipients teardown to lock-free readers).
     obituary_sent: AtomicBool,
     recipients: RwLock<Vec<sync::Weak<dyn DeathRecipient>>>,
+    /// Whether calls carry a trace context; see
+    /// [`RpcProxy::set_trace_propagation`].
+    #[cfg(feature = "tracing")]
+    propagate_trace: AtomicBool,
 }
 
 impl RpcProxy {
@@ -61,9 +65,25 @@ impl RpcProxy {
             session,
             obituary_sent: AtomicBool::new(false),
             recipients: RwLock::new(Vec::new()),
+            #[cfg(feature = "tracing")]
+            propagate_trace: AtomicBool::new(false),
         }
     }
 
+    /// Send the installed [`crate::trace::TracePropagator`]'s context with
+    /// calls made through this proxy (off by default). Only enable it for
+    /// rsbinder peers; libbinder rejects the trace-context trailer.
+    #[cfg(feature = "tracing")]
+    pub fn set_trace_propagation(&self, enable: bool) {
+        self.propagate_trace.store(enable, Ordering::Relaxed);
+    }
+
+    /// Whether [`Self::set_trace_propagation`] is on.
+    #[cfg(feature = "tracing")]
+    pub fn trace_propagation(&self) -> bool {
+        self.propagate_trace.load(Ordering::Relaxed)
+    }
+
     /// Fire `binder_died` on every registered recipient — called by the
     /// owning session when its connection drops (AOSP
     /// `BpBinder::sendObituary`, the RPC branch). Mirrors the kernel
@@ -180,6 +200,8 @@ impl RpcProxy {
             inner.fd_mode(),
             inner.records_fd_positions(),
         );
+        #[cfg(feature = "tracing")]
+        data.set_propagates_trace(self.trace_propagation());
         super::session::write_rpc_interface_token(&mut data, descriptor)?;
         Ok(data)
     }
@@ -193,7 +215,20 @@ impl RpcProxy {
         flags: TransactionFlags,
     ) -> Result<Option<Parcel>> {
         let inner = self.session.upgrade().ok_or(StatusCode::DeadObject)?;
-        inner.client_transact(self.addr, code, data, flags)
+        let flags = crate::trace::wire_flags(data, flags);
+        let trace = crate::trace::Transaction::client(
+            crate::trace::RPC,
+            self.descriptor_str(),
+            code,
+            flags,
+            data,
+        );
+        let mut reply = inner.client_transact(self.addr, code, data, flags);
+        match &mut reply {
+            Ok(parcel) => trace.finish(StatusCode::Ok, parcel.as_mut()),
+            Err(err) => trace.finish(*err, None),
+        }
+        reply
     }
 }
 
@@ -212,6 +247,8 @@ impl crate::binder::RemoteProxy for RpcProxy {
             inner.fd_mode(),
             inner.records_fd_positions(),
         );
+        #[cfg(feature = "tracing")]
+        data.set_propagates_trace(self.trace_propagation());
         if write_header {
             super::session::write_rpc_interface_token(&mut data, self.descriptor_str())?;
         }
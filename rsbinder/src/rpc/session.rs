@@ -1893,7 +1893,19 @@ impl RpcSessionInner {
             // the error path the reply parcel is unused (`send_reply` sends an
             // empty body with the status), so a partially-written `reply`
             // cannot leak to the peer.
-            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let caller = match *peer {
+                PeerIdentity::Local { uid, pid } => Some((uid, pid)),
+                _ => None,
+            };
+            let trace = crate::trace::Transaction::server(
+                crate::trace::RPC,
+                target.descriptor(),
+                t.code,
+                t.flags,
+                &mut reader,
+                caller,
+            );
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
                 let _calling = crate::thread_state::RpcCallingGuard::install(Arc::clone(&peer));
                 target.rpc_transact(t.code, &mut reader, &mut reply)
             }))
@@ -1905,7 +1917,12 @@ impl RpcSessionInner {
                     .unwrap_or("<non-string panic payload>");
                 log::error!("RPC on_transact panicked for code {}: {msg}", t.code);
                 Err(crate::StatusCode::Unknown)
-            })
+            });
+            match result {
+                Ok(()) => trace.finish(crate::StatusCode::Ok, Some(&mut reply)),
+                Err(err) => trace.finish(err, None),
+            }
+            result
         });
 
         if oneway {
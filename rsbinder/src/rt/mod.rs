@@ -2,3 +2,7 @@
 mod tokio_rt;
 #[cfg(feature = "tokio")]
 pub use tokio_rt::*;
+#[cfg(feature = "tokio")]
+mod reactor;
+#[cfg(feature = "tokio")]
+pub use reactor::{BinderReactor, TokioReactor};
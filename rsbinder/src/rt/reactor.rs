@@ -0,0 +1,414 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Kernel-binder transactions for async proxies without a blocking thread
+//! per call.
+//!
+//! [`Tokio`](crate::Tokio) runs every async proxy call on
+//! `spawn_blocking`, so a thousand concurrent calls hold a thousand
+//! threads. With [`TokioReactor`] as the generated `IFooAsync<P>`
+//! parameter, calls go to the [`BinderReactor`] instead: a fixed set of
+//! *lanes*, threads that send `BC_TRANSACTION` and then poll the binder
+//! fd, reading with `BINDER_WRITE_READ` only when the driver has
+//! something for them. Replies complete the callers' futures; nested
+//! calls back into this process that arrive while a lane waits are
+//! dispatched on the lane, as the driver requires.
+//!
+//! The driver allows one synchronous transaction in flight per thread,
+//! so the number of lanes bounds the synchronous calls in flight; the
+//! rest queue without holding a thread. Lanes do not enter the looper,
+//! so incoming calls to this process's services are still served by the
+//! binder thread pool ([`ProcessState::start_thread_pool`]): a thread
+//! serving a call cannot take another one before it replies, whichever
+//! way its service is written.
+//!
+//! Despite the name, this is not tokio's reactor: lanes poll with their
+//! own `poll(2)` loop rather than registering the fd with `AsyncFd`.
+//! Readiness of the binder fd is per thread (the driver reports the
+//! polling thread's work), and tokio waits on its descriptors from
+//! whichever worker thread drives its IO driver, so a readiness event
+//! there would say nothing about the task's transaction. Only the
+//! outbound side is covered; incoming calls are not delivered to futures.
+//!
+//! # Head-of-line blocking
+//!
+//! A lane carries one call at a time, from `BC_TRANSACTION` until its
+//! reply, and requests are handed to lanes in submission order. A slow
+//! service therefore holds a lane for as long as it takes to answer,
+//! and nested callbacks served on the lane hold it too. Once every lane
+//! is held, every queued call waits, oneway ones and calls to fast
+//! services included. Size [`BinderReactor::init`] for the number of
+//! slow calls expected in flight at once, or keep calls to services that
+//! may stall on [`Tokio`].
+//!
+//! ```text
+//! use rsbinder::{BinderReactor, TokioReactor};
+//!
+//! ProcessState::init_default()?;
+//! BinderReactor::init(8)?; // optional: defaults to DEFAULT_LANES lanes
+//! let svc = hub::get_interface::<dyn IFoo>("foo")?.into_async::<TokioReactor>();
+//! svc.bar().await?;
+//! ```
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::os::fd::OwnedFd;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+
+use rustix::event::{poll, PollFd, PollFlags};
+use tokio::sync::oneshot;
+
+use crate::thread_state::{self, PendingTransaction};
+use crate::{
+    BinderAsyncPool, BoxFuture, Parcel, ProcessState, Result, SIBinder, StatusCode, Tokio,
+    TransactionCode, TransactionFlags,
+};
+
+/// Use the [`BinderReactor`] for kernel-binder calls of async proxies.
+///
+/// Calls made while handling a transaction run inline, like with
+/// [`Tokio`], so the driver can route callbacks to the calling thread.
+/// RPC proxies and [`BinderAsyncPool::spawn`] fall back to [`Tokio`].
+pub enum TokioReactor {}
+
+impl BinderAsyncPool for TokioReactor {
+    fn spawn<'a, F1, F2, Fut, A, B, E>(
+        spawn_me: F1,
+        after_spawn: F2,
+    ) -> BoxFuture<'a, std::result::Result<B, E>>
+    where
+        F1: FnOnce() -> A,
+        F2: FnOnce(A) -> Fut,
+        Fut: std::future::Future<Output = std::result::Result<B, E>>,
+        F1: Send + 'static,
+        F2: Send + 'a,
+        Fut: Send + 'a,
+        A: Send + 'static,
+        B: Send + 'a,
+        E: From<crate::StatusCode>,
+    {
+        Tokio::spawn(spawn_me, after_spawn)
+    }
+
+    fn transact(
+        binder: SIBinder,
+        code: TransactionCode,
+        data: Parcel,
+        flags: TransactionFlags,
+    ) -> BoxFuture<'static, Result<Option<Parcel>>> {
+        // A kernel proxy implies an initialized `ProcessState`, which
+        // `is_handling_transaction` needs.
+        if binder.as_proxy().is_none() || crate::is_handling_transaction() {
+            return Tokio::transact(binder, code, data, flags);
+        }
+        match BinderReactor::get() {
+            Ok(reactor) => reactor.transact(&binder, code, data, flags),
+            Err(err) => Box::pin(std::future::ready(Err(err))),
+        }
+    }
+}
+
+/// The process-wide set of lanes behind [`TokioReactor`].
+///
+/// Each lane carries one call at a time, from `BC_TRANSACTION` until its
+/// reply, and queued calls go to lanes in submission order. A call to a
+/// slow service, or a nested callback served on the lane, holds the lane
+/// meanwhile; once every lane is held, all queued calls wait behind them,
+/// oneway ones included. Size [`Self::init`] for the slow calls expected
+/// in flight at once, or keep services that may stall on [`Tokio`].
+///
+/// The lanes poll the binder fd themselves instead of registering it with
+/// tokio's IO driver, since the driver reports readiness per thread.
+/// Incoming calls are still served by the binder thread pool.
+pub struct BinderReactor {
+    shared: Arc<Shared>,
+    lanes: usize,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Write ends of the lanes' wake-up sockets.
+    wakers: Vec<UnixStream>,
+    shutdown: AtomicBool,
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: VecDeque<Request>,
+    /// Lanes waiting for a request.
+    idle: Vec<usize>,
+}
+
+struct Request {
+    binder: SIBinder,
+    handle: u32,
+    code: TransactionCode,
+    data: Parcel,
+    flags: TransactionFlags,
+    reply: oneshot::Sender<Result<Option<Parcel>>>,
+}
+
+struct InFlight {
+    pending: PendingTransaction,
+    request: Request,
+}
+
+static REACTOR: OnceLock<BinderReactor> = OnceLock::new();
+static INIT: Mutex<()> = Mutex::new(());
+
+impl BinderReactor {
+    /// Lanes started by [`Self::get`] when [`Self::init`] was not called.
+    pub const DEFAULT_LANES: usize = 4;
+
+    /// Start the reactor with `lanes` lanes. Like [`ProcessState::init`],
+    /// returns the running reactor if it was already started.
+    ///
+    /// Fails with [`StatusCode::NoInit`] before [`ProcessState::init`] and
+    /// with [`StatusCode::BadValue`] for zero lanes.
+    pub fn init(lanes: usize) -> Result<&'static BinderReactor> {
+        let _guard = INIT.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(reactor) = REACTOR.get() {
+            return Ok(reactor);
+        }
+        let reactor = Self::start(lanes)?;
+        Ok(REACTOR.get_or_init(|| reactor))
+    }
+
+    /// The running reactor, started with [`Self::DEFAULT_LANES`] lanes if
+    /// needed.
+    pub fn get() -> Result<&'static BinderReactor> {
+        match REACTOR.get() {
+            Some(reactor) => Ok(reactor),
+            None => Self::init(Self::DEFAULT_LANES),
+        }
+    }
+
+    /// The number of lanes, i.e. of synchronous calls that can be in
+    /// flight at once.
+    pub fn lanes(&self) -> usize {
+        self.lanes
+    }
+
+    /// Send a transaction to the kernel proxy `binder` from a lane and
+    /// resolve to its reply (`None` for oneway calls).
+    ///
+    /// The call is sent even if the future is dropped; only its reply is
+    /// discarded. Fails with [`StatusCode::InvalidOperation`] if `binder`
+    /// is not a kernel proxy.
+    pub fn transact(
+        &self,
+        binder: &SIBinder,
+        code: TransactionCode,
+        data: Parcel,
+        flags: TransactionFlags,
+    ) -> BoxFuture<'static, Result<Option<Parcel>>> {
+        let Some(proxy) = binder.as_proxy() else {
+            return Box::pin(std::future::ready(Err(StatusCode::InvalidOperation)));
+        };
+        // Same fast-fail as `ProxyHandle::submit_transact`.
+        if proxy.is_dead() {
+            return Box::pin(std::future::ready(Err(StatusCode::DeadObject)));
+        }
+        let (reply, receiver) = oneshot::channel();
+        let flags = crate::trace::wire_flags(&data, flags);
+        self.shared.submit(Request {
+            binder: binder.clone(),
+            handle: proxy.handle(),
+            code,
+            data,
+            flags,
+            reply,
+        });
+        Box::pin(async move {
+            // The sender only goes away without a reply if a lane died.
+            receiver.await.unwrap_or(Err(StatusCode::FailedTransaction))
+        })
+    }
+
+    fn start(lanes: usize) -> Result<BinderReactor> {
+        if lanes == 0 {
+            return Err(StatusCode::BadValue);
+        }
+        if !ProcessState::is_initialized() {
+            return Err(StatusCode::NoInit);
+        }
+
+        let mut wakers = Vec::with_capacity(lanes);
+        let mut wake_ups = Vec::with_capacity(lanes);
+        for _ in 0..lanes {
+            let (wake_up, waker) = UnixStream::pair()?;
+            wake_up.set_nonblocking(true)?;
+            waker.set_nonblocking(true)?;
+            wakers.push(waker);
+            wake_ups.push(wake_up);
+        }
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue::default()),
+            wakers,
+            shutdown: AtomicBool::new(false),
+        });
+
+        let (ready, started) = mpsc::channel();
+        let pid = std::process::id();
+        for (index, wake_up) in wake_ups.into_iter().enumerate() {
+            let lane_shared = shared.clone();
+            let ready = ready.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("reactor:{pid}_{index:X}"))
+                .spawn(move || run_lane(index, &lane_shared, wake_up, ready));
+            if let Err(err) = spawned {
+                log::error!("failed to spawn binder reactor lane {index}: {err}");
+                shared.stop();
+                return Err(StatusCode::NoMemory);
+            }
+        }
+        drop(ready);
+        for _ in 0..lanes {
+            let result = started.recv().unwrap_or(Err(StatusCode::Unknown));
+            if let Err(err) = result {
+                shared.stop();
+                return Err(err);
+            }
+        }
+        Ok(BinderReactor { shared, lanes })
+    }
+}
+
+impl std::fmt::Debug for BinderReactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BinderReactor")
+            .field("lanes", &self.lanes)
+            .finish()
+    }
+}
+
+impl Shared {
+    fn submit(&self, request: Request) {
+        let lane = {
+            let mut queue = self.lock();
+            queue.pending.push_back(request);
+            queue.idle.pop()
+        };
+        if let Some(lane) = lane {
+            self.wake(lane);
+        }
+    }
+
+    /// The next request for `lane`, or `None` after marking it idle.
+    fn next(&self, lane: usize) -> Option<Request> {
+        let mut queue = self.lock();
+        let request = queue.pending.pop_front();
+        if request.is_none() && !queue.idle.contains(&lane) {
+            queue.idle.push(lane);
+        }
+        request
+    }
+
+    fn wake(&self, lane: usize) {
+        // A full socket already reads as ready.
+        let _ = std::io::Write::write(&mut &self.wakers[lane], &[1]);
+    }
+
+    fn stop(&self) {
+        self.shutdown.store(true, Ordering::Release);
+        for lane in 0..self.wakers.len() {
+            self.wake(lane);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn run_lane(index: usize, shared: &Shared, wake_up: UnixStream, ready: mpsc::Sender<Result<()>>) {
+    let binder = match thread_state::poll_fd() {
+        Ok(fd) => {
+            let _ = ready.send(Ok(()));
+            fd
+        }
+        Err(err) => {
+            log::error!("binder reactor lane {index} cannot poll the driver: {err}");
+            let _ = ready.send(Err(err));
+            return;
+        }
+    };
+    drop(ready);
+
+    let mut in_flight: Option<InFlight> = None;
+    loop {
+        if shared.shutdown.load(Ordering::Acquire) {
+            return;
+        }
+        while in_flight.is_none() {
+            let Some(request) = shared.next(index) else {
+                break;
+            };
+            match thread_state::start_transact(
+                request.handle,
+                request.code,
+                &request.data,
+                request.flags,
+            ) {
+                Ok(pending) => in_flight = Some(InFlight { pending, request }),
+                Err(err) => complete(request, Err(err)),
+            }
+        }
+
+        // Commands left over from the last read are not signalled by poll.
+        if !thread_state::has_pending_input() && !wait(&binder, &wake_up) {
+            continue;
+        }
+        match in_flight.take() {
+            Some(flight) => match flight.pending.poll() {
+                Ok(None) => in_flight = Some(flight),
+                Ok(Some(reply)) => complete(flight.request, Ok(reply)),
+                Err(err) => complete(flight.request, Err(err)),
+            },
+            None => {
+                if let Err(err) = thread_state::handle_polled_commands() {
+                    log::error!("binder reactor lane {index}: {err}");
+                }
+            }
+        }
+    }
+}
+
+/// Block until the driver or a submission wakes the lane; true if the
+/// driver has work for it.
+fn wait(binder: &OwnedFd, wake_up: &UnixStream) -> bool {
+    let mut fds = [
+        PollFd::new(binder, PollFlags::IN),
+        PollFd::new(wake_up, PollFlags::IN),
+    ];
+    match poll(&mut fds, None) {
+        Ok(_) => {}
+        Err(rustix::io::Errno::INTR) => return false,
+        Err(err) => {
+            log::error!("binder reactor poll failed: {err}");
+            return false;
+        }
+    }
+    if !fds[1].revents().is_empty() {
+        let mut buf = [0u8; 64];
+        while matches!((&*wake_up).read(&mut buf), Ok(n) if n > 0) {}
+    }
+    !fds[0].revents().is_empty()
+}
+
+fn complete(request: Request, result: Result<Option<Parcel>>) {
+    if thread_state::take_oneway_spam_suspect() {
+        if let Some(proxy) = request.binder.as_proxy() {
+            ProcessState::as_self().report_oneway_spam(
+                request.handle,
+                proxy.descriptor(),
+                request.code,
+            );
+        }
+    }
+    // A dropped receiver discards the reply, freeing its buffer here.
+    let _ = request.reply.send(result);
+}
@@ -111,19 +111,31 @@ pub mod kernel {
     //! [`crate::hub`] service manager.
 
     use super::*;
-    use crate::process_state::{CallRestriction, ProcessState};
+    use crate::process_state::{
+        CallRestriction, ProcessState, DEFAULT_ENABLE_ONEWAY_SPAM_DETECTION,
+    };
 
     /// Initialize (idempotently) the process-global kernel `ProcessState`
     /// and return a handle. `ProcessState::init`/`init_default` is
     /// process-wide; a second `Host` in the same process **reuses** the
     /// existing instance. See [`Host::builder`] for the loud-on-conflict
-    /// behavior.
-    fn init(driver: Option<&std::path::Path>, max_threads: u32) -> Result<()> {
+    /// behavior. `oneway_spam_detection` is applied when the driver is
+    /// opened, or to the existing instance; `None` keeps the default.
+    fn init(
+        driver: Option<&std::path::Path>,
+        max_threads: u32,
+        oneway_spam_detection: Option<bool>,
+    ) -> Result<()> {
         let pre = ProcessState::is_initialized();
-        let ps = match driver {
-            Some(p) => ProcessState::init(&p.to_string_lossy(), max_threads),
-            None => ProcessState::init_default(),
-        }
+        let path = match driver {
+            Some(p) => p.to_string_lossy().into_owned(),
+            None => ProcessState::default_driver_path().to_owned(),
+        };
+        let ps = ProcessState::init_with(
+            &path,
+            max_threads,
+            oneway_spam_detection.unwrap_or(DEFAULT_ENABLE_ONEWAY_SPAM_DETECTION),
+        )
         .map_err(|e| {
             log::error!("kernel::Host: ProcessState init failed: {e}");
             StatusCode::NoInit
@@ -151,6 +163,12 @@ pub mod kernel {
                     ps.max_threads()
                 );
             }
+            // A runtime setting: an existing instance takes it too.
+            if let Some(enable) = oneway_spam_detection {
+                if ps.oneway_spam_detection() != enable {
+                    ps.set_oneway_spam_detection(enable)?;
+                }
+            }
         }
         Ok(())
     }
@@ -169,7 +187,7 @@ pub mod kernel {
         /// Initialize the process `ProcessState` with the default binder
         /// path (idempotent) and return a host.
         pub fn new() -> Result<Self> {
-            init(None, 0)?;
+            init(None, 0, None)?;
             Ok(Host { _priv: () })
         }
 
@@ -203,6 +221,7 @@ pub mod kernel {
         driver: Option<std::path::PathBuf>,
         max_threads: u32,
         call_restriction: Option<CallRestriction>,
+        oneway_spam_detection: Option<bool>,
     }
 
     impl HostBuilder {
@@ -225,10 +244,21 @@ pub mod kernel {
             self
         }
 
+        /// Turn the driver's oneway spam detection on or off (default on).
+        /// Maps to [`crate::ProcessState::set_oneway_spam_detection`].
+        pub fn oneway_spam_detection(mut self, enable: bool) -> Self {
+            self.oneway_spam_detection = Some(enable);
+            self
+        }
+
         /// Initialize `ProcessState` (idempotent — warns if a prior init
         /// used a different driver/`max_threads`) and return the host.
         pub fn build(self) -> Result<Host> {
-            init(self.driver.as_deref(), self.max_threads)?;
+            init(
+                self.driver.as_deref(),
+                self.max_threads,
+                self.oneway_spam_detection,
+            )?;
             if let Some(cr) = self.call_restriction {
                 ProcessState::as_self().set_call_restriction(cr);
             }
@@ -246,7 +276,7 @@ pub mod kernel {
         /// Initialize the process `ProcessState` (idempotent) so the
         /// system service manager is reachable, and return a broker.
         pub fn new() -> Result<Self> {
-            init(None, 0)?;
+            init(None, 0, None)?;
             Ok(Broker { _priv: () })
         }
     }
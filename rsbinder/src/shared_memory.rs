@@ -1,57 +1,84 @@
 // Copyright 2026 Jeff Kim <hiking90@gmail.com>
 // SPDX-License-Identifier: Apache-2.0
 
-//! Shared-memory IPC trait skeleton.
+//! Shared memory over binder: AOSP `IMemoryHeap` / `IMemory`.
 //!
 //! AOSP `IMemory` / `IMemoryHeap`
 //! ([`IMemory.h`](https://cs.android.com/android/platform/superproject/+/android-16.0.0_r4:frameworks/native/libs/binder/include/binder/IMemory.h))
-//! are **handwritten** C++ binders, not AIDL. The Rust traits in this
-//! module mirror that surface for in-process use. Not yet implemented,
-//! left as future work:
+//! are **handwritten** C++ binders, not AIDL, so the `Bn`/`Bp` types here
+//! are handwritten too and speak the same wire format
+//! ([`IMemory.cpp`](https://cs.android.com/android/platform/superproject/+/android-16.0.0_r4:frameworks/native/libs/binder/IMemory.cpp)):
 //!
-//! * A `memfd_create(2)` + `F_ADD_SEALS` backed `MemoryHeapBase`
-//!   (Linux/Android only). Receiver-side `F_GET_SEALS` verification is
-//!   mandatory for the read-only PROT negotiation.
-//! * A `MemoryDealer` chunk allocator on top of a heap.
-//! * Hermetic verification that two processes can mmap the same page
-//!   through `ParcelFileDescriptor` transfer.
+//! * `android.utils.IMemoryHeap` `HEAP_ID` replies with the heap fd (a
+//!   bare fd object), `u64` size, `i32` flags and `u64` offset.
+//! * `android.utils.IMemory` `GET_MEMORY` replies with the heap binder,
+//!   `i64` offset and `u64` size of the slice.
 //!
-//! Interop with real `IMemory`/`IMemoryHeap` peers is out of scope — it
-//! would require manual `Bn`/`Bp` matching AOSP's handwritten transaction
-//! codes (`HEAP_ID_TRANSACTION` etc.).
+//! [`MemoryHeapBase`] allocates a heap with `memfd_create(2)` and maps it
+//! `MAP_SHARED` (Linux/Android; elsewhere only [`MemoryHeapBase::from_fd`]
+//! is available). Publish it with [`BnMemoryHeap::new_binder`] and hand out
+//! slices of it with [`MemoryBase`] / [`BnMemory::new_binder`]. Received
+//! heaps are [`BpMemoryHeap`]s, which map the fd on first use.
 //!
-//! The macOS host build compiles every trait declaration in this module
-//! but the concrete `MemoryHeapBase` stub returns
-//! `Err(StatusCode::InvalidOperation)` from every operation — there is no
-//! `memfd_create` equivalent on darwin. The Linux/Android impl, once
-//! added, is gated on `cfg(any(target_os = "linux", target_os = "android"))`.
+//! ```text
+//! let heap = MemoryHeapBase::new(4 << 20, 0)?;
+//! let frame = heap.as_mut_ptr(); // producer keeps writing frames here
+//! let heap = BnMemoryHeap::new_binder(heap);
+//! let memory = BnMemory::new_binder(MemoryBase::new(heap, 0, 1 << 20)?);
+//! parcel.write(&memory)?;
+//! // Receiver: `let memory: Strong<dyn IMemory> = reader.read()?;`
+//! // `memory.as_slice()` maps the heap and borrows the slice.
+//! ```
+//!
+//! Unlike AOSP's process-wide heap cache, every [`BpMemoryHeap`] maps on
+//! its own; keep the `Strong<dyn IMemoryHeap>` around to reuse a mapping.
+//! A `MemoryDealer` chunk allocator is not provided.
+
+use std::ffi::c_void;
+use std::fmt;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd};
+use std::sync::{Arc, OnceLock};
+
+use rustix::mm::{MapFlags, ProtFlags};
 
 use crate::error::{Result, StatusCode};
+use crate::{
+    Binder, FromIBinder, Interface, Parcel, Proxy, Remotable, SIBinder, Strong, TransactionCode,
+    FIRST_CALL_TRANSACTION,
+};
 
 /// AOSP `IMemoryHeap::READ_ONLY` flag
 /// ([IMemory.h:37-39](https://cs.android.com/android/platform/superproject/+/android-16.0.0_r4:frameworks/native/libs/binder/include/binder/IMemory.h;l=37)).
-/// Receivers MUST honor this by mapping `PROT_READ` only and rejecting
-/// any subsequent `mprotect(PROT_WRITE)`. The PROT negotiation also
-/// requires the sender to enforce `F_SEAL_WRITE` on the underlying
-/// `memfd` so the kernel rejects a mismatched mmap from a peer that
-/// ignores this flag.
+/// Receivers honor it by mapping `PROT_READ` only. A [`MemoryHeapBase`]
+/// created with it also seals its `memfd` with `F_SEAL_FUTURE_WRITE`, so
+/// the kernel rejects a writable mapping from a peer that ignores the
+/// flag; the creator keeps its own writable mapping.
 pub const FLAG_READ_ONLY: u32 = 0x0000_0001;
 
-/// Server-side representation of a heap. AOSP `IMemoryHeap` is keyed by
-/// the heap fd; this trait deliberately exposes the fd as a borrowed
-/// raw fd (`i32`) rather than an owned [`std::os::fd::OwnedFd`] so the
-/// transaction marshalling can dup the fd into a
-/// [`crate::ParcelFileDescriptor`] without taking ownership away from
+/// Interface descriptor of AOSP `IMemoryHeap`.
+pub const MEMORY_HEAP_DESCRIPTOR: &str = "android.utils.IMemoryHeap";
+/// Interface descriptor of AOSP `IMemory`.
+pub const MEMORY_DESCRIPTOR: &str = "android.utils.IMemory";
+
+/// AOSP `BnMemoryHeap` `HEAP_ID` transaction.
+pub const HEAP_ID_TRANSACTION: TransactionCode = FIRST_CALL_TRANSACTION;
+/// AOSP `BnMemory` `GET_MEMORY` transaction.
+pub const GET_MEMORY_TRANSACTION: TransactionCode = FIRST_CALL_TRANSACTION;
+
+/// A heap of shared memory backed by a file descriptor. AOSP
+/// `IMemoryHeap` is keyed by the heap fd; this trait exposes the fd as a
+/// borrowed raw fd (`i32`) rather than an owned [`OwnedFd`] so the
+/// transaction marshalling can send it without taking ownership away from
 /// the heap object.
 ///
-/// All methods return `&` borrows (heap geometry is immutable for the
-/// lifetime of the heap); the size and offset are captured at heap
-/// construction time and never mutate. Mutation surface is intentionally
-/// absent — heap resize is not in AOSP `IMemoryHeap` either
+/// Heap geometry is immutable for the lifetime of the heap — heap resize
+/// is not in AOSP `IMemoryHeap` either
 /// ([IMemory.h:41-45](https://cs.android.com/android/platform/superproject/+/android-16.0.0_r4:frameworks/native/libs/binder/include/binder/IMemory.h;l=41)).
-pub trait IMemoryHeap: Send + Sync {
-    /// AOSP `getHeapID()`. Returns the fd-as-i32 for parcel marshalling
-    /// (wrapped in `ParcelFileDescriptor` on the wire).
+/// As in AOSP, a remote heap that cannot be mapped reports `-1`, `0` and
+/// `None` from the getters.
+pub trait IMemoryHeap: Interface {
+    /// AOSP `getHeapID()`. The fd backing the heap, sent as a bare fd
+    /// object on the wire.
     fn heap_id(&self) -> i32;
     /// AOSP `getSize()`. Total byte length of the heap.
     fn size(&self) -> usize;
@@ -60,15 +87,15 @@ pub trait IMemoryHeap: Send + Sync {
     /// AOSP `getOffset()`. Offset within the underlying fd at which
     /// this heap begins; `0` for a freshly-allocated heap.
     fn offset(&self) -> usize;
-    /// AOSP `getBase()`. Returns the local mmap base pointer if the
-    /// heap is currently mapped into this process, else `None`.
-    /// A real `MemoryHeapBase` would populate this; the macOS stub
-    /// always returns `None`.
+    /// AOSP `getBase()`. The heap's mapping in this process, mapping a
+    /// remote heap first if needed.
     ///
     /// **Safety contract.** The returned slice is valid only for the
     /// lifetime of the heap (`&self`) and only points to memory mapped
     /// by *this* process — see AOSP `unsecurePointer()` security note
     /// ([IMemory.h:78-91](https://cs.android.com/android/platform/superproject/+/android-16.0.0_r4:frameworks/native/libs/binder/include/binder/IMemory.h;l=78)).
+    /// Other processes sharing the heap may write to it concurrently
+    /// unless it is [`FLAG_READ_ONLY`].
     fn base(&self) -> Option<&[u8]>;
 }
 
@@ -77,71 +104,581 @@ pub trait IMemoryHeap: Send + Sync {
 /// equivalent. An `IMemory` references a heap plus an `(offset, size)`
 /// pair so that one large heap can host many small allocations (the
 /// AOSP `MemoryDealer` pattern).
-pub trait IMemory: Send + Sync {
-    /// AOSP `getMemory(offset*, size*)`. Returns the backing heap plus
-    /// the in-heap offset and size for this slice. `&self` borrow keeps
-    /// the heap alive for the duration of the returned reference.
-    fn memory(&self) -> &dyn IMemoryHeap;
+pub trait IMemory: Interface {
+    /// AOSP `getMemory(offset*, size*)`. The backing heap, or `None` if a
+    /// remote memory could not be resolved.
+    fn memory(&self) -> Option<&dyn IMemoryHeap>;
     /// AOSP `offset()`. Offset within the backing heap.
     fn offset(&self) -> usize;
     /// AOSP `size()`. Byte length of this slice. May be smaller than
     /// the backing heap.
     fn size(&self) -> usize;
+
+    /// AOSP `unsecurePointer()` with the size attached: this slice of the
+    /// heap's mapping, or `None` if the heap is unavailable or the slice
+    /// does not fit in it. The [`IMemoryHeap::base`] contract applies.
+    fn as_slice(&self) -> Option<&[u8]> {
+        let base = self.memory()?.base()?;
+        base.get(self.offset()..self.offset().checked_add(self.size())?)
+    }
+}
+
+/// A `MAP_SHARED` mapping of a heap fd, unmapped on drop.
+struct Mapping {
+    fd: OwnedFd,
+    ptr: *mut c_void,
+    size: usize,
+    offset: usize,
+    flags: u32,
+}
+
+// SAFETY: the mapping is plain shared memory owned by this value; the
+// raw pointer is only turned into slices whose lifetime is tied to it.
+unsafe impl Send for Mapping {}
+// SAFETY: see `Send`; `&Mapping` hands out only shared slices and a raw
+// pointer whose use is the caller's responsibility.
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    /// Map `size` bytes of `fd` at `offset`, read-only if `flags` has
+    /// [`FLAG_READ_ONLY`].
+    fn new(fd: OwnedFd, size: usize, flags: u32, offset: usize) -> Result<Self> {
+        let prot = if flags & FLAG_READ_ONLY != 0 {
+            ProtFlags::READ
+        } else {
+            ProtFlags::READ | ProtFlags::WRITE
+        };
+        Self::map(fd, size, flags, offset, prot)
+    }
+
+    fn map(fd: OwnedFd, size: usize, flags: u32, offset: usize, prot: ProtFlags) -> Result<Self> {
+        if size == 0 || offset % rustix::param::page_size() != 0 {
+            return Err(StatusCode::BadValue);
+        }
+        // Touching a page past the end of a regular file (a memfd) raises
+        // SIGBUS, so refuse a heap that claims more than its file holds.
+        let stat = rustix::fs::fstat(&fd)?;
+        if rustix::fs::FileType::from_raw_mode(stat.st_mode) == rustix::fs::FileType::RegularFile {
+            let end = offset.checked_add(size).ok_or(StatusCode::BadValue)?;
+            if end as u64 > stat.st_size as u64 {
+                log::error!(
+                    "shared memory: heap of {size} bytes at {offset} exceeds its fd ({} bytes)",
+                    stat.st_size
+                );
+                return Err(StatusCode::BadValue);
+            }
+        }
+        // SAFETY: a fresh shared mapping at a kernel-chosen address; it
+        // aliases no Rust object and is unmapped exactly once in `Drop`.
+        let ptr = unsafe {
+            rustix::mm::mmap(
+                std::ptr::null_mut(),
+                size,
+                prot,
+                MapFlags::SHARED,
+                &fd,
+                offset as u64,
+            )?
+        };
+        Ok(Mapping {
+            fd,
+            ptr,
+            size,
+            offset,
+            flags,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: `ptr` maps `size` readable bytes for as long as `self`
+        // lives. Other processes may write to it; the bytes are plain data.
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.size) }
+    }
 }
 
-/// Concrete heap allocator. Currently only the public surface is
-/// present; the actual `memfd_create` + `F_ADD_SEALS` implementation is
-/// future work, gated on Linux/Android.
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: `ptr`/`size` are exactly what `mmap` returned in `map`.
+        if let Err(err) = unsafe { rustix::mm::munmap(self.ptr, self.size) } {
+            log::error!("shared memory: munmap failed: {err}");
+        }
+    }
+}
+
+/// A heap allocated or mapped by this process (AOSP `MemoryHeapBase`).
 ///
-/// On macOS this is a *compile-only* stub: every constructor returns
-/// `Err(StatusCode::InvalidOperation)` so callers can write
-/// `cfg`-portable code that depends on the trait surface but doesn't
-/// actually rely on shared memory backing existing.
-#[derive(Debug)]
+/// Clones share the mapping, so the producer can keep a clone for writing
+/// after handing one to [`BnMemoryHeap::new_binder`].
+#[derive(Clone)]
 pub struct MemoryHeapBase {
-    // `_` prefix suppresses dead-code until the real impl fills these.
-    _size: usize,
-    _flags: u32,
-    _offset: usize,
+    mapping: Arc<Mapping>,
 }
 
 impl MemoryHeapBase {
-    /// macOS / Linux / Android constructor stub. Returns
-    /// `Err(StatusCode::InvalidOperation)` until the
-    /// `memfd_create`-backed impl lands (Linux/Android cfg) — on macOS
-    /// it remains permanent (`Err(InvalidOperation)`).
+    /// Allocate a heap of `size` bytes, rounded up to whole pages, backed by
+    /// a sealed `memfd` (AOSP `MemoryHeapBase(size, flags, name)`).
+    ///
+    /// The memfd can neither shrink nor grow, so peers cannot make the
+    /// mapping fault. With [`FLAG_READ_ONLY`] it is also sealed against new
+    /// writable mappings; this process keeps its writable one
+    /// ([`Self::as_mut_ptr`]). Fails with [`StatusCode::BadValue`] for a
+    /// zero size, and with [`StatusCode::InvalidOperation`] on platforms
+    /// without `memfd_create`.
     pub fn new(size: usize, flags: u32) -> Result<Self> {
-        // Suppress unused: keep them in the signature so callers see
-        // the AOSP-faithful surface from day 1.
-        let _ = (size, flags);
         #[cfg(any(target_os = "linux", target_os = "android"))]
         {
-            // For now even Linux/Android returns `InvalidOperation`; the
-            // real impl will call `memfd_create(2)` + map here.
-            Err(StatusCode::InvalidOperation)
+            use rustix::fs::{MemfdFlags, SealFlags};
+
+            let page = rustix::param::page_size();
+            let size = size
+                .checked_next_multiple_of(page)
+                .filter(|&size| size > 0)
+                .ok_or(StatusCode::BadValue)?;
+            let fd = rustix::fs::memfd_create(
+                "MemoryHeapBase",
+                MemfdFlags::CLOEXEC | MemfdFlags::ALLOW_SEALING,
+            )?;
+            rustix::fs::ftruncate(&fd, size as u64)?;
+            let mapping = Mapping::map(fd, size, flags, 0, ProtFlags::READ | ProtFlags::WRITE)?;
+            let mut seals = SealFlags::SHRINK | SealFlags::GROW | SealFlags::SEAL;
+            if flags & FLAG_READ_ONLY != 0 {
+                seals |= SealFlags::FUTURE_WRITE;
+            }
+            rustix::fs::fcntl_add_seals(&mapping.fd, seals)?;
+            Ok(MemoryHeapBase {
+                mapping: Arc::new(mapping),
+            })
         }
         #[cfg(not(any(target_os = "linux", target_os = "android")))]
         {
+            let _ = (size, flags);
             Err(StatusCode::InvalidOperation)
         }
     }
+
+    /// Map `size` bytes of an existing `fd` starting at `offset` (AOSP
+    /// `MemoryHeapBase(fd, size, flags, offset)`), read-only with
+    /// [`FLAG_READ_ONLY`]. `offset` must be page-aligned and the range must
+    /// lie within the file if `fd` is a regular file.
+    pub fn from_fd(fd: OwnedFd, size: usize, flags: u32, offset: usize) -> Result<Self> {
+        Ok(MemoryHeapBase {
+            mapping: Arc::new(Mapping::new(fd, size, flags, offset)?),
+        })
+    }
+
+    /// Start of the mapping, for writing into the heap. Writable unless the
+    /// heap was mapped with [`Self::from_fd`] and [`FLAG_READ_ONLY`].
+    ///
+    /// Writes race with every other user of the heap, in this process and
+    /// in the peers it was sent to; synchronizing them is up to the caller.
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        self.mapping.ptr as *mut u8
+    }
+
+    /// The heap's fd, for handing it to another kind of descriptor
+    /// (e.g. an FMQ `NativeHandle`).
+    pub(crate) fn fd(&self) -> BorrowedFd<'_> {
+        self.mapping.fd.as_fd()
+    }
+}
+
+impl fmt::Debug for MemoryHeapBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryHeapBase")
+            .field("fd", &self.mapping.fd.as_raw_fd())
+            .field("size", &self.mapping.size)
+            .field("flags", &self.mapping.flags)
+            .field("offset", &self.mapping.offset)
+            .finish()
+    }
 }
 
+impl Interface for MemoryHeapBase {}
+
 impl IMemoryHeap for MemoryHeapBase {
     fn heap_id(&self) -> i32 {
-        -1
+        self.mapping.fd.as_raw_fd()
+    }
+    fn size(&self) -> usize {
+        self.mapping.size
+    }
+    fn flags(&self) -> u32 {
+        self.mapping.flags
+    }
+    fn offset(&self) -> usize {
+        self.mapping.offset
+    }
+    fn base(&self) -> Option<&[u8]> {
+        Some(self.mapping.as_slice())
+    }
+}
+
+/// A binder `Remotable` that serves an [`IMemoryHeap`] (AOSP
+/// `BnMemoryHeap`).
+pub struct BnMemoryHeap(Box<dyn IMemoryHeap>);
+
+impl BnMemoryHeap {
+    /// Publish `heap` as a binder.
+    pub fn new_binder<T: IMemoryHeap + 'static>(heap: T) -> Strong<dyn IMemoryHeap> {
+        Strong::new(Box::new(Binder::new(BnMemoryHeap(Box::new(heap)))))
+    }
+}
+
+impl Remotable for BnMemoryHeap {
+    fn descriptor() -> &'static str {
+        MEMORY_HEAP_DESCRIPTOR
+    }
+
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        _reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            HEAP_ID_TRANSACTION => {
+                let heap = &*self.0;
+                let fd = heap.heap_id();
+                if fd < 0 {
+                    return Err(StatusCode::BadFd);
+                }
+                // SAFETY: `heap_id` is the heap's open fd, which stays open
+                // while `heap` is borrowed.
+                let fd = unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) };
+                reply.write_file_descriptor(fd)?;
+                reply.write(&(heap.size() as u64))?;
+                reply.write(&(heap.flags() as i32))?;
+                reply.write(&(heap.offset() as u64))
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+
+    fn on_dump(&self, writer: &mut dyn std::io::Write, args: &[String]) -> Result<()> {
+        self.0.dump(writer, args)
+    }
+}
+
+impl IMemoryHeap for Binder<BnMemoryHeap> {
+    fn heap_id(&self) -> i32 {
+        self.0.heap_id()
     }
     fn size(&self) -> usize {
-        self._size
+        self.0.size()
     }
     fn flags(&self) -> u32 {
-        self._flags
+        self.0.flags()
     }
     fn offset(&self) -> usize {
-        self._offset
+        self.0.offset()
     }
     fn base(&self) -> Option<&[u8]> {
-        None
+        self.0.base()
+    }
+}
+
+/// A binder `Proxy` for a remote [`IMemoryHeap`] (AOSP `BpMemoryHeap`).
+/// The heap is fetched and mapped on first use of any getter; a failed
+/// attempt is retried on the next call.
+pub struct BpMemoryHeap {
+    binder: SIBinder,
+    mapping: OnceLock<Mapping>,
+}
+
+impl BpMemoryHeap {
+    fn mapping(&self) -> Option<&Mapping> {
+        if let Some(mapping) = self.mapping.get() {
+            return Some(mapping);
+        }
+        match self.fetch() {
+            // A racing thread may have won; its mapping is kept and ours
+            // is unmapped.
+            Ok(mapping) => Some(self.mapping.get_or_init(|| mapping)),
+            Err(err) => {
+                log::error!("BpMemoryHeap: cannot map the remote heap: {err}");
+                None
+            }
+        }
+    }
+
+    fn fetch(&self) -> Result<Mapping> {
+        let remote = self.binder.as_remote().ok_or(StatusCode::BadType)?;
+        let data = remote.prepare_transact(true)?;
+        let mut reply = remote
+            .submit_transact(HEAP_ID_TRANSACTION, &data, 0)?
+            .ok_or(StatusCode::UnexpectedNull)?;
+        let fd = reply.read_file_descriptor()?;
+        let size = usize::try_from(reply.read::<u64>()?).map_err(|_| StatusCode::BadValue)?;
+        let flags = reply.read::<i32>()? as u32;
+        let offset = usize::try_from(reply.read::<u64>()?).map_err(|_| StatusCode::BadValue)?;
+        Mapping::new(fd, size, flags, offset)
+    }
+}
+
+impl Interface for BpMemoryHeap {
+    fn as_binder(&self) -> SIBinder {
+        self.binder.clone()
+    }
+}
+
+impl Proxy for BpMemoryHeap {
+    fn descriptor() -> &'static str {
+        MEMORY_HEAP_DESCRIPTOR
+    }
+
+    fn from_binder(binder: SIBinder) -> Option<Self> {
+        crate::__rpc_stamp_descriptor(&binder, MEMORY_HEAP_DESCRIPTOR);
+        if binder.descriptor() != MEMORY_HEAP_DESCRIPTOR || binder.as_remote().is_none() {
+            return None;
+        }
+        Some(BpMemoryHeap {
+            binder,
+            mapping: OnceLock::new(),
+        })
+    }
+}
+
+impl IMemoryHeap for BpMemoryHeap {
+    fn heap_id(&self) -> i32 {
+        self.mapping()
+            .map_or(-1, |mapping| mapping.fd.as_fd().as_raw_fd())
+    }
+    fn size(&self) -> usize {
+        self.mapping().map_or(0, |mapping| mapping.size)
+    }
+    fn flags(&self) -> u32 {
+        self.mapping().map_or(0, |mapping| mapping.flags)
+    }
+    fn offset(&self) -> usize {
+        self.mapping().map_or(0, |mapping| mapping.offset)
+    }
+    fn base(&self) -> Option<&[u8]> {
+        self.mapping().map(Mapping::as_slice)
+    }
+}
+
+impl FromIBinder for dyn IMemoryHeap {
+    fn try_from(binder: SIBinder) -> Result<Strong<dyn IMemoryHeap>> {
+        match BpMemoryHeap::from_binder(binder.clone()) {
+            Some(proxy) => Ok(Strong::new(Box::new(proxy))),
+            None => Ok(Strong::new(Box::new(Binder::<BnMemoryHeap>::try_from(
+                binder,
+            )?))),
+        }
+    }
+}
+
+impl crate::Serialize for dyn IMemoryHeap + '_ {
+    fn serialize(&self, parcel: &mut Parcel) -> Result<()> {
+        parcel.write(&self.as_binder())
+    }
+}
+
+impl crate::SerializeOption for dyn IMemoryHeap + '_ {
+    fn serialize_option(this: Option<&Self>, parcel: &mut Parcel) -> Result<()> {
+        parcel.write(&this.map(Interface::as_binder))
+    }
+}
+
+impl fmt::Debug for dyn IMemoryHeap + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("IMemoryHeap")
+    }
+}
+
+/// A slice of a heap (AOSP `MemoryBase`).
+#[derive(Debug, Clone)]
+pub struct MemoryBase {
+    heap: Strong<dyn IMemoryHeap>,
+    offset: usize,
+    size: usize,
+}
+
+impl MemoryBase {
+    /// The `size` bytes of `heap` starting at `offset`. Fails with
+    /// [`StatusCode::BadValue`] if they do not fit in the heap.
+    pub fn new(heap: Strong<dyn IMemoryHeap>, offset: usize, size: usize) -> Result<Self> {
+        match offset.checked_add(size) {
+            Some(end) if end <= heap.size() => Ok(MemoryBase { heap, offset, size }),
+            _ => Err(StatusCode::BadValue),
+        }
+    }
+
+    /// The backing heap.
+    pub fn heap(&self) -> &Strong<dyn IMemoryHeap> {
+        &self.heap
+    }
+}
+
+impl Interface for MemoryBase {}
+
+impl IMemory for MemoryBase {
+    fn memory(&self) -> Option<&dyn IMemoryHeap> {
+        Some(&*self.heap)
+    }
+    fn offset(&self) -> usize {
+        self.offset
+    }
+    fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// A binder `Remotable` that serves an [`IMemory`] (AOSP `BnMemory`).
+pub struct BnMemory(Box<dyn IMemory>);
+
+impl BnMemory {
+    /// Publish `memory` as a binder.
+    pub fn new_binder<T: IMemory + 'static>(memory: T) -> Strong<dyn IMemory> {
+        Strong::new(Box::new(Binder::new(BnMemory(Box::new(memory)))))
+    }
+}
+
+impl Remotable for BnMemory {
+    fn descriptor() -> &'static str {
+        MEMORY_DESCRIPTOR
+    }
+
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        _reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            GET_MEMORY_TRANSACTION => {
+                let memory = &*self.0;
+                reply.write(&memory.memory().map(Interface::as_binder))?;
+                reply.write(&(memory.offset() as i64))?;
+                reply.write(&(memory.size() as u64))
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+
+    fn on_dump(&self, writer: &mut dyn std::io::Write, args: &[String]) -> Result<()> {
+        self.0.dump(writer, args)
+    }
+}
+
+impl IMemory for Binder<BnMemory> {
+    fn memory(&self) -> Option<&dyn IMemoryHeap> {
+        self.0.memory()
+    }
+    fn offset(&self) -> usize {
+        self.0.offset()
+    }
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+}
+
+/// The resolved `GET_MEMORY` reply of a [`BpMemory`].
+struct RemoteMemory {
+    heap: Strong<dyn IMemoryHeap>,
+    offset: usize,
+    size: usize,
+}
+
+/// A binder `Proxy` for a remote [`IMemory`] (AOSP `BpMemory`). The heap,
+/// offset and size are fetched on first use; the heap itself is mapped
+/// only when its base is needed.
+pub struct BpMemory {
+    binder: SIBinder,
+    memory: OnceLock<RemoteMemory>,
+}
+
+impl BpMemory {
+    fn remote(&self) -> Option<&RemoteMemory> {
+        if let Some(memory) = self.memory.get() {
+            return Some(memory);
+        }
+        match self.fetch() {
+            Ok(memory) => Some(self.memory.get_or_init(|| memory)),
+            Err(err) => {
+                log::error!("BpMemory: cannot resolve the remote memory: {err}");
+                None
+            }
+        }
+    }
+
+    fn fetch(&self) -> Result<RemoteMemory> {
+        let remote = self.binder.as_remote().ok_or(StatusCode::BadType)?;
+        let data = remote.prepare_transact(true)?;
+        let mut reply = remote
+            .submit_transact(GET_MEMORY_TRANSACTION, &data, 0)?
+            .ok_or(StatusCode::UnexpectedNull)?;
+        let heap: Option<SIBinder> = reply.read()?;
+        let heap = heap.ok_or(StatusCode::UnexpectedNull)?;
+        let offset = usize::try_from(reply.read::<i64>()?).map_err(|_| StatusCode::BadValue)?;
+        let size = usize::try_from(reply.read::<u64>()?).map_err(|_| StatusCode::BadValue)?;
+        Ok(RemoteMemory {
+            heap: FromIBinder::try_from(heap)?,
+            offset,
+            size,
+        })
+    }
+}
+
+impl Interface for BpMemory {
+    fn as_binder(&self) -> SIBinder {
+        self.binder.clone()
+    }
+}
+
+impl Proxy for BpMemory {
+    fn descriptor() -> &'static str {
+        MEMORY_DESCRIPTOR
+    }
+
+    fn from_binder(binder: SIBinder) -> Option<Self> {
+        crate::__rpc_stamp_descriptor(&binder, MEMORY_DESCRIPTOR);
+        if binder.descriptor() != MEMORY_DESCRIPTOR || binder.as_remote().is_none() {
+            return None;
+        }
+        Some(BpMemory {
+            binder,
+            memory: OnceLock::new(),
+        })
+    }
+}
+
+impl IMemory for BpMemory {
+    fn memory(&self) -> Option<&dyn IMemoryHeap> {
+        self.remote().map(|memory| &*memory.heap)
+    }
+    fn offset(&self) -> usize {
+        self.remote().map_or(0, |memory| memory.offset)
+    }
+    fn size(&self) -> usize {
+        self.remote().map_or(0, |memory| memory.size)
+    }
+}
+
+impl FromIBinder for dyn IMemory {
+    fn try_from(binder: SIBinder) -> Result<Strong<dyn IMemory>> {
+        match BpMemory::from_binder(binder.clone()) {
+            Some(proxy) => Ok(Strong::new(Box::new(proxy))),
+            None => Ok(Strong::new(Box::new(Binder::<BnMemory>::try_from(binder)?))),
+        }
+    }
+}
+
+impl crate::Serialize for dyn IMemory + '_ {
+    fn serialize(&self, parcel: &mut Parcel) -> Result<()> {
+        parcel.write(&self.as_binder())
+    }
+}
+
+impl crate::SerializeOption for dyn IMemory + '_ {
+    fn serialize_option(this: Option<&Self>, parcel: &mut Parcel) -> Result<()> {
+        parcel.write(&this.map(Interface::as_binder))
+    }
+}
+
+impl fmt::Debug for dyn IMemory + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("IMemory")
     }
 }
 
@@ -149,11 +686,10 @@ impl IMemoryHeap for MemoryHeapBase {
 mod tests {
     use super::*;
 
-    /// The public trait + struct surface compiles on the
-    /// host tree, including the macOS host where `memfd_create` is
-    /// absent. The stub constructor signals "not implemented" rather
-    /// than panicking, so caller code can opt out gracefully when
+    /// Without `memfd_create` the constructor signals "not implemented"
+    /// rather than panicking, so caller code can opt out gracefully when
     /// shared memory is unavailable.
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
     #[test]
     fn macos_stub_constructor_signals_not_implemented() {
         let err = MemoryHeapBase::new(4096, FLAG_READ_ONLY).unwrap_err();
@@ -167,12 +703,12 @@ mod tests {
     }
 
     /// The trait surface itself is object-safe — we can hold an
-    /// `&dyn IMemoryHeap`. The actual impl is exercised in hermetic
-    /// tests once the memfd backend exists.
+    /// `&dyn IMemoryHeap`.
     #[test]
     fn imemoryheap_is_object_safe() {
         // Trivial impl: zero-sized heap with no base mapping.
         struct Stub;
+        impl Interface for Stub {}
         impl IMemoryHeap for Stub {
             fn heap_id(&self) -> i32 {
                 42
@@ -193,4 +729,85 @@ mod tests {
         let h: &dyn IMemoryHeap = &Stub;
         assert_eq!(h.heap_id(), 42);
     }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn memfd_heap_is_page_rounded_and_shared_between_mappings() {
+        let page = rustix::param::page_size();
+        let heap = MemoryHeapBase::new(100, 0).unwrap();
+        assert_eq!(heap.size(), page);
+        assert_eq!(heap.offset(), 0);
+        assert!(heap.heap_id() >= 0);
+
+        // SAFETY: the heap is only accessed from this thread.
+        unsafe { heap.as_mut_ptr().add(10).write(0x5a) };
+        assert_eq!(heap.base().unwrap()[10], 0x5a);
+
+        // A second mapping of the same memfd sees the write.
+        let fd = rustix::io::fcntl_dupfd_cloexec(&heap.mapping.fd, 0).unwrap();
+        let other = MemoryHeapBase::from_fd(fd, page, FLAG_READ_ONLY, 0).unwrap();
+        assert_eq!(other.base().unwrap()[10], 0x5a);
+
+        assert_eq!(MemoryHeapBase::new(0, 0).unwrap_err(), StatusCode::BadValue);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn read_only_heap_rejects_writable_mappings_and_oversized_ranges() {
+        let page = rustix::param::page_size();
+        let heap = MemoryHeapBase::new(page, FLAG_READ_ONLY).unwrap();
+        let dup = || rustix::io::fcntl_dupfd_cloexec(&heap.mapping.fd, 0).unwrap();
+
+        // The creator still writes; a peer cannot map it writable.
+        // SAFETY: the heap is only accessed from this thread.
+        unsafe { heap.as_mut_ptr().write(1) };
+        assert!(MemoryHeapBase::from_fd(dup(), page, 0, 0).is_err());
+        assert_eq!(
+            MemoryHeapBase::from_fd(dup(), page, FLAG_READ_ONLY, 0)
+                .unwrap()
+                .base()
+                .unwrap()[0],
+            1
+        );
+
+        // Past the end of the memfd, or at an unaligned offset.
+        assert_eq!(
+            MemoryHeapBase::from_fd(dup(), 2 * page, FLAG_READ_ONLY, 0).unwrap_err(),
+            StatusCode::BadValue
+        );
+        assert_eq!(
+            MemoryHeapBase::from_fd(dup(), 1, FLAG_READ_ONLY, 1).unwrap_err(),
+            StatusCode::BadValue
+        );
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn memory_base_checks_bounds_and_slices_the_heap() {
+        let heap = MemoryHeapBase::new(4096, 0).unwrap();
+        // SAFETY: the heap is only accessed from this thread.
+        unsafe { heap.as_mut_ptr().add(8).write(7) };
+        let heap = BnMemoryHeap::new_binder(heap);
+        let size = heap.size();
+
+        assert_eq!(
+            MemoryBase::new(heap.clone(), size, 1).unwrap_err(),
+            StatusCode::BadValue
+        );
+        assert_eq!(
+            MemoryBase::new(heap.clone(), usize::MAX, 2).unwrap_err(),
+            StatusCode::BadValue
+        );
+
+        let memory = BnMemory::new_binder(MemoryBase::new(heap.clone(), 8, 16).unwrap());
+        assert_eq!(memory.offset(), 8);
+        assert_eq!(memory.as_slice().unwrap()[0], 7);
+        assert_eq!(memory.as_slice().unwrap().len(), 16);
+
+        // A local binder casts back to the local object.
+        let heap_again: Strong<dyn IMemoryHeap> = FromIBinder::try_from(heap.as_binder()).unwrap();
+        assert_eq!(heap_again.heap_id(), heap.heap_id());
+        let memory_again: Strong<dyn IMemory> = FromIBinder::try_from(memory.as_binder()).unwrap();
+        assert_eq!(memory_again.size(), 16);
+    }
 }
@@ -0,0 +1,273 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `SHELL_COMMAND_TRANSACTION` support (`cmd <service> ...`).
+//!
+//! A shell command carries the caller's stdin/stdout/stderr, the argument
+//! list, an optional [`IShellCallback`] for opening files in the caller's
+//! context, and an optional [`IResultReceiver`] that gets the command's
+//! result code. The layout is AOSP `IBinder::shellCommand`; the native
+//! side follows the NDK `ABBinder::onTransact` handling.
+//!
+//! Services implement [`crate::Interface::shell_command`]; the generated
+//! `Bn*` glue forwards [`crate::Remotable::on_shell_command`] to it. The
+//! default implementation answers `INVALID_OPERATION`, as AOSP `BBinder`
+//! does. Clients call [`crate::ProxyHandle::shell_command`].
+//!
+//! As in the NDK, only root, the shell user (`AID_SHELL`) and — since
+//! non-Android hosts have no shell user — the service's own uid may run
+//! shell commands. Others get `PERMISSION_DENIED`.
+//!
+//! `IShellCallback` and `IResultReceiver` are generated from
+//! `aidl/shell/com/android/internal/os/` (vendored from AOSP
+//! `frameworks/base/core/java/com/android/internal/os/`).
+
+include!(concat!(env!("OUT_DIR"), "/shell_command.rs"));
+
+pub use com::android::internal::os::IResultReceiver::{
+    BnResultReceiver, BpResultReceiver, IResultReceiver,
+};
+pub use com::android::internal::os::IShellCallback::{
+    BnShellCallback, BpShellCallback, IShellCallback,
+};
+
+use std::fs::File;
+
+use crate::error::{Result, StatusCode};
+use crate::parcelable::{DeserializeOption, SerializeOption};
+use crate::{FromIBinder, Parcel, SIBinder, Strong};
+
+/// `AID_ROOT`.
+const AID_ROOT: u32 = 0;
+/// `AID_SHELL`, the uid `adb shell` runs as.
+const AID_SHELL: u32 = 2000;
+
+/// An incoming shell command, handed to
+/// [`crate::Interface::shell_command`].
+pub struct ShellCommand {
+    /// The caller's standard input.
+    pub input: File,
+    /// The caller's standard output.
+    pub output: File,
+    /// The caller's standard error.
+    pub error: File,
+    /// The command line, without the service name.
+    pub args: Vec<String>,
+    callback: Option<Strong<dyn IShellCallback>>,
+}
+
+impl ShellCommand {
+    pub fn new(input: File, output: File, error: File, args: Vec<String>) -> Self {
+        Self {
+            input,
+            output,
+            error,
+            args,
+            callback: None,
+        }
+    }
+
+    /// The caller's [`IShellCallback`], if it sent one.
+    pub fn callback(&self) -> Option<&Strong<dyn IShellCallback>> {
+        self.callback.as_ref()
+    }
+
+    /// Open `path` in the caller's context through its [`IShellCallback`],
+    /// like Java `ShellCommand.openFileForSystem`. `mode` is a Java
+    /// `ParcelFileDescriptor` mode string (`"r"`, `"w"`, `"rw"`, ...).
+    ///
+    /// Fails with [`StatusCode::InvalidOperation`] if the caller sent no
+    /// callback and [`StatusCode::NameNotFound`] if it refused to open
+    /// the file.
+    pub fn open_file(&self, path: &str, selinux_context: &str, mode: &str) -> Result<File> {
+        let callback = self.callback.as_ref().ok_or(StatusCode::InvalidOperation)?;
+        let fd = callback
+            .openFile(path, selinux_context, mode)
+            .map_err(StatusCode::from)?
+            .ok_or(StatusCode::NameNotFound)?;
+        Ok(File::from(std::os::fd::OwnedFd::from(fd)))
+    }
+}
+
+impl std::fmt::Debug for ShellCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShellCommand")
+            .field("input", &self.input)
+            .field("output", &self.output)
+            .field("error", &self.error)
+            .field("args", &self.args)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+/// Serve `SHELL_COMMAND_TRANSACTION`: decode the request, check the caller
+/// and run `handler`, then report its status to the result receiver.
+pub(crate) fn on_transaction(
+    reader: &mut Parcel,
+    handler: impl FnOnce(&mut ShellCommand) -> Result<()>,
+) -> Result<()> {
+    let mut read_file = || reader.read_file_descriptor().map(File::from);
+    let files = (read_file(), read_file(), read_file());
+
+    let argc = reader.read::<i32>()?;
+    let mut args = Vec::new();
+    // AOSP stops early when the parcel runs dry rather than trusting argc.
+    for _ in 0..argc {
+        if reader.data_avail() == 0 {
+            break;
+        }
+        args.push(reader.read::<String>()?);
+    }
+    let callback: Option<SIBinder> = DeserializeOption::deserialize_option(reader)?;
+    let receiver: Option<SIBinder> = DeserializeOption::deserialize_option(reader)?;
+    let receiver = receiver.map(<dyn IResultReceiver>::try_from).transpose()?;
+
+    let send = |status: Result<()>| {
+        if let Some(receiver) = &receiver {
+            let code = i32::from(status.err().unwrap_or(StatusCode::Ok));
+            if let Err(err) = receiver.send(code) {
+                log::warn!("shell command: IResultReceiver::send failed: {err}");
+            }
+        }
+        status
+    };
+
+    let uid = crate::get_calling_uid();
+    if uid != AID_ROOT && uid != AID_SHELL && uid != rustix::process::getuid().as_raw() {
+        log::warn!("shell command: uid {uid} is not allowed");
+        return send(Err(StatusCode::PermissionDenied));
+    }
+
+    let (Ok(input), Ok(output), Ok(error)) = files else {
+        log::error!("shell command: missing in/out/err file descriptors");
+        return send(Err(StatusCode::BadValue));
+    };
+
+    let callback = callback.map(<dyn IShellCallback>::try_from).transpose()?;
+    let mut command = ShellCommand {
+        input,
+        output,
+        error,
+        args,
+        callback,
+    };
+    send(handler(&mut command))
+}
+
+/// Write a `SHELL_COMMAND_TRANSACTION` request (AOSP
+/// `IBinder::shellCommand`). The fds are borrowed; the driver dups them
+/// into the target.
+pub(crate) fn write_request(
+    parcel: &mut Parcel,
+    fds: [std::os::fd::BorrowedFd<'_>; 3],
+    args: &[String],
+    callback: Option<&Strong<dyn IShellCallback>>,
+    receiver: Option<&Strong<dyn IResultReceiver>>,
+) -> Result<()> {
+    for fd in fds {
+        parcel.write_file_descriptor(fd)?;
+    }
+    let argc = i32::try_from(args.len()).map_err(|_| StatusCode::BadValue)?;
+    parcel.write::<i32>(&argc)?;
+    for arg in args {
+        parcel.write(arg)?;
+    }
+    let callback = callback.map(|callback| callback.as_binder());
+    SerializeOption::serialize_option(callback.as_ref(), parcel)?;
+    let receiver = receiver.map(|receiver| receiver.as_binder());
+    SerializeOption::serialize_option(receiver.as_ref(), parcel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binder_object::flat_binder_object;
+    use std::io::{Read, Write};
+
+    fn pipe() -> (File, File) {
+        use std::os::fd::OwnedFd;
+        let (read, write) = std::os::unix::net::UnixStream::pair().unwrap();
+        (
+            File::from(OwnedFd::from(read)),
+            File::from(OwnedFd::from(write)),
+        )
+    }
+
+    /// A request as the driver would deliver it, plus the caller's ends
+    /// of stdout/stderr. The parcel borrows the fds, so the target ends
+    /// are returned too and must outlive it.
+    fn request(args: &[String]) -> (Parcel, [File; 3], File, File) {
+        use std::os::fd::AsFd;
+
+        let (input, _) = pipe();
+        let (out_read, output) = pipe();
+        let (err_read, error) = pipe();
+
+        let mut parcel = Parcel::new();
+        write_request(
+            &mut parcel,
+            [input.as_fd(), output.as_fd(), error.as_fd()],
+            args,
+            None,
+            None,
+        )
+        .unwrap();
+        parcel.set_data_position(0);
+        (parcel, [input, output, error], out_read, err_read)
+    }
+
+    #[test]
+    fn request_round_trip() {
+        let args = vec!["set-level".to_owned(), "3".to_owned()];
+        let (mut parcel, _fds, mut out_read, mut err_read) = request(&args);
+
+        on_transaction(&mut parcel, |command| {
+            assert_eq!(command.args, ["set-level", "3"]);
+            assert!(command.callback().is_none());
+            assert_eq!(
+                command.open_file("/x", "", "r").err(),
+                Some(StatusCode::InvalidOperation)
+            );
+            writeln!(command.output, "level=3").unwrap();
+            writeln!(command.error, "warning").unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let mut out = [0u8; 8];
+        out_read.read_exact(&mut out).unwrap();
+        assert_eq!(&out, b"level=3\n");
+        let mut err = [0u8; 8];
+        err_read.read_exact(&mut err).unwrap();
+        assert_eq!(&err, b"warning\n");
+    }
+
+    #[test]
+    fn handler_status_is_returned() {
+        let (mut parcel, _fds, _out, _err) = request(&[]);
+        assert_eq!(
+            on_transaction(&mut parcel, |_| Err(StatusCode::BadValue)),
+            Err(StatusCode::BadValue)
+        );
+    }
+
+    #[test]
+    fn missing_fds_are_rejected() {
+        // Plain bytes where the fd objects belong.
+        let mut parcel = Parcel::new();
+        for _ in 0..3 {
+            parcel
+                .write_aligned_data(&[0u8; std::mem::size_of::<flat_binder_object>()])
+                .unwrap();
+        }
+        parcel.write::<i32>(&0).unwrap();
+        SerializeOption::serialize_option(None::<&SIBinder>, &mut parcel).unwrap();
+        SerializeOption::serialize_option(None::<&SIBinder>, &mut parcel).unwrap();
+        parcel.set_data_position(0);
+        assert_eq!(
+            on_transaction(&mut parcel, |_| unreachable!()),
+            Err(StatusCode::BadValue)
+        );
+    }
+}
@@ -214,6 +214,11 @@ impl Status {
         }
     }
 
+    /// The exception message carried by this status, if any.
+    pub(crate) fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
     pub fn service_specific_error(&self) -> i32 {
         if let StatusCode::ServiceSpecific(err) = self.code {
             err
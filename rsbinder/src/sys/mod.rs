@@ -55,10 +55,9 @@ pub mod binder {
     pub const BR_ONEWAY_SPAM_SUSPECT: binder_driver_return_protocol =
         binder_driver_return_protocol_BR_ONEWAY_SPAM_SUSPECT;
 
-    // Freeze observer additions (Android 14+, kernel 6.5+). These BR
-    // constants are exposed so the RETURN_STRINGS table can decode them;
-    // dispatch is not yet wired, so the default arm still treats them as
-    // "Unknown BR_ return" and the kernel wire stays byte-unchanged.
+    // Freeze observer additions (Android 14+, kernel 6.5+).
+    // `BR_FROZEN_BINDER` / `BR_CLEAR_FREEZE_NOTIFICATION_DONE` are
+    // dispatched by `thread_state::execute_command`.
     pub const BR_TRANSACTION_PENDING_FROZEN: binder_driver_return_protocol =
         binder_driver_return_protocol_BR_TRANSACTION_PENDING_FROZEN;
     pub const BR_FROZEN_BINDER: binder_driver_return_protocol =
@@ -104,11 +103,9 @@ pub mod binder {
     pub const BC_REPLY_SG: binder_driver_command_protocol =
         binder_driver_command_protocol_BC_REPLY_SG;
 
-    // Freeze observer BC counterparts. The send path
-    // (`BC_REQUEST_FREEZE_NOTIFICATION` and friends) is not yet wired;
-    // these constants plus the `binder_handle_cookie` struct and
-    // `binder_frozen_state_info` payload are exposed so call sites can
-    // pattern-match on them once the dispatch arms land.
+    // Freeze observer BC counterparts, sent by
+    // `ProxyHandle::{add,remove}_frozen_state_change_callback` and the
+    // `BR_FROZEN_BINDER` arm.
     pub const BC_REQUEST_FREEZE_NOTIFICATION: binder_driver_command_protocol =
         binder_driver_command_protocol_BC_REQUEST_FREEZE_NOTIFICATION;
     pub const BC_CLEAR_FREEZE_NOTIFICATION: binder_driver_command_protocol =
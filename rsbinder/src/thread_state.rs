@@ -58,13 +58,13 @@
 //! `process_pending_derefs`.
 
 use log::error;
-use std::backtrace::Backtrace;
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::ffi::{CStr, CString};
 use std::fmt::Debug;
-use std::fs::File;
-use std::sync::{atomic::Ordering, Arc};
+use std::ops::ControlFlow;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
 
 use crate::{binder::*, error::*, parcel::*, process_state::*, sys::*};
 
@@ -72,10 +72,54 @@ use crate::{binder::*, error::*, parcel::*, process_state::*, sys::*};
 // calls that may re-borrow either cell (user callbacks, other binder entry
 // points). Use the P2 (minimal scope) and P3 (stack-save) patterns.
 thread_local! {
-    static THREAD_STATE: RefCell<ThreadState> = RefCell::new(ThreadState::new());
+    /// The thread's `ThreadState` for the [`ProcessState::init`]
+    /// singleton. Reached through [`THREAD_STATE`].
+    static DEFAULT_THREAD_STATE: RefCell<ThreadState> = RefCell::new(ThreadState::new());
+    /// The thread's `ThreadState` per additional binder context it has
+    /// used, keyed by the context's `ProcessState` address. Reached
+    /// through [`THREAD_STATE`].
+    static THREAD_STATES: RefCell<Vec<(usize, Rc<RefCell<ThreadState>>)>> =
+        const { RefCell::new(Vec::new()) };
     static BINDER_DEREFS: RefCell<BinderDerefs> = RefCell::new(BinderDerefs::new());
 }
 
+/// The `ThreadState` of the thread's current binder context
+/// (`ProcessState::as_self`), created on first use. A thread that calls
+/// into a second context keeps one per context: each has its own driver,
+/// command buffers and transaction stack.
+///
+/// The default context's is a thread-local of its own, so a process that
+/// opens no `BinderContext` pays one context lookup per access and
+/// nothing more. Those of additional contexts are few per thread and
+/// found by a short scan.
+static THREAD_STATE: ThreadStates = ThreadStates;
+
+struct ThreadStates;
+
+impl ThreadStates {
+    fn with<R>(&self, f: impl FnOnce(&RefCell<ThreadState>) -> R) -> R {
+        let Some(context) = ProcessState::current() else {
+            return DEFAULT_THREAD_STATE.with(f);
+        };
+        let context = context as *const ProcessState as usize;
+        // Cloned out of the list, whose borrow must not be held across
+        // `f`: a nested call into another context adds to it.
+        let state = THREAD_STATES.with(|states| {
+            let found = states
+                .borrow()
+                .iter()
+                .find(|(key, _)| *key == context)
+                .map(|(_, state)| state.clone());
+            found.unwrap_or_else(|| {
+                let state = Rc::new(RefCell::new(ThreadState::new()));
+                states.borrow_mut().push((context, state.clone()));
+                state
+            })
+        });
+        f(&state)
+    }
+}
+
 // ---- RPC calling context (Plan 2-16 Phase B) ------------------------
 //
 // The kernel calling identity lives in `THREAD_STATE.transaction`, but
@@ -507,7 +551,13 @@ pub(crate) struct ThreadState {
     is_looper: bool,
     is_flushing: bool,
     call_restriction: CallRestriction,
-    driver: Arc<File>,
+    driver: Driver,
+    /// Set by `BR_ONEWAY_SPAM_SUSPECT`, taken by
+    /// [`take_oneway_spam_suspect`].
+    oneway_spam_suspect: bool,
+    /// Driver detail of the last `BR_FAILED_REPLY`, read when it arrived
+    /// and kept for [`get_extended_error`].
+    extended_error: Option<ExtendedError>,
 }
 
 impl ThreadState {
@@ -520,7 +570,9 @@ impl ThreadState {
             is_looper: false,
             is_flushing: false,
             call_restriction: ProcessState::as_self().call_restriction(),
-            driver: ProcessState::as_self().driver(),
+            driver: ProcessState::as_self().binder_driver().clone(),
+            oneway_spam_suspect: false,
+            extended_error: None,
         }
     }
 
@@ -637,9 +689,34 @@ impl ThreadState {
             }
         };
 
+        // A parcel carrying `BINDER_TYPE_PTR` buffers must go out as the
+        // `_SG` variant so the driver reserves `buffers_size` extra bytes in
+        // the target buffer for the scatter-gather copies (AOSP libhwbinder
+        // `IPCThreadState::writeTransactionData`). Status-only replies carry
+        // no objects and keep the plain command.
+        let buffers_size = if *status == StatusCode::Ok.into() {
+            data.sg_buffers_size()
+        } else {
+            0
+        };
+        let cmd = match cmd {
+            binder::BC_TRANSACTION if buffers_size > 0 => binder::BC_TRANSACTION_SG,
+            binder::BC_REPLY if buffers_size > 0 => binder::BC_REPLY_SG,
+            cmd => cmd,
+        };
+
         let start = self.out_parcel.data_size();
         self.out_parcel.write::<u32>(&cmd)?;
-        if let Err(e) = self.out_parcel.write_aligned(&tr) {
+        let written = if buffers_size > 0 {
+            self.out_parcel
+                .write_aligned(&binder::binder_transaction_data_sg {
+                    transaction_data: tr,
+                    buffers_size: buffers_size as _,
+                })
+        } else {
+            self.out_parcel.write_aligned(&tr)
+        };
+        if let Err(e) = written {
             // Roll back the orphan cmd word: flushing a bare BC_* opcode with
             // no binder_transaction_data behind it would desync the driver
             // protocol.
@@ -713,6 +790,38 @@ pub fn get_strict_mode_policy() -> i32 {
     THREAD_STATE.with(|thread_state| thread_state.borrow().strict_mode_policy)
 }
 
+/// Flags of the transaction this thread is serving (`0` outside one, or in
+/// a pure-RPC process).
+pub(crate) fn last_transaction_binder_flags() -> u32 {
+    if !ProcessState::is_initialized() {
+        return 0;
+    }
+    THREAD_STATE.with(|thread_state| thread_state.borrow().last_transaction_binder_flags())
+}
+
+/// Whether the last oneway call on this thread was flagged
+/// `BR_ONEWAY_SPAM_SUSPECT`; clears the flag.
+pub(crate) fn take_oneway_spam_suspect() -> bool {
+    THREAD_STATE
+        .with(|thread_state| std::mem::take(&mut thread_state.borrow_mut().oneway_spam_suspect))
+}
+
+/// The driver detail of this thread's last failed transaction, if the
+/// driver gave one and [`get_extended_error`] has not returned it yet.
+pub(crate) fn last_extended_error() -> Option<ExtendedError> {
+    THREAD_STATE.with(|thread_state| thread_state.borrow().extended_error)
+}
+
+/// Take the kept detail; a deadline helper hands its failures' detail to
+/// the caller's thread with [`set_extended_error`].
+pub(crate) fn take_extended_error() -> Option<ExtendedError> {
+    THREAD_STATE.with(|thread_state| thread_state.borrow_mut().extended_error.take())
+}
+
+pub(crate) fn set_extended_error(extended_error: Option<ExtendedError>) {
+    THREAD_STATE.with(|thread_state| thread_state.borrow_mut().extended_error = extended_error);
+}
+
 pub(crate) fn should_propagate_work_source() -> bool {
     THREAD_STATE.with(|thread_state| {
         thread_state
@@ -766,145 +875,163 @@ fn wait_for_response(until: UntilResponse) -> Result<Option<Parcel>> {
             }
             let cmd: u32 = thread_state.borrow_mut().in_parcel.read::<i32>()? as _;
 
-            log::trace!("{:?}", return_to_str(cmd));
+            if let ControlFlow::Break(reply) = handle_response_command(thread_state, cmd, &until)? {
+                return Ok(reply);
+            }
+        }
+    })
+}
 
-            match cmd {
-                binder::BR_ONEWAY_SPAM_SUSPECT => {
-                    log::error!("Process seems to be sending too many oneway calls.");
-                    log::error!("{}", Backtrace::capture());
+/// One step of [`wait_for_response`]: handle `cmd`, breaking with the
+/// result once the awaited response has arrived.
+fn handle_response_command(
+    thread_state: &RefCell<ThreadState>,
+    cmd: u32,
+    until: &UntilResponse,
+) -> Result<ControlFlow<Option<Parcel>>> {
+    log::trace!("{:?}", return_to_str(cmd));
 
-                    if let UntilResponse::TransactionComplete = until {
-                        break;
-                    }
-                }
-                binder::BR_TRANSACTION_COMPLETE => {
-                    if let UntilResponse::TransactionComplete = until {
-                        break;
-                    }
-                }
-                binder::BR_TRANSACTION_PENDING_FROZEN => {
-                    log::warn!("Sending oneway calls to frozen process.");
-                    break;
-                }
-                binder::BR_DEAD_REPLY => {
-                    return Err(StatusCode::DeadObject);
-                }
-                binder::BR_FAILED_REPLY => {
-                    log::error!(
-                        "Received FAILED_REPLY transaction reply for pid {}",
-                        thread_state
-                            .borrow()
-                            .transaction
-                            .map_or(0, |state| state.calling_pid)
-                    );
-                    return Err(StatusCode::FailedTransaction);
-                }
-                binder::BR_FROZEN_REPLY => {
-                    log::error!(
-                        "Received FROZEN_REPLY transaction reply for pid {}",
-                        thread_state
-                            .borrow()
-                            .transaction
-                            .map_or(0, |state| state.calling_pid)
-                    );
-                    return Err(StatusCode::FailedTransaction);
-                }
-                binder::BR_ACQUIRE_RESULT => {
-                    let result = thread_state.borrow_mut().in_parcel.read::<i32>()?;
-                    if let UntilResponse::AcquireResult = until {
-                        let res = if result != 0 {
-                            Ok(None)
-                        } else {
-                            Err(StatusCode::InvalidOperation)
-                        };
-                        return res;
-                    } else if cfg!(debug_assertions) {
-                        panic!("Unexpected BR_ACQUIRE_RESULT");
-                    }
-                }
-                binder::BR_REPLY => {
-                    let tr = thread_state
-                        .borrow_mut()
-                        .in_parcel
-                        .read::<binder::binder_transaction_data>()?;
-                    // SAFETY: for a kernel-delivered BR_REPLY the driver populates
-                    // the `data.ptr` arm of the union (buffer/offsets pointers into
-                    // the mmap region), so reading that arm is valid.
-                    let (buffer, offsets) = unsafe { (tr.data.ptr.buffer, tr.data.ptr.offsets) };
-                    if let UntilResponse::Reply = until {
-                        if (tr.flags & transaction_flags_TF_STATUS_CODE) == 0 {
-                            // SAFETY: buffer and offsets are valid pointers from binder driver
-                            // transaction data, with sizes given by tr.data_size and tr.offsets_size
-                            let reply = unsafe {
-                                Parcel::from_ipc_parts(
-                                    buffer as _,
-                                    tr.data_size as _,
-                                    offsets as _,
-                                    (tr.offsets_size as usize)
-                                        / std::mem::size_of::<binder::binder_size_t>(),
-                                    free_buffer,
-                                )
-                            };
-                            return Ok(Some(reply));
-                        } else {
-                            // SAFETY: Reading status code from binder transaction reply
-                            // - We verify tr.data_size >= size_of::<i32>() before reading
-                            // - buffer points to valid memory owned by binder driver
-                            // - The data remains valid for the transaction lifetime
-                            // - We convert to StatusCode immediately after reading
-                            let status: StatusCode =
-                                if tr.data_size >= std::mem::size_of::<i32>() as u64 {
-                                    unsafe { (*(buffer as *const i32)).into() }
-                                } else {
-                                    log::error!(
-                                        "Buffer too small for status code: {} < {}",
-                                        tr.data_size,
-                                        std::mem::size_of::<i32>()
-                                    );
-                                    StatusCode::BadValue
-                                };
-                            log::trace!("binder::BR_REPLY ({status})");
-                            free_buffer(
-                                None,
-                                buffer,
-                                tr.data_size as _,
-                                offsets,
-                                (tr.offsets_size as usize) / std::mem::size_of::<binder_size_t>(),
-                            )?;
+    match cmd {
+        binder::BR_ONEWAY_SPAM_SUSPECT => {
+            // Sent in place of BR_TRANSACTION_COMPLETE; the proxy
+            // reports it once the call returns.
+            thread_state.borrow_mut().oneway_spam_suspect = true;
 
-                            if status != StatusCode::Ok {
-                                log::warn!("binder::BR_REPLY ({status})");
-                                return Err(status);
-                            }
-                            // AOSP `IPCThreadState::waitForResponse` ends the
-                            // status-code branch with an unconditional `goto
-                            // finish` (`return err`, even for NO_ERROR), so a
-                            // `TF_STATUS_CODE` reply carrying status 0 yields a
-                            // successful empty reply — it never loops. Falling
-                            // through here instead would re-enter the outer
-                            // `loop` and block in `talk_with_driver` waiting for
-                            // a command a conforming peer never sends, hanging
-                            // (potentially the main thread) on a malformed or
-                            // hostile reply. Return the empty reply to match.
-                            return Ok(Some(Parcel::new()));
-                        }
-                    } else {
-                        free_buffer(
-                            None,
-                            buffer,
+            if let UntilResponse::TransactionComplete = until {
+                return Ok(ControlFlow::Break(None));
+            }
+        }
+        binder::BR_TRANSACTION_COMPLETE => {
+            if let UntilResponse::TransactionComplete = until {
+                return Ok(ControlFlow::Break(None));
+            }
+        }
+        binder::BR_TRANSACTION_PENDING_FROZEN => {
+            log::warn!("Sending oneway calls to frozen process.");
+            return Ok(ControlFlow::Break(None));
+        }
+        binder::BR_DEAD_REPLY => {
+            return Err(StatusCode::DeadObject);
+        }
+        binder::BR_FAILED_REPLY => {
+            log::error!(
+                "Received FAILED_REPLY transaction reply for pid {}",
+                thread_state
+                    .borrow()
+                    .transaction
+                    .map_or(0, |state| state.calling_pid)
+            );
+            // Reading resets the driver's copy, so keep it for
+            // `get_extended_error`.
+            let detail = read_extended_error()
+                .ok()
+                .filter(|ee| ee.command != binder::BR_OK);
+            thread_state.borrow_mut().extended_error = detail;
+            return Err(StatusCode::FailedTransaction);
+        }
+        binder::BR_FROZEN_REPLY => {
+            log::error!(
+                "Received FROZEN_REPLY transaction reply for pid {}",
+                thread_state
+                    .borrow()
+                    .transaction
+                    .map_or(0, |state| state.calling_pid)
+            );
+            return Err(StatusCode::FailedTransaction);
+        }
+        binder::BR_ACQUIRE_RESULT => {
+            let result = thread_state.borrow_mut().in_parcel.read::<i32>()?;
+            if let UntilResponse::AcquireResult = until {
+                let res = if result != 0 {
+                    Ok(None)
+                } else {
+                    Err(StatusCode::InvalidOperation)
+                };
+                return res.map(ControlFlow::Break);
+            } else if cfg!(debug_assertions) {
+                panic!("Unexpected BR_ACQUIRE_RESULT");
+            }
+        }
+        binder::BR_REPLY => {
+            let tr = thread_state
+                .borrow_mut()
+                .in_parcel
+                .read::<binder::binder_transaction_data>()?;
+            // SAFETY: for a kernel-delivered BR_REPLY the driver populates
+            // the `data.ptr` arm of the union (buffer/offsets pointers into
+            // the mmap region), so reading that arm is valid.
+            let (buffer, offsets) = unsafe { (tr.data.ptr.buffer, tr.data.ptr.offsets) };
+            if let UntilResponse::Reply = until {
+                if (tr.flags & transaction_flags_TF_STATUS_CODE) == 0 {
+                    // SAFETY: buffer and offsets are valid pointers from binder driver
+                    // transaction data, with sizes given by tr.data_size and tr.offsets_size
+                    let reply = unsafe {
+                        Parcel::from_ipc_parts(
+                            buffer as _,
                             tr.data_size as _,
-                            offsets,
-                            (tr.offsets_size as usize) / std::mem::size_of::<binder_size_t>(),
-                        )?;
+                            offsets as _,
+                            (tr.offsets_size as usize)
+                                / std::mem::size_of::<binder::binder_size_t>(),
+                            free_buffer,
+                        )
+                    };
+                    return Ok(ControlFlow::Break(Some(reply)));
+                } else {
+                    // SAFETY: Reading status code from binder transaction reply
+                    // - We verify tr.data_size >= size_of::<i32>() before reading
+                    // - buffer points to valid memory owned by binder driver
+                    // - The data remains valid for the transaction lifetime
+                    // - We convert to StatusCode immediately after reading
+                    let status: StatusCode = if tr.data_size >= std::mem::size_of::<i32>() as u64 {
+                        unsafe { (*(buffer as *const i32)).into() }
+                    } else {
+                        log::error!(
+                            "Buffer too small for status code: {} < {}",
+                            tr.data_size,
+                            std::mem::size_of::<i32>()
+                        );
+                        StatusCode::BadValue
+                    };
+                    log::trace!("binder::BR_REPLY ({status})");
+                    free_buffer(
+                        None,
+                        buffer,
+                        tr.data_size as _,
+                        offsets,
+                        (tr.offsets_size as usize) / std::mem::size_of::<binder_size_t>(),
+                    )?;
+
+                    if status != StatusCode::Ok {
+                        log::warn!("binder::BR_REPLY ({status})");
+                        return Err(status);
                     }
+                    // AOSP `IPCThreadState::waitForResponse` ends the
+                    // status-code branch with an unconditional `goto
+                    // finish` (`return err`, even for NO_ERROR), so a
+                    // `TF_STATUS_CODE` reply carrying status 0 yields a
+                    // successful empty reply — it never loops. Falling
+                    // through here instead would re-enter the outer
+                    // `loop` and block in `talk_with_driver` waiting for
+                    // a command a conforming peer never sends, hanging
+                    // (potentially the main thread) on a malformed or
+                    // hostile reply. Return the empty reply to match.
+                    return Ok(ControlFlow::Break(Some(Parcel::new())));
                 }
-                _ => {
-                    execute_command(cmd as _)?;
-                }
-            };
+            } else {
+                free_buffer(
+                    None,
+                    buffer,
+                    tr.data_size as _,
+                    offsets,
+                    (tr.offsets_size as usize) / std::mem::size_of::<binder_size_t>(),
+                )?;
+            }
         }
-        Ok(None)
-    })
+        _ => {
+            execute_command(cmd as _)?;
+        }
+    }
+    Ok(ControlFlow::Continue(()))
 }
 
 /// Drive the kernel handshake for `BR_DEAD_BINDER` so that a
@@ -994,14 +1121,24 @@ where
 /// calls (re-entering this module). See module doc.
 fn dispatch_transact_caught(
     transactable: &dyn Transactable,
-    code: TransactionCode,
+    descriptor: &str,
+    tr: &binder::binder_transaction_data,
     reader: &mut Parcel,
     reply: &mut Parcel,
 ) -> Result<()> {
+    let code = tr.code;
+    let trace = crate::trace::Transaction::server(
+        crate::trace::KERNEL,
+        descriptor,
+        code,
+        tr.flags,
+        reader,
+        Some((tr.sender_euid, tr.sender_pid)),
+    );
     let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
         transactable.transact(code, reader, reply)
     }));
-    match result {
+    let result = match result {
         Ok(transact_result) => transact_result,
         Err(payload) => {
             let msg = payload
@@ -1016,7 +1153,12 @@ fn dispatch_transact_caught(
             *reply = Parcel::new();
             Err(StatusCode::Unknown)
         }
+    };
+    match result {
+        Ok(()) => trace.finish(StatusCode::Ok, Some(reply)),
+        Err(err) => trace.finish(err, None),
     }
+    result
 }
 
 fn execute_command(cmd: i32) -> Result<()> {
@@ -1116,7 +1258,8 @@ fn execute_command(cmd: i32) -> Result<()> {
                                     let result = match strong.as_transactable() {
                                         Some(t) => dispatch_transact_caught(
                                             t,
-                                            tr_secctx.transaction_data.code,
+                                            strong.descriptor(),
+                                            &tr_secctx.transaction_data,
                                             &mut reader,
                                             &mut reply,
                                         ),
@@ -1142,7 +1285,8 @@ fn execute_command(cmd: i32) -> Result<()> {
                             Some(context) => match context.as_transactable() {
                                 Some(t) => dispatch_transact_caught(
                                     t,
-                                    tr_secctx.transaction_data.code,
+                                    context.descriptor(),
+                                    &tr_secctx.transaction_data,
                                     &mut reader,
                                     &mut reply,
                                 ),
@@ -1348,6 +1492,36 @@ fn execute_command(cmd: i32) -> Result<()> {
                 let mut state = thread_state.borrow_mut();
                 state.in_parcel.read::<binder::binder_uintptr_t>()?;
             }
+            binder::BR_FROZEN_BINDER => {
+                let info = thread_state
+                    .borrow_mut()
+                    .in_parcel
+                    .read::<binder::binder_frozen_state_info>()?;
+
+                log::trace!(
+                    "BR_FROZEN_BINDER: handle {:X} is_frozen {}",
+                    info.cookie,
+                    info.is_frozen
+                );
+
+                // User callbacks run with no THREAD_STATE borrow held (R1).
+                ProcessState::as_self()
+                    .send_frozen_state_for_handle(info.cookie as _, (info.is_frozen != 0).into());
+
+                // The kernel holds further reports for this ref until it
+                // sees the ack, so queue it even if no proxy was alive.
+                let mut state = thread_state.borrow_mut();
+                state
+                    .out_parcel
+                    .write::<u32>(&(binder::BC_FREEZE_NOTIFICATION_DONE))?;
+                state
+                    .out_parcel
+                    .write::<binder::binder_uintptr_t>(&info.cookie)?;
+            }
+            binder::BR_CLEAR_FREEZE_NOTIFICATION_DONE => {
+                let mut state = thread_state.borrow_mut();
+                state.in_parcel.read::<binder::binder_uintptr_t>()?;
+            }
             _ => {
                 log::error!("*** BAD COMMAND {cmd} received from Binder driver\n");
                 return Err(StatusCode::Unknown);
@@ -1373,7 +1547,7 @@ fn execute_command(cmd: i32) -> Result<()> {
 /// tightened: future EINTR / signal-safety / cancellation handling
 /// changes, or any logic that gains a same-thread Rust callback here,
 /// would break the no-re-entry assumption and quietly turn this into
-/// an R1 violation. A defensive refactor would clone the `Arc<File>`
+/// an R1 violation. A defensive refactor would clone the `Driver`
 /// out under a short borrow and pass it by value across the syscall.
 /// Not done today (no concrete risk); recorded so a future change
 /// knows to revisit.
@@ -1435,7 +1609,7 @@ fn talk_with_driver(do_receive: bool) -> Result<()> {
         // }
 
         loop {
-            let res = binder::write_read(&thread_state.borrow().driver, &mut bwr);
+            let res = thread_state.borrow().driver.write_read(&mut bwr);
             match res {
                 Ok(_) => break,
                 Err(errno) if errno != rustix::io::Errno::INTR => {
@@ -1605,15 +1779,19 @@ pub(crate) fn flush_if_needed() -> Result<bool> {
     })
 }
 
-pub(crate) fn _handle_commands() -> Result<()> {
-    while {
-        get_and_execute_command()?;
-
-        THREAD_STATE.with(|thread_state| -> bool { !thread_state.borrow().in_parcel.is_empty() })
-    } {
-        flush_commands()?;
+#[cfg(feature = "tokio")]
+/// Handle the work a polling thread was woken for (AOSP
+/// `IPCThreadState::handlePolledCommands`), without blocking for more.
+pub(crate) fn handle_polled_commands() -> Result<()> {
+    talk_with_driver(true)?;
+    while THREAD_STATE.with(|thread_state| !thread_state.borrow().in_parcel.is_empty()) {
+        let cmd = THREAD_STATE.with(|thread_state| -> Result<i32> {
+            thread_state.borrow_mut().in_parcel.read::<i32>()
+        })?;
+        execute_command(cmd)?;
     }
-    Ok(())
+    process_pending_derefs()?;
+    flush_commands()
 }
 
 pub(crate) fn check_interface(reader: &mut Parcel, descriptor: &str) -> Result<bool> {
@@ -1655,11 +1833,23 @@ pub(crate) fn transact(
     handle: u32,
     code: u32,
     data: &Parcel,
-    mut flags: u32,
+    flags: u32,
 ) -> Result<Option<Parcel>> {
-    let mut reply: Option<Parcel> = None;
+    let until = write_transaction(handle, code, data, flags)?;
+    wait_for_response(until)
+}
 
+/// Queue `BC_TRANSACTION` and return the response it must wait for.
+fn write_transaction(
+    handle: u32,
+    code: u32,
+    data: &Parcel,
+    mut flags: u32,
+) -> Result<UntilResponse> {
     flags |= transaction_flags_TF_ACCEPT_FDS;
+    // The driver resets its extended error when a transaction starts;
+    // drop a detail kept from an earlier failure likewise.
+    set_extended_error(None);
 
     // Enforce the call restriction BEFORE queuing BC_TRANSACTION into
     // out_parcel. `write_transaction_data` records raw pointers into `data`'s
@@ -1688,12 +1878,80 @@ pub(crate) fn transact(
     })?;
 
     if (flags & transaction_flags_TF_ONE_WAY) == 0 {
-        reply = wait_for_response(UntilResponse::Reply)?;
+        Ok(UntilResponse::Reply)
     } else {
-        wait_for_response(UntilResponse::TransactionComplete)?;
+        Ok(UntilResponse::TransactionComplete)
+    }
+}
+
+/// A transaction started with [`start_transact`] whose response has not
+/// arrived yet. Only the thread that started it may poll or wait for it.
+pub(crate) struct PendingTransaction {
+    until: UntilResponse,
+}
+
+/// Send a transaction without waiting for its response: the non-blocking
+/// half of [`transact`] for threads that poll the driver (see
+/// `poll_fd`) or hand the wait to a helper thread. `data` is copied by
+/// the driver before this returns.
+pub(crate) fn start_transact(
+    handle: u32,
+    code: u32,
+    data: &Parcel,
+    flags: u32,
+) -> Result<PendingTransaction> {
+    let until = write_transaction(handle, code, data, flags)?;
+    if let Err(err) = flush_commands() {
+        // Do not leave a BC_TRANSACTION pointing into `data` behind.
+        THREAD_STATE.with(|thread_state| thread_state.borrow_mut().out_parcel.set_data_size(0))?;
+        return Err(err);
+    }
+    Ok(PendingTransaction { until })
+}
+
+impl PendingTransaction {
+    /// Block until the response arrives: the second half of [`transact`].
+    pub(crate) fn wait(self) -> Result<Option<Parcel>> {
+        wait_for_response(self.until)
     }
 
-    Ok(reply)
+    /// Read and handle what the driver has for this thread; call it once
+    /// [`poll_fd`] is readable or [`has_pending_input`] is true.
+    /// `Ok(None)` means the response has not arrived yet.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn poll(&self) -> Result<Option<Option<Parcel>>> {
+        THREAD_STATE.with(|thread_state| -> Result<Option<Option<Parcel>>> {
+            if thread_state.borrow().in_parcel.is_empty() {
+                talk_with_driver(true)?;
+            }
+            while !thread_state.borrow().in_parcel.is_empty() {
+                let cmd: u32 = thread_state.borrow_mut().in_parcel.read::<i32>()? as _;
+                if let ControlFlow::Break(reply) =
+                    handle_response_command(thread_state, cmd, &self.until)?
+                {
+                    return Ok(Some(reply));
+                }
+            }
+            // Reading again here could block; flush what the handled
+            // commands queued and wait for readiness instead.
+            flush_commands()?;
+            Ok(None)
+        })
+    }
+}
+
+#[cfg(feature = "tokio")]
+/// A descriptor that polls readable when the driver has work for the
+/// calling thread. A thread that is not a looper only ever gets its own
+/// work (replies, nested calls, ref-count requests), so readiness is exact.
+pub(crate) fn poll_fd() -> Result<std::os::fd::OwnedFd> {
+    THREAD_STATE.with(|thread_state| thread_state.borrow().driver.poll_fd())
+}
+
+#[cfg(feature = "tokio")]
+/// Whether the last read left commands that [`poll_fd`] will not signal.
+pub(crate) fn has_pending_input() -> bool {
+    THREAD_STATE.with(|thread_state| !thread_state.borrow().in_parcel.is_empty())
 }
 
 fn free_buffer(
@@ -1721,12 +1979,25 @@ fn free_buffer(
     Ok(())
 }
 
-pub(crate) fn query_interface(handle: u32) -> Result<String> {
+/// The interface descriptor of `handle`, a handle of `process`; asked in
+/// the HIDL wire format when `process` talks to `hwbinder`.
+pub(crate) fn query_interface(process: &ProcessState, handle: u32) -> Result<String> {
     #[cfg(all(target_os = "android", feature = "android_10"))]
     if handle == 0 && !crate::sdk_at_least(30) {
         return Ok(crate::hub::android_10::SERVICE_MANAGER_DESCRIPTOR.to_owned());
     }
 
+    if process.is_hwbinder() {
+        let data = crate::hidl::base_request()?;
+        let reply = transact(
+            handle,
+            crate::hidl::INTERFACE_DESCRIPTOR_TRANSACTION,
+            &data,
+            0,
+        )?;
+        return crate::hidl::read_descriptor_reply(reply);
+    }
+
     let data = Parcel::new();
     let reply = transact(handle, INTERFACE_TRANSACTION, &data, 0)?;
     // A two-way transact normally yields a reply, but a `break` path in
@@ -1737,7 +2008,14 @@ pub(crate) fn query_interface(handle: u32) -> Result<String> {
     Ok(interface)
 }
 
-pub(crate) fn ping_binder(handle: u32) -> Result<()> {
+/// Ping `handle`, a handle of `process`; see [`query_interface`].
+pub(crate) fn ping_binder(process: &ProcessState, handle: u32) -> Result<()> {
+    if process.is_hwbinder() {
+        let data = crate::hidl::base_request()?;
+        let reply = transact(handle, crate::hidl::PING_TRANSACTION, &data, 0)?;
+        return crate::hidl::read_empty_reply(reply);
+    }
+
     let data = Parcel::new();
     let _reply = transact(handle, PING_TRANSACTION, &data, 0)?;
     Ok(())
@@ -1875,6 +2153,38 @@ pub(crate) fn clear_death_notification(handle: u32) -> Result<()> {
     })
 }
 
+/// Queue `BC_REQUEST_FREEZE_NOTIFICATION`. As with death notifications the
+/// handle doubles as the cookie the kernel echoes in `BR_FROZEN_BINDER`.
+pub(crate) fn request_freeze_notification(handle: u32) -> Result<()> {
+    log::trace!("request_freeze_notification: {handle}");
+    THREAD_STATE.with(|thread_state| -> Result<()> {
+        let mut state = thread_state.borrow_mut();
+        state
+            .out_parcel
+            .write::<u32>(&(binder::BC_REQUEST_FREEZE_NOTIFICATION))?;
+        state.out_parcel.write::<u32>(&(handle))?;
+        state
+            .out_parcel
+            .write::<binder::binder_uintptr_t>(&(handle as _))?;
+        Ok(())
+    })
+}
+
+pub(crate) fn clear_freeze_notification(handle: u32) -> Result<()> {
+    log::trace!("clear_freeze_notification: {handle}");
+    THREAD_STATE.with(|thread_state| -> Result<()> {
+        let mut state = thread_state.borrow_mut();
+        state
+            .out_parcel
+            .write::<u32>(&(binder::BC_CLEAR_FREEZE_NOTIFICATION))?;
+        state.out_parcel.write::<u32>(&(handle))?;
+        state
+            .out_parcel
+            .write::<binder::binder_uintptr_t>(&(handle as _))?;
+        Ok(())
+    })
+}
+
 #[derive(Debug)]
 pub struct CallingContext {
     pub pid: binder::pid_t,
@@ -2280,6 +2590,20 @@ pub struct ExtendedError {
     pub param: i32,
 }
 
+impl ExtendedError {
+    /// Whether the driver rejected the transaction because the receiving
+    /// process's transaction buffer (the async half of it, for oneway
+    /// calls) had no room for it: `BR_FAILED_REPLY` with `-ENOSPC`.
+    ///
+    /// The driver does not say how much room was left; as root,
+    /// [`crate::binderfs::logs::ProcStats::free_async_space`] shows a
+    /// process's oneway space.
+    pub fn is_buffer_exhausted(&self) -> bool {
+        self.command == binder::BR_FAILED_REPLY
+            && self.param == -rustix::io::Errno::NOSPC.raw_os_error()
+    }
+}
+
 /// Retrieve the kernel-side detail of the most recent transaction
 /// failure on the current thread.
 ///
@@ -2304,8 +2628,11 @@ pub struct ExtendedError {
 /// }
 /// ```
 ///
-/// **Opt-in**: rsbinder does *not* call this automatically from the
-/// `BR_FAILED_REPLY` arm. A no-op for callers that never invoke it.
+/// The driver resets its copy when it is read, and rsbinder reads it
+/// itself when a transaction fails with `BR_FAILED_REPLY` (to report
+/// buffer exhaustion, see [`ExtendedError::is_buffer_exhausted`]). That
+/// detail is kept and returned by the next call here, so a failed
+/// `transact` followed by this call sees the same detail as with AOSP.
 pub fn get_extended_error() -> Result<ExtendedError> {
     // Consistent with the other public accessors in this module (calling
     // uid/pid/sid, strict-mode policy, identity): in a pure-RPC process kernel
@@ -2314,9 +2641,17 @@ pub fn get_extended_error() -> Result<ExtendedError> {
     if !ProcessState::is_initialized() {
         return Err(StatusCode::InvalidOperation);
     }
+    if let Some(ee) = take_extended_error() {
+        return Ok(ee);
+    }
+    read_extended_error()
+}
+
+/// `BINDER_GET_EXTENDED_ERROR` for the calling thread.
+fn read_extended_error() -> Result<ExtendedError> {
     let mut ee = binder::binder_extended_error::default();
-    let driver = ProcessState::as_self().driver();
-    crate::sys::binder::get_extended_error(driver.as_ref(), &mut ee).map_err(|errno| {
+    let driver = ProcessState::as_self().binder_driver();
+    driver.get_extended_error(&mut ee).map_err(|errno| {
         if errno == rustix::io::Errno::NOTTY {
             // Pre-Android-12 driver — feature unavailable.
             StatusCode::InvalidOperation
@@ -2356,6 +2691,7 @@ pub fn has_explicit_identity() -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
 
     /// Plan 2-16 Phase B/C: the RPC calling context is read by the public
     /// accessors (`get_calling_uid/pid`, `calling_caller`), restores on
@@ -2519,16 +2855,10 @@ mod tests {
         assert_eq!(s.reserved, 0);
     }
 
-    /// The default arm in `wait_for_response` is untouched: the new
-    /// constants are declared so the strings table can label them, but
-    /// no dispatch path consumes them yet. This is a smoke test — if a
-    /// future change accidentally dispatched the freeze-observer BRs
-    /// before its own arm is in place, the kernel wire would diverge.
-    /// Here we just confirm the constants are visible to user code.
+    /// The freeze-observer constants stay visible to user code alongside
+    /// the `execute_command` arms that consume them.
     #[test]
     fn freeze_observer_constants_are_pub_for_phase_b() {
-        // If these uses compile, the constants are exposed for a future
-        // dispatch path to consume.
         let _b1 = binder::BR_TRANSACTION_PENDING_FROZEN;
         let _b2 = binder::BR_FROZEN_BINDER;
         let _b3 = binder::BR_CLEAR_FREEZE_NOTIFICATION_DONE;
@@ -2594,6 +2924,15 @@ mod tests {
         );
     }
 
+    /// A kernel `binder_transaction_data` for `code`, everything else zero.
+    fn transaction_data(code: TransactionCode) -> binder::binder_transaction_data {
+        // SAFETY: a plain-old-data `repr(C)` struct of integers and
+        // pointer-sized unions; all-zero is a valid value.
+        let mut tr: binder::binder_transaction_data = unsafe { std::mem::zeroed() };
+        tr.code = code;
+        tr
+    }
+
     /// A panicking `Transactable::transact` must not unwind through
     /// `dispatch_transact_caught` and must surface as
     /// `Err(StatusCode::Unknown)` so the existing `BR_TRANSACTION`
@@ -2619,7 +2958,13 @@ mod tests {
 
         let mut reader = Parcel::new();
         let mut reply = Parcel::new();
-        let result = dispatch_transact_caught(&PanickingTransactable, 1, &mut reader, &mut reply);
+        let result = dispatch_transact_caught(
+            &PanickingTransactable,
+            "",
+            &transaction_data(1),
+            &mut reader,
+            &mut reply,
+        );
 
         assert!(
             matches!(result, Err(StatusCode::Unknown)),
@@ -2666,11 +3011,24 @@ mod tests {
 
         let mut reader = Parcel::new();
         let mut reply = Parcel::new();
-        assert!(dispatch_transact_caught(&OkTransactable, 1, &mut reader, &mut reply).is_ok());
+        assert!(dispatch_transact_caught(
+            &OkTransactable,
+            "",
+            &transaction_data(1),
+            &mut reader,
+            &mut reply,
+        )
+        .is_ok());
         assert_eq!(reply.data_size(), std::mem::size_of::<i32>());
 
         let mut reply = Parcel::new();
-        let err = dispatch_transact_caught(&ErrTransactable, 1, &mut reader, &mut reply);
+        let err = dispatch_transact_caught(
+            &ErrTransactable,
+            "",
+            &transaction_data(1),
+            &mut reader,
+            &mut reply,
+        );
         assert!(matches!(err, Err(StatusCode::PermissionDenied)));
     }
 
@@ -0,0 +1,467 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Structured tracing of binder transactions.
+//!
+//! With the `tracing` feature, every outgoing and incoming transaction —
+//! kernel binder and RPC alike — runs inside a `tracing` span on the
+//! [`TARGET`] target at `DEBUG` level:
+//!
+//! | span                | side   | fields |
+//! |---------------------|--------|--------|
+//! | `binder.transact`    | client | `transport`, `descriptor`, `method`, `code`, `flags`, `data_size`, `reply_size`, `status`, `duration_us` |
+//! | `binder.on_transact` | server | the same, plus `calling_uid` / `calling_pid` |
+//!
+//! `method` is filled in by AIDL-generated and `#[binder_interface]`
+//! code, which knows the method a transaction code stands for;
+//! hand-written stubs leave it empty. Nested calls nest naturally: a
+//! callback served while a client call is in flight is a child of the
+//! client span.
+//!
+//! The same hooks feed the `metrics` module when that feature is on.
+//! With neither feature every function here is an inlined no-op, so
+//! generated code calls them unconditionally.
+//!
+//! # Context propagation
+//!
+//! A `TracePropagator` installed with `set_propagator` lets a trace
+//! cross process boundaries. The client asks it to `inject` a
+//! `TraceContext` for the client span; the context travels as a 28-byte
+//! trailer after the transaction arguments (trace id, span id and
+//! flags), announced by [`FLAG_TRACE_CONTEXT`] in the transaction
+//! header. The receiving rsbinder process cuts the trailer off before
+//! the service reads the arguments, hands it to `extract` to parent its
+//! `binder.on_transact` span, and the reply path is unchanged. Argument
+//! bytes are never taken for a trailer, since only the header says one
+//! is there.
+//!
+//! Other binder implementations do not know the flag and see the
+//! trailer as excess data; AOSP services reject it (`BAD_VALUE` from
+//! the `enforceNoDataAvail` check of Android 13+ generated stubs). A
+//! context is therefore only sent to objects whose proxy opted in with
+//! `ProxyHandle::set_trace_propagation` (or the `RpcProxy` equivalent),
+//! and only while a propagator is installed; none is by default, which
+//! keeps outgoing parcels byte-identical to a build without tracing.
+//! Receiving needs no opt-in: every rsbinder process strips the
+//! trailer, with or without the `tracing` feature. Bridging to
+//! OpenTelemetry is a few lines with `tracing-opentelemetry`
+//! (`OpenTelemetrySpanExt::context` / `set_parent`).
+
+use crate::binder::TransactionFlags;
+use crate::error::{Result, StatusCode};
+use crate::parcel::Parcel;
+
+/// Transaction flag announcing a trace-context trailer after the
+/// arguments. rsbinder-private: only sent to proxies that opted in to
+/// trace propagation.
+pub const FLAG_TRACE_CONTEXT: TransactionFlags = 0x0100_0000;
+
+/// trace id + span id + flags (as `i32`).
+const TRAILER_LEN: usize = 16 + 8 + 4;
+
+/// Target of every span this module emits, for `EnvFilter` directives
+/// such as `rsbinder::transaction=debug`.
+pub const TARGET: &str = "rsbinder::transaction";
+
+/// Transport label recorded in the `transport` field.
+pub(crate) const KERNEL: &str = "kernel";
+/// Transport label recorded in the `transport` field.
+#[cfg(feature = "rpc")]
+pub(crate) const RPC: &str = "rpc";
+
+/// What [`client_call`] attaches to an outgoing request parcel.
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+pub(crate) struct ClientCall {
+    method: &'static str,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    /// Whether a trace-context trailer follows the arguments.
+    #[cfg(feature = "tracing")]
+    has_context: bool,
+}
+
+/// Name the AIDL method behind an outgoing request and open its client
+/// span. Generated `build_parcel_*` helpers call this once the arguments
+/// are written; with a `TracePropagator` installed and a target that
+/// opted in to trace propagation it also appends the trace-context
+/// trailer.
+#[doc(hidden)]
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+pub fn client_call(mut data: Parcel, method: &'static str) -> Result<Parcel> {
+    #[cfg(feature = "tracing")]
+    let span = client_span(Some(method));
+    #[cfg(feature = "tracing")]
+    let mut has_context = false;
+    #[cfg(feature = "tracing")]
+    if data.propagates_trace() {
+        if let Some(ctx) = propagator().and_then(|p| p.inject(&span)) {
+            ctx.write_trailer(&mut data)?;
+            has_context = true;
+        }
+    }
+    data.set_client_call(ClientCall {
+        method,
+        #[cfg(feature = "tracing")]
+        span,
+        #[cfg(feature = "tracing")]
+        has_context,
+    });
+    Ok(data)
+}
+
+/// No-op without the `tracing` and `metrics` features.
+#[doc(hidden)]
+#[cfg(not(any(feature = "tracing", feature = "metrics")))]
+#[inline]
+pub fn client_call(data: Parcel, _method: &'static str) -> Result<Parcel> {
+    Ok(data)
+}
+
+/// The method [`client_call`] named for `data`; always `None` without
+/// the `tracing` and `metrics` features.
+pub(crate) fn method(data: &Parcel) -> Option<&'static str> {
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    return data.client_call().map(|call| call.method);
+    #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+    {
+        let _ = data;
+        None
+    }
+}
+
+/// `flags` for sending `data`: with [`FLAG_TRACE_CONTEXT`] added when
+/// [`client_call`] wrote a trace-context trailer.
+pub(crate) fn wire_flags(data: &Parcel, flags: TransactionFlags) -> TransactionFlags {
+    #[cfg(feature = "tracing")]
+    if data.client_call().is_some_and(|call| call.has_context) {
+        return flags | FLAG_TRACE_CONTEXT;
+    }
+    let _ = data;
+    flags
+}
+
+/// Cut the trace-context trailer [`FLAG_TRACE_CONTEXT`] announces off
+/// the end of an incoming `data`, so the service reads the arguments
+/// alone.
+fn take_trailer(flags: TransactionFlags, data: &mut Parcel) -> Option<[u8; TRAILER_LEN]> {
+    if flags & FLAG_TRACE_CONTEXT == 0 {
+        return None;
+    }
+    let Some(len) = data.data_size().checked_sub(TRAILER_LEN) else {
+        log::warn!(
+            "trace-context flag on a {}-byte transaction",
+            data.data_size()
+        );
+        return None;
+    };
+    let trailer = data.data_bytes()[len..].try_into().ok()?;
+    if data.truncate_data(len).is_err() {
+        log::warn!("trace-context trailer overlaps a binder object");
+        return None;
+    }
+    Some(trailer)
+}
+
+/// Record the AIDL method an incoming transaction dispatched to on the
+/// current `binder.on_transact` span. Called first thing in every
+/// generated `on_transact` arm.
+#[doc(hidden)]
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+pub fn server_method(method: &'static str) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        if span.metadata().map(|m| m.name()) == Some(SERVER_SPAN) {
+            span.record("method", method);
+        }
+    }
+    #[cfg(feature = "metrics")]
+    crate::metrics::server_method(method);
+}
+
+/// No-op without the `tracing` and `metrics` features.
+#[doc(hidden)]
+#[cfg(not(any(feature = "tracing", feature = "metrics")))]
+#[inline]
+pub fn server_method(_method: &'static str) {}
+
+#[cfg(feature = "tracing")]
+pub use self::propagation::{set_propagator, TraceContext, TracePropagator};
+
+#[cfg(feature = "tracing")]
+const SERVER_SPAN: &str = "binder.on_transact";
+
+#[cfg(feature = "tracing")]
+fn client_span(method: Option<&'static str>) -> tracing::Span {
+    use tracing::field::Empty;
+    tracing::debug_span!(
+        target: TARGET,
+        "binder.transact",
+        transport = Empty,
+        descriptor = Empty,
+        method,
+        code = Empty,
+        flags = Empty,
+        data_size = Empty,
+        reply_size = Empty,
+        status = Empty,
+        duration_us = Empty,
+    )
+}
+
+#[cfg(feature = "tracing")]
+fn propagator() -> Option<std::sync::Arc<dyn TracePropagator>> {
+    propagation::PROPAGATOR
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+}
+
+/// One observed transaction, from submit (client) or dispatch (server)
+/// until [`Transaction::finish`]. A zero-sized no-op without the
+/// `tracing` and `metrics` features.
+pub(crate) struct Transaction {
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+    #[cfg(feature = "metrics")]
+    call: crate::metrics::Call,
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    start: std::time::Instant,
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+impl Transaction {
+    /// Start the client side of `data`: the span [`client_call`] opened,
+    /// or a fresh one for hand-written requests.
+    pub(crate) fn client(
+        transport: &'static str,
+        descriptor: &str,
+        code: u32,
+        flags: u32,
+        data: &Parcel,
+    ) -> Self {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        let call = data.client_call();
+        Transaction {
+            #[cfg(feature = "tracing")]
+            span: enter(
+                call.map_or_else(|| client_span(None), |c| c.span.clone()),
+                transport,
+                descriptor,
+                code,
+                flags,
+                data.data_size(),
+            ),
+            #[cfg(feature = "metrics")]
+            call: crate::metrics::Call::client(
+                descriptor,
+                call.map(|c| c.method),
+                code,
+                data.data_size(),
+            ),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Start the server side of an incoming `data`, parented by the
+    /// caller's trace-context trailer when a propagator is installed.
+    /// The trailer is removed from `data` in every build.
+    /// `caller` is the sender's `(uid, pid)` when the transport knows it.
+    pub(crate) fn server(
+        transport: &'static str,
+        descriptor: &str,
+        code: u32,
+        flags: u32,
+        data: &mut Parcel,
+        caller: Option<(u32, i32)>,
+    ) -> Self {
+        let trailer = take_trailer(flags, data);
+        #[cfg(not(feature = "tracing"))]
+        let _ = trailer;
+        Transaction {
+            #[cfg(feature = "tracing")]
+            span: enter(
+                server_span(trailer.map(|t| TraceContext::from_trailer(&t)), caller),
+                transport,
+                descriptor,
+                code,
+                flags,
+                data.data_size(),
+            ),
+            #[cfg(feature = "metrics")]
+            call: crate::metrics::Call::server(descriptor, code, data.data_size()),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Record the outcome. `reply` is the reply parcel of a successful
+    /// two-way call, `None` for oneway calls and failed transactions.
+    pub(crate) fn finish(self, status: StatusCode, reply: Option<&mut Parcel>) {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        let elapsed = self.start.elapsed();
+        #[cfg(feature = "tracing")]
+        {
+            let span = self.span.exit();
+            span.record("status", tracing::field::display(status));
+            if let Some(reply) = &reply {
+                span.record("reply_size", reply.data_size());
+            }
+            span.record("duration_us", elapsed.as_micros() as u64);
+        }
+        #[cfg(feature = "metrics")]
+        self.call.finish(status, reply, elapsed);
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn server_span(context: Option<TraceContext>, caller: Option<(u32, i32)>) -> tracing::Span {
+    use tracing::field::Empty;
+    let span = tracing::debug_span!(
+        target: TARGET,
+        "binder.on_transact",
+        transport = Empty,
+        descriptor = Empty,
+        method = Empty,
+        code = Empty,
+        flags = Empty,
+        data_size = Empty,
+        reply_size = Empty,
+        status = Empty,
+        duration_us = Empty,
+        calling_uid = Empty,
+        calling_pid = Empty,
+    );
+    if let Some((uid, pid)) = caller {
+        span.record("calling_uid", uid);
+        span.record("calling_pid", pid);
+    }
+    if !span.is_disabled() {
+        if let (Some(p), Some(ctx)) = (propagator(), context) {
+            p.extract(&span, &ctx);
+        }
+    }
+    span
+}
+
+#[cfg(feature = "tracing")]
+fn enter(
+    span: tracing::Span,
+    transport: &'static str,
+    descriptor: &str,
+    code: u32,
+    flags: u32,
+    data_size: usize,
+) -> tracing::span::EnteredSpan {
+    span.record("transport", transport);
+    span.record("descriptor", descriptor);
+    span.record("code", code);
+    span.record("flags", flags);
+    span.record("data_size", data_size);
+    span.entered()
+}
+
+#[cfg(feature = "tracing")]
+mod propagation {
+    use std::sync::{Arc, RwLock};
+
+    use super::TRAILER_LEN;
+    use crate::error::Result;
+    use crate::parcel::Parcel;
+
+    pub(super) static PROPAGATOR: RwLock<Option<Arc<dyn TracePropagator>>> = RwLock::new(None);
+
+    /// A W3C `traceparent`-shaped trace context carried across a binder
+    /// hop.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct TraceContext {
+        /// 128-bit trace id.
+        pub trace_id: [u8; 16],
+        /// 64-bit id of the client span.
+        pub span_id: [u8; 8],
+        /// W3C trace flags (bit 0: sampled).
+        pub flags: u8,
+    }
+
+    impl TraceContext {
+        pub(super) fn write_trailer(&self, data: &mut Parcel) -> Result<()> {
+            data.set_data_position(data.data_size());
+            data.write_aligned_data(&self.trace_id)?;
+            data.write_aligned_data(&self.span_id)?;
+            data.write(&(self.flags as i32))
+        }
+
+        pub(super) fn from_trailer(trailer: &[u8; TRAILER_LEN]) -> Self {
+            let mut context = TraceContext::default();
+            context.trace_id.copy_from_slice(&trailer[..16]);
+            context.span_id.copy_from_slice(&trailer[16..24]);
+            let mut flags = [0; 4];
+            flags.copy_from_slice(&trailer[24..]);
+            context.flags = i32::from_ne_bytes(flags) as u8;
+            context
+        }
+    }
+
+    /// Bridge between binder spans and a distributed-tracing system.
+    pub trait TracePropagator: Send + Sync {
+        /// The context to send with the transaction `span` describes, or
+        /// `None` to send none (e.g. the span is not sampled).
+        fn inject(&self, span: &tracing::Span) -> Option<TraceContext>;
+        /// Parent the server `span` with the context a caller sent.
+        fn extract(&self, span: &tracing::Span, context: &TraceContext);
+    }
+
+    /// Install (or with `None`, remove) the process-wide propagator.
+    /// Contexts are only sent to proxies that opted in with
+    /// `set_trace_propagation`, since other binder implementations reject
+    /// the trailer; see the [module docs](super).
+    pub fn set_propagator(propagator: Option<Arc<dyn TracePropagator>>) {
+        *PROPAGATOR
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = propagator;
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailer_round_trip() {
+        let ctx = TraceContext {
+            trace_id: [7; 16],
+            span_id: [9; 8],
+            flags: 1,
+        };
+        let mut data = Parcel::new();
+        data.write(&42i32).unwrap();
+        data.write(&"arg").unwrap();
+        let args = data.data_size();
+        ctx.write_trailer(&mut data).unwrap();
+
+        let trailer = take_trailer(FLAG_TRACE_CONTEXT, &mut data).unwrap();
+        assert_eq!(TraceContext::from_trailer(&trailer), ctx);
+        // The arguments are all that is left.
+        assert_eq!(data.data_size(), args);
+        data.set_data_position(0);
+        assert_eq!(data.read::<i32>().unwrap(), 42);
+        assert_eq!(data.read::<String>().unwrap(), "arg");
+        assert_eq!(data.data_avail(), 0);
+    }
+
+    #[test]
+    fn test_no_trailer_without_flag() {
+        // Argument bytes that happen to look like a trailer stay put.
+        let mut data = Parcel::new();
+        data.write_aligned_data(&[0u8; 64]).unwrap();
+        assert_eq!(take_trailer(0, &mut data), None);
+        assert_eq!(data.data_size(), 64);
+
+        // A flagged transaction too short for a trailer is left alone.
+        let mut data = Parcel::new();
+        data.write(&1i32).unwrap();
+        assert_eq!(take_trailer(FLAG_TRACE_CONTEXT, &mut data), None);
+        assert_eq!(data.data_size(), 4);
+    }
+}
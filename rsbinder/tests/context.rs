@@ -0,0 +1,224 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Two binder contexts in one process: the default `ProcessState` and a
+//! `BinderContext`, each on its own fake binder driver, bridged by a
+//! gateway that holds proxies of both.
+
+#![cfg(feature = "fake-driver")]
+
+use std::sync::OnceLock;
+
+use rsbinder::fake_driver::{FakeProcess, FAKE_BINDER_PATH};
+use rsbinder::{
+    Binder, BinderContext, Interface, Parcel, ProcessState, Remotable, Result, SIBinder,
+    StatusCode, TransactionCode, FIRST_CALL_TRANSACTION,
+};
+
+const DESC: &str = "rsbinder.test.INamed";
+const TX_NAME: TransactionCode = FIRST_CALL_TRANSACTION; // -> String
+const TX_ASK: TransactionCode = FIRST_CALL_TRANSACTION + 1; // INamed -> String
+const TX_CHILD: TransactionCode = FIRST_CALL_TRANSACTION + 2; // -> INamed
+
+/// Answers with its name; asked to, calls `TX_NAME` on the binder it is
+/// given and prefixes the answer with its own name, or hands out a new
+/// binder named after it.
+struct BnNamed(&'static str);
+impl Remotable for BnNamed {
+    fn descriptor() -> &'static str {
+        DESC
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            TX_NAME => reply.write(&self.0.to_owned()),
+            TX_ASK => {
+                let other: SIBinder = reader.read()?;
+                reply.write(&format!("{}>{}", self.0, name(&other)?))
+            }
+            TX_CHILD => {
+                let child = match self.0 {
+                    "system" => "system.child",
+                    "vendor" => "vendor.child",
+                    _ => return Err(StatusCode::BadValue),
+                };
+                reply.write(&Binder::new(BnNamed(child)).as_binder())
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Forwards `TX_NAME` to the service of the other domain.
+struct BnGateway(SIBinder);
+impl Remotable for BnGateway {
+    fn descriptor() -> &'static str {
+        DESC
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        _reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            TX_NAME => reply.write(&format!("gateway>{}", name(&self.0)?)),
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn call<T: rsbinder::Deserialize>(
+    binder: &SIBinder,
+    code: TransactionCode,
+    arg: Option<&SIBinder>,
+) -> Result<T> {
+    let proxy = binder.as_proxy().ok_or(StatusCode::BadType)?;
+    let mut data = proxy.prepare_transact(true)?;
+    if let Some(arg) = arg {
+        data.write(arg)?;
+    }
+    proxy
+        .submit_transact(code, &data, 0)?
+        .ok_or(StatusCode::UnexpectedNull)?
+        .read()
+}
+
+fn name(binder: &SIBinder) -> Result<String> {
+    call(binder, TX_NAME, None)
+}
+
+/// Service processes "system" on the default driver and "vendor" on the
+/// context's, each its driver's context manager.
+fn domains() -> BinderContext {
+    static DOMAINS: OnceLock<(BinderContext, FakeProcess, FakeProcess)> = OnceLock::new();
+    DOMAINS
+        .get_or_init(|| {
+            ProcessState::init(FAKE_BINDER_PATH, 4).expect("fake ProcessState");
+            ProcessState::start_thread_pool();
+            let vendor = BinderContext::open(FAKE_BINDER_PATH, 4).expect("second context");
+            vendor.start_thread_pool();
+
+            let system_service = FakeProcess::new(1000).unwrap();
+            system_service.start_thread_pool();
+            system_service
+                .spawn(|| {
+                    let binder = Binder::new(BnNamed("system"));
+                    ProcessState::as_self()
+                        .become_context_manager(binder.as_binder())
+                        .unwrap();
+                })
+                .join()
+                .unwrap();
+
+            let vendor_service = vendor.run(|| FakeProcess::new(1000)).unwrap();
+            vendor_service.start_thread_pool();
+            vendor_service
+                .spawn(|| {
+                    let binder = Binder::new(BnNamed("vendor"));
+                    ProcessState::as_self()
+                        .become_context_manager(binder.as_binder())
+                        .unwrap();
+                })
+                .join()
+                .unwrap();
+            (vendor, system_service, vendor_service)
+        })
+        .0
+}
+
+#[test]
+fn test_contexts_are_separate() {
+    let vendor = domains();
+    assert_ne!(
+        std::ptr::from_ref(ProcessState::as_self()),
+        std::ptr::from_ref(vendor.process_state())
+    );
+    assert_eq!(
+        vendor.run(|| std::ptr::from_ref(ProcessState::as_self())),
+        std::ptr::from_ref(vendor.process_state())
+    );
+    assert_eq!(vendor.driver_name(), std::path::Path::new(FAKE_BINDER_PATH));
+
+    let system = ProcessState::as_self().context_object().unwrap();
+    let vendor_manager = vendor.context_object().unwrap();
+    assert_eq!(name(&system), Ok("system".to_owned()));
+    // The proxy calls through its own context outside `run`, too.
+    assert_eq!(name(&vendor_manager), Ok("vendor".to_owned()));
+    assert_eq!(
+        vendor.run(|| name(&system)),
+        Ok("system".to_owned()),
+        "a default-context proxy keeps its context inside `run`"
+    );
+}
+
+#[test]
+fn test_gateway_bridges_domains() {
+    let vendor = domains();
+    let system = ProcessState::as_self().context_object().unwrap();
+    let vendor_manager = vendor.context_object().unwrap();
+
+    // A local callback handed to the vendor service, served on the
+    // context's pool, calling into the system domain from there.
+    let gateway = Binder::new(BnGateway(system.clone())).as_binder();
+    assert_eq!(
+        call(&vendor_manager, TX_ASK, Some(&gateway)),
+        Ok("vendor>gateway>system".to_owned())
+    );
+
+    // The same local binder goes to the other domain as well.
+    let local = Binder::new(BnNamed("local")).as_binder();
+    assert_eq!(
+        call(&system, TX_ASK, Some(&local)),
+        Ok("system>local".to_owned())
+    );
+    assert_eq!(
+        call(&vendor_manager, TX_ASK, Some(&local)),
+        Ok("vendor>local".to_owned())
+    );
+}
+
+#[test]
+fn test_reply_binders_belong_to_the_call_context() {
+    let vendor = domains();
+    let system = ProcessState::as_self().context_object().unwrap();
+    let vendor_manager = vendor.context_object().unwrap();
+
+    // Read on a thread in the default context: the handle in the reply
+    // is one of the vendor driver's all the same.
+    let vendor_child: SIBinder = call(&vendor_manager, TX_CHILD, None).unwrap();
+    assert_eq!(name(&vendor_child), Ok("vendor.child".to_owned()));
+    let system_child: SIBinder = vendor.run(|| call(&system, TX_CHILD, None)).unwrap();
+    assert_eq!(name(&system_child), Ok("system.child".to_owned()));
+    assert_eq!(
+        vendor.run(|| name(&vendor_child)),
+        Ok("vendor.child".to_owned())
+    );
+}
+
+#[test]
+fn test_proxy_of_another_context_is_not_sent() {
+    let vendor = domains();
+    let system = ProcessState::as_self().context_object().unwrap();
+    let vendor_manager = vendor.context_object().unwrap();
+
+    assert_eq!(
+        call::<String>(&system, TX_ASK, Some(&vendor_manager)),
+        Err(StatusCode::BadValue)
+    );
+    assert_eq!(
+        vendor.run(|| call::<String>(&vendor_manager, TX_ASK, Some(&system))),
+        Err(StatusCode::BadValue),
+        "the parcel follows the proxy, not the entered context"
+    );
+}
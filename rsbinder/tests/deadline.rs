@@ -0,0 +1,197 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Call deadlines on kernel proxies, against a service of the fake
+//! binder driver that stalls on request.
+
+#![cfg(feature = "fake-driver")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use rsbinder::fake_driver::{FakeProcess, FAKE_BINDER_PATH};
+use rsbinder::{
+    Binder, Interface, Parcel, ProcessState, Remotable, Result, SIBinder, StatusCode,
+    TransactionCode, FIRST_CALL_TRANSACTION, FLAG_ONEWAY,
+};
+
+const DESC: &str = "rsbinder.test.IStalling";
+const TX_STALL: TransactionCode = FIRST_CALL_TRANSACTION; // millis: i32 -> i32
+
+/// Stalled calls that have finished on the service side.
+static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+/// The tests share the context manager's proxy, and with it the
+/// proxy-wide deadline.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+struct BnStalling;
+impl Remotable for BnStalling {
+    fn descriptor() -> &'static str {
+        DESC
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            TX_STALL => {
+                let millis: i32 = reader.read()?;
+                std::thread::sleep(Duration::from_millis(millis as u64));
+                FINISHED.fetch_add(1, Ordering::SeqCst);
+                reply.write(&millis)
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn service() -> SIBinder {
+    static SERVICE: OnceLock<FakeProcess> = OnceLock::new();
+    SERVICE.get_or_init(|| {
+        ProcessState::init(FAKE_BINDER_PATH, 4).expect("fake ProcessState");
+        ProcessState::start_thread_pool();
+        let service = FakeProcess::new(1000).unwrap();
+        service.start_thread_pool();
+        service
+            .spawn(|| {
+                let binder = Binder::new(BnStalling);
+                ProcessState::as_self()
+                    .become_context_manager(binder.as_binder())
+                    .expect("context manager");
+            })
+            .join()
+            .unwrap();
+        service
+    });
+    ProcessState::as_self().context_object().unwrap()
+}
+
+fn stall_request(binder: &SIBinder, millis: i32) -> Parcel {
+    let mut data = binder.as_proxy().unwrap().prepare_transact(true).unwrap();
+    data.write(&millis).unwrap();
+    data
+}
+
+fn reply_of(reply: Result<Option<Parcel>>) -> Result<i32> {
+    reply?.ok_or(StatusCode::UnexpectedNull)?.read()
+}
+
+#[test]
+fn test_per_call_timeout() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let binder = service();
+    let proxy = binder.as_proxy().unwrap();
+    let finished = FINISHED.load(Ordering::SeqCst);
+
+    let started = Instant::now();
+    let data = stall_request(&binder, 500);
+    let reply = proxy.submit_transact_with_timeout(TX_STALL, &data, 0, Duration::from_millis(50));
+    assert_eq!(reply.err(), Some(StatusCode::TimedOut));
+    assert!(started.elapsed() < Duration::from_millis(400));
+    drop(data);
+
+    // The thread that timed out can call again right away.
+    let data = stall_request(&binder, 1);
+    assert_eq!(reply_of(proxy.submit_transact(TX_STALL, &data, 0)), Ok(1));
+    let data = stall_request(&binder, 1);
+    let reply = proxy.submit_transact_with_timeout(TX_STALL, &data, 0, Duration::from_secs(5));
+    assert_eq!(reply_of(reply), Ok(1));
+
+    // The abandoned call still completes, and its late reply is drained
+    // by the helper without disturbing later calls.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while FINISHED.load(Ordering::SeqCst) < finished + 3 {
+        assert!(Instant::now() < deadline, "stalled call never finished");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let data = stall_request(&binder, 2);
+    let reply = proxy.submit_transact_with_timeout(TX_STALL, &data, 0, Duration::from_secs(5));
+    assert_eq!(reply_of(reply), Ok(2));
+}
+
+#[test]
+fn test_per_proxy_timeout() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let binder = service();
+    let proxy = binder.as_proxy().unwrap();
+    let data = stall_request(&binder, 1);
+    proxy.set_timeout(Some(Duration::from_millis(50)));
+    assert_eq!(proxy.timeout(), Some(Duration::from_millis(50)));
+
+    let slow = stall_request(&binder, 500);
+    assert_eq!(
+        proxy.submit_transact(TX_STALL, &slow, 0).err(),
+        Some(StatusCode::TimedOut)
+    );
+    // Oneway calls never wait, so the deadline does not apply.
+    assert_eq!(
+        proxy
+            .submit_transact(TX_STALL, &slow, FLAG_ONEWAY)
+            .map(|r| r.is_none()),
+        Ok(true)
+    );
+    assert_eq!(reply_of(proxy.submit_transact(TX_STALL, &data, 0)), Ok(1));
+
+    proxy.set_timeout(None);
+    assert_eq!(reply_of(proxy.submit_transact(TX_STALL, &slow, 0)), Ok(500));
+}
+
+#[test]
+fn test_abandoned_calls_are_bounded() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let binder = service();
+    let proxy = binder.as_proxy().unwrap();
+    let finished = FINISHED.load(Ordering::SeqCst);
+
+    // Four helpers (the crate's `MAX_ABANDONED`) may be left waiting on
+    // one object for replies their callers gave up on.
+    let slow = stall_request(&binder, 300);
+    for _ in 0..4 {
+        let reply =
+            proxy.submit_transact_with_timeout(TX_STALL, &slow, 0, Duration::from_millis(20));
+        assert_eq!(reply.err(), Some(StatusCode::TimedOut));
+    }
+
+    // Further timed calls are refused without being sent.
+    let quick = stall_request(&binder, 1);
+    let started = Instant::now();
+    let reply = proxy.submit_transact_with_timeout(TX_STALL, &quick, 0, Duration::from_secs(5));
+    assert_eq!(reply.err(), Some(StatusCode::TimedOut));
+    assert!(started.elapsed() < Duration::from_millis(250));
+    // Untimed calls are not limited.
+    assert_eq!(reply_of(proxy.submit_transact(TX_STALL, &quick, 0)), Ok(1));
+
+    // Once the stalled calls are answered, timed calls go through again.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let reply = proxy.submit_transact_with_timeout(TX_STALL, &quick, 0, Duration::from_secs(5));
+        match reply_of(reply) {
+            Ok(value) => {
+                assert_eq!(value, 1);
+                break;
+            }
+            Err(err) => {
+                assert_eq!(err, StatusCode::TimedOut);
+                assert!(
+                    Instant::now() < deadline,
+                    "abandoned helpers never returned"
+                );
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+
+    // Leave no stalled call behind for the next test.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while FINISHED.load(Ordering::SeqCst) < finished + 6 {
+        assert!(Instant::now() < deadline, "stalled call never finished");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,545 @@
+// Copyright 2022 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Kernel-binder scenarios against the in-process fake driver.
+//!
+//! Every test shares one `ProcessState` on `FAKE_BINDER_PATH` and one
+//! registry process acting as the context manager. Services live in
+//! their own `FakeProcess`es; the test threads are the client process.
+//! Nothing here needs `/dev/binderfs`, root or the binder module.
+
+#![cfg(feature = "fake-driver")]
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, Write};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use rsbinder::fake_driver::{FakeProcess, FAKE_BINDER_PATH};
+use rsbinder::{
+    get_calling_pid, get_calling_uid, Binder, DeathRecipient, Interface, Parcel,
+    ParcelFileDescriptor, ProcessState, Remotable, Result, SIBinder, StatusCode, TransactionCode,
+    WIBinder, FIRST_CALL_TRANSACTION,
+};
+
+const REGISTRY_DESC: &str = "rsbinder.test.IFakeRegistry";
+const TX_ADD: TransactionCode = FIRST_CALL_TRANSACTION; // (String, IBinder)
+const TX_GET: TransactionCode = FIRST_CALL_TRANSACTION + 1; // String -> @nullable IBinder
+
+const SVC_DESC: &str = "rsbinder.test.IFakeService";
+const TX_WHO: TransactionCode = FIRST_CALL_TRANSACTION; // -> (pid, uid)
+const TX_CALL_BACK: TransactionCode = FIRST_CALL_TRANSACTION + 1; // (IBinder, String) -> String
+const TX_FD_LEN: TransactionCode = FIRST_CALL_TRANSACTION + 2; // fd -> i64
+const TX_PUSH: TransactionCode = FIRST_CALL_TRANSACTION + 3; // oneway i32
+const TX_PUSHED: TransactionCode = FIRST_CALL_TRANSACTION + 4; // -> Vec<i32>
+
+const CB_DESC: &str = "rsbinder.test.IFakeCallback";
+const TX_CB_ECHO: TransactionCode = FIRST_CALL_TRANSACTION; // String -> String
+
+const SERVER_UID: u32 = 1000;
+
+// ---- registry (context manager) -------------------------------------
+
+struct BnRegistry(Mutex<HashMap<String, SIBinder>>);
+impl Remotable for BnRegistry {
+    fn descriptor() -> &'static str {
+        REGISTRY_DESC
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            TX_ADD => {
+                let name: String = reader.read()?;
+                let binder: SIBinder = reader.read()?;
+                self.0.lock().unwrap().insert(name, binder);
+                Ok(())
+            }
+            TX_GET => {
+                let name: String = reader.read()?;
+                reply.write(&self.0.lock().unwrap().get(&name).cloned())
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Initialize the fake driver once and start the registry process.
+fn setup() {
+    static REGISTRY: OnceLock<FakeProcess> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let _ = env_logger::builder().is_test(true).try_init();
+        ProcessState::init(FAKE_BINDER_PATH, 4).expect("fake ProcessState");
+        ProcessState::start_thread_pool();
+
+        let registry = FakeProcess::new(0).unwrap();
+        registry.start_thread_pool();
+        registry
+            .spawn(|| {
+                let binder = Binder::new(BnRegistry(Mutex::new(HashMap::new())));
+                ProcessState::as_self()
+                    .become_context_manager(binder.as_binder())
+                    .expect("context manager");
+            })
+            .join()
+            .unwrap();
+        registry
+    });
+}
+
+fn call(binder: &SIBinder, code: TransactionCode, data: &Parcel) -> Result<Parcel> {
+    let proxy = binder.as_proxy().expect("remote binder");
+    proxy
+        .submit_transact(code, data, 0)?
+        .ok_or(StatusCode::UnexpectedNull)
+}
+
+fn add_service(name: &str, binder: SIBinder) -> Result<()> {
+    let registry = ProcessState::as_self().context_object()?;
+    let mut data = registry.as_proxy().unwrap().prepare_transact(true)?;
+    data.write(&name)?;
+    data.write(&binder)?;
+    call(&registry, TX_ADD, &data).map(|_| ())
+}
+
+fn get_service(name: &str) -> Result<Option<SIBinder>> {
+    let registry = ProcessState::as_self().context_object()?;
+    let mut data = registry.as_proxy().unwrap().prepare_transact(true)?;
+    data.write(&name)?;
+    call(&registry, TX_GET, &data)?.read()
+}
+
+// ---- service ----------------------------------------------------------
+
+struct BnService(Mutex<Vec<i32>>);
+impl Remotable for BnService {
+    fn descriptor() -> &'static str {
+        SVC_DESC
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            TX_WHO => {
+                reply.write(&get_calling_pid())?;
+                reply.write(&get_calling_uid())
+            }
+            TX_CALL_BACK => {
+                // A nested call into the process that is waiting on us.
+                let callback: SIBinder = reader.read()?;
+                let message: String = reader.read()?;
+                let mut data = callback.as_proxy().unwrap().prepare_transact(true)?;
+                data.write(&message)?;
+                let echoed: String = call(&callback, TX_CB_ECHO, &data)?.read()?;
+                reply.write(&echoed)
+            }
+            TX_FD_LEN => {
+                let pfd: ParcelFileDescriptor = reader.read()?;
+                let mut file =
+                    std::fs::File::from(pfd.as_ref().try_clone().map_err(|_| StatusCode::BadFd)?);
+                let mut buf = Vec::new();
+                file.rewind().map_err(|_| StatusCode::BadFd)?;
+                file.read_to_end(&mut buf).map_err(|_| StatusCode::BadFd)?;
+                reply.write(&(buf.len() as i64))
+            }
+            TX_PUSH => {
+                let value: i32 = reader.read()?;
+                std::thread::sleep(Duration::from_millis(2));
+                self.0.lock().unwrap().push(value);
+                Ok(())
+            }
+            TX_PUSHED => reply.write(&*self.0.lock().unwrap()),
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct BnCallback;
+impl Remotable for BnCallback {
+    fn descriptor() -> &'static str {
+        CB_DESC
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            TX_CB_ECHO => {
+                let message: String = reader.read()?;
+                reply.write(&format!("cb:{message}"))
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Start a server process publishing a `BnService` under `name`.
+fn start_server(name: &'static str) -> (FakeProcess, SIBinder) {
+    setup();
+    let server = FakeProcess::new(SERVER_UID).unwrap();
+    server.start_thread_pool();
+    server
+        .spawn(move || {
+            add_service(
+                name,
+                Binder::new(BnService(Mutex::new(Vec::new()))).as_binder(),
+            )
+        })
+        .join()
+        .unwrap()
+        .expect("add_service");
+    let service = get_service(name).unwrap().expect("service registered");
+    (server, service)
+}
+
+fn request(service: &SIBinder) -> Parcel {
+    service.as_proxy().unwrap().prepare_transact(true).unwrap()
+}
+
+// ---- tests ------------------------------------------------------------
+
+#[test]
+fn calls_carry_the_caller_identity() {
+    let (server, service) = start_server("identity");
+
+    let mut reply = call(&service, TX_WHO, &request(&service)).unwrap();
+    assert_eq!(reply.read::<i32>().unwrap(), std::process::id() as i32);
+    assert_eq!(
+        reply.read::<u32>().unwrap(),
+        rustix::process::geteuid().as_raw()
+    );
+
+    // From inside another simulated process, its pid and euid are seen.
+    let client = FakeProcess::new(2000).unwrap();
+    let (pid, uid) = client
+        .spawn(|| {
+            let service = get_service("identity").unwrap().unwrap();
+            let mut reply = call(&service, TX_WHO, &request(&service)).unwrap();
+            (reply.read::<i32>().unwrap(), reply.read::<u32>().unwrap())
+        })
+        .join()
+        .unwrap();
+    assert_eq!((pid, uid), (client.pid(), 2000));
+    assert_ne!(client.pid(), server.pid());
+}
+
+#[test]
+fn local_binders_come_home_and_nested_calls_reach_the_caller() {
+    let (_server, service) = start_server("nested");
+
+    let callback = Binder::new(BnCallback).as_binder();
+    let mut data = request(&service);
+    data.write(&callback).unwrap();
+    data.write(&"hello").unwrap();
+    let mut reply = call(&service, TX_CALL_BACK, &data).unwrap();
+    assert_eq!(reply.read::<String>().unwrap(), "cb:hello");
+
+    // Our own binder comes back as the local object, not a proxy.
+    let name = "nested.callback";
+    add_service(name, callback.clone()).unwrap();
+    let back = get_service(name).unwrap().unwrap();
+    assert!(back.as_proxy().is_none());
+    assert_eq!(back, callback);
+}
+
+#[test]
+fn file_descriptors_are_duplicated_into_the_receiver() {
+    let (_server, service) = start_server("fd");
+
+    let mut path = std::env::temp_dir();
+    path.push(format!("rsb_fake_fd_{}.tmp", std::process::id()));
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    let _ = std::fs::remove_file(&path);
+    file.write_all(b"twelve bytes").unwrap();
+
+    let mut data = request(&service);
+    data.write(&ParcelFileDescriptor::new(file)).unwrap();
+    let mut reply = call(&service, TX_FD_LEN, &data).unwrap();
+    assert_eq!(reply.read::<i64>().unwrap(), 12);
+}
+
+#[test]
+fn oneway_calls_to_a_node_run_in_order() {
+    let (_server, service) = start_server("oneway");
+    let proxy = service.as_proxy().unwrap();
+
+    for value in 0..32 {
+        let mut data = request(&service);
+        data.write(&value).unwrap();
+        assert!(proxy
+            .submit_transact(TX_PUSH, &data, rsbinder::FLAG_ONEWAY)
+            .unwrap()
+            .is_none());
+    }
+    // A synchronous call does not wait for the oneway queue, so poll.
+    let mut pushed = Vec::new();
+    for _ in 0..500 {
+        pushed = call(&service, TX_PUSHED, &request(&service))
+            .unwrap()
+            .read::<Vec<i32>>()
+            .unwrap();
+        if pushed.len() == 32 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(pushed, (0..32).collect::<Vec<_>>());
+}
+
+struct Notify(Mutex<mpsc::Sender<()>>);
+impl DeathRecipient for Notify {
+    fn binder_died(&self, _who: &WIBinder) {
+        let _ = self.0.lock().unwrap().send(());
+    }
+}
+
+#[test]
+fn killing_a_process_fires_death_recipients() {
+    let (server, service) = start_server("doomed");
+    let (tx, rx) = mpsc::channel();
+    let recipient = Arc::new(Notify(Mutex::new(tx)));
+    service.link_to_death_arc(&recipient).unwrap();
+
+    call(&service, TX_WHO, &request(&service)).unwrap();
+    assert!(server.is_alive());
+    server.kill();
+    assert!(!server.is_alive());
+
+    rx.recv_timeout(Duration::from_secs(5))
+        .expect("death notification");
+    assert_eq!(
+        call(&service, TX_WHO, &request(&service)).err(),
+        Some(StatusCode::DeadObject)
+    );
+}
+
+#[test]
+fn fake_process_requires_the_fake_driver_state() {
+    setup();
+    // Already initialized with the fake driver: a second process is fine.
+    let process = FakeProcess::new(3000).unwrap();
+    assert_eq!(process.euid(), 3000);
+    assert!(process.pid() > 0);
+}
+
+// ---- reactor ------------------------------------------------------------
+
+#[cfg(feature = "tokio")]
+fn reactor_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn reactor_runs_many_calls_on_a_few_lanes() {
+    use rsbinder::BinderReactor;
+
+    let (server, service) = start_server("reactor.many");
+    let reactor = BinderReactor::init(2).unwrap();
+    assert!(reactor.lanes() >= 1);
+    assert_eq!(BinderReactor::init(7).unwrap().lanes(), reactor.lanes());
+
+    let replies = reactor_runtime().block_on(async {
+        let calls: Vec<_> = (0..200)
+            .map(|_| reactor.transact(&service, TX_WHO, request(&service), 0))
+            .collect();
+        let mut replies = Vec::new();
+        for call in calls {
+            replies.push(call.await);
+        }
+        replies
+    });
+    for reply in replies {
+        let mut reply = reply.unwrap().expect("reply parcel");
+        assert_eq!(reply.read::<i32>().unwrap(), std::process::id() as i32);
+    }
+    assert!(server.is_alive());
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn reactor_lanes_serve_nested_calls_and_oneway() {
+    use rsbinder::BinderReactor;
+
+    let (_server, service) = start_server("reactor.nested");
+    let reactor = BinderReactor::get().unwrap();
+    let callback = Binder::new(BnCallback).as_binder();
+
+    reactor_runtime().block_on(async {
+        let calls: Vec<_> = (0..16)
+            .map(|i| {
+                let mut data = request(&service);
+                data.write(&callback).unwrap();
+                data.write(&format!("m{i}")).unwrap();
+                reactor.transact(&service, TX_CALL_BACK, data, 0)
+            })
+            .collect();
+        for (i, call) in calls.into_iter().enumerate() {
+            let mut reply = call.await.unwrap().unwrap();
+            assert_eq!(reply.read::<String>().unwrap(), format!("cb:m{i}"));
+        }
+
+        for value in 0..8 {
+            let mut data = request(&service);
+            data.write(&value).unwrap();
+            let reply = reactor
+                .transact(&service, TX_PUSH, data, rsbinder::FLAG_ONEWAY)
+                .await
+                .unwrap();
+            assert!(reply.is_none());
+        }
+    });
+
+    // Local binders are not kernel proxies.
+    let local =
+        reactor_runtime().block_on(reactor.transact(&callback, TX_CB_ECHO, Parcel::new(), 0));
+    assert_eq!(local.err(), Some(StatusCode::InvalidOperation));
+}
+
+// ---- shared memory ----------------------------------------------------
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn shared_memory_heaps_are_mapped_by_the_receiver() {
+    use rsbinder::shared_memory::{
+        BnMemory, BnMemoryHeap, IMemory, IMemoryHeap, MemoryBase, MemoryHeapBase, FLAG_READ_ONLY,
+    };
+    use rsbinder::{FromIBinder, Strong};
+
+    setup();
+    let server = FakeProcess::new(SERVER_UID).unwrap();
+    server.start_thread_pool();
+    let (heap, read_only) = server
+        .spawn(|| {
+            let heap = MemoryHeapBase::new(8192, 0).unwrap();
+            // SAFETY: nothing else uses the heap yet.
+            unsafe { heap.as_mut_ptr().add(4096).copy_from(b"frame".as_ptr(), 5) };
+            let memory = MemoryBase::new(BnMemoryHeap::new_binder(heap.clone()), 4096, 5).unwrap();
+            add_service("memory.frame", BnMemory::new_binder(memory).as_binder()).unwrap();
+
+            let read_only =
+                BnMemoryHeap::new_binder(MemoryHeapBase::new(1, FLAG_READ_ONLY).unwrap());
+            add_service("memory.read_only", read_only.as_binder()).unwrap();
+            (heap, read_only)
+        })
+        .join()
+        .unwrap();
+
+    let memory: Strong<dyn IMemory> =
+        FromIBinder::try_from(get_service("memory.frame").unwrap().unwrap()).unwrap();
+    assert_eq!((memory.offset(), memory.size()), (4096, 5));
+    assert_eq!(memory.as_slice().unwrap(), b"frame");
+    let remote_heap = memory.memory().unwrap();
+    assert_eq!(remote_heap.size(), 8192);
+    assert_ne!(remote_heap.heap_id(), heap.heap_id());
+
+    // Both mappings share the pages.
+    // SAFETY: the test synchronizes the two sides itself.
+    unsafe { heap.as_mut_ptr().add(4096).write(b'F') };
+    assert_eq!(memory.as_slice().unwrap(), b"Frame");
+
+    let read_only_heap: Strong<dyn IMemoryHeap> =
+        FromIBinder::try_from(get_service("memory.read_only").unwrap().unwrap()).unwrap();
+    assert_eq!(read_only_heap.flags(), FLAG_READ_ONLY);
+    assert_eq!(read_only_heap.base().unwrap().len(), read_only.size());
+}
+
+// ---- fast message queue -----------------------------------------------
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+struct BnQueue(Mutex<rsbinder::fmq::MessageQueue<i32, rsbinder::fmq::SynchronizedReadWrite>>);
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Remotable for BnQueue {
+    fn descriptor() -> &'static str {
+        "rsbinder.test.IFakeQueue"
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        _reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            FIRST_CALL_TRANSACTION => reply.write(&self.0.lock().unwrap().dupe_desc()?),
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn message_queue_descriptors_open_the_queue_in_the_receiver() {
+    use rsbinder::fmq::{MQDescriptor, MessageQueue, SynchronizedReadWrite};
+
+    setup();
+    let server = FakeProcess::new(SERVER_UID).unwrap();
+    server.start_thread_pool();
+    let mut writer = server
+        .spawn(|| {
+            let queue = MessageQueue::<i32, SynchronizedReadWrite>::new(4, true).unwrap();
+            let writer = MessageQueue::from_descriptor(&queue.dupe_desc().unwrap(), false).unwrap();
+            add_service(
+                "fmq.samples",
+                Binder::new(BnQueue(Mutex::new(queue))).as_binder(),
+            )
+            .unwrap();
+            writer
+        })
+        .join()
+        .unwrap();
+
+    let service = get_service("fmq.samples").unwrap().unwrap();
+    let desc: MQDescriptor<i32, SynchronizedReadWrite> =
+        call(&service, FIRST_CALL_TRANSACTION, &request(&service))
+            .unwrap()
+            .read()
+            .unwrap();
+    let mut reader = MessageQueue::from_descriptor(&desc, false).unwrap();
+    assert_eq!(reader.quantum_count(), 4);
+
+    // More samples than the ring holds: both sides have to block.
+    let producer = server.spawn(move || {
+        for value in 0..32 {
+            writer.write_blocking(&[value], None).unwrap();
+        }
+    });
+    let mut samples = [0; 32];
+    for chunk in samples.chunks_mut(2) {
+        reader
+            .read_blocking(chunk, Some(Duration::from_secs(5)))
+            .unwrap();
+    }
+    producer.join().unwrap();
+    assert_eq!(samples.to_vec(), (0..32).collect::<Vec<_>>());
+}
@@ -0,0 +1,228 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! `hub::default` across a service manager restart: the service manager
+//! process of the fake binder driver is killed and another takes its
+//! place, and the services and notification callbacks registered with the
+//! first come back on the second.
+
+#![cfg(feature = "fake-driver")]
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use rsbinder::fake_driver::{FakeProcess, FAKE_BINDER_PATH};
+use rsbinder::hub::android_16::android::os::{
+    ConnectionInfo, Service, ServiceDebugInfo, ServiceWithMetadata,
+};
+use rsbinder::hub::android_16::{BnServiceManager, IServiceManager};
+use rsbinder::hub::{self, BnServiceCallback, IClientCallback, IServiceCallback};
+use rsbinder::{
+    Binder, Interface, Parcel, ProcessState, Remotable, Result, SIBinder, StatusCode, Strong,
+    TransactionCode,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A service manager keeping just names and notification callbacks.
+#[derive(Default)]
+struct Registry {
+    services: Mutex<HashMap<String, SIBinder>>,
+    callbacks: Mutex<Vec<(String, Strong<dyn IServiceCallback>)>>,
+}
+
+impl Registry {
+    fn lookup(&self, name: &str) -> Service::Service {
+        Service::Service::ServiceWithMetadata(ServiceWithMetadata::ServiceWithMetadata {
+            service: self.services.lock().unwrap().get(name).cloned(),
+            isLazyService: false,
+        })
+    }
+}
+
+impl Interface for Registry {}
+
+impl IServiceManager for Registry {
+    fn getService(&self, name: &str) -> rsbinder::status::Result<Option<SIBinder>> {
+        Ok(self.services.lock().unwrap().get(name).cloned())
+    }
+    fn getService2(&self, name: &str) -> rsbinder::status::Result<Service::Service> {
+        Ok(self.lookup(name))
+    }
+    fn checkService(&self, name: &str) -> rsbinder::status::Result<Option<SIBinder>> {
+        self.getService(name)
+    }
+    fn checkService2(&self, name: &str) -> rsbinder::status::Result<Service::Service> {
+        Ok(self.lookup(name))
+    }
+    fn addService(
+        &self,
+        name: &str,
+        service: &SIBinder,
+        _allow_isolated: bool,
+        _dump_priority: i32,
+    ) -> rsbinder::status::Result<()> {
+        self.services
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), service.clone());
+        let callbacks = self.callbacks.lock().unwrap().clone();
+        for (_, callback) in callbacks.iter().filter(|(n, _)| n == name) {
+            callback.onRegistration(name, service)?;
+        }
+        Ok(())
+    }
+    fn listServices(&self, _dump_priority: i32) -> rsbinder::status::Result<Vec<String>> {
+        Ok(self.services.lock().unwrap().keys().cloned().collect())
+    }
+    fn registerForNotifications(
+        &self,
+        name: &str,
+        callback: &Strong<dyn IServiceCallback>,
+    ) -> rsbinder::status::Result<()> {
+        self.callbacks
+            .lock()
+            .unwrap()
+            .push((name.to_owned(), callback.clone()));
+        Ok(())
+    }
+    fn unregisterForNotifications(
+        &self,
+        name: &str,
+        callback: &Strong<dyn IServiceCallback>,
+    ) -> rsbinder::status::Result<()> {
+        self.callbacks
+            .lock()
+            .unwrap()
+            .retain(|(n, c)| !(n == name && c.as_binder() == callback.as_binder()));
+        Ok(())
+    }
+    fn isDeclared(&self, _name: &str) -> rsbinder::status::Result<bool> {
+        Ok(false)
+    }
+    fn getDeclaredInstances(&self, _iface: &str) -> rsbinder::status::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+    fn updatableViaApex(&self, _name: &str) -> rsbinder::status::Result<Option<String>> {
+        Ok(None)
+    }
+    fn getUpdatableNames(&self, _apex_name: &str) -> rsbinder::status::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+    fn getConnectionInfo(
+        &self,
+        _name: &str,
+    ) -> rsbinder::status::Result<Option<ConnectionInfo::ConnectionInfo>> {
+        Ok(None)
+    }
+    fn registerClientCallback(
+        &self,
+        _name: &str,
+        _service: &SIBinder,
+        _callback: &Strong<dyn IClientCallback>,
+    ) -> rsbinder::status::Result<()> {
+        Err(StatusCode::UnknownTransaction.into())
+    }
+    fn tryUnregisterService(
+        &self,
+        name: &str,
+        _service: &SIBinder,
+    ) -> rsbinder::status::Result<()> {
+        self.services.lock().unwrap().remove(name);
+        Ok(())
+    }
+    fn getServiceDebugInfo(
+        &self,
+    ) -> rsbinder::status::Result<Vec<ServiceDebugInfo::ServiceDebugInfo>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Start a service manager process with an empty registry.
+fn start_service_manager() -> FakeProcess {
+    let process = FakeProcess::new(0).unwrap();
+    process.start_thread_pool();
+    process
+        .spawn(|| {
+            let binder = BnServiceManager::new_binder(Registry::default());
+            ProcessState::as_self()
+                .become_context_manager(binder.as_binder())
+                .expect("context manager");
+        })
+        .join()
+        .unwrap();
+    process
+}
+
+struct BnEcho;
+impl Remotable for BnEcho {
+    fn descriptor() -> &'static str {
+        "rsbinder.test.IEcho"
+    }
+    fn on_transact(&self, _: TransactionCode, _: &mut Parcel, _: &mut Parcel) -> Result<()> {
+        Err(StatusCode::UnknownTransaction)
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Forwards the names of registered services.
+struct Notified(Mutex<mpsc::Sender<String>>);
+impl Interface for Notified {}
+impl IServiceCallback for Notified {
+    fn onRegistration(&self, name: &str, _service: &SIBinder) -> rsbinder::status::Result<()> {
+        let _ = self.0.lock().unwrap().send(name.to_owned());
+        Ok(())
+    }
+}
+
+#[test]
+fn registrations_survive_service_manager_restart() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    ProcessState::init(FAKE_BINDER_PATH, 4).expect("fake ProcessState");
+    ProcessState::start_thread_pool();
+
+    let first = start_service_manager();
+    let (restarted_tx, restarted) = mpsc::channel();
+    let restarted_tx = Mutex::new(restarted_tx);
+    hub::set_restart_callback(Some(Arc::new(move || {
+        let _ = restarted_tx.lock().unwrap().send(());
+    })));
+
+    let echo = Binder::new(BnEcho);
+    hub::add_service("restart.echo", echo.as_binder()).unwrap();
+    let (notified_tx, notified) = mpsc::channel();
+    let callback = BnServiceCallback::new_binder(Notified(Mutex::new(notified_tx)));
+    hub::register_for_notifications("restart.late", &callback).unwrap();
+    // Registered and unregistered: not re-issued.
+    let dropped = Binder::new(BnEcho);
+    hub::add_service("restart.dropped", dropped.as_binder()).unwrap();
+    hub::try_unregister_service("restart.dropped", &dropped.as_binder()).unwrap();
+    let before = hub::default().unwrap();
+
+    drop(first);
+    // Nobody answers until the next service manager starts.
+    assert!(hub::try_get_service("restart.echo").is_err());
+    assert!(restarted.try_recv().is_err());
+
+    let _second = start_service_manager();
+    restarted.recv_timeout(TIMEOUT).expect("restart callback");
+
+    let after = hub::default().unwrap();
+    assert!(!Arc::ptr_eq(&before, &after));
+    assert_eq!(
+        hub::check_service("restart.echo"),
+        Some(echo.as_binder()),
+        "service registered again"
+    );
+    assert_eq!(
+        hub::list_services(hub::DUMP_FLAG_PRIORITY_ALL),
+        ["restart.echo"]
+    );
+
+    let late = Binder::new(BnEcho);
+    hub::add_service("restart.late", late.as_binder()).unwrap();
+    assert_eq!(notified.recv_timeout(TIMEOUT).unwrap(), "restart.late");
+}
@@ -0,0 +1,145 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Large-transaction reports and buffer exhaustion, against the fake
+//! binder driver's emulated per-process buffer.
+
+#![cfg(feature = "fake-driver")]
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use rsbinder::fake_driver::{FakeProcess, BUFFER_SPACE, FAKE_BINDER_PATH};
+use rsbinder::{
+    thread_state, Binder, Interface, Parcel, ProcessState, Remotable, Result, SIBinder, StatusCode,
+    TransactionCode, FIRST_CALL_TRANSACTION,
+};
+
+const DESC: &str = "rsbinder.test.ISink";
+const TX_SINK: TransactionCode = FIRST_CALL_TRANSACTION; // bytes: Vec<u8> -> i32 (length)
+
+/// `(descriptor, code, data_size, buffer exhausted)` of each report.
+type Reports = Arc<Mutex<Vec<(String, TransactionCode, usize, Option<bool>)>>>;
+
+struct BnSink;
+impl Remotable for BnSink {
+    fn descriptor() -> &'static str {
+        DESC
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            TX_SINK => {
+                let bytes: Vec<u8> = reader.read()?;
+                reply.write(&(bytes.len() as i32))
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The sink service, and the reports of this process's callback.
+fn service() -> (SIBinder, Reports) {
+    static SERVICE: OnceLock<(FakeProcess, Reports)> = OnceLock::new();
+    let (_, reports) = SERVICE.get_or_init(|| {
+        ProcessState::init(FAKE_BINDER_PATH, 4).expect("fake ProcessState");
+        ProcessState::start_thread_pool();
+        let reports = Reports::default();
+        let sink = reports.clone();
+        ProcessState::as_self().set_large_transaction_callback(Some(Arc::new(move |large| {
+            sink.lock().unwrap().push((
+                large.descriptor.clone(),
+                large.code,
+                large.data_size,
+                large.failure.map(|ee| ee.is_buffer_exhausted()),
+            ));
+        })));
+        let service = FakeProcess::new(1000).unwrap();
+        service.start_thread_pool();
+        service
+            .spawn(|| {
+                let binder = Binder::new(BnSink);
+                ProcessState::as_self()
+                    .become_context_manager(binder.as_binder())
+                    .expect("context manager");
+            })
+            .join()
+            .unwrap();
+        (service, reports)
+    });
+    (
+        ProcessState::as_self().context_object().unwrap(),
+        reports.clone(),
+    )
+}
+
+fn sink(binder: &SIBinder, len: usize) -> Result<i32> {
+    let proxy = binder.as_proxy().unwrap();
+    let mut data = proxy.prepare_transact(true)?;
+    data.write(&vec![7u8; len])?;
+    proxy
+        .submit_transact(TX_SINK, &data, 0)?
+        .ok_or(StatusCode::UnexpectedNull)?
+        .read()
+}
+
+#[test]
+fn test_large_transactions() {
+    let (binder, reports) = service();
+    let process = ProcessState::as_self();
+    assert_eq!(process.large_transaction_threshold(), 300 * 1024);
+    process.set_large_transaction_threshold(64 * 1024);
+
+    // Below the threshold: no report.
+    assert_eq!(sink(&binder, 1024), Ok(1024));
+    assert!(reports.lock().unwrap().is_empty());
+
+    // Over it: reported before sending, and still delivered.
+    assert_eq!(sink(&binder, 100 * 1024), Ok(100 * 1024));
+    {
+        let reports = reports.lock().unwrap();
+        let [(descriptor, code, size, failure)] = reports.as_slice() else {
+            panic!("expected one report, got {reports:?}");
+        };
+        assert_eq!(
+            (descriptor.as_str(), *code, *failure),
+            (DESC, TX_SINK, None)
+        );
+        assert!(*size > 100 * 1024);
+    }
+
+    // More than the receiver's whole buffer: rejected by the driver,
+    // reported with its extended error, which the thread can still read.
+    reports.lock().unwrap().clear();
+    assert_eq!(
+        sink(&binder, BUFFER_SPACE + 1),
+        Err(StatusCode::FailedTransaction)
+    );
+    {
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 2, "{reports:?}");
+        assert_eq!(reports[0].3, None);
+        assert_eq!(reports[1].3, Some(true));
+    }
+    let ee = thread_state::get_extended_error().unwrap();
+    assert!(ee.is_buffer_exhausted(), "{ee:?}");
+
+    // The failed call left no buffer space charged.
+    process.set_large_transaction_threshold(usize::MAX);
+    assert_eq!(sink(&binder, 512 * 1024), Ok(512 * 1024));
+
+    // A kept detail does not outlive the thread's next transaction.
+    assert_eq!(
+        sink(&binder, BUFFER_SPACE + 1),
+        Err(StatusCode::FailedTransaction)
+    );
+    assert_eq!(sink(&binder, 1024), Ok(1024));
+    let ee = thread_state::get_extended_error();
+    assert!(!ee.is_ok_and(|ee| ee.is_buffer_exhausted()), "{ee:?}");
+}
@@ -0,0 +1,140 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Call metrics recorded across processes of the fake binder driver.
+
+#![cfg(all(feature = "fake-driver", feature = "metrics"))]
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use rsbinder::fake_driver::{FakeProcess, FAKE_BINDER_PATH};
+use rsbinder::metrics::{self, CallMetrics, Side};
+use rsbinder::{
+    trace, Binder, ExceptionCode, Interface, Parcel, ProcessState, Remotable, Result, SIBinder,
+    Status, StatusCode, TransactionCode, FIRST_CALL_TRANSACTION,
+};
+
+const DESC: &str = "rsbinder.test.IMetered";
+const TX_DIVIDE: TransactionCode = FIRST_CALL_TRANSACTION; // (i32, i32) -> i32
+
+struct BnMetered;
+impl Remotable for BnMetered {
+    fn descriptor() -> &'static str {
+        DESC
+    }
+    // Shaped like a generated `on_transact` arm.
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            TX_DIVIDE => {
+                trace::server_method("divide");
+                let (a, b): (i32, i32) = (reader.read()?, reader.read()?);
+                match a.checked_div(b) {
+                    Some(q) => {
+                        reply.write(&Status::from(StatusCode::Ok))?;
+                        reply.write(&q)
+                    }
+                    None => reply.write(&Status::new_service_specific_error(1, None)),
+                }
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn service() -> SIBinder {
+    static SERVICE: OnceLock<FakeProcess> = OnceLock::new();
+    SERVICE.get_or_init(|| {
+        ProcessState::init(FAKE_BINDER_PATH, 4).expect("fake ProcessState");
+        ProcessState::start_thread_pool();
+        let service = FakeProcess::new(1000).unwrap();
+        service.start_thread_pool();
+        service
+            .spawn(|| {
+                let binder = Binder::new(BnMetered);
+                ProcessState::as_self()
+                    .become_context_manager(binder.as_binder())
+                    .expect("context manager");
+            })
+            .join()
+            .unwrap();
+        service
+    });
+    ProcessState::as_self().context_object().unwrap()
+}
+
+// Shaped like a generated proxy method.
+fn divide(binder: &SIBinder, a: i32, b: i32) -> Result<Status> {
+    let proxy = binder.as_proxy().unwrap();
+    let mut data = proxy.prepare_transact(true)?;
+    data.write(&a)?;
+    data.write(&b)?;
+    let data = trace::client_call(data, "divide")?;
+    let mut reply = proxy
+        .submit_transact(TX_DIVIDE, &data, 0)?
+        .ok_or(StatusCode::UnexpectedNull)?;
+    reply.read()
+}
+
+fn metrics_of(side: Side, method: &str) -> CallMetrics {
+    metrics::snapshot()
+        .into_iter()
+        .find(|m| m.side == side && m.descriptor == DESC && m.method == method)
+        .unwrap_or_else(|| panic!("no {side:?} metrics for {method}"))
+}
+
+#[test]
+fn test_client_and_server_metrics() {
+    let binder = service();
+    assert!(divide(&binder, 6, 3).unwrap().is_ok());
+    assert_eq!(
+        divide(&binder, 1, 0).unwrap().exception_code(),
+        ExceptionCode::ServiceSpecific
+    );
+    let proxy = binder.as_proxy().unwrap();
+    let data = proxy.prepare_transact(true).unwrap();
+    assert_eq!(
+        proxy.submit_transact(TX_DIVIDE + 9, &data, 0).err(),
+        Some(StatusCode::UnknownTransaction)
+    );
+
+    for side in [Side::Client, Side::Server] {
+        let m = metrics_of(side, "divide");
+        assert_eq!((m.calls, m.in_flight), (2, 0), "{side:?}");
+        assert_eq!(
+            m.exceptions,
+            BTreeMap::from([(ExceptionCode::ServiceSpecific, 1)])
+        );
+        assert!(m.errors.is_empty());
+        assert_eq!(m.latency.buckets.iter().sum::<u64>(), 2);
+    }
+    // Both ends see the same parcels.
+    let (client, server) = (
+        metrics_of(Side::Client, "divide"),
+        metrics_of(Side::Server, "divide"),
+    );
+    assert!(client.request_bytes.max > 0);
+    assert_eq!(client.request_bytes, server.request_bytes);
+    assert_eq!(client.reply_bytes, server.reply_bytes);
+    let unknown = (TX_DIVIDE + 9).to_string();
+    for side in [Side::Client, Side::Server] {
+        let m = metrics_of(side, &unknown);
+        assert_eq!(
+            m.errors,
+            BTreeMap::from([(StatusCode::UnknownTransaction, 1)])
+        );
+    }
+
+    let text = metrics::render_prometheus(&metrics::snapshot());
+    assert!(text.contains(&format!(
+        "rsbinder_calls_total{{side=\"server\",descriptor=\"{DESC}\",method=\"divide\"}} 2"
+    )));
+}
@@ -0,0 +1,328 @@
+// Copyright 2026 Jeff Kim <hiking90@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Transaction spans and trace-context propagation across processes of
+//! the fake binder driver.
+
+#![cfg(all(feature = "fake-driver", feature = "tracing"))]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use rsbinder::fake_driver::{FakeProcess, FAKE_BINDER_PATH};
+use rsbinder::trace::{self, TraceContext, TracePropagator};
+use rsbinder::{
+    get_calling_pid, get_calling_uid, Binder, Interface, Parcel, ProcessState, Remotable, Result,
+    SIBinder, StatusCode, TransactionCode, FIRST_CALL_TRANSACTION,
+};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const DESC: &str = "rsbinder.test.ITraced";
+const TX_PING: TransactionCode = FIRST_CALL_TRANSACTION; // i32 -> i32
+const TX_RAW: TransactionCode = FIRST_CALL_TRANSACTION + 1; // -> ()
+const TX_STRICT: TransactionCode = FIRST_CALL_TRANSACTION + 2; // i32 -> i32
+
+const SERVER_UID: u32 = 1000;
+
+// ---- span recorder -----------------------------------------------------
+
+#[derive(Debug, Clone)]
+struct SpanData {
+    name: &'static str,
+    id: u64,
+    parent: Option<u64>,
+    fields: HashMap<&'static str, String>,
+}
+
+impl SpanData {
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+struct Fields<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_owned());
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<SpanData>>>);
+
+impl Recorder {
+    /// Finished spans of this test's interface named `name`.
+    fn spans(&self, name: &str) -> Vec<SpanData> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|s| s.name == name && s.field("descriptor") == Some(DESC))
+            .cloned()
+            .collect()
+    }
+}
+
+impl<S: tracing::Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        attrs.record(&mut Fields(&mut fields));
+        let parent = ctx
+            .span(id)
+            .and_then(|s| s.parent())
+            .map(|p| p.id().into_u64());
+        ctx.span(id).unwrap().extensions_mut().insert(SpanData {
+            name: attrs.metadata().name(),
+            id: id.into_u64(),
+            parent,
+            fields,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(data) = ctx.span(id).unwrap().extensions_mut().get_mut::<SpanData>() {
+            values.record(&mut Fields(&mut data.fields));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if let Some(data) = ctx.span(&id).unwrap().extensions_mut().remove::<SpanData>() {
+            self.0.lock().unwrap().push(data);
+        }
+    }
+}
+
+/// Sends the client span's id as the span id; remembers what arrives.
+#[derive(Default)]
+struct IdPropagator(Mutex<Vec<(u64, TraceContext)>>);
+
+impl TracePropagator for IdPropagator {
+    fn inject(&self, span: &tracing::Span) -> Option<TraceContext> {
+        Some(TraceContext {
+            trace_id: [0xab; 16],
+            span_id: span.id()?.into_u64().to_be_bytes(),
+            flags: 1,
+        })
+    }
+    fn extract(&self, span: &tracing::Span, context: &TraceContext) {
+        let id = span.id().expect("server span enabled").into_u64();
+        self.0.lock().unwrap().push((id, *context));
+    }
+}
+
+// ---- service -----------------------------------------------------------
+
+static CALLER: Mutex<Option<(i32, u32)>> = Mutex::new(None);
+
+struct BnTraced;
+impl Remotable for BnTraced {
+    fn descriptor() -> &'static str {
+        DESC
+    }
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        reader: &mut Parcel,
+        reply: &mut Parcel,
+    ) -> Result<()> {
+        match code {
+            // What a generated `on_transact` arm does.
+            TX_PING => {
+                trace::server_method("ping");
+                *CALLER.lock().unwrap() = Some((get_calling_pid(), get_calling_uid()));
+                let value: i32 = reader.read()?;
+                reply.write(&(value + 1))
+            }
+            TX_RAW => Ok(()),
+            // Like an AOSP stub with `enforceNoDataAvail`: excess data
+            // after the arguments is rejected.
+            TX_STRICT => {
+                trace::server_method("strict");
+                let value: i32 = reader.read()?;
+                if reader.data_avail() != 0 {
+                    return Err(StatusCode::BadValue);
+                }
+                reply.write(&value)
+            }
+            _ => Err(StatusCode::UnknownTransaction),
+        }
+    }
+    fn on_dump(&self, _w: &mut dyn std::io::Write, _a: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct Fixture {
+    recorder: Recorder,
+    propagator: Arc<IdPropagator>,
+    /// Keeps the opted-in proxy alive for every test.
+    _binder: SIBinder,
+    _service: FakeProcess,
+}
+
+fn fixture() -> &'static Fixture {
+    static FIXTURE: OnceLock<Fixture> = OnceLock::new();
+    FIXTURE.get_or_init(|| {
+        let recorder = Recorder::default();
+        tracing::subscriber::set_global_default(
+            tracing_subscriber::registry().with(recorder.clone()),
+        )
+        .unwrap();
+        let propagator = Arc::new(IdPropagator::default());
+        trace::set_propagator(Some(propagator.clone()));
+
+        ProcessState::init(FAKE_BINDER_PATH, 4).expect("fake ProcessState");
+        ProcessState::start_thread_pool();
+        let service = FakeProcess::new(SERVER_UID).unwrap();
+        service.start_thread_pool();
+        service
+            .spawn(|| {
+                let binder = Binder::new(BnTraced);
+                ProcessState::as_self()
+                    .become_context_manager(binder.as_binder())
+                    .expect("context manager");
+            })
+            .join()
+            .unwrap();
+        let binder = ProcessState::as_self().context_object().unwrap();
+        binder.as_proxy().unwrap().set_trace_propagation(true);
+        Fixture {
+            recorder,
+            propagator,
+            _binder: binder,
+            _service: service,
+        }
+    })
+}
+
+fn service() -> SIBinder {
+    ProcessState::as_self().context_object().unwrap()
+}
+
+#[test]
+fn test_generated_call_spans() {
+    let fixture = fixture();
+    let binder = service();
+    let proxy = binder.as_proxy().unwrap();
+
+    // What a generated proxy method does.
+    let mut data = proxy.prepare_transact(true).unwrap();
+    data.write(&41i32).unwrap();
+    let data = trace::client_call(data, "ping").unwrap();
+    let mut reply = proxy.submit_transact(TX_PING, &data, 0).unwrap().unwrap();
+    assert_eq!(reply.read::<i32>().unwrap(), 42);
+    drop(data);
+
+    let client = fixture.recorder.spans("binder.transact");
+    let client = client
+        .iter()
+        .find(|s| s.field("method") == Some("ping"))
+        .expect("client span");
+    assert_eq!(client.field("transport"), Some("kernel"));
+    assert_eq!(client.field("code"), Some(TX_PING.to_string().as_str()));
+    assert_eq!(client.field("status"), Some("Ok"));
+    assert!(client.field("data_size").is_some());
+    assert!(client.field("reply_size").is_some());
+    assert!(client.field("duration_us").is_some());
+
+    let server = fixture.recorder.spans("binder.on_transact");
+    let server = server
+        .iter()
+        .find(|s| s.field("method") == Some("ping"))
+        .expect("server span");
+    let (pid, uid) = CALLER.lock().unwrap().expect("service called");
+    assert_eq!(server.field("calling_pid"), Some(pid.to_string().as_str()));
+    assert_eq!(server.field("calling_uid"), Some(uid.to_string().as_str()));
+    assert_eq!(server.field("status"), Some("Ok"));
+    // Sent from another process, so no in-process parent.
+    assert_eq!(server.parent, None);
+
+    // The trailer carried the client span across the hop.
+    let extracted = fixture.propagator.0.lock().unwrap().clone();
+    let (_, context) = extracted
+        .iter()
+        .find(|(id, _)| *id == server.id)
+        .expect("context extracted for the server span");
+    assert_eq!(context.span_id, client.id.to_be_bytes());
+    assert_eq!(context.trace_id, [0xab; 16]);
+    assert_eq!(context.flags, 1);
+}
+
+#[test]
+fn test_hand_written_call_span() {
+    let fixture = fixture();
+    let binder = service();
+    let proxy = binder.as_proxy().unwrap();
+
+    let data = proxy.prepare_transact(true).unwrap();
+    proxy.submit_transact(TX_RAW, &data, 0).unwrap();
+
+    let client = fixture.recorder.spans("binder.transact");
+    let client = client
+        .iter()
+        .find(|s| s.field("code") == Some(TX_RAW.to_string().as_str()))
+        .expect("client span");
+    assert_eq!(client.field("method"), None);
+    let server = fixture.recorder.spans("binder.on_transact");
+    assert!(server.iter().any(
+        |s| s.field("code") == Some(TX_RAW.to_string().as_str()) && s.field("method").is_none()
+    ));
+}
+
+#[test]
+fn test_context_invisible_to_strict_service() {
+    let fixture = fixture();
+    let binder = service();
+    let proxy = binder.as_proxy().unwrap();
+
+    let mut data = proxy.prepare_transact(true).unwrap();
+    data.write(&7i32).unwrap();
+    let data = trace::client_call(data, "strict").unwrap();
+    let mut reply = proxy
+        .submit_transact(TX_STRICT, &data, 0)
+        .expect("trailer stripped before the stub reads");
+    assert_eq!(reply.as_mut().unwrap().read::<i32>().unwrap(), 7);
+    drop(data);
+
+    let client = fixture.recorder.spans("binder.transact");
+    let client = client
+        .iter()
+        .find(|s| s.field("method") == Some("strict"))
+        .expect("client span");
+    let flags: u32 = client.field("flags").unwrap().parse().unwrap();
+    assert_ne!(flags & trace::FLAG_TRACE_CONTEXT, 0);
+
+    let server = fixture.recorder.spans("binder.on_transact");
+    let server = server
+        .iter()
+        .find(|s| s.field("method") == Some("strict"))
+        .expect("server span");
+    assert!(fixture
+        .propagator
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|(id, ctx)| *id == server.id && ctx.span_id == client.id.to_be_bytes()));
+}
+
+#[test]
+fn test_no_context_without_opt_in() {
+    let _fixture = fixture();
+
+    // Not from an opted-in proxy: nothing is appended even with a
+    // propagator installed.
+    let mut data = Parcel::new();
+    data.write(&7i32).unwrap();
+    let size = data.data_size();
+    let data = trace::client_call(data, "strict").unwrap();
+    assert_eq!(data.data_size(), size);
+}